@@ -784,6 +784,11 @@ impl<T> Pool<T> {
         Handle::new(ticket.index, record.generation)
     }
 
+    /// Returns handle of a record that is reserved by given ticket.
+    pub fn ticket_handle(&self, ticket: &Ticket<T>) -> Handle<T> {
+        Handle::new(ticket.index, self.records[ticket.index as usize].generation)
+    }
+
     /// Forgets that value at ticket was reserved and makes it usable again.
     /// Useful when you don't need to put value back by ticket, but just make
     /// pool record usable again.
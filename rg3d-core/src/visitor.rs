@@ -436,6 +436,10 @@ impl From<FileLoadError> for VisitError {
 
 pub type VisitResult = Result<(), VisitError>;
 
+fn is_normalized(norm: f32) -> bool {
+    (norm - 1.0).abs() <= f32::EPSILON
+}
+
 impl Field {
     pub fn new(name: &str, kind: FieldKind) -> Self {
         Self {
@@ -589,7 +593,15 @@ impl Field {
                     let y = file.read_f32::<LittleEndian>()?;
                     let z = file.read_f32::<LittleEndian>()?;
                     let w = file.read_f32::<LittleEndian>()?;
-                    UnitQuaternion::new_normalize(Quaternion::new(w, x, y, z))
+                    let quaternion = Quaternion::new(w, x, y, z);
+                    // Normalizing already normalized quaternion could change its bits and
+                    // break exact round trip, so renormalize only values that are off (for
+                    // example written by hand or by older versions).
+                    if is_normalized(quaternion.norm()) {
+                        UnitQuaternion::new_unchecked(quaternion)
+                    } else {
+                        UnitQuaternion::new_normalize(quaternion)
+                    }
                 }),
                 13 => FieldKind::Matrix4({
                     let mut f = [0.0f32; 16];
@@ -633,7 +645,13 @@ impl Field {
                 20 => FieldKind::UnitComplex({
                     let re = file.read_f32::<LittleEndian>()?;
                     let im = file.read_f32::<LittleEndian>()?;
-                    UnitComplex::from_complex(Complex::new(re, im))
+                    // Same as for quaternions.
+                    let complex = Complex::new(re, im);
+                    if is_normalized(complex.norm_sqr().sqrt()) {
+                        UnitComplex::new_unchecked(complex)
+                    } else {
+                        UnitComplex::new_normalize(complex)
+                    }
                 }),
                 21 => {
                     let type_id = file.read_u8()?;
@@ -1345,8 +1363,16 @@ impl<T: Default + Visit, const SIZE: usize> Visit for [T; SIZE] {
 
 #[cfg(test)]
 mod test {
-    use crate::visitor::{Data, Visit, VisitError, VisitResult, Visitor};
-    use std::{fs::File, io::Write, path::Path, rc::Rc};
+    use crate::{
+        algebra::{Complex, Quaternion, UnitComplex, UnitQuaternion, Vector3},
+        visitor::{Data, Field, FieldKind, Visit, VisitError, VisitResult, Visitor},
+    };
+    use std::{
+        fs::File,
+        io::{Cursor, Write},
+        path::Path,
+        rc::Rc,
+    };
 
     pub struct Model {
         data: u64,
@@ -1494,4 +1520,40 @@ mod test {
             objects.visit("Objects", &mut visitor).unwrap();
         }
     }
+
+    fn round_trip(kind: FieldKind) -> FieldKind {
+        let mut bytes = Vec::new();
+        Field::save(&Field::new("Field", kind), &mut bytes).unwrap();
+        Field::load(&mut Cursor::new(bytes)).unwrap().kind
+    }
+
+    #[test]
+    fn rotations_are_normalized_on_load() {
+        // Normalized values are kept bit-exact.
+        let quaternion = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.7)
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 1.3);
+        match round_trip(FieldKind::UnitQuaternion(quaternion)) {
+            FieldKind::UnitQuaternion(loaded) => assert_eq!(loaded, quaternion),
+            _ => panic!("must be a quaternion"),
+        }
+        let complex = UnitComplex::new(0.7);
+        match round_trip(FieldKind::UnitComplex(complex)) {
+            FieldKind::UnitComplex(loaded) => assert_eq!(loaded, complex),
+            _ => panic!("must be a complex number"),
+        }
+
+        // Values that are not normalized are normalized.
+        let quaternion = UnitQuaternion::new_unchecked(Quaternion::new(2.0, 0.0, 0.0, 0.0));
+        match round_trip(FieldKind::UnitQuaternion(quaternion)) {
+            FieldKind::UnitQuaternion(loaded) => assert_eq!(loaded, UnitQuaternion::identity()),
+            _ => panic!("must be a quaternion"),
+        }
+        let complex = UnitComplex::new_unchecked(Complex::new(0.0, 3.0));
+        match round_trip(FieldKind::UnitComplex(complex)) {
+            FieldKind::UnitComplex(loaded) => {
+                assert_eq!(loaded.into_inner(), Complex::new(0.0, 1.0))
+            }
+            _ => panic!("must be a complex number"),
+        }
+    }
 }
@@ -20,10 +20,7 @@ impl SoundEngine {
     /// the same time, but you shouldn't do this because you can create multiple contexts which
     /// should cover 99% of use cases.
     pub fn new() -> Arc<Mutex<Self>> {
-        let engine = Arc::new(Mutex::new(Self {
-            contexts: Default::default(),
-            master_gain: 1.0,
-        }));
+        let engine = Self::without_device();
 
         // Run the default output device. Internally it creates separate thread, so we have
         // to share sound engine instance with it, this is the only reason why it is wrapped
@@ -43,6 +40,16 @@ impl SoundEngine {
         engine
     }
 
+    /// Creates new instance of a sound engine without output device. Such engine does not emit
+    /// any sound, contexts must be rendered manually. It is useful for tests and offline
+    /// processing.
+    pub fn without_device() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            contexts: Default::default(),
+            master_gain: 1.0,
+        }))
+    }
+
     /// Adds new context to the engine. Each context must be added to the engine to emit
     /// sounds.
     pub fn add_context(&mut self, context: SoundContext) {
@@ -11,7 +11,7 @@ use crate::{
         visitor::{Visit, VisitResult, Visitor},
     },
     resource::{model::Model, ResourceState},
-    scene::{graph::Graph, node::Node},
+    scene::{graph::Graph, mesh::morph::MorphTargetWeight, node::Node},
     utils::log::{Log, MessageKind},
 };
 use std::{
@@ -67,6 +67,97 @@ impl Visit for KeyFrame {
     }
 }
 
+/// Weight of a morph target at specific time.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MorphKeyFrame {
+    pub time: f32,
+    pub weight: f32,
+}
+
+impl MorphKeyFrame {
+    pub fn new(time: f32, weight: f32) -> Self {
+        Self { time, weight }
+    }
+}
+
+impl Visit for MorphKeyFrame {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.time.visit("Time", visitor)?;
+        self.weight.visit("Weight", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Animated weight of a morph target with specific name. Weights are linearly interpolated
+/// between key frames. See [crate::scene::mesh::morph] module docs for more info about morph
+/// targets.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphCurve {
+    name: String,
+    frames: Vec<MorphKeyFrame>,
+}
+
+impl MorphCurve {
+    /// Creates new curve for a morph target with given name. Key frames will be sorted by time.
+    pub fn new<N: AsRef<str>>(name: N, mut frames: Vec<MorphKeyFrame>) -> Self {
+        frames.sort_by(|a, b| {
+            a.time
+                .partial_cmp(&b.time)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Self {
+            name: name.as_ref().to_owned(),
+            frames,
+        }
+    }
+
+    /// Returns name of morph target which weight is animated by the curve.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns key frames of the curve sorted by time.
+    pub fn key_frames(&self) -> &[MorphKeyFrame] {
+        &self.frames
+    }
+
+    /// Returns time of last key frame.
+    pub fn max_time(&self) -> f32 {
+        self.frames.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Calculates weight at given time.
+    pub fn weight_at(&self, time: f32) -> f32 {
+        let right_index = match self.frames.iter().position(|k| k.time >= time) {
+            Some(index) => index,
+            None => return self.frames.last().map_or(0.0, |k| k.weight),
+        };
+
+        if right_index == 0 {
+            self.frames[0].weight
+        } else {
+            let left = &self.frames[right_index - 1];
+            let right = &self.frames[right_index];
+            let interpolator = (time - left.time) / (right.time - left.time);
+            left.weight + (right.weight - left.weight) * interpolator
+        }
+    }
+}
+
+impl Visit for MorphCurve {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.name.visit("Name", visitor)?;
+        self.frames.visit("Frames", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
 #[derive(Default, Copy, Clone, Debug)]
 pub struct PoseEvaluationFlags {
     pub ignore_position: bool,
@@ -88,9 +179,11 @@ impl Visit for PoseEvaluationFlags {
 
 #[derive(Debug)]
 pub struct Track {
-    // Frames are not serialized, because it makes no sense to store them in save file,
-    // they will be taken from resource on Resolve stage.
+    // Frames are not serialized as part of a track, because it makes no sense to store them
+    // in save file, they will be taken from resource on Resolve stage. The only exception is
+    // animations without resource, see `Animation::visit`. Same applies to morph curves.
     frames: Vec<KeyFrame>,
+    morph_curves: Vec<MorphCurve>,
     enabled: bool,
     max_time: f32,
     node: Handle<Node>,
@@ -101,6 +194,7 @@ impl Clone for Track {
     fn clone(&self) -> Self {
         Self {
             frames: self.frames.clone(),
+            morph_curves: self.morph_curves.clone(),
             enabled: self.enabled,
             max_time: self.max_time,
             node: self.node,
@@ -113,6 +207,7 @@ impl Default for Track {
     fn default() -> Self {
         Self {
             frames: Vec::new(),
+            morph_curves: Vec::new(),
             enabled: true,
             max_time: 0.0,
             node: Default::default(),
@@ -176,6 +271,30 @@ impl Track {
 
     pub fn set_key_frames(&mut self, key_frames: &[KeyFrame]) {
         self.frames = key_frames.to_vec();
+        self.update_max_time();
+    }
+
+    pub fn get_key_frames(&self) -> &[KeyFrame] {
+        &self.frames
+    }
+
+    /// Adds a curve that animates weight of a morph target of track's node. Node must be a mesh,
+    /// otherwise curves are ignored.
+    pub fn add_morph_curve(&mut self, curve: MorphCurve) {
+        self.max_time = self.max_time.max(curve.max_time());
+        self.morph_curves.push(curve);
+    }
+
+    pub fn set_morph_curves(&mut self, curves: &[MorphCurve]) {
+        self.morph_curves = curves.to_vec();
+        self.update_max_time();
+    }
+
+    pub fn morph_curves(&self) -> &[MorphCurve] {
+        &self.morph_curves
+    }
+
+    fn update_max_time(&mut self) {
         self.max_time = 0.0;
 
         for key_frame in self.frames.iter() {
@@ -183,27 +302,49 @@ impl Track {
                 self.max_time = key_frame.time;
             }
         }
-    }
 
-    pub fn get_key_frames(&self) -> &[KeyFrame] {
-        &self.frames
+        for curve in self.morph_curves.iter() {
+            self.max_time = self.max_time.max(curve.max_time());
+        }
     }
 
-    pub fn get_local_pose(&self, mut time: f32) -> Option<LocalPose> {
-        if self.frames.is_empty() {
+    pub fn get_local_pose(&self, time: f32) -> Option<LocalPose> {
+        if self.frames.is_empty() && self.morph_curves.is_empty() {
             return None;
         }
 
-        if time >= self.max_time {
+        // Tracks with morph curves only must not touch transform of a node.
+        let mut pose = self.get_transform_pose(time).unwrap_or_else(|| LocalPose {
+            node: self.node,
+            affects_transform: false,
+            ..Default::default()
+        });
+
+        let time = clampf(time, 0.0, self.max_time);
+        pose.morph_weights
+            .extend(self.morph_curves.iter().map(|curve| MorphTargetWeight {
+                name: curve.name.clone(),
+                weight: curve.weight_at(time),
+            }));
+
+        Some(pose)
+    }
+
+    fn get_transform_pose(&self, mut time: f32) -> Option<LocalPose> {
+        // Morph curves can be longer than key frames.
+        let max_time = self.frames.last()?.time;
+
+        if time >= max_time {
             return self.frames.last().map(|k| LocalPose {
                 node: self.node,
                 position: k.position,
                 scale: k.scale,
                 rotation: k.rotation,
+                ..Default::default()
             });
         }
 
-        time = clampf(time, 0.0, self.max_time);
+        time = clampf(time, 0.0, max_time);
 
         let mut right_index = 0;
         for (i, keyframe) in self.frames.iter().enumerate() {
@@ -219,6 +360,7 @@ impl Track {
                 position: k.position,
                 scale: k.scale,
                 rotation: k.rotation,
+                ..Default::default()
             })
         } else {
             let left = &self.frames[right_index - 1];
@@ -242,6 +384,7 @@ impl Track {
                 } else {
                     left.rotation.nlerp(&right.rotation, interpolator)
                 },
+                ..Default::default()
             })
         }
     }
@@ -255,11 +398,21 @@ impl Track {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
 pub struct AnimationEvent {
     pub signal_id: u64,
 }
 
+impl Visit for AnimationEvent {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.signal_id.visit("SignalId", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AnimationSignal {
     id: u64,
@@ -338,6 +491,9 @@ pub struct LocalPose {
     position: Vector3<f32>,
     scale: Vector3<f32>,
     rotation: UnitQuaternion<f32>,
+    // False if pose was produced by a track without transform key frames.
+    affects_transform: bool,
+    morph_weights: Vec<MorphTargetWeight>,
 }
 
 impl Default for LocalPose {
@@ -347,6 +503,8 @@ impl Default for LocalPose {
             position: Vector3::default(),
             scale: Vector3::new(1.0, 1.0, 1.0),
             rotation: UnitQuaternion::identity(),
+            affects_transform: true,
+            morph_weights: Default::default(),
         }
     }
 }
@@ -358,13 +516,54 @@ impl LocalPose {
             position: self.position.scale(weight),
             rotation: self.rotation.nlerp(&self.rotation, weight),
             scale: self.scale.scale(weight),
+            affects_transform: self.affects_transform,
+            morph_weights: self
+                .morph_weights
+                .iter()
+                .map(|w| MorphTargetWeight {
+                    name: w.name.clone(),
+                    weight: w.weight * weight,
+                })
+                .collect(),
         }
     }
 
     pub fn blend_with(&mut self, other: &LocalPose, weight: f32) {
-        self.position += other.position.scale(weight);
-        self.rotation = self.rotation.nlerp(&other.rotation, weight);
-        self.scale += other.scale.scale(weight);
+        // Poses without transform (produced by tracks with morph curves only) must not
+        // affect transform of a node.
+        if other.affects_transform {
+            if self.affects_transform {
+                self.position += other.position.scale(weight);
+                self.rotation = self.rotation.nlerp(&other.rotation, weight);
+                self.scale += other.scale.scale(weight);
+            } else {
+                // Current transform is meaningless, do fake blend between identity and other.
+                self.position = other.position.scale(weight);
+                self.rotation = other.rotation;
+                self.scale = other.scale.scale(weight);
+                self.affects_transform = true;
+            }
+        }
+        for other_weight in other.morph_weights.iter() {
+            if let Some(current) = self
+                .morph_weights
+                .iter_mut()
+                .find(|w| w.name == other_weight.name)
+            {
+                current.weight += other_weight.weight * weight;
+            } else {
+                self.morph_weights.push(MorphTargetWeight {
+                    name: other_weight.name.clone(),
+                    weight: other_weight.weight * weight,
+                });
+            }
+        }
+    }
+
+    /// Returns weights of morph targets of the node, it is empty if node's track has no morph
+    /// curves.
+    pub fn morph_weights(&self) -> &[MorphTargetWeight] {
+        &self.morph_weights
     }
 
     pub fn position(&self) -> Vector3<f32> {
@@ -418,11 +617,18 @@ impl AnimationPose {
             if node.is_none() {
                 Log::writeln(MessageKind::Error, "Invalid node handle found for animation pose, most likely it means that animation retargetting failed!".to_owned());
             } else {
-                graph[*node]
-                    .local_transform_mut()
-                    .set_position(local_pose.position)
-                    .set_rotation(local_pose.rotation)
-                    .set_scale(local_pose.scale);
+                let node = &mut graph[*node];
+                if local_pose.affects_transform {
+                    node.local_transform_mut()
+                        .set_position(local_pose.position)
+                        .set_rotation(local_pose.rotation)
+                        .set_scale(local_pose.scale);
+                }
+                if let Node::Mesh(mesh) = node {
+                    for morph_weight in local_pose.morph_weights.iter() {
+                        mesh.set_morph_weight(&morph_weight.name, morph_weight.weight);
+                    }
+                }
             }
         }
     }
@@ -669,6 +875,7 @@ impl Animation {
                                 == data.get_scene().graph[ref_track.get_node()].name()
                             {
                                 track.set_key_frames(ref_track.get_key_frames());
+                                track.set_morph_curves(ref_track.morph_curves());
                                 found = true;
                                 break;
                             }
@@ -723,6 +930,30 @@ impl Default for Animation {
     }
 }
 
+impl Animation {
+    fn visit_key_frames(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        // Region must be left even if some track is missing, otherwise the rest of the
+        // animation would be read from a wrong region.
+        let result = self
+            .tracks
+            .iter_mut()
+            .enumerate()
+            .try_for_each(|(i, track)| {
+                track.frames.visit(&format!("Track{}", i), visitor)?;
+                let _ = track
+                    .morph_curves
+                    .visit(&format!("Track{}MorphCurves", i), visitor);
+                Ok(())
+            });
+
+        visitor.leave_region()?;
+
+        result
+    }
+}
+
 impl Visit for Animation {
     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
         visitor.enter_region(name)?;
@@ -736,6 +967,19 @@ impl Visit for Animation {
         self.enabled.visit("Enabled", visitor)?;
         self.signals.visit("Signals", visitor)?;
 
+        // Pending events are part of runtime state, they must survive save/load.
+        let mut events = self.events.iter().cloned().collect::<Vec<_>>();
+        let _ = events.visit("Events", visitor);
+        if visitor.is_reading() {
+            self.events = events.into();
+        }
+
+        // Animations that were created from code have no resource to take key frames from
+        // on resolve stage, so their key frames must be stored directly.
+        if self.resource.is_none() {
+            let _ = self.visit_key_frames("KeyFrames", visitor);
+        }
+
         visitor.leave_region()
     }
 }
@@ -786,6 +1030,11 @@ impl AnimationContainer {
         self.pool.free(handle);
     }
 
+    #[inline]
+    pub fn is_valid_handle(&self, handle: Handle<Animation>) -> bool {
+        self.pool.is_valid_handle(handle)
+    }
+
     /// Extracts animation from container and reserves its handle. It is used to temporarily take
     /// ownership over animation, and then put animation back using given ticket.
     pub fn take_reserve(&mut self, handle: Handle<Animation>) -> (Ticket<Animation>, Animation) {
@@ -877,3 +1126,189 @@ impl IndexMut<Handle<Animation>> for AnimationContainer {
         &mut self.pool[index]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::{Animation, AnimationPose, KeyFrame, MorphCurve, MorphKeyFrame, Track},
+        core::{
+            algebra::{UnitQuaternion, Vector3},
+            futures::executor::block_on,
+            pool::Handle,
+            visitor::{Visit, Visitor},
+        },
+        scene::{
+            base::BaseBuilder, graph::Graph, mesh::MeshBuilder, node::Node,
+            transform::TransformBuilder,
+        },
+    };
+
+    fn morph_track(node: Handle<Node>) -> Track {
+        let mut track = Track::new();
+        track.set_node(node);
+        track.add_morph_curve(MorphCurve::new(
+            "Smile",
+            vec![MorphKeyFrame::new(0.0, 0.0), MorphKeyFrame::new(1.0, 1.0)],
+        ));
+        track
+    }
+
+    #[test]
+    fn morph_curve_sampling() {
+        // Key frames must be sorted.
+        let curve = MorphCurve::new(
+            "Smile",
+            vec![
+                MorphKeyFrame::new(2.0, 0.0),
+                MorphKeyFrame::new(0.5, 0.2),
+                MorphKeyFrame::new(1.0, 1.0),
+            ],
+        );
+        assert_eq!(curve.name(), "Smile");
+        assert_eq!(curve.max_time(), 2.0);
+        assert!(curve
+            .key_frames()
+            .windows(2)
+            .all(|pair| pair[0].time < pair[1].time));
+
+        // Before first key frame.
+        assert_eq!(curve.weight_at(0.0), 0.2);
+        // Exactly at key frame.
+        assert_eq!(curve.weight_at(1.0), 1.0);
+        // Between key frames.
+        assert!((curve.weight_at(0.75) - 0.6).abs() < 1.0e-6);
+        assert!((curve.weight_at(1.5) - 0.5).abs() < 1.0e-6);
+        // After last key frame.
+        assert_eq!(curve.weight_at(10.0), 0.0);
+
+        assert_eq!(MorphCurve::default().weight_at(1.0), 0.0);
+    }
+
+    #[test]
+    fn track_with_morph_curves_only_does_not_affect_transform() {
+        let mut graph = Graph::new();
+        let mesh = MeshBuilder::new(BaseBuilder::new()).build(&mut graph);
+
+        let track = morph_track(mesh);
+        assert_eq!(
+            track.get_local_pose(5.0).unwrap().morph_weights()[0].weight,
+            1.0
+        );
+
+        let local_pose = track.get_local_pose(0.25).unwrap();
+        assert!(!local_pose.affects_transform);
+        assert_eq!(local_pose.morph_weights()[0].name, "Smile");
+        assert_eq!(local_pose.morph_weights()[0].weight, 0.25);
+    }
+
+    #[test]
+    fn pose_blending() {
+        let mut graph = Graph::new();
+        let position = Vector3::new(1.0, 2.0, 3.0);
+        let mesh = MeshBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(position)
+                    .build(),
+            ),
+        )
+        .build(&mut graph);
+
+        let mut transform_track = Track::new();
+        transform_track.set_node(mesh);
+        transform_track.add_key_frame(KeyFrame::new(
+            0.0,
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            UnitQuaternion::identity(),
+        ));
+
+        // Morph only pose blended with morph only pose must not touch transform.
+        let mut pose = AnimationPose::default();
+        pose.add_local_pose(morph_track(mesh).get_local_pose(0.5).unwrap());
+        let mut other = AnimationPose::default();
+        other.add_local_pose(morph_track(mesh).get_local_pose(1.0).unwrap());
+        pose.blend_with(&other, 0.5);
+
+        let local_pose = &pose.local_poses[&mesh];
+        assert!(!local_pose.affects_transform);
+        assert_eq!(local_pose.morph_weights()[0].weight, 1.0);
+
+        pose.apply(&mut graph);
+        assert_eq!(**graph[mesh].local_transform().position(), position);
+        if let Node::Mesh(mesh) = &graph[mesh] {
+            assert_eq!(mesh.morph_weight("Smile"), 1.0);
+        } else {
+            unreachable!()
+        }
+
+        // Pose with transform blended into morph only pose takes its transform.
+        let mut transform_pose = AnimationPose::default();
+        transform_pose.add_local_pose(transform_track.get_local_pose(0.0).unwrap());
+        pose.blend_with(&transform_pose, 0.5);
+        let local_pose = &pose.local_poses[&mesh];
+        assert!(local_pose.affects_transform);
+        assert_eq!(local_pose.position(), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(local_pose.scale(), Vector3::new(0.5, 0.5, 0.5));
+        assert_eq!(local_pose.morph_weights()[0].weight, 1.0);
+
+        // Morph only pose blended into pose with transform keeps transform.
+        let mut pose = AnimationPose::default();
+        pose.add_local_pose(
+            transform_track
+                .get_local_pose(0.0)
+                .unwrap()
+                .weighted_clone(0.5),
+        );
+        pose.blend_with(&other, 0.5);
+        let local_pose = &pose.local_poses[&mesh];
+        assert!(local_pose.affects_transform);
+        assert_eq!(local_pose.position(), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(local_pose.scale(), Vector3::new(0.5, 0.5, 0.5));
+        assert_eq!(local_pose.morph_weights()[0].weight, 0.5);
+    }
+
+    #[test]
+    fn missing_key_frames_keep_regions_balanced() {
+        let make_animation = |track_count| {
+            let mut animation = Animation::default();
+            for _ in 0..track_count {
+                let mut track = Track::new();
+                track.add_key_frame(KeyFrame::new(
+                    1.0,
+                    Vector3::new(1.0, 2.0, 3.0),
+                    Vector3::new(1.0, 1.0, 1.0),
+                    UnitQuaternion::identity(),
+                ));
+                animation.add_track(track);
+            }
+            animation
+        };
+
+        let path = std::env::temp_dir().join(format!(
+            "{}_rg3d_missing_key_frames.bin",
+            std::process::id()
+        ));
+
+        let mut visitor = Visitor::new();
+        make_animation(1)
+            .visit_key_frames("KeyFrames", &mut visitor)
+            .unwrap();
+        let mut marker = 42u32;
+        marker.visit("Marker", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+
+        // Second track has no key frames in the file.
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let mut animation = make_animation(2);
+        assert!(animation
+            .visit_key_frames("KeyFrames", &mut visitor)
+            .is_err());
+
+        // Data after key frames must still be readable.
+        let mut marker = 0u32;
+        marker.visit("Marker", &mut visitor).unwrap();
+        assert_eq!(marker, 42);
+    }
+}
@@ -44,7 +44,8 @@ pub trait GameState: 'static {
         Self: Sized;
 
     /// Defines a function that will contain game logic. It has stabilized update rate of
-    /// 60 Hz. Callee can alter control flow of the game by modifying _control_flow parameter.
+    /// 60 Hz by default, see [`Framework::fixed_timestep`]. Callee can alter control flow of
+    /// the game by modifying _control_flow parameter.
     fn on_tick(&mut self, _engine: &mut GameEngine, _dt: f32, _control_flow: &mut ControlFlow) {}
 
     /// Defines a function that will be called when there is any message from user interface.
@@ -70,6 +71,7 @@ pub trait GameState: 'static {
 pub struct Framework<State: GameState> {
     engine: GameEngine,
     title: String,
+    fixed_timestep: f32,
     event_loop: EventLoop<()>,
     state: State,
 }
@@ -86,6 +88,7 @@ impl<State: GameState> Framework<State> {
 
         Ok(Self {
             title: "Game".to_owned(),
+            fixed_timestep: 1.0 / 60.0,
             state: State::init(&mut engine),
             engine,
             event_loop,
@@ -99,13 +102,21 @@ impl<State: GameState> Framework<State> {
         self
     }
 
+    /// Sets duration (in seconds) of a single update tick. Both [`GameState::on_tick`] and the
+    /// engine are updated with this duration. Default is 1/60 of a second.
+    #[must_use]
+    pub fn fixed_timestep(mut self, fixed_timestep: f32) -> Self {
+        self.fixed_timestep = fixed_timestep.max(f32::EPSILON);
+        self
+    }
+
     /// Runs a framework and your game. This function is never returns.
     pub fn run(self) -> ! {
         let mut engine = self.engine;
         engine.get_window().set_title(&self.title);
         let mut state = self.state;
         let clock = Instant::now();
-        let fixed_timestep = 1.0 / 60.0;
+        let fixed_timestep = self.fixed_timestep;
         let mut elapsed_time = 0.0;
 
         self.event_loop
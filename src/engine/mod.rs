@@ -221,23 +221,30 @@ impl<M: MessageData, C: Control<M, C>> Engine<M, C> {
     pub fn render(&mut self) -> Result<(), FrameworkError> {
         self.user_interface.draw();
 
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            self.renderer.render_and_swap_buffers(
-                &self.scenes,
-                &self.user_interface.get_drawing_context(),
-                &self.scenes2d,
-                &self.context,
-            )
+        // Scenes with fixed timestep are rendered in between of ticks.
+        for scene in self.scenes.iter_mut() {
+            scene.graph.begin_interpolation();
         }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = self.renderer.render_and_swap_buffers(
+            &self.scenes,
+            &self.user_interface.get_drawing_context(),
+            &self.scenes2d,
+            &self.context,
+        );
         #[cfg(target_arch = "wasm32")]
-        {
-            self.renderer.render_and_swap_buffers(
-                &self.scenes,
-                &self.user_interface.get_drawing_context(),
-                &self.scenes2d,
-            )
+        let result = self.renderer.render_and_swap_buffers(
+            &self.scenes,
+            &self.user_interface.get_drawing_context(),
+            &self.scenes2d,
+        );
+
+        for scene in self.scenes.iter_mut() {
+            scene.graph.end_interpolation();
         }
+
+        result
     }
 }
 
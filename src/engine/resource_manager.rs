@@ -4,6 +4,7 @@ use crate::{
     core::{futures::executor::ThreadPool, instant, visitor::prelude::*},
     renderer::TextureUploadSender,
     resource::{
+        material::{Material, MaterialData},
         model::{Model, ModelData},
         texture::{
             CompressionOptions, Texture, TextureData, TextureError, TextureMagnificationFilter,
@@ -114,6 +115,7 @@ pub struct ResourceManagerState {
     textures: Vec<TimedEntry<Texture>>,
     models: Vec<TimedEntry<Model>>,
     sound_buffers: Vec<TimedEntry<SharedSoundBuffer>>,
+    materials: Vec<TimedEntry<Material>>,
     textures_import_options: TextureImportOptions,
     #[cfg(not(target_arch = "wasm32"))]
     thread_pool: ThreadPool,
@@ -126,6 +128,7 @@ impl Default for ResourceManagerState {
             textures: Default::default(),
             models: Default::default(),
             sound_buffers: Default::default(),
+            materials: Default::default(),
             textures_import_options: Default::default(),
             #[cfg(not(target_arch = "wasm32"))]
             thread_pool: ThreadPool::new().unwrap(),
@@ -349,6 +352,33 @@ async fn load_sound_buffer(resource: SharedSoundBuffer, path: PathBuf, stream: b
     }
 }
 
+async fn load_material(material: Material, path: PathBuf, resource_manager: ResourceManager) {
+    match MaterialData::load_from_file(&path, resource_manager).await {
+        Ok(data) => {
+            Log::writeln(
+                MessageKind::Information,
+                format!("Material {:?} is loaded!", path),
+            );
+
+            material.state().commit(ResourceState::Ok(data));
+        }
+        Err(error) => {
+            Log::writeln(
+                MessageKind::Error,
+                format!(
+                    "Unable to load material from {:?}! Reason {:?}",
+                    path, error
+                ),
+            );
+
+            material.state().commit(ResourceState::LoadError {
+                path,
+                error: Some(Arc::new(error)),
+            });
+        }
+    }
+}
+
 async fn reload_texture(texture: Texture, path: PathBuf, compression: CompressionOptions) {
     match TextureData::load_from_file(&path, compression).await {
         Ok(data) => {
@@ -447,6 +477,17 @@ impl ResourceManager {
         }
     }
 
+    /// Creates resource manager that is not connected to a renderer, it can be used
+    /// in tests to load resources that do not require GPU.
+    #[cfg(test)]
+    pub(in crate) fn new_headless() -> Self {
+        Self {
+            state: Some(Arc::new(Mutex::new(ResourceManagerState::new(
+                TextureUploadSender::detached(),
+            )))),
+        }
+    }
+
     /// Returns a guarded reference to internal state of resource manager.
     pub fn state(&self) -> MutexGuard<'_, ResourceManagerState> {
         self.state.as_ref().unwrap().lock().unwrap()
@@ -614,6 +655,48 @@ impl ResourceManager {
         result
     }
 
+    /// Tries to load new material from given path or get instance of existing, if any. This
+    /// method is asynchronous, it immediately returns a material which can be shared across
+    /// multiple places.
+    ///
+    /// # Async/.await
+    ///
+    /// Each material implements Future trait and can be used in async contexts.
+    ///
+    /// # Supported formats
+    ///
+    /// Only native binary format is supported, such files can be created by
+    /// [MaterialData::save](crate::resource::material::MaterialData::save).
+    pub fn request_material<P: AsRef<Path>>(&self, path: P) -> Material {
+        let mut state = self.state();
+
+        if let Some(material) = state.find_material(path.as_ref()) {
+            return material;
+        }
+
+        let material = Material::new(ResourceState::new_pending(path.as_ref().to_owned()));
+        state.materials.push(TimedEntry {
+            value: material.clone(),
+            time_to_live: DEFAULT_RESOURCE_LIFETIME,
+        });
+
+        let result = material.clone();
+        let path = path.as_ref().to_owned();
+        let resource_manager = self.clone();
+
+        #[cfg(target_arch = "wasm32")]
+        crate::core::wasm_bindgen_futures::spawn_local(async move {
+            load_material(material, path, resource_manager).await;
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        state.thread_pool.spawn_ok(async move {
+            load_material(material, path, resource_manager).await;
+        });
+
+        result
+    }
+
     /// Reloads every loaded texture. This method is asynchronous, internally it uses thread pool
     /// to run reload on separate thread per texture.
     pub async fn reload_textures(&self) {
@@ -747,6 +830,41 @@ impl ResourceManager {
         crate::core::futures::future::join_all(buffers).await;
     }
 
+    /// Reloads every loaded material. This method is asynchronous, internally it uses thread pool
+    /// to run reload on separate thread per material.
+    pub async fn reload_materials(&self) {
+        let materials = {
+            let this = self.clone();
+            let state = self.state();
+
+            let materials = state
+                .materials
+                .iter()
+                .map(|m| m.value.clone())
+                .collect::<Vec<Material>>();
+
+            for material in materials.iter().cloned() {
+                let this = this.clone();
+                let path = material.state().path().to_path_buf();
+                *material.state() = ResourceState::new_pending(path.clone());
+
+                #[cfg(target_arch = "wasm32")]
+                crate::core::wasm_bindgen_futures::spawn_local(async move {
+                    load_material(material, path, this).await;
+                });
+
+                #[cfg(not(target_arch = "wasm32"))]
+                state.thread_pool.spawn_ok(async move {
+                    load_material(material, path, this).await;
+                })
+            }
+
+            materials
+        };
+
+        crate::core::futures::future::join_all(materials).await;
+    }
+
     /// Reloads all loaded resources. Normally it should never be called, because it is **very** heavy
     /// method! This method is asynchronous, it uses all available CPU power to reload resources as
     /// fast as possible.
@@ -754,7 +872,8 @@ impl ResourceManager {
         crate::core::futures::join!(
             self.reload_textures(),
             self.reload_models(),
-            self.reload_sound_buffers()
+            self.reload_sound_buffers(),
+            self.reload_materials()
         );
     }
 }
@@ -847,6 +966,7 @@ impl ResourceManagerState {
             textures: Vec::new(),
             models: Vec::new(),
             sound_buffers: Vec::new(),
+            materials: Vec::new(),
             textures_import_options: Default::default(),
             #[cfg(not(target_arch = "wasm32"))]
             thread_pool: ThreadPool::new().unwrap(),
@@ -908,6 +1028,22 @@ impl ResourceManagerState {
         None
     }
 
+    /// Returns shared reference to list of available materials.
+    #[inline]
+    pub fn materials(&self) -> &[TimedEntry<Material>] {
+        &self.materials
+    }
+
+    /// Tries to find material by its path. Returns None if no such material was found.
+    pub fn find_material<P: AsRef<Path>>(&self, path: P) -> Option<Material> {
+        for material in self.materials.iter() {
+            if material.state().path() == path.as_ref() {
+                return Some(material.value.clone());
+            }
+        }
+        None
+    }
+
     /// Returns total amount of textures in pending state.
     pub fn count_pending_textures(&self) -> usize {
         count_pending_resources(&self.textures)
@@ -938,11 +1074,22 @@ impl ResourceManagerState {
         count_loaded_resources(&self.models)
     }
 
+    /// Returns total amount of materials in pending state.
+    pub fn count_pending_materials(&self) -> usize {
+        count_pending_resources(&self.materials)
+    }
+
+    /// Returns total amount of loaded materials (including materials, that failed to load).
+    pub fn count_loaded_materials(&self) -> usize {
+        count_loaded_resources(&self.materials)
+    }
+
     /// Returns total amount of resources in pending state.
     pub fn count_pending_resources(&self) -> usize {
         self.count_pending_textures()
             + self.count_pending_sound_buffers()
             + self.count_pending_models()
+            + self.count_pending_materials()
     }
 
     /// Returns total amount of loaded resources.
@@ -950,11 +1097,12 @@ impl ResourceManagerState {
         self.count_loaded_textures()
             + self.count_loaded_sound_buffers()
             + self.count_loaded_models()
+            + self.count_loaded_materials()
     }
 
     /// Returns total amount of registered resources.
     pub fn count_registered_resources(&self) -> usize {
-        self.textures.len() + self.sound_buffers.len() + self.models.len()
+        self.textures.len() + self.sound_buffers.len() + self.models.len() + self.materials.len()
     }
 
     /// Returns percentage of loading progress. This method is useful to show progress on
@@ -976,6 +1124,8 @@ impl ResourceManagerState {
             .retain(|buffer| buffer.value.use_count() > 1);
         self.models.retain(|buffer| buffer.value.use_count() > 1);
         self.textures.retain(|buffer| buffer.value.use_count() > 1);
+        self.materials
+            .retain(|material| material.value.use_count() > 1);
     }
 
     fn update_textures(&mut self, dt: f32) {
@@ -1046,10 +1196,33 @@ impl ResourceManagerState {
         });
     }
 
+    fn update_materials(&mut self, dt: f32) {
+        for material in self.materials.iter_mut() {
+            material.time_to_live -= dt;
+            if material.use_count() > 1 {
+                material.time_to_live = DEFAULT_RESOURCE_LIFETIME;
+            }
+        }
+        self.materials.retain(|material| {
+            let retain = material.time_to_live > 0.0;
+            if !retain {
+                Log::writeln(
+                    MessageKind::Information,
+                    format!(
+                        "Material resource {:?} destroyed because it not used anymore!",
+                        material.state().path()
+                    ),
+                );
+            }
+            retain
+        });
+    }
+
     pub(in crate) fn update(&mut self, dt: f32) {
         self.update_textures(dt);
         self.update_model(dt);
         self.update_sound_buffers(dt);
+        self.update_materials(dt);
     }
 }
 
@@ -1066,10 +1239,14 @@ impl Visit for ResourceManagerState {
         crate::core::futures::executor::block_on(crate::core::futures::future::join_all(
             self.sound_buffers.iter().map(|m| m.value.clone()),
         ));
+        crate::core::futures::executor::block_on(crate::core::futures::future::join_all(
+            self.materials.iter().map(|m| m.value.clone()),
+        ));
 
         self.textures.visit("Textures", visitor)?;
         self.models.visit("Models", visitor)?;
         self.sound_buffers.visit("SoundBuffers", visitor)?;
+        let _ = self.materials.visit("Materials", visitor);
 
         visitor.leave_region()
     }
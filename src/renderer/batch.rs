@@ -1,4 +1,4 @@
-use crate::core::algebra::Vector2;
+use crate::core::algebra::{Vector2, Vector4};
 use crate::{
     core::{algebra::Matrix4, arrayvec::ArrayVec, color::Color, pool::Handle, scope_profile},
     renderer::framework::{
@@ -9,22 +9,60 @@ use crate::{
         state::PipelineState,
     },
     renderer::TextureCache,
+    resource::{
+        material::{Material, PropertyValue, SamplerFallback},
+        ResourceState,
+    },
     scene::{
         graph::Graph,
-        mesh::{surface::SurfaceData, RenderPath},
+        mesh::{
+            morph::{morph_target_weights, MAX_GPU_MORPH_TARGETS},
+            skin::SkinPose,
+            surface::SurfaceData,
+            RenderPath,
+        },
         node::Node,
     },
 };
 use std::{
     cell::RefCell,
-    collections::HashMap,
+    collections::{hash_map::DefaultHasher, HashMap},
     fmt::{Debug, Formatter},
+    hash::{Hash, Hasher},
     rc::Rc,
     sync::{Arc, RwLock},
 };
 
 pub const BONE_MATRICES_COUNT: usize = 64;
 
+/// Returns palette of a skinned surface. Palette is calculated once per frame by the graph,
+/// but a mesh may be rendered before its first update (or right after it was cloned), in
+/// this case palette is calculated from bones directly.
+fn bone_matrices(
+    graph: &Graph,
+    bones: &[Handle<Node>],
+    pose: Option<&SkinPose>,
+) -> ArrayVec<Matrix4<f32>, BONE_MATRICES_COUNT> {
+    match pose {
+        Some(pose) if pose.bone_matrices().len() == bones.len() => pose
+            .bone_matrices()
+            .iter()
+            .take(BONE_MATRICES_COUNT)
+            .copied()
+            .collect(),
+        _ if bones.is_empty() => Default::default(),
+        _ => {
+            let mut pose = SkinPose::default();
+            pose.update(graph, bones);
+            pose.bone_matrices()
+                .iter()
+                .take(BONE_MATRICES_COUNT)
+                .copied()
+                .collect()
+        }
+    }
+}
+
 #[repr(C)]
 #[doc(hidden)]
 pub struct InstanceData {
@@ -40,6 +78,9 @@ pub struct SurfaceInstance {
     pub bone_matrices: ArrayVec<Matrix4<f32>, BONE_MATRICES_COUNT>,
     pub color: Color,
     pub depth_offset: f32,
+    /// Weights of morph targets that are applied on GPU, empty if the surface is not morphed
+    /// on GPU.
+    pub morph_weights: ArrayVec<f32, MAX_GPU_MORPH_TARGETS>,
 }
 
 pub struct Batch {
@@ -59,6 +100,15 @@ pub struct Batch {
     pub is_terrain: bool,
     pub blend: bool,
     pub tex_coord_scale: Vector2<f32>,
+    /// Material of the batch, it is set only if the material is fully loaded.
+    pub material: Option<Material>,
+    /// GPU textures of sampler properties of the material.
+    pub material_textures: Vec<Rc<RefCell<GpuTexture>>>,
+    /// Offsets of morph targets packed in a texture, see [MorphDeltaStorage]. It is a dummy
+    /// texture if the surface is not morphed on GPU.
+    pub morph_deltas: Rc<RefCell<GpuTexture>>,
+    /// Amount of morph targets applied on GPU.
+    pub morph_target_count: usize,
     sort_index: u64,
 }
 
@@ -77,10 +127,18 @@ impl Debug for Batch {
 pub struct BatchStorage {
     buffers: Vec<Vec<SurfaceInstance>>,
     batch_map: HashMap<u64, usize>,
+    morph_delta_storage: MorphDeltaStorage,
     /// Sorted list of batches.
     pub batches: Vec<Batch>,
 }
 
+fn combine_keys(a: u64, b: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    a.hash(&mut hasher);
+    b.hash(&mut hasher);
+    hasher.finish()
+}
+
 impl BatchStorage {
     pub(in crate) fn generate_batches(
         &mut self,
@@ -101,11 +159,12 @@ impl BatchStorage {
 
         self.batches.clear();
         self.batch_map.clear();
+        self.morph_delta_storage.begin_frame();
 
         for (handle, node) in graph.pair_iter() {
             match node {
                 Node::Mesh(mesh) => {
-                    for surface in mesh.surfaces().iter() {
+                    for (surface_index, surface) in mesh.surfaces().iter().enumerate() {
                         let is_skinned = !surface.bones.is_empty();
 
                         let world = if is_skinned {
@@ -114,8 +173,33 @@ impl BatchStorage {
                             mesh.global_transform()
                         };
 
-                        let data = surface.data();
-                        let key = surface.batch_id();
+                        let mut data = surface.data();
+                        let mut key = surface.batch_id();
+                        let mut morph_deltas = black_dummy.clone();
+                        let mut morph_weights = ArrayVec::new();
+
+                        if let Some(morphed_data) = mesh.morphed_data(surface_index) {
+                            // Surface is morphed on CPU, so it uses its own copy of data.
+                            key = combine_keys(key, &*morphed_data as *const _ as u64);
+                            data = morphed_data;
+                        } else {
+                            let data_ref = data.read().unwrap();
+                            if mesh.is_gpu_morphed(surface, &data_ref) {
+                                morph_weights.extend(morph_target_weights(
+                                    mesh.morph_weights(),
+                                    &data_ref.morph_targets,
+                                ));
+                                drop(data_ref);
+                                if let Some(texture) = self.morph_delta_storage.get(state, &data) {
+                                    morph_deltas = texture;
+                                    // Morphed surfaces have unique weights so they can't be
+                                    // instanced.
+                                    key = combine_keys(key, handle.index() as u64);
+                                } else {
+                                    morph_weights.clear();
+                                }
+                            }
+                        }
 
                         let diffuse_texture = surface
                             .diffuse_texture_ref()
@@ -147,6 +231,43 @@ impl BatchStorage {
                             .and_then(|texture| texture_cache.get(state, texture))
                             .unwrap_or_else(|| black_dummy.clone());
 
+                        let material = surface
+                            .material_ref()
+                            .filter(|m| matches!(*m.state(), ResourceState::Ok(_)))
+                            .cloned();
+
+                        let material_textures = material
+                            .as_ref()
+                            .map(|material| {
+                                material
+                                    .data_ref()
+                                    .properties()
+                                    .filter_map(|(_, value)| {
+                                        if let PropertyValue::Sampler { value, fallback } = value {
+                                            Some(
+                                                value
+                                                    .as_ref()
+                                                    .and_then(|t| texture_cache.get(state, t))
+                                                    .unwrap_or_else(|| match fallback {
+                                                        SamplerFallback::White => {
+                                                            white_dummy.clone()
+                                                        }
+                                                        SamplerFallback::Black => {
+                                                            black_dummy.clone()
+                                                        }
+                                                        SamplerFallback::Normal => {
+                                                            normal_dummy.clone()
+                                                        }
+                                                    }),
+                                            )
+                                        } else {
+                                            None
+                                        }
+                                    })
+                                    .collect::<Vec<_>>()
+                            })
+                            .unwrap_or_default();
+
                         let batch = if let Some(&batch_index) = self.batch_map.get(&key) {
                             self.batches.get_mut(batch_index).unwrap()
                         } else {
@@ -171,6 +292,10 @@ impl BatchStorage {
                                 is_terrain: false,
                                 blend: false,
                                 tex_coord_scale: Vector2::new(1.0, 1.0),
+                                material: None,
+                                material_textures: Default::default(),
+                                morph_deltas: black_dummy.clone(),
+                                morph_target_count: 0,
                             });
                             self.batches.last_mut().unwrap()
                         };
@@ -185,21 +310,22 @@ impl BatchStorage {
                         batch.lightmap_texture = lightmap_texture;
                         batch.height_texture = height_texture;
                         batch.use_pom = surface.height_texture().is_some();
+                        batch.material = material;
+                        batch.material_textures = material_textures;
+                        batch.morph_deltas = morph_deltas;
+                        batch.morph_target_count = morph_weights.len();
 
                         batch.instances.push(SurfaceInstance {
                             world_transform: world,
-                            bone_matrices: surface
-                                .bones
-                                .iter()
-                                .map(|&bone_handle| {
-                                    let bone_node = &graph[bone_handle];
-                                    bone_node.global_transform()
-                                        * bone_node.inv_bind_pose_transform()
-                                })
-                                .collect(),
+                            bone_matrices: bone_matrices(
+                                graph,
+                                surface.bones(),
+                                mesh.skin_pose(surface_index),
+                            ),
                             color: surface.color(),
                             owner: handle,
                             depth_offset: mesh.depth_offset_factor(),
+                            morph_weights,
                         });
                     }
                 }
@@ -272,6 +398,10 @@ impl BatchStorage {
                                     is_terrain: true,
                                     blend: layer_index != 0,
                                     tex_coord_scale: layer.tile_factor,
+                                    material: None,
+                                    material_textures: Default::default(),
+                                    morph_deltas: black_dummy.clone(),
+                                    morph_target_count: 0,
                                 });
                                 self.batches.last_mut().unwrap()
                             };
@@ -294,6 +424,7 @@ impl BatchStorage {
                                 color: Color::WHITE,
                                 owner: handle,
                                 depth_offset: terrain.depth_offset_factor(),
+                                morph_weights: Default::default(),
                             });
                         }
                     }
@@ -303,6 +434,116 @@ impl BatchStorage {
         }
 
         self.batches.sort_unstable_by_key(|b| b.sort_index);
+
+        self.morph_delta_storage.end_frame();
+    }
+}
+
+/// Width of morph delta textures in texels.
+const MORPH_DELTA_TEXTURE_WIDTH: usize = 1024;
+
+struct MorphDeltaTexture {
+    // Holds data alive, so its address can't be reused by some other data while the entry
+    // exists.
+    data: Arc<RwLock<SurfaceData>>,
+    texture: Rc<RefCell<GpuTexture>>,
+    target_count: usize,
+    vertex_count: usize,
+    used: bool,
+}
+
+/// Storage of morph target offsets for surfaces that are morphed on GPU. Offsets of each surface
+/// data are packed into a RGBA32F texture, every vertex of every target takes three texels
+/// (position, normal and tangent offsets), so offset `k` of target `t` of vertex `v` is stored
+/// at texel `(v * target_count + t) * 3 + k`. Textures of data that was not rendered during
+/// a frame are destroyed.
+///
+/// Texture is rebuilt only when amount of targets or vertices changes, so morph targets must
+/// not be modified in place once data was rendered.
+#[derive(Default)]
+pub struct MorphDeltaStorage {
+    textures: HashMap<u64, MorphDeltaTexture>,
+}
+
+impl MorphDeltaStorage {
+    fn begin_frame(&mut self) {
+        for entry in self.textures.values_mut() {
+            entry.used = false;
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.textures.retain(|_, entry| entry.used);
+    }
+
+    fn get(
+        &mut self,
+        state: &mut PipelineState,
+        data: &Arc<RwLock<SurfaceData>>,
+    ) -> Option<Rc<RefCell<GpuTexture>>> {
+        let key = &**data as *const _ as u64;
+        let data_ref = data.read().unwrap();
+        let target_count = data_ref.morph_targets.len();
+        let vertex_count = data_ref.vertex_buffer.vertex_count() as usize;
+
+        if let Some(entry) = self.textures.get_mut(&key) {
+            if Arc::ptr_eq(&entry.data, data)
+                && entry.target_count == target_count
+                && entry.vertex_count == vertex_count
+            {
+                entry.used = true;
+                return Some(entry.texture.clone());
+            }
+        }
+
+        let texel_count = (target_count * vertex_count * 3).max(1);
+        let width = texel_count.min(MORPH_DELTA_TEXTURE_WIDTH);
+        let height = texel_count.div_ceil(width);
+
+        let mut texels = vec![Vector4::<f32>::default(); width * height];
+        for (target_index, target) in data_ref.morph_targets.iter().enumerate() {
+            for delta in target.vertices.iter() {
+                let vertex_index = delta.index as usize;
+                if vertex_index < vertex_count {
+                    let origin = (vertex_index * target_count + target_index) * 3;
+                    texels[origin] = delta.position.push(0.0);
+                    texels[origin + 1] = delta.normal.push(0.0);
+                    texels[origin + 2] = delta.tangent.push(0.0);
+                }
+            }
+        }
+
+        match GpuTexture::new(
+            state,
+            GpuTextureKind::Rectangle { width, height },
+            PixelKind::RGBA32F,
+            MinificationFilter::Nearest,
+            MagnificationFilter::Nearest,
+            1,
+            Some(unsafe {
+                std::slice::from_raw_parts(
+                    texels.as_slice() as *const _ as *const u8,
+                    texels.len() * std::mem::size_of::<Vector4<f32>>(),
+                )
+            }),
+        ) {
+            Ok(texture) => {
+                let texture = Rc::new(RefCell::new(texture));
+                drop(data_ref);
+                self.textures.insert(
+                    key,
+                    MorphDeltaTexture {
+                        data: data.clone(),
+                        texture: texture.clone(),
+                        target_count,
+                        vertex_count,
+                        used: true,
+                    },
+                );
+                Some(texture)
+            }
+            Err(_) => None,
+        }
     }
 }
 
@@ -384,3 +625,33 @@ impl MatrixStorage {
             .unwrap();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Matrix4, Vector3},
+        renderer::batch::bone_matrices,
+        scene::{base::BaseBuilder, graph::Graph, mesh::skin::SkinPose},
+    };
+
+    #[test]
+    fn palette_is_calculated_without_skin_pose() {
+        let mut graph = Graph::new();
+        let bone = BaseBuilder::new()
+            .with_inv_bind_pose_transform(Matrix4::new_translation(&Vector3::new(1.0, 0.0, 0.0)))
+            .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let expected = graph[bone].global_transform() * graph[bone].inv_bind_pose_transform();
+
+        // Mesh was not updated yet (or it was just cloned).
+        let palette = bone_matrices(&graph, &[bone], None);
+        assert_eq!(palette.as_slice(), &[expected]);
+
+        // Stale pose from a previous set of bones.
+        let palette = bone_matrices(&graph, &[bone], Some(&SkinPose::default()));
+        assert_eq!(palette.as_slice(), &[expected]);
+
+        assert!(bone_matrices(&graph, &[], None).is_empty());
+    }
+}
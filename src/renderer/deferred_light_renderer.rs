@@ -17,8 +17,12 @@ use crate::{
         },
         gbuffer::GBuffer,
         light_volume::LightVolumeRenderer,
+        reflection_renderer::{
+            ReflectionProbeInstance, ReflectionRenderContext, ReflectionRenderer,
+        },
         shadow_map_renderer::{
-            PointShadowMapRenderContext, PointShadowMapRenderer, SpotShadowMapRenderer,
+            select_cascade, CsmRenderer, PointShadowMapRenderContext, PointShadowMapRenderer,
+            SpotShadowMapRenderer,
         },
         skybox_shader::SkyboxShader,
         ssao::ScreenSpaceAmbientOcclusionRenderer,
@@ -26,7 +30,10 @@ use crate::{
     },
     scene::{
         camera::Camera,
-        light::Light,
+        light::{
+            directional::{self, CSM_MAX_CASCADE_COUNT},
+            Light,
+        },
         mesh::{
             buffer::{GeometryBuffer, VertexBuffer},
             surface::SurfaceData,
@@ -59,6 +66,7 @@ pub struct LightingStatistics {
     pub spot_lights_rendered: usize,
     pub spot_shadow_maps_rendered: usize,
     pub directional_lights_rendered: usize,
+    pub directional_shadow_maps_rendered: usize,
 }
 
 impl AddAssign for LightingStatistics {
@@ -68,6 +76,7 @@ impl AddAssign for LightingStatistics {
         self.spot_lights_rendered += rhs.spot_lights_rendered;
         self.spot_shadow_maps_rendered += rhs.spot_shadow_maps_rendered;
         self.directional_lights_rendered += rhs.directional_lights_rendered;
+        self.directional_shadow_maps_rendered += rhs.directional_shadow_maps_rendered;
     }
 }
 
@@ -80,12 +89,14 @@ impl Display for LightingStatistics {
             \tSpot Lights: {}\n\
             \tDirectional Lights: {}\n\
             \tPoint Shadow Maps: {}\n\
-            \tSpot Shadow Maps: {}",
+            \tSpot Shadow Maps: {}\n\
+            \tDirectional Shadow Maps: {}",
             self.point_lights_rendered,
             self.spot_lights_rendered,
             self.directional_lights_rendered,
             self.point_shadow_maps_rendered,
             self.spot_shadow_maps_rendered,
+            self.directional_shadow_maps_rendered,
         )
     }
 }
@@ -218,6 +229,15 @@ struct DirectionalLightShader {
     light_color: UniformLocation,
     inv_view_proj_matrix: UniformLocation,
     camera_position: UniformLocation,
+    view_matrix: UniformLocation,
+    light_view_proj_matrices: UniformLocation,
+    cascade_distances: UniformLocation,
+    cascade_count: UniformLocation,
+    shadow_cascades: [UniformLocation; CSM_MAX_CASCADE_COUNT],
+    shadows_enabled: UniformLocation,
+    soft_shadows: UniformLocation,
+    shadow_map_inv_size: UniformLocation,
+    shadow_bias: UniformLocation,
 }
 
 impl DirectionalLightShader {
@@ -235,6 +255,20 @@ impl DirectionalLightShader {
             light_color: program.uniform_location(state, "lightColor")?,
             inv_view_proj_matrix: program.uniform_location(state, "invViewProj")?,
             camera_position: program.uniform_location(state, "cameraPosition")?,
+            view_matrix: program.uniform_location(state, "viewMatrix")?,
+            light_view_proj_matrices: program.uniform_location(state, "lightViewProjMatrices")?,
+            cascade_distances: program.uniform_location(state, "cascadeDistances")?,
+            cascade_count: program.uniform_location(state, "cascadeCount")?,
+            shadow_cascades: [
+                program.uniform_location(state, "shadowCascade0")?,
+                program.uniform_location(state, "shadowCascade1")?,
+                program.uniform_location(state, "shadowCascade2")?,
+                program.uniform_location(state, "shadowCascade3")?,
+            ],
+            shadows_enabled: program.uniform_location(state, "shadowsEnabled")?,
+            soft_shadows: program.uniform_location(state, "softShadows")?,
+            shadow_map_inv_size: program.uniform_location(state, "shadowMapInvSize")?,
+            shadow_bias: program.uniform_location(state, "shadowBias")?,
             program,
         })
     }
@@ -253,7 +287,9 @@ pub struct DeferredLightRenderer {
     skybox_shader: SkyboxShader,
     spot_shadow_map_renderer: SpotShadowMapRenderer,
     point_shadow_map_renderer: PointShadowMapRenderer,
+    csm_renderer: CsmRenderer,
     light_volume: LightVolumeRenderer,
+    reflection_renderer: ReflectionRenderer,
 }
 
 pub(in crate) struct DeferredRendererContext<'a> {
@@ -262,11 +298,14 @@ pub(in crate) struct DeferredRendererContext<'a> {
     pub camera: &'a Camera,
     pub gbuffer: &'a mut GBuffer,
     pub white_dummy: Rc<RefCell<GpuTexture>>,
+    pub environment_dummy: Rc<RefCell<GpuTexture>>,
     pub ambient_color: Color,
     pub settings: &'a QualitySettings,
     pub textures: &'a mut TextureCache,
     pub geometry_cache: &'a mut GeometryCache,
     pub batch_storage: &'a BatchStorage,
+    /// Captured reflection probes that affect the camera, could be empty.
+    pub reflection_probes: &'a [ReflectionProbeInstance],
 }
 
 impl DeferredLightRenderer {
@@ -398,7 +437,14 @@ impl DeferredLightRenderer {
                 settings.point_shadow_map_size,
                 QualitySettings::default().point_shadow_map_precision,
             )?,
+            csm_renderer: CsmRenderer::new(
+                state,
+                settings.directional_shadow_map_size,
+                QualitySettings::default().directional_shadow_map_precision,
+                settings.directional_shadow_cascade_count,
+            )?,
             light_volume: LightVolumeRenderer::new(state)?,
+            reflection_renderer: ReflectionRenderer::new(state)?,
         })
     }
 
@@ -425,6 +471,17 @@ impl DeferredLightRenderer {
                 settings.point_shadow_map_precision,
             )?;
         }
+        if settings.directional_shadow_map_size != self.csm_renderer.size()
+            || settings.directional_shadow_map_precision != self.csm_renderer.precision()
+            || settings.directional_shadow_cascade_count != self.csm_renderer.cascade_count()
+        {
+            self.csm_renderer = CsmRenderer::new(
+                state,
+                settings.directional_shadow_map_size,
+                settings.directional_shadow_map_precision,
+                settings.directional_shadow_cascade_count,
+            )?;
+        }
         self.ssao_renderer.set_radius(settings.ssao_radius);
         Ok(())
     }
@@ -458,11 +515,13 @@ impl DeferredLightRenderer {
             camera,
             gbuffer,
             white_dummy,
+            environment_dummy,
             ambient_color,
             settings,
             textures,
             geometry_cache,
             batch_storage,
+            reflection_probes,
         } = args;
 
         let viewport = Rect::new(0, 0, gbuffer.width, gbuffer.height);
@@ -625,23 +684,20 @@ impl DeferredLightRenderer {
 
             let distance_to_camera = (light.global_position() - camera.global_position()).norm();
 
-            let v = match light {
+            let shadows_distance = match light {
                 Light::Directional(_) => 0.0,
                 Light::Spot(_) => settings.spot_shadows_distance,
                 Light::Point(_) => settings.point_shadows_distance,
             };
-            let b1 = v * 0.2;
-            let b2 = v * 0.4;
-            let cascade_index =
-                if distance_to_camera < b1 || frustum.is_contains_point(camera.global_position()) {
-                    0
-                } else if distance_to_camera > b1 && distance_to_camera < b2 {
-                    1
-                } else {
-                    2
-                };
+            let cascade_index = select_cascade(
+                distance_to_camera,
+                shadows_distance,
+                frustum.is_contains_point(camera.global_position()),
+            );
 
             let mut light_view_projection = Matrix4::identity();
+            let mut csm_view_projections = [Matrix4::identity(); CSM_MAX_CASCADE_COUNT];
+            let mut csm_distances = [0.0; CSM_MAX_CASCADE_COUNT];
             let shadows_enabled = light.is_cast_shadows()
                 && match light {
                     Light::Spot(spot)
@@ -703,9 +759,41 @@ impl DeferredLightRenderer {
 
                         true
                     }
-                    Light::Directional(_) => {
-                        // TODO: Add cascaded shadow map.
-                        false
+                    Light::Directional(directional) if settings.directional_shadows_enabled => {
+                        let z_near = camera.z_near();
+                        let z_far = camera.z_far().min(settings.directional_shadows_distance);
+                        let splits = directional.csm_options().split_options().split_distances(
+                            z_near,
+                            z_far,
+                            self.csm_renderer.cascade_count(),
+                        );
+
+                        for cascade in 0..self.csm_renderer.cascade_count() {
+                            csm_view_projections[cascade] = directional::cascade_view_projection(
+                                &inv_view_projection,
+                                z_near,
+                                camera.z_far(),
+                                splits[cascade],
+                                splits[cascade + 1],
+                                -emit_direction,
+                                self.csm_renderer.size(),
+                                settings.directional_shadows_distance,
+                            );
+                            csm_distances[cascade] = splits[cascade + 1];
+
+                            pass_stats += self.csm_renderer.render(
+                                state,
+                                &scene.graph,
+                                &csm_view_projections[cascade],
+                                batch_storage,
+                                geometry_cache,
+                                cascade,
+                            );
+                        }
+
+                        light_stats.directional_shadow_maps_rendered += 1;
+
+                        true
                     }
                     _ => false,
                 };
@@ -896,8 +984,10 @@ impl DeferredLightRenderer {
                         },
                     )
                 }
-                Light::Directional(_) => {
+                Light::Directional(directional) => {
                     let shader = &self.directional_light_shader;
+                    let csm_renderer = &self.csm_renderer;
+                    let cascade_count = csm_renderer.cascade_count();
 
                     light_stats.directional_lights_rendered += 1;
 
@@ -916,7 +1006,7 @@ impl DeferredLightRenderer {
                             blend: true,
                         },
                         |program_binding| {
-                            program_binding
+                            let mut program_binding = program_binding
                                 .set_vector3(&shader.light_direction, &emit_direction)
                                 .set_matrix4(&shader.inv_view_proj_matrix, &inv_view_projection)
                                 .set_color(&shader.light_color, &light.color())
@@ -924,7 +1014,32 @@ impl DeferredLightRenderer {
                                 .set_vector3(&shader.camera_position, &camera_global_position)
                                 .set_texture(&shader.depth_sampler, &gbuffer_depth_map)
                                 .set_texture(&shader.color_sampler, &gbuffer_diffuse_map)
-                                .set_texture(&shader.normal_sampler, &gbuffer_normal_map);
+                                .set_texture(&shader.normal_sampler, &gbuffer_normal_map)
+                                .set_matrix4(&shader.view_matrix, &camera.view_matrix())
+                                .set_matrix4_array(
+                                    &shader.light_view_proj_matrices,
+                                    &csm_view_projections,
+                                )
+                                .set_float_slice(&shader.cascade_distances, &csm_distances)
+                                .set_integer(&shader.cascade_count, cascade_count as i32)
+                                .set_bool(&shader.shadows_enabled, shadows_enabled)
+                                .set_bool(&shader.soft_shadows, settings.directional_soft_shadows)
+                                .set_float(
+                                    &shader.shadow_map_inv_size,
+                                    1.0 / csm_renderer.size() as f32,
+                                )
+                                .set_float(
+                                    &shader.shadow_bias,
+                                    directional.csm_options().shadow_bias(),
+                                );
+
+                            // Samplers of unused cascades still must be bound to something.
+                            for (i, location) in shader.shadow_cascades.iter().enumerate() {
+                                program_binding = program_binding.set_texture(
+                                    location,
+                                    &csm_renderer.cascade_texture(i.min(cascade_count - 1)),
+                                );
+                            }
                         },
                     )
                 }
@@ -947,6 +1062,26 @@ impl DeferredLightRenderer {
             }
         }
 
+        // Reflections are applied on top of lit frame.
+        let environment = camera
+            .environment_ref()
+            .and_then(|texture| textures.get(state, texture))
+            .unwrap_or(environment_dummy);
+
+        pass_stats += self.reflection_renderer.render(ReflectionRenderContext {
+            state,
+            gbuffer,
+            geometry_cache,
+            quad: &self.quad,
+            frame_matrix,
+            view_projection,
+            inv_view_projection,
+            camera_position: camera_global_position,
+            environment,
+            probes: reflection_probes,
+            settings,
+        });
+
         (pass_stats, light_stats)
     }
 }
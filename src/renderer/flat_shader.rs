@@ -1,25 +1,192 @@
-use crate::renderer::framework::{
-    error::FrameworkError,
-    gpu_program::{GpuProgram, UniformLocation},
-    state::PipelineState,
+use crate::{
+    core::{
+        algebra::{Matrix4, Vector3, Vector4},
+        math::Rect,
+    },
+    renderer::framework::{
+        backend::{Backend, BackendFrameBuffer, GlBackend},
+        error::FrameworkError,
+        framebuffer::{CullFace, DrawParameters},
+        geometry_buffer::DrawCallStatistics,
+        software::{
+            geometry_buffer::SoftwareVertex,
+            program::{SoftwareProgramBinding, SoftwareShader, Varyings},
+        },
+    },
 };
+use std::{cell::RefCell, rc::Rc};
 
-pub struct FlatShader {
-    pub program: GpuProgram,
-    pub wvp_matrix: UniformLocation,
-    pub diffuse_texture: UniformLocation,
+pub struct FlatShader<B: Backend = GlBackend> {
+    pub program: B::Program,
+    pub wvp_matrix: B::UniformLocation,
+    pub diffuse_texture: B::UniformLocation,
 }
 
-impl FlatShader {
-    pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+impl<B: Backend> FlatShader<B> {
+    pub fn new(state: &mut B::State) -> Result<Self, FrameworkError> {
         let fragment_source = include_str!("shaders/flat_fs.glsl");
         let vertex_source = include_str!("shaders/flat_vs.glsl");
 
-        let program = GpuProgram::from_source(state, "FlatShader", vertex_source, fragment_source)?;
+        let program = B::create_program(state, "FlatShader", vertex_source, fragment_source)?;
         Ok(Self {
-            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
-            diffuse_texture: program.uniform_location(state, "diffuseTexture")?,
+            wvp_matrix: B::uniform_location(state, &program, "worldViewProjection")?,
+            diffuse_texture: B::uniform_location(state, &program, "diffuseTexture")?,
             program,
         })
     }
+
+    /// Draws the texture over the whole viewport, `quad` must be a unit XY quad.
+    pub fn blit(
+        &self,
+        state: &mut B::State,
+        frame_buffer: &mut B::FrameBuffer,
+        quad: &B::GeometryBuffer,
+        viewport: Rect<i32>,
+        texture: &Rc<RefCell<B::Texture>>,
+    ) -> DrawCallStatistics {
+        let frame_matrix = Matrix4::new_orthographic(
+            0.0,
+            viewport.w() as f32,
+            viewport.h() as f32,
+            0.0,
+            -1.0,
+            1.0,
+        ) * Matrix4::new_nonuniform_scaling(&Vector3::new(
+            viewport.w() as f32,
+            viewport.h() as f32,
+            0.0,
+        ));
+
+        frame_buffer.draw(
+            quad,
+            state,
+            viewport,
+            &self.program,
+            &DrawParameters {
+                cull_face: CullFace::Back,
+                culling: false,
+                color_write: Default::default(),
+                depth_write: true,
+                stencil_test: false,
+                depth_test: false,
+                blend: false,
+            },
+            &mut |program_binding| {
+                program_binding.set_matrix4(&self.wvp_matrix, &frame_matrix);
+                program_binding.set_texture(&self.diffuse_texture, texture);
+            },
+        )
+    }
+}
+
+/// Software implementation of `flat_vs.glsl` and `flat_fs.glsl`.
+pub struct SoftwareFlatShader;
+
+impl SoftwareShader for SoftwareFlatShader {
+    fn uniforms(&self) -> &[&'static str] {
+        &["worldViewProjection", "diffuseTexture"]
+    }
+
+    fn vertex(
+        &self,
+        uniforms: &SoftwareProgramBinding,
+        vertex: &SoftwareVertex,
+        varyings: &mut Varyings,
+    ) -> Vector4<f32> {
+        varyings[0] = vertex.tex_coord.push(0.0).push(0.0);
+        uniforms.matrix4(0) * vertex.position.push(1.0)
+    }
+
+    fn fragment(
+        &self,
+        uniforms: &SoftwareProgramBinding,
+        varyings: &Varyings,
+        outputs: &mut [Vector4<f32>],
+    ) -> bool {
+        outputs[0] = uniforms
+            .texture(1)
+            .map(|texture| texture.borrow().sample(varyings[0].xy()))
+            .unwrap_or_default();
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector4, math::Rect},
+        renderer::{
+            flat_shader::FlatShader,
+            framework::{
+                backend::Backend,
+                software::{
+                    assert_matches_golden_image, framebuffer::SoftwareFrameBuffer,
+                    geometry_buffer::SoftwareGeometryBuffer, state::SoftwareState,
+                    texture::SoftwareTexture, SoftwareBackend,
+                },
+            },
+        },
+        scene::mesh::surface::SurfaceData,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    fn source_texture() -> Rc<RefCell<SoftwareTexture>> {
+        let mut texture = SoftwareTexture::new(4, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                texture.set_pixel(
+                    x,
+                    y,
+                    Vector4::new(x as f32 / 3.0, y as f32 / 3.0, ((x + y) % 2) as f32, 1.0),
+                );
+            }
+        }
+        Rc::new(RefCell::new(texture))
+    }
+
+    fn blit(size: usize) -> SoftwareFrameBuffer {
+        let mut state = SoftwareState::new();
+        let shader = FlatShader::<SoftwareBackend>::new(&mut state).unwrap();
+        let quad = SoftwareGeometryBuffer::from_surface_data(&SurfaceData::make_unit_xy_quad());
+
+        let mut frame_buffer = SoftwareFrameBuffer::new(size, size, 1, false);
+        shader.blit(
+            &mut state,
+            &mut frame_buffer,
+            &quad,
+            Rect::new(0, 0, size as i32, size as i32),
+            &source_texture(),
+        );
+        frame_buffer
+    }
+
+    #[test]
+    fn blit_copies_texture_on_software_backend() {
+        let frame_buffer = blit(4);
+
+        let source = source_texture();
+        let source = source.borrow();
+        let frame = frame_buffer.color_attachments()[0].borrow();
+        for y in 0..4 {
+            for x in 0..4 {
+                assert!((frame.pixel(x, y) - source.pixel(x, y)).norm() < 1.0e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn blit_matches_golden_image() {
+        let mut frame = blit(32).color_attachments()[0].borrow().clone();
+        frame.flip_vertically();
+        assert_matches_golden_image("flat_shader_blit", &frame.to_texture_data());
+    }
+
+    #[test]
+    fn unknown_program_is_an_error() {
+        let mut state = SoftwareState::new();
+        assert!(SoftwareBackend::create_program(&mut state, "Unknown", "", "").is_err());
+
+        let program = SoftwareBackend::create_program(&mut state, "FlatShader", "", "").unwrap();
+        assert!(SoftwareBackend::uniform_location(&mut state, &program, "unknown").is_err());
+    }
 }
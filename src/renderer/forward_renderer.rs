@@ -14,7 +14,11 @@ use crate::{
         gpu_program::{GpuProgram, UniformLocation},
         state::PipelineState,
     },
-    renderer::{batch::BatchStorage, GeometryCache, RenderPassStatistics},
+    renderer::{
+        batch::BatchStorage,
+        shader_cache::{InstanceUniforms, ShaderCache},
+        GeometryCache, RenderPassStatistics,
+    },
     scene::{camera::Camera, mesh::RenderPath},
 };
 
@@ -55,6 +59,7 @@ pub(in crate) struct ForwardRenderContext<'a, 'b> {
     pub batch_storage: &'a BatchStorage,
     pub framebuffer: &'a mut FrameBuffer,
     pub viewport: Rect<i32>,
+    pub shader_cache: &'a mut ShaderCache,
 }
 
 impl ForwardRenderer {
@@ -76,6 +81,7 @@ impl ForwardRenderer {
             batch_storage,
             framebuffer,
             viewport,
+            shader_cache,
         } = args;
 
         let params = DrawParameters {
@@ -91,6 +97,7 @@ impl ForwardRenderer {
         state.set_blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
 
         let initial_view_projection = camera.view_projection_matrix();
+        let camera_position = camera.global_position();
 
         for batch in batch_storage
             .batches
@@ -100,6 +107,13 @@ impl ForwardRenderer {
             let data = batch.data.read().unwrap();
             let geometry = geom_cache.get(state, &data);
 
+            let material = batch.material.as_ref().map(|m| m.data_ref());
+            let material_pass = material.as_ref().and_then(|material| {
+                shader_cache
+                    .get(state, material.shader())
+                    .and_then(|shader| shader.pass("Forward"))
+            });
+
             for instance in batch.instances.iter() {
                 if camera.visibility_cache.is_visible(instance.owner) {
                     let view_projection = if instance.depth_offset != 0.0 {
@@ -110,6 +124,32 @@ impl ForwardRenderer {
                         initial_view_projection
                     };
 
+                    if let (Some(material), Some(pass)) = (material.as_ref(), material_pass) {
+                        let world_view_projection = view_projection * instance.world_transform;
+                        statistics += framebuffer.draw(
+                            geometry,
+                            state,
+                            viewport,
+                            &pass.program,
+                            &params,
+                            |program_binding| {
+                                pass.apply(
+                                    program_binding,
+                                    material,
+                                    &batch.material_textures,
+                                    &InstanceUniforms {
+                                        world_matrix: &instance.world_transform,
+                                        world_view_projection: &world_view_projection,
+                                        use_skeletal_animation: batch.is_skinned,
+                                        bone_matrices: instance.bone_matrices.as_slice(),
+                                        camera_position: &camera_position,
+                                    },
+                                );
+                            },
+                        );
+                        continue;
+                    }
+
                     statistics += framebuffer.draw(
                         geometry,
                         state,
@@ -0,0 +1,165 @@
+//! Common interface of framework objects of GPU and software backends.
+//!
+//! Code that is written against [`Backend`] could be used with both OpenGL framework
+//! ([`GlBackend`]) and its CPU reference implementation
+//! ([`SoftwareBackend`](super::software::SoftwareBackend)). The interface covers only the most
+//! common operations - clearing and drawing into frame buffers, creating programs and setting
+//! their uniforms; everything else is backend-specific. Programs are identified by name: the
+//! software backend ignores GLSL sources and uses Rust implementation registered under the same
+//! name instead.
+//!
+//! Only the flat shader pass is generic over backend at the moment, scene passes (G-Buffer,
+//! lighting, shadows, post-processing) still use OpenGL framework directly and cannot run on
+//! CPU.
+
+use crate::{
+    core::{
+        algebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4},
+        color::Color,
+        math::Rect,
+    },
+    renderer::framework::{
+        error::FrameworkError,
+        framebuffer::{DrawParameters, FrameBuffer},
+        geometry_buffer::{DrawCallStatistics, GeometryBuffer},
+        gpu_program::{GpuProgram, UniformLocation},
+        gpu_texture::GpuTexture,
+        state::PipelineState,
+    },
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// Set of framework objects of a backend.
+pub trait Backend: Sized {
+    /// Shared state of the backend, everything is created and drawn through it.
+    type State;
+
+    /// Texture that could be sampled by programs and attached to frame buffers.
+    type Texture;
+
+    /// Vertices and triangles of a mesh that are ready to be drawn.
+    type GeometryBuffer;
+
+    /// Program that transforms vertices and shades fragments of a draw call.
+    type Program;
+
+    /// Handle of a uniform of a program, obtained by [`Self::uniform_location`].
+    type UniformLocation;
+
+    /// Frame buffer that draw calls render into.
+    type FrameBuffer: BackendFrameBuffer<Self>;
+
+    /// Creates a program from GLSL sources. `name` must be unique, it is used to find Rust
+    /// implementation of a program by backends that cannot compile GLSL.
+    fn create_program(
+        state: &mut Self::State,
+        name: &str,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<Self::Program, FrameworkError>;
+
+    /// Finds a uniform of a program by its name in shader sources. Fails if there is no such
+    /// uniform.
+    fn uniform_location(
+        state: &mut Self::State,
+        program: &Self::Program,
+        name: &str,
+    ) -> Result<Self::UniformLocation, FrameworkError>;
+
+    /// Sets blend factors, takes the same values as `glBlendFunc` (`glow::ONE`,
+    /// `glow::SRC_ALPHA`, etc.). Factors are used by draw calls with enabled blending.
+    fn set_blend_func(state: &mut Self::State, sfactor: u32, dfactor: u32);
+}
+
+/// Frame buffer operations that are supported by every backend.
+pub trait BackendFrameBuffer<B: Backend> {
+    /// Clears attachments of the frame buffer within the viewport, attachments with `None`
+    /// value are left untouched.
+    fn clear(
+        &mut self,
+        state: &mut B::State,
+        viewport: Rect<i32>,
+        color: Option<Color>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    );
+
+    /// Draws geometry into the frame buffer using given program and parameters. Uniforms of
+    /// the program are set by `apply_uniforms` right before the draw call.
+    fn draw(
+        &mut self,
+        geometry: &B::GeometryBuffer,
+        state: &mut B::State,
+        viewport: Rect<i32>,
+        program: &B::Program,
+        params: &DrawParameters,
+        apply_uniforms: &mut dyn FnMut(&mut dyn BackendProgramBinding<B>),
+    ) -> DrawCallStatistics;
+}
+
+/// Uniform setters that are supported by every backend. Each setter writes a value of the
+/// uniform at given location for the current draw call.
+pub trait BackendProgramBinding<B: Backend> {
+    /// Binds a texture to a `sampler` uniform.
+    fn set_texture(&mut self, location: &B::UniformLocation, texture: &Rc<RefCell<B::Texture>>);
+
+    /// Sets a `bool` uniform.
+    fn set_bool(&mut self, location: &B::UniformLocation, value: bool);
+
+    /// Sets an `int` uniform.
+    fn set_integer(&mut self, location: &B::UniformLocation, value: i32);
+
+    /// Sets a `float` uniform.
+    fn set_float(&mut self, location: &B::UniformLocation, value: f32);
+
+    /// Sets a `vec2` uniform.
+    fn set_vector2(&mut self, location: &B::UniformLocation, value: &Vector2<f32>);
+
+    /// Sets a `vec3` uniform.
+    fn set_vector3(&mut self, location: &B::UniformLocation, value: &Vector3<f32>);
+
+    /// Sets a `vec4` uniform.
+    fn set_vector4(&mut self, location: &B::UniformLocation, value: &Vector4<f32>);
+
+    /// Sets a `mat3` uniform.
+    fn set_matrix3(&mut self, location: &B::UniformLocation, value: &Matrix3<f32>);
+
+    /// Sets a `mat4` uniform.
+    fn set_matrix4(&mut self, location: &B::UniformLocation, value: &Matrix4<f32>);
+
+    /// Sets a `vec4` uniform from a color, components are normalized to `0..1` range.
+    fn set_color(&mut self, location: &B::UniformLocation, value: &Color);
+}
+
+/// OpenGL backend, the one that is used by [`Renderer`](crate::renderer::Renderer).
+pub struct GlBackend;
+
+impl Backend for GlBackend {
+    type State = PipelineState;
+    type Texture = GpuTexture;
+    type GeometryBuffer = GeometryBuffer;
+    type Program = GpuProgram;
+    type UniformLocation = UniformLocation;
+    type FrameBuffer = FrameBuffer;
+
+    fn create_program(
+        state: &mut Self::State,
+        name: &str,
+        vertex_source: &str,
+        fragment_source: &str,
+    ) -> Result<Self::Program, FrameworkError> {
+        GpuProgram::from_source(state, name, vertex_source, fragment_source)
+    }
+
+    fn uniform_location(
+        state: &mut Self::State,
+        program: &Self::Program,
+        name: &str,
+    ) -> Result<Self::UniformLocation, FrameworkError> {
+        program.uniform_location(state, name)
+    }
+
+    fn set_blend_func(state: &mut Self::State, sfactor: u32, dfactor: u32) {
+        state.set_blend_func(sfactor, dfactor);
+    }
+}
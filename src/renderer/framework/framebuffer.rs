@@ -2,6 +2,7 @@ use crate::renderer::framework::gpu_program::GpuProgramBinding;
 use crate::{
     core::{color::Color, math::Rect, scope_profile},
     renderer::framework::{
+        backend::{BackendFrameBuffer, BackendProgramBinding, GlBackend},
         error::FrameworkError,
         geometry_buffer::{DrawCallStatistics, GeometryBuffer},
         gpu_program::GpuProgram,
@@ -298,6 +299,39 @@ impl FrameBuffer {
     }
 }
 
+impl BackendFrameBuffer<GlBackend> for FrameBuffer {
+    fn clear(
+        &mut self,
+        state: &mut PipelineState,
+        viewport: Rect<i32>,
+        color: Option<Color>,
+        depth: Option<f32>,
+        stencil: Option<i32>,
+    ) {
+        FrameBuffer::clear(self, state, viewport, color, depth, stencil)
+    }
+
+    fn draw(
+        &mut self,
+        geometry: &GeometryBuffer,
+        state: &mut PipelineState,
+        viewport: Rect<i32>,
+        program: &GpuProgram,
+        params: &DrawParameters,
+        apply_uniforms: &mut dyn FnMut(&mut dyn BackendProgramBinding<GlBackend>),
+    ) -> DrawCallStatistics {
+        FrameBuffer::draw(
+            self,
+            geometry,
+            state,
+            viewport,
+            program,
+            params,
+            |mut program_binding| apply_uniforms(&mut program_binding),
+        )
+    }
+}
+
 fn pre_draw<F: FnOnce(GpuProgramBinding<'_>)>(
     fbo: glow::Framebuffer,
     state: &mut PipelineState,
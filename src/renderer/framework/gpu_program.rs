@@ -3,7 +3,12 @@ use crate::{
         algebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4},
         color::Color,
     },
-    renderer::framework::{error::FrameworkError, gpu_texture::GpuTexture, state::PipelineState},
+    renderer::framework::{
+        backend::{BackendProgramBinding, GlBackend},
+        error::FrameworkError,
+        gpu_texture::GpuTexture,
+        state::PipelineState,
+    },
     utils::log::{Log, MessageKind},
 };
 use glow::HasContext;
@@ -254,6 +259,57 @@ impl<'a> GpuProgramBinding<'a> {
     }
 }
 
+impl<'a> GpuProgramBinding<'a> {
+    fn reborrow(&mut self) -> GpuProgramBinding<'_> {
+        GpuProgramBinding {
+            state: self.state,
+            active_sampler: self.active_sampler,
+        }
+    }
+}
+
+impl<'a> BackendProgramBinding<GlBackend> for GpuProgramBinding<'a> {
+    fn set_texture(&mut self, location: &UniformLocation, texture: &Rc<RefCell<GpuTexture>>) {
+        self.active_sampler = self.reborrow().set_texture(location, texture).active_sampler;
+    }
+
+    fn set_bool(&mut self, location: &UniformLocation, value: bool) {
+        self.reborrow().set_bool(location, value);
+    }
+
+    fn set_integer(&mut self, location: &UniformLocation, value: i32) {
+        self.reborrow().set_integer(location, value);
+    }
+
+    fn set_float(&mut self, location: &UniformLocation, value: f32) {
+        self.reborrow().set_float(location, value);
+    }
+
+    fn set_vector2(&mut self, location: &UniformLocation, value: &Vector2<f32>) {
+        self.reborrow().set_vector2(location, value);
+    }
+
+    fn set_vector3(&mut self, location: &UniformLocation, value: &Vector3<f32>) {
+        self.reborrow().set_vector3(location, value);
+    }
+
+    fn set_vector4(&mut self, location: &UniformLocation, value: &Vector4<f32>) {
+        self.reborrow().set_vector4(location, value);
+    }
+
+    fn set_matrix3(&mut self, location: &UniformLocation, value: &Matrix3<f32>) {
+        self.reborrow().set_matrix3(location, value);
+    }
+
+    fn set_matrix4(&mut self, location: &UniformLocation, value: &Matrix4<f32>) {
+        self.reborrow().set_matrix4(location, value);
+    }
+
+    fn set_color(&mut self, location: &UniformLocation, value: &Color) {
+        self.reborrow().set_color(location, value);
+    }
+}
+
 impl GpuProgram {
     pub fn from_source(
         state: &mut PipelineState,
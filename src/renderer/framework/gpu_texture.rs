@@ -92,6 +92,11 @@ pub enum PixelKind {
     DXT3RGBA,
     DXT5RGBA,
     RGBA32F,
+    RGB32F,
+    RG32F,
+    RGBA16F,
+    RGB16F,
+    RG16F,
     R8RGTC,
     RG8RGTC,
 }
@@ -115,6 +120,14 @@ impl From<TexturePixelKind> for PixelKind {
             TexturePixelKind::DXT5RGBA => Self::DXT5RGBA,
             TexturePixelKind::R8RGTC => Self::R8RGTC,
             TexturePixelKind::RG8RGTC => Self::RG8RGTC,
+            TexturePixelKind::R16F => Self::F16,
+            TexturePixelKind::RG16F => Self::RG16F,
+            TexturePixelKind::RGB16F => Self::RGB16F,
+            TexturePixelKind::RGBA16F => Self::RGBA16F,
+            TexturePixelKind::R32F => Self::F32,
+            TexturePixelKind::RG32F => Self::RG32F,
+            TexturePixelKind::RGB32F => Self::RGB32F,
+            TexturePixelKind::RGBA32F => Self::RGBA32F,
         }
     }
 }
@@ -122,7 +135,7 @@ impl From<TexturePixelKind> for PixelKind {
 impl PixelKind {
     fn unpack_alignment(self) -> i32 {
         match self {
-            Self::RGBA16 | Self::RGB16 | Self::RGBA32F => 8,
+            Self::RGBA16 | Self::RGB16 | Self::RGBA32F | Self::RG32F | Self::RGBA16F => 8,
             Self::RGBA8
             | Self::RGB8
             | Self::BGRA8
@@ -131,8 +144,10 @@ impl PixelKind {
             | Self::R16
             | Self::D24S8
             | Self::D32
-            | Self::F32 => 4,
-            Self::RG8 | Self::D16 | Self::F16 => 2,
+            | Self::F32
+            | Self::RGB32F
+            | Self::RG16F => 4,
+            Self::RG8 | Self::D16 | Self::F16 | Self::RGB16F => 2,
             Self::R8 => 1,
             Self::DXT1RGB
             | Self::DXT1RGBA
@@ -168,7 +183,12 @@ impl PixelKind {
             | Self::D16
             | Self::F16
             | Self::R8
-            | Self::RGBA32F => false,
+            | Self::RGBA32F
+            | Self::RGB32F
+            | Self::RG32F
+            | Self::RGBA16F
+            | Self::RGB16F
+            | Self::RG16F => false,
         }
     }
 }
@@ -188,6 +208,12 @@ pub struct GpuTexture {
     thread_mark: PhantomData<*const u8>,
 }
 
+// Size of a side of given mip level, it is never less than 1 pixel. `None` if the level is
+// too deep to exist.
+fn mip_size(size: usize, mip: usize) -> Option<usize> {
+    size.checked_shr(mip as u32).map(|size| size.max(1))
+}
+
 fn ceil_div_4(x: usize) -> usize {
     (x + 3) / 4
 }
@@ -196,14 +222,16 @@ fn image_3d_size_bytes(pixel_kind: PixelKind, width: usize, height: usize, depth
     let pixel_count = width * height * depth;
     match pixel_kind {
         PixelKind::RGBA32F => 16 * pixel_count,
-        PixelKind::RGBA16 => 8 * pixel_count,
-        PixelKind::RGB16 => 6 * pixel_count,
+        PixelKind::RGB32F => 12 * pixel_count,
+        PixelKind::RGBA16 | PixelKind::RGBA16F | PixelKind::RG32F => 8 * pixel_count,
+        PixelKind::RGB16 | PixelKind::RGB16F => 6 * pixel_count,
         PixelKind::RGBA8
         | PixelKind::BGRA8
         | PixelKind::RG16
         | PixelKind::D24S8
         | PixelKind::D32
-        | PixelKind::F32 => 4 * pixel_count,
+        | PixelKind::F32
+        | PixelKind::RG16F => 4 * pixel_count,
         PixelKind::RGB8 | PixelKind::BGR8 => 3 * pixel_count,
         PixelKind::RG8 | PixelKind::R16 | PixelKind::D16 | PixelKind::F16 => 2 * pixel_count,
         PixelKind::R8 => pixel_count,
@@ -222,14 +250,16 @@ fn image_2d_size_bytes(pixel_kind: PixelKind, width: usize, height: usize) -> us
     let pixel_count = width * height;
     match pixel_kind {
         PixelKind::RGBA32F => 16 * pixel_count,
-        PixelKind::RGBA16 => 8 * pixel_count,
-        PixelKind::RGB16 => 6 * pixel_count,
+        PixelKind::RGB32F => 12 * pixel_count,
+        PixelKind::RGBA16 | PixelKind::RGBA16F | PixelKind::RG32F => 8 * pixel_count,
+        PixelKind::RGB16 | PixelKind::RGB16F => 6 * pixel_count,
         PixelKind::RGBA8
         | PixelKind::BGRA8
         | PixelKind::RG16
         | PixelKind::D24S8
         | PixelKind::D32
-        | PixelKind::F32 => 4 * pixel_count,
+        | PixelKind::F32
+        | PixelKind::RG16F => 4 * pixel_count,
         PixelKind::RGB8 | PixelKind::BGR8 => 3 * pixel_count,
         PixelKind::RG8 | PixelKind::R16 | PixelKind::D16 | PixelKind::F16 => 2 * pixel_count,
         PixelKind::R8 => pixel_count,
@@ -247,14 +277,16 @@ fn image_2d_size_bytes(pixel_kind: PixelKind, width: usize, height: usize) -> us
 fn image_1d_size_bytes(pixel_kind: PixelKind, length: usize) -> usize {
     match pixel_kind {
         PixelKind::RGBA32F => 16 * length,
-        PixelKind::RGBA16 => 8 * length,
-        PixelKind::RGB16 => 6 * length,
+        PixelKind::RGB32F => 12 * length,
+        PixelKind::RGBA16 | PixelKind::RGBA16F | PixelKind::RG32F => 8 * length,
+        PixelKind::RGB16 | PixelKind::RGB16F => 6 * length,
         PixelKind::RGBA8
         | PixelKind::BGRA8
         | PixelKind::RG16
         | PixelKind::D24S8
         | PixelKind::D32
-        | PixelKind::F32 => 4 * length,
+        | PixelKind::F32
+        | PixelKind::RG16F => 4 * length,
         PixelKind::RGB8 | PixelKind::BGR8 => 3 * length,
         PixelKind::RG8 | PixelKind::R16 | PixelKind::D16 | PixelKind::F16 => 2 * length,
         PixelKind::R8 => length,
@@ -502,27 +534,25 @@ impl<'a> TextureBinding<'a> {
         'mip_loop: for mip in 0..mip_count {
             match kind {
                 GpuTextureKind::Line { length } => {
-                    if let Some(length) = length.checked_shr(mip as u32) {
+                    if let Some(length) = mip_size(length, mip) {
                         desired_byte_count += image_1d_size_bytes(pixel_kind, length);
                     } else {
                         break 'mip_loop;
                     }
                 }
                 GpuTextureKind::Rectangle { width, height } => {
-                    if let (Some(width), Some(height)) = (
-                        width.checked_shr(mip as u32),
-                        height.checked_shr(mip as u32),
-                    ) {
+                    if let (Some(width), Some(height)) =
+                        (mip_size(width, mip), mip_size(height, mip))
+                    {
                         desired_byte_count += image_2d_size_bytes(pixel_kind, width, height);
                     } else {
                         break 'mip_loop;
                     }
                 }
                 GpuTextureKind::Cube { width, height } => {
-                    if let (Some(width), Some(height)) = (
-                        width.checked_shr(mip as u32),
-                        height.checked_shr(mip as u32),
-                    ) {
+                    if let (Some(width), Some(height)) =
+                        (mip_size(width, mip), mip_size(height, mip))
+                    {
                         desired_byte_count += 6 * image_2d_size_bytes(pixel_kind, width, height);
                     } else {
                         break 'mip_loop;
@@ -534,9 +564,9 @@ impl<'a> TextureBinding<'a> {
                     depth,
                 } => {
                     if let (Some(width), Some(height), Some(depth)) = (
-                        width.checked_shr(mip as u32),
-                        height.checked_shr(mip as u32),
-                        depth.checked_shr(mip as u32),
+                        mip_size(width, mip),
+                        mip_size(height, mip),
+                        mip_size(depth, mip),
                     ) {
                         desired_byte_count += image_3d_size_bytes(pixel_kind, width, height, depth);
                     } else {
@@ -566,7 +596,7 @@ impl<'a> TextureBinding<'a> {
 
             let (type_, format, internal_format) = match pixel_kind {
                 PixelKind::F32 => (glow::FLOAT, glow::RED, glow::R32F),
-                PixelKind::F16 => (glow::FLOAT, glow::RED, glow::R16F),
+                PixelKind::F16 => (glow::HALF_FLOAT, glow::RED, glow::R16F),
                 PixelKind::D32 => (glow::FLOAT, glow::DEPTH_COMPONENT, glow::DEPTH_COMPONENT32F),
                 PixelKind::D16 => (
                     glow::UNSIGNED_SHORT,
@@ -595,6 +625,11 @@ impl<'a> TextureBinding<'a> {
                 PixelKind::R8RGTC => (0, 0, COMPRESSED_RED_RGTC1),
                 PixelKind::RG8RGTC => (0, 0, COMPRESSED_RG_RGTC2),
                 PixelKind::RGBA32F => (glow::FLOAT, glow::RGBA, glow::RGBA32F),
+                PixelKind::RGB32F => (glow::FLOAT, glow::RGB, glow::RGB32F),
+                PixelKind::RG32F => (glow::FLOAT, glow::RG, glow::RG32F),
+                PixelKind::RGBA16F => (glow::HALF_FLOAT, glow::RGBA, glow::RGBA16F),
+                PixelKind::RGB16F => (glow::HALF_FLOAT, glow::RGB, glow::RGB16F),
+                PixelKind::RG16F => (glow::HALF_FLOAT, glow::RG, glow::RG16F),
             };
 
             let is_compressed = pixel_kind.is_compressed();
@@ -611,7 +646,7 @@ impl<'a> TextureBinding<'a> {
             'mip_loop2: for mip in 0..mip_count {
                 match kind {
                     GpuTextureKind::Line { length } => {
-                        if let Some(length) = length.checked_shr(mip as u32) {
+                        if let Some(length) = mip_size(length, mip) {
                             let pixels = data.map(|data| &data[mip_byte_offset..]);
                             let size = image_1d_size_bytes(pixel_kind, length) as i32;
 
@@ -640,15 +675,14 @@ impl<'a> TextureBinding<'a> {
 
                             mip_byte_offset += size as usize;
                         } else {
-                            // No need to add degenerated mips (0x1, 0x2, 4x0, etc).
+                            // Mip level is too deep to exist.
                             break 'mip_loop2;
                         }
                     }
                     GpuTextureKind::Rectangle { width, height } => {
-                        if let (Some(width), Some(height)) = (
-                            width.checked_shr(mip as u32),
-                            height.checked_shr(mip as u32),
-                        ) {
+                        if let (Some(width), Some(height)) =
+                            (mip_size(width, mip), mip_size(height, mip))
+                        {
                             let pixels = data.map(|data| &data[mip_byte_offset..]);
                             let size = image_2d_size_bytes(pixel_kind, width, height) as i32;
 
@@ -679,15 +713,14 @@ impl<'a> TextureBinding<'a> {
 
                             mip_byte_offset += size as usize;
                         } else {
-                            // No need to add degenerated mips (0x1, 0x2, 4x0, etc).
+                            // Mip level is too deep to exist.
                             break 'mip_loop2;
                         }
                     }
                     GpuTextureKind::Cube { width, height } => {
-                        if let (Some(width), Some(height)) = (
-                            width.checked_shr(mip as u32),
-                            height.checked_shr(mip as u32),
-                        ) {
+                        if let (Some(width), Some(height)) =
+                            (mip_size(width, mip), mip_size(height, mip))
+                        {
                             let bytes_per_face = image_2d_size_bytes(pixel_kind, width, height);
 
                             for face in 0..6 {
@@ -723,7 +756,7 @@ impl<'a> TextureBinding<'a> {
 
                             mip_byte_offset += 6 * bytes_per_face as usize;
                         } else {
-                            // No need to add degenerated mips (0x1, 0x2, 4x0, etc).
+                            // Mip level is too deep to exist.
                             break 'mip_loop2;
                         }
                     }
@@ -733,9 +766,9 @@ impl<'a> TextureBinding<'a> {
                         depth,
                     } => {
                         if let (Some(width), Some(height), Some(depth)) = (
-                            width.checked_shr(mip as u32),
-                            height.checked_shr(mip as u32),
-                            depth.checked_shr(mip as u32),
+                            mip_size(width, mip),
+                            mip_size(height, mip),
+                            mip_size(depth, mip),
                         ) {
                             let pixels = data.map(|data| &data[mip_byte_offset..]);
                             let size = image_3d_size_bytes(pixel_kind, width, height, depth) as i32;
@@ -769,12 +802,20 @@ impl<'a> TextureBinding<'a> {
 
                             mip_byte_offset += size as usize;
                         } else {
-                            // No need to add degenerated mips (0x1, 0x2, 4x0, etc).
+                            // Mip level is too deep to exist.
                             break 'mip_loop2;
                         }
                     }
                 }
             }
+
+            // Texture with partial mip chain (for example when its smallest side reached 1 pixel
+            // before the largest one) won't be complete unless its max level is set explicitly.
+            // 1000 is the default value in OpenGL.
+            let max_level = if mip_count > 1 { mip_count - 1 } else { 1000 };
+            self.state
+                .gl
+                .tex_parameter_i32(target, glow::TEXTURE_MAX_LEVEL, max_level as i32);
         }
 
         Ok(self)
@@ -286,4 +286,40 @@ vec2 S_ComputeParallaxTextureCoordinates(in sampler2D heightTexture, vec3 eyeVec
     float weight = nextH / (nextH - prevH);
 
     return prev * weight + currentTexCoords * (1.0 - weight);
-}
\ No newline at end of file
+}
+// Maximum amount of morph targets that can be applied in a vertex shader, must be in sync with
+// MAX_GPU_MORPH_TARGETS.
+const int S_MaxMorphTargets = 16;
+
+vec3 Internal_FetchMorphDelta(in sampler2D deltas, int texel) {
+    int width = textureSize(deltas, 0).x;
+    return texelFetch(deltas, ivec2(texel % width, texel / width), 0).xyz;
+}
+
+// Applies morph targets to a vertex. Offsets are packed by MorphDeltaStorage, each target of each
+// vertex takes three texels: offsets of position, normal and tangent.
+void S_ApplyMorphTargets(
+    in sampler2D deltas,
+    in float weights[S_MaxMorphTargets],
+    int targetCount,
+    int vertexIndex,
+    inout vec3 position,
+    inout vec3 normal,
+    inout vec3 tangent)
+{
+    for (int i = 0; i < targetCount; ++i) {
+        float weight = weights[i];
+        if (weight != 0.0) {
+            int texel = (vertexIndex * targetCount + i) * 3;
+            position += weight * Internal_FetchMorphDelta(deltas, texel);
+            normal += weight * Internal_FetchMorphDelta(deltas, texel + 1);
+            tangent += weight * Internal_FetchMorphDelta(deltas, texel + 2);
+        }
+    }
+}
+
+// Returns relative luminance of a linear color.
+float S_Luminance(vec3 color)
+{
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
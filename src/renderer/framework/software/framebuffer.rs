@@ -0,0 +1,565 @@
+use crate::{
+    core::{
+        algebra::{Vector2, Vector3, Vector4},
+        color::Color,
+        math::Rect,
+    },
+    renderer::framework::{
+        backend::{BackendFrameBuffer, BackendProgramBinding},
+        framebuffer::{CullFace, DrawParameters},
+        geometry_buffer::DrawCallStatistics,
+        software::{
+            geometry_buffer::SoftwareGeometryBuffer,
+            program::{
+                Interpolate, ShaderInvocation, SoftwareProgram, SoftwareProgramBinding,
+                SoftwareShader,
+            },
+            state::SoftwareState,
+            texture::SoftwareTexture,
+            SoftwareBackend,
+        },
+    },
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// CPU counterpart of frame buffer. All attachments have the same size. Row `y` of attachments
+/// is `y` pixels above the bottom of the image, as in OpenGL.
+pub struct SoftwareFrameBuffer {
+    width: usize,
+    height: usize,
+    color_attachments: Vec<Rc<RefCell<SoftwareTexture>>>,
+    depth: Option<Vec<f32>>,
+}
+
+#[derive(Copy, Clone)]
+struct ClipVertex<V> {
+    position: Vector4<f32>,
+    varying: V,
+}
+
+struct ScreenVertex<V> {
+    position: Vector2<f32>,
+    depth: f32,
+    inv_w: f32,
+    varying: V,
+}
+
+fn edge(a: Vector2<f32>, b: Vector2<f32>, p: Vector2<f32>) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Clips polygon against near plane (z >= -w) using Sutherland-Hodgman algorithm.
+fn clip_near<V: Interpolate>(polygon: &[ClipVertex<V>]) -> Vec<ClipVertex<V>> {
+    let distance = |v: &ClipVertex<V>| v.position.z + v.position.w;
+
+    let mut result = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let (dc, dn) = (distance(current), distance(next));
+        if dc >= 0.0 {
+            result.push(*current);
+        }
+        if (dc >= 0.0) != (dn >= 0.0) {
+            let t = dc / (dc - dn);
+            result.push(ClipVertex {
+                position: current.position.lerp(&next.position, t),
+                varying: V::lerp(&current.varying, &next.varying, t),
+            });
+        }
+    }
+    result
+}
+
+/// Top-left fill rule: pixels that lie exactly on an edge belong to a triangle only if the edge
+/// is its top or left edge, so pixels on edges shared by two triangles are drawn once.
+fn is_top_left(start: Vector2<f32>, end: Vector2<f32>, area: f32) -> bool {
+    let d = (end - start) * area.signum();
+    d.y < 0.0 || (d.y == 0.0 && d.x < 0.0)
+}
+
+fn blend_factor(factor: u32, source: &Vector4<f32>, destination: &Vector4<f32>) -> Vector4<f32> {
+    let one = Vector4::repeat(1.0);
+    match factor {
+        glow::ZERO => Vector4::default(),
+        glow::ONE => one,
+        glow::SRC_COLOR => *source,
+        glow::ONE_MINUS_SRC_COLOR => one - source,
+        glow::DST_COLOR => *destination,
+        glow::ONE_MINUS_DST_COLOR => one - destination,
+        glow::SRC_ALPHA => Vector4::repeat(source.w),
+        glow::ONE_MINUS_SRC_ALPHA => Vector4::repeat(1.0 - source.w),
+        glow::DST_ALPHA => Vector4::repeat(destination.w),
+        glow::ONE_MINUS_DST_ALPHA => Vector4::repeat(1.0 - destination.w),
+        _ => panic!("Unsupported blend factor {}", factor),
+    }
+}
+
+impl SoftwareFrameBuffer {
+    /// Creates new frame buffer with given amount of color attachments and optional depth
+    /// buffer.
+    pub fn new(width: usize, height: usize, color_attachments: usize, depth: bool) -> Self {
+        Self {
+            width,
+            height,
+            color_attachments: (0..color_attachments)
+                .map(|_| Rc::new(RefCell::new(SoftwareTexture::new(width, height))))
+                .collect(),
+            depth: if depth {
+                Some(vec![1.0; width * height])
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn color_attachments(&self) -> &[Rc<RefCell<SoftwareTexture>>] {
+        &self.color_attachments
+    }
+
+    /// Returns depth at given pixel in \[0; 1\] range, if frame buffer has depth buffer.
+    pub fn depth(&self, x: usize, y: usize) -> Option<f32> {
+        self.depth.as_ref().map(|depth| depth[y * self.width + x])
+    }
+
+    pub fn clear(&mut self, color: Option<Color>, depth: Option<f32>) {
+        if let Some(color) = color {
+            for attachment in self.color_attachments.iter() {
+                attachment.borrow_mut().fill(color);
+            }
+        }
+        if let (Some(value), Some(depth)) = (depth, self.depth.as_mut()) {
+            for d in depth.iter_mut() {
+                *d = value;
+            }
+        }
+    }
+
+    /// Rasterizes every triangle of the geometry using given program. Depth test passes if
+    /// fragment depth is less or equal to stored depth. Fragments are blended using blend
+    /// factors of the state, if blending is enabled.
+    pub fn draw<P: SoftwareProgram>(
+        &mut self,
+        geometry: &SoftwareGeometryBuffer,
+        state: &SoftwareState,
+        viewport: Rect<i32>,
+        program: &P,
+        params: &DrawParameters,
+    ) -> DrawCallStatistics {
+        let vertices = geometry
+            .vertices()
+            .iter()
+            .map(|vertex| {
+                let (position, varying) = program.vertex(vertex);
+                ClipVertex { position, varying }
+            })
+            .collect::<Vec<_>>();
+
+        for triangle in geometry.triangles() {
+            let polygon = clip_near(&[
+                vertices[triangle.0[0] as usize],
+                vertices[triangle.0[1] as usize],
+                vertices[triangle.0[2] as usize],
+            ]);
+
+            for i in 1..polygon.len().saturating_sub(1) {
+                self.draw_triangle(
+                    state,
+                    viewport,
+                    program,
+                    params,
+                    [&polygon[0], &polygon[i], &polygon[i + 1]],
+                );
+            }
+        }
+
+        DrawCallStatistics {
+            triangles: geometry.triangles().len(),
+        }
+    }
+
+    fn draw_triangle<P: SoftwareProgram>(
+        &mut self,
+        state: &SoftwareState,
+        viewport: Rect<i32>,
+        program: &P,
+        params: &DrawParameters,
+        triangle: [&ClipVertex<P::Varying>; 3],
+    ) {
+        let (origin_x, origin_y) = (viewport.x() as f32, viewport.y() as f32);
+        let (width, height) = (viewport.w() as f32, viewport.h() as f32);
+
+        let mut ndc = [Vector2::default(); 3];
+        let screen = triangle
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let inv_w = 1.0 / v.position.w;
+                let p = v.position.xyz() * inv_w;
+                ndc[i] = p.xy();
+                ScreenVertex {
+                    position: Vector2::new(
+                        origin_x + (p.x * 0.5 + 0.5) * width,
+                        origin_y + (p.y * 0.5 + 0.5) * height,
+                    ),
+                    depth: p.z * 0.5 + 0.5,
+                    inv_w,
+                    varying: v.varying,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Counter-clockwise triangles in normalized device coordinates are front-facing.
+        let ndc_area = edge(ndc[0], ndc[1], ndc[2]);
+        if params.culling {
+            let culled = match params.cull_face {
+                CullFace::Back => ndc_area <= 0.0,
+                CullFace::Front => ndc_area >= 0.0,
+            };
+            if culled {
+                return;
+            }
+        }
+
+        let (a, b, c) = (&screen[0], &screen[1], &screen[2]);
+        let area = edge(a.position, b.position, c.position);
+        if area == 0.0 {
+            return;
+        }
+
+        let min = a.position.inf(&b.position).inf(&c.position);
+        let max = a.position.sup(&b.position).sup(&c.position);
+        let min = min.sup(&Vector2::new(origin_x.max(0.0), origin_y.max(0.0)));
+        let max = max
+            .inf(&Vector2::new(origin_x + width, origin_y + height))
+            .inf(&Vector2::new(self.width as f32, self.height as f32));
+        let x_range = (min.x.floor() as usize)..(max.x.ceil().max(0.0) as usize);
+        let y_range = (min.y.floor() as usize)..(max.y.ceil().max(0.0) as usize);
+        let (sfactor, dfactor) = state.blend_func();
+        let top_left = [
+            is_top_left(b.position, c.position, area),
+            is_top_left(c.position, a.position, area),
+            is_top_left(a.position, b.position, area),
+        ];
+
+        let mut outputs = vec![Vector4::default(); self.color_attachments.len()];
+        for y in y_range {
+            for x in x_range.clone() {
+                let p = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = Vector3::new(
+                    edge(b.position, c.position, p),
+                    edge(c.position, a.position, p),
+                    edge(a.position, b.position, p),
+                ) / area;
+                if !weights
+                    .iter()
+                    .zip(top_left.iter())
+                    .all(|(w, top_left)| *w > 0.0 || (*w == 0.0 && *top_left))
+                {
+                    continue;
+                }
+
+                let depth = weights.x * a.depth + weights.y * b.depth + weights.z * c.depth;
+                let index = y * self.width + x;
+                if params.depth_test {
+                    if let Some(stored) = self.depth.as_ref().map(|d| d[index]) {
+                        if depth > stored {
+                            continue;
+                        }
+                    }
+                }
+
+                let perspective = Vector3::new(
+                    weights.x * a.inv_w,
+                    weights.y * b.inv_w,
+                    weights.z * c.inv_w,
+                );
+                let perspective = perspective / (perspective.x + perspective.y + perspective.z);
+                let varying =
+                    P::Varying::interpolate(&a.varying, &b.varying, &c.varying, perspective);
+
+                for output in outputs.iter_mut() {
+                    *output = Vector4::default();
+                }
+                if !program.fragment(&varying, &mut outputs) {
+                    continue;
+                }
+
+                let mask = params.color_write;
+                let mask = [mask.red, mask.green, mask.blue, mask.alpha];
+                for (attachment, output) in self.color_attachments.iter().zip(outputs.iter()) {
+                    let mut attachment = attachment.borrow_mut();
+                    let mut pixel = attachment.pixel(x, y);
+                    let value = if params.blend {
+                        output.component_mul(&blend_factor(sfactor, output, &pixel))
+                            + pixel.component_mul(&blend_factor(dfactor, output, &pixel))
+                    } else {
+                        *output
+                    };
+                    for (i, write) in mask.iter().enumerate() {
+                        if *write {
+                            pixel[i] = value[i];
+                        }
+                    }
+                    attachment.set_pixel(x, y, pixel);
+                }
+
+                if params.depth_write {
+                    if let Some(stored) = self.depth.as_mut() {
+                        stored[index] = depth;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl BackendFrameBuffer<SoftwareBackend> for SoftwareFrameBuffer {
+    fn clear(
+        &mut self,
+        _state: &mut SoftwareState,
+        _viewport: Rect<i32>,
+        color: Option<Color>,
+        depth: Option<f32>,
+        _stencil: Option<i32>,
+    ) {
+        SoftwareFrameBuffer::clear(self, color, depth)
+    }
+
+    fn draw(
+        &mut self,
+        geometry: &SoftwareGeometryBuffer,
+        state: &mut SoftwareState,
+        viewport: Rect<i32>,
+        program: &Rc<dyn SoftwareShader>,
+        params: &DrawParameters,
+        apply_uniforms: &mut dyn FnMut(&mut dyn BackendProgramBinding<SoftwareBackend>),
+    ) -> DrawCallStatistics {
+        let mut uniforms = SoftwareProgramBinding::new(&**program);
+        apply_uniforms(&mut uniforms);
+        SoftwareFrameBuffer::draw(
+            self,
+            geometry,
+            state,
+            viewport,
+            &ShaderInvocation {
+                shader: &**program,
+                uniforms: &uniforms,
+            },
+            params,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Vector2, Vector3, Vector4},
+            color::Color,
+            math::{Rect, TriangleDefinition},
+        },
+        renderer::framework::{
+            framebuffer::DrawParameters,
+            software::{
+                framebuffer::SoftwareFrameBuffer,
+                geometry_buffer::{SoftwareGeometryBuffer, SoftwareVertex},
+                program::SoftwareProgram,
+                state::SoftwareState,
+            },
+        },
+    };
+
+    struct FlatColor(Vector4<f32>);
+
+    impl SoftwareProgram for FlatColor {
+        type Varying = ();
+
+        fn vertex(&self, vertex: &SoftwareVertex) -> (Vector4<f32>, Self::Varying) {
+            (vertex.position.push(1.0), ())
+        }
+
+        fn fragment(&self, _: &Self::Varying, outputs: &mut [Vector4<f32>]) -> bool {
+            outputs[0] = self.0;
+            true
+        }
+    }
+
+    fn quad(depth: f32, size: f32) -> SoftwareGeometryBuffer {
+        let vertex = |x, y| SoftwareVertex {
+            position: Vector3::new(x, y, depth),
+            tex_coord: Vector2::default(),
+            normal: Vector3::default(),
+        };
+        SoftwareGeometryBuffer::new(
+            vec![
+                vertex(-size, -size),
+                vertex(size, -size),
+                vertex(size, size),
+                vertex(-size, size),
+            ],
+            vec![TriangleDefinition([0, 1, 2]), TriangleDefinition([0, 2, 3])],
+        )
+    }
+
+    #[test]
+    fn rasterizes_with_depth_test() {
+        let red = Vector4::new(1.0, 0.0, 0.0, 1.0);
+        let green = Vector4::new(0.0, 1.0, 0.0, 1.0);
+
+        let state = SoftwareState::new();
+        let viewport = Rect::new(0, 0, 16, 16);
+        let mut frame_buffer = SoftwareFrameBuffer::new(16, 16, 1, true);
+        frame_buffer.clear(Some(Color::BLACK), Some(1.0));
+
+        let params = DrawParameters::default();
+        // Small quad in front, then bigger quad behind it.
+        frame_buffer.draw(&quad(0.0, 0.5), &state, viewport, &FlatColor(red), &params);
+        let stats = frame_buffer.draw(
+            &quad(0.5, 1.0),
+            &state,
+            viewport,
+            &FlatColor(green),
+            &params,
+        );
+        assert_eq!(stats.triangles, 2);
+
+        let color = frame_buffer.color_attachments()[0].borrow();
+        assert_eq!(color.pixel(8, 8), red);
+        assert_eq!(color.pixel(0, 0), green);
+        assert_eq!(frame_buffer.depth(8, 8), Some(0.5));
+    }
+
+    #[test]
+    fn culls_back_faces() {
+        let state = SoftwareState::new();
+        let mut frame_buffer = SoftwareFrameBuffer::new(8, 8, 1, false);
+        frame_buffer.clear(Some(Color::BLACK), None);
+
+        let mut geometry = quad(0.0, 1.0);
+        let flipped = geometry
+            .triangles()
+            .iter()
+            .map(|t| TriangleDefinition([t.0[0], t.0[2], t.0[1]]))
+            .collect();
+        geometry = SoftwareGeometryBuffer::new(geometry.vertices().to_vec(), flipped);
+
+        let white = FlatColor(Vector4::new(1.0, 1.0, 1.0, 1.0));
+        frame_buffer.draw(
+            &geometry,
+            &state,
+            Rect::new(0, 0, 8, 8),
+            &white,
+            &DrawParameters::default(),
+        );
+        assert_eq!(
+            frame_buffer.color_attachments()[0].borrow().pixel(4, 4),
+            Color::BLACK.as_frgba()
+        );
+    }
+
+    #[test]
+    fn draws_only_inside_viewport() {
+        let state = SoftwareState::new();
+        let mut frame_buffer = SoftwareFrameBuffer::new(8, 8, 1, false);
+        frame_buffer.clear(Some(Color::BLACK), None);
+
+        let white = Vector4::new(1.0, 1.0, 1.0, 1.0);
+        // Bottom left quarter, rows go from bottom to top.
+        frame_buffer.draw(
+            &quad(0.0, 1.0),
+            &state,
+            Rect::new(0, 0, 4, 4),
+            &FlatColor(white),
+            &DrawParameters::default(),
+        );
+
+        let color = frame_buffer.color_attachments()[0].borrow();
+        assert_eq!(color.pixel(0, 0), white);
+        assert_eq!(color.pixel(3, 3), white);
+        assert_eq!(color.pixel(4, 3), Color::BLACK.as_frgba());
+        assert_eq!(color.pixel(3, 4), Color::BLACK.as_frgba());
+    }
+
+    #[test]
+    fn blends_with_blend_func() {
+        let destination = Color::from_rgba(0, 0, 255, 255);
+        let source = Vector4::new(1.0, 0.0, 0.0, 0.25);
+        let params = DrawParameters {
+            blend: true,
+            ..Default::default()
+        };
+
+        let cases = [
+            // Additive.
+            (glow::ONE, glow::ONE, Vector4::new(1.0, 0.0, 1.0, 1.25)),
+            // Alpha blending.
+            (
+                glow::SRC_ALPHA,
+                glow::ONE_MINUS_SRC_ALPHA,
+                Vector4::new(0.25, 0.0, 0.75, 0.8125),
+            ),
+            // Multiplicative.
+            (
+                glow::DST_COLOR,
+                glow::ZERO,
+                Vector4::new(0.0, 0.0, 0.0, 0.25),
+            ),
+            // Same as no blending.
+            (glow::ONE, glow::ZERO, source),
+        ];
+
+        for (sfactor, dfactor, expected) in cases.iter() {
+            let mut state = SoftwareState::new();
+            state.set_blend_func(*sfactor, *dfactor);
+
+            let mut frame_buffer = SoftwareFrameBuffer::new(4, 4, 1, false);
+            frame_buffer.clear(Some(destination), None);
+            frame_buffer.draw(
+                &quad(0.0, 1.0),
+                &state,
+                Rect::new(0, 0, 4, 4),
+                &FlatColor(source),
+                &params,
+            );
+
+            let pixel = frame_buffer.color_attachments()[0].borrow().pixel(2, 2);
+            assert!(
+                (pixel - expected).norm() < 1.0e-6,
+                "{} {}: {:?}",
+                sfactor,
+                dfactor,
+                pixel
+            );
+        }
+    }
+
+    #[test]
+    fn blending_is_disabled_by_draw_parameters() {
+        let mut state = SoftwareState::new();
+        state.set_blend_func(glow::ONE, glow::ONE);
+
+        let mut frame_buffer = SoftwareFrameBuffer::new(4, 4, 1, false);
+        frame_buffer.clear(Some(Color::WHITE), None);
+        let source = Vector4::new(0.5, 0.5, 0.5, 0.5);
+        frame_buffer.draw(
+            &quad(0.0, 1.0),
+            &state,
+            Rect::new(0, 0, 4, 4),
+            &FlatColor(source),
+            &DrawParameters::default(),
+        );
+
+        assert_eq!(
+            frame_buffer.color_attachments()[0].borrow().pixel(1, 1),
+            source
+        );
+    }
+}
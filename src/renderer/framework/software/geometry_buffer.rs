@@ -0,0 +1,67 @@
+use crate::{
+    core::{
+        algebra::{Vector2, Vector3},
+        math::TriangleDefinition,
+    },
+    scene::mesh::{
+        buffer::{VertexAttributeUsage, VertexReadTrait},
+        surface::SurfaceData,
+    },
+};
+
+/// Vertex of software geometry buffer, it has fixed layout unlike GPU vertices.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SoftwareVertex {
+    pub position: Vector3<f32>,
+    pub tex_coord: Vector2<f32>,
+    pub normal: Vector3<f32>,
+}
+
+/// Indexed triangle list.
+#[derive(Clone, Default, Debug)]
+pub struct SoftwareGeometryBuffer {
+    vertices: Vec<SoftwareVertex>,
+    triangles: Vec<TriangleDefinition>,
+}
+
+impl SoftwareGeometryBuffer {
+    pub fn new(vertices: Vec<SoftwareVertex>, triangles: Vec<TriangleDefinition>) -> Self {
+        Self {
+            vertices,
+            triangles,
+        }
+    }
+
+    /// Reads positions, first texture coordinates and normals of a surface, missing attributes
+    /// are replaced with zeros.
+    pub fn from_surface_data(data: &SurfaceData) -> Self {
+        let vertices = data
+            .vertex_buffer
+            .iter()
+            .map(|vertex| SoftwareVertex {
+                position: vertex
+                    .read_3_f32(VertexAttributeUsage::Position)
+                    .unwrap_or_default(),
+                tex_coord: vertex
+                    .read_2_f32(VertexAttributeUsage::TexCoord0)
+                    .unwrap_or_default(),
+                normal: vertex
+                    .read_3_f32(VertexAttributeUsage::Normal)
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        Self {
+            vertices,
+            triangles: data.geometry_buffer.triangles_ref().to_vec(),
+        }
+    }
+
+    pub fn vertices(&self) -> &[SoftwareVertex] {
+        &self.vertices
+    }
+
+    pub fn triangles(&self) -> &[TriangleDefinition] {
+        &self.triangles
+    }
+}
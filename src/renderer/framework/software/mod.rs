@@ -0,0 +1,124 @@
+//! Reference CPU implementation of the framework. It mirrors GPU framework entities - textures,
+//! frame buffers, geometry buffers and programs - but does everything on CPU, so it does not
+//! need graphics context at all. It is slow and supports only a small subset of features, it is
+//! intended to be used in tests on machines without GPU.
+//!
+//! Programs are written in Rust instead of GLSL, see [`program::SoftwareProgram`] and
+//! [`program::SoftwareShader`]. Frame buffers use the same
+//! [`DrawParameters`](super::framebuffer::DrawParameters) as GPU frame buffers, blend factors
+//! are taken from [`state::SoftwareState`]. Attachments of frame buffers follow OpenGL
+//! conventions: first row of an attachment is the bottom row of the image.
+//!
+//! [`SoftwareBackend`] implements [`Backend`] trait, so code that is generic over backend runs
+//! on CPU as well, as long as its programs have software implementation. Currently it is only
+//! the flat shader pass, scene passes of the renderer are not generic over backend yet.
+
+pub mod framebuffer;
+pub mod geometry_buffer;
+pub mod program;
+pub mod state;
+pub mod texture;
+
+use crate::renderer::framework::{
+    backend::Backend,
+    error::FrameworkError,
+    software::{
+        framebuffer::SoftwareFrameBuffer,
+        geometry_buffer::SoftwareGeometryBuffer,
+        program::{SoftwareShader, SoftwareUniformLocation},
+        state::SoftwareState,
+        texture::SoftwareTexture,
+    },
+};
+use std::rc::Rc;
+
+/// CPU backend, see module docs.
+pub struct SoftwareBackend;
+
+impl Backend for SoftwareBackend {
+    type State = SoftwareState;
+    type Texture = SoftwareTexture;
+    type GeometryBuffer = SoftwareGeometryBuffer;
+    type Program = Rc<dyn SoftwareShader>;
+    type UniformLocation = SoftwareUniformLocation;
+    type FrameBuffer = SoftwareFrameBuffer;
+
+    fn create_program(
+        state: &mut Self::State,
+        name: &str,
+        _vertex_source: &str,
+        _fragment_source: &str,
+    ) -> Result<Self::Program, FrameworkError> {
+        state
+            .program(name)
+            .ok_or_else(|| FrameworkError::ShaderCompilationFailed {
+                shader_name: name.to_owned(),
+                error_message: "There is no software implementation of the program.".to_owned(),
+            })
+    }
+
+    fn uniform_location(
+        _state: &mut Self::State,
+        program: &Self::Program,
+        name: &str,
+    ) -> Result<Self::UniformLocation, FrameworkError> {
+        program
+            .uniforms()
+            .iter()
+            .position(|uniform| *uniform == name)
+            .map(SoftwareUniformLocation)
+            .ok_or_else(|| FrameworkError::UnableToFindShaderUniform(name.to_owned()))
+    }
+
+    fn set_blend_func(state: &mut Self::State, sfactor: u32, dfactor: u32) {
+        state.set_blend_func(sfactor, dfactor);
+    }
+}
+
+#[cfg(test)]
+pub(in crate) fn assert_matches_golden_image(
+    name: &str,
+    frame: &crate::resource::texture::TextureData,
+) {
+    use crate::resource::texture::{TextureKind, TexturePixelKind};
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src/renderer/test_data")
+        .join(format!("{}.png", name));
+    let (width, height) = match frame.kind() {
+        TextureKind::Rectangle { width, height } => (width, height),
+        _ => panic!("golden image must be a rectangle"),
+    };
+    assert_eq!(frame.pixel_kind(), TexturePixelKind::RGBA8);
+
+    // Set RG3D_UPDATE_GOLDEN_IMAGES to regenerate reference images after intended changes.
+    if std::env::var_os("RG3D_UPDATE_GOLDEN_IMAGES").is_some() {
+        image::save_buffer(&path, frame.data(), width, height, image::ColorType::Rgba8).unwrap();
+    }
+
+    let golden = image::open(&path)
+        .unwrap_or_else(|e| panic!("unable to load golden image {:?}: {}", path, e))
+        .to_rgba8();
+    assert_eq!(golden.dimensions(), (width, height));
+
+    let mismatch = golden
+        .as_raw()
+        .iter()
+        .zip(frame.data())
+        .any(|(a, b)| (*a as i32 - *b as i32).abs() > 2);
+    if mismatch {
+        let actual = std::env::temp_dir().join(format!("{}_{}.png", name, std::process::id()));
+        image::save_buffer(
+            &actual,
+            frame.data(),
+            width,
+            height,
+            image::ColorType::Rgba8,
+        )
+        .unwrap();
+        panic!(
+            "frame does not match golden image {:?}, actual frame is saved to {:?}",
+            path, actual
+        );
+    }
+}
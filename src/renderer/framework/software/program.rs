@@ -0,0 +1,288 @@
+use crate::{
+    core::{
+        algebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4},
+        color::Color,
+    },
+    renderer::framework::{
+        backend::BackendProgramBinding,
+        software::{geometry_buffer::SoftwareVertex, texture::SoftwareTexture, SoftwareBackend},
+    },
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// Values that could be interpolated across a triangle. Implement it for your varyings struct
+/// (or use a tuple) to pass data from vertex stage to fragment stage.
+pub trait Interpolate: Copy {
+    /// Weighted sum of three values, weights are barycentric coordinates.
+    fn interpolate(a: &Self, b: &Self, c: &Self, weights: Vector3<f32>) -> Self;
+
+    /// Linear interpolation between two values, used for clipping.
+    fn lerp(a: &Self, b: &Self, t: f32) -> Self;
+}
+
+macro_rules! impl_interpolate {
+    ($($ty:ty),*) => {
+        $(
+            impl Interpolate for $ty {
+                fn interpolate(a: &Self, b: &Self, c: &Self, weights: Vector3<f32>) -> Self {
+                    *a * weights.x + *b * weights.y + *c * weights.z
+                }
+
+                fn lerp(a: &Self, b: &Self, t: f32) -> Self {
+                    *a + (*b - *a) * t
+                }
+            }
+        )*
+    };
+}
+
+impl_interpolate!(f32, Vector2<f32>, Vector3<f32>, Vector4<f32>);
+
+impl Interpolate for () {
+    fn interpolate(_: &Self, _: &Self, _: &Self, _: Vector3<f32>) -> Self {}
+
+    fn lerp(_: &Self, _: &Self, _: f32) -> Self {}
+}
+
+macro_rules! impl_interpolate_tuple {
+    ($($name:ident: $index:tt),*) => {
+        impl<$($name: Interpolate),*> Interpolate for ($($name,)*) {
+            fn interpolate(a: &Self, b: &Self, c: &Self, weights: Vector3<f32>) -> Self {
+                ($($name::interpolate(&a.$index, &b.$index, &c.$index, weights),)*)
+            }
+
+            fn lerp(a: &Self, b: &Self, t: f32) -> Self {
+                ($($name::lerp(&a.$index, &b.$index, t),)*)
+            }
+        }
+    };
+}
+
+impl<T: Interpolate, const N: usize> Interpolate for [T; N] {
+    fn interpolate(a: &Self, b: &Self, c: &Self, weights: Vector3<f32>) -> Self {
+        let mut result = *a;
+        for (i, value) in result.iter_mut().enumerate() {
+            *value = T::interpolate(&a[i], &b[i], &c[i], weights);
+        }
+        result
+    }
+
+    fn lerp(a: &Self, b: &Self, t: f32) -> Self {
+        let mut result = *a;
+        for (i, value) in result.iter_mut().enumerate() {
+            *value = T::lerp(&a[i], &b[i], t);
+        }
+        result
+    }
+}
+
+impl_interpolate_tuple!(A: 0);
+impl_interpolate_tuple!(A: 0, B: 1);
+impl_interpolate_tuple!(A: 0, B: 1, C: 2);
+impl_interpolate_tuple!(A: 0, B: 1, C: 2, D: 3);
+
+/// CPU counterpart of GPU program.
+pub trait SoftwareProgram {
+    /// Data passed from vertex stage to fragment stage, interpolated perspective-correctly.
+    type Varying: Interpolate;
+
+    /// Transforms a vertex into clip space.
+    fn vertex(&self, vertex: &SoftwareVertex) -> (Vector4<f32>, Self::Varying);
+
+    /// Calculates values of color attachments for a fragment. `outputs` has one element per
+    /// color attachment of a frame buffer. Return `false` to discard the fragment.
+    fn fragment(&self, varying: &Self::Varying, outputs: &mut [Vector4<f32>]) -> bool;
+}
+
+/// Varyings of [`SoftwareShader`], same as `out vec4` variables of GLSL shaders.
+pub type Varyings = [Vector4<f32>; 4];
+
+/// CPU counterpart of GPU program that takes its parameters from uniforms, it is used by
+/// [`SoftwareBackend`] to run the same rendering code as GPU backend does. Shaders are found by
+/// the name of GLSL program, see [`SoftwareState::register_program`](super::state::SoftwareState::register_program).
+pub trait SoftwareShader {
+    /// Names of uniforms of the shader, index of a name is its location.
+    fn uniforms(&self) -> &[&'static str];
+
+    /// Transforms a vertex into clip space and writes data for fragment stage into `varyings`.
+    fn vertex(
+        &self,
+        uniforms: &SoftwareProgramBinding,
+        vertex: &SoftwareVertex,
+        varyings: &mut Varyings,
+    ) -> Vector4<f32>;
+
+    /// Same as [`SoftwareProgram::fragment`].
+    fn fragment(
+        &self,
+        uniforms: &SoftwareProgramBinding,
+        varyings: &Varyings,
+        outputs: &mut [Vector4<f32>],
+    ) -> bool;
+}
+
+#[derive(Clone, Debug)]
+pub enum UniformValue {
+    None,
+    Bool(bool),
+    Integer(i32),
+    Float(f32),
+    Vector2(Vector2<f32>),
+    Vector3(Vector3<f32>),
+    Vector4(Vector4<f32>),
+    Matrix3(Matrix3<f32>),
+    Matrix4(Matrix4<f32>),
+    Texture(Rc<RefCell<SoftwareTexture>>),
+}
+
+/// Index of a name in [`SoftwareShader::uniforms`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SoftwareUniformLocation(pub usize);
+
+/// Uniform values of a draw call. Getters return default values (zero, identity matrix, no
+/// texture) for uniforms that were not set, just like uniforms of GLSL programs.
+pub struct SoftwareProgramBinding {
+    values: Vec<UniformValue>,
+}
+
+impl SoftwareProgramBinding {
+    pub(in crate) fn new(shader: &dyn SoftwareShader) -> Self {
+        Self {
+            values: vec![UniformValue::None; shader.uniforms().len()],
+        }
+    }
+
+    fn set(&mut self, location: &SoftwareUniformLocation, value: UniformValue) {
+        self.values[location.0] = value;
+    }
+
+    pub fn value(&self, location: usize) -> &UniformValue {
+        &self.values[location]
+    }
+
+    pub fn bool(&self, location: usize) -> bool {
+        matches!(self.values[location], UniformValue::Bool(true))
+    }
+
+    pub fn integer(&self, location: usize) -> i32 {
+        match self.values[location] {
+            UniformValue::Integer(value) => value,
+            _ => 0,
+        }
+    }
+
+    pub fn float(&self, location: usize) -> f32 {
+        match self.values[location] {
+            UniformValue::Float(value) => value,
+            _ => 0.0,
+        }
+    }
+
+    pub fn vector2(&self, location: usize) -> Vector2<f32> {
+        match self.values[location] {
+            UniformValue::Vector2(value) => value,
+            _ => Default::default(),
+        }
+    }
+
+    pub fn vector3(&self, location: usize) -> Vector3<f32> {
+        match self.values[location] {
+            UniformValue::Vector3(value) => value,
+            _ => Default::default(),
+        }
+    }
+
+    pub fn vector4(&self, location: usize) -> Vector4<f32> {
+        match self.values[location] {
+            UniformValue::Vector4(value) => value,
+            _ => Default::default(),
+        }
+    }
+
+    pub fn matrix3(&self, location: usize) -> Matrix3<f32> {
+        match self.values[location] {
+            UniformValue::Matrix3(value) => value,
+            _ => Matrix3::identity(),
+        }
+    }
+
+    pub fn matrix4(&self, location: usize) -> Matrix4<f32> {
+        match self.values[location] {
+            UniformValue::Matrix4(value) => value,
+            _ => Matrix4::identity(),
+        }
+    }
+
+    pub fn texture(&self, location: usize) -> Option<&Rc<RefCell<SoftwareTexture>>> {
+        match &self.values[location] {
+            UniformValue::Texture(texture) => Some(texture),
+            _ => None,
+        }
+    }
+}
+
+impl BackendProgramBinding<SoftwareBackend> for SoftwareProgramBinding {
+    fn set_texture(
+        &mut self,
+        location: &SoftwareUniformLocation,
+        texture: &Rc<RefCell<SoftwareTexture>>,
+    ) {
+        self.set(location, UniformValue::Texture(texture.clone()));
+    }
+
+    fn set_bool(&mut self, location: &SoftwareUniformLocation, value: bool) {
+        self.set(location, UniformValue::Bool(value));
+    }
+
+    fn set_integer(&mut self, location: &SoftwareUniformLocation, value: i32) {
+        self.set(location, UniformValue::Integer(value));
+    }
+
+    fn set_float(&mut self, location: &SoftwareUniformLocation, value: f32) {
+        self.set(location, UniformValue::Float(value));
+    }
+
+    fn set_vector2(&mut self, location: &SoftwareUniformLocation, value: &Vector2<f32>) {
+        self.set(location, UniformValue::Vector2(*value));
+    }
+
+    fn set_vector3(&mut self, location: &SoftwareUniformLocation, value: &Vector3<f32>) {
+        self.set(location, UniformValue::Vector3(*value));
+    }
+
+    fn set_vector4(&mut self, location: &SoftwareUniformLocation, value: &Vector4<f32>) {
+        self.set(location, UniformValue::Vector4(*value));
+    }
+
+    fn set_matrix3(&mut self, location: &SoftwareUniformLocation, value: &Matrix3<f32>) {
+        self.set(location, UniformValue::Matrix3(*value));
+    }
+
+    fn set_matrix4(&mut self, location: &SoftwareUniformLocation, value: &Matrix4<f32>) {
+        self.set(location, UniformValue::Matrix4(*value));
+    }
+
+    fn set_color(&mut self, location: &SoftwareUniformLocation, value: &Color) {
+        self.set(location, UniformValue::Vector4(value.as_frgba()));
+    }
+}
+
+/// Adapter that runs a shader with a set of uniforms as a [`SoftwareProgram`].
+pub(in crate) struct ShaderInvocation<'a> {
+    pub shader: &'a dyn SoftwareShader,
+    pub uniforms: &'a SoftwareProgramBinding,
+}
+
+impl<'a> SoftwareProgram for ShaderInvocation<'a> {
+    type Varying = Varyings;
+
+    fn vertex(&self, vertex: &SoftwareVertex) -> (Vector4<f32>, Self::Varying) {
+        let mut varyings = Varyings::default();
+        let position = self.shader.vertex(self.uniforms, vertex, &mut varyings);
+        (position, varyings)
+    }
+
+    fn fragment(&self, varying: &Self::Varying, outputs: &mut [Vector4<f32>]) -> bool {
+        self.shader.fragment(self.uniforms, varying, outputs)
+    }
+}
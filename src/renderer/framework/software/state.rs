@@ -0,0 +1,52 @@
+use crate::renderer::{
+    flat_shader::SoftwareFlatShader, framework::software::program::SoftwareShader,
+};
+use std::{collections::HashMap, rc::Rc};
+
+/// CPU counterpart of pipeline state. It stores blend factors and shaders that are used instead
+/// of GLSL programs.
+pub struct SoftwareState {
+    blend_src_factor: u32,
+    blend_dst_factor: u32,
+    programs: HashMap<String, Rc<dyn SoftwareShader>>,
+}
+
+impl Default for SoftwareState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SoftwareState {
+    /// Creates new state with default blend factors (`ONE`, `ZERO`) and shaders of built-in
+    /// programs that have software implementation.
+    pub fn new() -> Self {
+        let mut state = Self {
+            blend_src_factor: glow::ONE,
+            blend_dst_factor: glow::ZERO,
+            programs: Default::default(),
+        };
+        state.register_program("FlatShader", Rc::new(SoftwareFlatShader));
+        state
+    }
+
+    /// Registers a shader that will be used instead of GLSL program with given name. Previous
+    /// shader with the same name is replaced.
+    pub fn register_program(&mut self, name: &str, shader: Rc<dyn SoftwareShader>) {
+        self.programs.insert(name.to_owned(), shader);
+    }
+
+    pub fn program(&self, name: &str) -> Option<Rc<dyn SoftwareShader>> {
+        self.programs.get(name).cloned()
+    }
+
+    /// Sets blend factors, takes the same values as `glBlendFunc`.
+    pub fn set_blend_func(&mut self, sfactor: u32, dfactor: u32) {
+        self.blend_src_factor = sfactor;
+        self.blend_dst_factor = dfactor;
+    }
+
+    pub fn blend_func(&self) -> (u32, u32) {
+        (self.blend_src_factor, self.blend_dst_factor)
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    core::{
+        algebra::{Vector2, Vector4},
+        color::Color,
+    },
+    resource::texture::{TextureData, TextureKind, TexturePixelKind},
+};
+
+/// Rectangle texture with floating-point RGBA pixels. Rows are stored in the same order as in
+/// texture data, first row is sampled at `v = 0`.
+#[derive(Clone, Debug)]
+pub struct SoftwareTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vector4<f32>>,
+}
+
+impl SoftwareTexture {
+    /// Creates new texture filled with zeros.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Vector4::default(); width * height],
+        }
+    }
+
+    /// Converts texture data to software texture. Only rectangle textures with 8-bit
+    /// uncompressed pixels are supported, `None` is returned for any other texture. Only first
+    /// mip level is used.
+    pub fn from_texture_data(data: &TextureData) -> Option<Self> {
+        let (width, height) = match data.kind() {
+            TextureKind::Rectangle { width, height } => (width as usize, height as usize),
+            _ => return None,
+        };
+
+        let decode: fn(&[u8]) -> Vector4<u8> = match data.pixel_kind() {
+            TexturePixelKind::R8 => |p| Vector4::new(p[0], 0, 0, 255),
+            TexturePixelKind::RG8 => |p| Vector4::new(p[0], p[1], 0, 255),
+            TexturePixelKind::RGB8 => |p| Vector4::new(p[0], p[1], p[2], 255),
+            TexturePixelKind::RGBA8 => |p| Vector4::new(p[0], p[1], p[2], p[3]),
+            TexturePixelKind::BGR8 => |p| Vector4::new(p[2], p[1], p[0], 255),
+            TexturePixelKind::BGRA8 => |p| Vector4::new(p[2], p[1], p[0], p[3]),
+            _ => return None,
+        };
+
+        let bytes_per_pixel = match data.pixel_kind() {
+            TexturePixelKind::R8 => 1,
+            TexturePixelKind::RG8 => 2,
+            TexturePixelKind::RGB8 | TexturePixelKind::BGR8 => 3,
+            _ => 4,
+        };
+
+        let pixels = data
+            .data()
+            .chunks_exact(bytes_per_pixel)
+            .take(width * height)
+            .map(|pixel| decode(pixel).cast::<f32>() / 255.0)
+            .collect::<Vec<_>>();
+
+        if pixels.len() != width * height {
+            return None;
+        }
+
+        Some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns pixel at given coordinates, (0, 0) is the first pixel of the first row.
+    pub fn pixel(&self, x: usize, y: usize) -> Vector4<f32> {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, value: Vector4<f32>) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    /// Reverses order of rows, use it to convert images rendered by software frame buffers to
+    /// usual top-to-bottom order.
+    pub fn flip_vertically(&mut self) {
+        let width = self.width;
+        for y in 0..self.height / 2 {
+            let (top, bottom) = self.pixels.split_at_mut((self.height - y - 1) * width);
+            top[y * width..(y + 1) * width].swap_with_slice(&mut bottom[..width]);
+        }
+    }
+
+    pub fn fill(&mut self, color: Color) {
+        let value = color.as_frgba();
+        for pixel in self.pixels.iter_mut() {
+            *pixel = value;
+        }
+    }
+
+    /// Samples the texture using bilinear filtering and repeat wrap mode.
+    pub fn sample(&self, tex_coord: Vector2<f32>) -> Vector4<f32> {
+        if self.pixels.is_empty() {
+            return Vector4::default();
+        }
+
+        let x = tex_coord.x * self.width as f32 - 0.5;
+        let y = tex_coord.y * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        let fetch = |x: f32, y: f32| {
+            let x = (x as i64).rem_euclid(self.width as i64) as usize;
+            let y = (y as i64).rem_euclid(self.height as i64) as usize;
+            self.pixel(x, y)
+        };
+
+        let top = fetch(x0, y0).lerp(&fetch(x0 + 1.0, y0), tx);
+        let bottom = fetch(x0, y0 + 1.0).lerp(&fetch(x0 + 1.0, y0 + 1.0), tx);
+        top.lerp(&bottom, ty)
+    }
+
+    /// Converts the texture to RGBA8 texture data, values are clamped to \[0; 1\] range. Use
+    /// [`TextureData::set_path`] and [`TextureData::save`] to save it to an image file.
+    pub fn to_texture_data(&self) -> TextureData {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in self.pixels.iter() {
+            for i in 0..4 {
+                bytes.push((pixel[i].clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+
+        TextureData::from_bytes(
+            TextureKind::Rectangle {
+                width: self.width as u32,
+                height: self.height as u32,
+            },
+            TexturePixelKind::RGBA8,
+            bytes,
+            true,
+        )
+        .unwrap()
+    }
+}
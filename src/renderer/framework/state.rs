@@ -154,6 +154,14 @@ impl PipelineState {
     pub fn new(context: glow::Context) -> Self {
         unsafe {
             context.depth_func(glow::LEQUAL);
+
+            // Vertex attributes that are not present in a vertex buffer are read from current
+            // generic attribute values, which are (0, 0, 0, 1) by default. Second set of bone
+            // weights exists only in extended skinning layout, so set it to zeros to make it
+            // harmless for vertices with up to 4 bones.
+            for location in [13, 14].iter() {
+                context.vertex_attrib_4_f32(*location, 0.0, 0.0, 0.0, 0.0);
+            }
         }
 
         Self {
@@ -203,6 +211,37 @@ impl PipelineState {
         }
     }
 
+    /// Copies `width` x `height` pixels of given buffers from `source` framebuffer into `dest`
+    /// framebuffer. Destination is bound using [`Self::set_framebuffer`], so binding cache stays
+    /// valid, read framebuffer binding is not cached at all.
+    pub fn blit_framebuffer(
+        &mut self,
+        source: glow::Framebuffer,
+        dest: glow::Framebuffer,
+        width: i32,
+        height: i32,
+        mask: u32,
+    ) {
+        self.set_framebuffer(dest);
+
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::READ_FRAMEBUFFER, Some(source));
+            self.gl.blit_framebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                mask,
+                glow::NEAREST,
+            );
+        }
+    }
+
     pub fn set_viewport(&mut self, viewport: Rect<i32>) {
         if self.viewport != viewport {
             self.viewport = viewport;
@@ -528,3 +567,34 @@ impl PipelineState {
         }
     }
 }
+
+/// Runs given closure with pipeline state of an offscreen OpenGL 3.3 context. Tests that use
+/// it need a GPU (or a software OpenGL implementation), so they are ignored by default and
+/// should be run with `cargo test -- --ignored`.
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub(in crate) fn with_test_state<F: FnOnce(&mut PipelineState)>(func: F) {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    use glutin::platform::unix::EventLoopExtUnix;
+    #[cfg(windows)]
+    use glutin::platform::windows::EventLoopExtWindows;
+    use glutin::{
+        dpi::PhysicalSize, event_loop::EventLoop, Api, ContextBuilder, GlProfile, GlRequest,
+    };
+
+    // Tests are running on worker threads.
+    #[cfg(any(windows, all(unix, not(target_os = "macos"))))]
+    let event_loop = EventLoop::<()>::new_any_thread();
+    #[cfg(target_os = "macos")]
+    let event_loop = EventLoop::<()>::new();
+    let context = ContextBuilder::new()
+        .with_gl_profile(GlProfile::Core)
+        .with_gl(GlRequest::Specific(Api::OpenGl, (3, 3)))
+        .build_headless(&event_loop, PhysicalSize::new(1, 1))
+        .expect("Unable to create OpenGL context!");
+    let context = unsafe { context.make_current() }
+        .map_err(|(_, e)| e)
+        .unwrap();
+    let gl = unsafe { glow::Context::from_loader_function(|s| context.get_proc_address(s)) };
+
+    func(&mut PipelineState::new(gl));
+}
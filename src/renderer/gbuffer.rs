@@ -15,7 +15,8 @@ use crate::{
     },
     renderer::{
         batch::{BatchStorage, InstanceData, MatrixStorage, BONE_MATRICES_COUNT},
-        GeometryCache, RenderPassStatistics, TextureCache,
+        shader_cache::{InstanceUniforms, ShaderCache},
+        GeometryCache, RenderPassStatistics,
     },
     scene::{camera::Camera, mesh::RenderPath},
 };
@@ -109,9 +110,19 @@ fn make_vertex_shader_source(features: UberShaderFeatures) -> String {
             uniform mat4 worldMatrix;
             uniform mat4 worldViewProjection;
             uniform mat4 boneMatrices[60];
+            uniform sampler2D morphDeltas;
+            uniform float morphWeights[S_MaxMorphTargets];
+            uniform int morphTargetCount;
         "#;
     }
 
+    // Second set of weights exists only in extended skinning layout, otherwise these attributes
+    // are disabled and read as zeros (see PipelineState::new).
+    source += r#"
+        layout(location = 13) in vec4 extraBoneWeights;
+        layout(location = 14) in vec4 extraBoneIndices;
+    "#;
+
     source += r#"
         uniform bool useSkeletalAnimation;
 
@@ -140,15 +151,33 @@ fn make_vertex_shader_source(features: UberShaderFeatures) -> String {
             vec4 localPosition = vec4(0);
             vec3 localNormal = vec3(0);
             vec3 localTangent = vec3(0);
-    
+
+            vec3 morphedPosition = vertexPosition;
+            vec3 morphedNormal = vertexNormal;
+            vec3 morphedTangent = vertexTangent.xyz;
+            "#;
+
+    // Morphed surfaces are never instanced, because each instance has its own weights.
+    if !features.contains(UberShaderFeatures::INSTANCING) {
+        source += r#"
+            S_ApplyMorphTargets(morphDeltas, morphWeights, morphTargetCount, gl_VertexID,
+                morphedPosition, morphedNormal, morphedTangent);
+            "#;
+    }
+
+    source += r#"
             if (useSkeletalAnimation)
             {
-                vec4 vertex = vec4(vertexPosition, 1.0);
+                vec4 vertex = vec4(morphedPosition, 1.0);
     
                 int i0 = int(boneIndices.x);
                 int i1 = int(boneIndices.y);
                 int i2 = int(boneIndices.z);
                 int i3 = int(boneIndices.w);
+                int i4 = int(extraBoneIndices.x);
+                int i5 = int(extraBoneIndices.y);
+                int i6 = int(extraBoneIndices.z);
+                int i7 = int(extraBoneIndices.w);
                 "#;
 
     if features.contains(UberShaderFeatures::INSTANCING) {
@@ -159,6 +188,10 @@ fn make_vertex_shader_source(features: UberShaderFeatures) -> String {
                 mat4 m1 = ReadMatrix(boneIndexOrigin + i1);
                 mat4 m2 = ReadMatrix(boneIndexOrigin + i2);
                 mat4 m3 = ReadMatrix(boneIndexOrigin + i3);
+                mat4 m4 = ReadMatrix(boneIndexOrigin + i4);
+                mat4 m5 = ReadMatrix(boneIndexOrigin + i5);
+                mat4 m6 = ReadMatrix(boneIndexOrigin + i6);
+                mat4 m7 = ReadMatrix(boneIndexOrigin + i7);
                 "#;
     } else {
         source += r#"
@@ -166,6 +199,10 @@ fn make_vertex_shader_source(features: UberShaderFeatures) -> String {
                 mat4 m1 = boneMatrices[i1];
                 mat4 m2 = boneMatrices[i2];
                 mat4 m3 = boneMatrices[i3];
+                mat4 m4 = boneMatrices[i4];
+                mat4 m5 = boneMatrices[i5];
+                mat4 m6 = boneMatrices[i6];
+                mat4 m7 = boneMatrices[i7];
                 "#;
     }
 
@@ -174,22 +211,34 @@ fn make_vertex_shader_source(features: UberShaderFeatures) -> String {
                 localPosition += m1 * vertex * boneWeights.y;
                 localPosition += m2 * vertex * boneWeights.z;
                 localPosition += m3 * vertex * boneWeights.w;
+                localPosition += m4 * vertex * extraBoneWeights.x;
+                localPosition += m5 * vertex * extraBoneWeights.y;
+                localPosition += m6 * vertex * extraBoneWeights.z;
+                localPosition += m7 * vertex * extraBoneWeights.w;
                 
-                localNormal += mat3(m0) * vertexNormal * boneWeights.x;
-                localNormal += mat3(m1) * vertexNormal * boneWeights.y;
-                localNormal += mat3(m2) * vertexNormal * boneWeights.z;
-                localNormal += mat3(m3) * vertexNormal * boneWeights.w;
+                localNormal += mat3(m0) * morphedNormal * boneWeights.x;
+                localNormal += mat3(m1) * morphedNormal * boneWeights.y;
+                localNormal += mat3(m2) * morphedNormal * boneWeights.z;
+                localNormal += mat3(m3) * morphedNormal * boneWeights.w;
+                localNormal += mat3(m4) * morphedNormal * extraBoneWeights.x;
+                localNormal += mat3(m5) * morphedNormal * extraBoneWeights.y;
+                localNormal += mat3(m6) * morphedNormal * extraBoneWeights.z;
+                localNormal += mat3(m7) * morphedNormal * extraBoneWeights.w;
                 
-                localTangent += mat3(m0) * vertexTangent.xyz * boneWeights.x;
-                localTangent += mat3(m1) * vertexTangent.xyz * boneWeights.y;
-                localTangent += mat3(m2) * vertexTangent.xyz * boneWeights.z;
-                localTangent += mat3(m3) * vertexTangent.xyz * boneWeights.w;             
+                localTangent += mat3(m0) * morphedTangent * boneWeights.x;
+                localTangent += mat3(m1) * morphedTangent * boneWeights.y;
+                localTangent += mat3(m2) * morphedTangent * boneWeights.z;
+                localTangent += mat3(m3) * morphedTangent * boneWeights.w;
+                localTangent += mat3(m4) * morphedTangent * extraBoneWeights.x;
+                localTangent += mat3(m5) * morphedTangent * extraBoneWeights.y;
+                localTangent += mat3(m6) * morphedTangent * extraBoneWeights.z;
+                localTangent += mat3(m7) * morphedTangent * extraBoneWeights.w;             
             }
             else
             {
-                localPosition = vec4(vertexPosition, 1.0);
-                localNormal = vertexNormal;
-                localTangent = vertexTangent.xyz;
+                localPosition = vec4(morphedPosition, 1.0);
+                localNormal = morphedNormal;
+                localTangent = morphedTangent;
             }
 
             mat3 nm = mat3(worldMatrix);
@@ -233,13 +282,13 @@ fn make_fragment_shader_source(features: UberShaderFeatures) -> String {
         layout(location = 0) out vec4 outColor;
         layout(location = 1) out vec4 outNormal;
         layout(location = 2) out vec4 outAmbient;
+        layout(location = 3) out vec4 outMaterial;
 
         uniform sampler2D diffuseTexture;
         uniform sampler2D normalTexture;
         uniform sampler2D specularTexture;
         uniform sampler2D roughnessTexture;
         uniform sampler2D heightTexture;
-        uniform samplerCube environmentMap;
         uniform vec3 cameraPosition;
         uniform bool usePOM;
         uniform vec2 texCoordScale;
@@ -324,10 +373,8 @@ fn make_fragment_shader_source(features: UberShaderFeatures) -> String {
     }
 
     source += r#"
-        // reflection mapping
-        float roughness = texture(roughnessTexture, tc).r;
-        vec3 reflectionTexCoord = reflect(toFragment, normalize(n.xyz));
-        outColor = (1.0 - roughness) * outColor + roughness * vec4(texture(environmentMap, reflectionTexCoord).rgb, outColor.a);
+        // Reflections are applied in light pass, here we just store reflection factor.
+        outMaterial = vec4(texture(roughnessTexture, tc).r, 0.0, 0.0, 1.0);
     "#;
 
     if features.contains(UberShaderFeatures::TERRAIN) {
@@ -337,6 +384,7 @@ fn make_fragment_shader_source(features: UberShaderFeatures) -> String {
         outColor.a *= mask;     
         outAmbient.a *= mask;         
         outNormal.a *= mask;        
+        outMaterial.a *= mask;
         "#;
     }
 
@@ -359,7 +407,6 @@ struct UberShader {
     matrix_buffer_stride: Option<UniformLocation>,
     matrix_storage_size: Option<UniformLocation>,
     matrix_storage: Option<UniformLocation>,
-    environment_map: UniformLocation,
     camera_position: UniformLocation,
     view_projection_matrix: Option<UniformLocation>,
     use_pom: UniformLocation,
@@ -369,6 +416,9 @@ struct UberShader {
     wvp_matrix: Option<UniformLocation>,
     bone_matrices: Option<UniformLocation>,
     diffuse_color: Option<UniformLocation>,
+    morph_deltas: Option<UniformLocation>,
+    morph_weights: Option<UniformLocation>,
+    morph_target_count: Option<UniformLocation>,
     // Terrain.
     mask_texture: Option<UniformLocation>,
 }
@@ -447,7 +497,6 @@ impl UberShader {
             } else {
                 None
             },
-            environment_map: program.uniform_location(state, "environmentMap")?,
             camera_position: program.uniform_location(state, "cameraPosition")?,
             view_projection_matrix: if instancing {
                 Some(program.uniform_location(state, "viewProjectionMatrix")?)
@@ -466,6 +515,21 @@ impl UberShader {
             } else {
                 None
             },
+            morph_deltas: if !instancing {
+                Some(program.uniform_location(state, "morphDeltas")?)
+            } else {
+                None
+            },
+            morph_weights: if !instancing {
+                Some(program.uniform_location(state, "morphWeights")?)
+            } else {
+                None
+            },
+            morph_target_count: if !instancing {
+                Some(program.uniform_location(state, "morphTargetCount")?)
+            } else {
+                None
+            },
             mask_texture: if terrain {
                 Some(program.uniform_location(state, "maskTexture")?)
             } else {
@@ -491,9 +555,8 @@ pub(in crate) struct GBufferRenderContext<'a, 'b> {
     pub camera: &'b Camera,
     pub geom_cache: &'a mut GeometryCache,
     pub batch_storage: &'a BatchStorage,
-    pub texture_cache: &'a mut TextureCache,
-    pub environment_dummy: Rc<RefCell<GpuTexture>>,
     pub use_parallax_mapping: bool,
+    pub shader_cache: &'a mut ShaderCache,
 }
 
 impl GBuffer {
@@ -562,6 +625,20 @@ impl GBuffer {
             .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
             .set_wrap(Coordinate::T, WrapMode::ClampToEdge);
 
+        let mut material_texture = GpuTexture::new(
+            state,
+            GpuTextureKind::Rectangle { width, height },
+            PixelKind::RGBA8,
+            MinificationFilter::Nearest,
+            MagnificationFilter::Nearest,
+            1,
+            None,
+        )?;
+        material_texture
+            .bind_mut(state, 0)
+            .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
+            .set_wrap(Coordinate::T, WrapMode::ClampToEdge);
+
         let framebuffer = FrameBuffer::new(
             state,
             Some(Attachment {
@@ -581,6 +658,10 @@ impl GBuffer {
                     kind: AttachmentKind::Color,
                     texture: Rc::new(RefCell::new(ambient_texture)),
                 },
+                Attachment {
+                    kind: AttachmentKind::Color,
+                    texture: Rc::new(RefCell::new(material_texture)),
+                },
             ],
         )?;
 
@@ -600,10 +681,11 @@ impl GBuffer {
 
         let final_frame_depth_stencil = Rc::new(RefCell::new(final_frame_depth_stencil_texture));
 
+        // Frame is in high dynamic range, it is mapped to displayable range by post-processing.
         let frame_texture = GpuTexture::new(
             state,
             GpuTextureKind::Rectangle { width, height },
-            PixelKind::RGBA8,
+            PixelKind::RGBA16F,
             MinificationFilter::Linear,
             MagnificationFilter::Linear,
             1,
@@ -661,6 +743,11 @@ impl GBuffer {
         self.framebuffer.color_attachments()[2].texture.clone()
     }
 
+    /// Returns texture with material properties, red channel contains reflection factor.
+    pub fn material_texture(&self) -> Rc<RefCell<GpuTexture>> {
+        self.framebuffer.color_attachments()[3].texture.clone()
+    }
+
     #[must_use]
     pub(in crate) fn fill(&mut self, args: GBufferRenderContext) -> RenderPassStatistics {
         scope_profile!();
@@ -672,9 +759,8 @@ impl GBuffer {
             camera,
             geom_cache,
             batch_storage,
-            texture_cache,
-            environment_dummy,
             use_parallax_mapping,
+            shader_cache,
         } = args;
 
         let viewport = Rect::new(0, 0, self.width, self.height);
@@ -705,12 +791,57 @@ impl GBuffer {
         {
             let data = batch.data.read().unwrap();
             let geometry = geom_cache.get(state, &data);
-            let use_instanced_rendering = batch.instances.len() > 1;
 
-            let environment = match camera.environment_ref() {
-                Some(texture) => texture_cache.get(state, texture).unwrap(),
-                None => environment_dummy.clone(),
-            };
+            // Surfaces with custom materials are drawn one-by-one using material shader.
+            if let Some(material) = batch.material.as_ref() {
+                let material = material.data_ref();
+                if let Some(pass) = shader_cache
+                    .get(state, material.shader())
+                    .and_then(|shader| shader.pass("GBuffer"))
+                {
+                    let camera_position = camera.global_position();
+                    params.blend = false;
+
+                    for instance in batch.instances.iter() {
+                        if camera.visibility_cache.is_visible(instance.owner) {
+                            let view_projection = if instance.depth_offset != 0.0 {
+                                let mut projection = camera.projection_matrix();
+                                projection[14] -= instance.depth_offset;
+                                projection * camera.view_matrix()
+                            } else {
+                                initial_view_projection
+                            };
+                            let world_view_projection = view_projection * instance.world_transform;
+
+                            statistics += self.framebuffer.draw(
+                                geometry,
+                                state,
+                                viewport,
+                                &pass.program,
+                                &params,
+                                |program_binding| {
+                                    pass.apply(
+                                        program_binding,
+                                        &material,
+                                        &batch.material_textures,
+                                        &InstanceUniforms {
+                                            world_matrix: &instance.world_transform,
+                                            world_view_projection: &world_view_projection,
+                                            use_skeletal_animation: batch.is_skinned,
+                                            bone_matrices: instance.bone_matrices.as_slice(),
+                                            camera_position: &camera_position,
+                                        },
+                                    );
+                                },
+                            );
+                        }
+                    }
+
+                    continue;
+                }
+            }
+
+            let use_instanced_rendering = batch.instances.len() > 1;
 
             // Prepare batch info storage in case if we're rendering multiple objects
             // at once.
@@ -759,7 +890,6 @@ impl GBuffer {
                         .set_texture(&shader.diffuse_texture, &batch.diffuse_texture)
                         .set_texture(&shader.normal_texture, &batch.normal_texture)
                         .set_texture(&shader.specular_texture, &batch.specular_texture)
-                        .set_texture(&shader.environment_map, &environment)
                         .set_texture(&shader.roughness_texture, &batch.roughness_texture)
                         .set_texture(&shader.height_texture, &batch.height_texture)
                         .set_vector3(&shader.camera_position, &camera.global_position())
@@ -835,6 +965,15 @@ impl GBuffer {
                             .set_matrix4(
                                 shader.world_matrix.as_ref().unwrap(),
                                 &instance.world_transform,
+                            )
+                            .set_texture(shader.morph_deltas.as_ref().unwrap(), &batch.morph_deltas)
+                            .set_float_slice(
+                                shader.morph_weights.as_ref().unwrap(),
+                                instance.morph_weights.as_slice(),
+                            )
+                            .set_integer(
+                                shader.morph_target_count.as_ref().unwrap(),
+                                batch.morph_target_count as i32,
                             );
                     }
                 };
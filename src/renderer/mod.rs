@@ -11,6 +11,7 @@
 pub mod cache;
 pub mod debug_renderer;
 pub mod framework;
+pub mod post_processing;
 pub mod renderer2d;
 
 mod batch;
@@ -22,6 +23,9 @@ mod fxaa;
 mod gbuffer;
 mod light_volume;
 mod particle_system_renderer;
+mod reflection_probe_renderer;
+mod reflection_renderer;
+mod shader_cache;
 mod shadow_map_renderer;
 mod skybox_shader;
 mod sprite_renderer;
@@ -31,14 +35,7 @@ mod ui_renderer;
 use crate::renderer::cache::CacheEntry;
 use crate::scene::camera::Camera;
 use crate::{
-    core::{
-        algebra::{Matrix4, Vector2, Vector3},
-        color::Color,
-        instant,
-        math::Rect,
-        pool::Handle,
-        scope_profile,
-    },
+    core::{algebra::Vector2, color::Color, instant, math::Rect, pool::Handle, scope_profile},
     gui::{draw::DrawingContext, message::MessageData, Control, UserInterface},
     renderer::{
         batch::BatchStorage,
@@ -51,7 +48,7 @@ use crate::{
         forward_renderer::{ForwardRenderContext, ForwardRenderer},
         framework::{
             error::FrameworkError,
-            framebuffer::{Attachment, AttachmentKind, CullFace, DrawParameters, FrameBuffer},
+            framebuffer::{Attachment, AttachmentKind, FrameBuffer},
             geometry_buffer::DrawCallStatistics,
             gpu_texture::{
                 GpuTexture, GpuTextureKind, MagnificationFilter, MinificationFilter, PixelKind,
@@ -61,7 +58,10 @@ use crate::{
         fxaa::FxaaRenderer,
         gbuffer::{GBuffer, GBufferRenderContext},
         particle_system_renderer::{ParticleSystemRenderContext, ParticleSystemRenderer},
+        post_processing::{PostEffect, PostProcessingContext, PostProcessingRenderer},
+        reflection_probe_renderer::{ReflectionProbeCaptureContext, ReflectionProbeRenderer},
         renderer2d::Renderer2d,
+        shader_cache::ShaderCache,
         sprite_renderer::{SpriteRenderContext, SpriteRenderer},
         ui_renderer::{UiRenderContext, UiRenderer},
     },
@@ -216,6 +216,22 @@ pub struct QualitySettings {
     /// quality and performance.
     pub spot_shadow_map_precision: ShadowMapPrecision,
 
+    /// Directional shadows
+    /// Size of square shadow map texture of each cascade in pixels.
+    pub directional_shadow_map_size: usize,
+    /// Use or not percentage close filtering (smoothing) for directional shadows.
+    pub directional_soft_shadows: bool,
+    /// Directional shadows enabled or not.
+    pub directional_shadows_enabled: bool,
+    /// Maximum distance from camera to draw directional shadows, view frustum of
+    /// a camera is split into cascades up to this distance.
+    pub directional_shadows_distance: f32,
+    /// Directional shadow map precision. Allows you to select compromise between
+    /// quality and performance.
+    pub directional_shadow_map_precision: ShadowMapPrecision,
+    /// Amount of shadow cascades, must be in `[1; 4]` range.
+    pub directional_shadow_cascade_count: usize,
+
     /// Whether to use screen space ambient occlusion or not.
     pub use_ssao: bool,
     /// Radius of sampling hemisphere used in SSAO, it defines much ambient
@@ -231,6 +247,15 @@ pub struct QualitySettings {
 
     /// Whether to use Parallax Mapping or not.
     pub use_parallax_mapping: bool,
+
+    /// Whether to use screen space reflections or not. Reflections from reflection
+    /// probes and environment maps are used regardless of this setting.
+    pub use_ssr: bool,
+    /// Maximum length of a ray (in world units) traced by screen space reflections.
+    pub ssr_max_distance: f32,
+    /// Amount of steps of a ray traced by screen space reflections. More steps gives
+    /// more precise reflections, but costs more.
+    pub ssr_steps: usize,
 }
 
 impl Default for QualitySettings {
@@ -253,6 +278,12 @@ impl QualitySettings {
             spot_shadows_enabled: true,
             spot_soft_shadows: true,
 
+            directional_shadow_map_size: 2048,
+            directional_shadows_distance: 100.0,
+            directional_shadows_enabled: true,
+            directional_soft_shadows: true,
+            directional_shadow_cascade_count: 4,
+
             use_ssao: true,
             ssao_radius: 0.5,
 
@@ -260,10 +291,15 @@ impl QualitySettings {
 
             point_shadow_map_precision: ShadowMapPrecision::Full,
             spot_shadow_map_precision: ShadowMapPrecision::Full,
+            directional_shadow_map_precision: ShadowMapPrecision::Full,
 
             fxaa: true,
 
             use_parallax_mapping: false, // TODO: Enable when it is fixed!
+
+            use_ssr: true,
+            ssr_max_distance: 20.0,
+            ssr_steps: 64,
         }
     }
 
@@ -280,6 +316,12 @@ impl QualitySettings {
             spot_shadows_enabled: true,
             spot_soft_shadows: true,
 
+            directional_shadow_map_size: 2048,
+            directional_shadows_distance: 60.0,
+            directional_shadows_enabled: true,
+            directional_soft_shadows: true,
+            directional_shadow_cascade_count: 3,
+
             use_ssao: true,
             ssao_radius: 0.5,
 
@@ -287,10 +329,15 @@ impl QualitySettings {
 
             point_shadow_map_precision: ShadowMapPrecision::Full,
             spot_shadow_map_precision: ShadowMapPrecision::Full,
+            directional_shadow_map_precision: ShadowMapPrecision::Full,
 
             fxaa: true,
 
             use_parallax_mapping: false, // TODO: Enable when it is fixed!
+
+            use_ssr: true,
+            ssr_max_distance: 15.0,
+            ssr_steps: 32,
         }
     }
 
@@ -307,6 +354,12 @@ impl QualitySettings {
             spot_shadows_enabled: true,
             spot_soft_shadows: false,
 
+            directional_shadow_map_size: 1024,
+            directional_shadows_distance: 30.0,
+            directional_shadows_enabled: true,
+            directional_soft_shadows: false,
+            directional_shadow_cascade_count: 2,
+
             use_ssao: true,
             ssao_radius: 0.5,
 
@@ -314,10 +367,15 @@ impl QualitySettings {
 
             point_shadow_map_precision: ShadowMapPrecision::Half,
             spot_shadow_map_precision: ShadowMapPrecision::Half,
+            directional_shadow_map_precision: ShadowMapPrecision::Half,
 
             fxaa: true,
 
             use_parallax_mapping: false,
+
+            use_ssr: false,
+            ssr_max_distance: 10.0,
+            ssr_steps: 16,
         }
     }
 
@@ -334,6 +392,12 @@ impl QualitySettings {
             spot_shadows_enabled: false,
             spot_soft_shadows: false,
 
+            directional_shadow_map_size: 1,
+            directional_shadows_distance: 0.0,
+            directional_shadows_enabled: false,
+            directional_soft_shadows: false,
+            directional_shadow_cascade_count: 1,
+
             use_ssao: false,
             ssao_radius: 0.5,
 
@@ -341,10 +405,15 @@ impl QualitySettings {
 
             point_shadow_map_precision: ShadowMapPrecision::Half,
             spot_shadow_map_precision: ShadowMapPrecision::Half,
+            directional_shadow_map_precision: ShadowMapPrecision::Half,
 
             fxaa: false,
 
             use_parallax_mapping: false,
+
+            use_ssr: false,
+            ssr_max_distance: 10.0,
+            ssr_steps: 16,
         }
     }
 }
@@ -408,6 +477,15 @@ pub struct TextureUploadSender {
 }
 
 impl TextureUploadSender {
+    /// Creates a sender that is not connected to a renderer, uploads are discarded.
+    #[cfg(test)]
+    pub(in crate) fn detached() -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        // Sending fails if receiver is dropped.
+        std::mem::forget(receiver);
+        Self { sender }
+    }
+
     /// Requests an upload of the texture to GPU memory.
     pub fn request_upload(&self, texture: Texture) {
         self.sender
@@ -420,7 +498,9 @@ impl TextureUploadSender {
 pub struct Renderer {
     backbuffer: FrameBuffer,
     scene_render_passes: Vec<Arc<Mutex<dyn SceneRenderPass>>>,
+    post_effects: Vec<Arc<Mutex<dyn PostEffect>>>,
     deferred_light_renderer: DeferredLightRenderer,
+    reflection_probe_renderer: ReflectionProbeRenderer,
     flat_shader: FlatShader,
     sprite_renderer: SpriteRenderer,
     particle_system_renderer: ParticleSystemRenderer,
@@ -447,9 +527,11 @@ pub struct Renderer {
     backbuffer_clear_color: Color,
     texture_cache: TextureCache,
     geometry_cache: GeometryCache,
+    shader_cache: ShaderCache,
     batch_storage: BatchStorage,
     forward_renderer: ForwardRenderer,
     fxaa_renderer: FxaaRenderer,
+    post_processing_renderer: PostProcessingRenderer,
     renderer2d: Renderer2d,
     texture_upload_receiver: Receiver<Texture>,
     texture_upload_sender: Sender<Texture>,
@@ -610,7 +692,8 @@ impl Renderer {
             backbuffer: FrameBuffer::backbuffer(&mut state),
             frame_size,
             deferred_light_renderer: DeferredLightRenderer::new(&mut state, frame_size, &settings)?,
-            flat_shader: FlatShader::new(&mut state)?,
+            reflection_probe_renderer: Default::default(),
+            flat_shader: FlatShader::new(&mut *state)?,
             sprite_renderer: SpriteRenderer::new(&mut state)?,
             white_dummy: Rc::new(RefCell::new(GpuTexture::new(
                 &mut state,
@@ -688,16 +771,19 @@ impl Renderer {
             backbuffer_clear_color: Color::BLACK,
             texture_cache: Default::default(),
             geometry_cache: Default::default(),
+            shader_cache: Default::default(),
             batch_storage: Default::default(),
             forward_renderer: ForwardRenderer::new(&mut state)?,
             ui_frame_buffers: Default::default(),
             fxaa_renderer: FxaaRenderer::new(&mut state)?,
+            post_processing_renderer: PostProcessingRenderer::new(&mut state)?,
             statistics: Statistics::default(),
             renderer2d: Renderer2d::new(&mut state)?,
             texture_upload_receiver,
             texture_upload_sender,
             state,
             scene_render_passes: Default::default(),
+            post_effects: Default::default(),
         })
     }
 
@@ -706,6 +792,12 @@ impl Renderer {
         self.scene_render_passes.push(pass);
     }
 
+    /// Adds a custom post effect, it will be applied after built-in post effects of each
+    /// camera. See [`post_processing`] module docs for more info.
+    pub fn add_post_effect(&mut self, effect: Arc<Mutex<dyn PostEffect>>) {
+        self.post_effects.push(effect);
+    }
+
     /// Returns statistics for last frame.
     pub fn get_statistics(&self) -> Statistics {
         self.statistics
@@ -779,6 +871,7 @@ impl Renderer {
     pub fn flush(&mut self) {
         self.texture_cache.clear();
         self.geometry_cache.clear();
+        self.shader_cache.clear();
         self.renderer2d.flush();
     }
 
@@ -876,6 +969,7 @@ impl Renderer {
         // Update caches - this will remove timed out resources.
         self.update_texture_cache(dt);
         self.geometry_cache.update(dt);
+        self.shader_cache.update(dt);
         self.renderer2d.update(dt);
     }
 
@@ -893,6 +987,8 @@ impl Renderer {
         // object have same name.
         self.state.invalidate_resource_bindings_cache();
         self.statistics.begin_frame();
+        self.reflection_probe_renderer.begin_frame();
+        self.post_processing_renderer.begin_frame();
 
         let window_viewport = Rect::new(0, 0, self.frame_size.0 as i32, self.frame_size.1 as i32);
         self.backbuffer.clear(
@@ -934,6 +1030,26 @@ impl Renderer {
                 &mut self.texture_cache,
             );
 
+            // Reflection probes must be captured before cameras, so cameras will see
+            // up-to-date reflections.
+            let (pass_stats, light_stats) =
+                self.reflection_probe_renderer
+                    .update(ReflectionProbeCaptureContext {
+                        state,
+                        scene,
+                        scene_handle,
+                        deferred_light_renderer: &mut self.deferred_light_renderer,
+                        geometry_cache: &mut self.geometry_cache,
+                        texture_cache: &mut self.texture_cache,
+                        shader_cache: &mut self.shader_cache,
+                        batch_storage: &self.batch_storage,
+                        white_dummy: self.white_dummy.clone(),
+                        environment_dummy: self.environment_dummy.clone(),
+                        settings: &self.quality_settings,
+                    })?;
+            self.statistics.lighting += light_stats;
+            self.statistics.geometry += pass_stats;
+
             let gbuffer = self
                 .scene_to_gbuffer_map
                 .entry(scene_handle)
@@ -966,10 +1082,10 @@ impl Renderer {
                 );
             }
 
-            for camera in graph.linear_iter().filter_map(|node| {
+            for (camera_handle, camera) in graph.pair_iter().filter_map(|(handle, node)| {
                 if let Node::Camera(camera) = node {
                     if camera.is_enabled() {
-                        Some(camera)
+                        Some((handle, camera))
                     } else {
                         None
                     }
@@ -984,11 +1100,14 @@ impl Renderer {
                     camera,
                     geom_cache: &mut self.geometry_cache,
                     batch_storage: &self.batch_storage,
-                    texture_cache: &mut self.texture_cache,
-                    environment_dummy: self.environment_dummy.clone(),
                     use_parallax_mapping: self.quality_settings.use_parallax_mapping,
+                    shader_cache: &mut self.shader_cache,
                 });
 
+                let reflection_probes =
+                    self.reflection_probe_renderer
+                        .visible_probes(scene_handle, graph, camera);
+
                 let (pass_stats, light_stats) =
                     self.deferred_light_renderer
                         .render(DeferredRendererContext {
@@ -997,11 +1116,13 @@ impl Renderer {
                             camera,
                             gbuffer,
                             white_dummy: self.white_dummy.clone(),
+                            environment_dummy: self.environment_dummy.clone(),
                             ambient_color: scene.ambient_lighting_color,
                             settings: &self.quality_settings,
                             textures: &mut self.texture_cache,
                             geometry_cache: &mut self.geometry_cache,
                             batch_storage: &self.batch_storage,
+                            reflection_probes: &reflection_probes,
                         });
 
                 self.statistics.lighting += light_stats;
@@ -1042,8 +1163,25 @@ impl Renderer {
                     batch_storage: &self.batch_storage,
                     framebuffer: &mut gbuffer.final_frame, // TODO: GBuffer **must not** contain final frame.
                     viewport,
+                    shader_cache: &mut self.shader_cache,
                 });
 
+                // Debug geometry and custom render passes are not affected by post-processing.
+                self.statistics.geometry +=
+                    self.post_processing_renderer
+                        .render(PostProcessingContext {
+                            state,
+                            gbuffer,
+                            geometry_cache: &mut self.geometry_cache,
+                            texture_cache: &mut self.texture_cache,
+                            scene,
+                            scene_handle,
+                            camera,
+                            camera_handle,
+                            white_dummy: self.white_dummy.clone(),
+                            effects: &self.post_effects,
+                        })?;
+
                 self.statistics += self.debug_renderer.render(
                     state,
                     viewport,
@@ -1087,45 +1225,22 @@ impl Renderer {
                             &mut self.geometry_cache,
                         );
                     } else {
-                        let shader = &self.flat_shader;
-                        self.statistics.geometry += self.backbuffer.draw(
-                            self.geometry_cache.get(state, &self.quad),
+                        let quad = self.geometry_cache.get(state, &self.quad);
+                        self.statistics.geometry += self.flat_shader.blit(
                             state,
+                            &mut self.backbuffer,
+                            quad,
                             viewport,
-                            &self.flat_shader.program,
-                            &DrawParameters {
-                                cull_face: CullFace::Back,
-                                culling: false,
-                                color_write: Default::default(),
-                                depth_write: true,
-                                stencil_test: false,
-                                depth_test: false,
-                                blend: false,
-                            },
-                            |program_binding| {
-                                program_binding
-                                    .set_matrix4(&shader.wvp_matrix, &{
-                                        Matrix4::new_orthographic(
-                                            0.0,
-                                            viewport.w() as f32,
-                                            viewport.h() as f32,
-                                            0.0,
-                                            -1.0,
-                                            1.0,
-                                        ) * Matrix4::new_nonuniform_scaling(&Vector3::new(
-                                            viewport.w() as f32,
-                                            viewport.h() as f32,
-                                            0.0,
-                                        ))
-                                    })
-                                    .set_texture(&shader.diffuse_texture, &gbuffer.frame_texture());
-                            },
+                            &gbuffer.frame_texture(),
                         );
                     }
                 }
             }
         }
 
+        self.reflection_probe_renderer.end_frame();
+        self.post_processing_renderer.end_frame();
+
         self.statistics += self.renderer2d.render(
             &mut self.state,
             &mut self.backbuffer,
@@ -0,0 +1,1176 @@
+//! Post-processing chain of cameras. See [`crate::scene::post_processing`] for overview of
+//! available effects and their order.
+//!
+//! # Custom effects
+//!
+//! Custom effects could be added by implementing [`PostEffect`] trait and registering the
+//! effect in the renderer using [`crate::renderer::Renderer::add_post_effect`]. Custom effects
+//! are applied after built-in effects, in order of registration. Each effect takes frame in low
+//! dynamic range and writes result into given frame buffer.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Vector2, Vector3},
+        math::Rect,
+        instant,
+        pool::Handle,
+        scope_profile,
+    },
+    renderer::{
+        framework::{
+            error::FrameworkError,
+            framebuffer::{Attachment, AttachmentKind, CullFace, DrawParameters, FrameBuffer},
+            gpu_program::{GpuProgram, UniformLocation},
+            gpu_texture::{
+                Coordinate, GpuTexture, GpuTextureKind, MagnificationFilter, MinificationFilter,
+                PixelKind, WrapMode,
+            },
+            state::PipelineState,
+        },
+        gbuffer::GBuffer,
+        GeometryCache, RenderPassStatistics, TextureCache,
+    },
+    scene::{
+        camera::Camera, mesh::surface::SurfaceData, node::Node, post_processing::Exposure, Scene,
+    },
+};
+use std::{
+    cell::RefCell,
+    collections::{hash_map::Entry, HashMap},
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+/// Amount of bins of luminance histogram.
+const HISTOGRAM_BIN_COUNT: usize = 64;
+/// Frame is downsampled to this size before building luminance histogram.
+const LUMINANCE_TEXTURE_SIZE: usize = 64;
+/// Luminance range covered by the histogram, in log2 units.
+const MIN_LOG_LUMINANCE: f32 = -10.0;
+const LOG_LUMINANCE_RANGE: f32 = 22.0;
+
+/// A context for custom post effects.
+pub struct PostEffectContext<'a, 'b> {
+    /// A pipeline state that is used as a wrapper to underlying graphics API.
+    pub pipeline_state: &'a mut PipelineState,
+
+    /// A texture cache that uploads engine's `Texture` as internal `GpuTexture` to GPU.
+    pub texture_cache: &'a mut TextureCache,
+
+    /// A geometry cache that uploads engine's `SurfaceData` as internal `GeometryBuffer` to GPU.
+    pub geometry_cache: &'a mut GeometryCache,
+
+    /// Result of previous effects in low dynamic range.
+    pub input_texture: Rc<RefCell<GpuTexture>>,
+
+    /// Depth buffer of the frame.
+    pub depth_texture: Rc<RefCell<GpuTexture>>,
+
+    /// Frame buffer the effect must write its result to, it has the same size as input texture.
+    pub framebuffer: &'a mut FrameBuffer,
+
+    /// Viewport that covers whole frame buffer.
+    pub viewport: Rect<i32>,
+
+    /// A scene being rendered.
+    pub scene: &'b Scene,
+
+    /// A camera from the scene that is used as "eyes".
+    pub camera: &'b Camera,
+
+    /// A handle of the scene being rendered.
+    pub scene_handle: Handle<Scene>,
+}
+
+/// A trait for custom post effects, see module docs.
+pub trait PostEffect {
+    /// Applies the effect. It will be called for **each** camera of **each** scene, but you are
+    /// able to filter out scenes by their handles. The effect must write every pixel of output
+    /// frame buffer, otherwise the contents of these pixels are undefined.
+    fn render(&mut self, ctx: PostEffectContext) -> Result<RenderPassStatistics, FrameworkError>;
+}
+
+struct LuminanceShader {
+    program: GpuProgram,
+    wvp_matrix: UniformLocation,
+    frame_texture: UniformLocation,
+}
+
+impl LuminanceShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("shaders/luminance_fs.glsl");
+        let vertex_source = include_str!("shaders/flat_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "LuminanceShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            frame_texture: program.uniform_location(state, "frameTexture")?,
+            program,
+        })
+    }
+}
+
+struct HistogramShader {
+    program: GpuProgram,
+    wvp_matrix: UniformLocation,
+    luminance_texture: UniformLocation,
+    min_log_luminance: UniformLocation,
+    log_luminance_range: UniformLocation,
+    bin_count: UniformLocation,
+}
+
+impl HistogramShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("shaders/histogram_fs.glsl");
+        let vertex_source = include_str!("shaders/flat_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "HistogramShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            luminance_texture: program.uniform_location(state, "luminanceTexture")?,
+            min_log_luminance: program.uniform_location(state, "minLogLuminance")?,
+            log_luminance_range: program.uniform_location(state, "logLuminanceRange")?,
+            bin_count: program.uniform_location(state, "binCount")?,
+            program,
+        })
+    }
+}
+
+struct ExposureShader {
+    program: GpuProgram,
+    wvp_matrix: UniformLocation,
+    histogram_texture: UniformLocation,
+    previous_exposure_texture: UniformLocation,
+    min_log_luminance: UniformLocation,
+    log_luminance_range: UniformLocation,
+    bin_count: UniformLocation,
+    key_value: UniformLocation,
+    min_luminance: UniformLocation,
+    max_luminance: UniformLocation,
+    adaptation: UniformLocation,
+}
+
+impl ExposureShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("shaders/exposure_fs.glsl");
+        let vertex_source = include_str!("shaders/flat_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "ExposureShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            histogram_texture: program.uniform_location(state, "histogramTexture")?,
+            previous_exposure_texture: program
+                .uniform_location(state, "previousExposureTexture")?,
+            min_log_luminance: program.uniform_location(state, "minLogLuminance")?,
+            log_luminance_range: program.uniform_location(state, "logLuminanceRange")?,
+            bin_count: program.uniform_location(state, "binCount")?,
+            key_value: program.uniform_location(state, "keyValue")?,
+            min_luminance: program.uniform_location(state, "minLuminance")?,
+            max_luminance: program.uniform_location(state, "maxLuminance")?,
+            adaptation: program.uniform_location(state, "adaptation")?,
+            program,
+        })
+    }
+}
+
+struct BloomShader {
+    program: GpuProgram,
+    wvp_matrix: UniformLocation,
+    frame_texture: UniformLocation,
+    exposure_texture: UniformLocation,
+    auto_exposure: UniformLocation,
+    exposure: UniformLocation,
+    threshold: UniformLocation,
+}
+
+impl BloomShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("shaders/bloom_fs.glsl");
+        let vertex_source = include_str!("shaders/flat_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "BloomShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            frame_texture: program.uniform_location(state, "frameTexture")?,
+            exposure_texture: program.uniform_location(state, "exposureTexture")?,
+            auto_exposure: program.uniform_location(state, "autoExposure")?,
+            exposure: program.uniform_location(state, "exposure")?,
+            threshold: program.uniform_location(state, "threshold")?,
+            program,
+        })
+    }
+}
+
+struct GaussianBlurShader {
+    program: GpuProgram,
+    wvp_matrix: UniformLocation,
+    input_texture: UniformLocation,
+    direction: UniformLocation,
+}
+
+impl GaussianBlurShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("shaders/gaussian_blur_fs.glsl");
+        let vertex_source = include_str!("shaders/flat_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "GaussianBlurShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            input_texture: program.uniform_location(state, "inputTexture")?,
+            direction: program.uniform_location(state, "direction")?,
+            program,
+        })
+    }
+}
+
+struct PostProcessShader {
+    program: GpuProgram,
+    wvp_matrix: UniformLocation,
+    frame_texture: UniformLocation,
+    bloom_texture: UniformLocation,
+    exposure_texture: UniformLocation,
+    lut_texture: UniformLocation,
+    auto_exposure: UniformLocation,
+    exposure: UniformLocation,
+    bloom_enabled: UniformLocation,
+    bloom_intensity: UniformLocation,
+    tone_mapping: UniformLocation,
+    color_grading_enabled: UniformLocation,
+    vignette_enabled: UniformLocation,
+    vignette_intensity: UniformLocation,
+    vignette_radius: UniformLocation,
+    vignette_smoothness: UniformLocation,
+    chromatic_aberration: UniformLocation,
+}
+
+impl PostProcessShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("shaders/post_process_fs.glsl");
+        let vertex_source = include_str!("shaders/flat_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "PostProcessShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            frame_texture: program.uniform_location(state, "frameTexture")?,
+            bloom_texture: program.uniform_location(state, "bloomTexture")?,
+            exposure_texture: program.uniform_location(state, "exposureTexture")?,
+            lut_texture: program.uniform_location(state, "lutTexture")?,
+            auto_exposure: program.uniform_location(state, "autoExposure")?,
+            exposure: program.uniform_location(state, "exposure")?,
+            bloom_enabled: program.uniform_location(state, "bloomEnabled")?,
+            bloom_intensity: program.uniform_location(state, "bloomIntensity")?,
+            tone_mapping: program.uniform_location(state, "toneMapping")?,
+            color_grading_enabled: program.uniform_location(state, "colorGradingEnabled")?,
+            vignette_enabled: program.uniform_location(state, "vignetteEnabled")?,
+            vignette_intensity: program.uniform_location(state, "vignetteIntensity")?,
+            vignette_radius: program.uniform_location(state, "vignetteRadius")?,
+            vignette_smoothness: program.uniform_location(state, "vignetteSmoothness")?,
+            chromatic_aberration: program.uniform_location(state, "chromaticAberration")?,
+            program,
+        })
+    }
+}
+
+fn make_render_target(
+    state: &mut PipelineState,
+    width: usize,
+    height: usize,
+    pixel_kind: PixelKind,
+    data: Option<&[u8]>,
+) -> Result<FrameBuffer, FrameworkError> {
+    let mut texture = GpuTexture::new(
+        state,
+        GpuTextureKind::Rectangle { width, height },
+        pixel_kind,
+        MinificationFilter::Linear,
+        MagnificationFilter::Linear,
+        1,
+        data,
+    )?;
+    texture
+        .bind_mut(state, 0)
+        .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
+        .set_wrap(Coordinate::T, WrapMode::ClampToEdge);
+
+    FrameBuffer::new(
+        state,
+        None,
+        vec![Attachment {
+            kind: AttachmentKind::Color,
+            texture: Rc::new(RefCell::new(texture)),
+        }],
+    )
+}
+
+fn color_texture(framebuffer: &FrameBuffer) -> Rc<RefCell<GpuTexture>> {
+    framebuffer.color_attachments()[0].texture.clone()
+}
+
+fn frame_matrix(viewport: Rect<i32>) -> Matrix4<f32> {
+    Matrix4::new_orthographic(
+        0.0,
+        viewport.w() as f32,
+        viewport.h() as f32,
+        0.0,
+        -1.0,
+        1.0,
+    ) * Matrix4::new_nonuniform_scaling(&Vector3::new(
+        viewport.w() as f32,
+        viewport.h() as f32,
+        0.0,
+    ))
+}
+
+fn full_screen_draw_parameters() -> DrawParameters {
+    DrawParameters {
+        cull_face: CullFace::Back,
+        culling: false,
+        color_write: Default::default(),
+        depth_write: false,
+        stencil_test: false,
+        depth_test: false,
+        blend: false,
+    }
+}
+
+// Render targets that depend on frame size, one set per scene.
+struct FrameTargets {
+    width: usize,
+    height: usize,
+    // Ping-pong targets in low dynamic range.
+    ldr: [FrameBuffer; 2],
+    // Ping-pong targets of bloom in half resolution.
+    bloom: [FrameBuffer; 2],
+    luminance: FrameBuffer,
+    histogram: FrameBuffer,
+    used: bool,
+}
+
+impl FrameTargets {
+    fn new(state: &mut PipelineState, width: usize, height: usize) -> Result<Self, FrameworkError> {
+        let (bloom_width, bloom_height) = ((width / 2).max(1), (height / 2).max(1));
+        Ok(Self {
+            width,
+            height,
+            ldr: [
+                make_render_target(state, width, height, PixelKind::RGBA8, None)?,
+                make_render_target(state, width, height, PixelKind::RGBA8, None)?,
+            ],
+            bloom: [
+                make_render_target(state, bloom_width, bloom_height, PixelKind::RGBA16F, None)?,
+                make_render_target(state, bloom_width, bloom_height, PixelKind::RGBA16F, None)?,
+            ],
+            luminance: make_render_target(
+                state,
+                LUMINANCE_TEXTURE_SIZE,
+                LUMINANCE_TEXTURE_SIZE,
+                PixelKind::F32,
+                None,
+            )?,
+            histogram: make_render_target(state, HISTOGRAM_BIN_COUNT, 1, PixelKind::F32, None)?,
+            used: true,
+        })
+    }
+}
+
+// Adapted exposure of a camera, it is stored in 1x1 texture and never leaves GPU.
+struct ExposureState {
+    // Ping-pong targets, previous exposure is read from one and written to another.
+    targets: [FrameBuffer; 2],
+    current: usize,
+    last_update: instant::Instant,
+    used: bool,
+}
+
+impl ExposureState {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        // Zero means that there is no previous exposure.
+        let zero = 0.0f32.to_ne_bytes();
+        Ok(Self {
+            targets: [
+                make_render_target(state, 1, 1, PixelKind::F32, Some(&zero))?,
+                make_render_target(state, 1, 1, PixelKind::F32, Some(&zero))?,
+            ],
+            current: 0,
+            last_update: instant::Instant::now(),
+            used: true,
+        })
+    }
+}
+
+pub(in crate) struct PostProcessingContext<'a, 'b> {
+    pub state: &'a mut PipelineState,
+    pub gbuffer: &'a mut GBuffer,
+    pub geometry_cache: &'a mut GeometryCache,
+    pub texture_cache: &'a mut TextureCache,
+    pub scene: &'b Scene,
+    pub scene_handle: Handle<Scene>,
+    pub camera: &'b Camera,
+    pub camera_handle: Handle<Node>,
+    pub white_dummy: Rc<RefCell<GpuTexture>>,
+    pub effects: &'a [Arc<Mutex<dyn PostEffect>>],
+}
+
+pub(in crate) struct PostProcessingRenderer {
+    luminance_shader: LuminanceShader,
+    histogram_shader: HistogramShader,
+    exposure_shader: ExposureShader,
+    bloom_shader: BloomShader,
+    blur_shader: GaussianBlurShader,
+    post_process_shader: PostProcessShader,
+    // Used when there is no color grading table.
+    lut_dummy: Rc<RefCell<GpuTexture>>,
+    quad: SurfaceData,
+    frame_targets: HashMap<Handle<Scene>, FrameTargets>,
+    exposure: HashMap<(Handle<Scene>, Handle<Node>), ExposureState>,
+}
+
+impl PostProcessingRenderer {
+    pub(in crate) fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        Ok(Self {
+            luminance_shader: LuminanceShader::new(state)?,
+            histogram_shader: HistogramShader::new(state)?,
+            exposure_shader: ExposureShader::new(state)?,
+            bloom_shader: BloomShader::new(state)?,
+            blur_shader: GaussianBlurShader::new(state)?,
+            post_process_shader: PostProcessShader::new(state)?,
+            lut_dummy: Rc::new(RefCell::new(GpuTexture::new(
+                state,
+                GpuTextureKind::Volume {
+                    width: 1,
+                    height: 1,
+                    depth: 1,
+                },
+                PixelKind::RGBA8,
+                MinificationFilter::Linear,
+                MagnificationFilter::Linear,
+                1,
+                Some(&[255u8, 255u8, 255u8, 255u8]),
+            )?)),
+            quad: SurfaceData::make_unit_xy_quad(),
+            frame_targets: Default::default(),
+            exposure: Default::default(),
+        })
+    }
+
+    /// Must be called before rendering any scene.
+    pub(in crate) fn begin_frame(&mut self) {
+        for targets in self.frame_targets.values_mut() {
+            targets.used = false;
+        }
+        for exposure in self.exposure.values_mut() {
+            exposure.used = false;
+        }
+    }
+
+    /// Must be called after all scenes are rendered, removes resources of deleted scenes and
+    /// cameras.
+    pub(in crate) fn end_frame(&mut self) {
+        self.frame_targets.retain(|_, targets| targets.used);
+        self.exposure.retain(|_, exposure| exposure.used);
+    }
+
+    // Calculates exposure of current frame and returns 1x1 texture with it.
+    fn update_exposure(
+        &mut self,
+        state: &mut PipelineState,
+        geometry_cache: &mut GeometryCache,
+        frame_texture: Rc<RefCell<GpuTexture>>,
+        key: (Handle<Scene>, Handle<Node>),
+        exposure: Exposure,
+        statistics: &mut RenderPassStatistics,
+    ) -> Result<Rc<RefCell<GpuTexture>>, FrameworkError> {
+        let (key_value, min_luminance, max_luminance) = match exposure {
+            Exposure::Auto {
+                key_value,
+                min_luminance,
+                max_luminance,
+                ..
+            } => (key_value, min_luminance, max_luminance),
+            Exposure::Manual(_) => unreachable!(),
+        };
+
+        let targets = self.frame_targets.get_mut(&key.0).unwrap();
+
+        let exposure_state = match self.exposure.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(ExposureState::new(state)?),
+        };
+        exposure_state.used = true;
+
+        let now = instant::Instant::now();
+        let dt = now.duration_since(exposure_state.last_update).as_secs_f32();
+        exposure_state.last_update = now;
+        let adaptation = exposure.adaptation(dt);
+
+        // Downsample frame and convert it to log luminance.
+        let viewport = Rect::new(
+            0,
+            0,
+            LUMINANCE_TEXTURE_SIZE as i32,
+            LUMINANCE_TEXTURE_SIZE as i32,
+        );
+        let shader = &self.luminance_shader;
+        *statistics += targets.luminance.draw(
+            geometry_cache.get(state, &self.quad),
+            state,
+            viewport,
+            &shader.program,
+            &full_screen_draw_parameters(),
+            |program_binding| {
+                program_binding
+                    .set_matrix4(&shader.wvp_matrix, &frame_matrix(viewport))
+                    .set_texture(&shader.frame_texture, &frame_texture);
+            },
+        );
+
+        // Build histogram.
+        let viewport = Rect::new(0, 0, HISTOGRAM_BIN_COUNT as i32, 1);
+        let luminance_texture = color_texture(&targets.luminance);
+        let shader = &self.histogram_shader;
+        *statistics += targets.histogram.draw(
+            geometry_cache.get(state, &self.quad),
+            state,
+            viewport,
+            &shader.program,
+            &full_screen_draw_parameters(),
+            |program_binding| {
+                program_binding
+                    .set_matrix4(&shader.wvp_matrix, &frame_matrix(viewport))
+                    .set_texture(&shader.luminance_texture, &luminance_texture)
+                    .set_float(&shader.min_log_luminance, MIN_LOG_LUMINANCE)
+                    .set_float(&shader.log_luminance_range, LOG_LUMINANCE_RANGE)
+                    .set_integer(&shader.bin_count, HISTOGRAM_BIN_COUNT as i32);
+            },
+        );
+
+        // Adapt exposure.
+        let viewport = Rect::new(0, 0, 1, 1);
+        let histogram_texture = color_texture(&targets.histogram);
+        let previous_exposure = color_texture(&exposure_state.targets[exposure_state.current]);
+        exposure_state.current = 1 - exposure_state.current;
+        let shader = &self.exposure_shader;
+        *statistics += exposure_state.targets[exposure_state.current].draw(
+            geometry_cache.get(state, &self.quad),
+            state,
+            viewport,
+            &shader.program,
+            &full_screen_draw_parameters(),
+            |program_binding| {
+                program_binding
+                    .set_matrix4(&shader.wvp_matrix, &frame_matrix(viewport))
+                    .set_texture(&shader.histogram_texture, &histogram_texture)
+                    .set_texture(&shader.previous_exposure_texture, &previous_exposure)
+                    .set_float(&shader.min_log_luminance, MIN_LOG_LUMINANCE)
+                    .set_float(&shader.log_luminance_range, LOG_LUMINANCE_RANGE)
+                    .set_integer(&shader.bin_count, HISTOGRAM_BIN_COUNT as i32)
+                    .set_float(&shader.key_value, key_value)
+                    .set_float(&shader.min_luminance, min_luminance)
+                    .set_float(&shader.max_luminance, max_luminance)
+                    .set_float(&shader.adaptation, adaptation);
+            },
+        );
+
+        Ok(color_texture(
+            &exposure_state.targets[exposure_state.current],
+        ))
+    }
+
+    /// Applies post-processing chain of a camera to the frame in the G-Buffer. Does nothing if
+    /// post-processing of the camera does not change the image and there is no custom effects.
+    pub(in crate) fn render(
+        &mut self,
+        args: PostProcessingContext,
+    ) -> Result<RenderPassStatistics, FrameworkError> {
+        scope_profile!();
+
+        let mut statistics = RenderPassStatistics::default();
+
+        let PostProcessingContext {
+            state,
+            gbuffer,
+            geometry_cache,
+            texture_cache,
+            scene,
+            scene_handle,
+            camera,
+            camera_handle,
+            white_dummy,
+            effects,
+        } = args;
+
+        let settings = camera.post_processing();
+        if settings.is_identity() && effects.is_empty() {
+            return Ok(statistics);
+        }
+
+        let (width, height) = (gbuffer.width as usize, gbuffer.height as usize);
+        match self.frame_targets.entry(scene_handle) {
+            Entry::Occupied(entry) => {
+                let targets = entry.into_mut();
+                if targets.width != width || targets.height != height {
+                    *targets = FrameTargets::new(state, width, height)?;
+                }
+                targets.used = true;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(FrameTargets::new(state, width, height)?);
+            }
+        }
+
+        let frame_texture = gbuffer.frame_texture();
+
+        let (auto_exposure, exposure, exposure_texture) = match settings.exposure {
+            Exposure::Manual(exposure) => (false, exposure, white_dummy.clone()),
+            Exposure::Auto { .. } => (
+                true,
+                1.0,
+                self.update_exposure(
+                    state,
+                    geometry_cache,
+                    frame_texture.clone(),
+                    (scene_handle, camera_handle),
+                    settings.exposure,
+                    &mut statistics,
+                )?,
+            ),
+        };
+
+        let targets = self.frame_targets.get_mut(&scene_handle).unwrap();
+
+        let bloom_texture = if settings.bloom.enabled {
+            let bloom_viewport =
+                Rect::new(0, 0, (width / 2).max(1) as i32, (height / 2).max(1) as i32);
+            let bloom_matrix = frame_matrix(bloom_viewport);
+
+            // Extract bright parts.
+            let shader = &self.bloom_shader;
+            statistics += targets.bloom[0].draw(
+                geometry_cache.get(state, &self.quad),
+                state,
+                bloom_viewport,
+                &shader.program,
+                &full_screen_draw_parameters(),
+                |program_binding| {
+                    program_binding
+                        .set_matrix4(&shader.wvp_matrix, &bloom_matrix)
+                        .set_texture(&shader.frame_texture, &frame_texture)
+                        .set_texture(&shader.exposure_texture, &exposure_texture)
+                        .set_bool(&shader.auto_exposure, auto_exposure)
+                        .set_float(&shader.exposure, exposure)
+                        .set_float(&shader.threshold, settings.bloom.threshold);
+                },
+            );
+
+            // Blur them, each pass is horizontal and vertical blur.
+            let shader = &self.blur_shader;
+            for _ in 0..settings.bloom.blur_passes {
+                for (source, destination, direction) in [
+                    (0, 1, Vector2::new(1.0, 0.0)),
+                    (1, 0, Vector2::new(0.0, 1.0)),
+                ]
+                .iter()
+                {
+                    let input = color_texture(&targets.bloom[*source]);
+                    statistics += targets.bloom[*destination].draw(
+                        geometry_cache.get(state, &self.quad),
+                        state,
+                        bloom_viewport,
+                        &shader.program,
+                        &full_screen_draw_parameters(),
+                        |program_binding| {
+                            program_binding
+                                .set_matrix4(&shader.wvp_matrix, &bloom_matrix)
+                                .set_texture(&shader.input_texture, &input)
+                                .set_vector2(&shader.direction, direction);
+                        },
+                    );
+                }
+            }
+
+            color_texture(&targets.bloom[0])
+        } else {
+            white_dummy
+        };
+
+        let lut = settings
+            .color_grading_lut
+            .as_ref()
+            .and_then(|lut| texture_cache.get(state, lut));
+
+        let viewport = Rect::new(0, 0, width as i32, height as i32);
+
+        let shader = &self.post_process_shader;
+        let lut_dummy = &self.lut_dummy;
+        statistics += targets.ldr[0].draw(
+            geometry_cache.get(state, &self.quad),
+            state,
+            viewport,
+            &shader.program,
+            &full_screen_draw_parameters(),
+            |program_binding| {
+                program_binding
+                    .set_matrix4(&shader.wvp_matrix, &frame_matrix(viewport))
+                    .set_texture(&shader.frame_texture, &frame_texture)
+                    .set_texture(&shader.bloom_texture, &bloom_texture)
+                    .set_texture(&shader.exposure_texture, &exposure_texture)
+                    .set_texture(&shader.lut_texture, lut.as_ref().unwrap_or(lut_dummy))
+                    .set_bool(&shader.auto_exposure, auto_exposure)
+                    .set_float(&shader.exposure, exposure)
+                    .set_bool(&shader.bloom_enabled, settings.bloom.enabled)
+                    .set_float(&shader.bloom_intensity, settings.bloom.intensity)
+                    .set_integer(&shader.tone_mapping, settings.tone_mapping as i32)
+                    .set_bool(&shader.color_grading_enabled, lut.is_some())
+                    .set_bool(&shader.vignette_enabled, settings.vignette.enabled)
+                    .set_float(&shader.vignette_intensity, settings.vignette.intensity)
+                    .set_float(&shader.vignette_radius, settings.vignette.radius)
+                    .set_float(&shader.vignette_smoothness, settings.vignette.smoothness)
+                    .set_float(&shader.chromatic_aberration, settings.chromatic_aberration);
+            },
+        );
+
+        let mut current = 0;
+        for effect in effects {
+            let (input, output) = if current == 0 {
+                let (input, output) = targets.ldr.split_at_mut(1);
+                (&input[0], &mut output[0])
+            } else {
+                let (output, input) = targets.ldr.split_at_mut(1);
+                (&input[0], &mut output[0])
+            };
+
+            statistics += effect.lock().unwrap().render(PostEffectContext {
+                pipeline_state: state,
+                texture_cache,
+                geometry_cache,
+                input_texture: color_texture(input),
+                depth_texture: gbuffer.depth(),
+                framebuffer: output,
+                viewport,
+                scene,
+                camera,
+                scene_handle,
+            })?;
+
+            current = 1 - current;
+        }
+
+        // Put result back in the frame, so the rest of the pipeline is not aware of
+        // post-processing.
+        state.blit_framebuffer(
+            targets.ldr[current].id(),
+            gbuffer.final_frame.id(),
+            width as i32,
+            height as i32,
+            glow::COLOR_BUFFER_BIT,
+        );
+
+        Ok(statistics)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector3, math::Rect, pool::Handle},
+        renderer::{
+            framework::{
+                framebuffer::FrameBuffer,
+                gpu_texture::{
+                    GpuTexture, GpuTextureKind, MagnificationFilter, MinificationFilter, PixelKind,
+                },
+                state::{with_test_state, PipelineState},
+            },
+            post_processing::{
+                color_texture, frame_matrix, full_screen_draw_parameters, make_render_target,
+                FrameTargets, PostProcessingRenderer, HISTOGRAM_BIN_COUNT, LOG_LUMINANCE_RANGE,
+                MIN_LOG_LUMINANCE,
+            },
+            GeometryCache,
+        },
+        scene::post_processing::{Exposure, ToneMapping},
+    };
+    use glow::HasContext;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn rgba32f_target(state: &mut PipelineState, width: usize, color: Vector3<f32>) -> FrameBuffer {
+        let bytes = (0..width)
+            .flat_map(|_| vec![color.x, color.y, color.z, 1.0])
+            .flat_map(|c| c.to_ne_bytes().to_vec())
+            .collect::<Vec<_>>();
+        make_render_target(state, width, 1, PixelKind::RGBA32F, Some(&bytes)).unwrap()
+    }
+
+    fn f32_target(state: &mut PipelineState, values: &[f32]) -> FrameBuffer {
+        let bytes = values
+            .iter()
+            .flat_map(|c| c.to_ne_bytes().to_vec())
+            .collect::<Vec<_>>();
+        make_render_target(state, values.len(), 1, PixelKind::F32, Some(&bytes)).unwrap()
+    }
+
+    fn read_pixel(state: &mut PipelineState, framebuffer: &FrameBuffer) -> [f32; 4] {
+        state.set_framebuffer(framebuffer.id());
+        let mut bytes = [0u8; 16];
+        unsafe {
+            state.gl.read_pixels(
+                0,
+                0,
+                1,
+                1,
+                glow::RGBA,
+                glow::FLOAT,
+                glow::PixelPackData::Slice(&mut bytes),
+            );
+        }
+        let mut pixel = [0.0; 4];
+        for (i, chunk) in bytes.chunks_exact(4).enumerate() {
+            pixel[i] = f32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        pixel
+    }
+
+    fn assert_color(actual: [f32; 4], expected: Vector3<f32>, eps: f32) {
+        for i in 0..3 {
+            assert!(
+                (actual[i] - expected[i]).abs() <= eps,
+                "expected {:?}, got {:?}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    struct Composition {
+        frame: Vector3<f32>,
+        exposure: Exposure,
+        auto_exposure_value: f32,
+        bloom: Option<(Vector3<f32>, f32)>,
+        tone_mapping: ToneMapping,
+        lut: Option<Rc<RefCell<GpuTexture>>>,
+    }
+
+    impl Default for Composition {
+        fn default() -> Self {
+            Self {
+                frame: Vector3::new(0.25, 0.5, 0.75),
+                exposure: Exposure::Manual(1.0),
+                auto_exposure_value: 1.0,
+                bloom: None,
+                tone_mapping: ToneMapping::None,
+                lut: None,
+            }
+        }
+    }
+
+    // Runs final pass of the chain for a single pixel.
+    fn compose(
+        state: &mut PipelineState,
+        renderer: &PostProcessingRenderer,
+        composition: Composition,
+    ) -> [f32; 4] {
+        let mut geometry_cache = GeometryCache::default();
+        let frame = rgba32f_target(state, 1, composition.frame);
+        let (bloom_color, bloom_intensity) = composition.bloom.unwrap_or_default();
+        let bloom = rgba32f_target(state, 1, bloom_color);
+        let exposure = f32_target(state, &[composition.auto_exposure_value]);
+        let mut output = rgba32f_target(state, 1, Vector3::default());
+
+        let (auto_exposure, manual_exposure) = match composition.exposure {
+            Exposure::Manual(exposure) => (false, exposure),
+            Exposure::Auto { .. } => (true, 1.0),
+        };
+
+        let viewport = Rect::new(0, 0, 1, 1);
+        let shader = &renderer.post_process_shader;
+        output.draw(
+            geometry_cache.get(state, &renderer.quad),
+            state,
+            viewport,
+            &shader.program,
+            &full_screen_draw_parameters(),
+            |program_binding| {
+                program_binding
+                    .set_matrix4(&shader.wvp_matrix, &frame_matrix(viewport))
+                    .set_texture(&shader.frame_texture, &color_texture(&frame))
+                    .set_texture(&shader.bloom_texture, &color_texture(&bloom))
+                    .set_texture(&shader.exposure_texture, &color_texture(&exposure))
+                    .set_texture(
+                        &shader.lut_texture,
+                        composition.lut.as_ref().unwrap_or(&renderer.lut_dummy),
+                    )
+                    .set_bool(&shader.auto_exposure, auto_exposure)
+                    .set_float(&shader.exposure, manual_exposure)
+                    .set_bool(&shader.bloom_enabled, composition.bloom.is_some())
+                    .set_float(&shader.bloom_intensity, bloom_intensity)
+                    .set_integer(&shader.tone_mapping, composition.tone_mapping as i32)
+                    .set_bool(&shader.color_grading_enabled, composition.lut.is_some())
+                    .set_bool(&shader.vignette_enabled, false)
+                    .set_float(&shader.vignette_intensity, 0.0)
+                    .set_float(&shader.vignette_radius, 0.0)
+                    .set_float(&shader.vignette_smoothness, 0.0)
+                    .set_float(&shader.chromatic_aberration, 0.0);
+            },
+        );
+        assert!(!state.check_error());
+
+        read_pixel(state, &output)
+    }
+
+    #[test]
+    #[ignore = "requires OpenGL 3.3 context"]
+    fn tone_mapping_operators() {
+        with_test_state(|state| {
+            let renderer = PostProcessingRenderer::new(state).unwrap();
+
+            let frame = Vector3::new(0.5, 1.0, 4.0);
+
+            // Shader must use the same curves as CPU implementation.
+            for tone_mapping in [
+                ToneMapping::None,
+                ToneMapping::Reinhard,
+                ToneMapping::Aces,
+                ToneMapping::Uncharted2,
+            ]
+            .iter()
+            {
+                let pixel = compose(
+                    state,
+                    &renderer,
+                    Composition {
+                        frame,
+                        tone_mapping: *tone_mapping,
+                        ..Default::default()
+                    },
+                );
+                assert_color(pixel, tone_mapping.map(frame), 1.0e-3);
+            }
+        });
+    }
+
+    #[test]
+    #[ignore = "requires OpenGL 3.3 context"]
+    fn exposure_and_bloom_composition() {
+        with_test_state(|state| {
+            let renderer = PostProcessingRenderer::new(state).unwrap();
+
+            // Manual exposure is applied before tone mapping.
+            let pixel = compose(
+                state,
+                &renderer,
+                Composition {
+                    frame: Vector3::new(0.5, 0.5, 0.5),
+                    exposure: Exposure::Manual(2.0),
+                    tone_mapping: ToneMapping::Reinhard,
+                    ..Default::default()
+                },
+            );
+            assert_color(pixel, Vector3::new(0.5, 0.5, 0.5), 1.0e-4);
+
+            // Auto exposure is taken from exposure texture, manual value is ignored.
+            let pixel = compose(
+                state,
+                &renderer,
+                Composition {
+                    frame: Vector3::new(0.1, 0.2, 0.3),
+                    exposure: Exposure::auto(),
+                    auto_exposure_value: 3.0,
+                    ..Default::default()
+                },
+            );
+            assert_color(pixel, Vector3::new(0.3, 0.6, 0.9), 1.0e-4);
+
+            // Bloom is added to the exposed frame with intensity.
+            let pixel = compose(
+                state,
+                &renderer,
+                Composition {
+                    frame: Vector3::new(0.1, 0.1, 0.1),
+                    exposure: Exposure::Manual(2.0),
+                    bloom: Some((Vector3::new(0.4, 0.2, 0.0), 0.5)),
+                    ..Default::default()
+                },
+            );
+            assert_color(pixel, Vector3::new(0.4, 0.3, 0.2), 1.0e-4);
+        });
+    }
+
+    #[test]
+    #[ignore = "requires OpenGL 3.3 context"]
+    fn color_grading() {
+        with_test_state(|state| {
+            let renderer = PostProcessingRenderer::new(state).unwrap();
+
+            // 2x2x2 table that inverts colors.
+            let mut inverted = Vec::new();
+            for b in 0..2u8 {
+                for g in 0..2u8 {
+                    for r in 0..2u8 {
+                        inverted.extend_from_slice(&[
+                            255 * (1 - r),
+                            255 * (1 - g),
+                            255 * (1 - b),
+                            255,
+                        ]);
+                    }
+                }
+            }
+            let lut = GpuTexture::new(
+                state,
+                GpuTextureKind::Volume {
+                    width: 2,
+                    height: 2,
+                    depth: 2,
+                },
+                PixelKind::RGBA8,
+                MinificationFilter::Linear,
+                MagnificationFilter::Linear,
+                1,
+                Some(&inverted),
+            )
+            .unwrap();
+
+            let pixel = compose(
+                state,
+                &renderer,
+                Composition {
+                    frame: Vector3::new(0.25, 0.5, 1.0),
+                    lut: Some(Rc::new(RefCell::new(lut))),
+                    ..Default::default()
+                },
+            );
+            // Linear interpolation between texels, 8-bit precision.
+            assert_color(pixel, Vector3::new(0.75, 0.5, 0.0), 1.0 / 128.0);
+        });
+    }
+
+    #[test]
+    #[ignore = "requires OpenGL 3.3 context"]
+    fn bloom_extracts_bright_parts() {
+        with_test_state(|state| {
+            let renderer = PostProcessingRenderer::new(state).unwrap();
+            let mut geometry_cache = GeometryCache::default();
+
+            let mut extract = |color: Vector3<f32>, exposure: f32| {
+                let frame = rgba32f_target(state, 1, color);
+                let exposure_texture = f32_target(state, &[1.0]);
+                let mut output = rgba32f_target(state, 1, Vector3::default());
+                let viewport = Rect::new(0, 0, 1, 1);
+                let shader = &renderer.bloom_shader;
+                output.draw(
+                    geometry_cache.get(state, &renderer.quad),
+                    state,
+                    viewport,
+                    &shader.program,
+                    &full_screen_draw_parameters(),
+                    |program_binding| {
+                        program_binding
+                            .set_matrix4(&shader.wvp_matrix, &frame_matrix(viewport))
+                            .set_texture(&shader.frame_texture, &color_texture(&frame))
+                            .set_texture(
+                                &shader.exposure_texture,
+                                &color_texture(&exposure_texture),
+                            )
+                            .set_bool(&shader.auto_exposure, false)
+                            .set_float(&shader.exposure, exposure)
+                            .set_float(&shader.threshold, 1.0);
+                    },
+                );
+                read_pixel(state, &output)
+            };
+
+            // Dark parts do not glow.
+            assert_color(
+                extract(Vector3::new(0.5, 0.5, 0.5), 1.0),
+                Vector3::default(),
+                1.0e-6,
+            );
+            // Bright parts are passed as is.
+            assert_color(
+                extract(Vector3::new(2.0, 2.0, 2.0), 1.0),
+                Vector3::new(2.0, 2.0, 2.0),
+                1.0e-4,
+            );
+            // Exposure is applied before threshold.
+            assert_color(
+                extract(Vector3::new(0.5, 0.5, 0.5), 4.0),
+                Vector3::new(2.0, 2.0, 2.0),
+                1.0e-4,
+            );
+        });
+    }
+
+    #[test]
+    #[ignore = "requires OpenGL 3.3 context"]
+    fn auto_exposure_adapts_to_average_luminance() {
+        with_test_state(|state| {
+            let mut renderer = PostProcessingRenderer::new(state).unwrap();
+            let mut geometry_cache = GeometryCache::default();
+            renderer
+                .frame_targets
+                .insert(Handle::NONE, FrameTargets::new(state, 4, 4).unwrap());
+
+            // Luminance of the frame is 1.0, it falls into a bin with log luminance near zero.
+            let frame = rgba32f_target(state, 4, Vector3::new(1.0, 1.0, 1.0));
+            let bin = ((0.0 - MIN_LOG_LUMINANCE) / LOG_LUMINANCE_RANGE
+                * (HISTOGRAM_BIN_COUNT - 1) as f32)
+                .round();
+            let average_luminance = 2.0f32.powf(
+                MIN_LOG_LUMINANCE + bin / (HISTOGRAM_BIN_COUNT - 1) as f32 * LOG_LUMINANCE_RANGE,
+            );
+
+            let exposure = Exposure::Auto {
+                key_value: 0.18,
+                min_luminance: 0.01,
+                max_luminance: 100.0,
+                adaptation_speed: 1.0,
+            };
+            let mut statistics = Default::default();
+            let texture = renderer
+                .update_exposure(
+                    state,
+                    &mut geometry_cache,
+                    color_texture(&frame),
+                    (Handle::NONE, Handle::NONE),
+                    exposure,
+                    &mut statistics,
+                )
+                .unwrap();
+            assert!(!state.check_error());
+            assert!(Rc::ptr_eq(
+                &texture,
+                &color_texture(&renderer.exposure[&(Handle::NONE, Handle::NONE)].targets[1])
+            ));
+
+            // There is no previous exposure on first frame, so target exposure is used as is.
+            let exposure_state = &renderer.exposure[&(Handle::NONE, Handle::NONE)];
+            let value = read_pixel(state, &exposure_state.targets[exposure_state.current])[0];
+            assert!((value - exposure.target(average_luminance)).abs() < 1.0e-3);
+
+            // Clamping of average luminance.
+            let texture = renderer
+                .update_exposure(
+                    state,
+                    &mut geometry_cache,
+                    color_texture(&frame),
+                    (Handle::NONE, Handle::NONE),
+                    Exposure::Auto {
+                        key_value: 0.18,
+                        min_luminance: 4.0,
+                        max_luminance: 8.0,
+                        // Instant adaptation.
+                        adaptation_speed: 1.0e9,
+                    },
+                    &mut statistics,
+                )
+                .unwrap();
+            let exposure_state = &renderer.exposure[&(Handle::NONE, Handle::NONE)];
+            assert!(Rc::ptr_eq(
+                &texture,
+                &color_texture(&exposure_state.targets[exposure_state.current])
+            ));
+            let value = read_pixel(state, &exposure_state.targets[exposure_state.current])[0];
+            assert!((value - 0.18 / 4.0).abs() < 1.0e-3);
+        });
+    }
+}
@@ -0,0 +1,384 @@
+//! Captures reflection probes of scenes into cube maps. Each face of a cube map is rendered by
+//! regular deferred pipeline (G-Buffer and lighting) and then copied into the cube map. Only
+//! geometry of deferred render path and skybox are captured, particles, sprites and forward
+//! geometry are not.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, UnitQuaternion, Vector2, Vector3, Vector4},
+        math::frustum::Frustum,
+        pool::Handle,
+        scope_profile,
+    },
+    renderer::{
+        batch::BatchStorage,
+        deferred_light_renderer::{
+            DeferredLightRenderer, DeferredRendererContext, LightingStatistics,
+        },
+        framework::{
+            error::FrameworkError,
+            framebuffer::{Attachment, AttachmentKind, FrameBuffer},
+            gpu_texture::{
+                Coordinate, CubeMapFace, GpuTexture, GpuTextureKind, MagnificationFilter,
+                MinificationFilter, PixelKind, WrapMode,
+            },
+            state::PipelineState,
+        },
+        gbuffer::{GBuffer, GBufferRenderContext},
+        reflection_renderer::{ReflectionProbeInstance, MAX_REFLECTION_PROBES},
+        shader_cache::ShaderCache,
+        GeometryCache, QualitySettings, RenderPassStatistics, TextureCache,
+    },
+    scene::{
+        base::BaseBuilder,
+        camera::{Camera, CameraBuilder},
+        graph::Graph,
+        node::Node,
+        reflection_probe::{ReflectionProbe, ReflectionProbeUpdateMode},
+        Scene,
+    },
+};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{hash_map::Entry, HashMap},
+    rc::Rc,
+};
+
+const FACES: [CubeMapFace; 6] = [
+    CubeMapFace::PositiveX,
+    CubeMapFace::NegativeX,
+    CubeMapFace::PositiveY,
+    CubeMapFace::NegativeY,
+    CubeMapFace::PositiveZ,
+    CubeMapFace::NegativeZ,
+];
+
+// Returns look and up vectors of a camera that renders given face of a cube map.
+fn face_basis(face: CubeMapFace) -> (Vector3<f32>, Vector3<f32>) {
+    match face {
+        CubeMapFace::PositiveX => (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+        CubeMapFace::NegativeX => (Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 0.0)),
+        CubeMapFace::PositiveY => (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+        CubeMapFace::NegativeY => (Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 0.0, -1.0)),
+        CubeMapFace::PositiveZ => (Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, -1.0, 0.0)),
+        CubeMapFace::NegativeZ => (Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, -1.0, 0.0)),
+    }
+}
+
+struct CapturedProbe {
+    // Single color attachment - cube map.
+    framebuffer: FrameBuffer,
+    resolution: u32,
+    capture_generation: u64,
+    captured: bool,
+    used: bool,
+}
+
+impl CapturedProbe {
+    fn new(state: &mut PipelineState, resolution: u32) -> Result<Self, FrameworkError> {
+        let mut cube_map = GpuTexture::new(
+            state,
+            GpuTextureKind::Cube {
+                width: resolution as usize,
+                height: resolution as usize,
+            },
+            PixelKind::RGBA8,
+            MinificationFilter::Linear,
+            MagnificationFilter::Linear,
+            1,
+            None,
+        )?;
+        cube_map
+            .bind_mut(state, 0)
+            .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
+            .set_wrap(Coordinate::T, WrapMode::ClampToEdge)
+            .set_wrap(Coordinate::R, WrapMode::ClampToEdge);
+
+        Ok(Self {
+            framebuffer: FrameBuffer::new(
+                state,
+                None,
+                vec![Attachment {
+                    kind: AttachmentKind::Color,
+                    texture: Rc::new(RefCell::new(cube_map)),
+                }],
+            )?,
+            resolution,
+            capture_generation: 0,
+            captured: false,
+            used: true,
+        })
+    }
+
+    fn texture(&self) -> Rc<RefCell<GpuTexture>> {
+        self.framebuffer.color_attachments()[0].texture.clone()
+    }
+
+    fn need_capture(&self, probe: &ReflectionProbe) -> bool {
+        !self.captured
+            || self.capture_generation != probe.capture_generation()
+            || probe.update_mode() == ReflectionProbeUpdateMode::EachFrame
+    }
+}
+
+pub(in crate) struct ReflectionProbeCaptureContext<'a> {
+    pub state: &'a mut PipelineState,
+    pub scene: &'a Scene,
+    pub scene_handle: Handle<Scene>,
+    pub deferred_light_renderer: &'a mut DeferredLightRenderer,
+    pub geometry_cache: &'a mut GeometryCache,
+    pub texture_cache: &'a mut TextureCache,
+    pub shader_cache: &'a mut ShaderCache,
+    pub batch_storage: &'a BatchStorage,
+    pub white_dummy: Rc<RefCell<GpuTexture>>,
+    pub environment_dummy: Rc<RefCell<GpuTexture>>,
+    pub settings: &'a QualitySettings,
+}
+
+#[derive(Default)]
+pub struct ReflectionProbeRenderer {
+    // G-Buffer for capturing, it is re-created when resolution of a probe differs.
+    gbuffer: Option<GBuffer>,
+    probes: HashMap<(Handle<Scene>, Handle<Node>), CapturedProbe>,
+}
+
+impl ReflectionProbeRenderer {
+    /// Must be called before capturing probes of any scene.
+    pub(in crate) fn begin_frame(&mut self) {
+        for probe in self.probes.values_mut() {
+            probe.used = false;
+        }
+    }
+
+    /// Must be called after all scenes are rendered, removes cube maps of deleted probes.
+    pub(in crate) fn end_frame(&mut self) {
+        self.probes.retain(|_, probe| probe.used);
+    }
+
+    /// Captures every probe of a scene that needs to be captured.
+    pub(in crate) fn update(
+        &mut self,
+        args: ReflectionProbeCaptureContext,
+    ) -> Result<(RenderPassStatistics, LightingStatistics), FrameworkError> {
+        scope_profile!();
+
+        let mut pass_stats = RenderPassStatistics::default();
+        let mut light_stats = LightingStatistics::default();
+
+        let ReflectionProbeCaptureContext {
+            state,
+            scene,
+            scene_handle,
+            deferred_light_renderer,
+            geometry_cache,
+            texture_cache,
+            shader_cache,
+            batch_storage,
+            white_dummy,
+            environment_dummy,
+            settings,
+        } = args;
+
+        // Probes do not reflect each other and screen-space effects are useless for them.
+        let capture_settings = QualitySettings {
+            use_ssao: false,
+            use_ssr: false,
+            light_scatter_enabled: false,
+            ..*settings
+        };
+
+        // Skybox and environment are taken from first active camera.
+        let source_camera = scene.graph.linear_iter().find_map(|node| match node {
+            Node::Camera(camera) if camera.is_enabled() => Some(camera),
+            _ => None,
+        });
+
+        for (handle, probe) in scene.graph.pair_iter().filter_map(|(handle, node)| {
+            if let Node::ReflectionProbe(probe) = node {
+                Some((handle, probe))
+            } else {
+                None
+            }
+        }) {
+            if !probe.global_visibility() {
+                continue;
+            }
+
+            let resolution = probe.resolution();
+            let captured_probe = match self.probes.entry((scene_handle, handle)) {
+                Entry::Occupied(entry) => {
+                    let captured_probe = entry.into_mut();
+                    if captured_probe.resolution != resolution {
+                        *captured_probe = CapturedProbe::new(state, resolution)?;
+                    }
+                    captured_probe
+                }
+                Entry::Vacant(entry) => entry.insert(CapturedProbe::new(state, resolution)?),
+            };
+            captured_probe.used = true;
+
+            if !captured_probe.need_capture(probe) {
+                continue;
+            }
+            captured_probe.captured = true;
+            captured_probe.capture_generation = probe.capture_generation();
+
+            if self
+                .gbuffer
+                .as_ref()
+                .map_or(true, |gbuffer| gbuffer.width != resolution as i32)
+            {
+                self.gbuffer = Some(GBuffer::new(
+                    state,
+                    resolution as usize,
+                    resolution as usize,
+                )?);
+            }
+            let gbuffer = self.gbuffer.as_mut().unwrap();
+
+            let position = probe.global_position();
+
+            for &face in FACES.iter() {
+                let mut camera = make_capture_camera(probe, source_camera, face);
+                camera.calculate_matrices(Vector2::new(resolution as f32, resolution as f32));
+                let frustum = Frustum::from(camera.view_projection_matrix()).unwrap_or_default();
+                camera.visibility_cache.update(
+                    &scene.graph,
+                    position,
+                    probe.z_near(),
+                    probe.z_far(),
+                    Some(&[&frustum]),
+                );
+
+                pass_stats += gbuffer.fill(GBufferRenderContext {
+                    state,
+                    camera: &camera,
+                    geom_cache: geometry_cache,
+                    batch_storage,
+                    use_parallax_mapping: capture_settings.use_parallax_mapping,
+                    shader_cache,
+                });
+
+                let (face_pass_stats, face_light_stats) =
+                    deferred_light_renderer.render(DeferredRendererContext {
+                        state,
+                        scene,
+                        camera: &camera,
+                        gbuffer,
+                        white_dummy: white_dummy.clone(),
+                        environment_dummy: environment_dummy.clone(),
+                        ambient_color: scene.ambient_lighting_color,
+                        settings: &capture_settings,
+                        textures: texture_cache,
+                        geometry_cache,
+                        batch_storage,
+                        reflection_probes: &[],
+                    });
+                pass_stats += face_pass_stats;
+                light_stats += face_light_stats;
+
+                captured_probe.framebuffer.set_cubemap_face(state, 0, face);
+                state.blit_framebuffer(
+                    gbuffer.final_frame.id(),
+                    captured_probe.framebuffer.id(),
+                    gbuffer.width,
+                    gbuffer.height,
+                    glow::COLOR_BUFFER_BIT,
+                );
+            }
+        }
+
+        Ok((pass_stats, light_stats))
+    }
+
+    /// Returns captured probes of a scene that intersect view frustum of a camera. If there are
+    /// more than [MAX_REFLECTION_PROBES] such probes, nearest to the camera are returned.
+    pub(in crate) fn visible_probes(
+        &self,
+        scene_handle: Handle<Scene>,
+        graph: &Graph,
+        camera: &Camera,
+    ) -> Vec<ReflectionProbeInstance> {
+        let frustum = Frustum::from(camera.view_projection_matrix()).unwrap_or_default();
+        let camera_position = camera.global_position();
+
+        let mut probes = graph
+            .pair_iter()
+            .filter_map(|(handle, node)| {
+                let probe = match node {
+                    Node::ReflectionProbe(probe) if probe.global_visibility() => probe,
+                    _ => return None,
+                };
+
+                let captured_probe = self.probes.get(&(scene_handle, handle))?;
+                if !captured_probe.captured {
+                    return None;
+                }
+
+                let bounds = probe.world_bounds();
+                if !frustum.is_intersects_aabb(&bounds) {
+                    return None;
+                }
+
+                // Zero if camera is inside the volume.
+                let distance = (camera_position.sup(&bounds.min).inf(&bounds.max)
+                    - camera_position)
+                    .norm_squared();
+
+                Some((
+                    distance,
+                    ReflectionProbeInstance {
+                        position: probe.global_position(),
+                        bounds_min: bounds.min,
+                        bounds_max: bounds.max,
+                        parameters: Vector4::new(
+                            probe.blend_distance(),
+                            probe.intensity(),
+                            if probe.is_box_projection() { 1.0 } else { 0.0 },
+                            0.0,
+                        ),
+                        texture: captured_probe.texture(),
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        probes.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        probes
+            .into_iter()
+            .take(MAX_REFLECTION_PROBES)
+            .map(|(_, probe)| probe)
+            .collect()
+    }
+}
+
+fn make_capture_camera(
+    probe: &ReflectionProbe,
+    source_camera: Option<&Camera>,
+    face: CubeMapFace,
+) -> Camera {
+    let mut builder = CameraBuilder::new(BaseBuilder::new())
+        .with_fov(std::f32::consts::FRAC_PI_2)
+        .with_z_near(probe.z_near())
+        .with_z_far(probe.z_far());
+    if let Some(source_camera) = source_camera {
+        if let Some(skybox) = source_camera.skybox_ref() {
+            builder = builder.with_skybox(skybox.clone());
+        }
+        if let Some(environment) = source_camera.environment_map() {
+            builder = builder.with_environment(environment);
+        }
+    }
+    let camera = builder.build_camera();
+
+    // Camera is not a part of a graph, so its global transform is set directly.
+    let (look, up) = face_basis(face);
+    camera.global_transform.set(
+        Matrix4::new_translation(&probe.global_position())
+            * UnitQuaternion::face_towards(&look, &up).to_homogeneous(),
+    );
+
+    camera
+}
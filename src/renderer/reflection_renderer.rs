@@ -0,0 +1,276 @@
+//! Reflection pass of deferred renderer. It blends reflections from reflection probes, camera
+//! environment map and (optionally) screen-space reflections with lit frame using reflection
+//! factor from G-Buffer.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Vector3, Vector4},
+        math::Rect,
+        scope_profile,
+    },
+    renderer::{
+        framework::{
+            error::FrameworkError,
+            framebuffer::{Attachment, AttachmentKind, CullFace, DrawParameters, FrameBuffer},
+            gpu_program::{GpuProgram, UniformLocation},
+            gpu_texture::{
+                Coordinate, GpuTexture, GpuTextureKind, MagnificationFilter, MinificationFilter,
+                PixelKind, WrapMode,
+            },
+            state::PipelineState,
+        },
+        gbuffer::GBuffer,
+        GeometryCache, QualitySettings, RenderPassStatistics,
+    },
+    scene::mesh::surface::SurfaceData,
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// Maximum amount of reflection probes that can affect a frame. Keep in sync with shader define.
+pub const MAX_REFLECTION_PROBES: usize = 4;
+
+/// Captured reflection probe prepared for rendering.
+pub(in crate) struct ReflectionProbeInstance {
+    pub position: Vector3<f32>,
+    pub bounds_min: Vector3<f32>,
+    pub bounds_max: Vector3<f32>,
+    /// x - blend distance, y - intensity, z - box projection flag.
+    pub parameters: Vector4<f32>,
+    pub texture: Rc<RefCell<GpuTexture>>,
+}
+
+struct ReflectionShader {
+    program: GpuProgram,
+    wvp_matrix: UniformLocation,
+    depth_texture: UniformLocation,
+    normal_texture: UniformLocation,
+    material_texture: UniformLocation,
+    frame_texture: UniformLocation,
+    environment_map: UniformLocation,
+    probe_textures: [UniformLocation; MAX_REFLECTION_PROBES],
+    inv_view_proj_matrix: UniformLocation,
+    view_proj_matrix: UniformLocation,
+    camera_position: UniformLocation,
+    probe_count: UniformLocation,
+    probe_positions: UniformLocation,
+    probe_bounds_min: UniformLocation,
+    probe_bounds_max: UniformLocation,
+    probe_parameters: UniformLocation,
+    ssr_enabled: UniformLocation,
+    ssr_steps: UniformLocation,
+    ssr_max_distance: UniformLocation,
+}
+
+impl ReflectionShader {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("shaders/reflection_fs.glsl");
+        let vertex_source = include_str!("shaders/deferred_light_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "ReflectionShader", vertex_source, fragment_source)?;
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            depth_texture: program.uniform_location(state, "depthTexture")?,
+            normal_texture: program.uniform_location(state, "normalTexture")?,
+            material_texture: program.uniform_location(state, "materialTexture")?,
+            frame_texture: program.uniform_location(state, "frameTexture")?,
+            environment_map: program.uniform_location(state, "environmentMap")?,
+            probe_textures: [
+                program.uniform_location(state, "probeTexture0")?,
+                program.uniform_location(state, "probeTexture1")?,
+                program.uniform_location(state, "probeTexture2")?,
+                program.uniform_location(state, "probeTexture3")?,
+            ],
+            inv_view_proj_matrix: program.uniform_location(state, "invViewProj")?,
+            view_proj_matrix: program.uniform_location(state, "viewProj")?,
+            camera_position: program.uniform_location(state, "cameraPosition")?,
+            probe_count: program.uniform_location(state, "probeCount")?,
+            probe_positions: program.uniform_location(state, "probePositions")?,
+            probe_bounds_min: program.uniform_location(state, "probeBoundsMin")?,
+            probe_bounds_max: program.uniform_location(state, "probeBoundsMax")?,
+            probe_parameters: program.uniform_location(state, "probeParameters")?,
+            ssr_enabled: program.uniform_location(state, "ssrEnabled")?,
+            ssr_steps: program.uniform_location(state, "ssrSteps")?,
+            ssr_max_distance: program.uniform_location(state, "ssrMaxDistance")?,
+            program,
+        })
+    }
+}
+
+fn make_frame_copy(
+    state: &mut PipelineState,
+    width: usize,
+    height: usize,
+) -> Result<FrameBuffer, FrameworkError> {
+    let mut texture = GpuTexture::new(
+        state,
+        GpuTextureKind::Rectangle { width, height },
+        PixelKind::RGBA16F,
+        MinificationFilter::Linear,
+        MagnificationFilter::Linear,
+        1,
+        None,
+    )?;
+    texture
+        .bind_mut(state, 0)
+        .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
+        .set_wrap(Coordinate::T, WrapMode::ClampToEdge);
+
+    FrameBuffer::new(
+        state,
+        None,
+        vec![Attachment {
+            kind: AttachmentKind::Color,
+            texture: Rc::new(RefCell::new(texture)),
+        }],
+    )
+}
+
+pub(in crate) struct ReflectionRenderContext<'a> {
+    pub state: &'a mut PipelineState,
+    pub gbuffer: &'a mut GBuffer,
+    pub geometry_cache: &'a mut GeometryCache,
+    pub quad: &'a SurfaceData,
+    pub frame_matrix: Matrix4<f32>,
+    pub view_projection: Matrix4<f32>,
+    pub inv_view_projection: Matrix4<f32>,
+    pub camera_position: Vector3<f32>,
+    pub environment: Rc<RefCell<GpuTexture>>,
+    pub probes: &'a [ReflectionProbeInstance],
+    pub settings: &'a QualitySettings,
+}
+
+pub struct ReflectionRenderer {
+    shader: ReflectionShader,
+    // Copy of lit frame for screen-space reflections, created on demand.
+    frame_copy: Option<FrameBuffer>,
+    frame_copy_size: (i32, i32),
+}
+
+impl ReflectionRenderer {
+    pub fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        Ok(Self {
+            shader: ReflectionShader::new(state)?,
+            frame_copy: None,
+            frame_copy_size: (0, 0),
+        })
+    }
+
+    fn copy_frame(
+        &mut self,
+        state: &mut PipelineState,
+        gbuffer: &GBuffer,
+    ) -> Result<Rc<RefCell<GpuTexture>>, FrameworkError> {
+        let size = (gbuffer.width, gbuffer.height);
+        if self.frame_copy.is_none() || self.frame_copy_size != size {
+            self.frame_copy = Some(make_frame_copy(state, size.0 as usize, size.1 as usize)?);
+            self.frame_copy_size = size;
+        }
+
+        let frame_copy = self.frame_copy.as_ref().unwrap();
+
+        state.blit_framebuffer(
+            gbuffer.final_frame.id(),
+            frame_copy.id(),
+            size.0,
+            size.1,
+            glow::COLOR_BUFFER_BIT,
+        );
+
+        Ok(frame_copy.color_attachments()[0].texture.clone())
+    }
+
+    #[must_use]
+    pub(in crate) fn render(&mut self, args: ReflectionRenderContext) -> RenderPassStatistics {
+        scope_profile!();
+
+        let ReflectionRenderContext {
+            state,
+            gbuffer,
+            geometry_cache,
+            quad,
+            frame_matrix,
+            view_projection,
+            inv_view_projection,
+            camera_position,
+            environment,
+            probes,
+            settings,
+        } = args;
+
+        // Frame is copied before drawing, because reflection pass reads and writes it.
+        let frame_copy = if settings.use_ssr {
+            self.copy_frame(state, gbuffer).ok()
+        } else {
+            None
+        };
+
+        let probes = &probes[..probes.len().min(MAX_REFLECTION_PROBES)];
+        let mut positions = [Vector3::default(); MAX_REFLECTION_PROBES];
+        let mut bounds_min = [Vector3::default(); MAX_REFLECTION_PROBES];
+        let mut bounds_max = [Vector3::default(); MAX_REFLECTION_PROBES];
+        let mut parameters = [Vector4::default(); MAX_REFLECTION_PROBES];
+        for (i, probe) in probes.iter().enumerate() {
+            positions[i] = probe.position;
+            bounds_min[i] = probe.bounds_min;
+            bounds_max[i] = probe.bounds_max;
+            parameters[i] = probe.parameters;
+        }
+
+        let viewport = Rect::new(0, 0, gbuffer.width, gbuffer.height);
+        let depth_texture = gbuffer.depth();
+        let normal_texture = gbuffer.normal_texture();
+        let material_texture = gbuffer.material_texture();
+        let shader = &self.shader;
+
+        let mut statistics = RenderPassStatistics::default();
+
+        state.set_blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+
+        statistics += gbuffer.final_frame.draw(
+            geometry_cache.get(state, quad),
+            state,
+            viewport,
+            &shader.program,
+            &DrawParameters {
+                cull_face: CullFace::Back,
+                culling: false,
+                color_write: Default::default(),
+                depth_write: false,
+                stencil_test: false,
+                depth_test: false,
+                blend: true,
+            },
+            |program_binding| {
+                let mut program_binding = program_binding
+                    .set_matrix4(&shader.wvp_matrix, &frame_matrix)
+                    .set_matrix4(&shader.inv_view_proj_matrix, &inv_view_projection)
+                    .set_matrix4(&shader.view_proj_matrix, &view_projection)
+                    .set_vector3(&shader.camera_position, &camera_position)
+                    .set_texture(&shader.depth_texture, &depth_texture)
+                    .set_texture(&shader.normal_texture, &normal_texture)
+                    .set_texture(&shader.material_texture, &material_texture)
+                    .set_texture(&shader.environment_map, &environment)
+                    .set_integer(&shader.probe_count, probes.len() as i32)
+                    .set_vector3_slice(&shader.probe_positions, &positions)
+                    .set_vector3_slice(&shader.probe_bounds_min, &bounds_min)
+                    .set_vector3_slice(&shader.probe_bounds_max, &bounds_max)
+                    .set_vector4_slice(&shader.probe_parameters, &parameters)
+                    .set_bool(&shader.ssr_enabled, frame_copy.is_some())
+                    .set_integer(&shader.ssr_steps, settings.ssr_steps.max(1) as i32)
+                    .set_float(&shader.ssr_max_distance, settings.ssr_max_distance)
+                    .set_texture(
+                        &shader.frame_texture,
+                        frame_copy.as_ref().unwrap_or(&material_texture),
+                    );
+
+                // Samplers of unused probes still must be bound to something.
+                for (i, location) in shader.probe_textures.iter().enumerate() {
+                    let texture = probes.get(i).map_or(&environment, |probe| &probe.texture);
+                    program_binding = program_binding.set_texture(location, texture);
+                }
+            },
+        );
+
+        statistics
+    }
+}
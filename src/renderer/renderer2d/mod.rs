@@ -2,7 +2,7 @@
 
 use crate::{
     core::{
-        algebra::{Matrix4, Vector2, Vector4},
+        algebra::{Matrix4, Vector2, Vector3, Vector4},
         math::Rect,
         pool::Handle,
     },
@@ -11,6 +11,10 @@ use crate::{
         framework::{
             error::FrameworkError,
             framebuffer::{Attachment, AttachmentKind, CullFace, DrawParameters, FrameBuffer},
+            geometry_buffer::{
+                AttributeDefinition, AttributeKind, BufferBuilder, ElementKind, GeometryBuffer,
+                GeometryBufferBuilder, GeometryBufferKind,
+            },
             gpu_program::{GpuProgram, UniformLocation},
             gpu_texture::{
                 GpuTexture, GpuTextureKind, MagnificationFilter, MinificationFilter, PixelKind,
@@ -21,7 +25,7 @@ use crate::{
         RenderPassStatistics, TextureCache,
     },
     resource::texture::TextureKind,
-    scene2d::{light::Light, node::Node, Scene2d, Scene2dContainer},
+    scene2d::{light::Light, node::Node, Scene2d, Scene2dContainer, SceneDrawingContext},
     utils::log::{Log, MessageKind},
 };
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
@@ -59,6 +63,122 @@ impl SpriteShader {
     }
 }
 
+#[repr(C)]
+struct LineVertex {
+    position: Vector3<f32>,
+    color: u32,
+}
+
+/// Converts lines of a drawing context into vertices and line indices, each line is a pair of
+/// vertices at Z = 0.
+fn fill_line_buffers(
+    drawing_context: &SceneDrawingContext,
+    vertices: &mut Vec<LineVertex>,
+    line_indices: &mut Vec<[u32; 2]>,
+) {
+    vertices.clear();
+    line_indices.clear();
+
+    let mut i = 0;
+    for line in drawing_context.lines.iter() {
+        let color = line.color.into();
+        vertices.push(LineVertex {
+            position: Vector3::new(line.begin.x, line.begin.y, 0.0),
+            color,
+        });
+        vertices.push(LineVertex {
+            position: Vector3::new(line.end.x, line.end.y, 0.0),
+            color,
+        });
+        line_indices.push([i, i + 1]);
+        i += 2;
+    }
+}
+
+/// Renders lines of a scene drawing context, shares shaders with 3D debug renderer.
+struct LineRenderer {
+    program: GpuProgram,
+    wvp_matrix: UniformLocation,
+    geometry: GeometryBuffer,
+    vertices: Vec<LineVertex>,
+    line_indices: Vec<[u32; 2]>,
+}
+
+impl LineRenderer {
+    fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
+        let fragment_source = include_str!("../shaders/debug_fs.glsl");
+        let vertex_source = include_str!("../shaders/debug_vs.glsl");
+        let program =
+            GpuProgram::from_source(state, "LineShader2D", vertex_source, fragment_source)?;
+
+        let geometry = GeometryBufferBuilder::new(ElementKind::Line)
+            .with_buffer_builder(
+                BufferBuilder::new::<LineVertex>(GeometryBufferKind::DynamicDraw, None)
+                    .with_attribute(AttributeDefinition {
+                        location: 0,
+                        divisor: 0,
+                        kind: AttributeKind::Float3,
+                        normalized: false,
+                    })
+                    .with_attribute(AttributeDefinition {
+                        location: 1,
+                        kind: AttributeKind::UnsignedByte4,
+                        normalized: true,
+                        divisor: 0,
+                    }),
+            )
+            .build(state)?;
+
+        Ok(Self {
+            wvp_matrix: program.uniform_location(state, "worldViewProjection")?,
+            program,
+            geometry,
+            vertices: Default::default(),
+            line_indices: Default::default(),
+        })
+    }
+
+    fn render(
+        &mut self,
+        state: &mut PipelineState,
+        viewport: Rect<i32>,
+        framebuffer: &mut FrameBuffer,
+        drawing_context: &SceneDrawingContext,
+        view_projection: &Matrix4<f32>,
+    ) -> RenderPassStatistics {
+        let mut statistics = RenderPassStatistics::default();
+
+        if drawing_context.lines.is_empty() {
+            return statistics;
+        }
+
+        fill_line_buffers(drawing_context, &mut self.vertices, &mut self.line_indices);
+        self.geometry.set_buffer_data(state, 0, &self.vertices);
+        self.geometry.bind(state).set_lines(&self.line_indices);
+
+        statistics += framebuffer.draw(
+            &self.geometry,
+            state,
+            viewport,
+            &self.program,
+            &DrawParameters {
+                cull_face: CullFace::Back,
+                culling: false,
+                color_write: Default::default(),
+                depth_write: false,
+                stencil_test: false,
+                depth_test: false,
+                blend: false,
+            },
+            |program_binding| {
+                program_binding.set_matrix4(&self.wvp_matrix, view_projection);
+            },
+        );
+
+        statistics
+    }
+}
+
 struct RenderTarget {
     width: u32,
     height: u32,
@@ -99,6 +219,7 @@ impl RenderTarget {
 
 pub(in crate) struct Renderer2d {
     sprite_shader: SpriteShader,
+    line_renderer: LineRenderer,
     quad: Mesh,
     geometry_cache: GeometryCache,
     framebuffers: HashMap<Handle<Scene2d>, RenderTarget>,
@@ -187,6 +308,7 @@ impl Renderer2d {
     pub(in crate) fn new(state: &mut PipelineState) -> Result<Self, FrameworkError> {
         Ok(Self {
             sprite_shader: SpriteShader::new(state)?,
+            line_renderer: LineRenderer::new(state)?,
             quad: Mesh::new_unit_quad(),
             geometry_cache: Default::default(),
             framebuffers: Default::default(),
@@ -361,6 +483,14 @@ impl Renderer2d {
                         },
                     );
                 }
+
+                stats += self.line_renderer.render(
+                    state,
+                    viewport,
+                    frame_buffer,
+                    &scene.drawing_context,
+                    &view_projection,
+                );
             }
         }
         Ok(stats)
@@ -370,3 +500,48 @@ impl Renderer2d {
         self.geometry_cache.clear();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Vector2, Vector3},
+            color::Color,
+        },
+        renderer::renderer2d::fill_line_buffers,
+        scene2d::SceneDrawingContext,
+    };
+
+    #[test]
+    fn lines_are_converted_to_vertex_pairs() {
+        let mut context = SceneDrawingContext::default();
+        context.draw_line(Vector2::new(1.0, 2.0), Vector2::new(3.0, 4.0), Color::RED);
+        context.draw_circle(Vector2::new(-1.0, 0.0), 1.0, 8, Color::GREEN);
+
+        let mut vertices = Vec::new();
+        let mut line_indices = vec![[7, 7]];
+        fill_line_buffers(&context, &mut vertices, &mut line_indices);
+
+        assert_eq!(vertices.len(), 2 * context.lines.len());
+        assert_eq!(line_indices.len(), context.lines.len());
+        for (line, indices) in context.lines.iter().zip(line_indices.iter()) {
+            let begin = &vertices[indices[0] as usize];
+            let end = &vertices[indices[1] as usize];
+            assert_eq!(
+                begin.position,
+                Vector3::new(line.begin.x, line.begin.y, 0.0)
+            );
+            assert_eq!(end.position, Vector3::new(line.end.x, line.end.y, 0.0));
+            let color: u32 = line.color.into();
+            assert_eq!((begin.color, end.color), (color, color));
+        }
+        assert_ne!(vertices[0].color, vertices[2].color);
+
+        // Buffers are reused between frames and must not keep lines of previous frame.
+        context.clear_lines();
+        context.draw_line(Vector2::default(), Vector2::new(1.0, 0.0), Color::WHITE);
+        fill_line_buffers(&context, &mut vertices, &mut line_indices);
+        assert_eq!(vertices.len(), 2);
+        assert_eq!(line_indices, vec![[0, 1]]);
+    }
+}
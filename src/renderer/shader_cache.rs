@@ -0,0 +1,195 @@
+//! Shader cache holds GPU programs of material shaders, see [material](crate::resource::material)
+//! module docs for more info.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Vector3},
+        scope_profile,
+    },
+    engine::resource_manager::DEFAULT_RESOURCE_LIFETIME,
+    renderer::{
+        cache::CacheEntry,
+        framework::{
+            gpu_program::{GpuProgram, GpuProgramBinding, UniformLocation},
+            gpu_texture::GpuTexture,
+            state::PipelineState,
+        },
+    },
+    resource::material::{MaterialData, PropertyValue, ShaderDefinition},
+    utils::log::{Log, MessageKind},
+};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// Names of uniforms that are provided by renderer to every material pass.
+const BUILT_IN_UNIFORMS: [&str; 5] = [
+    "worldMatrix",
+    "worldViewProjection",
+    "useSkeletalAnimation",
+    "boneMatrices",
+    "cameraPosition",
+];
+
+/// Per-instance data that is passed to built-in uniforms of a material pass.
+pub(in crate) struct InstanceUniforms<'a> {
+    pub world_matrix: &'a Matrix4<f32>,
+    pub world_view_projection: &'a Matrix4<f32>,
+    pub use_skeletal_animation: bool,
+    pub bone_matrices: &'a [Matrix4<f32>],
+    pub camera_position: &'a Vector3<f32>,
+}
+
+pub(in crate) struct MaterialPass {
+    pub program: GpuProgram,
+    uniforms: HashMap<String, UniformLocation>,
+}
+
+impl MaterialPass {
+    /// Binds built-in uniforms and every property of the material. `textures` must contain
+    /// GPU textures of sampler properties in the order of [`MaterialData::properties`].
+    pub fn apply<'a>(
+        &self,
+        mut binding: GpuProgramBinding<'a>,
+        material: &MaterialData,
+        textures: &[Rc<RefCell<GpuTexture>>],
+        instance: &InstanceUniforms,
+    ) -> GpuProgramBinding<'a> {
+        if let Some(location) = self.uniforms.get("worldMatrix") {
+            binding = binding.set_matrix4(location, instance.world_matrix);
+        }
+        if let Some(location) = self.uniforms.get("worldViewProjection") {
+            binding = binding.set_matrix4(location, instance.world_view_projection);
+        }
+        if let Some(location) = self.uniforms.get("useSkeletalAnimation") {
+            binding = binding.set_bool(location, instance.use_skeletal_animation);
+        }
+        if let Some(location) = self.uniforms.get("boneMatrices") {
+            binding = binding.set_matrix4_array(location, instance.bone_matrices);
+        }
+        if let Some(location) = self.uniforms.get("cameraPosition") {
+            binding = binding.set_vector3(location, instance.camera_position);
+        }
+
+        let mut textures = textures.iter();
+        for (name, value) in material.properties() {
+            let texture = if let PropertyValue::Sampler { .. } = value {
+                textures.next()
+            } else {
+                None
+            };
+
+            let location = match self.uniforms.get(name) {
+                Some(location) => location,
+                None => continue,
+            };
+
+            binding = match value {
+                PropertyValue::Float(v) => binding.set_float(location, *v),
+                PropertyValue::Int(v) => binding.set_integer(location, *v),
+                PropertyValue::Bool(v) => binding.set_bool(location, *v),
+                PropertyValue::Vector2(v) => binding.set_vector2(location, v),
+                PropertyValue::Vector3(v) => binding.set_vector3(location, v),
+                PropertyValue::Vector4(v) => binding.set_vector4(location, v),
+                PropertyValue::Color(v) => binding.set_color(location, v),
+                PropertyValue::Matrix4(v) => binding.set_matrix4(location, v),
+                PropertyValue::Sampler { .. } => match texture {
+                    Some(texture) => binding.set_texture(location, texture),
+                    None => binding,
+                },
+            };
+        }
+
+        binding
+    }
+}
+
+pub(in crate) struct MaterialShader {
+    passes: HashMap<String, MaterialPass>,
+}
+
+impl MaterialShader {
+    fn new(state: &mut PipelineState, definition: &ShaderDefinition) -> Option<Self> {
+        let mut passes = HashMap::new();
+
+        for pass in definition.passes.iter() {
+            let program = match GpuProgram::from_source(
+                state,
+                &format!("{}_{}", definition.name, pass.name),
+                &pass.vertex_shader,
+                &pass.fragment_shader,
+            ) {
+                Ok(program) => program,
+                Err(e) => {
+                    Log::writeln(
+                        MessageKind::Error,
+                        format!(
+                            "Unable to create pass {} of shader {}. Reason: {:?}",
+                            pass.name, definition.name, e
+                        ),
+                    );
+                    return None;
+                }
+            };
+
+            // Programs may not use every property or built-in uniform, such uniforms will be
+            // skipped at binding.
+            let mut uniforms = HashMap::new();
+            for name in BUILT_IN_UNIFORMS
+                .iter()
+                .cloned()
+                .chain(definition.properties.iter().map(|p| p.name.as_str()))
+            {
+                if let Ok(location) = program.uniform_location(state, name) {
+                    uniforms.insert(name.to_owned(), location);
+                }
+            }
+
+            passes.insert(pass.name.clone(), MaterialPass { program, uniforms });
+        }
+
+        Some(Self { passes })
+    }
+
+    pub fn pass(&self, name: &str) -> Option<&MaterialPass> {
+        self.passes.get(name)
+    }
+}
+
+#[derive(Default)]
+pub struct ShaderCache {
+    // Shaders that failed to compile are stored as `None` to prevent compiling them every frame.
+    map: HashMap<u64, CacheEntry<Option<MaterialShader>>>,
+}
+
+impl ShaderCache {
+    pub(in crate) fn get(
+        &mut self,
+        state: &mut PipelineState,
+        definition: &ShaderDefinition,
+    ) -> Option<&MaterialShader> {
+        scope_profile!();
+
+        let key = definition.source_hash();
+
+        let entry = self.map.entry(key).or_insert_with(|| CacheEntry {
+            value: MaterialShader::new(state, definition),
+            value_hash: key,
+            time_to_live: DEFAULT_RESOURCE_LIFETIME,
+        });
+
+        entry.time_to_live = DEFAULT_RESOURCE_LIFETIME;
+        entry.value.as_ref()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        scope_profile!();
+
+        for entry in self.map.values_mut() {
+            entry.time_to_live -= dt;
+        }
+        self.map.retain(|_, v| v.time_to_live > 0.0);
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
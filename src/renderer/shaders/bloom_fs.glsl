@@ -0,0 +1,21 @@
+#version 330 core
+
+// Extracts parts of a frame that are brighter than threshold.
+
+uniform sampler2D frameTexture;
+uniform sampler2D exposureTexture;
+uniform bool autoExposure;
+uniform float exposure;
+uniform float threshold;
+
+in vec2 texCoord;
+out vec4 FragColor;
+
+void main()
+{
+    float frameExposure = autoExposure ? texelFetch(exposureTexture, ivec2(0, 0), 0).r : exposure;
+    vec3 color = texture(frameTexture, texCoord).rgb * frameExposure;
+    // Soft transition to avoid flickering of pixels with luminance near threshold.
+    float factor = smoothstep(threshold, threshold * 1.5 + 0.001, S_Luminance(color));
+    FragColor = vec4(color * factor, 1.0);
+}
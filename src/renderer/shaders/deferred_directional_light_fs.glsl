@@ -1,18 +1,69 @@
 #version 330 core
 
+#define MAX_CASCADE_COUNT 4
+
 uniform sampler2D depthTexture;
 uniform sampler2D colorTexture;
 uniform sampler2D normalTexture;
-uniform samplerCube pointShadowTexture;
+uniform sampler2D shadowCascade0;
+uniform sampler2D shadowCascade1;
+uniform sampler2D shadowCascade2;
+uniform sampler2D shadowCascade3;
 
 uniform vec3 lightDirection;
 uniform vec4 lightColor;
 uniform mat4 invViewProj;
 uniform vec3 cameraPosition;
+uniform mat4 viewMatrix;
+uniform mat4 lightViewProjMatrices[MAX_CASCADE_COUNT];
+// Far distances of each cascade in view space.
+uniform float cascadeDistances[MAX_CASCADE_COUNT];
+uniform int cascadeCount;
+uniform bool shadowsEnabled;
+uniform bool softShadows;
+uniform float shadowMapInvSize;
+uniform float shadowBias;
 
 in vec2 texCoord;
 out vec4 FragColor;
 
+float CsmShadowFactor(vec3 fragmentPosition)
+{
+    float fragmentZ = -(viewMatrix * vec4(fragmentPosition, 1.0)).z;
+
+    for (int i = 0; i < MAX_CASCADE_COUNT; ++i)
+    {
+        if (i >= cascadeCount)
+        {
+            break;
+        }
+
+        if (fragmentZ <= cascadeDistances[i])
+        {
+            mat4 matrix = lightViewProjMatrices[i];
+            if (i == 0)
+            {
+                return S_SpotShadowFactor(true, softShadows, shadowBias, fragmentPosition, matrix, shadowMapInvSize, shadowCascade0);
+            }
+            else if (i == 1)
+            {
+                return S_SpotShadowFactor(true, softShadows, shadowBias, fragmentPosition, matrix, shadowMapInvSize, shadowCascade1);
+            }
+            else if (i == 2)
+            {
+                return S_SpotShadowFactor(true, softShadows, shadowBias, fragmentPosition, matrix, shadowMapInvSize, shadowCascade2);
+            }
+            else
+            {
+                return S_SpotShadowFactor(true, softShadows, shadowBias, fragmentPosition, matrix, shadowMapInvSize, shadowCascade3);
+            }
+        }
+    }
+
+    // Fragment is farther than shadows distance.
+    return 1.0;
+}
+
 void main()
 {
     vec3 fragmentNormal = normalize(texture(normalTexture, texCoord).xyz * 2.0 - 1.0);
@@ -24,7 +75,9 @@ void main()
 
     float lambertian = max(dot(fragmentNormal, lightDirection), 0.0);
 
+    float shadow = shadowsEnabled ? CsmShadowFactor(fragmentPosition) : 1.0;
+
     FragColor = texture(colorTexture, texCoord);
     FragColor.xyz += 0.4 * specular;
-    FragColor *= lambertian * lightColor;
+    FragColor *= shadow * lambertian * lightColor;
 }
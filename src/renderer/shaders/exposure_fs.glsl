@@ -0,0 +1,54 @@
+#version 330 core
+
+// Calculates average luminance of a frame from its luminance histogram and adapts exposure to it.
+// Output is a single texel with exposure.
+
+uniform sampler2D histogramTexture;
+uniform sampler2D previousExposureTexture;
+uniform float minLogLuminance;
+uniform float logLuminanceRange;
+uniform int binCount;
+uniform float keyValue;
+uniform float minLuminance;
+uniform float maxLuminance;
+// Portion of difference between current and target exposure that is applied this frame.
+uniform float adaptation;
+
+out float FragColor;
+
+// Darkest and brightest parts of a frame are ignored, otherwise few very bright pixels (like sun)
+// will affect exposure too much.
+const float lowPercentile = 0.5;
+const float highPercentile = 0.95;
+
+void main()
+{
+    float accumulated = 0.0;
+    float sum = 0.0;
+    float weight = 0.0;
+    for (int i = 0; i < binCount; ++i)
+    {
+        float fraction = texelFetch(histogramTexture, ivec2(i, 0), 0).r;
+        float begin = accumulated;
+        accumulated += fraction;
+
+        float part = max(0.0, min(accumulated, highPercentile) - max(begin, lowPercentile));
+        float logLuminance = minLogLuminance + float(i) / float(binCount - 1) * logLuminanceRange;
+        sum += part * logLuminance;
+        weight += part;
+    }
+
+    float averageLuminance = clamp(exp2(sum / max(weight, 0.0001)), minLuminance, maxLuminance);
+    float targetExposure = keyValue / averageLuminance;
+
+    float previousExposure = texelFetch(previousExposureTexture, ivec2(0, 0), 0).r;
+    if (previousExposure <= 0.0)
+    {
+        // First frame, no need to adapt.
+        FragColor = targetExposure;
+    }
+    else
+    {
+        FragColor = mix(previousExposure, targetExposure, adaptation);
+    }
+}
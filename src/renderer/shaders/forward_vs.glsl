@@ -5,6 +5,8 @@ layout(location = 1) in vec2 vertexTexCoord;
 // TODO: Put rest of vertex attributes here.
 layout(location = 5) in vec4 boneWeights;
 layout(location = 6) in vec4 boneIndices;
+layout(location = 13) in vec4 extraBoneWeights;
+layout(location = 14) in vec4 extraBoneIndices;
 
 uniform mat4 worldViewProjection;
 uniform bool useSkeletalAnimation;
@@ -24,11 +26,19 @@ void main()
         int i1 = int(boneIndices.y);
         int i2 = int(boneIndices.z);
         int i3 = int(boneIndices.w);
+        int i4 = int(extraBoneIndices.x);
+        int i5 = int(extraBoneIndices.y);
+        int i6 = int(extraBoneIndices.z);
+        int i7 = int(extraBoneIndices.w);
 
         localPosition += boneMatrices[i0] * vertex * boneWeights.x;
         localPosition += boneMatrices[i1] * vertex * boneWeights.y;
         localPosition += boneMatrices[i2] * vertex * boneWeights.z;
         localPosition += boneMatrices[i3] * vertex * boneWeights.w;
+        localPosition += boneMatrices[i4] * vertex * extraBoneWeights.x;
+        localPosition += boneMatrices[i5] * vertex * extraBoneWeights.y;
+        localPosition += boneMatrices[i6] * vertex * extraBoneWeights.z;
+        localPosition += boneMatrices[i7] * vertex * extraBoneWeights.w;
     }
     else
     {
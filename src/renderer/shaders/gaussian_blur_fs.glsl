@@ -0,0 +1,25 @@
+#version 330 core
+
+// Separable 9-tap gaussian blur, direction is defined by uniform.
+
+uniform sampler2D inputTexture;
+uniform vec2 direction;
+
+in vec2 texCoord;
+out vec4 FragColor;
+
+// Weights and offsets of 9-tap kernel packed into 5 bilinear fetches.
+const float offsets[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float weights[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main()
+{
+    vec2 step = direction / vec2(textureSize(inputTexture, 0));
+    vec3 result = texture(inputTexture, texCoord).rgb * weights[0];
+    for (int i = 1; i < 3; ++i)
+    {
+        result += texture(inputTexture, texCoord + step * offsets[i]).rgb * weights[i];
+        result += texture(inputTexture, texCoord - step * offsets[i]).rgb * weights[i];
+    }
+    FragColor = vec4(result, 1.0);
+}
@@ -0,0 +1,34 @@
+#version 330 core
+
+// Each fragment of the target is a bin of luminance histogram, it counts texels of luminance
+// texture that fall into the bin.
+
+uniform sampler2D luminanceTexture;
+uniform float minLogLuminance;
+uniform float logLuminanceRange;
+uniform int binCount;
+
+out float FragColor;
+
+void main()
+{
+    int bin = int(gl_FragCoord.x);
+    ivec2 size = textureSize(luminanceTexture, 0);
+
+    float count = 0.0;
+    for (int y = 0; y < size.y; ++y)
+    {
+        for (int x = 0; x < size.x; ++x)
+        {
+            float logLuminance = texelFetch(luminanceTexture, ivec2(x, y), 0).r;
+            float t = clamp((logLuminance - minLogLuminance) / logLuminanceRange, 0.0, 1.0);
+            if (int(t * float(binCount - 1) + 0.5) == bin)
+            {
+                count += 1.0;
+            }
+        }
+    }
+
+    // Normalized, so sum of all bins is one.
+    FragColor = count / float(size.x * size.y);
+}
@@ -0,0 +1,13 @@
+#version 330 core
+
+// Writes logarithm of luminance of a frame, it is used to build luminance histogram.
+
+uniform sampler2D frameTexture;
+
+in vec2 texCoord;
+out float FragColor;
+
+void main()
+{
+    FragColor = log2(max(S_Luminance(texture(frameTexture, texCoord).rgb), 0.0001));
+}
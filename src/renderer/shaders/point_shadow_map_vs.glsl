@@ -4,11 +4,16 @@ layout(location = 0) in vec3 vertexPosition;
 layout(location = 1) in vec2 vertexTexCoord;
 layout(location = 4) in vec4 boneWeights;
 layout(location = 5) in vec4 boneIndices;
+layout(location = 13) in vec4 extraBoneWeights;
+layout(location = 14) in vec4 extraBoneIndices;
 
 uniform mat4 worldMatrix;
 uniform mat4 worldViewProjection;
 uniform bool useSkeletalAnimation;
 uniform mat4 boneMatrices[60];
+uniform sampler2D morphDeltas;
+uniform float morphWeights[S_MaxMorphTargets];
+uniform int morphTargetCount;
 
 out vec2 texCoord;
 out vec3 worldPosition;
@@ -17,18 +22,29 @@ void main()
 {
     vec4 localPosition = vec4(0);
 
+    // Only positions are needed for shadows.
+    vec3 morphedPosition = vertexPosition;
+    vec3 morphedNormal = vec3(0.0);
+    vec3 morphedTangent = vec3(0.0);
+    S_ApplyMorphTargets(morphDeltas, morphWeights, morphTargetCount, gl_VertexID,
+        morphedPosition, morphedNormal, morphedTangent);
+
     if (useSkeletalAnimation)
     {
-        vec4 vertex = vec4(vertexPosition, 1.0);
+        vec4 vertex = vec4(morphedPosition, 1.0);
 
         localPosition += boneMatrices[int(boneIndices.x)] * vertex * boneWeights.x;
         localPosition += boneMatrices[int(boneIndices.y)] * vertex * boneWeights.y;
         localPosition += boneMatrices[int(boneIndices.z)] * vertex * boneWeights.z;
         localPosition += boneMatrices[int(boneIndices.w)] * vertex * boneWeights.w;
+        localPosition += boneMatrices[int(extraBoneIndices.x)] * vertex * extraBoneWeights.x;
+        localPosition += boneMatrices[int(extraBoneIndices.y)] * vertex * extraBoneWeights.y;
+        localPosition += boneMatrices[int(extraBoneIndices.z)] * vertex * extraBoneWeights.z;
+        localPosition += boneMatrices[int(extraBoneIndices.w)] * vertex * extraBoneWeights.w;
     }
     else
     {
-        localPosition = vec4(vertexPosition, 1.0);
+        localPosition = vec4(morphedPosition, 1.0);
     }
 
     gl_Position = worldViewProjection * localPosition;
@@ -0,0 +1,99 @@
+#version 330 core
+
+// Final pass of built-in post-processing chain: exposure, bloom composition, tone mapping,
+// color grading, chromatic aberration and vignette. Output is in low dynamic range.
+
+uniform sampler2D frameTexture;
+uniform sampler2D bloomTexture;
+uniform sampler2D exposureTexture;
+uniform sampler3D lutTexture;
+
+uniform bool autoExposure;
+uniform float exposure;
+uniform bool bloomEnabled;
+uniform float bloomIntensity;
+// 0 - none, 1 - Reinhard, 2 - ACES, 3 - Uncharted 2.
+uniform int toneMapping;
+uniform bool colorGradingEnabled;
+uniform bool vignetteEnabled;
+uniform float vignetteIntensity;
+uniform float vignetteRadius;
+uniform float vignetteSmoothness;
+uniform float chromaticAberration;
+
+in vec2 texCoord;
+out vec4 FragColor;
+
+vec3 Uncharted2Curve(vec3 x)
+{
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 ToneMap(vec3 color)
+{
+    if (toneMapping == 1)
+    {
+        return color / (1.0 + color);
+    }
+    else if (toneMapping == 2)
+    {
+        // Fit by Krzysztof Narkowicz.
+        return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+    }
+    else if (toneMapping == 3)
+    {
+        const float whitePoint = 11.2;
+        return Uncharted2Curve(2.0 * color) / Uncharted2Curve(vec3(whitePoint));
+    }
+    return clamp(color, 0.0, 1.0);
+}
+
+void main()
+{
+    vec2 fromCenter = texCoord - 0.5;
+
+    vec3 color;
+    if (chromaticAberration > 0.0)
+    {
+        // Offset grows towards edges of the frame.
+        vec2 offset = fromCenter * 2.0 * chromaticAberration;
+        color.r = texture(frameTexture, texCoord + offset).r;
+        color.g = texture(frameTexture, texCoord).g;
+        color.b = texture(frameTexture, texCoord - offset).b;
+    }
+    else
+    {
+        color = texture(frameTexture, texCoord).rgb;
+    }
+
+    color *= autoExposure ? texelFetch(exposureTexture, ivec2(0, 0), 0).r : exposure;
+
+    if (bloomEnabled)
+    {
+        // Bloom is already exposed.
+        color += texture(bloomTexture, texCoord).rgb * bloomIntensity;
+    }
+
+    color = ToneMap(color);
+
+    if (colorGradingEnabled)
+    {
+        // Sample centers of edge texels, so colors of the table are not interpolated with border.
+        vec3 size = vec3(textureSize(lutTexture, 0));
+        color = texture(lutTexture, color * (size - 1.0) / size + 0.5 / size).rgb;
+    }
+
+    if (vignetteEnabled)
+    {
+        float vignette = smoothstep(vignetteRadius, vignetteRadius + vignetteSmoothness, length(fromCenter));
+        color *= 1.0 - vignette * vignetteIntensity;
+    }
+
+    FragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,171 @@
+#version 330 core
+
+#define MAX_PROBE_COUNT 4
+
+uniform sampler2D depthTexture;
+uniform sampler2D normalTexture;
+uniform sampler2D materialTexture;
+// Copy of lit frame, screen-space reflections take colors from it.
+uniform sampler2D frameTexture;
+uniform samplerCube environmentMap;
+uniform samplerCube probeTexture0;
+uniform samplerCube probeTexture1;
+uniform samplerCube probeTexture2;
+uniform samplerCube probeTexture3;
+
+uniform mat4 invViewProj;
+uniform mat4 viewProj;
+uniform vec3 cameraPosition;
+
+uniform int probeCount;
+uniform vec3 probePositions[MAX_PROBE_COUNT];
+uniform vec3 probeBoundsMin[MAX_PROBE_COUNT];
+uniform vec3 probeBoundsMax[MAX_PROBE_COUNT];
+// x - blend distance, y - intensity, z - box projection flag.
+uniform vec4 probeParameters[MAX_PROBE_COUNT];
+
+uniform bool ssrEnabled;
+uniform int ssrSteps;
+uniform float ssrMaxDistance;
+
+in vec2 texCoord;
+out vec4 FragColor;
+
+float ProbeInfluence(int i, vec3 position)
+{
+    vec3 d = min(position - probeBoundsMin[i], probeBoundsMax[i] - position);
+    float distanceToFace = min(d.x, min(d.y, d.z));
+    if (distanceToFace < 0.0)
+    {
+        return 0.0;
+    }
+    float blendDistance = probeParameters[i].x;
+    return blendDistance > 0.0 ? min(distanceToFace / blendDistance, 1.0) : 1.0;
+}
+
+// Intersects reflection vector with the volume of a probe and returns direction from capture
+// point to the intersection point.
+vec3 ProbeDirection(int i, vec3 position, vec3 reflection)
+{
+    if (probeParameters[i].z < 0.5)
+    {
+        return reflection;
+    }
+
+    vec3 firstPlane = (probeBoundsMax[i] - position) / reflection;
+    vec3 secondPlane = (probeBoundsMin[i] - position) / reflection;
+    vec3 furthestPlane = max(firstPlane, secondPlane);
+    float distance = min(furthestPlane.x, min(furthestPlane.y, furthestPlane.z));
+
+    return position + reflection * distance - probePositions[i];
+}
+
+vec3 SampleProbe(int i, vec3 direction)
+{
+    if (i == 0)
+    {
+        return texture(probeTexture0, direction).rgb;
+    }
+    else if (i == 1)
+    {
+        return texture(probeTexture1, direction).rgb;
+    }
+    else if (i == 2)
+    {
+        return texture(probeTexture2, direction).rgb;
+    }
+    else
+    {
+        return texture(probeTexture3, direction).rgb;
+    }
+}
+
+// Marches along reflection ray and compares depth of each step with depth buffer. Returns color
+// of hit point in rgb and confidence of the hit in alpha.
+vec4 TraceScreenSpaceReflection(vec3 position, vec3 reflection)
+{
+    float stepLength = ssrMaxDistance / float(ssrSteps);
+    // Ray must not be thinner than a step, otherwise thin objects will be skipped.
+    float thickness = 2.0 * stepLength;
+
+    for (int i = 1; i <= ssrSteps; ++i)
+    {
+        vec3 samplePosition = position + reflection * (stepLength * float(i));
+        vec3 screenPosition = S_Project(samplePosition, viewProj);
+
+        if (screenPosition.x < 0.0 || screenPosition.x > 1.0 ||
+            screenPosition.y < 0.0 || screenPosition.y > 1.0 ||
+            screenPosition.z > 1.0)
+        {
+            break;
+        }
+
+        float sceneDepth = texture(depthTexture, screenPosition.xy).r;
+        vec3 scenePosition = S_UnProject(vec3(screenPosition.xy, sceneDepth), invViewProj);
+
+        float difference = distance(cameraPosition, samplePosition) - distance(cameraPosition, scenePosition);
+        if (difference > 0.0 && difference < thickness)
+        {
+            // Fade out near edges of the screen and at the end of the ray to hide the seam
+            // between screen-space reflections and probes.
+            vec2 edges = smoothstep(0.0, 0.1, screenPosition.xy) * (1.0 - smoothstep(0.9, 1.0, screenPosition.xy));
+            float confidence = edges.x * edges.y * (1.0 - float(i) / float(ssrSteps));
+            return vec4(texture(frameTexture, screenPosition.xy).rgb, confidence);
+        }
+    }
+
+    return vec4(0.0);
+}
+
+void main()
+{
+    float depth = texture(depthTexture, texCoord).r;
+    float reflectionFactor = texture(materialTexture, texCoord).r;
+
+    // Nothing to do with background and non-reflective surfaces.
+    if (depth >= 1.0 || reflectionFactor <= 0.0)
+    {
+        discard;
+    }
+
+    vec3 fragmentPosition = S_UnProject(vec3(texCoord, depth), invViewProj);
+    vec3 fragmentNormal = normalize(texture(normalTexture, texCoord).xyz * 2.0 - 1.0);
+    vec3 reflection = reflect(normalize(fragmentPosition - cameraPosition), fragmentNormal);
+
+    vec3 color = vec3(0.0);
+    float totalInfluence = 0.0;
+    for (int i = 0; i < MAX_PROBE_COUNT; ++i)
+    {
+        if (i >= probeCount)
+        {
+            break;
+        }
+
+        float influence = ProbeInfluence(i, fragmentPosition);
+        if (influence > 0.0)
+        {
+            vec3 direction = ProbeDirection(i, fragmentPosition, reflection);
+            color += influence * probeParameters[i].y * SampleProbe(i, direction);
+            totalInfluence += influence;
+        }
+    }
+
+    if (totalInfluence > 1.0)
+    {
+        color /= totalInfluence;
+    }
+    else
+    {
+        // Rest is taken from environment.
+        color += (1.0 - totalInfluence) * texture(environmentMap, reflection).rgb;
+    }
+
+    if (ssrEnabled)
+    {
+        vec4 ssr = TraceScreenSpaceReflection(fragmentPosition, reflection);
+        color = mix(color, ssr.rgb, ssr.a);
+    }
+
+    // Blended with lit frame using reflection factor.
+    FragColor = vec4(color, reflectionFactor);
+}
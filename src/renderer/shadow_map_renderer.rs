@@ -18,7 +18,7 @@ use crate::{
         state::{ColorMask, PipelineState},
     },
     renderer::{batch::BatchStorage, GeometryCache, RenderPassStatistics, ShadowMapPrecision},
-    scene::{graph::Graph, node::Node},
+    scene::{graph::Graph, light::directional::CSM_MAX_CASCADE_COUNT, node::Node},
 };
 use std::{cell::RefCell, rc::Rc};
 
@@ -28,6 +28,9 @@ struct SpotShadowMapShader {
     world_view_projection_matrix: UniformLocation,
     use_skeletal_animation: UniformLocation,
     diffuse_texture: UniformLocation,
+    morph_deltas: UniformLocation,
+    morph_weights: UniformLocation,
+    morph_target_count: UniformLocation,
 }
 
 impl SpotShadowMapShader {
@@ -41,6 +44,9 @@ impl SpotShadowMapShader {
             world_view_projection_matrix: program.uniform_location(state, "worldViewProjection")?,
             use_skeletal_animation: program.uniform_location(state, "useSkeletalAnimation")?,
             diffuse_texture: program.uniform_location(state, "diffuseTexture")?,
+            morph_deltas: program.uniform_location(state, "morphDeltas")?,
+            morph_weights: program.uniform_location(state, "morphWeights")?,
+            morph_target_count: program.uniform_location(state, "morphTargetCount")?,
 
             program,
         })
@@ -67,64 +73,254 @@ fn cascade_size(base_size: usize, cascade: usize) -> usize {
     }
 }
 
+/// Selects cascade of spot or point shadow map for a light at given distance from camera.
+/// `shadows_distance` is maximum distance at which shadows of the light are rendered. The
+/// largest cascade is used regardless of the distance if `force_largest` is set.
+pub(in crate) fn select_cascade(
+    distance_to_camera: f32,
+    shadows_distance: f32,
+    force_largest: bool,
+) -> usize {
+    if force_largest || distance_to_camera < shadows_distance * 0.2 {
+        0
+    } else if distance_to_camera < shadows_distance * 0.4 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Textures of a shadow map cascade, see [`make_cascade_framebuffer`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CascadeLayout {
+    /// Square depth texture, used by spot and directional lights.
+    Depth { size: usize },
+    /// Depth texture and cube map color texture with distances to the light, used by point
+    /// lights. Every face of the cube map is rendered separately.
+    CubeMap { size: usize },
+}
+
+fn make_cascade_framebuffer(
+    state: &mut PipelineState,
+    layout: CascadeLayout,
+    precision: ShadowMapPrecision,
+) -> Result<FrameBuffer, FrameworkError> {
+    match layout {
+        CascadeLayout::Depth { size } => make_depth_framebuffer(state, size, precision),
+        CascadeLayout::CubeMap { size } => make_cube_map_framebuffer(state, size, precision),
+    }
+}
+
+fn make_depth_framebuffer(
+    state: &mut PipelineState,
+    size: usize,
+    precision: ShadowMapPrecision,
+) -> Result<FrameBuffer, FrameworkError> {
+    let depth = {
+        let kind = GpuTextureKind::Rectangle {
+            width: size,
+            height: size,
+        };
+        let mut texture = GpuTexture::new(
+            state,
+            kind,
+            match precision {
+                ShadowMapPrecision::Full => PixelKind::D32,
+                ShadowMapPrecision::Half => PixelKind::D16,
+            },
+            MinificationFilter::Linear,
+            MagnificationFilter::Linear,
+            1,
+            None,
+        )?;
+        texture
+            .bind_mut(state, 0)
+            .set_wrap(Coordinate::T, WrapMode::ClampToEdge)
+            .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
+            .set_border_color(Color::WHITE);
+        texture
+    };
+
+    FrameBuffer::new(
+        state,
+        Some(Attachment {
+            kind: AttachmentKind::Depth,
+            texture: Rc::new(RefCell::new(depth)),
+        }),
+        vec![],
+    )
+}
+
+fn make_cube_map_framebuffer(
+    state: &mut PipelineState,
+    size: usize,
+    precision: ShadowMapPrecision,
+) -> Result<FrameBuffer, FrameworkError> {
+    let depth = {
+        let kind = GpuTextureKind::Rectangle {
+            width: size,
+            height: size,
+        };
+        let mut texture = GpuTexture::new(
+            state,
+            kind,
+            match precision {
+                ShadowMapPrecision::Full => PixelKind::D32,
+                ShadowMapPrecision::Half => PixelKind::D16,
+            },
+            MinificationFilter::Nearest,
+            MagnificationFilter::Nearest,
+            1,
+            None,
+        )?;
+        texture
+            .bind_mut(state, 0)
+            .set_minification_filter(MinificationFilter::Nearest)
+            .set_magnification_filter(MagnificationFilter::Nearest)
+            .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
+            .set_wrap(Coordinate::T, WrapMode::ClampToEdge);
+        texture
+    };
+
+    let cube_map = {
+        let kind = GpuTextureKind::Cube {
+            width: size,
+            height: size,
+        };
+        let mut texture = GpuTexture::new(
+            state,
+            kind,
+            PixelKind::F16,
+            MinificationFilter::Linear,
+            MagnificationFilter::Linear,
+            1,
+            None,
+        )?;
+        texture
+            .bind_mut(state, 0)
+            .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
+            .set_wrap(Coordinate::T, WrapMode::ClampToEdge)
+            .set_wrap(Coordinate::R, WrapMode::ClampToEdge);
+        texture
+    };
+
+    FrameBuffer::new(
+        state,
+        Some(Attachment {
+            kind: AttachmentKind::Depth,
+            texture: Rc::new(RefCell::new(depth)),
+        }),
+        vec![Attachment {
+            kind: AttachmentKind::Color,
+            texture: Rc::new(RefCell::new(cube_map)),
+        }],
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_depth_batches(
+    state: &mut PipelineState,
+    graph: &Graph,
+    light_view_projection: &Matrix4<f32>,
+    batches: &BatchStorage,
+    geom_cache: &mut GeometryCache,
+    framebuffer: &mut FrameBuffer,
+    viewport: Rect<i32>,
+    shader: &SpotShadowMapShader,
+) -> RenderPassStatistics {
+    let mut statistics = RenderPassStatistics::default();
+
+    framebuffer.clear(state, viewport, None, Some(1.0), None);
+    let frustum = Frustum::from(*light_view_projection).unwrap_or_default();
+
+    for batch in batches.batches.iter() {
+        let geometry = geom_cache.get(state, &batch.data.read().unwrap());
+
+        for instance in batch.instances.iter() {
+            let node = &graph[instance.owner];
+
+            let visible = node.global_visibility() && {
+                match node {
+                    Node::Mesh(mesh) => {
+                        mesh.cast_shadows() && mesh.is_intersect_frustum(graph, &frustum)
+                    }
+                    Node::Terrain(_) => {
+                        // https://github.com/rg3dengine/rg3d/issues/117
+                        true
+                    }
+                    _ => false,
+                }
+            };
+
+            if visible {
+                statistics += framebuffer.draw(
+                    geometry,
+                    state,
+                    viewport,
+                    &shader.program,
+                    &DrawParameters {
+                        cull_face: CullFace::Back,
+                        culling: true,
+                        color_write: ColorMask::all(false),
+                        depth_write: true,
+                        stencil_test: false,
+                        depth_test: true,
+                        blend: false,
+                    },
+                    |program_binding| {
+                        program_binding
+                            .set_matrix4(
+                                &shader.world_view_projection_matrix,
+                                &(light_view_projection * instance.world_transform),
+                            )
+                            .set_bool(&shader.use_skeletal_animation, batch.is_skinned)
+                            .set_matrix4_array(
+                                &shader.bone_matrices,
+                                instance.bone_matrices.as_slice(),
+                            )
+                            .set_texture(&shader.diffuse_texture, &batch.diffuse_texture)
+                            .set_texture(&shader.morph_deltas, &batch.morph_deltas)
+                            .set_float_slice(
+                                &shader.morph_weights,
+                                instance.morph_weights.as_slice(),
+                            )
+                            .set_integer(
+                                &shader.morph_target_count,
+                                batch.morph_target_count as i32,
+                            );
+                    },
+                );
+            }
+        }
+    }
+
+    statistics
+}
+
 impl SpotShadowMapRenderer {
     pub fn new(
         state: &mut PipelineState,
         size: usize,
         precision: ShadowMapPrecision,
     ) -> Result<Self, FrameworkError> {
-        fn make_cascade(
-            state: &mut PipelineState,
-            size: usize,
-            precision: ShadowMapPrecision,
-        ) -> Result<FrameBuffer, FrameworkError> {
-            let depth = {
-                let kind = GpuTextureKind::Rectangle {
-                    width: size,
-                    height: size,
-                };
-                let mut texture = GpuTexture::new(
-                    state,
-                    kind,
-                    match precision {
-                        ShadowMapPrecision::Full => PixelKind::D32,
-                        ShadowMapPrecision::Half => PixelKind::D16,
-                    },
-                    MinificationFilter::Linear,
-                    MagnificationFilter::Linear,
-                    1,
-                    None,
-                )?;
-                texture
-                    .bind_mut(state, 0)
-                    .set_wrap(Coordinate::T, WrapMode::ClampToEdge)
-                    .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
-                    .set_border_color(Color::WHITE);
-                texture
-            };
-
-            FrameBuffer::new(
-                state,
-                Some(Attachment {
-                    kind: AttachmentKind::Depth,
-                    texture: Rc::new(RefCell::new(depth)),
-                }),
-                vec![],
-            )
-        }
-
         Ok(Self {
             precision,
             size,
             cascades: [
-                make_cascade(state, cascade_size(size, 0), precision)?,
-                make_cascade(state, cascade_size(size, 1), precision)?,
-                make_cascade(state, cascade_size(size, 2), precision)?,
+                make_cascade_framebuffer(state, Self::cascade_layout(size, 0), precision)?,
+                make_cascade_framebuffer(state, Self::cascade_layout(size, 1), precision)?,
+                make_cascade_framebuffer(state, Self::cascade_layout(size, 2), precision)?,
             ],
             shader: SpotShadowMapShader::new(state)?,
         })
     }
 
+    fn cascade_layout(base_size: usize, cascade: usize) -> CascadeLayout {
+        CascadeLayout::Depth {
+            size: cascade_size(base_size, cascade),
+        }
+    }
+
     pub fn base_size(&self) -> usize {
         self.size
     }
@@ -157,70 +353,98 @@ impl SpotShadowMapRenderer {
     ) -> RenderPassStatistics {
         scope_profile!();
 
-        let mut statistics = RenderPassStatistics::default();
-
         let framebuffer = &mut self.cascades[cascade];
         let cascade_size = cascade_size(self.size, cascade);
 
         let viewport = Rect::new(0, 0, cascade_size as i32, cascade_size as i32);
 
-        framebuffer.clear(state, viewport, None, Some(1.0), None);
-        let frustum = Frustum::from(*light_view_projection).unwrap_or_default();
+        render_depth_batches(
+            state,
+            graph,
+            light_view_projection,
+            batches,
+            geom_cache,
+            framebuffer,
+            viewport,
+            &self.shader,
+        )
+    }
+}
 
-        for batch in batches.batches.iter() {
-            let geometry = geom_cache.get(state, &batch.data.read().unwrap());
+/// Cascaded shadow maps renderer for directional lights. Each cascade has the same size,
+/// amount of cascades is defined by quality settings.
+pub struct CsmRenderer {
+    precision: ShadowMapPrecision,
+    shader: SpotShadowMapShader,
+    cascades: Vec<FrameBuffer>,
+    size: usize,
+}
 
-            for instance in batch.instances.iter() {
-                let node = &graph[instance.owner];
+impl CsmRenderer {
+    pub fn new(
+        state: &mut PipelineState,
+        size: usize,
+        precision: ShadowMapPrecision,
+        cascade_count: usize,
+    ) -> Result<Self, FrameworkError> {
+        Ok(Self {
+            precision,
+            size,
+            cascades: (0..cascade_count.max(1).min(CSM_MAX_CASCADE_COUNT))
+                .map(|_| make_cascade_framebuffer(state, Self::cascade_layout(size), precision))
+                .collect::<Result<Vec<_>, _>>()?,
+            shader: SpotShadowMapShader::new(state)?,
+        })
+    }
 
-                let visible = node.global_visibility() && {
-                    match node {
-                        Node::Mesh(mesh) => {
-                            mesh.cast_shadows() && mesh.is_intersect_frustum(graph, &frustum)
-                        }
-                        Node::Terrain(_) => {
-                            // https://github.com/rg3dengine/rg3d/issues/117
-                            true
-                        }
-                        _ => false,
-                    }
-                };
-
-                if visible {
-                    let shader = &self.shader;
-                    statistics += framebuffer.draw(
-                        geometry,
-                        state,
-                        viewport,
-                        &self.shader.program,
-                        &DrawParameters {
-                            cull_face: CullFace::Back,
-                            culling: true,
-                            color_write: ColorMask::all(false),
-                            depth_write: true,
-                            stencil_test: false,
-                            depth_test: true,
-                            blend: false,
-                        },
-                        |program_binding| {
-                            program_binding
-                                .set_matrix4(
-                                    &shader.world_view_projection_matrix,
-                                    &(light_view_projection * instance.world_transform),
-                                )
-                                .set_bool(&shader.use_skeletal_animation, batch.is_skinned)
-                                .set_matrix4_array(
-                                    &shader.bone_matrices,
-                                    instance.bone_matrices.as_slice(),
-                                )
-                                .set_texture(&shader.diffuse_texture, &batch.diffuse_texture);
-                        },
-                    );
-                }
-            }
-        }
+    fn cascade_layout(size: usize) -> CascadeLayout {
+        CascadeLayout::Depth { size }
+    }
 
-        statistics
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn precision(&self) -> ShadowMapPrecision {
+        self.precision
+    }
+
+    pub fn cascade_count(&self) -> usize {
+        self.cascades.len()
+    }
+
+    pub fn cascade_texture(&self, cascade: usize) -> Rc<RefCell<GpuTexture>> {
+        self.cascades[cascade]
+            .depth_attachment()
+            .unwrap()
+            .texture
+            .clone()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(in crate) fn render(
+        &mut self,
+        state: &mut PipelineState,
+        graph: &Graph,
+        light_view_projection: &Matrix4<f32>,
+        batches: &BatchStorage,
+        geom_cache: &mut GeometryCache,
+        cascade: usize,
+    ) -> RenderPassStatistics {
+        scope_profile!();
+
+        let viewport = Rect::new(0, 0, self.size as i32, self.size as i32);
+
+        render_depth_batches(
+            state,
+            graph,
+            light_view_projection,
+            batches,
+            geom_cache,
+            &mut self.cascades[cascade],
+            viewport,
+            &self.shader,
+        )
     }
 }
 
@@ -232,6 +456,9 @@ struct PointShadowMapShader {
     use_skeletal_animation: UniformLocation,
     diffuse_texture: UniformLocation,
     light_position: UniformLocation,
+    morph_deltas: UniformLocation,
+    morph_weights: UniformLocation,
+    morph_target_count: UniformLocation,
 }
 
 impl PointShadowMapShader {
@@ -251,6 +478,9 @@ impl PointShadowMapShader {
             use_skeletal_animation: program.uniform_location(state, "useSkeletalAnimation")?,
             diffuse_texture: program.uniform_location(state, "diffuseTexture")?,
             light_position: program.uniform_location(state, "lightPosition")?,
+            morph_deltas: program.uniform_location(state, "morphDeltas")?,
+            morph_weights: program.uniform_location(state, "morphWeights")?,
+            morph_target_count: program.uniform_location(state, "morphTargetCount")?,
             program,
         })
     }
@@ -286,78 +516,12 @@ impl PointShadowMapRenderer {
         size: usize,
         precision: ShadowMapPrecision,
     ) -> Result<Self, FrameworkError> {
-        fn make_cascade(
-            state: &mut PipelineState,
-            size: usize,
-            precision: ShadowMapPrecision,
-        ) -> Result<FrameBuffer, FrameworkError> {
-            let depth = {
-                let kind = GpuTextureKind::Rectangle {
-                    width: size,
-                    height: size,
-                };
-                let mut texture = GpuTexture::new(
-                    state,
-                    kind,
-                    match precision {
-                        ShadowMapPrecision::Full => PixelKind::D32,
-                        ShadowMapPrecision::Half => PixelKind::D16,
-                    },
-                    MinificationFilter::Nearest,
-                    MagnificationFilter::Nearest,
-                    1,
-                    None,
-                )?;
-                texture
-                    .bind_mut(state, 0)
-                    .set_minification_filter(MinificationFilter::Nearest)
-                    .set_magnification_filter(MagnificationFilter::Nearest)
-                    .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
-                    .set_wrap(Coordinate::T, WrapMode::ClampToEdge);
-                texture
-            };
-
-            let cube_map = {
-                let kind = GpuTextureKind::Cube {
-                    width: size,
-                    height: size,
-                };
-                let mut texture = GpuTexture::new(
-                    state,
-                    kind,
-                    PixelKind::F16,
-                    MinificationFilter::Linear,
-                    MagnificationFilter::Linear,
-                    1,
-                    None,
-                )?;
-                texture
-                    .bind_mut(state, 0)
-                    .set_wrap(Coordinate::S, WrapMode::ClampToEdge)
-                    .set_wrap(Coordinate::T, WrapMode::ClampToEdge)
-                    .set_wrap(Coordinate::R, WrapMode::ClampToEdge);
-                texture
-            };
-
-            FrameBuffer::new(
-                state,
-                Some(Attachment {
-                    kind: AttachmentKind::Depth,
-                    texture: Rc::new(RefCell::new(depth)),
-                }),
-                vec![Attachment {
-                    kind: AttachmentKind::Color,
-                    texture: Rc::new(RefCell::new(cube_map)),
-                }],
-            )
-        }
-
         Ok(Self {
             precision,
             cascades: [
-                make_cascade(state, cascade_size(size, 0), precision)?,
-                make_cascade(state, cascade_size(size, 1), precision)?,
-                make_cascade(state, cascade_size(size, 2), precision)?,
+                make_cascade_framebuffer(state, Self::cascade_layout(size, 0), precision)?,
+                make_cascade_framebuffer(state, Self::cascade_layout(size, 1), precision)?,
+                make_cascade_framebuffer(state, Self::cascade_layout(size, 2), precision)?,
             ],
             size,
             shader: PointShadowMapShader::new(state)?,
@@ -396,6 +560,12 @@ impl PointShadowMapRenderer {
         })
     }
 
+    fn cascade_layout(base_size: usize, cascade: usize) -> CascadeLayout {
+        CascadeLayout::CubeMap {
+            size: cascade_size(base_size, cascade),
+        }
+    }
+
     pub fn base_size(&self) -> usize {
         self.size
     }
@@ -500,7 +670,16 @@ impl PointShadowMapRenderer {
                                         &shader.bone_matrices,
                                         instance.bone_matrices.as_slice(),
                                     )
-                                    .set_texture(&shader.diffuse_texture, &batch.diffuse_texture);
+                                    .set_texture(&shader.diffuse_texture, &batch.diffuse_texture)
+                                    .set_texture(&shader.morph_deltas, &batch.morph_deltas)
+                                    .set_float_slice(
+                                        &shader.morph_weights,
+                                        instance.morph_weights.as_slice(),
+                                    )
+                                    .set_integer(
+                                        &shader.morph_target_count,
+                                        batch.morph_target_count as i32,
+                                    );
                             },
                         );
                     }
@@ -511,3 +690,95 @@ impl PointShadowMapRenderer {
         statistics
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        renderer::{
+            batch::BatchStorage,
+            framework::{gpu_texture::GpuTextureKind, state::with_test_state},
+            shadow_map_renderer::{
+                cascade_size, select_cascade, CascadeLayout, CsmRenderer,
+                PointShadowMapRenderContext, PointShadowMapRenderer, SpotShadowMapRenderer,
+            },
+            GeometryCache, ShadowMapPrecision,
+        },
+        scene::graph::Graph,
+    };
+
+    #[test]
+    fn cascade_layouts() {
+        assert_eq!(
+            (0..3).map(|i| cascade_size(64, i)).collect::<Vec<_>>(),
+            [64, 32, 16]
+        );
+        assert_eq!(cascade_size(2, 2), 1);
+
+        for cascade in 0..3 {
+            let size = cascade_size(64, cascade);
+            // Point shadows are stored in cube maps, rendering of faces and cascade_texture
+            // rely on it.
+            assert_eq!(
+                PointShadowMapRenderer::cascade_layout(64, cascade),
+                CascadeLayout::CubeMap { size }
+            );
+            assert_eq!(
+                SpotShadowMapRenderer::cascade_layout(64, cascade),
+                CascadeLayout::Depth { size }
+            );
+        }
+
+        // Every cascade of directional light has the same size.
+        assert_eq!(
+            CsmRenderer::cascade_layout(128),
+            CascadeLayout::Depth { size: 128 }
+        );
+    }
+
+    #[test]
+    fn cascade_selection() {
+        assert_eq!(select_cascade(1.0, 10.0, false), 0);
+        assert_eq!(select_cascade(3.0, 10.0, false), 1);
+        assert_eq!(select_cascade(5.0, 10.0, false), 2);
+        assert_eq!(select_cascade(50.0, 10.0, false), 2);
+        // Boundaries belong to the next cascade.
+        assert_eq!(select_cascade(2.0, 10.0, false), 1);
+        assert_eq!(select_cascade(4.0, 10.0, false), 2);
+        assert_eq!(select_cascade(5.0, 10.0, true), 0);
+    }
+
+    #[test]
+    #[ignore = "requires OpenGL 3.3 context"]
+    fn point_shadow_map_cascades_are_cube_maps() {
+        with_test_state(|state| {
+            let mut renderer =
+                PointShadowMapRenderer::new(state, 64, ShadowMapPrecision::Half).unwrap();
+            let graph = Graph::new();
+            let batch_storage = BatchStorage::default();
+            let mut geom_cache = GeometryCache::default();
+
+            for cascade in 0..3 {
+                let size = cascade_size(64, cascade);
+                match renderer.cascade_texture(cascade).borrow().kind() {
+                    GpuTextureKind::Cube { width, height } => {
+                        assert_eq!((width, height), (size, size))
+                    }
+                    _ => panic!("cascade {} must be a cube map", cascade),
+                }
+
+                // Every face of the cube map must be attachable.
+                renderer.render(PointShadowMapRenderContext {
+                    state,
+                    graph: &graph,
+                    light_pos: Vector3::default(),
+                    light_radius: 10.0,
+                    geom_cache: &mut geom_cache,
+                    cascade,
+                    batch_storage: &batch_storage,
+                });
+                assert!(!state.check_error());
+            }
+        });
+    }
+}
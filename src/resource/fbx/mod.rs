@@ -13,9 +13,9 @@ mod scene;
 use crate::core::io;
 use crate::engine::resource_manager::MaterialSearchOptions;
 use crate::scene::mesh::buffer::{VertexAttributeUsage, VertexWriteTrait};
-use crate::scene::mesh::vertex::{AnimatedVertex, StaticVertex};
+use crate::scene::mesh::vertex::{AnimatedVertex, ExtendedAnimatedVertex, StaticVertex};
 use crate::{
-    animation::{Animation, AnimationContainer, KeyFrame, Track},
+    animation::{Animation, AnimationContainer, KeyFrame, MorphCurve, MorphKeyFrame, Track},
     core::instant::Instant,
     core::{
         algebra::{Matrix4, Point3, UnitQuaternion, Vector2, Vector3, Vector4},
@@ -35,7 +35,8 @@ use crate::{
         base::BaseBuilder,
         graph::Graph,
         mesh::{
-            surface::{Surface, SurfaceData, VertexWeightSet},
+            morph::{MorphTarget, MorphTargetVertex},
+            surface::{Surface, SurfaceData, VertexWeightSet, MAX_VERTEX_WEIGHTS},
             MeshBuilder,
         },
         node::Node,
@@ -162,6 +163,23 @@ impl Into<AnimatedVertex> for UnpackedVertex {
     }
 }
 
+impl Into<ExtendedAnimatedVertex> for UnpackedVertex {
+    fn into(self) -> ExtendedAnimatedVertex {
+        ExtendedAnimatedVertex {
+            position: self.position,
+            tex_coord: self.uv,
+            normal: self.normal,
+            tangent: Vector4::new(self.tangent.x, self.tangent.y, self.tangent.z, 1.0),
+            // Correct values will be assigned in second pass of conversion
+            // when all nodes will be converted.
+            bone_weights: Default::default(),
+            bone_indices: Default::default(),
+            extra_bone_weights: Default::default(),
+            extra_bone_indices: Default::default(),
+        }
+    }
+}
+
 impl Into<StaticVertex> for UnpackedVertex {
     fn into(self) -> StaticVertex {
         StaticVertex {
@@ -223,6 +241,7 @@ fn convert_vertex(
 enum FbxMeshBuilder {
     Static(RawMeshBuilder<StaticVertex>),
     Animated(RawMeshBuilder<AnimatedVertex>),
+    ExtendedAnimated(RawMeshBuilder<ExtendedAnimatedVertex>),
 }
 
 impl FbxMeshBuilder {
@@ -234,6 +253,9 @@ impl FbxMeshBuilder {
             FbxMeshBuilder::Animated(builder) => {
                 SurfaceData::from_raw_mesh(builder.build(), AnimatedVertex::layout(), false)
             }
+            FbxMeshBuilder::ExtendedAnimated(builder) => {
+                SurfaceData::from_raw_mesh(builder.build(), ExtendedAnimatedVertex::layout(), false)
+            }
         }
     }
 }
@@ -242,6 +264,110 @@ impl FbxMeshBuilder {
 struct FbxSurfaceData {
     builder: FbxMeshBuilder,
     skin_data: Vec<VertexWeightSet>,
+    // Index of control point of every unique vertex, it is used to map blend shapes.
+    control_points: Vec<usize>,
+    morph_targets: Vec<MorphTarget>,
+}
+
+impl FbxSurfaceData {
+    fn build(self) -> (SurfaceData, Vec<VertexWeightSet>) {
+        let mut data = self.builder.build();
+        data.morph_targets = self.morph_targets;
+        (data, self.skin_data)
+    }
+}
+
+fn convert_blend_shapes(
+    fbx_scene: &FbxScene,
+    geom: &FbxGeometry,
+    geometric_transform: &Matrix4<f32>,
+    control_points: &[usize],
+) -> Result<Vec<MorphTarget>, FbxError> {
+    let mut morph_targets = Vec::new();
+    for &blend_shape_handle in geom.blend_shapes.iter() {
+        let blend_shape = fbx_scene.get(blend_shape_handle).as_blend_shape()?;
+        for &channel_handle in blend_shape.channels.iter() {
+            let channel = fbx_scene.get(channel_handle).as_blend_shape_channel()?;
+
+            // In-between shapes are not supported, only full shape is used.
+            let shape = match channel.shapes.last() {
+                Some(&shape_handle) => fbx_scene.get(shape_handle).as_shape()?,
+                None => continue,
+            };
+
+            let mut deltas = HashMap::new();
+            for (i, &control_point) in shape.indices.iter().enumerate() {
+                deltas.insert(
+                    control_point as usize,
+                    (
+                        shape.vertices[i],
+                        shape.normals.get(i).cloned().unwrap_or_default(),
+                    ),
+                );
+            }
+
+            let vertices = control_points
+                .iter()
+                .enumerate()
+                .filter_map(|(vertex_index, control_point)| {
+                    deltas
+                        .get(control_point)
+                        .map(|(position, normal)| MorphTargetVertex {
+                            index: vertex_index as u32,
+                            position: geometric_transform.transform_vector(position),
+                            normal: geometric_transform.transform_vector(normal),
+                            tangent: Default::default(),
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            // Surface is not affected by the shape at all.
+            if !vertices.is_empty() {
+                morph_targets.push(MorphTarget::new(&channel.name, vertices));
+            }
+        }
+    }
+    Ok(morph_targets)
+}
+
+fn convert_morph_curves(
+    fbx_scene: &FbxScene,
+    model: &FbxModel,
+) -> Result<Vec<MorphCurve>, FbxError> {
+    let mut curves = Vec::new();
+    for &geom_handle in model.geoms.iter() {
+        let geom = fbx_scene.get(geom_handle).as_geometry()?;
+        for &blend_shape_handle in geom.blend_shapes.iter() {
+            let blend_shape = fbx_scene.get(blend_shape_handle).as_blend_shape()?;
+            for &channel_handle in blend_shape.channels.iter() {
+                let channel = fbx_scene.get(channel_handle).as_blend_shape_channel()?;
+                if channel.animation_curve_node.is_none() {
+                    continue;
+                }
+                if let FbxComponent::AnimationCurveNode(curve_node) =
+                    fbx_scene.get(channel.animation_curve_node)
+                {
+                    if curve_node.actual_type != FbxAnimationCurveNodeType::DeformPercent {
+                        continue;
+                    }
+                    if let Some(FbxComponent::AnimationCurve(curve)) =
+                        curve_node.curves.first().map(|&h| fbx_scene.get(h))
+                    {
+                        // Weights are stored in [0; 100] range.
+                        curves.push(MorphCurve::new(
+                            &channel.name,
+                            curve
+                                .keys
+                                .iter()
+                                .map(|key| MorphKeyFrame::new(key.time, key.value / 100.0))
+                                .collect(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    Ok(curves)
 }
 
 async fn create_surfaces(
@@ -257,15 +383,16 @@ async fn create_surfaces(
     // Create surfaces per material
     if model.materials.is_empty() {
         assert_eq!(data_set.len(), 1);
-        let data = data_set.into_iter().next().unwrap();
-        let mut surface = Surface::new(Arc::new(RwLock::new(data.builder.build())));
-        surface.vertex_weights = data.skin_data;
+        let (data, skin_data) = data_set.into_iter().next().unwrap().build();
+        let mut surface = Surface::new(Arc::new(RwLock::new(data)));
+        surface.vertex_weights = skin_data;
         surfaces.push(surface);
     } else {
         assert_eq!(data_set.len(), model.materials.len());
         for (&material_handle, data) in model.materials.iter().zip(data_set.into_iter()) {
-            let mut surface = Surface::new(Arc::new(RwLock::new(data.builder.build())));
-            surface.vertex_weights = data.skin_data;
+            let (data, skin_data) = data.build();
+            let mut surface = Surface::new(Arc::new(RwLock::new(data)));
+            surface.vertex_weights = skin_data;
             let material = fbx_scene.get(material_handle).as_material()?;
             for (name, texture_handle) in material.textures.iter() {
                 let texture = fbx_scene.get(*texture_handle).as_texture()?;
@@ -345,18 +472,25 @@ async fn convert_mesh(
     let mut face_triangles = Vec::new();
 
     let mut mesh_surfaces = Vec::new();
+    let mut morph_weights = Vec::new();
     for &geom_handle in &model.geoms {
         let geom = fbx_scene.get(geom_handle).as_geometry()?;
         let skin_data = geom.get_skin_data(fbx_scene)?;
+        // Use larger vertex only if there are vertices affected by more than 4 bones.
+        let extended_skinning = skin_data.iter().any(|weights| weights.len() > 4);
 
         let mut data_set = vec![
             FbxSurfaceData {
                 builder: if geom.deformers.is_empty() {
                     FbxMeshBuilder::Static(RawMeshBuilder::new(1024, 1024))
+                } else if extended_skinning {
+                    FbxMeshBuilder::ExtendedAnimated(RawMeshBuilder::new(1024, 1024))
                 } else {
                     FbxMeshBuilder::Animated(RawMeshBuilder::new(1024, 1024))
                 },
                 skin_data: Default::default(),
+                control_points: Default::default(),
+                morph_targets: Default::default(),
             };
             model.materials.len().max(1)
         ];
@@ -388,11 +522,15 @@ async fn convert_mesh(
                     let is_unique_vertex = match data.builder {
                         FbxMeshBuilder::Static(ref mut builder) => builder.insert(vertex.into()),
                         FbxMeshBuilder::Animated(ref mut builder) => builder.insert(vertex.into()),
+                        FbxMeshBuilder::ExtendedAnimated(ref mut builder) => {
+                            builder.insert(vertex.into())
+                        }
                     };
                     if is_unique_vertex {
                         if let Some(skin_data) = weights {
                             data.skin_data.push(skin_data);
                         }
+                        data.control_points.push(index);
                     }
                 }
             }
@@ -403,6 +541,27 @@ async fn convert_mesh(
             }
         }
 
+        if !geom.blend_shapes.is_empty() {
+            for data in data_set.iter_mut() {
+                data.morph_targets = convert_blend_shapes(
+                    fbx_scene,
+                    geom,
+                    &geometric_transform,
+                    &data.control_points,
+                )?;
+            }
+
+            for &blend_shape_handle in geom.blend_shapes.iter() {
+                let blend_shape = fbx_scene.get(blend_shape_handle).as_blend_shape()?;
+                for &channel_handle in blend_shape.channels.iter() {
+                    let channel = fbx_scene.get(channel_handle).as_blend_shape_channel()?;
+                    if channel.deform_percent != 0.0 {
+                        morph_weights.push((channel.name.clone(), channel.deform_percent / 100.0));
+                    }
+                }
+            }
+        }
+
         let mut surfaces = create_surfaces(
             fbx_scene,
             data_set,
@@ -429,9 +588,17 @@ async fn convert_mesh(
         }
     }
 
-    Ok(MeshBuilder::new(base)
+    let handle = MeshBuilder::new(base)
         .with_surfaces(mesh_surfaces)
-        .build(graph))
+        .build(graph);
+
+    if let Node::Mesh(mesh) = &mut graph[handle] {
+        for (name, weight) in morph_weights {
+            mesh.set_morph_weight(name, weight);
+        }
+    }
+
+    Ok(handle)
 }
 
 fn convert_model_to_base(model: &FbxModel) -> BaseBuilder {
@@ -484,6 +651,9 @@ async fn convert_model(
     };
 
     // Convert animations
+    let mut track = Track::new();
+    track.set_node(node_handle);
+
     if !model.animation_curve_nodes.is_empty() {
         // Find supported curve nodes (translation, rotation, scale)
         let mut lcl_translation = None;
@@ -503,9 +673,6 @@ async fn convert_model(
         }
 
         // Convert to engine format
-        let mut track = Track::new();
-        track.set_node(node_handle);
-
         let node_local_rotation = quat_from_euler(model.rotation);
 
         let mut time = 0.0;
@@ -547,7 +714,13 @@ async fn convert_model(
 
             time = next_time;
         }
+    }
+
+    for curve in convert_morph_curves(fbx_scene, model)? {
+        track.add_morph_curve(curve);
+    }
 
+    if !track.get_key_frames().is_empty() || !track.morph_curves().is_empty() {
         animations.get_mut(animation_handle).add_track(track);
     }
 
@@ -617,13 +790,21 @@ async fn convert(
                 let data_rc = surface.data();
                 let mut data = data_rc.write().unwrap();
                 if data.vertex_buffer.vertex_count() as usize == surface.vertex_weights.len() {
+                    let extended = data
+                        .vertex_buffer
+                        .has_attribute(VertexAttributeUsage::ExtraBoneWeight);
                     let mut vertex_buffer_mut = data.vertex_buffer.modify();
                     for (mut view, weight_set) in vertex_buffer_mut
                         .iter_mut()
                         .zip(surface.vertex_weights.iter())
                     {
-                        let mut indices = Vector4::default();
-                        let mut weights = Vector4::default();
+                        let mut weight_set = *weight_set;
+                        if !extended {
+                            weight_set.truncate(4);
+                        }
+
+                        let mut indices = [0u8; MAX_VERTEX_WEIGHTS];
+                        let mut weights = [0.0f32; MAX_VERTEX_WEIGHTS];
                         for (k, weight) in weight_set.iter().enumerate() {
                             indices[k] = surface
                                 .bones
@@ -634,10 +815,28 @@ async fn convert(
                             weights[k] = weight.value;
                         }
 
-                        view.write_4_f32(VertexAttributeUsage::BoneWeight, weights)
+                        view.write_4_f32(
+                            VertexAttributeUsage::BoneWeight,
+                            Vector4::new(weights[0], weights[1], weights[2], weights[3]),
+                        )
+                        .unwrap();
+                        view.write_4_u8(
+                            VertexAttributeUsage::BoneIndices,
+                            Vector4::new(indices[0], indices[1], indices[2], indices[3]),
+                        )
+                        .unwrap();
+                        if extended {
+                            view.write_4_f32(
+                                VertexAttributeUsage::ExtraBoneWeight,
+                                Vector4::new(weights[4], weights[5], weights[6], weights[7]),
+                            )
                             .unwrap();
-                        view.write_4_u8(VertexAttributeUsage::BoneIndices, indices)
+                            view.write_4_u8(
+                                VertexAttributeUsage::ExtraBoneIndices,
+                                Vector4::new(indices[4], indices[5], indices[6], indices[7]),
+                            )
                             .unwrap();
+                        }
                     }
                 }
             }
@@ -93,6 +93,7 @@ pub enum FbxAnimationCurveNodeType {
     Translation,
     Rotation,
     Scale,
+    DeformPercent,
 }
 
 pub struct FbxAnimationCurveNode {
@@ -108,6 +109,9 @@ impl FbxAnimationCurveNode {
                 "T" | "AnimCurveNode::T" => FbxAnimationCurveNodeType::Translation,
                 "R" | "AnimCurveNode::R" => FbxAnimationCurveNodeType::Rotation,
                 "S" | "AnimCurveNode::S" => FbxAnimationCurveNodeType::Scale,
+                "DeformPercent" | "AnimCurveNode::DeformPercent" => {
+                    FbxAnimationCurveNodeType::DeformPercent
+                }
                 _ => FbxAnimationCurveNodeType::Unknown,
             },
             curves: Vec::new(),
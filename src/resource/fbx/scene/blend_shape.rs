@@ -0,0 +1,220 @@
+//! Blend shapes are stored in FBX as a chain of components:
+//!
+//! `Geometry (mesh) <- BlendShape <- BlendShapeChannel <- Geometry (shape)`
+//!
+//! Each channel is converted into a morph target of engine surfaces, `DeformPercent` of a
+//! channel is its weight in [0; 100] range, it can be animated by animation curve node that
+//! is linked with the channel.
+
+use crate::{
+    core::{algebra::Vector3, pool::Handle},
+    resource::fbx::{
+        document::{FbxNode, FbxNodeContainer},
+        error::FbxError,
+        scene::FbxComponent,
+    },
+};
+
+/// Offsets of control points of a geometry.
+pub struct FbxShape {
+    pub indices: Vec<i32>,
+    pub vertices: Vec<Vector3<f32>>,
+    /// Optional, some exporters do not write normals of shapes.
+    pub normals: Vec<Vector3<f32>>,
+}
+
+fn read_vec3_array(
+    shape_handle: Handle<FbxNode>,
+    nodes: &FbxNodeContainer,
+    name: &str,
+) -> Result<Vec<Vector3<f32>>, FbxError> {
+    let array_node_handle = nodes.find(shape_handle, name)?;
+    let array_node = nodes.get_by_name(array_node_handle, "a")?;
+    let mut values = Vec::with_capacity(array_node.attrib_count() / 3);
+    for value in array_node.attributes().chunks_exact(3) {
+        values.push(Vector3::new(
+            value[0].as_f32()?,
+            value[1].as_f32()?,
+            value[2].as_f32()?,
+        ));
+    }
+    Ok(values)
+}
+
+impl FbxShape {
+    pub(in crate::resource::fbx) fn read(
+        shape_handle: Handle<FbxNode>,
+        nodes: &FbxNodeContainer,
+    ) -> Result<Self, FbxError> {
+        let indices_handle = nodes.find(shape_handle, "Indexes")?;
+        let indices_node = nodes.get_by_name(indices_handle, "a")?;
+        let mut indices = Vec::with_capacity(indices_node.attrib_count());
+        for index in indices_node.attributes() {
+            indices.push(index.as_i32()?);
+        }
+
+        let vertices = read_vec3_array(shape_handle, nodes, "Vertices")?;
+        if vertices.len() != indices.len() {
+            return Err(FbxError::Custom(Box::new(String::from(
+                "FBX: Shape vertex count does not match index count!",
+            ))));
+        }
+
+        let normals = read_vec3_array(shape_handle, nodes, "Normals")
+            .ok()
+            .filter(|normals| normals.len() == indices.len())
+            .unwrap_or_default();
+
+        Ok(Self {
+            indices,
+            vertices,
+            normals,
+        })
+    }
+}
+
+pub struct FbxBlendShape {
+    pub channels: Vec<Handle<FbxComponent>>,
+}
+
+impl FbxBlendShape {
+    pub(in crate::resource::fbx) fn read() -> Self {
+        Self {
+            channels: Default::default(),
+        }
+    }
+}
+
+pub struct FbxBlendShapeChannel {
+    pub name: String,
+    /// Default weight of the channel in [0; 100] range.
+    pub deform_percent: f32,
+    /// Shapes of the channel, there could be more than one shape if channel has in-between
+    /// shapes, only last (full) shape is used.
+    pub shapes: Vec<Handle<FbxComponent>>,
+    pub animation_curve_node: Handle<FbxComponent>,
+}
+
+impl FbxBlendShapeChannel {
+    pub(in crate::resource::fbx) fn read(
+        channel_handle: Handle<FbxNode>,
+        nodes: &FbxNodeContainer,
+    ) -> Result<Self, FbxError> {
+        let channel_node = nodes.get(channel_handle);
+
+        let mut name = channel_node.get_attrib(1)?.as_string();
+        // Remove prefix
+        if let Some(stripped) = name.strip_prefix("SubDeformer::") {
+            name = stripped.to_owned();
+        }
+
+        let deform_percent = match nodes.find(channel_handle, "DeformPercent") {
+            Ok(deform_percent_handle) => {
+                nodes.get(deform_percent_handle).get_attrib(0)?.as_f32()?
+            }
+            Err(_) => 0.0,
+        };
+
+        Ok(Self {
+            name,
+            deform_percent,
+            shapes: Default::default(),
+            animation_curve_node: Handle::NONE,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Matrix4, Vector3},
+            futures::executor::block_on,
+        },
+        resource::fbx::{convert_blend_shapes, document::FbxDocument, scene::FbxScene},
+    };
+
+    const BLEND_SHAPE_FBX: &str = r#"; FBX 7.4.0 project file
+FBXHeaderExtension:  {
+    FBXVersion: 7400
+}
+Objects:  {
+    Geometry: 1, "Geometry::Mesh", "Mesh" {
+        Vertices: *9 {
+            a: 0,0,0,1,0,0,0,1,0
+        }
+        PolygonVertexIndex: *3 {
+            a: 0,1,-3
+        }
+    }
+    Deformer: 2, "Deformer::Face", "BlendShape" {
+    }
+    Deformer: 3, "SubDeformer::Smile", "BlendShapeChannel" {
+        DeformPercent: 50
+    }
+    Geometry: 4, "Geometry::Smile", "Shape" {
+        Indexes: *1 {
+            a: 2
+        }
+        Vertices: *3 {
+            a: 0,0.5,0
+        }
+        Normals: *3 {
+            a: 0,0,1
+        }
+    }
+}
+Connections:  {
+    C: "OO",2,1
+    C: "OO",3,2
+    C: "OO",4,3
+}
+"#;
+
+    #[test]
+    fn blend_shape_import() {
+        let path =
+            std::env::temp_dir().join(format!("rg3d_blend_shape_{}.fbx", std::process::id()));
+        std::fs::write(&path, BLEND_SHAPE_FBX).unwrap();
+        let document = block_on(FbxDocument::new(&path));
+        let _ = std::fs::remove_file(&path);
+
+        let scene = FbxScene::new(&document.unwrap()).unwrap();
+        let geom = scene
+            .pair_iter()
+            .find_map(|(_, c)| c.as_geometry().ok())
+            .unwrap();
+        assert_eq!(geom.blend_shapes.len(), 1);
+
+        let blend_shape = scene.get(geom.blend_shapes[0]).as_blend_shape().unwrap();
+        assert_eq!(blend_shape.channels.len(), 1);
+
+        let channel = scene
+            .get(blend_shape.channels[0])
+            .as_blend_shape_channel()
+            .unwrap();
+        assert_eq!(channel.name, "Smile");
+        assert_eq!(channel.deform_percent, 50.0);
+        assert_eq!(channel.shapes.len(), 1);
+
+        let shape = scene.get(channel.shapes[0]).as_shape().unwrap();
+        assert_eq!(shape.indices, vec![2]);
+        assert_eq!(shape.vertices, vec![Vector3::new(0.0, 0.5, 0.0)]);
+        assert_eq!(shape.normals, vec![Vector3::new(0.0, 0.0, 1.0)]);
+
+        // Control point 2 is used by vertices 1 and 3 of the surface.
+        let morph_targets =
+            convert_blend_shapes(&scene, geom, &Matrix4::new_scaling(2.0), &[0, 2, 1, 2]).unwrap();
+        assert_eq!(morph_targets.len(), 1);
+        assert_eq!(morph_targets[0].name, "Smile");
+        let vertices = &morph_targets[0].vertices;
+        assert_eq!(
+            vertices.iter().map(|v| v.index).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert!(vertices
+            .iter()
+            .all(|v| v.position == Vector3::new(0.0, 1.0, 0.0)
+                && v.normal == Vector3::new(0.0, 0.0, 2.0)));
+    }
+}
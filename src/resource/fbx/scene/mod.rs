@@ -9,6 +9,7 @@ use crate::{
         fix_index,
         scene::{
             animation::{FbxAnimationCurve, FbxAnimationCurveNode},
+            blend_shape::{FbxBlendShape, FbxBlendShapeChannel, FbxShape},
             geometry::FbxGeometry,
             light::FbxLight,
             model::FbxModel,
@@ -19,6 +20,7 @@ use crate::{
 use std::collections::HashMap;
 
 pub mod animation;
+pub mod blend_shape;
 pub mod geometry;
 pub mod light;
 pub mod model;
@@ -53,9 +55,14 @@ impl FbxScene {
             let mut component_handle: Handle<FbxComponent> = Handle::NONE;
             match object.name() {
                 "Geometry" => {
-                    component_handle = components.spawn(FbxComponent::Geometry(Box::new(
-                        FbxGeometry::read(*object_handle, nodes)?,
-                    )));
+                    if object.attrib_count() > 2 && object.get_attrib(2)?.as_string() == "Shape" {
+                        component_handle = components
+                            .spawn(FbxComponent::Shape(FbxShape::read(*object_handle, nodes)?));
+                    } else {
+                        component_handle = components.spawn(FbxComponent::Geometry(Box::new(
+                            FbxGeometry::read(*object_handle, nodes)?,
+                        )));
+                    }
                 }
                 "Model" => {
                     component_handle = components.spawn(FbxComponent::Model(Box::new(
@@ -99,6 +106,15 @@ impl FbxScene {
                             FbxDeformer::read(*object_handle, nodes),
                         ));
                     }
+                    "BlendShape" => {
+                        component_handle =
+                            components.spawn(FbxComponent::BlendShape(FbxBlendShape::read()));
+                    }
+                    "BlendShapeChannel" => {
+                        component_handle = components.spawn(FbxComponent::BlendShapeChannel(
+                            FbxBlendShapeChannel::read(*object_handle, nodes)?,
+                        ));
+                    }
                     _ => (),
                 },
                 _ => (),
@@ -173,12 +189,24 @@ fn link_child_with_parent_component(
                 deformer.sub_deformers.push(child_handle);
             }
         }
-        // Link geometry with deformers
-        FbxComponent::Geometry(geometry) => {
-            if let FbxComponent::Deformer(_) = child {
-                geometry.deformers.push(child_handle);
+        // Link geometry with deformers and blend shapes
+        FbxComponent::Geometry(geometry) => match child {
+            FbxComponent::Deformer(_) => geometry.deformers.push(child_handle),
+            FbxComponent::BlendShape(_) => geometry.blend_shapes.push(child_handle),
+            _ => (),
+        },
+        // Link blend shape with its channels
+        FbxComponent::BlendShape(blend_shape) => {
+            if let FbxComponent::BlendShapeChannel(_) = child {
+                blend_shape.channels.push(child_handle);
             }
         }
+        // Link blend shape channel with shapes and weight animation
+        FbxComponent::BlendShapeChannel(channel) => match child {
+            FbxComponent::Shape(_) => channel.shapes.push(child_handle),
+            FbxComponent::AnimationCurveNode(_) => channel.animation_curve_node = child_handle,
+            _ => (),
+        },
         // Link sub-deformer with model
         FbxComponent::SubDeformer(sub_deformer) => {
             if let FbxComponent::Model(model) = child {
@@ -201,6 +229,9 @@ pub enum FbxComponent {
     AnimationCurveNode(FbxAnimationCurveNode),
     AnimationCurve(FbxAnimationCurve),
     Geometry(Box<FbxGeometry>),
+    Shape(FbxShape),
+    BlendShape(FbxBlendShape),
+    BlendShapeChannel(FbxBlendShapeChannel),
 }
 
 macro_rules! define_as {
@@ -222,6 +253,14 @@ impl FbxComponent {
     define_as!(self, as_light, FbxLight, Light);
     define_as!(self, as_material, FbxMaterial, Material);
     define_as!(self, as_geometry, FbxGeometry, Geometry);
+    define_as!(self, as_shape, FbxShape, Shape);
+    define_as!(self, as_blend_shape, FbxBlendShape, BlendShape);
+    define_as!(
+        self,
+        as_blend_shape_channel,
+        FbxBlendShapeChannel,
+        BlendShapeChannel
+    );
 }
 
 // https://help.autodesk.com/view/FBX/2016/ENU/?guid=__cpp_ref_class_fbx_anim_curve_html
@@ -0,0 +1,594 @@
+#![warn(missing_docs)]
+
+//! Material is a set of properties that defines appearance of a surface.
+//!
+//! Material consists of two parts: shader definition and property values. Shader definition
+//! describes render passes (a pair of vertex and fragment GLSL programs per pass), and a list
+//! of properties with their types and default values. Property values of a material override
+//! default values of the shader, so many materials can share same shader definition and differ
+//! only by property values (textures, colors, etc).
+//!
+//! # Render passes
+//!
+//! Renderer looks for passes with specific names:
+//!
+//! - `GBuffer` - used when a mesh with the material uses deferred render path. Fragment shader
+//!   must write into four outputs: diffuse color, normal (packed in \[0; 1\] range), ambient
+//!   color and material properties (location 3) with reflection factor in red channel, like the
+//!   standard G-Buffer shader does. Contents of an output that is not written by a shader are
+//!   undefined, so non-reflective surfaces must explicitly write zero into location 3.
+//! - `Forward` - used when a mesh with the material uses forward render path. Fragment shader
+//!   writes single color output.
+//!
+//! If a material does not have a pass for a render path, the surface will be rendered using
+//! standard shaders of the engine.
+//!
+//! # Built-in uniforms
+//!
+//! Renderer provides following uniforms to every pass (if a program uses them):
+//!
+//! - `mat4 worldMatrix` - world transform of an instance.
+//! - `mat4 worldViewProjection` - world-view-projection matrix of an instance.
+//! - `bool useSkeletalAnimation` - true if a surface is skinned.
+//! - `mat4 boneMatrices[60]` - bone matrices of an instance. Bone weights and indices are
+//!   bound to attribute locations 4 and 5, second set of weights and indices of surfaces with
+//!   up to 8 bones per vertex is bound to locations 13 and 14 (zeros for other surfaces).
+//! - `vec3 cameraPosition` - world-space position of a camera.
+//!
+//! Every property of a material is bound to a uniform with the same name.
+//!
+//! # Example
+//!
+//! ```
+//! use rg3d::{
+//!     core::color::Color,
+//!     resource::material::{
+//!         Material, MaterialData, PropertyDefinition, PropertyValue, RenderPassDefinition,
+//!         ShaderDefinition,
+//!     },
+//! };
+//!
+//! let shader = ShaderDefinition {
+//!     name: "Unlit".to_owned(),
+//!     passes: vec![RenderPassDefinition {
+//!         name: "Forward".to_owned(),
+//!         vertex_shader: r#"
+//!             #version 330 core
+//!             layout(location = 0) in vec3 vertexPosition;
+//!             uniform mat4 worldViewProjection;
+//!             void main() { gl_Position = worldViewProjection * vec4(vertexPosition, 1.0); }
+//!         "#
+//!         .to_owned(),
+//!         fragment_shader: r#"
+//!             #version 330 core
+//!             uniform vec4 tint;
+//!             out vec4 FragColor;
+//!             void main() { FragColor = tint; }
+//!         "#
+//!         .to_owned(),
+//!     }],
+//!     properties: vec![PropertyDefinition {
+//!         name: "tint".to_owned(),
+//!         default: PropertyValue::Color(Color::WHITE),
+//!     }],
+//! };
+//!
+//! let mut material = MaterialData::new(shader);
+//! material
+//!     .set_property("tint", PropertyValue::Color(Color::opaque(255, 0, 0)))
+//!     .unwrap();
+//! let material = Material::from(material);
+//! ```
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Vector2, Vector3, Vector4},
+        color::Color,
+        visitor::{Visit, VisitError, VisitResult, Visitor},
+    },
+    engine::resource_manager::ResourceManager,
+    resource::{texture::Texture, Resource, ResourceData, ResourceState},
+};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+/// A texture that will be used if a sampler property has no texture.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Visit)]
+#[repr(u32)]
+pub enum SamplerFallback {
+    /// A 1x1 white texture.
+    White = 0,
+    /// A 1x1 black texture.
+    Black = 1,
+    /// A 1x1 texture with (0, 1, 0) normal vector.
+    Normal = 2,
+}
+
+impl Default for SamplerFallback {
+    fn default() -> Self {
+        Self::White
+    }
+}
+
+/// Value of a material property.
+#[derive(Clone, Debug, Visit)]
+pub enum PropertyValue {
+    /// Real number.
+    Float(f32),
+    /// Integer number.
+    Int(i32),
+    /// Boolean value.
+    Bool(bool),
+    /// Two-dimensional vector.
+    Vector2(Vector2<f32>),
+    /// Three-dimensional vector.
+    Vector3(Vector3<f32>),
+    /// Four-dimensional vector.
+    Vector4(Vector4<f32>),
+    /// Color, it is passed to a shader as `vec4` in \[0; 1\] range.
+    Color(Color),
+    /// 4x4 matrix.
+    Matrix4(Matrix4<f32>),
+    /// Texture.
+    Sampler {
+        /// Actual texture, if it is `None`, then fallback texture will be used.
+        value: Option<Texture>,
+        /// Texture that will be used if there is no actual texture or it is not loaded yet.
+        fallback: SamplerFallback,
+    },
+}
+
+impl Default for PropertyValue {
+    fn default() -> Self {
+        Self::Float(0.0)
+    }
+}
+
+impl Hash for PropertyValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        // Floats are hashed by their bits, textures - by their paths.
+        let floats = match self {
+            PropertyValue::Float(v) => std::slice::from_ref(v),
+            PropertyValue::Int(v) => {
+                v.hash(state);
+                &[]
+            }
+            PropertyValue::Bool(v) => {
+                v.hash(state);
+                &[]
+            }
+            PropertyValue::Vector2(v) => v.as_slice(),
+            PropertyValue::Vector3(v) => v.as_slice(),
+            PropertyValue::Vector4(v) => v.as_slice(),
+            PropertyValue::Color(v) => {
+                let v: u32 = (*v).into();
+                v.hash(state);
+                &[]
+            }
+            PropertyValue::Matrix4(v) => v.as_slice(),
+            PropertyValue::Sampler { value, fallback } => {
+                value
+                    .as_ref()
+                    .map(|texture| texture.state().path().into_owned())
+                    .hash(state);
+                fallback.hash(state);
+                &[]
+            }
+        };
+
+        for v in floats {
+            v.to_bits().hash(state);
+        }
+    }
+}
+
+impl PropertyValue {
+    /// Returns true if both values have same type.
+    pub fn is_same_kind(&self, other: &PropertyValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Returns texture of a sampler property, `None` for other kinds of properties.
+    pub fn as_sampler(&self) -> Option<&Texture> {
+        if let PropertyValue::Sampler { value, .. } = self {
+            value.as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+/// Declaration of a property of a shader.
+#[derive(Clone, Debug, Default, Visit, Hash)]
+pub struct PropertyDefinition {
+    /// Name of the property, it must match the name of a uniform in shader programs.
+    pub name: String,
+    /// Default value of the property, it also defines the type of the property.
+    pub default: PropertyValue,
+}
+
+/// A render pass of a shader.
+#[derive(Clone, Debug, Default, Visit, Hash)]
+pub struct RenderPassDefinition {
+    /// Name of the pass. See module docs for the list of passes that are used by renderer.
+    pub name: String,
+    /// Source code of a vertex shader in GLSL.
+    pub vertex_shader: String,
+    /// Source code of a fragment shader in GLSL.
+    pub fragment_shader: String,
+}
+
+/// Shader definition describes render passes and properties of a shader.
+#[derive(Clone, Debug, Default, Visit, Hash)]
+pub struct ShaderDefinition {
+    /// Name of the shader.
+    pub name: String,
+    /// A list of render passes.
+    pub passes: Vec<RenderPassDefinition>,
+    /// A list of property declarations.
+    pub properties: Vec<PropertyDefinition>,
+}
+
+impl ShaderDefinition {
+    /// Tries to find a pass with given name.
+    pub fn find_pass(&self, name: &str) -> Option<&RenderPassDefinition> {
+        self.passes.iter().find(|p| p.name == name)
+    }
+
+    /// Tries to find a property declaration with given name.
+    pub fn find_property(&self, name: &str) -> Option<&PropertyDefinition> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Calculates hash of the whole shader definition. GPU programs of a shader depend not
+    /// only on source code of passes, but also on declared properties, so only shaders with
+    /// equal definitions can share same GPU programs.
+    pub fn source_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// All possible errors that may occur while working with a material.
+#[derive(Debug)]
+pub enum MaterialError {
+    /// An error occurred while reading a data source.
+    Visit(VisitError),
+    /// Shader does not declare a property with given name.
+    NoSuchProperty(String),
+    /// Type of a value does not match type of a property declaration.
+    TypeMismatch {
+        /// Name of the property.
+        property: String,
+        /// Expected value (default value of the property).
+        expected: Box<PropertyValue>,
+        /// Actual value that was passed.
+        actual: Box<PropertyValue>,
+    },
+}
+
+impl From<VisitError> for MaterialError {
+    fn from(e: VisitError) -> Self {
+        Self::Visit(e)
+    }
+}
+
+/// See module docs.
+#[derive(Debug, Default)]
+pub struct MaterialData {
+    path: PathBuf,
+    shader: ShaderDefinition,
+    properties: HashMap<String, PropertyValue>,
+}
+
+/// See module docs.
+pub type Material = Resource<MaterialData, MaterialError>;
+
+impl From<MaterialData> for Material {
+    fn from(data: MaterialData) -> Self {
+        Self::new(ResourceState::Ok(data))
+    }
+}
+
+impl ResourceData for MaterialData {
+    fn path(&self) -> Cow<Path> {
+        Cow::Borrowed(&self.path)
+    }
+
+    fn set_path(&mut self, path: PathBuf) {
+        self.path = path;
+    }
+}
+
+impl Visit for MaterialData {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.path.visit("Path", visitor)?;
+        self.shader.visit("Shader", visitor)?;
+        self.properties.visit("Properties", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+impl MaterialData {
+    /// Creates new material with given shader definition. Every property will have default
+    /// value.
+    pub fn new(shader: ShaderDefinition) -> Self {
+        Self {
+            path: Default::default(),
+            shader,
+            properties: Default::default(),
+        }
+    }
+
+    /// Loads material from a file, that was previously saved by [`Self::save`]. Textures of
+    /// the material will be requested from given resource manager.
+    pub async fn load_from_file<P: AsRef<Path>>(
+        path: P,
+        resource_manager: ResourceManager,
+    ) -> Result<Self, MaterialError> {
+        let mut visitor = Visitor::load_binary(path.as_ref()).await?;
+        let mut material = MaterialData::default();
+        material.visit("Material", &mut visitor)?;
+        material.path = path.as_ref().to_owned();
+
+        material.restore_textures(&resource_manager);
+
+        Ok(material)
+    }
+
+    /// Material stores only paths to textures, this method replaces them with real resources.
+    pub(in crate) fn restore_textures(&mut self, resource_manager: &ResourceManager) {
+        for value in self
+            .properties
+            .values_mut()
+            .chain(self.shader.properties.iter_mut().map(|p| &mut p.default))
+        {
+            if let PropertyValue::Sampler {
+                value: Some(texture),
+                ..
+            } = value
+            {
+                let path = texture.state().path().to_path_buf();
+                *texture = resource_manager.request_texture(path);
+            }
+        }
+    }
+
+    /// Saves material to a file.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> VisitResult {
+        let mut visitor = Visitor::new();
+        self.visit("Material", &mut visitor)?;
+        visitor.save_binary(path)
+    }
+
+    /// Returns shader definition of the material.
+    pub fn shader(&self) -> &ShaderDefinition {
+        &self.shader
+    }
+
+    /// Sets new value of a property. The property must be declared in the shader definition
+    /// and value must have same type as default value of the property.
+    pub fn set_property(&mut self, name: &str, value: PropertyValue) -> Result<(), MaterialError> {
+        let definition = self
+            .shader
+            .find_property(name)
+            .ok_or_else(|| MaterialError::NoSuchProperty(name.to_owned()))?;
+
+        if definition.default.is_same_kind(&value) {
+            self.properties.insert(name.to_owned(), value);
+            Ok(())
+        } else {
+            Err(MaterialError::TypeMismatch {
+                property: name.to_owned(),
+                expected: Box::new(definition.default.clone()),
+                actual: Box::new(value),
+            })
+        }
+    }
+
+    /// Resets a property to its default value.
+    pub fn reset_property(&mut self, name: &str) {
+        self.properties.remove(name);
+    }
+
+    /// Returns value of a property, if the property was not set, then default value from
+    /// shader definition will be returned.
+    pub fn property(&self, name: &str) -> Option<&PropertyValue> {
+        self.properties
+            .get(name)
+            .or_else(|| self.shader.find_property(name).map(|p| &p.default))
+    }
+
+    /// Returns an iterator over (name, value) pairs of every declared property. Values that
+    /// were not set explicitly will have default values.
+    pub fn properties(&self) -> impl Iterator<Item = (&str, &PropertyValue)> {
+        self.shader.properties.iter().map(move |definition| {
+            (
+                definition.name.as_str(),
+                self.properties
+                    .get(&definition.name)
+                    .unwrap_or(&definition.default),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{color::Color, futures::executor::block_on},
+        engine::resource_manager::ResourceManager,
+        resource::{
+            material::{
+                MaterialData, MaterialError, PropertyDefinition, PropertyValue,
+                RenderPassDefinition, SamplerFallback, ShaderDefinition,
+            },
+            texture::Texture,
+            ResourceState,
+        },
+    };
+
+    fn make_shader() -> ShaderDefinition {
+        ShaderDefinition {
+            name: "Test".to_owned(),
+            passes: vec![RenderPassDefinition {
+                name: "Forward".to_owned(),
+                vertex_shader: "void main() {}".to_owned(),
+                fragment_shader: "void main() {}".to_owned(),
+            }],
+            properties: vec![
+                PropertyDefinition {
+                    name: "tint".to_owned(),
+                    default: PropertyValue::Color(Color::WHITE),
+                },
+                PropertyDefinition {
+                    name: "diffuseTexture".to_owned(),
+                    default: PropertyValue::Sampler {
+                        value: None,
+                        fallback: SamplerFallback::White,
+                    },
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn material_properties() {
+        let mut material = MaterialData::new(ShaderDefinition {
+            name: "Test".to_owned(),
+            passes: vec![],
+            properties: vec![
+                PropertyDefinition {
+                    name: "tint".to_owned(),
+                    default: PropertyValue::Color(Color::WHITE),
+                },
+                PropertyDefinition {
+                    name: "strength".to_owned(),
+                    default: PropertyValue::Float(1.0),
+                },
+            ],
+        });
+
+        assert!(matches!(
+            material.property("strength"),
+            Some(PropertyValue::Float(v)) if *v == 1.0
+        ));
+
+        material
+            .set_property("strength", PropertyValue::Float(2.0))
+            .unwrap();
+        assert!(matches!(
+            material.property("strength"),
+            Some(PropertyValue::Float(v)) if *v == 2.0
+        ));
+
+        assert!(matches!(
+            material.set_property("strength", PropertyValue::Int(2)),
+            Err(MaterialError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            material.set_property("unknown", PropertyValue::Int(2)),
+            Err(MaterialError::NoSuchProperty(_))
+        ));
+
+        material.reset_property("strength");
+        assert!(matches!(
+            material.property("strength"),
+            Some(PropertyValue::Float(v)) if *v == 1.0
+        ));
+        assert_eq!(material.properties().count(), 2);
+    }
+
+    #[test]
+    fn source_hash_covers_whole_definition() {
+        let shader = make_shader();
+        assert_eq!(shader.source_hash(), make_shader().source_hash());
+
+        // Same passes, but different properties - uniforms of GPU programs will differ.
+        let mut other = make_shader();
+        other.properties[0].name = "color".to_owned();
+        assert_ne!(shader.source_hash(), other.source_hash());
+
+        let mut other = make_shader();
+        other.properties[1].default = PropertyValue::Sampler {
+            value: None,
+            fallback: SamplerFallback::Normal,
+        };
+        assert_ne!(shader.source_hash(), other.source_hash());
+
+        let mut other = make_shader();
+        other.name = "Other".to_owned();
+        assert_ne!(shader.source_hash(), other.source_hash());
+    }
+
+    #[test]
+    fn material_resource_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "{}_rg3d_material_round_trip.material",
+            std::process::id()
+        ));
+        let texture_path = std::env::temp_dir().join("rg3d_material_missing_texture.png");
+
+        let mut material = MaterialData::new(make_shader());
+        material
+            .set_property("tint", PropertyValue::Color(Color::opaque(10, 20, 30)))
+            .unwrap();
+        material
+            .set_property(
+                "diffuseTexture",
+                PropertyValue::Sampler {
+                    // Texture file does not exist, but path of the texture must be kept.
+                    value: Some(Texture::new(ResourceState::LoadError {
+                        path: texture_path.clone(),
+                        error: None,
+                    })),
+                    fallback: SamplerFallback::Black,
+                },
+            )
+            .unwrap();
+        material.save(&path).unwrap();
+
+        let resource_manager = ResourceManager::new_headless();
+        let loaded = block_on(resource_manager.request_material(&path)).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Second request must give the same resource.
+        assert_eq!(resource_manager.request_material(&path).key(), loaded.key());
+
+        let loaded = loaded.data_ref();
+        assert_eq!(loaded.path, path);
+        assert_eq!(
+            loaded.shader().source_hash(),
+            material.shader().source_hash()
+        );
+        assert_eq!(loaded.properties().count(), 2);
+        assert!(matches!(
+            loaded.property("tint"),
+            Some(PropertyValue::Color(c)) if *c == Color::opaque(10, 20, 30)
+        ));
+        match loaded.property("diffuseTexture") {
+            Some(PropertyValue::Sampler {
+                value: Some(texture),
+                fallback: SamplerFallback::Black,
+            }) => {
+                // Texture must be requested from resource manager.
+                assert_eq!(texture.state().path(), texture_path.as_path());
+                assert_eq!(
+                    resource_manager.request_texture(&texture_path).key(),
+                    texture.key()
+                );
+            }
+            value => panic!("unexpected value {:?}", value),
+        }
+    }
+}
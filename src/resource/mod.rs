@@ -15,8 +15,11 @@ use std::{
 };
 
 pub mod fbx;
+pub mod material;
 pub mod model;
+pub mod procedural_texture;
 pub mod texture;
+pub mod texture_cooker;
 
 /// A trait for resource data.
 pub trait ResourceData: 'static + Default + Debug + Visit + Send {
@@ -30,10 +30,14 @@ use crate::{
         Resource, ResourceData,
     },
     scene::{node::Node, Scene},
-    utils::log::{Log, MessageKind},
+    utils::{
+        log::{Log, MessageKind},
+        navmesh::Navmesh,
+    },
 };
 use std::{
     borrow::Cow,
+    collections::HashMap,
     path::{Path, PathBuf},
 };
 
@@ -67,6 +71,25 @@ impl Model {
             &mut dest_scene.graph,
             &mut |_, _| true,
         );
+
+        std::mem::drop(data);
+
+        self.finish_instantiation(dest_scene, root, old_to_new);
+
+        root
+    }
+
+    /// Turns a copy of the graph of the model into an instance of the model: marks instantiated
+    /// nodes, embeds navmeshes and physics entities. `old_to_new` must contain every node of
+    /// the graph of the model, handles in the copy must be already remapped.
+    pub(in crate) fn finish_instantiation(
+        &self,
+        dest_scene: &mut Scene,
+        root: Handle<Node>,
+        old_to_new: HashMap<Handle<Node>, Handle<Node>>,
+    ) -> Vec<Handle<Navmesh>> {
+        let data = self.data_ref();
+
         dest_scene.graph[root].is_resource_instance_root = true;
 
         // Notify instantiated nodes about resource they were created from.
@@ -88,9 +111,12 @@ impl Model {
         // Embed navmeshes.
         // TODO: This also must provide a map which will make it possible to extract navmesh
         // from resource later on.
-        for navmesh in data.scene.navmeshes.iter() {
-            dest_scene.navmeshes.add(navmesh.clone());
-        }
+        let navmeshes = data
+            .scene
+            .navmeshes
+            .iter()
+            .map(|navmesh| dest_scene.navmeshes.add(navmesh.clone()))
+            .collect();
 
         std::mem::drop(data);
 
@@ -101,7 +127,7 @@ impl Model {
             self.clone(),
         );
 
-        root
+        navmeshes
     }
 
     /// Tries to instantiate model from given resource.
@@ -0,0 +1,581 @@
+//! Procedural textures are generated on CPU from mathematical patterns instead of loaded from
+//! files.
+//!
+//! # Overview
+//!
+//! Generation is done in two steps: first a [`HeightMap`] is generated from some [`Pattern`],
+//! it is a scalar field where each value is in `[0; 1]` range. Then the height map is converted
+//! to a texture: as is (grayscale), by mapping values to colors using a color gradient, or into
+//! a normal map. Generation runs in parallel on every available CPU core, so it is fast enough
+//! to be done at load time.
+//!
+//! # Seamless tiling
+//!
+//! Noise patterns can be generated to tile seamlessly, it means that the left edge of a texture
+//! matches its right edge, and the top edge matches the bottom edge. Perlin and Worley noise
+//! tile exactly, simplex noise is tiled by blending four shifted samples, which slightly reduces
+//! its contrast. Checkerboards with integer amount of cells always tile.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::{
+//!         color::Color,
+//!         color_gradient::{ColorGradientBuilder, GradientPoint},
+//!     },
+//!     resource::{
+//!         procedural_texture::{HeightMap, Noise, NoiseKind, Pattern},
+//!         texture::Texture,
+//!     },
+//! };
+//!
+//! fn make_rock_textures() -> (Texture, Texture) {
+//!     let height_map = HeightMap::generate(
+//!         256,
+//!         256,
+//!         &Pattern::Noise(
+//!             Noise::new(NoiseKind::Perlin)
+//!                 .with_frequency(8)
+//!                 .with_octaves(5)
+//!                 .with_seamless(true),
+//!         ),
+//!     );
+//!
+//!     let gradient = ColorGradientBuilder::new()
+//!         .with_point(GradientPoint::new(0.0, Color::opaque(60, 50, 40)))
+//!         .with_point(GradientPoint::new(1.0, Color::opaque(170, 160, 150)))
+//!         .build();
+//!
+//!     (
+//!         height_map.to_color_texture(&gradient),
+//!         height_map.to_normal_map(4.0, true),
+//!     )
+//! }
+//! ```
+
+use crate::{
+    core::color_gradient::ColorGradient,
+    resource::texture::{Texture, TextureKind, TexturePixelKind},
+};
+use rayon::prelude::*;
+
+/// Kind of noise.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Classic gradient noise.
+    Perlin,
+
+    /// Gradient noise on a simplex (triangular) grid, has less directional artifacts than
+    /// Perlin noise.
+    Simplex,
+
+    /// Cellular noise, value is a distance to the closest feature point.
+    Worley,
+}
+
+/// Fractal noise settings. Multiple octaves of noise are summed together (fBm - fractional
+/// Brownian motion), each next octave has 2 times higher frequency and `gain` times lower
+/// amplitude than previous.
+#[derive(Clone, Debug)]
+pub struct Noise {
+    kind: NoiseKind,
+    seed: u32,
+    frequency: u32,
+    octaves: u32,
+    gain: f32,
+    seamless: bool,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new(NoiseKind::Perlin)
+    }
+}
+
+impl Noise {
+    /// Creates single octave noise of given kind with frequency of 4 cells per texture.
+    pub fn new(kind: NoiseKind) -> Self {
+        Self {
+            kind,
+            seed: 0,
+            frequency: 4,
+            octaves: 1,
+            gain: 0.5,
+            seamless: false,
+        }
+    }
+
+    /// Sets seed of the noise, different seeds give different patterns.
+    pub fn with_seed(mut self, seed: u32) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets amount of noise cells per texture for first octave. Frequency is integer to make
+    /// seamless tiling possible.
+    pub fn with_frequency(mut self, frequency: u32) -> Self {
+        self.frequency = frequency.max(1);
+        self
+    }
+
+    /// Sets amount of octaves.
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    /// Sets amplitude multiplier for each next octave. Usually it is in `(0; 1)` range.
+    pub fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Enables or disables seamless tiling.
+    pub fn with_seamless(mut self, seamless: bool) -> Self {
+        self.seamless = seamless;
+        self
+    }
+
+    // Returns value in [0; 1] range for given normalized coordinates.
+    fn sample(&self, u: f32, v: f32) -> f32 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut total_amplitude = 0.0;
+        let mut frequency = self.frequency;
+        for octave in 0..self.octaves {
+            // Each octave must have its own pattern.
+            let seed = self.seed.wrapping_add(octave.wrapping_mul(0x9e37_79b9));
+            sum += amplitude * self.sample_octave(u, v, frequency, seed);
+            total_amplitude += amplitude;
+            amplitude *= self.gain;
+            frequency = frequency.saturating_mul(2);
+        }
+        (sum / total_amplitude * 0.5 + 0.5).max(0.0).min(1.0)
+    }
+
+    // Returns value in [-1; 1] range.
+    fn sample_octave(&self, u: f32, v: f32, frequency: u32, seed: u32) -> f32 {
+        let x = u * frequency as f32;
+        let y = v * frequency as f32;
+        let period = if self.seamless {
+            Some(frequency as i32)
+        } else {
+            None
+        };
+        match self.kind {
+            NoiseKind::Perlin => perlin(x, y, period, seed),
+            NoiseKind::Worley => worley(x, y, period, seed),
+            NoiseKind::Simplex => {
+                if self.seamless {
+                    // Simplex grid cannot be wrapped, so blend samples shifted by one period
+                    // instead - result matches at the opposite edges.
+                    let f = frequency as f32;
+                    let top = lerp(simplex(x, y, seed), simplex(x - f, y, seed), u);
+                    let bottom = lerp(simplex(x, y - f, seed), simplex(x - f, y - f, seed), u);
+                    lerp(top, bottom, v)
+                } else {
+                    simplex(x, y, seed)
+                }
+            }
+        }
+    }
+}
+
+/// Pattern defines a scalar field which is used to generate height map.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// Fractal noise.
+    Noise(Noise),
+
+    /// Checkerboard with given amount of cells, black cell is in the top left corner.
+    Checkerboard {
+        /// Amount of cells along horizontal axis.
+        cells_x: u32,
+        /// Amount of cells along vertical axis.
+        cells_y: u32,
+    },
+
+    /// Linear gradient from 0 to 1 which goes through the center of the texture in given
+    /// direction. Angle is in radians, zero angle means left-to-right gradient.
+    LinearGradient {
+        /// Angle of the gradient in radians.
+        angle: f32,
+    },
+
+    /// Radial gradient, 0 in the center and 1 at the edges of the texture.
+    RadialGradient,
+}
+
+impl Pattern {
+    fn sample(&self, u: f32, v: f32) -> f32 {
+        match self {
+            Pattern::Noise(noise) => noise.sample(u, v),
+            Pattern::Checkerboard { cells_x, cells_y } => {
+                let x = (u * *cells_x as f32).floor() as i64;
+                let y = (v * *cells_y as f32).floor() as i64;
+                ((x + y) & 1) as f32
+            }
+            Pattern::LinearGradient { angle } => {
+                let (sin, cos) = angle.sin_cos();
+                // Projection of a corner of the texture on the direction.
+                let extent = 0.5 * (cos.abs() + sin.abs());
+                let t = ((u - 0.5) * cos + (v - 0.5) * sin) / extent;
+                (t * 0.5 + 0.5).max(0.0).min(1.0)
+            }
+            Pattern::RadialGradient => {
+                let dx = u - 0.5;
+                let dy = v - 0.5;
+                ((dx * dx + dy * dy).sqrt() * 2.0).min(1.0)
+            }
+        }
+    }
+}
+
+/// Scalar field with values in `[0; 1]` range, see module docs.
+#[derive(Clone, Debug)]
+pub struct HeightMap {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl HeightMap {
+    /// Generates new height map of given size using given pattern.
+    pub fn generate(width: u32, height: u32, pattern: &Pattern) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        let mut values = vec![0.0; width as usize * height as usize];
+        values
+            .par_chunks_mut(width as usize)
+            .enumerate()
+            .for_each(|(y, row)| {
+                // Sample at the center of each pixel.
+                let v = (y as f32 + 0.5) / height as f32;
+                for (x, value) in row.iter_mut().enumerate() {
+                    let u = (x as f32 + 0.5) / width as f32;
+                    *value = pattern.sample(u, v);
+                }
+            });
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Returns width of the height map.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns height of the height map.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns values of the height map, row by row.
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Returns value at given pixel.
+    pub fn value(&self, x: u32, y: u32) -> f32 {
+        self.values[y as usize * self.width as usize + x as usize]
+    }
+
+    fn value_at(&self, x: i64, y: i64, wrap: bool) -> f32 {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (x, y) = if wrap {
+            (x.rem_euclid(w), y.rem_euclid(h))
+        } else {
+            (x.max(0).min(w - 1), y.max(0).min(h - 1))
+        };
+        self.values[(y * w + x) as usize]
+    }
+
+    fn make_texture(&self, pixel_kind: TexturePixelKind, bytes: Vec<u8>) -> Texture {
+        Texture::from_bytes(
+            TextureKind::Rectangle {
+                width: self.width,
+                height: self.height,
+            },
+            pixel_kind,
+            bytes,
+            // There is no source file, so content must be serialized.
+            true,
+        )
+        .unwrap()
+    }
+
+    /// Converts height map into single-channel (R8) texture.
+    pub fn to_texture(&self) -> Texture {
+        let bytes = self
+            .values
+            .par_iter()
+            .map(|v| (v * 255.0 + 0.5) as u8)
+            .collect();
+        self.make_texture(TexturePixelKind::R8, bytes)
+    }
+
+    /// Converts height map into RGBA8 texture, each value is mapped to a color using given
+    /// gradient.
+    pub fn to_color_texture(&self, gradient: &ColorGradient) -> Texture {
+        let mut bytes = vec![0; self.values.len() * 4];
+        bytes
+            .par_chunks_mut(4)
+            .zip(self.values.par_iter())
+            .for_each(|(pixel, value)| {
+                let color = gradient.get_color(*value);
+                pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            });
+        self.make_texture(TexturePixelKind::RGBA8, bytes)
+    }
+
+    /// Converts height map into RGB8 tangent-space normal map. `strength` defines how steep
+    /// slopes are, `seamless` should be set if height map is tileable, so slopes at the edges
+    /// will be calculated using pixels on the opposite side.
+    pub fn to_normal_map(&self, strength: f32, seamless: bool) -> Texture {
+        let width = self.width as usize;
+        let mut bytes = vec![0; self.values.len() * 3];
+        bytes
+            .par_chunks_mut(width * 3)
+            .enumerate()
+            .for_each(|(y, row)| {
+                let y = y as i64;
+                for (x, pixel) in row.chunks_exact_mut(3).enumerate() {
+                    let x = x as i64;
+                    // Central differences.
+                    let dx = (self.value_at(x + 1, y, seamless)
+                        - self.value_at(x - 1, y, seamless))
+                        * 0.5
+                        * strength;
+                    let dy = (self.value_at(x, y + 1, seamless)
+                        - self.value_at(x, y - 1, seamless))
+                        * 0.5
+                        * strength;
+                    let length = (dx * dx + dy * dy + 1.0).sqrt();
+                    let normal = [-dx / length, -dy / length, 1.0 / length];
+                    for (byte, component) in pixel.iter_mut().zip(normal.iter()) {
+                        *byte = ((component * 0.5 + 0.5) * 255.0 + 0.5) as u8;
+                    }
+                }
+            });
+        self.make_texture(TexturePixelKind::RGB8, bytes)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn hash(x: i32, y: i32, seed: u32) -> u32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x27d4_eb2d)
+        ^ (y as u32).wrapping_mul(0x1656_67b1).rotate_left(16);
+    h = (h ^ (h >> 15)).wrapping_mul(0x85eb_ca6b);
+    h = (h ^ (h >> 13)).wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+fn wrap(coordinate: i32, period: Option<i32>) -> i32 {
+    match period {
+        Some(period) => coordinate.rem_euclid(period),
+        None => coordinate,
+    }
+}
+
+// Unit gradient vector for a lattice point.
+fn gradient(x: i32, y: i32, seed: u32) -> (f32, f32) {
+    let angle = (hash(x, y, seed) & 0xffff) as f32 / 65536.0 * std::f32::consts::TAU;
+    let (sin, cos) = angle.sin_cos();
+    (cos, sin)
+}
+
+fn perlin(x: f32, y: f32, period: Option<i32>, seed: u32) -> f32 {
+    let x0 = x.floor();
+    let y0 = y.floor();
+    let fx = x - x0;
+    let fy = y - y0;
+    let (x0, y0) = (x0 as i32, y0 as i32);
+
+    let corner = |cx: i32, cy: i32, dx: f32, dy: f32| {
+        let (gx, gy) = gradient(wrap(cx, period), wrap(cy, period), seed);
+        gx * dx + gy * dy
+    };
+
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let (u, v) = (fade(fx), fade(fy));
+
+    let top = lerp(corner(x0, y0, fx, fy), corner(x0 + 1, y0, fx - 1.0, fy), u);
+    let bottom = lerp(
+        corner(x0, y0 + 1, fx, fy - 1.0),
+        corner(x0 + 1, y0 + 1, fx - 1.0, fy - 1.0),
+        u,
+    );
+    // Max value of 2D Perlin noise with unit gradients is sqrt(0.5).
+    lerp(top, bottom, v) * std::f32::consts::SQRT_2
+}
+
+fn simplex(x: f32, y: f32, seed: u32) -> f32 {
+    const F2: f32 = 0.366_025_4; // (sqrt(3) - 1) / 2
+    const G2: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+
+    let s = (x + y) * F2;
+    let i = (x + s).floor();
+    let j = (y + s).floor();
+    let t = (i + j) * G2;
+    let x0 = x - (i - t);
+    let y0 = y - (j - t);
+    let (i, j) = (i as i32, j as i32);
+
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+    let x1 = x0 - i1 as f32 + G2;
+    let y1 = y0 - j1 as f32 + G2;
+    let x2 = x0 - 1.0 + 2.0 * G2;
+    let y2 = y0 - 1.0 + 2.0 * G2;
+
+    let contribution = |ci: i32, cj: i32, dx: f32, dy: f32| {
+        let t = 0.5 - dx * dx - dy * dy;
+        if t < 0.0 {
+            0.0
+        } else {
+            let (gx, gy) = gradient(ci, cj, seed);
+            t * t * t * t * (gx * dx + gy * dy)
+        }
+    };
+
+    let n = contribution(i, j, x0, y0)
+        + contribution(i + i1, j + j1, x1, y1)
+        + contribution(i + 1, j + 1, x2, y2);
+    // Scale to [-1; 1] range for unit gradients.
+    (n * 99.2).max(-1.0).min(1.0)
+}
+
+fn worley(x: f32, y: f32, period: Option<i32>, seed: u32) -> f32 {
+    let cx = x.floor() as i32;
+    let cy = y.floor() as i32;
+    let mut min_distance = f32::MAX;
+    for ny in (cy - 1)..=(cy + 1) {
+        for nx in (cx - 1)..=(cx + 1) {
+            // One feature point per cell, its position is taken from wrapped cell, so the
+            // pattern repeats with given period.
+            let h = hash(wrap(nx, period), wrap(ny, period), seed);
+            let px = nx as f32 + (h & 0xffff) as f32 / 65536.0;
+            let py = ny as f32 + (h >> 16) as f32 / 65536.0;
+            let distance = ((px - x) * (px - x) + (py - y) * (py - y)).sqrt();
+            min_distance = min_distance.min(distance);
+        }
+    }
+    // Distance to closest point rarely exceeds cell size.
+    min_distance.min(1.0) * 2.0 - 1.0
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            color::Color,
+            color_gradient::{ColorGradientBuilder, GradientPoint},
+        },
+        resource::{
+            procedural_texture::{HeightMap, Noise, NoiseKind, Pattern},
+            texture::{TextureKind, TexturePixelKind},
+        },
+    };
+
+    #[test]
+    fn seamless_noise_tiles() {
+        for kind in [NoiseKind::Perlin, NoiseKind::Simplex, NoiseKind::Worley].iter() {
+            let noise = Noise::new(*kind)
+                .with_frequency(3)
+                .with_octaves(3)
+                .with_seed(42)
+                .with_seamless(true);
+            for i in 0..16 {
+                let t = i as f32 / 16.0;
+                // Opposite edges must have the same values.
+                assert!((noise.sample(0.0, t) - noise.sample(1.0, t)).abs() < 1.0e-4);
+                assert!((noise.sample(t, 0.0) - noise.sample(t, 1.0)).abs() < 1.0e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn height_map_range() {
+        let patterns = [
+            Pattern::Noise(Noise::new(NoiseKind::Perlin).with_octaves(4)),
+            Pattern::Noise(Noise::new(NoiseKind::Simplex).with_octaves(4)),
+            Pattern::Noise(Noise::new(NoiseKind::Worley)),
+            Pattern::Checkerboard {
+                cells_x: 4,
+                cells_y: 4,
+            },
+            Pattern::LinearGradient { angle: 0.5 },
+            Pattern::RadialGradient,
+        ];
+        for pattern in patterns.iter() {
+            let height_map = HeightMap::generate(32, 16, pattern);
+            assert_eq!(height_map.values().len(), 32 * 16);
+            assert!(height_map.values().iter().all(|v| (0.0..=1.0).contains(v)));
+        }
+
+        let checkerboard = HeightMap::generate(
+            4,
+            4,
+            &Pattern::Checkerboard {
+                cells_x: 2,
+                cells_y: 2,
+            },
+        );
+        assert_eq!(checkerboard.value(0, 0), 0.0);
+        assert_eq!(checkerboard.value(2, 0), 1.0);
+        assert_eq!(checkerboard.value(2, 2), 0.0);
+    }
+
+    #[test]
+    fn texture_conversions() {
+        let height_map = HeightMap::generate(
+            8,
+            4,
+            &Pattern::Checkerboard {
+                cells_x: 2,
+                cells_y: 2,
+            },
+        );
+        let kind = TextureKind::Rectangle {
+            width: 8,
+            height: 4,
+        };
+
+        let texture = height_map.to_texture();
+        let texture = texture.data_ref();
+        assert_eq!(texture.kind(), kind);
+        assert_eq!(texture.pixel_kind(), TexturePixelKind::R8);
+        assert_eq!(texture.data().len(), 8 * 4);
+        assert_eq!(texture.data()[0], 0);
+        assert_eq!(texture.data()[4], 255);
+
+        let gradient = ColorGradientBuilder::new()
+            .with_point(GradientPoint::new(0.0, Color::opaque(10, 20, 30)))
+            .with_point(GradientPoint::new(1.0, Color::opaque(200, 150, 100)))
+            .build();
+        let texture = height_map.to_color_texture(&gradient);
+        let texture = texture.data_ref();
+        assert_eq!(texture.kind(), kind);
+        assert_eq!(texture.pixel_kind(), TexturePixelKind::RGBA8);
+        assert_eq!(texture.data().len(), 8 * 4 * 4);
+        assert_eq!(&texture.data()[0..4], &[10, 20, 30, 255]);
+        assert_eq!(&texture.data()[16..20], &[200, 150, 100, 255]);
+
+        let texture = height_map.to_normal_map(1.0, false);
+        let texture = texture.data_ref();
+        assert_eq!(texture.kind(), kind);
+        assert_eq!(texture.pixel_kind(), TexturePixelKind::RGB8);
+        assert_eq!(texture.data().len(), 8 * 4 * 3);
+        // Flat area points straight up.
+        assert_eq!(&texture.data()[0..3], &[128, 128, 255]);
+        // Height rises to the right on the edge of a cell, so normal leans to the left.
+        assert!(texture.data()[3 * 3] < 128);
+    }
+}
@@ -9,9 +9,18 @@
 //! To load images and decode them, rg3d uses image and ddsfile crates. Here is the list of
 //! supported formats: png, tga, bmp, dds, jpg, gif, tiff, dds.
 //!
+//! ## HDR textures
+//!
+//! Radiance (.hdr) images are loaded as RGB texture with 32-bit floating-point components.
+//! OpenEXR (.exr) images are loaded as 16- or 32-bit floating-point textures, depending on
+//! pixel type of channels in the file, only uncompressed scanline images are supported. HDR
+//! textures cannot be compressed, compression options are ignored for them.
+//!
 //! ## Compressed textures
 //!
 //! rg3d supports most commonly used formats of compressed textures: DXT1, DXT3, DXT5.
+//! Textures can be compressed and get full mip chain offline, see
+//! [texture_cooker](crate::resource::texture_cooker) module docs.
 //!
 //! ## Render target
 //!
@@ -40,7 +49,7 @@ use std::{
 };
 
 /// Texture kind.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TextureKind {
     /// 1D texture.
     Line {
@@ -72,6 +81,36 @@ pub enum TextureKind {
     },
 }
 
+impl TextureKind {
+    /// Returns kind of given mip level, each mip level is 2 times smaller than previous.
+    /// Every side is at least 1 pixel, so non-square textures keep the last row (or column)
+    /// of pixels until their largest side reaches 1 pixel too.
+    pub fn mip(self, mip: u32) -> Self {
+        match self {
+            Self::Line { length } => Self::Line {
+                length: (length >> mip).max(1),
+            },
+            Self::Rectangle { width, height } => Self::Rectangle {
+                width: (width >> mip).max(1),
+                height: (height >> mip).max(1),
+            },
+            Self::Cube { width, height } => Self::Cube {
+                width: (width >> mip).max(1),
+                height: (height >> mip).max(1),
+            },
+            Self::Volume {
+                width,
+                height,
+                depth,
+            } => Self::Volume {
+                width: (width >> mip).max(1),
+                height: (height >> mip).max(1),
+                depth: (depth >> mip).max(1),
+            },
+        }
+    }
+}
+
 impl Default for TextureKind {
     fn default() -> Self {
         Self::Rectangle {
@@ -503,6 +542,30 @@ pub enum TexturePixelKind {
 
     /// Compressed RG8 texture (RGTC).
     RG8RGTC = 15,
+
+    /// 2 byte (half precision) floating-point red.
+    R16F = 16,
+
+    /// Red and green, each by 2 byte floating-point number.
+    RG16F = 17,
+
+    /// Red, green, and blue components, each by 2 byte floating-point number.
+    RGB16F = 18,
+
+    /// Red, green, blue, and alpha components, each by 2 byte floating-point number.
+    RGBA16F = 19,
+
+    /// 4 byte floating-point red.
+    R32F = 20,
+
+    /// Red and green, each by 4 byte floating-point number.
+    RG32F = 21,
+
+    /// Red, green, and blue components, each by 4 byte floating-point number.
+    RGB32F = 22,
+
+    /// Red, green, blue, and alpha components, each by 4 byte floating-point number.
+    RGBA32F = 23,
 }
 
 impl TexturePixelKind {
@@ -524,6 +587,14 @@ impl TexturePixelKind {
             13 => Ok(Self::DXT5RGBA),
             14 => Ok(Self::R8RGTC),
             15 => Ok(Self::RG8RGTC),
+            16 => Ok(Self::R16F),
+            17 => Ok(Self::RG16F),
+            18 => Ok(Self::RGB16F),
+            19 => Ok(Self::RGBA16F),
+            20 => Ok(Self::R32F),
+            21 => Ok(Self::RG32F),
+            22 => Ok(Self::RGB32F),
+            23 => Ok(Self::RGBA32F),
             _ => Err(format!("Invalid texture kind {}!", id)),
         }
     }
@@ -531,6 +602,22 @@ impl TexturePixelKind {
     fn id(self) -> u32 {
         self as u32
     }
+
+    /// Returns true if pixels of this kind are stored as floating-point numbers. Such textures
+    /// can hold values outside of [0; 1] range, so they can be used for HDR content.
+    pub fn is_floating_point(self) -> bool {
+        matches!(
+            self,
+            Self::R16F
+                | Self::RG16F
+                | Self::RGB16F
+                | Self::RGBA16F
+                | Self::R32F
+                | Self::RG32F
+                | Self::RGB32F
+                | Self::RGBA32F
+        )
+    }
 }
 
 /// An error that may occur during texture operations.
@@ -606,11 +693,19 @@ fn transmute_slice<T>(bytes: &[u8]) -> &'_ [T] {
     }
 }
 
-fn compress_bc1<T: tbc::color::ColorRgba8>(bytes: &[u8], width: usize, height: usize) -> Vec<u8> {
+pub(in crate) fn compress_bc1<T: tbc::color::ColorRgba8>(
+    bytes: &[u8],
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
     tbc::encode_image_bc1_conv_u8::<T>(transmute_slice::<T>(bytes), width, height)
 }
 
-fn compress_bc3<T: tbc::color::ColorRgba8>(bytes: &[u8], width: usize, height: usize) -> Vec<u8> {
+pub(in crate) fn compress_bc3<T: tbc::color::ColorRgba8>(
+    bytes: &[u8],
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
     tbc::encode_image_bc3_conv_u8::<T>(transmute_slice::<T>(bytes), width, height)
 }
 
@@ -632,6 +727,334 @@ fn data_hash(data: &[u8]) -> u64 {
     hasher.finish()
 }
 
+fn image_size_bytes(kind: TextureKind, pixel_kind: TexturePixelKind) -> u32 {
+    let pixel_count = match kind {
+        TextureKind::Line { length } => length,
+        TextureKind::Rectangle { width, height } => width * height,
+        TextureKind::Cube { width, height } => 6 * width * height,
+        TextureKind::Volume {
+            width,
+            height,
+            depth,
+        } => width * height * depth,
+    };
+    match pixel_kind {
+        // Uncompressed formats.
+        TexturePixelKind::R8 => pixel_count,
+        TexturePixelKind::R16 | TexturePixelKind::RG8 => 2 * pixel_count,
+        TexturePixelKind::RGB8 | TexturePixelKind::BGR8 => 3 * pixel_count,
+        TexturePixelKind::RGBA8 | TexturePixelKind::BGRA8 | TexturePixelKind::RG16 => {
+            4 * pixel_count
+        }
+        TexturePixelKind::RGB16 => 6 * pixel_count,
+        TexturePixelKind::RGBA16 => 8 * pixel_count,
+
+        // Floating-point formats.
+        TexturePixelKind::R16F => 2 * pixel_count,
+        TexturePixelKind::RG16F | TexturePixelKind::R32F => 4 * pixel_count,
+        TexturePixelKind::RGB16F => 6 * pixel_count,
+        TexturePixelKind::RGBA16F | TexturePixelKind::RG32F => 8 * pixel_count,
+        TexturePixelKind::RGB32F => 12 * pixel_count,
+        TexturePixelKind::RGBA32F => 16 * pixel_count,
+
+        // Compressed formats.
+        TexturePixelKind::DXT1RGB
+        | TexturePixelKind::DXT1RGBA
+        | TexturePixelKind::DXT3RGBA
+        | TexturePixelKind::DXT5RGBA
+        | TexturePixelKind::R8RGTC
+        | TexturePixelKind::RG8RGTC => {
+            let block_size = match pixel_kind {
+                TexturePixelKind::DXT1RGB
+                | TexturePixelKind::DXT1RGBA
+                | TexturePixelKind::R8RGTC => 8,
+                TexturePixelKind::DXT3RGBA
+                | TexturePixelKind::DXT5RGBA
+                | TexturePixelKind::RG8RGTC => 16,
+                _ => unreachable!(),
+            };
+            match kind {
+                TextureKind::Line { length } => ceil_div_4(length) * block_size,
+                TextureKind::Rectangle { width, height } => {
+                    ceil_div_4(width) * ceil_div_4(height) * block_size
+                }
+                TextureKind::Cube { width, height } => {
+                    6 * ceil_div_4(width) * ceil_div_4(height) * block_size
+                }
+                TextureKind::Volume {
+                    width,
+                    height,
+                    depth,
+                } => ceil_div_4(width) * ceil_div_4(height) * ceil_div_4(depth) * block_size,
+            }
+        }
+    }
+}
+
+fn is_radiance(data: &[u8]) -> bool {
+    data.starts_with(b"#?RADIANCE") || data.starts_with(b"#?RGBE")
+}
+
+fn load_radiance(data: &[u8]) -> Result<TextureData, TextureError> {
+    let decoder = image::codecs::hdr::HdrDecoder::new(Cursor::new(data))?;
+    let metadata = decoder.metadata();
+    let pixels = decoder.read_image_hdr()?;
+
+    let mut bytes = Vec::with_capacity(pixels.len() * 12);
+    for pixel in pixels {
+        for component in pixel.0.iter() {
+            bytes.extend_from_slice(&component.to_le_bytes());
+        }
+    }
+
+    Ok(TextureData {
+        pixel_kind: TexturePixelKind::RGB32F,
+        kind: TextureKind::Rectangle {
+            width: metadata.width,
+            height: metadata.height,
+        },
+        data_hash: data_hash(&bytes),
+        bytes,
+        ..Default::default()
+    })
+}
+
+const OPEN_EXR_MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+fn is_open_exr(data: &[u8]) -> bool {
+    data.starts_with(&OPEN_EXR_MAGIC)
+}
+
+struct ExrReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ExrReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], TextureError> {
+        let end = match self.position.checked_add(count) {
+            Some(end) if end <= self.data.len() => end,
+            _ => {
+                return Err(TextureError::Io(std::io::Error::from(
+                    std::io::ErrorKind::UnexpectedEof,
+                )))
+            }
+        };
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, TextureError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, TextureError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(i32::from_le_bytes(bytes))
+    }
+
+    // Sizes are stored as signed integers, negative ones are invalid.
+    fn size(&mut self) -> Result<usize, TextureError> {
+        let size = self.i32()?;
+        if size < 0 {
+            return Err(TextureError::UnsupportedFormat);
+        }
+        Ok(size as usize)
+    }
+
+    fn u64(&mut self) -> Result<u64, TextureError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn string(&mut self) -> Result<&'a str, TextureError> {
+        let length = self
+            .data
+            .get(self.position..)
+            .unwrap_or_default()
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(TextureError::UnsupportedFormat)?;
+        let string = std::str::from_utf8(self.bytes(length)?)
+            .map_err(|_| TextureError::UnsupportedFormat)?;
+        // Skip null terminator.
+        self.position += 1;
+        Ok(string)
+    }
+}
+
+struct ExrChannel {
+    name: String,
+    // 0 - UINT, 1 - HALF, 2 - FLOAT
+    pixel_type: i32,
+}
+
+impl ExrChannel {
+    fn size(&self) -> usize {
+        if self.pixel_type == 1 {
+            2
+        } else {
+            4
+        }
+    }
+}
+
+/// Loads single-part, scanline OpenEXR image without compression. HALF channels are loaded
+/// as 16-bit floating-point textures, FLOAT channels - as 32-bit floating-point textures.
+/// Compressed, tiled, deep and multi-part images are not supported.
+fn load_open_exr(data: &[u8]) -> Result<TextureData, TextureError> {
+    let mut reader = ExrReader {
+        data,
+        position: OPEN_EXR_MAGIC.len(),
+    };
+
+    let version = reader.i32()?;
+    // Tiled, deep or multi-part file.
+    if version & 0x1a00 != 0 {
+        return Err(TextureError::UnsupportedFormat);
+    }
+
+    let mut channels = Vec::new();
+    let mut compression = None;
+    let mut data_window = None;
+    loop {
+        let name = reader.string()?;
+        if name.is_empty() {
+            break;
+        }
+        let kind = reader.string()?;
+        let size = reader.size()?;
+        let start = reader.position;
+        match (name, kind) {
+            ("channels", "chlist") => loop {
+                let channel_name = reader.string()?;
+                if channel_name.is_empty() {
+                    break;
+                }
+                let pixel_type = reader.i32()?;
+                // pLinear + reserved bytes.
+                reader.bytes(4)?;
+                let x_sampling = reader.i32()?;
+                let y_sampling = reader.i32()?;
+                if x_sampling != 1 || y_sampling != 1 {
+                    return Err(TextureError::UnsupportedFormat);
+                }
+                channels.push(ExrChannel {
+                    name: channel_name.to_owned(),
+                    pixel_type,
+                });
+            },
+            ("compression", "compression") => compression = Some(reader.u8()?),
+            ("dataWindow", "box2i") => {
+                data_window = Some((reader.i32()?, reader.i32()?, reader.i32()?, reader.i32()?))
+            }
+            _ => (),
+        }
+        reader.position = start + size;
+    }
+
+    if compression != Some(0) {
+        return Err(TextureError::UnsupportedFormat);
+    }
+    let (x_min, y_min, x_max, y_max) = data_window.ok_or(TextureError::UnsupportedFormat)?;
+    // Computed in 64 bits, because the difference of two 32-bit coordinates may overflow.
+    let width = i64::from(x_max) - i64::from(x_min) + 1;
+    let height = i64::from(y_max) - i64::from(y_min) + 1;
+    if width <= 0 || height <= 0 || width > u32::MAX as i64 || height > u32::MAX as i64 {
+        return Err(TextureError::UnsupportedFormat);
+    }
+    let width = width as usize;
+    let height = height as usize;
+
+    // Channels are stored in alphabetical order in the file, but texture needs RGBA order.
+    // Luminance-only images are loaded as red channel.
+    let find = |name: &str| channels.iter().position(|c| c.name == name);
+    let layout = match (
+        find("R").or_else(|| find("Y")),
+        find("G"),
+        find("B"),
+        find("A"),
+    ) {
+        (Some(r), None, None, None) => vec![r],
+        (Some(r), Some(g), None, None) => vec![r, g],
+        (Some(r), Some(g), Some(b), None) => vec![r, g, b],
+        (Some(r), Some(g), Some(b), Some(a)) => vec![r, g, b, a],
+        _ => return Err(TextureError::UnsupportedFormat),
+    };
+
+    let pixel_type = channels[layout[0]].pixel_type;
+    if layout.iter().any(|i| channels[*i].pixel_type != pixel_type) {
+        return Err(TextureError::UnsupportedFormat);
+    }
+    let pixel_kind = match (pixel_type, layout.len()) {
+        (1, 1) => TexturePixelKind::R16F,
+        (1, 2) => TexturePixelKind::RG16F,
+        (1, 3) => TexturePixelKind::RGB16F,
+        (1, 4) => TexturePixelKind::RGBA16F,
+        (2, 1) => TexturePixelKind::R32F,
+        (2, 2) => TexturePixelKind::RG32F,
+        (2, 3) => TexturePixelKind::RGB32F,
+        (2, 4) => TexturePixelKind::RGBA32F,
+        _ => return Err(TextureError::UnsupportedFormat),
+    };
+
+    // Offset of each channel inside of a scan line.
+    let mut channel_offsets = Vec::with_capacity(channels.len());
+    let mut line_size = 0;
+    for channel in channels.iter() {
+        channel_offsets.push(line_size);
+        line_size += channel.size() * width;
+    }
+
+    // Uncompressed pixels must fit in the file, this also prevents huge allocations for
+    // malformed data windows.
+    match line_size.checked_mul(height) {
+        Some(size) if size <= data.len() => (),
+        _ => return Err(TextureError::UnsupportedFormat),
+    }
+
+    let component_size = channels[layout[0]].size();
+    let pixel_size = component_size * layout.len();
+    let mut bytes = vec![0; pixel_size * width * height];
+
+    // Uncompressed files store one scan line per chunk.
+    let offsets = (0..height)
+        .map(|_| reader.u64())
+        .collect::<Result<Vec<_>, _>>()?;
+    for offset in offsets {
+        reader.position = offset as usize;
+        let y = i64::from(reader.i32()?) - i64::from(y_min);
+        let size = reader.size()?;
+        if y < 0 || y as usize >= height || size != line_size {
+            return Err(TextureError::UnsupportedFormat);
+        }
+        let line = reader.bytes(size)?;
+        let row = &mut bytes[y as usize * width * pixel_size..][..width * pixel_size];
+        for (component, channel) in layout.iter().enumerate() {
+            let src = &line[channel_offsets[*channel]..][..width * component_size];
+            for x in 0..width {
+                let dest = x * pixel_size + component * component_size;
+                row[dest..dest + component_size]
+                    .copy_from_slice(&src[x * component_size..][..component_size]);
+            }
+        }
+    }
+
+    Ok(TextureData {
+        pixel_kind,
+        kind: TextureKind::Rectangle {
+            width: width as u32,
+            height: height as u32,
+        },
+        data_hash: data_hash(&bytes),
+        bytes,
+        ..Default::default()
+    })
+}
+
 impl TextureData {
     /// Tries to load a texture from given data. Use this method if you want to
     /// load a texture from embedded data.
@@ -672,23 +1095,19 @@ impl TextureData {
                 D3DFormat::DXT5 => TexturePixelKind::DXT5RGBA,
                 D3DFormat::L8 | D3DFormat::A8 => TexturePixelKind::R8,
                 D3DFormat::L16 => TexturePixelKind::R16,
-                D3DFormat::R8G8B8 => TexturePixelKind::RGB8,
+                // Formats are named from most significant bits to least significant, so the
+                // order of components in memory is reversed.
+                D3DFormat::R8G8B8 => TexturePixelKind::BGR8,
                 D3DFormat::A8L8 => TexturePixelKind::RG8,
-                D3DFormat::A8R8G8B8 => {
-                    // // ARGB8 -> RGBA8
-                    // assert_eq!(bytes.len() % 4, 0);
-                    // for chunk in bytes.chunks_exact_mut(4) {
-                    //     let a = chunk[0];
-                    //     let r = chunk[1];
-                    //     let g = chunk[2];
-                    //     let b = chunk[3];
-                    //     chunk[0] = r;
-                    //     chunk[1] = g;
-                    //     chunk[2] = b;
-                    //     chunk[3] = a;
-                    // }
-                    TexturePixelKind::RGBA8
-                }
+                D3DFormat::A8R8G8B8 => TexturePixelKind::BGRA8,
+                D3DFormat::A8B8G8R8 => TexturePixelKind::RGBA8,
+                D3DFormat::A16B16G16R16 => TexturePixelKind::RGBA16,
+                D3DFormat::R16F => TexturePixelKind::R16F,
+                D3DFormat::G16R16F => TexturePixelKind::RG16F,
+                D3DFormat::A16B16G16R16F => TexturePixelKind::RGBA16F,
+                D3DFormat::R32F => TexturePixelKind::R32F,
+                D3DFormat::G32R32F => TexturePixelKind::RG32F,
+                D3DFormat::A32B32G32R32F => TexturePixelKind::RGBA32F,
                 D3DFormat::G16R16 => {
                     // GR16 -> RG16
                     assert_eq!(bytes.len() % 4, 0);
@@ -738,6 +1157,10 @@ impl TextureData {
                 },
                 ..Default::default()
             }
+        } else if is_radiance(data) {
+            load_radiance(data)?
+        } else if is_open_exr(data) {
+            load_open_exr(data)?
         } else {
             // Commonly used formats are all rectangle textures.
             let dyn_img = image::load_from_memory(data)
@@ -835,59 +1258,23 @@ impl TextureData {
         bytes: Vec<u8>,
         serialize_content: bool,
     ) -> Option<Self> {
-        let pixel_count = match kind {
-            TextureKind::Line { length } => length,
-            TextureKind::Rectangle { width, height } => width * height,
-            TextureKind::Cube { width, height } => 6 * width * height,
-            TextureKind::Volume {
-                width,
-                height,
-                depth,
-            } => width * height * depth,
-        };
-        let required_bytes = match pixel_kind {
-            // Uncompressed formats.
-            TexturePixelKind::R8 => pixel_count,
-            TexturePixelKind::R16 | TexturePixelKind::RG8 => 2 * pixel_count,
-            TexturePixelKind::RGB8 | TexturePixelKind::BGR8 => 3 * pixel_count,
-            TexturePixelKind::RGBA8 | TexturePixelKind::BGRA8 | TexturePixelKind::RG16 => {
-                4 * pixel_count
-            }
-            TexturePixelKind::RGB16 => 6 * pixel_count,
-            TexturePixelKind::RGBA16 => 8 * pixel_count,
+        Self::from_bytes_with_mips(kind, pixel_kind, 1, bytes, serialize_content)
+    }
 
-            // Compressed formats.
-            TexturePixelKind::DXT1RGB
-            | TexturePixelKind::DXT1RGBA
-            | TexturePixelKind::DXT3RGBA
-            | TexturePixelKind::DXT5RGBA
-            | TexturePixelKind::R8RGTC
-            | TexturePixelKind::RG8RGTC => {
-                let block_size = match pixel_kind {
-                    TexturePixelKind::DXT1RGB
-                    | TexturePixelKind::DXT1RGBA
-                    | TexturePixelKind::R8RGTC => 8,
-                    TexturePixelKind::DXT3RGBA
-                    | TexturePixelKind::DXT5RGBA
-                    | TexturePixelKind::RG8RGTC => 16,
-                    _ => unreachable!(),
-                };
-                match kind {
-                    TextureKind::Line { length } => ceil_div_4(length) * block_size,
-                    TextureKind::Rectangle { width, height } => {
-                        ceil_div_4(width) * ceil_div_4(height) * block_size
-                    }
-                    TextureKind::Cube { width, height } => {
-                        6 * ceil_div_4(width) * ceil_div_4(height) * block_size
-                    }
-                    TextureKind::Volume {
-                        width,
-                        height,
-                        depth,
-                    } => ceil_div_4(width) * ceil_div_4(height) * ceil_div_4(depth) * block_size,
-                }
-            }
-        };
+    /// Creates new texture instance from given parameters. `bytes` must contain all mip levels
+    /// one after another, where each mip is 2 times smaller than previous. Cube textures must
+    /// store all six faces of a mip before next mip.
+    pub fn from_bytes_with_mips(
+        kind: TextureKind,
+        pixel_kind: TexturePixelKind,
+        mip_count: u32,
+        bytes: Vec<u8>,
+        serialize_content: bool,
+    ) -> Option<Self> {
+        let mip_count = mip_count.max(1);
+        let required_bytes = (0..mip_count)
+            .map(|mip| image_size_bytes(kind.mip(mip), pixel_kind))
+            .sum::<u32>();
         if required_bytes != bytes.len() as u32 {
             None
         } else {
@@ -897,6 +1284,7 @@ impl TextureData {
                 data_hash: data_hash(&bytes),
                 bytes,
                 pixel_kind,
+                mip_count,
                 serialize_content,
                 ..Default::default()
             })
@@ -986,8 +1374,16 @@ impl TextureData {
         self.path = path.as_ref().to_owned();
     }
 
-    /// Tries to save internal buffer into source file.
+    /// Tries to save internal buffer into source file. If the file has `dds` extension, the
+    /// texture is saved as is with all its mip levels, so compressed and floating-point textures
+    /// can be saved as well. Other file formats store only first mip level.
     pub fn save(&self) -> Result<(), TextureError> {
+        if self.path.extension().map_or(false, |ext| {
+            ext.to_string_lossy().eq_ignore_ascii_case("dds")
+        }) {
+            return self.save_dds();
+        }
+
         let color_type = match self.pixel_kind {
             TexturePixelKind::R8 => ColorType::L8,
             TexturePixelKind::RGB8 => ColorType::Rgb8,
@@ -1004,12 +1400,21 @@ impl TextureData {
             | TexturePixelKind::DXT3RGBA
             | TexturePixelKind::DXT5RGBA
             | TexturePixelKind::R8RGTC
-            | TexturePixelKind::RG8RGTC => return Err(TextureError::UnsupportedFormat),
+            | TexturePixelKind::RG8RGTC
+            | TexturePixelKind::R16F
+            | TexturePixelKind::RG16F
+            | TexturePixelKind::RGB16F
+            | TexturePixelKind::RGBA16F
+            | TexturePixelKind::R32F
+            | TexturePixelKind::RG32F
+            | TexturePixelKind::RGB32F
+            | TexturePixelKind::RGBA32F => return Err(TextureError::UnsupportedFormat),
         };
         if let TextureKind::Rectangle { width, height } = self.kind {
+            let first_mip_size = image_size_bytes(self.kind, self.pixel_kind) as usize;
             Ok(image::save_buffer(
                 &self.path,
-                self.bytes.as_ref(),
+                &self.bytes[..first_mip_size],
                 width,
                 height,
                 color_type,
@@ -1019,6 +1424,52 @@ impl TextureData {
         }
     }
 
+    fn save_dds(&self) -> Result<(), TextureError> {
+        let (width, height) = match self.kind {
+            TextureKind::Rectangle { width, height } => (width, height),
+            _ => return Err(TextureError::UnsupportedFormat),
+        };
+        let format = match self.pixel_kind {
+            TexturePixelKind::R8 => D3DFormat::L8,
+            TexturePixelKind::RG8 => D3DFormat::A8L8,
+            TexturePixelKind::RGBA8 => D3DFormat::A8B8G8R8,
+            TexturePixelKind::BGR8 => D3DFormat::R8G8B8,
+            TexturePixelKind::BGRA8 => D3DFormat::A8R8G8B8,
+            TexturePixelKind::R16 => D3DFormat::L16,
+            TexturePixelKind::RGBA16 => D3DFormat::A16B16G16R16,
+            TexturePixelKind::R16F => D3DFormat::R16F,
+            TexturePixelKind::RG16F => D3DFormat::G16R16F,
+            TexturePixelKind::RGBA16F => D3DFormat::A16B16G16R16F,
+            TexturePixelKind::R32F => D3DFormat::R32F,
+            TexturePixelKind::RG32F => D3DFormat::G32R32F,
+            TexturePixelKind::RGBA32F => D3DFormat::A32B32G32R32F,
+            TexturePixelKind::DXT1RGB | TexturePixelKind::DXT1RGBA => D3DFormat::DXT1,
+            TexturePixelKind::DXT3RGBA => D3DFormat::DXT3,
+            TexturePixelKind::DXT5RGBA => D3DFormat::DXT5,
+            // There is no matching D3D format for these.
+            TexturePixelKind::RGB8
+            | TexturePixelKind::RG16
+            | TexturePixelKind::RGB16
+            | TexturePixelKind::RGB16F
+            | TexturePixelKind::RGB32F
+            | TexturePixelKind::R8RGTC
+            | TexturePixelKind::RG8RGTC => return Err(TextureError::UnsupportedFormat),
+        };
+
+        let mut dds =
+            ddsfile::Dds::new_d3d(height, width, None, format, Some(self.mip_count), None)
+                .map_err(|_| TextureError::UnsupportedFormat)?;
+        dds.data = self.bytes.clone();
+
+        let mut file = std::fs::File::create(&self.path)?;
+        dds.write(&mut file).map_err(|e| {
+            TextureError::Io(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("{:?}", e),
+            ))
+        })
+    }
+
     /// Returns a special reference holder that provides mutable access to content of the
     /// texture and automatically calculates hash of the data in its destructor.
     pub fn modify(&mut self) -> TextureDataRefMut<'_> {
@@ -1058,3 +1509,229 @@ impl<'a> TextureDataRefMut<'a> {
         &mut self.texture.bytes
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::resource::texture::{
+        CompressionOptions, TextureData, TextureKind, TexturePixelKind,
+    };
+    use ddsfile::{D3DFormat, Dds};
+
+    fn make_dds(format: D3DFormat, data: Vec<u8>) -> Vec<u8> {
+        let mut dds = Dds::new_d3d(1, 2, None, format, None, None).unwrap();
+        dds.data = data;
+        let mut bytes = Vec::new();
+        dds.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn dds_rgb_formats_keep_memory_order() {
+        // A8R8G8B8 is stored as B, G, R, A in memory.
+        let bgra = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let texture = TextureData::load_from_memory(
+            &make_dds(D3DFormat::A8R8G8B8, bgra.clone()),
+            CompressionOptions::NoCompression,
+        )
+        .unwrap();
+        assert_eq!(texture.pixel_kind(), TexturePixelKind::BGRA8);
+        assert_eq!(texture.data(), bgra.as_slice());
+
+        // R8G8B8 is stored as B, G, R in memory.
+        let bgr = vec![1, 2, 3, 4, 5, 6];
+        let texture = TextureData::load_from_memory(
+            &make_dds(D3DFormat::R8G8B8, bgr.clone()),
+            CompressionOptions::NoCompression,
+        )
+        .unwrap();
+        assert_eq!(texture.pixel_kind(), TexturePixelKind::BGR8);
+        assert_eq!(texture.data(), bgr.as_slice());
+    }
+
+    #[test]
+    fn dds_bgra_save_load_round_trip() {
+        let bytes = vec![10, 20, 30, 40, 50, 60, 70, 80];
+        let mut texture = TextureData::from_bytes(
+            TextureKind::Rectangle {
+                width: 2,
+                height: 1,
+            },
+            TexturePixelKind::BGRA8,
+            bytes.clone(),
+            false,
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "rg3d_dds_bgra_round_trip_{}.dds",
+            std::process::id()
+        ));
+        texture.set_path(&path);
+        texture.save().unwrap();
+
+        let loaded = TextureData::load_from_memory(
+            &std::fs::read(&path).unwrap(),
+            CompressionOptions::NoCompression,
+        )
+        .unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.pixel_kind(), TexturePixelKind::BGRA8);
+        assert_eq!(loaded.data(), bytes.as_slice());
+    }
+
+    const RADIANCE: &[u8] = include_bytes!("test_data/gradient.hdr");
+    const OPEN_EXR: &[u8] = include_bytes!("test_data/gradient.exr");
+
+    // Both fixtures contain the same 2x2 image.
+    const PIXELS: [[f32; 3]; 4] = [
+        [1.0, 0.5, 0.25],
+        [2.0, 1.0, 0.5],
+        [0.5, 0.25, 0.125],
+        [4.0, 0.0, 1.0],
+    ];
+
+    fn floats(texture: &TextureData) -> Vec<f32> {
+        texture
+            .data()
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect()
+    }
+
+    fn patch_i32(data: &mut [u8], position: usize, value: i32) {
+        data[position..position + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    // Returns position of the value of an attribute of the OpenEXR fixture.
+    fn exr_attribute(data: &[u8], name: &str) -> usize {
+        let name = format!("{}\0", name);
+        let position = data
+            .windows(name.len())
+            .position(|w| w == name.as_bytes())
+            .unwrap();
+        let kind_end = position
+            + name.len()
+            + data[position + name.len()..]
+                .iter()
+                .position(|b| *b == 0)
+                .unwrap();
+        // Skip null terminator of the type and size of the value.
+        kind_end + 1 + 4
+    }
+
+    #[test]
+    fn radiance_fixture() {
+        let texture =
+            TextureData::load_from_memory(RADIANCE, CompressionOptions::NoCompression).unwrap();
+        assert_eq!(texture.pixel_kind(), TexturePixelKind::RGB32F);
+        assert!(matches!(
+            texture.kind(),
+            TextureKind::Rectangle {
+                width: 2,
+                height: 2
+            }
+        ));
+        assert_eq!(floats(&texture), PIXELS.concat());
+    }
+
+    #[test]
+    fn open_exr_fixture() {
+        let texture =
+            TextureData::load_from_memory(OPEN_EXR, CompressionOptions::NoCompression).unwrap();
+        assert_eq!(texture.pixel_kind(), TexturePixelKind::RGB32F);
+        assert!(matches!(
+            texture.kind(),
+            TextureKind::Rectangle {
+                width: 2,
+                height: 2
+            }
+        ));
+        // Channels are stored in B, G, R order in the file.
+        assert_eq!(floats(&texture), PIXELS.concat());
+    }
+
+    #[test]
+    fn open_exr_rejects_invalid_sizes() {
+        let load = |data: &[u8]| {
+            TextureData::load_from_memory(data, CompressionOptions::NoCompression).is_err()
+        };
+
+        let window = exr_attribute(OPEN_EXR, "dataWindow");
+
+        // Inverted data window.
+        let mut data = OPEN_EXR.to_vec();
+        patch_i32(&mut data, window + 8, -1);
+        assert!(load(&data));
+
+        // Data window which size does not fit in 32 bits.
+        let mut data = OPEN_EXR.to_vec();
+        patch_i32(&mut data, window, i32::MIN);
+        patch_i32(&mut data, window + 8, i32::MAX);
+        assert!(load(&data));
+
+        // Data window that is much larger than the file.
+        let mut data = OPEN_EXR.to_vec();
+        patch_i32(&mut data, window + 8, 1_000_000);
+        patch_i32(&mut data, window + 12, 1_000_000);
+        assert!(load(&data));
+
+        // Negative size of an attribute.
+        let mut data = OPEN_EXR.to_vec();
+        patch_i32(&mut data, exr_attribute(OPEN_EXR, "compression") - 4, -1);
+        assert!(load(&data));
+
+        // Negative size of a scan line.
+        let mut data = OPEN_EXR.to_vec();
+        let first_line = u64::from_le_bytes({
+            let mut offset = [0; 8];
+            let table = exr_attribute(OPEN_EXR, "screenWindowWidth") + 4 + 1;
+            offset.copy_from_slice(&data[table..table + 8]);
+            offset
+        }) as usize;
+        patch_i32(&mut data, first_line + 4, -1);
+        assert!(load(&data));
+
+        // Truncated file.
+        assert!(load(&OPEN_EXR[..OPEN_EXR.len() - 1]));
+    }
+
+    #[test]
+    fn mips_of_non_square_texture_are_at_least_one_pixel() {
+        let kind = TextureKind::Rectangle {
+            width: 8,
+            height: 2,
+        };
+        let chain = (0..4).map(|mip| kind.mip(mip)).collect::<Vec<_>>();
+        assert_eq!(
+            chain,
+            [(8, 2), (4, 1), (2, 1), (1, 1)]
+                .iter()
+                .map(|&(width, height)| TextureKind::Rectangle { width, height })
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            TextureKind::Volume {
+                width: 4,
+                height: 1,
+                depth: 2
+            }
+            .mip(2),
+            TextureKind::Volume {
+                width: 1,
+                height: 1,
+                depth: 1
+            }
+        );
+        assert_eq!(
+            TextureKind::Line { length: 2 }.mip(3),
+            TextureKind::Line { length: 1 }
+        );
+
+        // Full chain of RGBA8 texture down to 1x1.
+        let bytes = vec![0; (16 + 4 + 2 + 1) * 4];
+        let texture =
+            TextureData::from_bytes_with_mips(kind, TexturePixelKind::RGBA8, 4, bytes, false)
+                .unwrap();
+        assert_eq!(texture.mip_count(), 4);
+    }
+}
@@ -0,0 +1,682 @@
+//! Texture cooker prepares textures for fast loading at runtime.
+//!
+//! # Overview
+//!
+//! Images in common formats (png, jpg, tga, etc.) have only one level of detail and are stored
+//! without GPU compression, so the engine has to compress them and generate mips each time
+//! a texture is loaded. Texture cooker does this work offline: it builds full mip chain on CPU,
+//! optionally compresses each mip and writes the result into a DDS file, which can be loaded
+//! directly to GPU.
+//!
+//! # Mip filters
+//!
+//! Each mip is built from previous one using selected [`MipFilter`]. Box filter is fast but
+//! produces slightly blurry mips, Kaiser filter keeps more details. Color textures are usually
+//! stored in sRGB space, averaging such colors directly makes mips darker than they should be,
+//! gamma correction fixes this by filtering in linear space. Normal maps must be filtered as
+//! vectors - [`TextureCooker::with_normal_map`] enables renormalization of filtered normals.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rg3d::resource::{
+//!     texture::CompressionOptions,
+//!     texture_cooker::{MipFilter, TextureCooker},
+//! };
+//!
+//! TextureCooker::new()
+//!     .with_filter(MipFilter::Kaiser)
+//!     .with_gamma_correction(true)
+//!     .with_compression(CompressionOptions::Quality)
+//!     .cook_file("data/textures/bricks.png", "data/textures/bricks.dds")
+//!     .unwrap();
+//! ```
+
+use crate::resource::texture::{
+    compress_bc1, compress_bc3, CompressionOptions, TextureData, TextureError, TextureKind,
+    TexturePixelKind, TextureWrapMode,
+};
+use std::path::Path;
+
+/// Filter that is used to build mip levels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MipFilter {
+    /// Average of 2x2 pixels of previous mip.
+    Box,
+
+    /// Windowed sinc filter, gives sharper mips than box filter.
+    Kaiser,
+}
+
+/// See module docs.
+#[derive(Copy, Clone)]
+pub struct TextureCooker {
+    filter: MipFilter,
+    gamma_correction: bool,
+    normal_map: bool,
+    compression: CompressionOptions,
+}
+
+impl Default for TextureCooker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Pixel format of source texture.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum SourceFormat {
+    Unorm8,
+    Unorm16,
+    Float16,
+    Float32,
+}
+
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Image {
+    fn fetch(&self, x: isize, y: isize, wrap_x: bool, wrap_y: bool) -> [f32; 4] {
+        let x = address(x, self.width, wrap_x);
+        let y = address(y, self.height, wrap_y);
+        self.pixels[y * self.width + x]
+    }
+}
+
+fn address(coordinate: isize, size: usize, wrap: bool) -> usize {
+    if wrap {
+        coordinate.rem_euclid(size as isize) as usize
+    } else {
+        coordinate.max(0).min(size as isize - 1) as usize
+    }
+}
+
+impl TextureCooker {
+    /// Creates new texture cooker with box filter and without compression.
+    pub fn new() -> Self {
+        Self {
+            filter: MipFilter::Box,
+            gamma_correction: false,
+            normal_map: false,
+            compression: CompressionOptions::NoCompression,
+        }
+    }
+
+    /// Sets desired mip filter.
+    pub fn with_filter(mut self, filter: MipFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Enables or disables gamma-correct filtering. Should be enabled for color textures
+    /// stored in sRGB space. It has no effect on floating-point textures, since they are
+    /// already in linear space, and on normal maps.
+    pub fn with_gamma_correction(mut self, gamma_correction: bool) -> Self {
+        self.gamma_correction = gamma_correction;
+        self
+    }
+
+    /// Marks the texture as normal map. Normals will be decoded from colors, filtered and
+    /// renormalized at each mip level.
+    pub fn with_normal_map(mut self, normal_map: bool) -> Self {
+        self.normal_map = normal_map;
+        self
+    }
+
+    /// Sets compression of cooked texture. Only 8-bit color textures with three or four
+    /// channels can be compressed, compression is ignored for other textures.
+    pub fn with_compression(mut self, compression: CompressionOptions) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Builds full mip chain for given texture and compresses it if needed. Only rectangle
+    /// textures with uncompressed pixel formats are supported. Cooked texture keeps sampling
+    /// settings (filters, wrap modes, anisotropy) of the source texture.
+    pub fn cook(&self, texture: &TextureData) -> Result<TextureData, TextureError> {
+        let (width, height) = match texture.kind() {
+            TextureKind::Rectangle { width, height } if width > 0 && height > 0 => {
+                (width as usize, height as usize)
+            }
+            _ => return Err(TextureError::UnsupportedFormat),
+        };
+
+        let (format, channels) = match texture.pixel_kind() {
+            TexturePixelKind::R8 => (SourceFormat::Unorm8, 1),
+            TexturePixelKind::RG8 => (SourceFormat::Unorm8, 2),
+            TexturePixelKind::RGB8 | TexturePixelKind::BGR8 => (SourceFormat::Unorm8, 3),
+            TexturePixelKind::RGBA8 | TexturePixelKind::BGRA8 => (SourceFormat::Unorm8, 4),
+            TexturePixelKind::R16 => (SourceFormat::Unorm16, 1),
+            TexturePixelKind::RGB16 => (SourceFormat::Unorm16, 3),
+            TexturePixelKind::RGBA16 => (SourceFormat::Unorm16, 4),
+            TexturePixelKind::R16F => (SourceFormat::Float16, 1),
+            TexturePixelKind::RG16F => (SourceFormat::Float16, 2),
+            TexturePixelKind::RGB16F => (SourceFormat::Float16, 3),
+            TexturePixelKind::RGBA16F => (SourceFormat::Float16, 4),
+            TexturePixelKind::R32F => (SourceFormat::Float32, 1),
+            TexturePixelKind::RG32F => (SourceFormat::Float32, 2),
+            TexturePixelKind::RGB32F => (SourceFormat::Float32, 3),
+            TexturePixelKind::RGBA32F => (SourceFormat::Float32, 4),
+            // RG16 has no matching DDS format, block-compressed textures cannot be filtered.
+            TexturePixelKind::RG16
+            | TexturePixelKind::DXT1RGB
+            | TexturePixelKind::DXT1RGBA
+            | TexturePixelKind::DXT3RGBA
+            | TexturePixelKind::DXT5RGBA
+            | TexturePixelKind::R8RGTC
+            | TexturePixelKind::RG8RGTC => return Err(TextureError::UnsupportedFormat),
+        };
+
+        let normal_map = self.normal_map && channels >= 3;
+        let gamma_correction =
+            self.gamma_correction && !normal_map && format == SourceFormat::Unorm8;
+        // Luminance-alpha textures have only one color channel.
+        let color_channels = if channels == 2 { 1 } else { channels.min(3) };
+
+        let mut image = decode(texture, width, height, format, channels);
+        for pixel in image.pixels.iter_mut() {
+            if gamma_correction {
+                for component in pixel.iter_mut().take(color_channels) {
+                    *component = srgb_to_linear(*component);
+                }
+            }
+            if normal_map {
+                for component in pixel.iter_mut().take(3) {
+                    *component = *component * 2.0 - 1.0;
+                }
+            }
+        }
+
+        let wrap_x = texture.s_wrap_mode() == TextureWrapMode::Repeat;
+        let wrap_y = texture.t_wrap_mode() == TextureWrapMode::Repeat;
+
+        // Textures with non-square sizes stop at the mip where smallest side becomes 1 pixel.
+        let mip_count = 32 - (width.min(height) as u32).leading_zeros();
+
+        let output = OutputFormat::select(format, channels, self.compression);
+
+        let mut bytes = Vec::new();
+        for mip in 0..mip_count {
+            if mip > 0 {
+                image = match self.filter {
+                    MipFilter::Box => downsample_box(&image, wrap_x, wrap_y),
+                    MipFilter::Kaiser => downsample_kaiser(&image, wrap_x, wrap_y),
+                };
+                if normal_map {
+                    for pixel in image.pixels.iter_mut() {
+                        renormalize(pixel);
+                    }
+                }
+            }
+
+            let mut pixels = image.pixels.clone();
+            for pixel in pixels.iter_mut() {
+                if gamma_correction {
+                    for component in pixel.iter_mut().take(color_channels) {
+                        *component = linear_to_srgb(*component);
+                    }
+                }
+                if normal_map {
+                    for component in pixel.iter_mut().take(3) {
+                        *component = *component * 0.5 + 0.5;
+                    }
+                }
+            }
+
+            output.encode(&pixels, image.width, image.height, &mut bytes);
+        }
+
+        let mut cooked = TextureData::from_bytes_with_mips(
+            TextureKind::Rectangle {
+                width: width as u32,
+                height: height as u32,
+            },
+            output.pixel_kind(),
+            mip_count,
+            bytes,
+            false,
+        )
+        .ok_or(TextureError::UnsupportedFormat)?;
+        cooked.set_minification_filter(texture.minification_filter());
+        cooked.set_magnification_filter(texture.magnification_filter());
+        cooked.set_s_wrap_mode(texture.s_wrap_mode());
+        cooked.set_t_wrap_mode(texture.t_wrap_mode());
+        cooked.set_anisotropy_level(texture.anisotropy_level());
+        Ok(cooked)
+    }
+
+    /// Loads texture from `source` file, cooks it and saves result to `destination` file,
+    /// which should have `dds` extension to keep mips and compression.
+    pub fn cook_file<S: AsRef<Path>, D: AsRef<Path>>(
+        &self,
+        source: S,
+        destination: D,
+    ) -> Result<(), TextureError> {
+        let data = std::fs::read(source.as_ref())?;
+        let texture = TextureData::load_from_memory(&data, CompressionOptions::NoCompression)?;
+        let mut cooked = self.cook(&texture)?;
+        cooked.set_path(destination);
+        cooked.save()
+    }
+}
+
+fn decode(
+    texture: &TextureData,
+    width: usize,
+    height: usize,
+    format: SourceFormat,
+    channels: usize,
+) -> Image {
+    let component_size = match format {
+        SourceFormat::Unorm8 => 1,
+        SourceFormat::Unorm16 | SourceFormat::Float16 => 2,
+        SourceFormat::Float32 => 4,
+    };
+    let swap_red_blue = matches!(
+        texture.pixel_kind(),
+        TexturePixelKind::BGR8 | TexturePixelKind::BGRA8
+    );
+
+    let pixel_size = component_size * channels;
+    // Only first mip is used, rest of mips (if any) will be rebuilt.
+    let data = &texture.data()[..width * height * pixel_size];
+    let pixels = data
+        .chunks_exact(pixel_size)
+        .map(|bytes| {
+            // Missing color channels are zero, missing alpha is one.
+            let mut pixel = [0.0, 0.0, 0.0, 1.0];
+            for (i, component) in bytes.chunks_exact(component_size).enumerate() {
+                pixel[i] = match format {
+                    SourceFormat::Unorm8 => component[0] as f32 / 255.0,
+                    SourceFormat::Unorm16 => {
+                        u16::from_le_bytes([component[0], component[1]]) as f32 / 65535.0
+                    }
+                    SourceFormat::Float16 => {
+                        f16_to_f32(u16::from_le_bytes([component[0], component[1]]))
+                    }
+                    SourceFormat::Float32 => {
+                        f32::from_le_bytes([component[0], component[1], component[2], component[3]])
+                    }
+                };
+            }
+            if swap_red_blue {
+                pixel.swap(0, 2);
+            }
+            pixel
+        })
+        .collect();
+
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+fn downsample_box(image: &Image, wrap_x: bool, wrap_y: bool) -> Image {
+    let width = (image.width / 2).max(1);
+    let height = (image.height / 2).max(1);
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0; 4];
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+                let pixel =
+                    image.fetch((2 * x + dx) as isize, (2 * y + dy) as isize, wrap_x, wrap_y);
+                for (s, p) in sum.iter_mut().zip(pixel.iter()) {
+                    *s += *p * 0.25;
+                }
+            }
+            pixels.push(sum);
+        }
+    }
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+// Radius of Kaiser filter in pixels of destination mip.
+const KAISER_RADIUS: f32 = 3.0;
+const KAISER_ALPHA: f32 = 4.0;
+
+// Zeroth order modified Bessel function of the first kind.
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x * 0.5;
+    for k in 1..32 {
+        term *= half_x / k as f32;
+        sum += term * term;
+        if term * term < sum * 1.0e-8 {
+            break;
+        }
+    }
+    sum
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1.0e-5 {
+        1.0
+    } else {
+        let x = std::f32::consts::PI * x;
+        x.sin() / x
+    }
+}
+
+fn kaiser(x: f32) -> f32 {
+    let t = x / KAISER_RADIUS;
+    if t.abs() > 1.0 {
+        0.0
+    } else {
+        sinc(x) * bessel_i0(KAISER_ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(KAISER_ALPHA)
+    }
+}
+
+// Weights of source pixels (relative to 2 * destination pixel) for one destination pixel.
+// Center of destination pixel `x` is between source pixels `2x` and `2x + 1`.
+fn kaiser_weights() -> Vec<(isize, f32)> {
+    let radius = (2.0 * KAISER_RADIUS).ceil() as isize;
+    let mut weights = ((1 - radius)..=radius)
+        .map(|offset| (offset, kaiser((offset as f32 - 0.5) * 0.5)))
+        .collect::<Vec<_>>();
+    let sum = weights.iter().map(|(_, w)| *w).sum::<f32>();
+    for (_, weight) in weights.iter_mut() {
+        *weight /= sum;
+    }
+    weights
+}
+
+fn downsample_kaiser(image: &Image, wrap_x: bool, wrap_y: bool) -> Image {
+    let width = (image.width / 2).max(1);
+    let height = (image.height / 2).max(1);
+    let weights = kaiser_weights();
+
+    // Separable filter - horizontal pass first, then vertical.
+    let mut horizontal = Image {
+        width,
+        height: image.height,
+        pixels: Vec::with_capacity(width * image.height),
+    };
+    for y in 0..image.height {
+        for x in 0..width {
+            let mut sum = [0.0; 4];
+            for (offset, weight) in weights.iter() {
+                let pixel = image.fetch(2 * x as isize + offset, y as isize, wrap_x, wrap_y);
+                for (s, p) in sum.iter_mut().zip(pixel.iter()) {
+                    *s += *p * *weight;
+                }
+            }
+            horizontal.pixels.push(sum);
+        }
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0; 4];
+            for (offset, weight) in weights.iter() {
+                let pixel = horizontal.fetch(x as isize, 2 * y as isize + offset, wrap_x, wrap_y);
+                for (s, p) in sum.iter_mut().zip(pixel.iter()) {
+                    *s += *p * *weight;
+                }
+            }
+            pixels.push(sum);
+        }
+    }
+
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+fn renormalize(pixel: &mut [f32; 4]) {
+    let length = (pixel[0] * pixel[0] + pixel[1] * pixel[1] + pixel[2] * pixel[2]).sqrt();
+    if length > f32::EPSILON {
+        for component in pixel.iter_mut().take(3) {
+            *component /= length;
+        }
+    } else {
+        pixel[0] = 0.0;
+        pixel[1] = 0.0;
+        pixel[2] = 1.0;
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    sign * match exponent {
+        // Subnormal numbers.
+        0 => mantissa * 2.0f32.powi(-24),
+        31 => {
+            if mantissa == 0.0 {
+                f32::INFINITY
+            } else {
+                f32::NAN
+            }
+        }
+        _ => (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+    }
+}
+
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if exponent == 0xff {
+        // Infinity or NaN.
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 31 {
+        // Too large, clamp to infinity.
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        if exponent < -10 {
+            // Too small, flush to zero.
+            sign
+        } else {
+            // Subnormal number.
+            let mantissa = mantissa | 0x80_0000;
+            let shift = (14 - exponent) as u32;
+            let half = (mantissa >> shift) as u16;
+            let round = ((mantissa >> (shift - 1)) & 1) as u16;
+            sign | (half + round)
+        }
+    } else {
+        let half = sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16;
+        // Round to nearest, carry to exponent is correct here.
+        half + ((mantissa >> 12) & 1) as u16
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum OutputFormat {
+    R8,
+    RG8,
+    RGBA8,
+    DXT1 { alpha: bool },
+    DXT5,
+    R16,
+    RGBA16,
+    R16F,
+    RG16F,
+    RGBA16F,
+    R32F,
+    RG32F,
+    RGBA32F,
+}
+
+impl OutputFormat {
+    // Three-channel formats are expanded to four channels, because DDS does not have matching
+    // formats for them.
+    fn select(format: SourceFormat, channels: usize, compression: CompressionOptions) -> Self {
+        match (format, channels) {
+            (SourceFormat::Unorm8, 1) => Self::R8,
+            (SourceFormat::Unorm8, 2) => Self::RG8,
+            (SourceFormat::Unorm8, _) => match compression {
+                CompressionOptions::NoCompression => Self::RGBA8,
+                CompressionOptions::Speed => Self::DXT1 {
+                    alpha: channels == 4,
+                },
+                CompressionOptions::Quality => Self::DXT5,
+            },
+            (SourceFormat::Unorm16, 1) => Self::R16,
+            (SourceFormat::Unorm16, _) => Self::RGBA16,
+            (SourceFormat::Float16, 1) => Self::R16F,
+            (SourceFormat::Float16, 2) => Self::RG16F,
+            (SourceFormat::Float16, _) => Self::RGBA16F,
+            (SourceFormat::Float32, 1) => Self::R32F,
+            (SourceFormat::Float32, 2) => Self::RG32F,
+            (SourceFormat::Float32, _) => Self::RGBA32F,
+        }
+    }
+
+    fn pixel_kind(self) -> TexturePixelKind {
+        match self {
+            Self::R8 => TexturePixelKind::R8,
+            Self::RG8 => TexturePixelKind::RG8,
+            Self::RGBA8 => TexturePixelKind::RGBA8,
+            Self::DXT1 { alpha: true } => TexturePixelKind::DXT1RGBA,
+            Self::DXT1 { alpha: false } => TexturePixelKind::DXT1RGB,
+            Self::DXT5 => TexturePixelKind::DXT5RGBA,
+            Self::R16 => TexturePixelKind::R16,
+            Self::RGBA16 => TexturePixelKind::RGBA16,
+            Self::R16F => TexturePixelKind::R16F,
+            Self::RG16F => TexturePixelKind::RG16F,
+            Self::RGBA16F => TexturePixelKind::RGBA16F,
+            Self::R32F => TexturePixelKind::R32F,
+            Self::RG32F => TexturePixelKind::RG32F,
+            Self::RGBA32F => TexturePixelKind::RGBA32F,
+        }
+    }
+
+    fn channels(self) -> usize {
+        match self {
+            Self::R8 | Self::R16 | Self::R16F | Self::R32F => 1,
+            Self::RG8 | Self::RG16F | Self::RG32F => 2,
+            _ => 4,
+        }
+    }
+
+    fn encode(self, pixels: &[[f32; 4]], width: usize, height: usize, bytes: &mut Vec<u8>) {
+        let unorm8 = |v: f32| (v.max(0.0).min(1.0) * 255.0 + 0.5) as u8;
+
+        match self {
+            Self::DXT1 { .. } | Self::DXT5 => {
+                // Block compression works with 4x4 blocks, so small mips must be padded.
+                let padded_width = width.div_ceil(4) * 4;
+                let padded_height = height.div_ceil(4) * 4;
+                let mut rgba = Vec::with_capacity(padded_width * padded_height * 4);
+                for y in 0..padded_height {
+                    for x in 0..padded_width {
+                        let pixel = pixels[y.min(height - 1) * width + x.min(width - 1)];
+                        rgba.extend(pixel.iter().map(|c| unorm8(*c)));
+                    }
+                }
+                let compressed = if self == Self::DXT5 {
+                    compress_bc3::<tbc::color::Rgba8>(&rgba, padded_width, padded_height)
+                } else {
+                    compress_bc1::<tbc::color::Rgba8>(&rgba, padded_width, padded_height)
+                };
+                bytes.extend_from_slice(&compressed);
+            }
+            _ => {
+                let channels = self.channels();
+                for pixel in pixels {
+                    for component in pixel.iter().take(channels) {
+                        match self {
+                            Self::R8 | Self::RG8 | Self::RGBA8 => bytes.push(unorm8(*component)),
+                            Self::R16 | Self::RGBA16 => {
+                                let value = (component.max(0.0).min(1.0) * 65535.0 + 0.5) as u16;
+                                bytes.extend_from_slice(&value.to_le_bytes());
+                            }
+                            Self::R16F | Self::RG16F | Self::RGBA16F => {
+                                bytes.extend_from_slice(&f32_to_f16(*component).to_le_bytes());
+                            }
+                            _ => bytes.extend_from_slice(&component.to_le_bytes()),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::resource::{
+        texture::{CompressionOptions, TextureData, TextureKind, TexturePixelKind},
+        texture_cooker::{f16_to_f32, f32_to_f16, MipFilter, TextureCooker},
+    };
+
+    #[test]
+    fn half_float_round_trip() {
+        for value in [0.0, 1.0, -2.5, 0.333, 65504.0, 1.0e-5].iter() {
+            let converted = f16_to_f32(f32_to_f16(*value));
+            assert!((converted - value).abs() <= value.abs() * 1.0e-3 + 1.0e-7);
+        }
+    }
+
+    #[test]
+    fn cook_full_mip_chain() {
+        let texture = TextureData::from_bytes(
+            TextureKind::Rectangle {
+                width: 16,
+                height: 8,
+            },
+            TexturePixelKind::RGB8,
+            vec![255; 16 * 8 * 3],
+            false,
+        )
+        .unwrap();
+
+        for filter in [MipFilter::Box, MipFilter::Kaiser].iter() {
+            let cooked = TextureCooker::new()
+                .with_filter(*filter)
+                .with_gamma_correction(true)
+                .cook(&texture)
+                .unwrap();
+            assert_eq!(cooked.pixel_kind(), TexturePixelKind::RGBA8);
+            // 16x8, 8x4, 4x2, 2x1
+            assert_eq!(cooked.mip_count(), 4);
+            assert_eq!(cooked.data().len(), (128 + 32 + 8 + 2) * 4);
+            // Constant color must be preserved by any filter.
+            assert!(cooked.data().iter().all(|b| *b == 255));
+        }
+
+        let compressed = TextureCooker::new()
+            .with_compression(CompressionOptions::Speed)
+            .cook(&texture)
+            .unwrap();
+        assert_eq!(compressed.pixel_kind(), TexturePixelKind::DXT1RGB);
+        assert_eq!(compressed.data().len(), (8 + 2 + 1 + 1) * 8);
+    }
+}
@@ -14,7 +14,13 @@ use crate::{
         visitor::{Visit, VisitError, VisitResult, Visitor},
     },
     resource::model::Model,
-    scene::{graph::Graph, node::Node, transform::Transform},
+    scene::{
+        component::{Component, ComponentContainer},
+        graph::Graph,
+        node::Node,
+        timestep,
+        transform::Transform,
+    },
 };
 use std::cell::Cell;
 
@@ -231,6 +237,12 @@ pub struct Base {
     pub(in crate) parent: Handle<Node>,
     pub(in crate) children: Vec<Handle<Node>>,
     pub(in crate) global_transform: Cell<Matrix4<f32>>,
+    /// Global transform from previous fixed-timestep tick. Non-serializable.
+    pub(in crate) previous_global_transform: Cell<Matrix4<f32>>,
+    /// Whether global transform was calculated at least once. A node that was never updated
+    /// has no meaningful previous transform, so it is snapped to the current one on first
+    /// update. Non-serializable.
+    pub(in crate) global_transform_calculated: Cell<bool>,
     /// Bone-specific matrix. Non-serializable.
     pub(in crate) inv_bind_pose_transform: Matrix4<f32>,
     /// A resource from which this node was instantiated from, can work in pair
@@ -251,12 +263,16 @@ pub struct Base {
     mobility: Mobility,
     tag: String,
     pub(in crate) physics_binding: PhysicsBinding,
+    /// Set when name of the node has changed, used by graph to emit rename events.
+    pub(in crate) name_changed: bool,
+    components: ComponentContainer,
 }
 
 impl Base {
     /// Sets name of node. Can be useful to mark a node to be able to find it later on.
     pub fn set_name<N: AsRef<str>>(&mut self, name: N) -> &mut Self {
         self.name = name.as_ref().to_owned();
+        self.name_changed = true;
         self
     }
 
@@ -326,6 +342,24 @@ impl Base {
         self.global_transform.get()
     }
 
+    /// Returns global transform matrix that node had before last scene update (or before last
+    /// tick if scene is updated with fixed timestep).
+    pub fn previous_global_transform(&self) -> Matrix4<f32> {
+        self.previous_global_transform.get()
+    }
+
+    /// Returns global transform matrix interpolated between previous and current fixed-timestep
+    /// ticks. Interpolation factor should be taken from [Graph::interpolation_factor].
+    pub fn interpolated_global_transform(&self, factor: f32) -> Matrix4<f32> {
+        let current = self.global_transform.get();
+        let previous = self.previous_global_transform.get();
+        if factor >= 1.0 || previous == current {
+            current
+        } else {
+            timestep::interpolate_transforms(&previous, &current, factor)
+        }
+    }
+
     /// Returns inverse of bind pose matrix. Bind pose matrix - is special matrix
     /// for bone nodes, it stores initial transform of bone node at the moment
     /// of "binding" vertices to bones.
@@ -457,6 +491,37 @@ impl Base {
         self.physics_binding = binding;
     }
 
+    /// Attaches a component to the node. Node can have only one component of each type,
+    /// previous component of the same type (if any) will be returned.
+    pub fn add_component<T: Component>(&mut self, component: T) -> Option<T> {
+        self.components.add(component)
+    }
+
+    /// Detaches a component of given type from the node and returns it.
+    pub fn remove_component<T: Component>(&mut self) -> Option<T> {
+        self.components.remove::<T>()
+    }
+
+    /// Returns shared reference to a component of given type.
+    pub fn component<T: Component>(&self) -> Option<&T> {
+        self.components.get::<T>()
+    }
+
+    /// Returns mutable reference to a component of given type.
+    pub fn component_mut<T: Component>(&mut self) -> Option<&mut T> {
+        self.components.get_mut::<T>()
+    }
+
+    /// Returns true if the node has a component of given type.
+    pub fn has_component<T: Component>(&self) -> bool {
+        self.components.contains::<T>()
+    }
+
+    /// Returns shared reference to the set of components of the node.
+    pub fn components(&self) -> &ComponentContainer {
+        &self.components
+    }
+
     /// Shallow copy of node data. You should never use this directly, shallow copy
     /// will produce invalid node in most cases!
     pub fn raw_copy(&self) -> Self {
@@ -464,6 +529,10 @@ impl Base {
             name: self.name.clone(),
             local_transform: self.local_transform.clone(),
             global_transform: self.global_transform.clone(),
+            previous_global_transform: self.previous_global_transform.clone(),
+            // Copy may be placed anywhere, it must not be interpolated from the location of
+            // the original.
+            global_transform_calculated: Cell::new(false),
             visibility: self.visibility,
             global_visibility: self.global_visibility.clone(),
             inv_bind_pose_transform: self.inv_bind_pose_transform,
@@ -474,6 +543,7 @@ impl Base {
             tag: self.tag.clone(),
             physics_binding: self.physics_binding,
             lod_group: self.lod_group.clone(),
+            components: self.components.clone(),
             // Rest of data is *not* copied!
             ..Default::default()
         }
@@ -506,6 +576,11 @@ impl Visit for Base {
             .visit("Original", visitor)?;
         self.tag.visit("Tag", visitor)?;
         self.physics_binding.visit("PhysicsBinding", visitor)?;
+        // Components are optional to be able to load older saves.
+        match self.components.visit("Components", visitor) {
+            Err(VisitError::RegionDoesNotExist(region)) if region == "Components" => (),
+            result => result?,
+        }
 
         visitor.leave_region()
     }
@@ -523,6 +598,7 @@ pub struct BaseBuilder {
     mobility: Mobility,
     inv_bind_pose_transform: Matrix4<f32>,
     tag: String,
+    components: ComponentContainer,
 }
 
 impl Default for BaseBuilder {
@@ -545,6 +621,7 @@ impl BaseBuilder {
             mobility: Mobility::Dynamic,
             inv_bind_pose_transform: Matrix4::identity(),
             tag: Default::default(),
+            components: Default::default(),
         }
     }
 
@@ -615,6 +692,12 @@ impl BaseBuilder {
         self
     }
 
+    /// Adds desired component.
+    pub fn with_component<T: Component>(mut self, component: T) -> Self {
+        self.components.add(component);
+        self
+    }
+
     pub(in crate) fn build_base(self) -> Base {
         Base {
             name: self.name,
@@ -625,6 +708,8 @@ impl BaseBuilder {
             global_visibility: Cell::new(true),
             parent: Handle::NONE,
             global_transform: Cell::new(Matrix4::identity()),
+            previous_global_transform: Cell::new(Matrix4::identity()),
+            global_transform_calculated: Cell::new(false),
             inv_bind_pose_transform: self.inv_bind_pose_transform,
             resource: None,
             original_handle_in_resource: Handle::NONE,
@@ -634,6 +719,8 @@ impl BaseBuilder {
             mobility: self.mobility,
             tag: self.tag,
             physics_binding: PhysicsBinding::NodeWithBody,
+            name_changed: false,
+            components: self.components,
         }
     }
 
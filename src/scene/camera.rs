@@ -13,6 +13,10 @@
 //!
 //! Each camera forces engine to re-render same scene one more time, which may cause
 //! almost double load of your GPU.
+//!
+//! # Post-processing
+//!
+//! Each camera has its own post-processing settings, see [`PostProcessing`] docs for more info.
 
 use crate::core::algebra::{Matrix4, Vector2, Vector3, Vector4};
 use crate::core::pool::Handle;
@@ -27,6 +31,7 @@ use crate::{
     scene::{
         base::{Base, BaseBuilder},
         node::Node,
+        post_processing::PostProcessing,
         VisibilityCache,
     },
 };
@@ -46,6 +51,7 @@ pub struct Camera {
     enabled: bool,
     skybox: Option<Box<SkyBox>>,
     environment: Option<Texture>,
+    post_processing: PostProcessing,
     /// Visibility cache allows you to quickly check if object is visible from the camera or not.
     pub visibility_cache: VisibilityCache,
 }
@@ -81,6 +87,7 @@ impl Visit for Camera {
         self.enabled.visit("Enabled", visitor)?;
         self.skybox.visit("SkyBox", visitor)?;
         self.environment.visit("Environment", visitor)?;
+        let _ = self.post_processing.visit("PostProcessing", visitor);
         // self.visibility_cache intentionally not serialized. It is valid only for one frame.
         visitor.leave_region()
     }
@@ -91,11 +98,7 @@ impl Camera {
     /// this method, it will be called automatically when new frame starts.
     #[inline]
     pub fn calculate_matrices(&mut self, frame_size: Vector2<f32>) {
-        let pos = self.base.global_position();
-        let look = self.base.look_vector();
-        let up = self.base.up_vector();
-
-        self.view_matrix = Matrix4::look_at_rh(&Point3::from(pos), &Point3::from(pos + look), &up);
+        self.calculate_view_matrix();
 
         let viewport = self.viewport_pixels(frame_size);
         let aspect = viewport.w() as f32 / viewport.h() as f32;
@@ -103,6 +106,14 @@ impl Camera {
             Matrix4::new_perspective(aspect, self.fov, self.z_near, self.z_far);
     }
 
+    pub(in crate) fn calculate_view_matrix(&mut self) {
+        let pos = self.base.global_position();
+        let look = self.base.look_vector();
+        let up = self.base.up_vector();
+
+        self.view_matrix = Matrix4::look_at_rh(&Point3::from(pos), &Point3::from(pos + look), &up);
+    }
+
     /// Sets new viewport in resolution-independent format. In other words
     /// each parameter of viewport defines portion of your current resolution
     /// in percents. In example viewport (0.0, 0.0, 0.5, 1.0) will force camera
@@ -242,6 +253,22 @@ impl Camera {
         self.environment.clone()
     }
 
+    /// Sets new post-processing settings.
+    pub fn set_post_processing(&mut self, post_processing: PostProcessing) -> &mut Self {
+        self.post_processing = post_processing;
+        self
+    }
+
+    /// Returns shared reference to post-processing settings.
+    pub fn post_processing(&self) -> &PostProcessing {
+        &self.post_processing
+    }
+
+    /// Returns mutable reference to post-processing settings.
+    pub fn post_processing_mut(&mut self) -> &mut PostProcessing {
+        &mut self.post_processing
+    }
+
     /// Creates picking ray from given screen coordinates.
     pub fn make_ray(&self, screen_coord: Vector2<f32>, screen_size: Vector2<f32>) -> Ray {
         let viewport = self.viewport_pixels(screen_size);
@@ -294,6 +321,7 @@ impl Camera {
             enabled: self.enabled,
             skybox: self.skybox.clone(),
             environment: self.environment.clone(),
+            post_processing: self.post_processing.clone(),
             // No need to copy cache. It is valid only for one frame.
             visibility_cache: Default::default(),
         }
@@ -311,6 +339,7 @@ pub struct CameraBuilder {
     enabled: bool,
     skybox: Option<SkyBox>,
     environment: Option<Texture>,
+    post_processing: PostProcessing,
 }
 
 impl CameraBuilder {
@@ -325,6 +354,7 @@ impl CameraBuilder {
             viewport: Rect::new(0.0, 0.0, 1.0, 1.0),
             skybox: None,
             environment: None,
+            post_processing: Default::default(),
         }
     }
 
@@ -370,6 +400,12 @@ impl CameraBuilder {
         self
     }
 
+    /// Sets desired post-processing settings.
+    pub fn with_post_processing(mut self, post_processing: PostProcessing) -> Self {
+        self.post_processing = post_processing;
+        self
+    }
+
     /// Creates new instance of camera.
     pub fn build_camera(self) -> Camera {
         Camera {
@@ -386,6 +422,7 @@ impl CameraBuilder {
             visibility_cache: Default::default(),
             skybox: self.skybox.map(Box::new),
             environment: self.environment,
+            post_processing: self.post_processing,
         }
     }
 
@@ -0,0 +1,278 @@
+//! Contains all structures and methods to attach arbitrary user data to scene nodes.
+//!
+//! Component is a piece of typed user data that is stored directly in a scene node.
+//! Components are copied together with their nodes (see [Graph::copy_node](crate::scene::graph::Graph::copy_node)),
+//! dropped when their nodes are removed and saved together with a scene. To be able to load
+//! a component back, its type must be registered in global component registry by
+//! [`register_component`] before loading.
+//!
+//! # Example
+//!
+//! ```
+//! use rg3d::{
+//!     core::{
+//!         uuid::Uuid,
+//!         visitor::{Visit, VisitResult, Visitor},
+//!     },
+//!     scene::{
+//!         base::BaseBuilder,
+//!         component::{register_component, Component},
+//!     },
+//! };
+//!
+//! #[derive(Default, Clone, Debug)]
+//! struct Health(f32);
+//!
+//! impl Visit for Health {
+//!     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+//!         self.0.visit(name, visitor)
+//!     }
+//! }
+//!
+//! impl Component for Health {
+//!     const TYPE_UUID: Uuid = Uuid::from_u128(0x8f2c_9a4e_3b71_4d0e_a5c6_1e2f_7d3b_9c40);
+//! }
+//!
+//! register_component::<Health>();
+//!
+//! let mut node = BaseBuilder::new().build_node();
+//! node.add_component(Health(100.0));
+//! assert_eq!(node.component::<Health>().unwrap().0, 100.0);
+//! ```
+
+use crate::{
+    core::{
+        uuid::Uuid,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    lazy_static::lazy_static,
+    utils::log::{Log, MessageKind},
+};
+use std::{any::Any, collections::HashMap, fmt::Debug, sync::Mutex};
+
+/// A piece of typed user data that can be attached to a scene node. See module docs
+/// for more info.
+///
+/// # Notes
+///
+/// Components are copied as is, this means that if a component holds handles to other
+/// nodes, those handles won't be remapped when a node is copied.
+pub trait Component: Any + Clone + Default + Debug + Visit + Send {
+    /// Unique and stable identifier of the component type. It is written in a save file
+    /// and used to create an instance of the component on load, so it must never change.
+    const TYPE_UUID: Uuid;
+}
+
+/// Object-safe version of [`Component`]. It is implemented automatically for every
+/// component, there is no need to implement it manually.
+pub trait ComponentObject: Debug + Visit + Send {
+    /// Returns identifier of the type of the component.
+    fn type_uuid(&self) -> Uuid;
+
+    /// Creates a boxed copy of the component.
+    fn clone_boxed(&self) -> Box<dyn ComponentObject>;
+
+    /// Casts component to `&dyn Any`.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Casts component to `&mut dyn Any`.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Casts boxed component to `Box<dyn Any>`.
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Component> ComponentObject for T {
+    fn type_uuid(&self) -> Uuid {
+        T::TYPE_UUID
+    }
+
+    fn clone_boxed(&self) -> Box<dyn ComponentObject> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+type ComponentConstructor = fn() -> Box<dyn ComponentObject>;
+
+lazy_static! {
+    static ref REGISTRY: Mutex<HashMap<Uuid, ComponentConstructor>> = Default::default();
+}
+
+fn construct<T: Component>() -> Box<dyn ComponentObject> {
+    Box::new(T::default())
+}
+
+/// Registers component type in global registry. Registration is required to be able
+/// to load components of given type, it is safe to register same type multiple times.
+pub fn register_component<T: Component>() {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .insert(T::TYPE_UUID, construct::<T>);
+}
+
+/// Returns true if component type with given identifier is registered.
+pub fn is_component_registered(type_uuid: &Uuid) -> bool {
+    REGISTRY.lock().unwrap().contains_key(type_uuid)
+}
+
+fn create_component(type_uuid: &Uuid) -> Option<Box<dyn ComponentObject>> {
+    REGISTRY
+        .lock()
+        .unwrap()
+        .get(type_uuid)
+        .map(|constructor| constructor())
+}
+
+/// A set of components of a node, it can hold only one component of each type.
+#[derive(Debug, Default)]
+pub struct ComponentContainer {
+    components: Vec<Box<dyn ComponentObject>>,
+}
+
+impl Clone for ComponentContainer {
+    fn clone(&self) -> Self {
+        Self {
+            components: self.components.iter().map(|c| c.clone_boxed()).collect(),
+        }
+    }
+}
+
+impl ComponentContainer {
+    fn position<T: Component>(&self) -> Option<usize> {
+        self.components
+            .iter()
+            .position(|c| c.type_uuid() == T::TYPE_UUID)
+    }
+
+    /// Adds new component to the set, returns previous component of the same type (if any).
+    pub fn add<T: Component>(&mut self, component: T) -> Option<T> {
+        let previous = self.remove::<T>();
+        self.components.push(Box::new(component));
+        previous
+    }
+
+    /// Removes a component of given type from the set and returns it.
+    pub fn remove<T: Component>(&mut self) -> Option<T> {
+        self.position::<T>().and_then(|i| {
+            self.components
+                .remove(i)
+                .into_any()
+                .downcast::<T>()
+                .ok()
+                .map(|c| *c)
+        })
+    }
+
+    /// Returns shared reference to a component of given type.
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        self.components
+            .iter()
+            .find_map(|c| c.as_any().downcast_ref::<T>())
+    }
+
+    /// Returns mutable reference to a component of given type.
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+        self.components
+            .iter_mut()
+            .find_map(|c| c.as_any_mut().downcast_mut::<T>())
+    }
+
+    /// Returns true if the set contains a component of given type.
+    pub fn contains<T: Component>(&self) -> bool {
+        self.position::<T>().is_some()
+    }
+
+    /// Returns an iterator over every component in the set.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn ComponentObject> {
+        self.components.iter().map(|c| &**c)
+    }
+
+    /// Returns amount of components in the set.
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns true if the set has no components.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Removes every component from the set.
+    pub fn clear(&mut self) {
+        self.components.clear();
+    }
+}
+
+impl ComponentContainer {
+    fn visit_items(&mut self, visitor: &mut Visitor) -> VisitResult {
+        let mut len = self.components.len() as u32;
+        len.visit("Length", visitor)?;
+
+        if visitor.is_reading() {
+            self.components.clear();
+            for index in 0..len {
+                visitor.enter_region(&format!("Item{}", index))?;
+                let result = self.read_item(visitor);
+                visitor.leave_region()?;
+                result?;
+            }
+        } else {
+            for (index, component) in self.components.iter_mut().enumerate() {
+                visitor.enter_region(&format!("Item{}", index))?;
+                let mut type_uuid = component.type_uuid();
+                let result = type_uuid
+                    .visit("TypeUuid", visitor)
+                    .and_then(|_| component.visit("Data", visitor));
+                visitor.leave_region()?;
+                result?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_item(&mut self, visitor: &mut Visitor) -> VisitResult {
+        let mut type_uuid = Uuid::nil();
+        type_uuid.visit("TypeUuid", visitor)?;
+
+        if let Some(mut component) = create_component(&type_uuid) {
+            component.visit("Data", visitor)?;
+            self.components.push(component);
+        } else {
+            Log::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Unknown component type {}, it will be skipped! \
+                    Did you forget to register it?",
+                    type_uuid
+                ),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Visit for ComponentContainer {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+        // Region must be left even if a component has failed to load, otherwise the caller
+        // would continue reading fields from the wrong region.
+        let result = self.visit_items(visitor);
+        visitor.leave_region()?;
+        result
+    }
+}
@@ -38,16 +38,55 @@ use crate::{
     utils::log::{Log, MessageKind},
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     ops::{Index, IndexMut},
 };
 
+/// A change that happened to a node in a graph. Events are recorded only if recording
+/// was enabled by [`Graph::set_event_recording`], use [`Graph::pop_event`] to fetch them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GraphEvent {
+    /// A node was added to the graph.
+    NodeAdded(Handle<Node>),
+
+    /// A node was removed from the graph. Handle is already invalid at the moment
+    /// when you receive this event.
+    NodeRemoved(Handle<Node>),
+
+    /// A node was attached to a new parent.
+    NodeLinked {
+        /// Handle of a child node.
+        child: Handle<Node>,
+        /// Handle of a new parent node.
+        parent: Handle<Node>,
+    },
+
+    /// A node was temporarily extracted from the graph by [`Graph::take_reserve`]. Its handle
+    /// stays reserved until the node is put back or the ticket is forgotten.
+    NodeTaken(Handle<Node>),
+
+    /// A previously extracted node was put back into the graph. The node is attached to the
+    /// root of the graph, so this event is followed by [`GraphEvent::NodeLinked`].
+    NodePutBack(Handle<Node>),
+
+    /// Name of a node has changed.
+    NodeRenamed(Handle<Node>),
+
+    /// Global transform of a node has changed. This event is emitted during hierarchical
+    /// data update, so it also covers changes that were caused by movement of ancestors.
+    TransformChanged(Handle<Node>),
+}
+
 /// See module docs.
 #[derive(Debug)]
 pub struct Graph {
     root: Handle<Node>,
     pool: Pool<Node>,
     stack: Vec<Handle<Node>>,
+    record_events: bool,
+    events: VecDeque<GraphEvent>,
+    interpolation_factor: f32,
+    actual_transforms: Vec<Matrix4<f32>>,
 }
 
 impl Default for Graph {
@@ -56,6 +95,10 @@ impl Default for Graph {
             root: Handle::NONE,
             pool: Pool::new(),
             stack: Vec::new(),
+            record_events: false,
+            events: Default::default(),
+            interpolation_factor: 1.0,
+            actual_transforms: Default::default(),
         }
     }
 }
@@ -75,7 +118,10 @@ pub struct SubGraph {
     pub descendants: Vec<(Ticket<Node>, Node)>,
 }
 
-fn remap_handles(old_new_mapping: &HashMap<Handle<Node>, Handle<Node>>, dest_graph: &mut Graph) {
+pub(in crate) fn remap_handles(
+    old_new_mapping: &HashMap<Handle<Node>, Handle<Node>>,
+    dest_graph: &mut Graph,
+) {
     // Iterate over instantiated nodes and remap handles.
     for (_, &new_node_handle) in old_new_mapping.iter() {
         let new_node = &mut dest_graph.pool[new_node_handle];
@@ -119,6 +165,51 @@ impl Graph {
             stack: Vec::new(),
             root,
             pool,
+            record_events: false,
+            events: Default::default(),
+            interpolation_factor: 1.0,
+            actual_transforms: Default::default(),
+        }
+    }
+
+    /// Enables or disables recording of graph events. Recording is disabled by default,
+    /// because events must be fetched by [`Self::pop_event`] each frame, otherwise the
+    /// queue will grow indefinitely. Disabling recording also clears the queue.
+    pub fn set_event_recording(&mut self, enabled: bool) {
+        if enabled && !self.record_events {
+            // Discard changes that were made before recording was enabled.
+            for node in self.pool.iter_mut() {
+                node.name_changed = false;
+            }
+        }
+        if !enabled {
+            self.events.clear();
+        }
+        self.record_events = enabled;
+    }
+
+    /// Returns true if graph records events, false - otherwise.
+    pub fn is_event_recording_enabled(&self) -> bool {
+        self.record_events
+    }
+
+    /// Extracts oldest event from the queue of graph events. Events are recorded only
+    /// if recording is enabled, see [`Self::set_event_recording`].
+    ///
+    /// ```
+    /// use rg3d::scene::{graph::{Graph, GraphEvent}, node::Node};
+    /// let mut graph = Graph::new();
+    /// graph.set_event_recording(true);
+    /// let node = graph.add_node(Node::Base(Default::default()));
+    /// assert_eq!(graph.pop_event(), Some(GraphEvent::NodeAdded(node)));
+    /// ```
+    pub fn pop_event(&mut self) -> Option<GraphEvent> {
+        self.events.pop_front()
+    }
+
+    fn push_event(&mut self, event: GraphEvent) {
+        if self.record_events {
+            self.events.push_back(event);
         }
     }
 
@@ -130,8 +221,9 @@ impl Graph {
         let children = node.children.clone();
         node.children.clear();
         let handle = self.pool.spawn(node);
+        self.push_event(GraphEvent::NodeAdded(handle));
         if self.root.is_some() {
-            self.link_nodes(handle, self.root);
+            self.link_nodes_internal(handle, self.root);
         }
         for child in children {
             self.link_nodes(child, handle);
@@ -196,6 +288,7 @@ impl Graph {
                 self.stack.push(child);
             }
             self.pool.free(handle);
+            self.push_event(GraphEvent::NodeRemoved(handle));
         }
     }
 
@@ -215,6 +308,11 @@ impl Graph {
     /// Links specified child with specified parent.
     #[inline]
     pub fn link_nodes(&mut self, child: Handle<Node>, parent: Handle<Node>) {
+        self.link_nodes_internal(child, parent);
+        self.push_event(GraphEvent::NodeLinked { child, parent });
+    }
+
+    fn link_nodes_internal(&mut self, child: Handle<Node>, parent: Handle<Node>) {
         self.unlink_internal(child);
         self.pool[child].parent = parent;
         self.pool[parent].children.push(child);
@@ -759,7 +857,11 @@ impl Graph {
     /// need to know global transform of nodes before entering update loop, then you can call
     /// this method.
     pub fn update_hierarchical_data(&mut self) {
-        fn update_recursively(graph: &Graph, node_handle: Handle<Node>) {
+        fn update_recursively(
+            graph: &Graph,
+            node_handle: Handle<Node>,
+            changed: &mut Option<Vec<Handle<Node>>>,
+        ) {
             let node = &graph.pool[node_handle];
 
             let (parent_global_transform, parent_visibility) =
@@ -769,17 +871,112 @@ impl Graph {
                     (Matrix4::identity(), true)
                 };
 
-            node.global_transform
-                .set(parent_global_transform * node.local_transform().matrix());
+            let new_global_transform = parent_global_transform * node.local_transform().matrix();
+            if let Some(changed) = changed.as_mut() {
+                if node.global_transform.get() != new_global_transform {
+                    changed.push(node_handle);
+                }
+            }
+            node.global_transform.set(new_global_transform);
+            if !node.global_transform_calculated.get() {
+                // Node was added (or copied) between ticks, it must appear at its actual
+                // location instead of moving there from its initial transform.
+                node.previous_global_transform.set(new_global_transform);
+                node.global_transform_calculated.set(true);
+            }
             node.global_visibility
                 .set(parent_visibility && node.visibility());
 
             for &child in node.children() {
-                update_recursively(graph, child);
+                update_recursively(graph, child, changed);
             }
         }
 
-        update_recursively(self, self.root);
+        let mut changed = if self.record_events {
+            Some(Vec::new())
+        } else {
+            None
+        };
+
+        update_recursively(self, self.root, &mut changed);
+
+        if let Some(changed) = changed {
+            for (handle, node) in self.pool.pair_iter_mut() {
+                if node.name_changed {
+                    node.name_changed = false;
+                    self.events.push_back(GraphEvent::NodeRenamed(handle));
+                }
+            }
+
+            self.events
+                .extend(changed.into_iter().map(GraphEvent::TransformChanged));
+        }
+    }
+
+    /// Returns interpolation factor between previous and current fixed-timestep ticks. It is
+    /// always 1.0 if scene is not updated with fixed timestep. Use it with
+    /// [Base::interpolated_global_transform](crate::scene::base::Base::interpolated_global_transform)
+    /// to get smooth movement of nodes when tick rate does not match frame rate, or see
+    /// [`Self::begin_interpolation`].
+    pub fn interpolation_factor(&self) -> f32 {
+        self.interpolation_factor
+    }
+
+    pub(in crate) fn set_interpolation_factor(&mut self, factor: f32) {
+        self.interpolation_factor = factor;
+    }
+
+    /// Replaces global transforms of nodes with transforms interpolated between previous and
+    /// current fixed-timestep ticks and recalculates data that depends on them (view matrices
+    /// of cameras and skin poses), so every kind of node is rendered at its interpolated
+    /// location. Actual transforms must be brought back by [`Self::end_interpolation`] before
+    /// next update. Does nothing if interpolation factor is 1.0.
+    ///
+    /// [Engine::render](crate::engine::Engine::render) calls this method automatically, call it
+    /// manually only if you render scenes by the renderer directly.
+    pub fn begin_interpolation(&mut self) {
+        if self.interpolation_factor >= 1.0 || !self.actual_transforms.is_empty() {
+            return;
+        }
+
+        let factor = self.interpolation_factor;
+        for node in self.pool.iter() {
+            self.actual_transforms.push(node.global_transform.get());
+            node.global_transform
+                .set(node.interpolated_global_transform(factor));
+        }
+        self.update_transform_dependent_data();
+    }
+
+    /// Brings back actual global transforms of nodes that were replaced by
+    /// [`Self::begin_interpolation`].
+    pub fn end_interpolation(&mut self) {
+        if self.actual_transforms.is_empty() {
+            return;
+        }
+
+        for (node, transform) in self.pool.iter().zip(self.actual_transforms.drain(..)) {
+            node.global_transform.set(transform);
+        }
+        self.update_transform_dependent_data();
+    }
+
+    fn update_transform_dependent_data(&mut self) {
+        for node in self.pool.iter_mut() {
+            if let Node::Camera(camera) = node {
+                camera.calculate_view_matrix();
+            }
+        }
+        self.update_skin_poses();
+    }
+
+    /// Remembers current global transform of each node as previous. Called at the beginning
+    /// of each fixed-timestep tick.
+    pub(in crate) fn save_previous_transforms(&mut self) {
+        for node in self.pool.iter() {
+            node.previous_global_transform
+                .set(node.global_transform.get());
+        }
     }
 
     /// Checks whether given node handle is valid or not.
@@ -787,9 +984,33 @@ impl Graph {
         self.pool.is_valid_handle(node_handle)
     }
 
+    /// Recalculates skin poses of every skinned mesh in the graph. Must be called after global
+    /// transforms are updated, [`Graph::update_nodes`] does it automatically.
+    pub fn update_skin_poses(&mut self) {
+        for i in 0..self.pool.get_capacity() {
+            // Take poses out of the mesh to reuse their memory while the graph is borrowed.
+            let mut poses = match self.pool.at_mut(i) {
+                Some(Node::Mesh(mesh)) if mesh.is_skinned() => mesh.take_skin_poses(),
+                _ => continue,
+            };
+
+            if let Some(Node::Mesh(mesh)) = self.pool.at(i) {
+                poses.resize_with(mesh.surfaces().len(), Default::default);
+                for (pose, surface) in poses.iter_mut().zip(mesh.surfaces()) {
+                    pose.update(self, surface.bones());
+                }
+            }
+
+            if let Some(Node::Mesh(mesh)) = self.pool.at_mut(i) {
+                mesh.set_skin_poses(poses);
+            }
+        }
+    }
+
     /// Updates nodes in graph using given delta time. There is no need to call it manually.
     pub fn update_nodes(&mut self, frame_size: Vector2<f32>, dt: f32) {
         self.update_hierarchical_data();
+        self.update_skin_poses();
 
         for i in 0..self.pool.get_capacity() {
             if let Some(node) = self.pool.at_mut(i) {
@@ -833,6 +1054,7 @@ impl Graph {
                         }
                         Node::ParticleSystem(particle_system) => particle_system.update(dt),
                         Node::Terrain(terrain) => terrain.update(),
+                        Node::Mesh(mesh) => mesh.update_morph_targets(),
                         _ => (),
                     }
                 }
@@ -909,19 +1131,33 @@ impl Graph {
     /// detached from its parent!
     pub fn take_reserve(&mut self, handle: Handle<Node>) -> (Ticket<Node>, Node) {
         self.unlink_internal(handle);
-        self.pool.take_reserve(handle)
+        self.take_reserve_internal(handle)
+    }
+
+    fn take_reserve_internal(&mut self, handle: Handle<Node>) -> (Ticket<Node>, Node) {
+        let pair = self.pool.take_reserve(handle);
+        self.push_event(GraphEvent::NodeTaken(handle));
+        pair
     }
 
     /// Puts node back by given ticket. Attaches back to root node of graph.
     pub fn put_back(&mut self, ticket: Ticket<Node>, node: Node) -> Handle<Node> {
-        let handle = self.pool.put_back(ticket, node);
+        let handle = self.put_back_internal(ticket, node);
         self.link_nodes(handle, self.root);
         handle
     }
 
+    fn put_back_internal(&mut self, ticket: Ticket<Node>, node: Node) -> Handle<Node> {
+        let handle = self.pool.put_back(ticket, node);
+        self.push_event(GraphEvent::NodePutBack(handle));
+        handle
+    }
+
     /// Makes node handle vacant again.
     pub fn forget_ticket(&mut self, ticket: Ticket<Node>) {
-        self.pool.forget_ticket(ticket)
+        let handle = self.pool.ticket_handle(&ticket);
+        self.pool.forget_ticket(ticket);
+        self.push_event(GraphEvent::NodeRemoved(handle));
     }
 
     /// Extracts sub-graph starting from a given node. All handles to extracted nodes
@@ -934,7 +1170,7 @@ impl Graph {
         let mut stack = self[root].children().to_vec();
         while let Some(handle) = stack.pop() {
             stack.extend_from_slice(self[handle].children());
-            descendants.push(self.pool.take_reserve(handle));
+            descendants.push(self.take_reserve_internal(handle));
         }
 
         SubGraph {
@@ -949,7 +1185,7 @@ impl Graph {
     /// parent.
     pub fn put_sub_graph_back(&mut self, sub_graph: SubGraph) -> Handle<Node> {
         for (ticket, node) in sub_graph.descendants {
-            self.pool.put_back(ticket, node);
+            self.put_back_internal(ticket, node);
         }
 
         let (ticket, node) = sub_graph.root;
@@ -963,10 +1199,10 @@ impl Graph {
     /// Forgets entire sub-graph making handles to nodes invalid.
     pub fn forget_sub_graph(&mut self, sub_graph: SubGraph) {
         for (ticket, _) in sub_graph.descendants {
-            self.pool.forget_ticket(ticket);
+            self.forget_ticket(ticket);
         }
         let (ticket, _) = sub_graph.root;
-        self.pool.forget_ticket(ticket);
+        self.forget_ticket(ticket);
     }
 
     /// Returns amount of nodes in graph.s
@@ -1182,10 +1418,62 @@ impl Visit for Graph {
 #[cfg(test)]
 mod test {
     use crate::{
-        core::pool::Handle,
-        scene::{base::Base, graph::Graph, node::Node},
+        core::{
+            algebra::Vector3,
+            futures::executor::block_on,
+            pool::Handle,
+            uuid::Uuid,
+            visitor::{Visit, VisitError, VisitResult, Visitor},
+        },
+        engine::resource_manager::{MaterialSearchOptions, ResourceManager},
+        scene::{
+            base::{Base, BaseBuilder},
+            component::{register_component, Component, ComponentContainer},
+            graph::{Graph, GraphEvent},
+            node::Node,
+            Scene,
+        },
     };
 
+    #[derive(Default, Clone, Debug, PartialEq)]
+    struct Health(f32);
+
+    impl Visit for Health {
+        fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+            self.0.visit(name, visitor)
+        }
+    }
+
+    impl Component for Health {
+        const TYPE_UUID: Uuid = Uuid::from_u128(0x0e5b_31c2_77d4_4a8f_9b1e_62c0_d3f4_a517);
+    }
+
+    // A component that always fails to load.
+    #[derive(Default, Clone, Debug)]
+    struct Broken;
+
+    impl Visit for Broken {
+        fn visit(&mut self, _name: &str, visitor: &mut Visitor) -> VisitResult {
+            if visitor.is_reading() {
+                Err(VisitError::User("Broken component".to_owned()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl Component for Broken {
+        const TYPE_UUID: Uuid = Uuid::from_u128(0x5d1a_0c3e_92b4_4f67_8e21_b7a9_40c6_f318);
+    }
+
+    fn save_scene(scene: &mut Scene, name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}.rgs", name, std::process::id()));
+        let mut visitor = Visitor::new();
+        scene.visit("Scene", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+        path
+    }
+
     #[test]
     fn graph_init_test() {
         let graph = Graph::new();
@@ -1201,4 +1489,203 @@ mod test {
         graph.add_node(Node::Base(Base::default()));
         assert_eq!(graph.pool.alive_count(), 4);
     }
+
+    #[test]
+    fn graph_events_test() {
+        let mut graph = Graph::new();
+
+        // Nothing is recorded by default.
+        graph.add_node(Node::Base(Base::default()));
+        assert_eq!(graph.pop_event(), None);
+
+        graph.set_event_recording(true);
+
+        let a = graph.add_node(Node::Base(Base::default()));
+        let b = graph.add_node(Node::Base(Base::default()));
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeAdded(a)));
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeAdded(b)));
+        assert_eq!(graph.pop_event(), None);
+
+        graph.link_nodes(b, a);
+        assert_eq!(
+            graph.pop_event(),
+            Some(GraphEvent::NodeLinked {
+                child: b,
+                parent: a
+            })
+        );
+
+        // Flush initial transforms.
+        graph.update_hierarchical_data();
+        while graph.pop_event().is_some() {}
+
+        graph[a].set_name("A");
+        graph[a]
+            .local_transform_mut()
+            .set_position(Vector3::new(1.0, 0.0, 0.0));
+        graph.update_hierarchical_data();
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeRenamed(a)));
+        // Child moves with its parent.
+        assert_eq!(graph.pop_event(), Some(GraphEvent::TransformChanged(a)));
+        assert_eq!(graph.pop_event(), Some(GraphEvent::TransformChanged(b)));
+        assert_eq!(graph.pop_event(), None);
+
+        graph.update_hierarchical_data();
+        assert_eq!(graph.pop_event(), None);
+
+        graph.remove_node(a);
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeRemoved(a)));
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeRemoved(b)));
+    }
+
+    #[test]
+    fn graph_reserve_events_test() {
+        let mut graph = Graph::new();
+        let a = graph.add_node(Node::Base(Base::default()));
+        let b = graph.add_node(Node::Base(Base::default()));
+        graph.link_nodes(b, a);
+        graph.set_event_recording(true);
+
+        let (ticket, node) = graph.take_reserve(b);
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeTaken(b)));
+        assert_eq!(graph.pop_event(), None);
+
+        let root = graph.get_root();
+        assert_eq!(graph.put_back(ticket, node), b);
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodePutBack(b)));
+        assert_eq!(
+            graph.pop_event(),
+            Some(GraphEvent::NodeLinked {
+                child: b,
+                parent: root
+            })
+        );
+
+        let (ticket, _) = graph.take_reserve(b);
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeTaken(b)));
+        graph.forget_ticket(ticket);
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeRemoved(b)));
+        assert_eq!(graph.pop_event(), None);
+
+        let c = graph.add_node(Node::Base(Base::default()));
+        graph.link_nodes(c, a);
+        while graph.pop_event().is_some() {}
+
+        let sub_graph = graph.take_reserve_sub_graph(a);
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeTaken(c)));
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeTaken(a)));
+        graph.forget_sub_graph(sub_graph);
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeRemoved(c)));
+        assert_eq!(graph.pop_event(), Some(GraphEvent::NodeRemoved(a)));
+        assert_eq!(graph.pop_event(), None);
+    }
+
+    #[test]
+    fn graph_copy_components_test() {
+        let mut graph = Graph::new();
+        let child = BaseBuilder::new()
+            .with_component(Health(50.0))
+            .build(&mut graph);
+        let parent = BaseBuilder::new()
+            .with_component(Health(100.0))
+            .with_children(&[child])
+            .build(&mut graph);
+
+        let (copy, mapping) = graph.copy_node_inplace(parent, &mut |_, _| true);
+        assert_eq!(graph[copy].component::<Health>(), Some(&Health(100.0)));
+        assert_eq!(
+            graph[mapping[&child]].component::<Health>(),
+            Some(&Health(50.0))
+        );
+
+        // Copies are independent.
+        graph[copy].component_mut::<Health>().unwrap().0 = 10.0;
+        assert_eq!(graph[parent].component::<Health>(), Some(&Health(100.0)));
+
+        assert_eq!(graph[copy].remove_component::<Health>(), Some(Health(10.0)));
+        assert!(!graph[copy].has_component::<Health>());
+    }
+
+    #[test]
+    fn graph_components_visit_round_trip() {
+        register_component::<Health>();
+
+        let mut scene = Scene::new();
+        let node = BaseBuilder::new()
+            .with_component(Health(42.0))
+            .build(&mut scene.graph);
+        let path = save_scene(&mut scene, "rg3d_graph_components_visit");
+
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let mut loaded = Scene::default();
+        loaded.visit("Scene", &mut visitor).unwrap();
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(
+            loaded.graph[node].component::<Health>(),
+            Some(&Health(42.0))
+        );
+    }
+
+    #[test]
+    fn failed_component_keeps_regions_balanced() {
+        register_component::<Broken>();
+
+        let mut container = ComponentContainer::default();
+        container.add(Broken);
+        let mut after = 123u32;
+
+        let mut visitor = Visitor::new();
+        container.visit("Components", &mut visitor).unwrap();
+        after.visit("After", &mut visitor).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("rg3d_failed_component_{}.bin", std::process::id()));
+        visitor.save_binary(&path).unwrap();
+
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut container = ComponentContainer::default();
+        assert!(container.visit("Components", &mut visitor).is_err());
+        // Next field must be read from the same region.
+        let mut after = 0u32;
+        after.visit("After", &mut visitor).unwrap();
+        assert_eq!(after, 123);
+    }
+
+    #[test]
+    fn prefab_instance_keeps_components() {
+        register_component::<Health>();
+
+        let mut prefab = Scene::new();
+        BaseBuilder::new()
+            .with_name("Unit")
+            .with_component(Health(7.0))
+            .build(&mut prefab.graph);
+        let prefab_path = save_scene(&mut prefab, "rg3d_prefab_components");
+
+        let resource_manager = ResourceManager::new_headless();
+        let model = block_on(
+            resource_manager.request_model(&prefab_path, MaterialSearchOptions::UsePathDirectly),
+        )
+        .unwrap();
+
+        let mut scene = Scene::new();
+        let root = model.instantiate_geometry(&mut scene);
+        let unit = scene.graph.find_by_name(root, "Unit");
+        assert_eq!(scene.graph[unit].component::<Health>(), Some(&Health(7.0)));
+
+        // Instance must keep its components after save and load of the scene.
+        scene.graph[unit].component_mut::<Health>().unwrap().0 = 3.0;
+        let scene_path = save_scene(&mut scene, "rg3d_prefab_instance_components");
+        let loaded = block_on(Scene::from_file(
+            &scene_path,
+            resource_manager,
+            &MaterialSearchOptions::UsePathDirectly,
+        ))
+        .unwrap();
+        let _ = std::fs::remove_file(prefab_path);
+        let _ = std::fs::remove_file(scene_path);
+
+        assert_eq!(loaded.graph[unit].component::<Health>(), Some(&Health(3.0)));
+    }
 }
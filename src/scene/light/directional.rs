@@ -2,15 +2,24 @@
 //! excellent example in real life - Sun. It does not have position,
 //! only direction which defined by parent light scene node.
 //!
-//! # Notes
+//! # Shadows
 //!
-//! Current directional light does *not* support shadows, it is still
-//! on list of features that should be implemented.
+//! Directional light uses cascaded shadow maps (CSM). View frustum of a camera is split
+//! into multiple parts (cascades) along its depth, and each cascade gets its own shadow
+//! map. Cascades close to the camera cover small area, so they have high resolution,
+//! distant cascades cover large area with lower resolution. Distribution of cascades
+//! is defined by [`FrustumSplitOptions`], amount of cascades, size of shadow maps and
+//! maximum shadow distance are defined by renderer's quality settings.
+//!
+//! Each cascade is fitted into a bounding sphere of its part of the frustum and its
+//! position is snapped to shadow map texels, so shadows do not shimmer when camera
+//! moves or rotates.
 
 use crate::{
     core::{
+        algebra::{Matrix4, Point3, Vector3},
         pool::Handle,
-        visitor::{Visit, VisitResult, Visitor},
+        visitor::{Visit, VisitError, VisitResult, Visitor},
     },
     scene::{
         graph::Graph,
@@ -20,15 +29,258 @@ use crate::{
 };
 use std::ops::{Deref, DerefMut};
 
+/// Maximum amount of shadow cascades supported by renderer.
+pub const CSM_MAX_CASCADE_COUNT: usize = 4;
+
+/// Defines how view frustum of a camera is split into cascades.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FrustumSplitOptions {
+    /// Cascades have equal length along depth. Gives poor resolution of close
+    /// cascades, but distant shadows have good quality.
+    Linear,
+
+    /// Length of each next cascade grows exponentially, this matches perspective
+    /// distortion, so close cascades have best possible resolution.
+    Logarithmic,
+
+    /// Blend between logarithmic and linear distributions, `lambda` defines
+    /// weight of logarithmic distribution and should be in `[0; 1]` range.
+    Practical {
+        /// Weight of logarithmic distribution.
+        lambda: f32,
+    },
+}
+
+impl Default for FrustumSplitOptions {
+    fn default() -> Self {
+        Self::Practical { lambda: 0.75 }
+    }
+}
+
+impl Visit for FrustumSplitOptions {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut id = match self {
+            Self::Linear => 0u32,
+            Self::Logarithmic => 1,
+            Self::Practical { .. } => 2,
+        };
+        id.visit("Id", visitor)?;
+
+        let mut lambda = match self {
+            Self::Practical { lambda } => *lambda,
+            _ => 0.75,
+        };
+        lambda.visit("Lambda", visitor)?;
+
+        if visitor.is_reading() {
+            *self = match id {
+                0 => Self::Linear,
+                1 => Self::Logarithmic,
+                2 => Self::Practical { lambda },
+                _ => {
+                    return VisitResult::Err(VisitError::User(format!(
+                        "Invalid frustum split options {}!",
+                        id
+                    )))
+                }
+            };
+        }
+
+        visitor.leave_region()
+    }
+}
+
+impl FrustumSplitOptions {
+    /// Calculates distances from camera to boundaries of `cascade_count` cascades that
+    /// cover `[z_near; z_far]` range. Returned vector contains `cascade_count + 1` values,
+    /// first value is always `z_near` and last value is always `z_far`.
+    pub fn split_distances(self, z_near: f32, z_far: f32, cascade_count: usize) -> Vec<f32> {
+        // Logarithmic distribution is undefined for zero near plane.
+        let z_near = z_near.max(f32::EPSILON);
+        let z_far = z_far.max(z_near);
+        let count = cascade_count.max(1);
+        (0..=count)
+            .map(|i| {
+                if i == 0 {
+                    return z_near;
+                } else if i == count {
+                    return z_far;
+                }
+                let k = i as f32 / count as f32;
+                let linear = z_near + (z_far - z_near) * k;
+                let logarithmic = z_near * (z_far / z_near).powf(k);
+                match self {
+                    Self::Linear => linear,
+                    Self::Logarithmic => logarithmic,
+                    Self::Practical { lambda } => {
+                        let lambda = lambda.max(0.0).min(1.0);
+                        lambda * logarithmic + (1.0 - lambda) * linear
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Calculates view-projection matrix of a shadow cascade.
+///
+/// `inv_view_projection` is inverse of view-projection matrix of a camera with
+/// `[z_near; z_far]` depth range, the cascade covers part of camera's frustum between
+/// `split_near` and `split_far` distances. `light_direction` is direction of light rays.
+/// `shadow_map_size` is size of cascade's shadow map in pixels, it is used to snap the
+/// cascade to texels. `caster_distance` defines how far behind the cascade (towards the
+/// light) shadow casters will be captured.
+#[allow(clippy::too_many_arguments)]
+pub fn cascade_view_projection(
+    inv_view_projection: &Matrix4<f32>,
+    z_near: f32,
+    z_far: f32,
+    split_near: f32,
+    split_far: f32,
+    light_direction: Vector3<f32>,
+    shadow_map_size: usize,
+    caster_distance: f32,
+) -> Matrix4<f32> {
+    let light_direction = light_direction
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(|| -Vector3::y());
+
+    // Corners of the whole frustum in world space.
+    let mut near_corners = [Vector3::default(); 4];
+    let mut far_corners = [Vector3::default(); 4];
+    for (i, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .iter()
+        .enumerate()
+    {
+        near_corners[i] = inv_view_projection
+            .transform_point(&Point3::new(*x, *y, -1.0))
+            .coords;
+        far_corners[i] = inv_view_projection
+            .transform_point(&Point3::new(*x, *y, 1.0))
+            .coords;
+    }
+
+    // Corners of cascade's part of the frustum. Depth changes linearly along frustum edges.
+    let depth_range = (z_far - z_near).max(f32::EPSILON);
+    let t_near = ((split_near - z_near) / depth_range).max(0.0).min(1.0);
+    let t_far = ((split_far - z_near) / depth_range).max(0.0).min(1.0);
+    let mut corners = [Vector3::default(); 8];
+    for i in 0..4 {
+        let edge = far_corners[i] - near_corners[i];
+        corners[i] = near_corners[i] + edge.scale(t_near);
+        corners[i + 4] = near_corners[i] + edge.scale(t_far);
+    }
+
+    // Bounding sphere does not depend on camera orientation, so size of the cascade
+    // does not change when the camera rotates.
+    let center = corners.iter().sum::<Vector3<f32>>().scale(1.0 / 8.0);
+    let radius = corners
+        .iter()
+        .map(|c| (c - center).norm())
+        .fold(0.0f32, f32::max);
+    // Round radius up to reduce its fluctuations caused by floating point errors.
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    // Light view has fixed origin and orientation, only its projection moves.
+    let up = if light_direction.y.abs() > 0.99 {
+        Vector3::z()
+    } else {
+        Vector3::y()
+    };
+    let light_view = Matrix4::look_at_rh(&Point3::origin(), &Point3::from(light_direction), &up);
+
+    let light_space_center = light_view.transform_point(&Point3::from(center));
+
+    // Snap the cascade to texels, so each texel of the shadow map always covers the same
+    // area of the world. Snapping moves the cascade by less than one texel, so its extents
+    // are enlarged to keep the bounding sphere inside.
+    let size = shadow_map_size.max(3) as f32;
+    let half_extent = radius * size / (size - 2.0);
+    let texel_size = 2.0 * half_extent / size;
+    let x = (light_space_center.x / texel_size).floor() * texel_size;
+    let y = (light_space_center.y / texel_size).floor() * texel_size;
+
+    // Light looks along negative Z axis of its view space.
+    let z_near = -(light_space_center.z + radius + caster_distance.max(0.0));
+    let z_far = -(light_space_center.z - radius);
+
+    let light_projection = Matrix4::new_orthographic(
+        x - half_extent,
+        x + half_extent,
+        y - half_extent,
+        y + half_extent,
+        z_near,
+        z_far,
+    );
+
+    light_projection * light_view
+}
+
+/// Shadow settings of a directional light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CsmOptions {
+    split_options: FrustumSplitOptions,
+    shadow_bias: f32,
+}
+
+impl Default for CsmOptions {
+    fn default() -> Self {
+        Self {
+            split_options: Default::default(),
+            shadow_bias: 0.00025,
+        }
+    }
+}
+
+impl Visit for CsmOptions {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.split_options.visit("SplitOptions", visitor)?;
+        self.shadow_bias.visit("ShadowBias", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+impl CsmOptions {
+    /// Sets new frustum split options.
+    pub fn set_split_options(&mut self, options: FrustumSplitOptions) {
+        self.split_options = options;
+    }
+
+    /// Returns current frustum split options.
+    pub fn split_options(&self) -> FrustumSplitOptions {
+        self.split_options
+    }
+
+    /// Sets new shadow bias value. Bias will be used to offset fragment's depth before
+    /// compare it with shadow map value, it is used to remove "shadow acne".
+    pub fn set_shadow_bias(&mut self, bias: f32) {
+        self.shadow_bias = bias;
+    }
+
+    /// Returns current value of shadow bias.
+    pub fn shadow_bias(&self) -> f32 {
+        self.shadow_bias
+    }
+}
+
 /// See module docs.
 #[derive(Default, Debug)]
 pub struct DirectionalLight {
     base_light: BaseLight,
+    csm_options: CsmOptions,
 }
 
 impl From<BaseLight> for DirectionalLight {
     fn from(base_light: BaseLight) -> Self {
-        Self { base_light }
+        Self {
+            base_light,
+            csm_options: Default::default(),
+        }
     }
 }
 
@@ -51,6 +303,7 @@ impl Visit for DirectionalLight {
         visitor.enter_region(name)?;
 
         self.base_light.visit("BaseLight", visitor)?;
+        let _ = self.csm_options.visit("CsmOptions", visitor);
 
         visitor.leave_region()
     }
@@ -61,25 +314,47 @@ impl DirectionalLight {
     pub fn raw_copy(&self) -> Self {
         Self {
             base_light: self.base_light.raw_copy(),
+            csm_options: self.csm_options,
         }
     }
+
+    /// Returns shadow settings of the light.
+    pub fn csm_options(&self) -> &CsmOptions {
+        &self.csm_options
+    }
+
+    /// Returns shadow settings of the light.
+    pub fn csm_options_mut(&mut self) -> &mut CsmOptions {
+        &mut self.csm_options
+    }
 }
 
 /// Allows you to build directional light in declarative manner.
 pub struct DirectionalLightBuilder {
     base_light_builder: BaseLightBuilder,
+    csm_options: CsmOptions,
 }
 
 impl DirectionalLightBuilder {
     /// Creates new builder instance.
     pub fn new(base_light_builder: BaseLightBuilder) -> Self {
-        Self { base_light_builder }
+        Self {
+            base_light_builder,
+            csm_options: Default::default(),
+        }
+    }
+
+    /// Sets desired shadow settings.
+    pub fn with_csm_options(mut self, csm_options: CsmOptions) -> Self {
+        self.csm_options = csm_options;
+        self
     }
 
     /// Creates new instance of directional light.
     pub fn build_directional_light(self) -> DirectionalLight {
         DirectionalLight {
             base_light: self.base_light_builder.build(),
+            csm_options: self.csm_options,
         }
     }
 
@@ -93,3 +368,106 @@ impl DirectionalLightBuilder {
         graph.add_node(self.build_node())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Matrix4, Point3, Vector3},
+        scene::light::directional::{cascade_view_projection, FrustumSplitOptions},
+    };
+
+    #[test]
+    fn split_distances() {
+        for options in [
+            FrustumSplitOptions::Linear,
+            FrustumSplitOptions::Logarithmic,
+            FrustumSplitOptions::Practical { lambda: 0.5 },
+        ]
+        .iter()
+        {
+            let splits = options.split_distances(0.1, 100.0, 4);
+            assert_eq!(splits.len(), 5);
+            assert_eq!(splits[0], 0.1);
+            assert_eq!(splits[4], 100.0);
+            assert!(splits.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        let linear = FrustumSplitOptions::Linear.split_distances(0.0, 100.0, 2);
+        assert!((linear[1] - 50.0).abs() < 1.0e-3);
+        let logarithmic = FrustumSplitOptions::Logarithmic.split_distances(1.0, 100.0, 2);
+        assert!((logarithmic[1] - 10.0).abs() < 1.0e-3);
+    }
+
+    fn camera_inv_view_projection(position: Vector3<f32>) -> Matrix4<f32> {
+        let projection = Matrix4::new_perspective(16.0 / 9.0, 1.2, 0.1, 100.0);
+        let view = Matrix4::look_at_rh(
+            &Point3::from(position),
+            &Point3::from(position + Vector3::new(0.3, -0.2, -1.0)),
+            &Vector3::y(),
+        );
+        (projection * view).try_inverse().unwrap()
+    }
+
+    #[test]
+    fn cascade_contains_frustum_part() {
+        let light_direction = Vector3::new(-0.4, -1.0, 0.3);
+        let inv_view_projection = camera_inv_view_projection(Vector3::new(1.0, 2.0, 3.0));
+        let cascade = cascade_view_projection(
+            &inv_view_projection,
+            0.1,
+            100.0,
+            5.0,
+            20.0,
+            light_direction,
+            1024,
+            10.0,
+        );
+
+        // Corners of the frustum part between 5 and 20 units in NDC of the camera.
+        let depth = |d: f32| (100.0 + 0.1) / (100.0 - 0.1) - 2.0 * 100.0 * 0.1 / (100.0 - 0.1) / d;
+        for d in [5.0, 20.0].iter() {
+            for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter() {
+                let world = inv_view_projection.transform_point(&Point3::new(*x, *y, depth(*d)));
+                let light_space = cascade.transform_point(&world);
+                for c in light_space.coords.iter() {
+                    assert!(c.abs() <= 1.0 + 1.0e-3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cascade_snapped_to_texels() {
+        let light_direction = Vector3::new(-0.4, -1.0, 0.3);
+        let size = 512;
+        let a = cascade_view_projection(
+            &camera_inv_view_projection(Vector3::new(1.0, 2.0, 3.0)),
+            0.1,
+            100.0,
+            0.1,
+            10.0,
+            light_direction,
+            size,
+            10.0,
+        );
+        let b = cascade_view_projection(
+            &camera_inv_view_projection(Vector3::new(1.013, 2.0, 3.007)),
+            0.1,
+            100.0,
+            0.1,
+            10.0,
+            light_direction,
+            size,
+            10.0,
+        );
+
+        // The same world point must be projected with offset of whole amount of texels.
+        let point = Point3::new(0.5, 0.0, -2.0);
+        let pa = a.transform_point(&point);
+        let pb = b.transform_point(&point);
+        for i in 0..2 {
+            let texels = (pa[i] - pb[i]) * 0.5 * size as f32;
+            assert!((texels - texels.round()).abs() < 1.0e-2);
+        }
+    }
+}
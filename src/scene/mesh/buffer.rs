@@ -85,6 +85,10 @@ pub enum VertexAttributeUsage {
     BoneWeight = 11,
     /// Bone indices. Usually Vector4<u8>.
     BoneIndices = 12,
+    /// Second set of bone weights for vertices affected by up to 8 bones. Usually Vector4<f32>.
+    ExtraBoneWeight = 13,
+    /// Second set of bone indices for vertices affected by up to 8 bones. Usually Vector4<u8>.
+    ExtraBoneIndices = 14,
     /// Maximum amount of attribute kinds.
     Count,
 }
@@ -140,7 +144,7 @@ pub struct VertexAttribute {
 #[derive(Clone, Visit, Default, Debug)]
 pub struct VertexBuffer {
     dense_layout: Vec<VertexAttribute>,
-    sparse_layout: [Option<VertexAttribute>; 13],
+    sparse_layout: [Option<VertexAttribute>; VertexAttributeUsage::Count as usize],
     vertex_size: u8,
     vertex_count: u32,
     data: Vec<u8>,
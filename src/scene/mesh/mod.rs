@@ -11,7 +11,7 @@
 use crate::scene::mesh::buffer::{VertexAttributeUsage, VertexReadTrait};
 use crate::{
     core::{
-        algebra::{Matrix4, Point3, Vector3},
+        algebra::Point3,
         color::Color,
         math::{aabb::AxisAlignedBoundingBox, frustum::Frustum},
         pool::Handle,
@@ -20,16 +20,26 @@ use crate::{
     scene::{
         base::{Base, BaseBuilder},
         graph::Graph,
-        mesh::surface::Surface,
+        mesh::{
+            morph::{
+                apply_morph_targets, morph_target_weights, MorphTargetApplication,
+                MorphTargetWeight, MAX_GPU_MORPH_TARGETS,
+            },
+            skin::SkinPose,
+            surface::{Surface, SurfaceData},
+        },
         node::Node,
     },
 };
 use std::{
     cell::Cell,
     ops::{Deref, DerefMut},
+    sync::{Arc, RwLock},
 };
 
 pub mod buffer;
+pub mod morph;
+pub mod skin;
 pub mod surface;
 pub mod vertex;
 
@@ -72,6 +82,25 @@ pub struct Mesh {
     bounding_box_dirty: Cell<bool>,
     cast_shadows: bool,
     render_path: RenderPath,
+    skin_poses: Vec<SkinPose>,
+    morph_weights: Vec<MorphTargetWeight>,
+    morph_target_application: MorphTargetApplication,
+    // Per-surface copies of data deformed on CPU, see MorphTargetApplication::Cpu.
+    morphed_data: Vec<Option<Arc<RwLock<SurfaceData>>>>,
+    morph_dirty: bool,
+}
+
+// Checks whether morph targets of a surface can be applied in vertex shaders.
+fn is_gpu_morphed(
+    application: MorphTargetApplication,
+    render_path: RenderPath,
+    surface: &Surface,
+    data: &SurfaceData,
+) -> bool {
+    application == MorphTargetApplication::Gpu
+        && render_path == RenderPath::Deferred
+        && surface.material_ref().is_none()
+        && data.morph_targets.len() <= MAX_GPU_MORPH_TARGETS
 }
 
 impl Default for Mesh {
@@ -83,6 +112,11 @@ impl Default for Mesh {
             bounding_box_dirty: Cell::new(true),
             cast_shadows: true,
             render_path: RenderPath::Deferred,
+            skin_poses: Default::default(),
+            morph_weights: Default::default(),
+            morph_target_application: Default::default(),
+            morphed_data: Default::default(),
+            morph_dirty: true,
         }
     }
 }
@@ -118,6 +152,18 @@ impl Visit for Mesh {
         // recreated on resolve stage! Serialization of surfaces needed for procedural surfaces.
         self.surfaces.visit("Surfaces", visitor)?;
 
+        let _ = self.morph_weights.visit("MorphWeights", visitor);
+
+        let mut morph_target_application = self.morph_target_application as u32;
+        if morph_target_application
+            .visit("MorphTargetApplication", visitor)
+            .is_ok()
+            && visitor.is_reading()
+        {
+            self.morph_target_application =
+                MorphTargetApplication::from_id(morph_target_application)?;
+        }
+
         visitor.leave_region()
     }
 }
@@ -132,6 +178,7 @@ impl Mesh {
     /// Returns mutable reference to array of surfaces.
     #[inline]
     pub fn surfaces_mut(&mut self) -> &mut [Surface] {
+        self.morph_dirty = true;
         &mut self.surfaces
     }
 
@@ -140,6 +187,7 @@ impl Mesh {
     pub fn clear_surfaces(&mut self) {
         self.surfaces.clear();
         self.bounding_box_dirty.set(true);
+        self.morph_dirty = true;
     }
 
     /// Adds new surface into mesh, can be used to procedurally generate meshes.
@@ -147,6 +195,7 @@ impl Mesh {
     pub fn add_surface(&mut self, surface: Surface) {
         self.surfaces.push(surface);
         self.bounding_box_dirty.set(true);
+        self.morph_dirty = true;
     }
 
     /// Applies given color to all surfaces.
@@ -169,6 +218,13 @@ impl Mesh {
         self.cast_shadows = cast_shadows;
     }
 
+    /// Marks bounding box as outdated, it must be called when vertices of surfaces were changed
+    /// directly through shared surface data, otherwise the mesh can be wrongly culled.
+    #[inline]
+    pub fn invalidate_bounding_box(&self) {
+        self.bounding_box_dirty.set(true);
+    }
+
     /// Performs lazy bounding box evaluation. Bounding box presented in *local coordinates*
     /// WARNING: This method does *not* includes bounds of bones!
     pub fn bounding_box(&self) -> AxisAlignedBoundingBox {
@@ -191,6 +247,7 @@ impl Mesh {
     /// Sets new render path for the mesh.
     pub fn set_render_path(&mut self, render_path: RenderPath) {
         self.render_path = render_path;
+        self.morph_dirty = true;
     }
 
     /// Returns current render path of the mesh.
@@ -198,6 +255,128 @@ impl Mesh {
         self.render_path
     }
 
+    /// Returns skin poses of surfaces, one pose per surface in the same order as surfaces.
+    /// Poses are calculated once per frame by [Graph::update_skin_poses], non-skinned surfaces
+    /// have empty poses, and meshes without skinned surfaces have no poses at all. See
+    /// [SkinPose] docs for more info.
+    pub fn skin_poses(&self) -> &[SkinPose] {
+        &self.skin_poses
+    }
+
+    /// Returns skin pose of a surface at given index.
+    pub fn skin_pose(&self, surface_index: usize) -> Option<&SkinPose> {
+        self.skin_poses.get(surface_index)
+    }
+
+    pub(in crate) fn take_skin_poses(&mut self) -> Vec<SkinPose> {
+        std::mem::take(&mut self.skin_poses)
+    }
+
+    pub(in crate) fn set_skin_poses(&mut self, poses: Vec<SkinPose>) {
+        self.skin_poses = poses;
+    }
+
+    /// Returns true if any surface of the mesh is skinned.
+    pub fn is_skinned(&self) -> bool {
+        self.surfaces.iter().any(|s| !s.bones().is_empty())
+    }
+
+    /// Sets weight of a morph target with given name. Weight is applied to every surface that
+    /// has a morph target with such name. See [morph] module docs for more info.
+    pub fn set_morph_weight<N: AsRef<str>>(&mut self, name: N, weight: f32) {
+        let name = name.as_ref();
+        if let Some(existing) = self.morph_weights.iter_mut().find(|w| w.name == name) {
+            if existing.weight != weight {
+                existing.weight = weight;
+                self.morph_dirty = true;
+            }
+        } else {
+            self.morph_weights.push(MorphTargetWeight {
+                name: name.to_owned(),
+                weight,
+            });
+            self.morph_dirty = true;
+        }
+    }
+
+    /// Returns weight of a morph target with given name, targets without weight have zero
+    /// weight.
+    pub fn morph_weight<N: AsRef<str>>(&self, name: N) -> f32 {
+        self.morph_weights
+            .iter()
+            .find(|w| w.name == name.as_ref())
+            .map_or(0.0, |w| w.weight)
+    }
+
+    /// Returns every weight that was set for the mesh.
+    pub fn morph_weights(&self) -> &[MorphTargetWeight] {
+        &self.morph_weights
+    }
+
+    /// Sets a way of how morph targets will be applied to surfaces of the mesh.
+    pub fn set_morph_target_application(&mut self, application: MorphTargetApplication) {
+        self.morph_target_application = application;
+        self.morph_dirty = true;
+    }
+
+    /// Returns current morph target application mode.
+    pub fn morph_target_application(&self) -> MorphTargetApplication {
+        self.morph_target_application
+    }
+
+    /// Returns data of a surface at given index deformed by morph targets on CPU. It is `None`
+    /// if the surface is morphed on GPU, has no morph targets or all its weights are zero.
+    pub fn morphed_data(&self, surface_index: usize) -> Option<Arc<RwLock<SurfaceData>>> {
+        self.morphed_data.get(surface_index).cloned().flatten()
+    }
+
+    /// Returns true if morph targets of given surface of the mesh are applied on GPU.
+    pub(in crate) fn is_gpu_morphed(&self, surface: &Surface, data: &SurfaceData) -> bool {
+        !data.morph_targets.is_empty()
+            && is_gpu_morphed(
+                self.morph_target_application,
+                self.render_path,
+                surface,
+                data,
+            )
+    }
+
+    /// Recalculates surfaces that are morphed on CPU if weights have changed since last call.
+    /// It is called automatically by [Graph::update_nodes].
+    pub fn update_morph_targets(&mut self) {
+        if !self.morph_dirty {
+            return;
+        }
+        self.morph_dirty = false;
+
+        self.morphed_data
+            .resize_with(self.surfaces.len(), Default::default);
+        for (surface, morphed_data) in self.surfaces.iter().zip(self.morphed_data.iter_mut()) {
+            let data = surface.data();
+            let data = data.read().unwrap();
+
+            let morph_on_cpu = !data.morph_targets.is_empty()
+                && !is_gpu_morphed(
+                    self.morph_target_application,
+                    self.render_path,
+                    surface,
+                    &data,
+                )
+                && morph_target_weights(&self.morph_weights, &data.morph_targets).any(|w| w != 0.0);
+
+            if morph_on_cpu {
+                let morphed_data = morphed_data.get_or_insert_with(Default::default);
+                apply_morph_targets(
+                    &data,
+                    morph_target_weights(&self.morph_weights, &data.morph_targets),
+                    &mut morphed_data.write().unwrap(),
+                );
+            } else {
+                *morphed_data = None;
+            }
+        }
+    }
+
     /// Calculate bounding box in *world coordinates*. This method is very heavy and not
     /// intended to use every frame! WARNING: This method does *not* includes bounds of bones!
     pub fn world_bounding_box(&self) -> AxisAlignedBoundingBox {
@@ -240,33 +419,34 @@ impl Mesh {
                 // influence.
 
                 // Precalculate bone matrices first to speed up calculations.
-                let bone_matrices = surface
-                    .bones()
-                    .iter()
-                    .map(|&b| {
-                        let bone_node = &graph[b];
-                        bone_node.global_transform() * bone_node.inv_bind_pose_transform()
-                    })
-                    .collect::<Vec<Matrix4<f32>>>();
+                let mut pose = SkinPose::default();
+                pose.update(graph, surface.bones());
+
+                let extended = data
+                    .vertex_buffer
+                    .has_attribute(VertexAttributeUsage::ExtraBoneWeight);
 
                 for view in data.vertex_buffer.iter() {
-                    let mut position = Vector3::default();
-                    for (&bone_index, &weight) in view
-                        .read_4_u8(VertexAttributeUsage::BoneIndices)
-                        .unwrap()
-                        .iter()
-                        .zip(
-                            view.read_4_f32(VertexAttributeUsage::BoneWeight)
+                    let local_position = view.read_3_f32(VertexAttributeUsage::Position).unwrap();
+                    let mut position = pose.transform_point(
+                        local_position,
+                        view.read_4_u8(VertexAttributeUsage::BoneIndices)
+                            .unwrap()
+                            .as_slice(),
+                        view.read_4_f32(VertexAttributeUsage::BoneWeight)
+                            .unwrap()
+                            .as_slice(),
+                    );
+                    if extended {
+                        position += pose.transform_point(
+                            local_position,
+                            view.read_4_u8(VertexAttributeUsage::ExtraBoneIndices)
                                 .unwrap()
-                                .iter(),
-                        )
-                    {
-                        position += bone_matrices[bone_index as usize]
-                            .transform_point(&Point3::from(
-                                view.read_3_f32(VertexAttributeUsage::Position).unwrap(),
-                            ))
-                            .coords
-                            .scale(weight);
+                                .as_slice(),
+                            view.read_4_f32(VertexAttributeUsage::ExtraBoneWeight)
+                                .unwrap()
+                                .as_slice(),
+                        );
                     }
 
                     bounding_box.add_point(position);
@@ -305,6 +485,13 @@ impl Mesh {
             bounding_box_dirty: self.bounding_box_dirty.clone(),
             cast_shadows: self.cast_shadows,
             render_path: self.render_path,
+            // Poses will be recalculated on next update, bones have to be remapped first.
+            skin_poses: Default::default(),
+            morph_weights: self.morph_weights.clone(),
+            morph_target_application: self.morph_target_application,
+            // Morphed copies must not be shared, they will be recalculated on next update.
+            morphed_data: Default::default(),
+            morph_dirty: true,
         }
     }
 }
@@ -315,6 +502,7 @@ pub struct MeshBuilder {
     surfaces: Vec<Surface>,
     cast_shadows: bool,
     render_path: RenderPath,
+    morph_target_application: MorphTargetApplication,
 }
 
 impl MeshBuilder {
@@ -325,6 +513,7 @@ impl MeshBuilder {
             surfaces: Default::default(),
             cast_shadows: true,
             render_path: RenderPath::Deferred,
+            morph_target_application: Default::default(),
         }
     }
 
@@ -347,6 +536,15 @@ impl MeshBuilder {
         self
     }
 
+    /// Sets desired morph target application mode.
+    pub fn with_morph_target_application(
+        mut self,
+        morph_target_application: MorphTargetApplication,
+    ) -> Self {
+        self.morph_target_application = morph_target_application;
+        self
+    }
+
     /// Creates new mesh.
     pub fn build_node(self) -> Node {
         Node::Mesh(Mesh {
@@ -356,6 +554,11 @@ impl MeshBuilder {
             bounding_box: Default::default(),
             bounding_box_dirty: Cell::new(true),
             render_path: self.render_path,
+            skin_poses: Default::default(),
+            morph_weights: Default::default(),
+            morph_target_application: self.morph_target_application,
+            morphed_data: Default::default(),
+            morph_dirty: true,
         })
     }
 
@@ -364,3 +567,108 @@ impl MeshBuilder {
         graph.add_node(self.build_node())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            mesh::{
+                buffer::{VertexAttributeUsage, VertexReadTrait},
+                morph::{MorphTarget, MorphTargetApplication, MorphTargetVertex},
+                surface::{SurfaceBuilder, SurfaceData},
+                MeshBuilder, RenderPath,
+            },
+            node::Node,
+        },
+    };
+    use std::sync::{Arc, RwLock};
+
+    fn morphed_position(mesh: &crate::scene::mesh::Mesh) -> Option<Vector3<f32>> {
+        mesh.morphed_data(0).map(|data| {
+            data.read()
+                .unwrap()
+                .vertex_buffer
+                .get(0)
+                .unwrap()
+                .read_3_f32(VertexAttributeUsage::Position)
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn mesh_morph_targets_application() {
+        let mut data = SurfaceData::make_unit_xy_quad();
+        let base = data
+            .vertex_buffer
+            .get(0)
+            .unwrap()
+            .read_3_f32(VertexAttributeUsage::Position)
+            .unwrap();
+        data.morph_targets = vec![MorphTarget::new(
+            "Up",
+            vec![MorphTargetVertex {
+                index: 0,
+                position: Vector3::new(0.0, 2.0, 0.0),
+                ..Default::default()
+            }],
+        )];
+
+        let mut graph = Graph::new();
+        let handle = MeshBuilder::new(BaseBuilder::new())
+            .with_surfaces(vec![
+                SurfaceBuilder::new(Arc::new(RwLock::new(data))).build()
+            ])
+            .with_morph_target_application(MorphTargetApplication::Cpu)
+            .build(&mut graph);
+
+        let mesh = if let Node::Mesh(mesh) = &mut graph[handle] {
+            mesh
+        } else {
+            unreachable!()
+        };
+
+        // No weights - no copy.
+        mesh.update_morph_targets();
+        assert!(mesh.morphed_data(0).is_none());
+
+        mesh.set_morph_weight("Up", 0.5);
+        mesh.set_morph_weight("Unknown", 1.0);
+        assert_eq!(mesh.morph_weight("Up"), 0.5);
+        assert_eq!(mesh.morph_weight("Missing"), 0.0);
+        mesh.update_morph_targets();
+        assert_eq!(
+            morphed_position(mesh),
+            Some(base + Vector3::new(0.0, 1.0, 0.0))
+        );
+
+        // Clone must not share morphed copy, but must keep weights.
+        let mut copy = mesh.raw_copy();
+        assert!(copy.morphed_data(0).is_none());
+        copy.set_morph_weight("Up", 1.0);
+        copy.update_morph_targets();
+        assert_eq!(
+            morphed_position(&copy),
+            Some(base + Vector3::new(0.0, 2.0, 0.0))
+        );
+        assert_eq!(
+            morphed_position(mesh),
+            Some(base + Vector3::new(0.0, 1.0, 0.0))
+        );
+
+        // Deferred surfaces are morphed on GPU.
+        mesh.set_morph_target_application(MorphTargetApplication::Gpu);
+        mesh.update_morph_targets();
+        assert!(mesh.morphed_data(0).is_none());
+
+        // Forward render path falls back to CPU.
+        mesh.set_render_path(RenderPath::Forward);
+        mesh.update_morph_targets();
+        assert_eq!(
+            morphed_position(mesh),
+            Some(base + Vector3::new(0.0, 1.0, 0.0))
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Morph targets (also known as blend shapes) allow to deform a surface by a weighted sum of
+//! per-vertex offsets.
+//!
+//! # Overview
+//!
+//! Each morph target stores sparse set of offsets (deltas) of position, normal and tangent of
+//! vertices it affects. Final vertex is calculated like so:
+//!
+//! ```text
+//! position = base_position + w0 * delta0 + w1 * delta1 + ... + wN * deltaN
+//! ```
+//!
+//! Morph targets are stored in [SurfaceData](super::surface::SurfaceData), so they're shared
+//! between every instance of a surface, while weights are stored per mesh and can be changed
+//! at runtime by [Mesh::set_morph_weight](super::Mesh::set_morph_weight) or by animation
+//! tracks. Weights are matched with targets by names, so surfaces of a mesh that share a target
+//! name will be deformed together.
+//!
+//! Typical usage of morph targets is facial animation, a face is modelled in neutral pose and
+//! every expression (smile, blink, etc.) is a separate morph target.
+//!
+//! # Application
+//!
+//! Morph targets can be applied either on CPU or on GPU, see [MorphTargetApplication] for
+//! more info.
+
+use crate::{
+    core::{
+        algebra::Vector3,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::mesh::{
+        buffer::{VertexAttributeUsage, VertexReadTrait, VertexWriteTrait},
+        surface::SurfaceData,
+    },
+};
+
+/// Maximum amount of morph targets per surface that can be applied on GPU. Surfaces with more
+/// targets will be morphed on CPU.
+pub const MAX_GPU_MORPH_TARGETS: usize = 16;
+
+/// Offsets of a single vertex of a morph target.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MorphTargetVertex {
+    /// Index of a vertex in a vertex buffer of a surface.
+    pub index: u32,
+    /// Offset of position in local coordinates.
+    pub position: Vector3<f32>,
+    /// Offset of normal in local coordinates.
+    pub normal: Vector3<f32>,
+    /// Offset of tangent in local coordinates.
+    pub tangent: Vector3<f32>,
+}
+
+impl Visit for MorphTargetVertex {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.index.visit("Index", visitor)?;
+        self.position.visit("Position", visitor)?;
+        self.normal.visit("Normal", visitor)?;
+        self.tangent.visit("Tangent", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTarget {
+    /// Name of the target, weights of a mesh are matched with targets using this name.
+    pub name: String,
+    /// Sparse set of vertex offsets, vertices that are not in the set are not affected.
+    pub vertices: Vec<MorphTargetVertex>,
+}
+
+impl MorphTarget {
+    /// Creates new morph target.
+    pub fn new<N: AsRef<str>>(name: N, vertices: Vec<MorphTargetVertex>) -> Self {
+        Self {
+            name: name.as_ref().to_owned(),
+            vertices,
+        }
+    }
+}
+
+impl Visit for MorphTarget {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.name.visit("Name", visitor)?;
+        self.vertices.visit("Vertices", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Named weight of a morph target.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTargetWeight {
+    /// Name of morph target.
+    pub name: String,
+    /// Weight of morph target, usually in [0; 1] range, but other values are allowed too.
+    pub weight: f32,
+}
+
+impl Visit for MorphTargetWeight {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.name.visit("Name", visitor)?;
+        self.weight.visit("Weight", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Defines a way of how morph targets are applied to surfaces of a mesh.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+#[repr(u32)]
+pub enum MorphTargetApplication {
+    /// Each surface with morph targets gets its own copy of vertex buffer, which is recalculated
+    /// every time when weights are changed. Such copy is then uploaded to GPU, so this mode is
+    /// suitable for meshes with rarely changing weights. Vertices are available on CPU, so
+    /// they can be used for precise hit tests. Works with every render path and material.
+    Cpu = 0,
+
+    /// Offsets of every morph target are stored in a texture, and applied in vertex shaders,
+    /// only weights are sent to GPU each frame. This is the fastest mode for meshes with
+    /// animated weights. Surfaces that cannot be morphed on GPU (forward render path, surfaces
+    /// with custom materials or with more than [MAX_GPU_MORPH_TARGETS] targets) automatically
+    /// fall back to [MorphTargetApplication::Cpu] mode.
+    #[default]
+    Gpu = 1,
+}
+
+impl MorphTargetApplication {
+    /// Creates application mode from its id.
+    pub fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::Cpu),
+            1 => Ok(Self::Gpu),
+            _ => Err(format!("Invalid morph target application id {}!", id)),
+        }
+    }
+}
+
+/// Returns weights of given targets taken from given set of named weights. Targets that does not
+/// have a weight in the set have zero weight.
+pub fn morph_target_weights<'a>(
+    weights: &'a [MorphTargetWeight],
+    targets: &'a [MorphTarget],
+) -> impl Iterator<Item = f32> + 'a {
+    targets.iter().map(move |target| {
+        weights
+            .iter()
+            .find(|w| w.name == target.name)
+            .map_or(0.0, |w| w.weight)
+    })
+}
+
+/// Writes vertices of `source` deformed by its morph targets with given weights into `dest`.
+/// Weights must be in the same order as morph targets of the source. Memory of the destination
+/// buffers is reused. Normals and tangents are not re-normalized, shaders do it anyway.
+pub fn apply_morph_targets(
+    source: &SurfaceData,
+    weights: impl Iterator<Item = f32>,
+    dest: &mut SurfaceData,
+) {
+    if dest.geometry_buffer.data_hash() != source.geometry_buffer.data_hash() {
+        dest.geometry_buffer = source.geometry_buffer.clone();
+    }
+
+    dest.vertex_buffer.clone_from(&source.vertex_buffer);
+
+    let mut vertex_buffer = dest.vertex_buffer.modify();
+    for (target, weight) in source.morph_targets.iter().zip(weights) {
+        if weight == 0.0 {
+            continue;
+        }
+
+        for delta in target.vertices.iter() {
+            if let Some(mut vertex) = vertex_buffer.get_mut(delta.index as usize) {
+                if let Ok(position) = vertex.read_3_f32(VertexAttributeUsage::Position) {
+                    let _ = vertex.write_3_f32(
+                        VertexAttributeUsage::Position,
+                        position + delta.position.scale(weight),
+                    );
+                }
+                if let Ok(normal) = vertex.read_3_f32(VertexAttributeUsage::Normal) {
+                    let _ = vertex.write_3_f32(
+                        VertexAttributeUsage::Normal,
+                        normal + delta.normal.scale(weight),
+                    );
+                }
+                if let Ok(mut tangent) = vertex.read_4_f32(VertexAttributeUsage::Tangent) {
+                    tangent.x += delta.tangent.x * weight;
+                    tangent.y += delta.tangent.y * weight;
+                    tangent.z += delta.tangent.z * weight;
+                    let _ = vertex.write_4_f32(VertexAttributeUsage::Tangent, tangent);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        scene::mesh::{
+            buffer::{VertexAttributeUsage, VertexReadTrait},
+            morph::{
+                apply_morph_targets, morph_target_weights, MorphTarget, MorphTargetVertex,
+                MorphTargetWeight,
+            },
+            surface::SurfaceData,
+        },
+    };
+
+    #[test]
+    fn morph_targets_are_blended() {
+        let mut source = SurfaceData::make_unit_xy_quad();
+        let base = source
+            .vertex_buffer
+            .get(1)
+            .unwrap()
+            .read_3_f32(VertexAttributeUsage::Position)
+            .unwrap();
+
+        source.morph_targets = vec![
+            MorphTarget::new(
+                "Up",
+                vec![MorphTargetVertex {
+                    index: 1,
+                    position: Vector3::new(0.0, 2.0, 0.0),
+                    ..Default::default()
+                }],
+            ),
+            MorphTarget::new(
+                "Right",
+                vec![MorphTargetVertex {
+                    index: 1,
+                    position: Vector3::new(4.0, 0.0, 0.0),
+                    ..Default::default()
+                }],
+            ),
+        ];
+
+        let weights = [MorphTargetWeight {
+            name: "Right".to_owned(),
+            weight: 0.25,
+        }];
+        let mut dest = SurfaceData::default();
+        apply_morph_targets(
+            &source,
+            morph_target_weights(&weights, &source.morph_targets),
+            &mut dest,
+        );
+
+        let position = dest
+            .vertex_buffer
+            .get(1)
+            .unwrap()
+            .read_3_f32(VertexAttributeUsage::Position)
+            .unwrap();
+        assert_eq!(position, base + Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(dest.geometry_buffer.len(), source.geometry_buffer.len());
+        // Source must stay untouched.
+        assert_eq!(
+            source
+                .vertex_buffer
+                .get(1)
+                .unwrap()
+                .read_3_f32(VertexAttributeUsage::Position)
+                .unwrap(),
+            base
+        );
+    }
+}
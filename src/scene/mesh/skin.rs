@@ -0,0 +1,169 @@
+//! Skin pose is a palette of final bone transforms of a skinned surface.
+//!
+//! # Overview
+//!
+//! Every vertex of a skinned surface is transformed by a weighted sum of bone matrices,
+//! each bone matrix is a product of global transform of a bone and its inverse bind pose
+//! transform. Palette of such matrices is calculated once per frame for each skinned
+//! surface of a mesh when graph is updated, renderer then uses it for every render pass
+//! (g-buffer, shadows, forward pass, etc.) without touching bone nodes again.
+//!
+//! Palette is available through [Mesh::skin_poses](crate::scene::mesh::Mesh::skin_poses),
+//! so game code can read final bone transforms too, for example to do precise hit tests
+//! against skinned geometry on CPU.
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Point3, Vector3},
+        pool::Handle,
+    },
+    scene::{graph::Graph, node::Node},
+};
+
+/// See module docs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SkinPose {
+    bone_matrices: Vec<Matrix4<f32>>,
+}
+
+impl SkinPose {
+    /// Calculates new palette for given set of bones. Reuses memory of the previous palette.
+    pub fn update(&mut self, graph: &Graph, bones: &[Handle<Node>]) {
+        self.bone_matrices.clear();
+        self.bone_matrices.extend(bones.iter().map(|&bone_handle| {
+            let bone_node = &graph[bone_handle];
+            bone_node.global_transform() * bone_node.inv_bind_pose_transform()
+        }));
+    }
+
+    /// Returns final transforms of bones. Order of matrices matches order of bones of a surface,
+    /// so bone indices of vertices can be used to index this array.
+    pub fn bone_matrices(&self) -> &[Matrix4<f32>] {
+        &self.bone_matrices
+    }
+
+    /// Returns final transform of a bone at given index.
+    pub fn bone_matrix(&self, index: usize) -> Option<&Matrix4<f32>> {
+        self.bone_matrices.get(index)
+    }
+
+    /// Returns true if palette is empty, this is the case for non-skinned surfaces.
+    pub fn is_empty(&self) -> bool {
+        self.bone_matrices.is_empty()
+    }
+
+    /// Transforms a point using given bone indices and weights. Zero weights are ignored, so
+    /// unused slots can have any index.
+    pub fn transform_point(
+        &self,
+        position: Vector3<f32>,
+        bone_indices: &[u8],
+        bone_weights: &[f32],
+    ) -> Vector3<f32> {
+        let mut result = Vector3::default();
+        for (&index, &weight) in bone_indices.iter().zip(bone_weights.iter()) {
+            if weight != 0.0 {
+                if let Some(matrix) = self.bone_matrices.get(index as usize) {
+                    result += matrix
+                        .transform_point(&Point3::from(position))
+                        .coords
+                        .scale(weight);
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Matrix4, Vector2, Vector3, Vector4},
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            mesh::{
+                buffer::{VertexAttributeUsage, VertexBuffer, VertexReadTrait},
+                skin::SkinPose,
+                vertex::ExtendedAnimatedVertex,
+            },
+            transform::TransformBuilder,
+        },
+    };
+
+    #[test]
+    fn skin_pose_blends_bones() {
+        let mut graph = Graph::new();
+        let a = BaseBuilder::new()
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(1.0, 0.0, 0.0))
+                    .build(),
+            )
+            .build(&mut graph);
+        let b = BaseBuilder::new()
+            .with_inv_bind_pose_transform(Matrix4::new_translation(&Vector3::new(0.0, -2.0, 0.0)))
+            .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let mut pose = SkinPose::default();
+        pose.update(&graph, &[a, b]);
+        assert_eq!(pose.bone_matrices().len(), 2);
+
+        let p = pose.transform_point(Vector3::default(), &[0, 1, 0, 0], &[0.5, 0.5, 0.0, 0.0]);
+        assert_eq!(p, Vector3::new(0.5, -1.0, 0.0));
+    }
+
+    #[test]
+    fn skin_pose_blends_eight_bones() {
+        let mut graph = Graph::new();
+        let bones = (0..8)
+            .map(|i| {
+                BaseBuilder::new()
+                    .with_local_transform(
+                        TransformBuilder::new()
+                            .with_local_position(Vector3::new(i as f32, 0.0, 0.0))
+                            .build(),
+                    )
+                    .build(&mut graph)
+            })
+            .collect::<Vec<_>>();
+        graph.update_hierarchical_data();
+
+        let vertex = ExtendedAnimatedVertex {
+            position: Vector3::new(0.0, 1.0, 0.0),
+            tex_coord: Vector2::default(),
+            normal: Vector3::default(),
+            tangent: Vector4::default(),
+            bone_weights: [0.125; 4],
+            bone_indices: [0, 1, 2, 3],
+            extra_bone_weights: [0.125; 4],
+            extra_bone_indices: [4, 5, 6, 7],
+        };
+        let buffer = VertexBuffer::new(1, ExtendedAnimatedVertex::layout(), vec![vertex]).unwrap();
+        let view = buffer.iter().next().unwrap();
+
+        let weights = view.read_4_f32(VertexAttributeUsage::BoneWeight).unwrap();
+        let indices = view.read_4_u8(VertexAttributeUsage::BoneIndices).unwrap();
+        let extra_weights = view
+            .read_4_f32(VertexAttributeUsage::ExtraBoneWeight)
+            .unwrap();
+        let extra_indices = view
+            .read_4_u8(VertexAttributeUsage::ExtraBoneIndices)
+            .unwrap();
+        assert_eq!(extra_indices, Vector4::new(4, 5, 6, 7));
+
+        let all_indices = indices.iter().chain(extra_indices.iter()).copied();
+        let all_weights = weights.iter().chain(extra_weights.iter()).copied();
+
+        let mut pose = SkinPose::default();
+        pose.update(&graph, &bones);
+        let p = pose.transform_point(
+            view.read_3_f32(VertexAttributeUsage::Position).unwrap(),
+            &all_indices.collect::<Vec<_>>(),
+            &all_weights.collect::<Vec<_>>(),
+        );
+        // Average of offsets 0..8 is 3.5.
+        assert_eq!(p, Vector3::new(3.5, 1.0, 0.0));
+    }
+}
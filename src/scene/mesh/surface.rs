@@ -14,13 +14,14 @@ use crate::{
         pool::{ErasedHandle, Handle},
         visitor::{Visit, VisitResult, Visitor},
     },
-    resource::texture::Texture,
+    resource::{material::Material, texture::Texture},
     scene::{
         mesh::{
             buffer::{
                 VertexAttributeDescriptor, VertexAttributeUsage, VertexBuffer, VertexReadTrait,
                 VertexWriteTrait,
             },
+            morph::MorphTarget,
             vertex::StaticVertex,
         },
         node::Node,
@@ -42,6 +43,8 @@ pub struct SurfaceData {
     pub vertex_buffer: VertexBuffer,
     /// Current geometry buffer.
     pub geometry_buffer: GeometryBuffer,
+    /// Morph targets (blend shapes) of the surface, see [morph](super::morph) module docs.
+    pub morph_targets: Vec<MorphTarget>,
     // If true - indicates that surface was generated and does not have reference
     // resource. Procedural data will be serialized.
     is_procedural: bool,
@@ -52,6 +55,7 @@ impl Default for SurfaceData {
         Self {
             vertex_buffer: Default::default(),
             geometry_buffer: Default::default(),
+            morph_targets: Default::default(),
             is_procedural: false,
         }
     }
@@ -67,6 +71,7 @@ impl SurfaceData {
         Self {
             vertex_buffer,
             geometry_buffer: triangles,
+            morph_targets: Default::default(),
             is_procedural,
         }
     }
@@ -97,6 +102,15 @@ impl SurfaceData {
             )?;
         }
 
+        // Offsets are vectors, so they must be transformed without translation.
+        for target in self.morph_targets.iter_mut() {
+            for delta in target.vertices.iter_mut() {
+                delta.position = transform.transform_vector(&delta.position);
+                delta.normal = normal_matrix.transform_vector(&delta.normal);
+                delta.tangent = normal_matrix.transform_vector(&delta.tangent);
+            }
+        }
+
         Ok(())
     }
 
@@ -110,6 +124,7 @@ impl SurfaceData {
         Self {
             vertex_buffer: VertexBuffer::new(raw.vertices.len(), layout, raw.vertices).unwrap(),
             geometry_buffer: GeometryBuffer::new(raw.triangles),
+            morph_targets: Default::default(),
             is_procedural,
         }
     }
@@ -823,6 +838,7 @@ impl Visit for SurfaceData {
                 triangles.visit("Triangles", visitor)?;
                 self.geometry_buffer = GeometryBuffer::new(triangles);
             }
+            let _ = self.morph_targets.visit("MorphTargets", visitor);
         }
 
         visitor.leave_region()
@@ -852,10 +868,15 @@ impl Default for VertexWeight {
     }
 }
 
-/// Weight set contains up to four pairs of (bone; weight).
+/// Maximum amount of bones that can affect a single vertex. First four weights are stored
+/// in [AnimatedVertex](super::vertex::AnimatedVertex) layout, all eight can be stored in
+/// [ExtendedAnimatedVertex](super::vertex::ExtendedAnimatedVertex) layout.
+pub const MAX_VERTEX_WEIGHTS: usize = 8;
+
+/// Weight set contains up to eight pairs of (bone; weight).
 #[derive(Copy, Clone, Debug)]
 pub struct VertexWeightSet {
-    weights: [VertexWeight; 4],
+    weights: [VertexWeight; MAX_VERTEX_WEIGHTS],
     count: usize,
 }
 
@@ -881,6 +902,24 @@ impl VertexWeightSet {
         }
     }
 
+    /// Sorts weights by their values in descending order and keeps only first `count` weights.
+    /// Remaining weights are normalized so their sum is equal to 1.0. This method is used to fit
+    /// weight set into a vertex layout that supports less weights than the set has.
+    pub fn truncate(&mut self, count: usize) {
+        self.weights[0..self.count].sort_by(|a, b| {
+            b.value
+                .partial_cmp(&a.value)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        self.count = self.count.min(count);
+        let sum = self.iter().fold(0.0, |sum, w| sum + w.value);
+        if sum >= std::f32::EPSILON {
+            for w in self.iter_mut() {
+                w.value /= sum;
+            }
+        }
+    }
+
     /// Returns exact amount of weights in the set.
     pub fn len(&self) -> usize {
         self.count
@@ -901,9 +940,9 @@ impl VertexWeightSet {
         self.weights[0..self.count].iter_mut()
     }
 
-    /// Normalizes weights in the set so they form unit 4-d vector. This method is useful
-    /// when mesh has more than 4 weights per vertex. Engine supports only 4 weights per
-    /// vertex so when there are more than 4 weights, first four weights may not give sum
+    /// Normalizes weights in the set so they form unit vector. This method is useful
+    /// when mesh has more than 8 weights per vertex. Engine supports only 8 weights per
+    /// vertex so when there are more than 8 weights, first eight weights may not give sum
     /// equal to 1.0, we must fix that to prevent weirdly looking results.
     pub fn normalize(&mut self) {
         let len = self.iter().fold(0.0, |qs, w| qs + w.value * w.value).sqrt();
@@ -928,6 +967,7 @@ pub struct Surface {
     specular_texture: Option<Texture>,
     roughness_texture: Option<Texture>,
     height_texture: Option<Texture>,
+    material: Option<Material>,
     /// Temporal array for FBX conversion needs, it holds skinning data (weight + bone handle)
     /// and will be used to fill actual bone indices and weight in vertices that will be
     /// sent to GPU. The idea is very simple: GPU needs to know only indices of matrices of
@@ -959,6 +999,7 @@ impl Clone for Surface {
             vertex_weights: Vec::new(), // Intentionally not copied.
             color: self.color,
             lightmap_texture: self.lightmap_texture.clone(),
+            material: self.material.clone(),
         }
     }
 }
@@ -978,6 +1019,7 @@ impl Surface {
             vertex_weights: Vec::new(),
             color: Color::WHITE,
             lightmap_texture: None,
+            material: None,
         }
     }
 
@@ -1002,6 +1044,10 @@ impl Surface {
             texture.key().hash(&mut hasher);
         }
 
+        if let Some(material) = self.material.as_ref() {
+            material.key().hash(&mut hasher);
+        }
+
         hasher.finish()
     }
 
@@ -1119,6 +1165,25 @@ impl Surface {
         self.height_texture.as_ref()
     }
 
+    /// Sets new material. When a surface has a material, renderer uses shaders and properties
+    /// of the material instead of standard shaders, if the material has a suitable render pass.
+    #[inline]
+    pub fn set_material(&mut self, material: Option<Material>) {
+        self.material = material;
+    }
+
+    /// Returns current material.
+    #[inline]
+    pub fn material(&self) -> Option<Material> {
+        self.material.clone()
+    }
+
+    /// Returns current material by ref.
+    #[inline]
+    pub fn material_ref(&self) -> Option<&Material> {
+        self.material.as_ref()
+    }
+
     /// Sets color of surface. Keep in mind that alpha component is **not** compatible
     /// with deferred render path. You have to use forward render path if you need
     /// transparent surfaces.
@@ -1153,6 +1218,7 @@ impl Visit for Surface {
         self.color.visit("Color", visitor)?;
         self.bones.visit("Bones", visitor)?;
         self.lightmap_texture.visit("LightmapTexture", visitor)?;
+        let _ = self.material.visit("Material", visitor);
         // self.vertex_weights intentionally not serialized!
 
         visitor.leave_region()
@@ -1168,6 +1234,7 @@ pub struct SurfaceBuilder {
     specular_texture: Option<Texture>,
     roughness_texture: Option<Texture>,
     height_texture: Option<Texture>,
+    material: Option<Material>,
     bones: Vec<Handle<Node>>,
     color: Color,
 }
@@ -1183,6 +1250,7 @@ impl SurfaceBuilder {
             specular_texture: None,
             roughness_texture: None,
             height_texture: None,
+            material: None,
             bones: Default::default(),
             color: Color::WHITE,
         }
@@ -1224,6 +1292,12 @@ impl SurfaceBuilder {
         self
     }
 
+    /// Sets desired material.
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = Some(material);
+        self
+    }
+
     /// Sets desired color of surface.
     pub fn with_color(mut self, color: Color) -> Self {
         self.color = color;
@@ -1246,9 +1320,63 @@ impl SurfaceBuilder {
             specular_texture: self.specular_texture,
             roughness_texture: self.roughness_texture,
             height_texture: self.height_texture,
+            material: self.material,
             vertex_weights: Default::default(),
             bones: self.bones,
             color: self.color,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::pool::ErasedHandle,
+        scene::mesh::surface::{VertexWeight, VertexWeightSet, MAX_VERTEX_WEIGHTS},
+    };
+
+    fn weight(value: f32, index: u32) -> VertexWeight {
+        VertexWeight {
+            value,
+            effector: ErasedHandle::new(index, 1),
+        }
+    }
+
+    #[test]
+    fn vertex_weight_set_is_limited() {
+        let mut set = VertexWeightSet::default();
+        for i in 0..MAX_VERTEX_WEIGHTS {
+            assert!(set.push(weight(1.0, i as u32)));
+        }
+        assert!(!set.push(weight(1.0, 100)));
+        assert_eq!(set.len(), MAX_VERTEX_WEIGHTS);
+    }
+
+    #[test]
+    fn vertex_weight_set_truncate() {
+        let mut set = VertexWeightSet::default();
+        let values = [0.05, 0.3, 0.1, 0.2, 0.05, 0.15, 0.1, 0.05];
+        for (i, &value) in values.iter().enumerate() {
+            set.push(weight(value, i as u32));
+        }
+
+        set.truncate(4);
+        assert_eq!(set.len(), 4);
+
+        // Strongest weights must be kept in descending order.
+        let effectors = set.iter().map(|w| w.effector.index()).collect::<Vec<_>>();
+        assert_eq!(effectors, vec![1, 3, 5, 2]);
+
+        let sum = set.iter().map(|w| w.value).sum::<f32>();
+        assert!((sum - 1.0).abs() < 1.0e-6);
+        assert!((set.iter().next().unwrap().value - 0.3 / 0.75).abs() < 1.0e-6);
+
+        // Truncating to larger count must keep set as is.
+        let copy = set;
+        set.truncate(MAX_VERTEX_WEIGHTS);
+        assert!(set
+            .iter()
+            .zip(copy.iter())
+            .all(|(a, b)| a.value == b.value && a.effector == b.effector));
+    }
+}
@@ -215,6 +215,128 @@ impl Hash for AnimatedVertex {
     }
 }
 
+/// A vertex for animated (via skinning) mesh with up to 8 bones per vertex. It should be
+/// used only for meshes that really need more than 4 bones per vertex (faces, hands with
+/// dense rigs, etc.), because it is larger than [`AnimatedVertex`].
+#[derive(Copy, Clone, Debug, Default)]
+#[repr(C)] // OpenGL expects this structure packed as in C
+pub struct ExtendedAnimatedVertex {
+    /// Position of vertex in local coordinates.
+    pub position: Vector3<f32>,
+    /// Texture coordinates.
+    pub tex_coord: Vector2<f32>,
+    /// Normal in local coordinates.
+    pub normal: Vector3<f32>,
+    /// Tangent vector in local coordinates.
+    pub tangent: Vector4<f32>,
+    /// Array of first four bone weights. Unused bones will have 0.0 weight so they won't
+    /// impact the shape of mesh.
+    pub bone_weights: [f32; 4],
+    /// Array of first four bone indices. It has indices of bones in array of bones of a
+    /// surface.
+    pub bone_indices: [u8; 4],
+    /// Array of last four bone weights.
+    pub extra_bone_weights: [f32; 4],
+    /// Array of last four bone indices.
+    pub extra_bone_indices: [u8; 4],
+}
+
+impl ExtendedAnimatedVertex {
+    /// Returns layout of the vertex. First four weights use same locations as in
+    /// [`AnimatedVertex`], last four weights are bound to `shader_location = 13` and
+    /// `shader_location = 14`.
+    pub fn layout() -> &'static [VertexAttributeDescriptor] {
+        static LAYOUT: [VertexAttributeDescriptor; 8] = [
+            VertexAttributeDescriptor {
+                usage: VertexAttributeUsage::Position,
+                data_type: VertexAttributeDataType::F32,
+                size: 3,
+                divisor: 0,
+                shader_location: 0,
+            },
+            VertexAttributeDescriptor {
+                usage: VertexAttributeUsage::TexCoord0,
+                data_type: VertexAttributeDataType::F32,
+                size: 2,
+                divisor: 0,
+                shader_location: 1,
+            },
+            VertexAttributeDescriptor {
+                usage: VertexAttributeUsage::Normal,
+                data_type: VertexAttributeDataType::F32,
+                size: 3,
+                divisor: 0,
+                shader_location: 2,
+            },
+            VertexAttributeDescriptor {
+                usage: VertexAttributeUsage::Tangent,
+                data_type: VertexAttributeDataType::F32,
+                size: 4,
+                divisor: 0,
+                shader_location: 3,
+            },
+            VertexAttributeDescriptor {
+                usage: VertexAttributeUsage::BoneWeight,
+                data_type: VertexAttributeDataType::F32,
+                size: 4,
+                divisor: 0,
+                shader_location: 4,
+            },
+            VertexAttributeDescriptor {
+                usage: VertexAttributeUsage::BoneIndices,
+                data_type: VertexAttributeDataType::U8,
+                size: 4,
+                divisor: 0,
+                shader_location: 5,
+            },
+            VertexAttributeDescriptor {
+                usage: VertexAttributeUsage::ExtraBoneWeight,
+                data_type: VertexAttributeDataType::F32,
+                size: 4,
+                divisor: 0,
+                shader_location: 13,
+            },
+            VertexAttributeDescriptor {
+                usage: VertexAttributeUsage::ExtraBoneIndices,
+                data_type: VertexAttributeDataType::U8,
+                size: 4,
+                divisor: 0,
+                shader_location: 14,
+            },
+        ];
+        &LAYOUT
+    }
+}
+
+impl PartialEq for ExtendedAnimatedVertex {
+    fn eq(&self, other: &Self) -> bool {
+        self.position == other.position
+            && self.tex_coord == other.tex_coord
+            && self.normal == other.normal
+            && self.tangent == other.tangent
+            && self.bone_weights == other.bone_weights
+            && self.bone_indices == other.bone_indices
+            && self.extra_bone_weights == other.extra_bone_weights
+            && self.extra_bone_indices == other.extra_bone_indices
+    }
+}
+
+// This is safe because Vertex is tightly packed struct with C representation
+// there is no padding bytes which may contain garbage data. This is strictly
+// required because vertices will be directly passed on GPU.
+impl Hash for ExtendedAnimatedVertex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        #[allow(unsafe_code)]
+        unsafe {
+            let bytes = self as *const Self as *const u8;
+            state.write(std::slice::from_raw_parts(
+                bytes,
+                std::mem::size_of::<Self>(),
+            ))
+        }
+    }
+}
+
 /// Simple vertex with position.
 #[derive(Copy, Clone, Debug, Default)]
 #[repr(C)] // OpenGL expects this structure packed as in C
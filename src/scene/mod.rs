@@ -6,14 +6,19 @@
 
 pub mod base;
 pub mod camera;
+pub mod component;
 pub mod graph;
 pub mod light;
 pub mod mesh;
 pub mod node;
 pub mod particle_system;
 pub mod physics;
+pub mod post_processing;
+pub mod reflection_probe;
 pub mod sprite;
+pub mod streaming;
 pub mod terrain;
+pub mod timestep;
 pub mod transform;
 
 use crate::engine::resource_manager::MaterialSearchOptions;
@@ -28,7 +33,7 @@ use crate::{
         visitor::{Visit, VisitError, VisitResult, Visitor},
     },
     engine::{resource_manager::ResourceManager, PhysicsBinder},
-    resource::texture::Texture,
+    resource::{material::Material, texture::Texture, ResourceState},
     scene::{
         base::PhysicsBinding,
         graph::Graph,
@@ -39,8 +44,13 @@ use crate::{
         },
         node::Node,
         physics::{Physics, PhysicsPerformanceStatistics},
+        timestep::FixedTimestep,
+    },
+    sound::{
+        buffer::{DataSource, SoundBuffer},
+        context::SoundContext,
+        engine::SoundEngine,
     },
-    sound::{context::SoundContext, engine::SoundEngine},
     utils::{lightmap::Lightmap, log::Log, log::MessageKind, navmesh::Navmesh},
 };
 use std::{
@@ -919,6 +929,17 @@ pub struct Scene {
     /// to false for menu's scene and when you need to open a menu - set it to true and
     /// set `enabled` flag to false for level's scene.
     pub enabled: bool,
+
+    /// Fixed timestep settings. If set, the scene is advanced by ticks of constant duration
+    /// regardless of delta time passed to [`Scene::update`], which makes simulation reproducible.
+    /// Default is None - scene is stepped with given delta time. See [`timestep`] module docs
+    /// for more info.
+    ///
+    /// # Notes
+    ///
+    /// Physics is stepped with the duration of a tick, so `dt` of physics integration parameters
+    /// is overwritten on each update while fixed timestep is set.
+    pub fixed_timestep: Option<FixedTimestep>,
 }
 
 impl Default for Scene {
@@ -936,6 +957,7 @@ impl Default for Scene {
             performance_statistics: Default::default(),
             ambient_lighting_color: Color::opaque(100, 100, 100),
             enabled: true,
+            fixed_timestep: None,
         }
     }
 }
@@ -949,6 +971,23 @@ fn map_texture(tex: Option<Texture>, rm: ResourceManager) -> Option<Texture> {
     }
 }
 
+fn map_material(material: Option<Material>, rm: ResourceManager) -> Option<Material> {
+    if let Some(shallow_material) = material {
+        let path = shallow_material.state().path().to_path_buf();
+        if path.as_os_str().is_empty() {
+            // Material is embedded in the scene, only its textures must be restored.
+            if let ResourceState::Ok(data) = &mut *shallow_material.state() {
+                data.restore_textures(&rm);
+            }
+            Some(shallow_material)
+        } else {
+            Some(rm.request_material(path))
+        }
+    } else {
+        None
+    }
+}
+
 /// A structure that holds times that specific update step took.
 #[derive(Clone, Default, Debug)]
 pub struct PerformanceStatistics {
@@ -1001,6 +1040,7 @@ impl Scene {
             performance_statistics: Default::default(),
             ambient_lighting_color: Color::opaque(100, 100, 100),
             enabled: true,
+            fixed_timestep: None,
         }
     }
 
@@ -1023,9 +1063,23 @@ impl Scene {
             scene.visit("Scene", &mut visitor)?;
         }
 
+        scene
+            .restore_resources(resource_manager, material_search_options)
+            .await;
+
+        Ok(scene)
+    }
+
+    /// Restores real resources of the scene after load (scene stores only paths to resources)
+    /// and resolves the scene.
+    pub(in crate) async fn restore_resources(
+        &mut self,
+        resource_manager: ResourceManager,
+        material_search_options: &MaterialSearchOptions,
+    ) {
         // Collect all used resources and wait for them.
         let mut resources = Vec::new();
-        for node in scene.graph.linear_iter_mut() {
+        for node in self.graph.linear_iter_mut() {
             if let Some(shallow_resource) = node.resource.clone() {
                 let search_options =
                     if material_search_options == &MaterialSearchOptions::UsePathDirectly {
@@ -1049,7 +1103,7 @@ impl Scene {
         // Restore pointers to resources. Scene saves only paths to resources, here we must
         // find real resources instead.
 
-        for node in scene.graph.linear_iter_mut() {
+        for node in self.graph.linear_iter_mut() {
             match node {
                 Node::Mesh(mesh) => {
                     for surface in mesh.surfaces_mut() {
@@ -1073,6 +1127,11 @@ impl Scene {
                             resource_manager.clone(),
                         ));
 
+                        surface.set_material(map_material(
+                            surface.material(),
+                            resource_manager.clone(),
+                        ));
+
                         // Do not resolve lightmap texture here, it makes no sense anyway,
                         // it will be resolved below.
                     }
@@ -1101,6 +1160,19 @@ impl Scene {
                         skybox.front = map_texture(skybox.front.clone(), resource_manager.clone());
                         skybox.back = map_texture(skybox.back.clone(), resource_manager.clone());
                     }
+
+                    // Procedural look-up tables have no path, their content is serialized.
+                    let post_processing = camera.post_processing_mut();
+                    if post_processing
+                        .color_grading_lut
+                        .as_ref()
+                        .map_or(false, |lut| !lut.state().path().as_os_str().is_empty())
+                    {
+                        post_processing.color_grading_lut = map_texture(
+                            post_processing.color_grading_lut.clone(),
+                            resource_manager.clone(),
+                        );
+                    }
                 }
                 Node::Terrain(terrain) => {
                     for chunk in terrain.chunks_mut() {
@@ -1134,7 +1206,7 @@ impl Scene {
             }
         }
 
-        if let Some(lightmap) = scene.lightmap.as_mut() {
+        if let Some(lightmap) = self.lightmap.as_mut() {
             for entries in lightmap.map.values_mut() {
                 for entry in entries.iter_mut() {
                     entry.texture = map_texture(entry.texture.clone(), resource_manager.clone());
@@ -1142,10 +1214,12 @@ impl Scene {
             }
         }
 
+        self.restore_sound_buffers().await;
+
         // We have to wait until skybox textures are all loaded, because we need to read their data
         // to re-create cube map.
         let mut skybox_textures = Vec::new();
-        for node in scene.graph.linear_iter() {
+        for node in self.graph.linear_iter() {
             if let Node::Camera(camera) = node {
                 if let Some(skybox) = camera.skybox_ref() {
                     skybox_textures.extend(skybox.textures().iter().filter_map(|t| t.clone()));
@@ -1155,13 +1229,65 @@ impl Scene {
         crate::core::futures::future::join_all(skybox_textures).await;
 
         // And do resolve to extract correct graphical data and so on.
-        scene.resolve();
+        self.resolve();
+    }
 
-        Ok(scene)
+    // Sound buffers store only paths to their data, so the data must be loaded again.
+    async fn restore_sound_buffers(&mut self) {
+        let mut buffers: Vec<Arc<Mutex<SoundBuffer>>> = Vec::new();
+        for source in self.sound_context.state().sources().iter() {
+            if let Some(buffer) = source.buffer() {
+                if !buffers.iter().any(|b| Arc::ptr_eq(b, &buffer)) {
+                    buffers.push(buffer);
+                }
+            }
+        }
+
+        for buffer in buffers {
+            let (path, stream) = {
+                let buffer = buffer.lock().unwrap();
+                if !buffer.is_empty() {
+                    continue;
+                }
+                (
+                    buffer.external_data_path().map(|p| p.to_owned()),
+                    matches!(*buffer, SoundBuffer::Streaming(_)),
+                )
+            };
+
+            if let Some(path) = path {
+                let new_buffer = match DataSource::from_file(&path).await {
+                    Ok(data_source) if stream => SoundBuffer::raw_streaming(data_source).ok(),
+                    Ok(data_source) => SoundBuffer::raw_generic(data_source).ok(),
+                    Err(_) => None,
+                };
+
+                if let Some(new_buffer) = new_buffer {
+                    *buffer.lock().unwrap() = new_buffer;
+                } else {
+                    Log::writeln(
+                        MessageKind::Error,
+                        format!("Unable to restore sound buffer {:?}!", path),
+                    );
+                }
+            }
+        }
+
+        // Decoders of streaming buffers must be moved to playback positions of their sources.
+        for source in self.sound_context.state().sources_mut().iter_mut() {
+            let is_streaming = match source.buffer() {
+                Some(buffer) => matches!(*buffer.lock().unwrap(), SoundBuffer::Streaming(_)),
+                None => false,
+            };
+            if is_streaming {
+                let time = source.playback_time();
+                source.set_playback_time(time);
+            }
+        }
     }
 
     fn update_physics(&mut self) {
-        self.physics.step();
+        self.physics.step(&self.physics_binder);
 
         self.performance_statistics.physics = self.physics.performance_statistics.clone();
         self.physics.performance_statistics.reset();
@@ -1233,6 +1359,9 @@ impl Scene {
         self.animations.resolve(&self.graph);
 
         self.graph.update_hierarchical_data();
+        // There is no previous state right after load, so interpolation must not produce
+        // any movement.
+        self.graph.save_previous_transforms();
         self.physics.resolve(&self.physics_binder, &self.graph);
 
         // Re-apply lightmap if any. This has to be done after resolve because we must patch surface
@@ -1339,7 +1468,48 @@ impl Scene {
     /// Performs single update tick with given delta time from last frame. Internally
     /// it updates physics, animations, and each graph node. In most cases there is
     /// no need to call it directly, engine automatically updates all available scenes.
+    ///
+    /// If [`Scene::fixed_timestep`] is set, given delta time is accumulated and the scene
+    /// is advanced by zero or more ticks of fixed duration.
     pub fn update(&mut self, frame_size: Vector2<f32>, dt: f32) {
+        if let Some(fixed_timestep) = self.fixed_timestep.as_mut() {
+            let steps = fixed_timestep.accumulate(dt);
+            let step_duration = fixed_timestep.step_duration();
+            let interpolation_factor = fixed_timestep.interpolation_factor();
+
+            // Physics must be stepped with exactly the same duration as everything else.
+            if self.physics.integration_parameters.dt != step_duration {
+                Log::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Physics time step {} is replaced by fixed timestep duration {}!",
+                        self.physics.integration_parameters.dt, step_duration
+                    ),
+                );
+                self.physics.integration_parameters.dt = step_duration;
+            }
+
+            for _ in 0..steps {
+                self.tick(frame_size, step_duration);
+            }
+
+            self.graph.set_interpolation_factor(interpolation_factor);
+        } else {
+            self.tick(frame_size, dt);
+
+            self.graph.set_interpolation_factor(1.0);
+        }
+
+        self.performance_statistics.sound_update_time = self
+            .sound_context
+            .state()
+            .full_render_duration()
+            .as_secs_f32();
+    }
+
+    fn tick(&mut self, frame_size: Vector2<f32>, dt: f32) {
+        self.graph.save_previous_transforms();
+
         self.update_physics();
 
         let last = instant::Instant::now();
@@ -1351,12 +1521,6 @@ impl Scene {
         self.graph.update_nodes(frame_size, dt);
         self.performance_statistics.graph_update_time =
             (instant::Instant::now() - last).as_secs_f32();
-
-        self.performance_statistics.sound_update_time = self
-            .sound_context
-            .state()
-            .full_render_duration()
-            .as_secs_f32();
     }
 
     /// Creates deep copy of a scene, filter predicate allows you to filter out nodes
@@ -1402,6 +1566,7 @@ impl Scene {
                 performance_statistics: Default::default(),
                 ambient_lighting_color: self.ambient_lighting_color,
                 enabled: self.enabled,
+                fixed_timestep: self.fixed_timestep.clone(),
             },
             old_new_map,
         )
@@ -1421,6 +1586,7 @@ impl Visit for Scene {
         self.ambient_lighting_color
             .visit("AmbientLightingColor", visitor)?;
         self.enabled.visit("Enabled", visitor)?;
+        let _ = self.fixed_timestep.visit("FixedTimestep", visitor);
         visitor.leave_region()
     }
 }
@@ -1499,6 +1665,72 @@ impl SceneContainer {
     pub fn forget_ticket(&mut self, ticket: Ticket<Scene>) {
         self.pool.forget_ticket(ticket)
     }
+
+    /// Saves complete state of every scene in the container into a file. Unlike editor-oriented
+    /// saving of a single scene, "save game" also captures runtime state: playing sounds with
+    /// their playback positions, time positions and pending events of animations, velocities
+    /// of rigid bodies, lifetimes of nodes, fixed timestep accumulators and so on. Use
+    /// [`Self::load_game`] to restore the state.
+    ///
+    /// # Notes
+    ///
+    /// Animation state machines are not owned by scenes, so if you use them, you have to save
+    /// them separately - [Machine](crate::animation::machine::Machine) implements `Visit` and
+    /// stores its active state and progress of active transition.
+    pub fn save_game<P: AsRef<Path>>(&mut self, path: P) -> VisitResult {
+        let mut visitor = Visitor::new();
+        self.visit("SceneContainer", &mut visitor)?;
+        visitor.save_binary(path)
+    }
+
+    /// Loads complete state of scenes that was previously saved by [`Self::save_game`].
+    /// Every existing scene in the container will be replaced by loaded ones. If loading fails,
+    /// the container is left untouched. Handles to scenes, nodes, rigid bodies, etc. remain the
+    /// same as they were at the moment of saving.
+    pub async fn load_game<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        resource_manager: ResourceManager,
+    ) -> VisitResult {
+        let mut visitor = Visitor::load_binary(path).await?;
+
+        // Sound engine is shared with the temporary container and will be overwritten, remember
+        // its state to be able to bring it back on failure.
+        let (contexts, master_gain) = {
+            let sound_engine = self.sound_engine.lock().unwrap();
+            (sound_engine.contexts().to_vec(), sound_engine.master_gain())
+        };
+
+        // Load into a temporary container first, so a failed load won't leave this container
+        // half-empty.
+        let mut loaded = SceneContainer::new(self.sound_engine.clone());
+        if let Err(error) = loaded.visit("SceneContainer", &mut visitor) {
+            let mut sound_engine = self.sound_engine.lock().unwrap();
+            for context in sound_engine.contexts().to_vec() {
+                sound_engine.remove_context(context);
+            }
+            for context in contexts {
+                sound_engine.add_context(context);
+            }
+            sound_engine.set_master_gain(master_gain);
+            return Err(error);
+        }
+
+        // Sound engine now has contexts of loaded scenes only, so previous scenes can be
+        // dropped directly.
+        std::mem::swap(&mut self.pool, &mut loaded.pool);
+
+        for scene in self.pool.iter_mut() {
+            scene
+                .restore_resources(
+                    resource_manager.clone(),
+                    &MaterialSearchOptions::UsePathDirectly,
+                )
+                .await;
+        }
+
+        Ok(())
+    }
 }
 
 impl Index<Handle<Scene>> for SceneContainer {
@@ -1733,3 +1965,374 @@ where
         visitor.leave_region()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        animation::{
+            machine::{Machine, Parameter, PoseNode, State, Transition},
+            Animation, KeyFrame, Track,
+        },
+        core::{
+            algebra::{Point3, UnitQuaternion, Vector2, Vector3},
+            futures::executor::block_on,
+            math::Matrix4Ext,
+            visitor::{Visit, Visitor},
+        },
+        engine::resource_manager::ResourceManager,
+        physics::{dynamics::RigidBodyBuilder, geometry::ColliderBuilder},
+        scene::{
+            base::BaseBuilder, camera::CameraBuilder, timestep::FixedTimestep,
+            transform::TransformBuilder, Scene, SceneContainer,
+        },
+        sound::{
+            buffer::{DataSource, SoundBuffer},
+            engine::SoundEngine,
+            source::{generic::GenericSourceBuilder, Status},
+        },
+    };
+    use std::path::{Path, PathBuf};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}_{}", std::process::id(), name))
+    }
+
+    // Writes two seconds of 16-bit mono PCM sound.
+    fn write_wav(path: &Path) {
+        let sample_rate = 8000u32;
+        let samples = (0..2 * sample_rate)
+            .map(|i| ((i as f32 * 0.1).sin() * 1000.0) as i16)
+            .collect::<Vec<_>>();
+        let data_size = samples.len() as u32 * 2;
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    fn make_scene() -> Scene {
+        let mut scene = Scene::new();
+
+        scene.fixed_timestep = Some(FixedTimestep::new(60.0, 4));
+
+        let falling = BaseBuilder::new()
+            .with_name("Falling")
+            .build(&mut scene.graph);
+        let body = scene.physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .linvel(Vector3::new(1.0, 2.0, 0.0))
+                .build(),
+        );
+        scene
+            .physics
+            .add_collider(ColliderBuilder::ball(0.5).build(), &body);
+        scene.physics_binder.bind(falling, body);
+
+        let animated = BaseBuilder::new()
+            .with_name("Animated")
+            .build(&mut scene.graph);
+        let mut track = Track::new();
+        track.set_node(animated);
+        track.add_key_frame(KeyFrame::new(
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            UnitQuaternion::identity(),
+        ));
+        track.add_key_frame(KeyFrame::new(
+            2.0,
+            Vector3::new(4.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            UnitQuaternion::identity(),
+        ));
+        let mut animation = Animation::default();
+        animation.add_track(track);
+        scene.animations.add(animation);
+
+        BaseBuilder::new()
+            .with_name("Temporary")
+            .with_lifetime(0.5)
+            .build(&mut scene.graph);
+
+        scene
+    }
+
+    fn step(scene: &mut Scene, frames: usize) {
+        for _ in 0..frames {
+            scene.update(Vector2::new(800.0, 600.0), 1.0 / 45.0);
+        }
+    }
+
+    fn assert_same_state(a: &Scene, b: &Scene) {
+        assert_eq!(a.graph.node_count(), b.graph.node_count());
+
+        for (handle, node) in a.graph.pair_iter() {
+            let other = &b.graph[handle];
+            assert_eq!(node.name(), other.name());
+            assert_eq!(node.lifetime(), other.lifetime());
+            assert!((node.global_position() - other.global_position()).norm() < 1.0e-5);
+        }
+
+        for (handle, animation) in a.animations.pair_iter() {
+            let other = b.animations.get(handle);
+            assert_eq!(animation.get_time_position(), other.get_time_position());
+            for (track, other_track) in animation.get_tracks().iter().zip(other.get_tracks()) {
+                assert_eq!(
+                    track.get_key_frames().len(),
+                    other_track.get_key_frames().len()
+                );
+            }
+        }
+
+        for (node, body) in a.physics_binder.forward_map().iter() {
+            assert_eq!(b.physics_binder.body_of(*node), Some(body));
+            let other = b.physics.bodies.get(body).unwrap();
+            let body = a.physics.bodies.get(body).unwrap();
+            assert!((body.linvel() - other.linvel()).norm() < 1.0e-5);
+            assert!((body.angvel() - other.angvel()).norm() < 1.0e-5);
+        }
+
+        let sounds = a.sound_context.state();
+        let other_sounds = b.sound_context.state();
+        assert_eq!(
+            sounds.sources().alive_count(),
+            other_sounds.sources().alive_count()
+        );
+        for (handle, source) in sounds.sources().pair_iter() {
+            let other = other_sounds.source(handle);
+            assert_eq!(source.status(), other.status());
+            assert_eq!(source.gain(), other.gain());
+            assert_eq!(source.playback_time(), other.playback_time());
+            let samples = other.buffer().unwrap().lock().unwrap().samples().len();
+            assert_eq!(
+                source.buffer().unwrap().lock().unwrap().samples().len(),
+                samples
+            );
+        }
+
+        assert_eq!(a.fixed_timestep, b.fixed_timestep);
+    }
+
+    #[test]
+    fn save_game_round_trip() {
+        let sound_path = temp_path("rg3d_save_game_sound.wav");
+        write_wav(&sound_path);
+        let path = temp_path("rg3d_save_game.bin");
+
+        let mut container = SceneContainer::new(SoundEngine::without_device());
+        let scene = make_scene();
+        let buffer =
+            SoundBuffer::new_generic(block_on(DataSource::from_file(&sound_path)).unwrap())
+                .ok()
+                .unwrap();
+        let source = GenericSourceBuilder::new()
+            .with_buffer(buffer)
+            .with_status(Status::Playing)
+            .with_gain(0.3)
+            .build_source()
+            .unwrap();
+        let source = scene.sound_context.state().add_source(source);
+        scene
+            .sound_context
+            .state()
+            .source_mut(source)
+            .set_playback_time(std::time::Duration::from_secs(1));
+        let handle = container.add(scene);
+        step(&mut container[handle], 10);
+
+        container.save_game(&path).unwrap();
+
+        let mut loaded = SceneContainer::new(SoundEngine::without_device());
+        // Existing scenes must be replaced.
+        loaded.add(Scene::new());
+        block_on(loaded.load_game(&path, ResourceManager::new_headless())).unwrap();
+
+        assert_eq!(loaded.iter().count(), 1);
+        assert_eq!(loaded.sound_engine.lock().unwrap().contexts().len(), 1);
+        assert_same_state(&container[handle], &loaded[handle]);
+
+        // Lifetime of temporary node will expire after save, both scenes must behave equally.
+        step(&mut container[handle], 30);
+        step(&mut loaded[handle], 30);
+
+        assert_same_state(&container[handle], &loaded[handle]);
+
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(sound_path);
+    }
+
+    #[test]
+    fn failed_load_game_keeps_scenes() {
+        let path = temp_path("rg3d_broken_save_game.bin");
+
+        // A valid file that has no scene container inside.
+        let mut visitor = Visitor::new();
+        let mut value = 1u32;
+        value.visit("Value", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+
+        let sound_engine = SoundEngine::without_device();
+        sound_engine.lock().unwrap().set_master_gain(0.5);
+        let mut container = SceneContainer::new(sound_engine.clone());
+        let handle = container.add(make_scene());
+
+        assert!(block_on(container.load_game(&path, ResourceManager::new_headless())).is_err());
+
+        assert_eq!(container.iter().count(), 1);
+        assert_eq!(container[handle].graph.node_count(), 4);
+        let sound_engine = sound_engine.lock().unwrap();
+        assert!(sound_engine.has_context(&container[handle].sound_context));
+        assert_eq!(sound_engine.master_gain(), 0.5);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn machine_state_round_trip() {
+        let scene = make_scene();
+        let animation = scene.animations.pair_iter().next().unwrap().0;
+
+        let mut machine = Machine::new();
+        let a = machine.add_node(PoseNode::make_play_animation(animation));
+        let a = machine.add_state(State::new("A", a));
+        let b = machine.add_node(PoseNode::make_play_animation(animation));
+        let b = machine.add_state(State::new("B", b));
+        machine.add_transition(Transition::new("AB", a, b, 1.0, "Go"));
+        machine.set_entry_state(a);
+        machine.set_parameter("Go", Parameter::Rule(true));
+
+        // Stop in the middle of transition.
+        for _ in 0..3 {
+            machine.evaluate_pose(&scene.animations, 0.25);
+        }
+        assert!(machine.active_transition().is_some());
+
+        let path = temp_path("rg3d_machine_state.bin");
+        let mut visitor = Visitor::new();
+        machine.visit("Machine", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+
+        let mut loaded = Machine::new();
+        let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+        loaded.visit("Machine", &mut visitor).unwrap();
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(loaded.active_state(), machine.active_state());
+        assert_eq!(loaded.active_transition(), machine.active_transition());
+
+        // Both machines must finish the transition at the same time.
+        for _ in 0..2 {
+            machine.evaluate_pose(&scene.animations, 0.25);
+            loaded.evaluate_pose(&scene.animations, 0.25);
+            assert_eq!(loaded.active_state(), machine.active_state());
+            assert_eq!(loaded.active_transition(), machine.active_transition());
+        }
+        assert_eq!(loaded.active_state(), b);
+    }
+
+    #[test]
+    fn fixed_timestep_interpolates_cameras() {
+        let mut scene = Scene::new();
+        scene.fixed_timestep = Some(FixedTimestep::new(10.0, 4));
+        scene.physics.gravity = Vector3::default();
+
+        let camera = CameraBuilder::new(BaseBuilder::new()).build(&mut scene.graph);
+        let body = scene.physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .linvel(Vector3::new(10.0, 0.0, 0.0))
+                .build(),
+        );
+        scene
+            .physics
+            .add_collider(ColliderBuilder::ball(0.5).build(), &body);
+        scene.physics_binder.bind(camera, body);
+
+        // Two ticks and a half.
+        scene.update(Vector2::new(800.0, 600.0), 0.25);
+        assert!((scene.graph.interpolation_factor() - 0.5).abs() < 1.0e-3);
+
+        let previous = scene.graph[camera].previous_global_transform().position();
+        let current = scene.graph[camera].global_position();
+        assert!((current - previous).norm() > 0.5);
+        let expected = previous.lerp(&current, scene.graph.interpolation_factor());
+
+        scene.graph.begin_interpolation();
+        assert!((scene.graph[camera].global_position() - expected).norm() < 1.0e-4);
+        // View matrix must follow interpolated position.
+        let eye = scene.graph[camera]
+            .as_camera()
+            .view_matrix()
+            .transform_point(&Point3::from(expected));
+        assert!(eye.coords.norm() < 1.0e-4);
+
+        scene.graph.end_interpolation();
+        assert_eq!(scene.graph[camera].global_position(), current);
+        let eye = scene.graph[camera]
+            .as_camera()
+            .view_matrix()
+            .transform_point(&Point3::from(current));
+        assert!(eye.coords.norm() < 1.0e-4);
+    }
+
+    #[test]
+    fn nodes_spawned_between_ticks_are_not_interpolated() {
+        let mut scene = Scene::new();
+        scene.fixed_timestep = Some(FixedTimestep::new(10.0, 4));
+        scene.physics.gravity = Vector3::default();
+
+        let mover = BaseBuilder::new().build(&mut scene.graph);
+        let body = scene.physics.add_body(
+            RigidBodyBuilder::new_dynamic()
+                .linvel(Vector3::new(10.0, 0.0, 0.0))
+                .build(),
+        );
+        scene
+            .physics
+            .add_collider(ColliderBuilder::ball(0.5).build(), &body);
+        scene.physics_binder.bind(mover, body);
+
+        scene.update(Vector2::new(800.0, 600.0), 0.25);
+
+        // Spawn nodes in the middle of a frame, one of them is a copy of the moving node which
+        // has different previous transform.
+        let spawned = BaseBuilder::new()
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(5.0, 1.0, 0.0))
+                    .build(),
+            )
+            .build(&mut scene.graph);
+        let (copy, _) = scene.graph.copy_node_inplace(mover, &mut |_, _| true);
+
+        scene.update(Vector2::new(800.0, 600.0), 0.1);
+        assert!((scene.graph.interpolation_factor() - 0.5).abs() < 1.0e-3);
+
+        let actual_copy_position = scene.graph[copy].global_position();
+        scene.graph.begin_interpolation();
+        assert_eq!(
+            scene.graph[spawned].global_position(),
+            Vector3::new(5.0, 1.0, 0.0)
+        );
+        assert_eq!(scene.graph[copy].global_position(), actual_copy_position);
+        // Nodes that existed before must still be interpolated.
+        assert_ne!(
+            scene.graph[mover].global_position(),
+            scene.graph[mover].previous_global_transform().position()
+        );
+        scene.graph.end_interpolation();
+    }
+}
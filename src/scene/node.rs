@@ -8,7 +8,7 @@ use crate::{
     core::visitor::{Visit, VisitResult, Visitor},
     scene::{
         base::Base, camera::Camera, light::Light, mesh::Mesh, particle_system::ParticleSystem,
-        sprite::Sprite,
+        reflection_probe::ReflectionProbe, sprite::Sprite,
     },
 };
 use std::ops::{Deref, DerefMut};
@@ -25,6 +25,7 @@ macro_rules! static_dispatch {
             Node::ParticleSystem(v) => v.$func($($args),*),
             Node::Sprite(v) => v.$func($($args),*),
             Node::Terrain(v) => v.$func($($args),*),
+            Node::ReflectionProbe(v) => v.$func($($args),*),
         }
     };
 }
@@ -58,6 +59,8 @@ pub enum Node {
     ParticleSystem(ParticleSystem),
     /// See Terrain node docs.
     Terrain(Terrain),
+    /// See ReflectionProbe node docs.
+    ReflectionProbe(ReflectionProbe),
 }
 
 macro_rules! static_dispatch_deref {
@@ -70,6 +73,7 @@ macro_rules! static_dispatch_deref {
             Node::ParticleSystem(v) => v,
             Node::Sprite(v) => v,
             Node::Terrain(v) => v,
+            Node::ReflectionProbe(v) => v,
         }
     };
 }
@@ -105,6 +109,7 @@ impl Node {
             4 => Ok(Self::Sprite(Default::default())),
             5 => Ok(Self::ParticleSystem(Default::default())),
             6 => Ok(Self::Terrain(Default::default())),
+            7 => Ok(Self::ReflectionProbe(Default::default())),
             _ => Err(format!("Invalid node kind {}", id)),
         }
     }
@@ -119,6 +124,7 @@ impl Node {
             Self::Sprite(_) => 4,
             Self::ParticleSystem(_) => 5,
             Self::Terrain(_) => 6,
+            Self::ReflectionProbe(_) => 7,
         }
     }
 
@@ -134,6 +140,7 @@ impl Node {
             Node::Sprite(v) => Node::Sprite(v.raw_copy()),
             Node::ParticleSystem(v) => Node::ParticleSystem(v.raw_copy()),
             Node::Terrain(v) => Node::Terrain(v.raw_copy()),
+            Node::ReflectionProbe(v) => Node::ReflectionProbe(v.raw_copy()),
         }
     }
 
@@ -143,4 +150,5 @@ impl Node {
     define_is_as!(Node : ParticleSystem -> ref ParticleSystem => fn is_particle_system, fn as_particle_system, fn as_particle_system_mut);
     define_is_as!(Node : Sprite -> ref Sprite => fn is_sprite, fn as_sprite, fn as_sprite_mut);
     define_is_as!(Node : Terrain -> ref Terrain => fn is_terrain, fn as_terrain, fn as_terrain_mut);
+    define_is_as!(Node : ReflectionProbe -> ref ReflectionProbe => fn is_reflection_probe, fn as_reflection_probe, fn as_reflection_probe_mut);
 }
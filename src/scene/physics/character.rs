@@ -0,0 +1,793 @@
+//! Kinematic character controller.
+//!
+//! # Overview
+//!
+//! Character controller moves a capsule through physics world using shape casts instead of
+//! forces, so it never bounces off walls, never tips over and always does exactly what it was
+//! told to do (as far as obstacles allow). It supports:
+//!
+//! - Collide-and-slide movement - when the capsule hits an obstacle, remaining movement is
+//!   projected on the surface of the obstacle.
+//! - Step climbing - obstacles lower than [`CharacterController::max_step_height`] are climbed
+//!   automatically.
+//! - Slope limit - surfaces steeper than [`CharacterController::max_slope_angle`] are treated as
+//!   walls.
+//! - Ground snapping - the capsule sticks to the ground when it walks down slopes or stairs.
+//! - Moving platforms - the capsule is carried by colliders it stands on.
+//!
+//! The controller does not apply gravity by itself, add it to desired movement, it is simple
+//! and gives full control over jumps.
+//!
+//! # Usage
+//!
+//! Controller owns kinematic rigid body with a capsule collider, bind a scene node to the body
+//! using physics binder and the node will follow the controller.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::{algebra::Vector3, pool::Handle},
+//!     scene::{node::Node, physics::character::CharacterControllerBuilder, Scene},
+//! };
+//!
+//! fn create_player(scene: &mut Scene, node: Handle<Node>) {
+//!     let controller = CharacterControllerBuilder::new()
+//!         .with_radius(0.3)
+//!         .with_height(1.8)
+//!         .with_position(Vector3::new(0.0, 2.0, 0.0))
+//!         .build(&mut scene.physics);
+//!     scene.physics_binder.bind(node, controller.body());
+//!
+//!     // Then every frame:
+//!     // controller.move_and_slide(&mut scene.physics, velocity * dt);
+//! }
+//! ```
+
+use crate::{
+    core::{
+        algebra::{Isometry3, Point3, Translation3, UnitQuaternion, Vector3},
+        visitor::prelude::*,
+    },
+    engine::{ColliderHandle, RigidBodyHandle},
+    scene::physics::{Physics, ShapeCastOptions, ShapeCastResult},
+};
+use rapier3d::{
+    dynamics::{RigidBodyBuilder, RigidBodyType},
+    geometry::{Capsule, ColliderBuilder, InteractionGroups},
+};
+
+/// See module docs.
+#[derive(Clone, Debug, Visit)]
+pub struct CharacterController {
+    body: RigidBodyHandle,
+    collider: ColliderHandle,
+    position: Vector3<f32>,
+    radius: f32,
+    height: f32,
+    /// Maximum height of obstacles that will be climbed automatically.
+    pub max_step_height: f32,
+    /// Maximum angle (in radians) between a surface and horizontal plane that still can be
+    /// walked on.
+    pub max_slope_angle: f32,
+    /// Maximum distance to the ground at which the controller will be snapped to it.
+    pub snap_distance: f32,
+    /// Small gap that is kept between the capsule and obstacles, prevents the capsule from
+    /// getting stuck inside of them because of precision issues.
+    pub skin_width: f32,
+    /// Groups of colliders that will block the controller.
+    #[visit(skip)]
+    pub groups: InteractionGroups,
+    grounded: bool,
+    ground_normal: Vector3<f32>,
+    #[visit(skip)]
+    ground: Option<(ColliderHandle, Isometry3<f32>)>,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            body: Default::default(),
+            collider: Default::default(),
+            position: Default::default(),
+            radius: 0.5,
+            height: 2.0,
+            max_step_height: 0.3,
+            max_slope_angle: 45.0f32.to_radians(),
+            snap_distance: 0.2,
+            skin_width: 0.01,
+            groups: InteractionGroups::all(),
+            grounded: false,
+            ground_normal: Vector3::y(),
+            ground: None,
+        }
+    }
+}
+
+/// Maximum amount of collide-and-slide iterations per one movement.
+const MAX_SLIDE_ITERATIONS: usize = 4;
+
+struct SlideResult {
+    position: Vector3<f32>,
+    blocked: bool,
+}
+
+impl CharacterController {
+    /// Returns handle of kinematic rigid body of the controller, bind a scene node to it to
+    /// make the node follow the controller.
+    pub fn body(&self) -> RigidBodyHandle {
+        self.body
+    }
+
+    /// Returns handle of capsule collider of the controller.
+    pub fn collider(&self) -> ColliderHandle {
+        self.collider
+    }
+
+    /// Returns position of the center of the capsule in world coordinates.
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    /// Returns true if the controller stands on walkable surface.
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// Returns normal of the surface the controller stands on, or up vector if the controller
+    /// is in the air.
+    pub fn ground_normal(&self) -> Vector3<f32> {
+        self.ground_normal
+    }
+
+    /// Returns collider the controller stands on.
+    pub fn ground_collider(&self) -> Option<ColliderHandle> {
+        self.ground.map(|(collider, _)| collider)
+    }
+
+    /// Teleports the controller to given position, obstacles are ignored.
+    pub fn set_position(&mut self, physics: &mut Physics, position: Vector3<f32>) {
+        self.position = position;
+        self.ground = None;
+        self.grounded = false;
+        self.sync_body(physics);
+    }
+
+    fn shape(&self) -> Capsule {
+        let half_height = (self.height * 0.5 - self.radius).max(0.0);
+        Capsule::new(
+            Point3::new(0.0, -half_height, 0.0),
+            Point3::new(0.0, half_height, 0.0),
+            self.radius,
+        )
+    }
+
+    fn is_walkable(&self, normal: &Vector3<f32>) -> bool {
+        normal.dot(&Vector3::y()) >= self.max_slope_angle.cos()
+    }
+
+    fn cast(
+        &self,
+        physics: &Physics,
+        position: Vector3<f32>,
+        direction: Vector3<f32>,
+        max_len: f32,
+    ) -> Option<ShapeCastResult> {
+        physics.cast_shape(ShapeCastOptions {
+            shape: &self.shape(),
+            position: Isometry3::translation(position.x, position.y, position.z),
+            direction,
+            max_len,
+            groups: self.groups,
+            exclude: Some(self.collider),
+        })
+    }
+
+    /// Moves the capsule along given vector and slides it along obstacles.
+    fn slide(
+        &self,
+        physics: &Physics,
+        mut position: Vector3<f32>,
+        motion: Vector3<f32>,
+    ) -> SlideResult {
+        let mut remaining = motion;
+        let mut blocked = false;
+        for _ in 0..MAX_SLIDE_ITERATIONS {
+            let length = remaining.norm();
+            if length <= f32::EPSILON {
+                break;
+            }
+            let direction = remaining / length;
+
+            let hit = match self.cast(physics, position, direction, length + self.skin_width) {
+                Some(hit) => hit,
+                None => {
+                    position += remaining;
+                    break;
+                }
+            };
+
+            let travel = (hit.toi - self.skin_width).max(0.0).min(length);
+            position += direction * travel;
+            remaining -= direction * travel;
+
+            let mut normal = hit.normal;
+            if !self.is_walkable(&normal) {
+                // Steep surfaces are walls, remove vertical part of the normal so the capsule
+                // won't climb them.
+                blocked = true;
+                normal.y = normal.y.min(0.0);
+                normal = match normal.try_normalize(f32::EPSILON) {
+                    Some(normal) => normal,
+                    None => break,
+                };
+            }
+
+            remaining -= normal * remaining.dot(&normal);
+        }
+
+        SlideResult { position, blocked }
+    }
+
+    /// Moves the capsule down to the ground if the ground is closer than given distance.
+    fn snap_down(
+        &self,
+        physics: &Physics,
+        position: Vector3<f32>,
+        distance: f32,
+    ) -> Option<(Vector3<f32>, ShapeCastResult)> {
+        let hit = self.cast(physics, position, -Vector3::y(), distance + self.skin_width)?;
+        if self.is_walkable(&hit.normal) {
+            let travel = (hit.toi - self.skin_width).max(0.0);
+            Some((position - Vector3::y() * travel, hit))
+        } else {
+            None
+        }
+    }
+
+    fn sync_body(&self, physics: &mut Physics) {
+        if let Some(body) = physics.bodies.get_mut(&self.body) {
+            body.set_next_kinematic_position(Isometry3 {
+                translation: Translation3 {
+                    vector: self.position,
+                },
+                rotation: UnitQuaternion::identity(),
+            });
+        }
+    }
+
+    /// Moves the controller by given vector, usually it is `velocity * dt`. Returns actual
+    /// movement of the controller, it can differ from desired one because of obstacles.
+    pub fn move_and_slide(
+        &mut self,
+        physics: &mut Physics,
+        desired_translation: Vector3<f32>,
+    ) -> Vector3<f32> {
+        let start = self.position;
+        let mut position = self.position;
+
+        // Carry the controller with the collider it stands on.
+        if let Some((ground_collider, last_ground_position)) = self.ground {
+            if let Some(ground) = physics.colliders.get(&ground_collider) {
+                let delta = ground.position() * last_ground_position.inverse();
+                position = delta.transform_point(&Point3::from(position)).coords;
+            }
+        }
+
+        let vertical = Vector3::y() * desired_translation.y;
+        let horizontal = desired_translation - vertical;
+
+        // Horizontal movement with step climbing.
+        let mut result = self.slide(physics, position, horizontal);
+        if result.blocked && self.grounded && self.max_step_height > 0.0 {
+            let raised = self.slide(physics, position, Vector3::y() * self.max_step_height);
+            let moved = self.slide(physics, raised.position, horizontal);
+            let climbed = raised.position.y - position.y;
+            if let Some((stepped, hit)) = self.snap_down(physics, moved.position, climbed) {
+                let progress =
+                    |p: Vector3<f32>| Vector3::new(p.x - position.x, 0.0, p.z - position.z).norm();
+                // Rounded bottom of the capsule can land on an edge of an obstacle that is
+                // higher than max step height, so check height of the contact itself.
+                let step_height = hit.position.y - (position.y - self.height * 0.5);
+                if progress(stepped) > progress(result.position) + self.skin_width
+                    && step_height <= self.max_step_height + self.skin_width
+                {
+                    result.position = stepped;
+                }
+            }
+        }
+        position = result.position;
+
+        // Vertical movement, walkable ground stops falling so the controller does not slide
+        // down gentle slopes.
+        let landed = if vertical.y < 0.0 {
+            self.snap_down(physics, position, -vertical.y)
+        } else {
+            None
+        };
+        position = match landed {
+            Some((landed, _)) => landed,
+            None => self.slide(physics, position, vertical).position,
+        };
+
+        // Snap to the ground when walking down, but not when jumping.
+        if self.grounded && vertical.y <= 0.0 {
+            if let Some((snapped, _)) = self.snap_down(physics, position, self.snap_distance) {
+                position = snapped;
+            }
+        }
+
+        // Update ground info.
+        match self.cast(physics, position, -Vector3::y(), self.skin_width * 2.0) {
+            Some(hit) if self.is_walkable(&hit.normal) => {
+                self.grounded = true;
+                self.ground_normal = hit.normal;
+                self.ground = physics
+                    .colliders
+                    .get(&hit.collider)
+                    .map(|collider| (hit.collider, *collider.position()));
+            }
+            _ => {
+                self.grounded = false;
+                self.ground_normal = Vector3::y();
+                self.ground = None;
+            }
+        }
+
+        self.position = position;
+        self.sync_body(physics);
+
+        position - start
+    }
+}
+
+/// Creates character controller with kinematic rigid body and capsule collider.
+pub struct CharacterControllerBuilder {
+    position: Vector3<f32>,
+    radius: f32,
+    height: f32,
+    max_step_height: f32,
+    max_slope_angle: f32,
+    snap_distance: f32,
+    skin_width: f32,
+    groups: InteractionGroups,
+}
+
+impl Default for CharacterControllerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CharacterControllerBuilder {
+    /// Creates new builder with default settings.
+    pub fn new() -> Self {
+        let defaults = CharacterController::default();
+        Self {
+            position: Default::default(),
+            radius: defaults.radius,
+            height: defaults.height,
+            max_step_height: defaults.max_step_height,
+            max_slope_angle: defaults.max_slope_angle,
+            snap_distance: defaults.snap_distance,
+            skin_width: defaults.skin_width,
+            groups: defaults.groups,
+        }
+    }
+
+    /// Sets initial position of the center of the capsule.
+    pub fn with_position(mut self, position: Vector3<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Sets radius of the capsule.
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets full height of the capsule, including caps.
+    pub fn with_height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    /// Sets maximum height of obstacles that will be climbed automatically.
+    pub fn with_max_step_height(mut self, max_step_height: f32) -> Self {
+        self.max_step_height = max_step_height;
+        self
+    }
+
+    /// Sets maximum angle (in radians) of walkable surfaces.
+    pub fn with_max_slope_angle(mut self, max_slope_angle: f32) -> Self {
+        self.max_slope_angle = max_slope_angle;
+        self
+    }
+
+    /// Sets maximum distance to the ground at which the controller will be snapped to it.
+    pub fn with_snap_distance(mut self, snap_distance: f32) -> Self {
+        self.snap_distance = snap_distance;
+        self
+    }
+
+    /// Sets gap that is kept between the capsule and obstacles.
+    pub fn with_skin_width(mut self, skin_width: f32) -> Self {
+        self.skin_width = skin_width;
+        self
+    }
+
+    /// Sets groups of colliders that will block the controller.
+    pub fn with_groups(mut self, groups: InteractionGroups) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// Creates rigid body and collider of the controller in given physics world.
+    pub fn build(self, physics: &mut Physics) -> CharacterController {
+        let half_height = (self.height * 0.5 - self.radius).max(0.0);
+
+        let body = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::KinematicPositionBased)
+                .position(Isometry3 {
+                    translation: Translation3 {
+                        vector: self.position,
+                    },
+                    rotation: UnitQuaternion::identity(),
+                })
+                .build(),
+        );
+        let collider = physics.add_collider(
+            ColliderBuilder::capsule_y(half_height, self.radius)
+                .collision_groups(self.groups)
+                .build(),
+            &body,
+        );
+
+        CharacterController {
+            body,
+            collider,
+            position: self.position,
+            radius: self.radius,
+            height: self.height,
+            max_step_height: self.max_step_height,
+            max_slope_angle: self.max_slope_angle,
+            snap_distance: self.snap_distance,
+            skin_width: self.skin_width,
+            groups: self.groups,
+            grounded: false,
+            ground_normal: Vector3::y(),
+            ground: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Isometry3, Translation3, UnitQuaternion, Vector3},
+        engine::{ColliderHandle, PhysicsBinder, RigidBodyHandle},
+        scene::physics::{
+            character::{CharacterController, CharacterControllerBuilder},
+            Physics,
+        },
+    };
+    use rapier3d::{
+        dynamics::{RigidBodyBuilder, RigidBodyType},
+        geometry::ColliderBuilder,
+    };
+
+    fn add_box(physics: &mut Physics, position: Vector3<f32>, half_extents: Vector3<f32>) {
+        let body = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .position(Isometry3 {
+                    translation: Translation3 { vector: position },
+                    rotation: UnitQuaternion::identity(),
+                })
+                .build(),
+        );
+        physics.add_collider(
+            ColliderBuilder::cuboid(half_extents.x, half_extents.y, half_extents.z).build(),
+            &body,
+        );
+    }
+
+    fn add_floor(physics: &mut Physics) {
+        add_box(
+            physics,
+            Vector3::new(0.0, -0.5, 0.0),
+            Vector3::new(20.0, 0.5, 20.0),
+        );
+    }
+
+    // Adds a plank that rises along X axis with given angle, its top surface crosses the floor
+    // (y = 0) at x = 2.
+    fn add_ramp(physics: &mut Physics, angle: f32) {
+        let half_thickness = 0.5;
+        let start = 2.0 + half_thickness * angle.sin();
+        let center = Vector3::new(
+            start + 5.0 * angle.cos(),
+            5.0 * angle.sin() - half_thickness * angle.cos(),
+            0.0,
+        );
+        let body = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .position(Isometry3 {
+                    translation: Translation3 { vector: center },
+                    rotation: UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angle),
+                })
+                .build(),
+        );
+        physics.add_collider(
+            ColliderBuilder::cuboid(5.0 + half_thickness, half_thickness, 5.0).build(),
+            &body,
+        );
+    }
+
+    fn add_platform(
+        physics: &mut Physics,
+        position: Vector3<f32>,
+    ) -> (RigidBodyHandle, ColliderHandle) {
+        let body = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::KinematicPositionBased)
+                .translation(position)
+                .build(),
+        );
+        let collider = physics.add_collider(ColliderBuilder::cuboid(2.0, 0.5, 2.0).build(), &body);
+        (body, collider)
+    }
+
+    fn standing_controller(physics: &mut Physics, position: Vector3<f32>) -> CharacterController {
+        let mut controller = CharacterControllerBuilder::new()
+            .with_radius(0.5)
+            .with_height(2.0)
+            .with_position(position)
+            .build(physics);
+        controller.move_and_slide(physics, Vector3::new(0.0, -0.1, 0.0));
+        assert!(controller.is_grounded());
+        controller
+    }
+
+    #[test]
+    fn walks_on_ground_and_climbs_steps() {
+        let mut physics = Physics::new();
+
+        // Floor with the top at y = 0 and a small step at x = 2.
+        add_box(
+            &mut physics,
+            Vector3::new(0.0, -0.5, 0.0),
+            Vector3::new(20.0, 0.5, 20.0),
+        );
+        add_box(
+            &mut physics,
+            Vector3::new(4.0, 0.1, 0.0),
+            Vector3::new(2.0, 0.1, 2.0),
+        );
+
+        let mut controller = CharacterControllerBuilder::new()
+            .with_radius(0.5)
+            .with_height(2.0)
+            .with_position(Vector3::new(0.0, 1.5, 0.0))
+            .build(&mut physics);
+
+        // Fall down to the floor.
+        for _ in 0..10 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.0, -0.2, 0.0));
+        }
+        assert!(controller.is_grounded());
+        assert!((controller.position().y - 1.0).abs() < 0.05);
+
+        // Walk onto the step, it is lower than max step height.
+        for _ in 0..20 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, -0.1, 0.0));
+        }
+        assert!(controller.is_grounded());
+        assert!(controller.position().x > 3.0);
+        assert!((controller.position().y - 1.2).abs() < 0.05);
+    }
+
+    #[test]
+    fn stops_at_walls() {
+        let mut physics = Physics::new();
+
+        add_box(
+            &mut physics,
+            Vector3::new(0.0, -0.5, 0.0),
+            Vector3::new(20.0, 0.5, 20.0),
+        );
+        // Wall at x = 3 that is way higher than max step height.
+        add_box(
+            &mut physics,
+            Vector3::new(3.5, 2.0, 0.0),
+            Vector3::new(0.5, 2.0, 5.0),
+        );
+
+        let mut controller = CharacterControllerBuilder::new()
+            .with_radius(0.5)
+            .with_height(2.0)
+            .with_position(Vector3::new(0.0, 1.0 + 0.01, 0.0))
+            .build(&mut physics);
+
+        for _ in 0..40 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, -0.1, 0.0));
+        }
+        assert!(controller.position().x < 2.5 + 0.05);
+        assert!(controller.position().x > 2.3);
+    }
+
+    #[test]
+    fn does_not_climb_high_steps() {
+        let mut physics = Physics::new();
+
+        add_floor(&mut physics);
+        // Step at x = 2 that is a bit higher than max step height.
+        add_box(
+            &mut physics,
+            Vector3::new(4.0, 0.2, 0.0),
+            Vector3::new(2.0, 0.2, 2.0),
+        );
+
+        let mut controller = CharacterControllerBuilder::new()
+            .with_max_step_height(0.3)
+            .build(&mut physics);
+        controller.set_position(&mut physics, Vector3::new(0.0, 1.0 + 0.01, 0.0));
+
+        for _ in 0..20 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, -0.1, 0.0));
+        }
+        assert!(controller.is_grounded());
+        assert!(controller.position().x < 1.55);
+        assert!((controller.position().y - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn walks_up_gentle_slopes() {
+        let mut physics = Physics::new();
+
+        let angle = 20.0f32.to_radians();
+        add_floor(&mut physics);
+        add_ramp(&mut physics, angle);
+
+        let mut controller = standing_controller(&mut physics, Vector3::new(0.0, 1.01, 0.0));
+        for _ in 0..30 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, -0.1, 0.0));
+        }
+
+        // The capsule touches the ramp with its bottom hemisphere.
+        let x = controller.position().x;
+        let surface = (x - 2.0) * angle.tan() + 0.5 * (1.0 / angle.cos() - 1.0);
+        assert!(x > 5.0);
+        assert!(controller.is_grounded());
+        assert!((controller.position().y - (surface + 1.0)).abs() < 0.05);
+        assert!((controller.ground_normal().y - angle.cos()).abs() < 1.0e-3);
+
+        // Gravity does not make the controller slide down walkable slopes.
+        let position = controller.position();
+        for _ in 0..10 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.0, -0.1, 0.0));
+        }
+        assert!(controller.is_grounded());
+        assert!((controller.position() - position).norm() < 1.0e-3);
+    }
+
+    #[test]
+    fn steep_slopes_are_walls() {
+        let mut physics = Physics::new();
+
+        let angle = 60.0f32.to_radians();
+        add_floor(&mut physics);
+        add_ramp(&mut physics, angle);
+
+        let mut controller = standing_controller(&mut physics, Vector3::new(0.0, 1.01, 0.0));
+        for _ in 0..30 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, -0.1, 0.0));
+            assert!(controller.position().y < 1.05);
+        }
+        assert!(controller.is_grounded());
+        assert!(controller.position().x > 1.5);
+        assert!(controller.position().x < 2.0);
+
+        // The same slope is walkable with bigger slope limit.
+        controller.max_slope_angle = 65.0f32.to_radians();
+        for _ in 0..20 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, -0.1, 0.0));
+        }
+        assert!(controller.is_grounded());
+        assert!(controller.position().y > 1.5);
+    }
+
+    #[test]
+    fn snaps_to_ground_when_walking_down() {
+        let mut physics = Physics::new();
+
+        // Floor with the top at y = 0 and a stair down at x = 2.
+        add_box(
+            &mut physics,
+            Vector3::new(-8.0, -0.5, 0.0),
+            Vector3::new(10.0, 0.5, 10.0),
+        );
+        add_box(
+            &mut physics,
+            Vector3::new(12.0, -0.65, 0.0),
+            Vector3::new(10.0, 0.5, 10.0),
+        );
+
+        let mut controller = standing_controller(&mut physics, Vector3::new(0.0, 1.01, 0.0));
+        // No gravity, the controller must be kept on the ground by snapping.
+        for _ in 0..20 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, 0.0, 0.0));
+            assert!(controller.is_grounded());
+        }
+        assert!(controller.position().x > 3.5);
+        assert!((controller.position().y - 0.85).abs() < 0.05);
+    }
+
+    #[test]
+    fn does_not_snap_down_cliffs_or_jumps() {
+        let mut physics = Physics::new();
+
+        // Floor with the top at y = 0 and a cliff at x = 2.
+        add_box(
+            &mut physics,
+            Vector3::new(-8.0, -0.5, 0.0),
+            Vector3::new(10.0, 0.5, 10.0),
+        );
+        add_box(
+            &mut physics,
+            Vector3::new(12.0, -1.5, 0.0),
+            Vector3::new(10.0, 0.5, 10.0),
+        );
+
+        let mut controller = standing_controller(&mut physics, Vector3::new(0.0, 1.01, 0.0));
+
+        // Jump.
+        controller.move_and_slide(&mut physics, Vector3::new(0.0, 0.1, 0.0));
+        assert!(!controller.is_grounded());
+        assert!((controller.position().y - 1.1).abs() < 0.02);
+        controller.move_and_slide(&mut physics, Vector3::new(0.0, -0.2, 0.0));
+        assert!(controller.is_grounded());
+
+        // Walk off the cliff, it is deeper than snap distance.
+        for _ in 0..20 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, 0.0, 0.0));
+        }
+        assert!(!controller.is_grounded());
+        assert!(controller.position().x > 3.5);
+        assert!((controller.position().y - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn moves_with_platforms() {
+        let mut physics = Physics::new();
+        let binder = PhysicsBinder::default();
+
+        let (platform, platform_collider) =
+            add_platform(&mut physics, Vector3::new(0.0, -0.5, 0.0));
+        let mut controller = standing_controller(&mut physics, Vector3::new(0.0, 1.01, 0.0));
+        let start = controller.position();
+
+        // Move the platform diagonally upwards and rotate it a bit.
+        let move_platform = |physics: &mut Physics, i: usize| {
+            let body = physics.bodies.get_mut(&platform).unwrap();
+            body.set_next_kinematic_position(Isometry3 {
+                translation: Translation3::new(0.02 * i as f32, -0.5 + 0.01 * i as f32, 0.0),
+                rotation: UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.01 * i as f32),
+            });
+        };
+        for i in 1..=30 {
+            move_platform(&mut physics, i);
+            physics.step(&binder);
+            controller.move_and_slide(&mut physics, Vector3::new(0.0, -0.01, 0.0));
+            assert!(controller.is_grounded());
+            assert_eq!(controller.ground_collider(), Some(platform_collider));
+        }
+
+        let offset = controller.position() - start;
+        assert!((offset - Vector3::new(0.6, 0.3, 0.0)).norm() < 0.05);
+
+        // Leaves the platform when it walks off.
+        for _ in 0..30 {
+            controller.move_and_slide(&mut physics, Vector3::new(0.2, -0.2, 0.0));
+        }
+        assert!(!controller.is_grounded());
+        assert!(controller.ground_collider().is_none());
+    }
+}
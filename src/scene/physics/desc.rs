@@ -1,7 +1,9 @@
 //! A module which contains data model for serialization.
 //!
 //! We have to use custom serialization format, because we can't rely on backward
-//! compatibility of Rapier.
+//! compatibility of Rapier. Exact state of the solver (contacts, warm-starting impulses,
+//! internal state of joints) is stored too, but only as an addition to descriptors - if it
+//! cannot be read, the physics world is restored from descriptors.
 
 use crate::{
     core::{
@@ -10,18 +12,22 @@ use crate::{
             VecStorage, Vector3,
         },
         pool::ErasedHandle,
-        visitor::prelude::*,
+        visitor::{prelude::*, PodVecView},
         BiDirHashMap,
     },
     engine::{ColliderHandle, JointHandle, RigidBodyHandle},
+    scene::physics::{convex_hull_shape, joint::JointLimits},
+    utils::log::{Log, MessageKind},
 };
 use rapier3d::{
     dynamics::{
-        BallJoint, FixedJoint, IntegrationParameters, Joint, JointParams, PrismaticJoint,
-        RevoluteJoint, RigidBody, RigidBodyBuilder, RigidBodyType,
+        BallJoint, FixedJoint, IntegrationParameters, Joint, JointParams, MassProperties,
+        PrismaticJoint, RevoluteJoint, RigidBody, RigidBodyActivation, RigidBodyBuilder,
+        RigidBodyType, SpringModel,
     },
     geometry::{Collider, ColliderBuilder, InteractionGroups, Segment, Shape, SharedShape},
     math::AngVector,
+    pipeline::ActiveEvents,
 };
 use std::{collections::HashMap, hash::Hash};
 
@@ -90,7 +96,38 @@ impl Into<RigidBodyType> for RigidBodyTypeDesc {
     }
 }
 
-#[derive(Clone, Debug, Visit)]
+#[derive(Default, Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct MassPropertiesDesc {
+    pub local_com: Vector3<f32>,
+    pub inv_mass: f32,
+    pub inv_principal_inertia_sqrt: Vector3<f32>,
+    pub principal_inertia_local_frame: UnitQuaternion<f32>,
+}
+
+impl From<MassProperties> for MassPropertiesDesc {
+    fn from(props: MassProperties) -> Self {
+        Self {
+            local_com: props.local_com.coords,
+            inv_mass: props.inv_mass,
+            inv_principal_inertia_sqrt: props.inv_principal_inertia_sqrt,
+            principal_inertia_local_frame: props.principal_inertia_local_frame,
+        }
+    }
+}
+
+impl Into<MassProperties> for MassPropertiesDesc {
+    fn into(self) -> MassProperties {
+        MassProperties {
+            local_com: Point3::from(self.local_com),
+            inv_mass: self.inv_mass,
+            inv_principal_inertia_sqrt: self.inv_principal_inertia_sqrt,
+            principal_inertia_local_frame: self.principal_inertia_local_frame,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 #[doc(hidden)]
 pub struct RigidBodyDesc<C> {
     pub position: Vector3<f32>,
@@ -105,10 +142,25 @@ pub struct RigidBodyDesc<C> {
     pub y_rotation_locked: bool,
     pub z_rotation_locked: bool,
     pub translation_locked: bool,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub gravity_scale: f32,
+    pub dominance_group: i8,
+    pub ccd_enabled: bool,
+    pub force: Vector3<f32>,
+    pub torque: Vector3<f32>,
+    pub next_position: Vector3<f32>,
+    pub next_rotation: UnitQuaternion<f32>,
+    pub activation_energy: f32,
+    pub activation_threshold: f32,
+    // Exact mass properties, including ones of colliders. They can be applied only after
+    // colliders were attached to the body. `None` for data saved by older versions.
+    pub mass_properties: Option<MassPropertiesDesc>,
 }
 
 impl<C> Default for RigidBodyDesc<C> {
     fn default() -> Self {
+        let activation = RigidBodyActivation::active();
         Self {
             position: Default::default(),
             rotation: Default::default(),
@@ -122,10 +174,63 @@ impl<C> Default for RigidBodyDesc<C> {
             y_rotation_locked: false,
             z_rotation_locked: false,
             translation_locked: false,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            gravity_scale: 1.0,
+            dominance_group: 0,
+            ccd_enabled: false,
+            force: Default::default(),
+            torque: Default::default(),
+            next_position: Default::default(),
+            next_rotation: Default::default(),
+            activation_energy: activation.energy,
+            activation_threshold: activation.threshold,
+            mass_properties: None,
         }
     }
 }
 
+impl<C: 'static + Visit + Default> Visit for RigidBodyDesc<C> {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.position.visit("Position", visitor)?;
+        self.rotation.visit("Rotation", visitor)?;
+        self.lin_vel.visit("LinVel", visitor)?;
+        self.ang_vel.visit("AngVel", visitor)?;
+        self.sleeping.visit("Sleeping", visitor)?;
+        self.status.visit("Status", visitor)?;
+        self.colliders.visit("Colliders", visitor)?;
+        self.mass.visit("Mass", visitor)?;
+        self.x_rotation_locked.visit("XRotationLocked", visitor)?;
+        self.y_rotation_locked.visit("YRotationLocked", visitor)?;
+        self.z_rotation_locked.visit("ZRotationLocked", visitor)?;
+        self.translation_locked
+            .visit("TranslationLocked", visitor)?;
+        let _ = self.linear_damping.visit("LinearDamping", visitor);
+        let _ = self.angular_damping.visit("AngularDamping", visitor);
+        let _ = self.gravity_scale.visit("GravityScale", visitor);
+        let _ = self.dominance_group.visit("DominanceGroup", visitor);
+        let _ = self.ccd_enabled.visit("CcdEnabled", visitor);
+        let _ = self.force.visit("Force", visitor);
+        let _ = self.torque.visit("Torque", visitor);
+        // Next position makes sense only for kinematic bodies, start from current one otherwise.
+        if visitor.is_reading() {
+            self.next_position = self.position;
+            self.next_rotation = self.rotation;
+        }
+        let _ = self.next_position.visit("NextPosition", visitor);
+        let _ = self.next_rotation.visit("NextRotation", visitor);
+        let _ = self.activation_energy.visit("ActivationEnergy", visitor);
+        let _ = self
+            .activation_threshold
+            .visit("ActivationThreshold", visitor);
+        let _ = self.mass_properties.visit("MassProperties", visitor);
+
+        visitor.leave_region()
+    }
+}
+
 impl<C: Hash + Clone + Eq> RigidBodyDesc<C> {
     #[doc(hidden)]
     pub fn from_body(
@@ -150,6 +255,19 @@ impl<C: Hash + Clone + Eq> RigidBodyDesc<C> {
             y_rotation_locked: rotation_locked[1],
             z_rotation_locked: rotation_locked[2],
             translation_locked: body.is_translation_locked(),
+            linear_damping: body.linear_damping(),
+            angular_damping: body.angular_damping(),
+            gravity_scale: body.gravity_scale(),
+            dominance_group: body.dominance_group(),
+            ccd_enabled: body.is_ccd_enabled(),
+            // Accumulated forces are not accessible through a body, they're filled by physics.
+            force: Default::default(),
+            torque: Default::default(),
+            next_position: body.next_position().translation.vector,
+            next_rotation: body.next_position().rotation,
+            activation_energy: body.activation().energy,
+            activation_threshold: body.activation().threshold,
+            mass_properties: Some((*body.mass_properties()).into()),
         }
     }
 
@@ -161,27 +279,50 @@ impl<C: Hash + Clone + Eq> RigidBodyDesc<C> {
                 },
                 rotation: self.rotation,
             })
-            .additional_mass(self.mass)
             .linvel(self.lin_vel)
             .angvel(AngVector::new(
                 self.ang_vel.x,
                 self.ang_vel.y,
                 self.ang_vel.z,
             ))
+            // Builder takes flags that *allow* rotations.
             .restrict_rotations(
-                self.x_rotation_locked,
-                self.y_rotation_locked,
-                self.z_rotation_locked,
-            );
+                !self.x_rotation_locked,
+                !self.y_rotation_locked,
+                !self.z_rotation_locked,
+            )
+            .linear_damping(self.linear_damping)
+            .angular_damping(self.angular_damping)
+            .gravity_scale(self.gravity_scale)
+            .dominance_group(self.dominance_group)
+            .ccd_enabled(self.ccd_enabled);
 
         if self.translation_locked {
             builder = builder.lock_translations();
         }
 
+        // Exact mass properties will be set after colliders are attached.
+        if self.mass_properties.is_none() {
+            builder = builder.additional_mass(self.mass);
+        }
+
         let mut body = builder.build();
         if self.sleeping {
             body.sleep();
         }
+        let activation = body.activation_mut();
+        activation.energy = self.activation_energy;
+        activation.threshold = self.activation_threshold;
+        body.apply_force(self.force, false);
+        body.apply_torque(self.torque, false);
+        if body.is_kinematic() {
+            body.set_next_kinematic_position(Isometry3 {
+                translation: Translation {
+                    vector: self.next_position,
+                },
+                rotation: self.next_rotation,
+            });
+        }
         body
     }
 }
@@ -280,7 +421,27 @@ impl Visit for HeightfieldDesc {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Default, Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct ConvexHullDesc {
+    pub vertices: Vec<Vector3<f32>>,
+}
+
+#[derive(Default, Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct CompoundPartDesc {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub shape: ColliderShapeDesc,
+}
+
+#[derive(Default, Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct CompoundDesc {
+    pub parts: Vec<CompoundPartDesc>,
+}
+
+#[derive(Clone, Debug)]
 #[doc(hidden)]
 pub enum ColliderShapeDesc {
     Ball(BallDesc),
@@ -293,6 +454,8 @@ pub enum ColliderShapeDesc {
     Triangle(TriangleDesc),
     Trimesh(TrimeshDesc),
     Heightfield(HeightfieldDesc),
+    ConvexHull(ConvexHullDesc),
+    Compound(CompoundDesc),
 }
 
 impl Default for ColliderShapeDesc {
@@ -315,6 +478,8 @@ impl ColliderShapeDesc {
             ColliderShapeDesc::Triangle(_) => 7,
             ColliderShapeDesc::Trimesh(_) => 8,
             ColliderShapeDesc::Heightfield(_) => 9,
+            ColliderShapeDesc::ConvexHull(_) => 10,
+            ColliderShapeDesc::Compound(_) => 11,
         }
     }
 
@@ -330,6 +495,8 @@ impl ColliderShapeDesc {
             7 => Ok(ColliderShapeDesc::Triangle(Default::default())),
             8 => Ok(ColliderShapeDesc::Trimesh(Default::default())),
             9 => Ok(ColliderShapeDesc::Heightfield(Default::default())),
+            10 => Ok(ColliderShapeDesc::ConvexHull(Default::default())),
+            11 => Ok(ColliderShapeDesc::Compound(Default::default())),
             _ => Err(format!("Invalid collider shape desc id {}!", id)),
         }
     }
@@ -381,6 +548,26 @@ impl ColliderShapeDesc {
             ColliderShapeDesc::Trimesh(TrimeshDesc)
         } else if shape.as_heightfield().is_some() {
             ColliderShapeDesc::Heightfield(HeightfieldDesc)
+        } else if let Some(convex_polyhedron) = shape.as_convex_polyhedron() {
+            ColliderShapeDesc::ConvexHull(ConvexHullDesc {
+                vertices: convex_polyhedron
+                    .points()
+                    .iter()
+                    .map(|p| p.coords)
+                    .collect(),
+            })
+        } else if let Some(compound) = shape.as_compound() {
+            ColliderShapeDesc::Compound(CompoundDesc {
+                parts: compound
+                    .shapes()
+                    .iter()
+                    .map(|(position, shape)| CompoundPartDesc {
+                        translation: position.translation.vector,
+                        rotation: position.rotation,
+                        shape: Self::from_collider_shape(&**shape),
+                    })
+                    .collect(),
+            })
         } else {
             unreachable!()
         }
@@ -431,6 +618,47 @@ impl ColliderShapeDesc {
                 )),
                 Default::default(),
             ),
+            ColliderShapeDesc::ConvexHull(convex_hull) => {
+                let points = convex_hull
+                    .vertices
+                    .into_iter()
+                    .map(Point3::from)
+                    .collect::<Vec<_>>();
+                convex_hull_shape(&points).unwrap_or_else(|| {
+                    Log::writeln(
+                        MessageKind::Warning,
+                        "Unable to restore convex hull, its vertices are degenerate!".to_owned(),
+                    );
+                    SharedShape::ball(0.0)
+                })
+            }
+            ColliderShapeDesc::Compound(compound) => {
+                if compound.parts.is_empty() {
+                    Log::writeln(
+                        MessageKind::Warning,
+                        "Unable to restore compound shape, it has no parts!".to_owned(),
+                    );
+                    SharedShape::ball(0.0)
+                } else {
+                    SharedShape::compound(
+                        compound
+                            .parts
+                            .into_iter()
+                            .map(|part| {
+                                (
+                                    Isometry3 {
+                                        translation: Translation3 {
+                                            vector: part.translation,
+                                        },
+                                        rotation: part.rotation,
+                                    },
+                                    part.shape.into_collider_shape(),
+                                )
+                            })
+                            .collect(),
+                    )
+                }
+            }
         }
     }
 }
@@ -455,6 +683,8 @@ impl Visit for ColliderShapeDesc {
             ColliderShapeDesc::Triangle(v) => v.visit(name, visitor)?,
             ColliderShapeDesc::Trimesh(v) => v.visit(name, visitor)?,
             ColliderShapeDesc::Heightfield(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::ConvexHull(v) => v.visit(name, visitor)?,
+            ColliderShapeDesc::Compound(v) => v.visit(name, visitor)?,
         }
 
         visitor.leave_region()
@@ -474,6 +704,7 @@ pub struct ColliderDesc<R> {
     pub rotation: UnitQuaternion<f32>,
     pub collision_groups: InteractionGroupsDesc,
     pub solver_groups: InteractionGroupsDesc,
+    pub active_events: u32,
 }
 
 #[doc(hidden)]
@@ -514,6 +745,7 @@ impl<R: Default> Default for ColliderDesc<R> {
             rotation: Default::default(),
             collision_groups: Default::default(),
             solver_groups: Default::default(),
+            active_events: 0,
         }
     }
 }
@@ -537,6 +769,7 @@ impl<R: Hash + Clone + Eq> ColliderDesc<R> {
             rotation: collider.position_wrt_parent().unwrap().rotation,
             collision_groups: collider.collision_groups().into(),
             solver_groups: collider.solver_groups().into(),
+            active_events: collider.active_events().bits(),
         }
     }
 
@@ -556,9 +789,10 @@ impl<R: Hash + Clone + Eq> ColliderDesc<R> {
             ))
             .collision_groups(InteractionGroups::new(
                 self.collision_groups.memberships,
-                self.collision_groups.memberships,
+                self.collision_groups.filter,
             ))
-            .sensor(self.is_sensor);
+            .sensor(self.is_sensor)
+            .active_events(ActiveEvents::from_bits_truncate(self.active_events));
         if let Some(density) = self.density {
             builder = builder.density(density);
         }
@@ -580,6 +814,7 @@ impl<R: 'static + Visit + Default> Visit for ColliderDesc<R> {
         let _ = self.collision_groups.visit("CollisionGroups", visitor);
         let _ = self.solver_groups.visit("SolverGroups", visitor);
         self.density.visit("Density", visitor)?;
+        let _ = self.active_events.visit("ActiveEvents", visitor);
 
         visitor.leave_region()
     }
@@ -678,6 +913,12 @@ impl Visit for IntegrationParametersDesc {
         self.velocity_based_erp.visit("VelocityBasedErp", visitor)?;
         self.allowed_linear_error
             .visit("AllowedLinearError", visitor)?;
+        let _ = self
+            .allowed_angular_error
+            .visit("AllowedAngularError", visitor);
+        let _ = self
+            .prediction_distance
+            .visit("PredictionDistance", visitor);
         self.max_linear_correction
             .visit("MaxLinearCorrection", visitor)?;
         self.max_angular_correction
@@ -698,38 +939,202 @@ impl Visit for IntegrationParametersDesc {
     }
 }
 
-#[derive(Default, Clone, Debug, Visit)]
+#[doc(hidden)]
+pub fn spring_model_to_id(model: SpringModel) -> u32 {
+    match model {
+        SpringModel::Disabled => 0,
+        SpringModel::VelocityBased => 1,
+        SpringModel::AccelerationBased => 2,
+        SpringModel::ForceBased => 3,
+    }
+}
+
+#[doc(hidden)]
+pub fn spring_model_from_id(id: u32) -> SpringModel {
+    match id {
+        0 => SpringModel::Disabled,
+        2 => SpringModel::AccelerationBased,
+        3 => SpringModel::ForceBased,
+        _ => SpringModel::VelocityBased,
+    }
+}
+
+// Motor state of prismatic and revolute joints.
+#[derive(Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct JointMotorDesc {
+    pub target_vel: f32,
+    pub target_pos: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub max_impulse: f32,
+    pub impulse: f32,
+    pub model: u32,
+}
+
+impl Default for JointMotorDesc {
+    fn default() -> Self {
+        Self {
+            target_vel: 0.0,
+            target_pos: 0.0,
+            stiffness: 0.0,
+            damping: 0.0,
+            max_impulse: f32::MAX,
+            impulse: 0.0,
+            model: spring_model_to_id(SpringModel::default()),
+        }
+    }
+}
+
+// Motor state of ball joint.
+#[derive(Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct BallJointMotorDesc {
+    pub target_vel: Vector3<f32>,
+    pub target_pos: UnitQuaternion<f32>,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub max_impulse: f32,
+    pub impulse: Vector3<f32>,
+    pub model: u32,
+}
+
+impl Default for BallJointMotorDesc {
+    fn default() -> Self {
+        Self {
+            target_vel: Default::default(),
+            target_pos: Default::default(),
+            stiffness: 0.0,
+            damping: 0.0,
+            max_impulse: f32::MAX,
+            impulse: Default::default(),
+            model: spring_model_to_id(SpringModel::default()),
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug)]
 #[doc(hidden)]
 pub struct BallJointDesc {
     pub local_anchor1: Vector3<f32>,
     pub local_anchor2: Vector3<f32>,
+    pub impulse: Vector3<f32>,
+    pub motor: BallJointMotorDesc,
 }
 
-#[derive(Default, Clone, Debug, Visit)]
+impl Visit for BallJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1.visit("LocalAnchor1", visitor)?;
+        self.local_anchor2.visit("LocalAnchor2", visitor)?;
+        let _ = self.impulse.visit("Impulse", visitor);
+        let _ = self.motor.visit("Motor", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
 #[doc(hidden)]
 pub struct FixedJointDesc {
     pub local_anchor1_translation: Vector3<f32>,
     pub local_anchor1_rotation: UnitQuaternion<f32>,
     pub local_anchor2_translation: Vector3<f32>,
     pub local_anchor2_rotation: UnitQuaternion<f32>,
+    pub impulse: [f32; 6],
 }
 
-#[derive(Default, Clone, Debug, Visit)]
+impl Visit for FixedJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1_translation
+            .visit("LocalAnchor1Translation", visitor)?;
+        self.local_anchor1_rotation
+            .visit("LocalAnchor1Rotation", visitor)?;
+        self.local_anchor2_translation
+            .visit("LocalAnchor2Translation", visitor)?;
+        self.local_anchor2_rotation
+            .visit("LocalAnchor2Rotation", visitor)?;
+        let _ = self.impulse.visit("Impulse", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Clone, Debug)]
 #[doc(hidden)]
 pub struct PrismaticJointDesc {
     pub local_anchor1: Vector3<f32>,
     pub local_axis1: Vector3<f32>,
     pub local_anchor2: Vector3<f32>,
     pub local_axis2: Vector3<f32>,
+    pub impulse: [f32; 5],
+    pub limits_enabled: bool,
+    pub limits: [f32; 2],
+    pub limits_impulse: f32,
+    pub motor: JointMotorDesc,
 }
 
-#[derive(Default, Clone, Debug, Visit)]
+impl Default for PrismaticJointDesc {
+    fn default() -> Self {
+        Self {
+            local_anchor1: Default::default(),
+            local_axis1: Default::default(),
+            local_anchor2: Default::default(),
+            local_axis2: Default::default(),
+            impulse: Default::default(),
+            limits_enabled: false,
+            limits: [-f32::MAX, f32::MAX],
+            limits_impulse: 0.0,
+            motor: Default::default(),
+        }
+    }
+}
+
+impl Visit for PrismaticJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1.visit("LocalAnchor1", visitor)?;
+        self.local_axis1.visit("LocalAxis1", visitor)?;
+        self.local_anchor2.visit("LocalAnchor2", visitor)?;
+        self.local_axis2.visit("LocalAxis2", visitor)?;
+        let _ = self.impulse.visit("Impulse", visitor);
+        let _ = self.limits_enabled.visit("LimitsEnabled", visitor);
+        let _ = self.limits.visit("Limits", visitor);
+        let _ = self.limits_impulse.visit("LimitsImpulse", visitor);
+        let _ = self.motor.visit("Motor", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
 #[doc(hidden)]
 pub struct RevoluteJointDesc {
     pub local_anchor1: Vector3<f32>,
     pub local_axis1: Vector3<f32>,
     pub local_anchor2: Vector3<f32>,
     pub local_axis2: Vector3<f32>,
+    pub impulse: [f32; 5],
+    pub motor: JointMotorDesc,
+}
+
+impl Visit for RevoluteJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1.visit("LocalAnchor1", visitor)?;
+        self.local_axis1.visit("LocalAxis1", visitor)?;
+        self.local_anchor2.visit("LocalAnchor2", visitor)?;
+        self.local_axis2.visit("LocalAxis2", visitor)?;
+        let _ = self.impulse.visit("Impulse", visitor);
+        let _ = self.motor.visit("Motor", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -750,38 +1155,78 @@ impl Default for JointParamsDesc {
 impl Into<JointParams> for JointParamsDesc {
     fn into(self) -> JointParams {
         match self {
-            JointParamsDesc::BallJoint(v) => JointParams::from(BallJoint::new(
-                Point3::from(v.local_anchor1),
-                Point3::from(v.local_anchor2),
-            )),
-            JointParamsDesc::FixedJoint(v) => JointParams::from(FixedJoint::new(
-                Isometry3 {
-                    translation: Translation3 {
-                        vector: v.local_anchor1_translation,
+            JointParamsDesc::BallJoint(v) => {
+                let mut joint =
+                    BallJoint::new(Point3::from(v.local_anchor1), Point3::from(v.local_anchor2));
+                joint.impulse = v.impulse;
+                joint.motor_target_vel = v.motor.target_vel;
+                joint.motor_target_pos = v.motor.target_pos;
+                joint.motor_stiffness = v.motor.stiffness;
+                joint.motor_damping = v.motor.damping;
+                joint.motor_max_impulse = v.motor.max_impulse;
+                joint.motor_impulse = v.motor.impulse;
+                joint.motor_model = spring_model_from_id(v.motor.model);
+                JointParams::from(joint)
+            }
+            JointParamsDesc::FixedJoint(v) => {
+                let mut joint = FixedJoint::new(
+                    Isometry3 {
+                        translation: Translation3 {
+                            vector: v.local_anchor1_translation,
+                        },
+                        rotation: v.local_anchor1_rotation,
                     },
-                    rotation: v.local_anchor1_rotation,
-                },
-                Isometry3 {
-                    translation: Translation3 {
-                        vector: v.local_anchor2_translation,
+                    Isometry3 {
+                        translation: Translation3 {
+                            vector: v.local_anchor2_translation,
+                        },
+                        rotation: v.local_anchor2_rotation,
                     },
-                    rotation: v.local_anchor2_rotation,
-                },
-            )),
-            JointParamsDesc::PrismaticJoint(v) => JointParams::from(PrismaticJoint::new(
-                Point3::from(v.local_anchor1),
-                Unit::<Vector3<f32>>::new_normalize(v.local_axis1),
-                Default::default(), // TODO
-                Point3::from(v.local_anchor2),
-                Unit::<Vector3<f32>>::new_normalize(v.local_axis2),
-                Default::default(), // TODO
-            )),
-            JointParamsDesc::RevoluteJoint(v) => JointParams::from(RevoluteJoint::new(
-                Point3::from(v.local_anchor1),
-                Unit::<Vector3<f32>>::new_normalize(v.local_axis1),
-                Point3::from(v.local_anchor2),
-                Unit::<Vector3<f32>>::new_normalize(v.local_axis2),
-            )),
+                );
+                joint.impulse = v.impulse.into();
+                JointParams::from(joint)
+            }
+            JointParamsDesc::PrismaticJoint(v) => {
+                let mut joint = PrismaticJoint::new(
+                    Point3::from(v.local_anchor1),
+                    Unit::<Vector3<f32>>::new_normalize(v.local_axis1),
+                    Default::default(), // TODO
+                    Point3::from(v.local_anchor2),
+                    Unit::<Vector3<f32>>::new_normalize(v.local_axis2),
+                    Default::default(), // TODO
+                );
+                joint.impulse = v.impulse.into();
+                joint.limits_enabled = v.limits_enabled;
+                joint.limits = v.limits;
+                joint.limits_impulse = v.limits_impulse;
+                joint.motor_target_vel = v.motor.target_vel;
+                joint.motor_target_pos = v.motor.target_pos;
+                joint.motor_stiffness = v.motor.stiffness;
+                joint.motor_damping = v.motor.damping;
+                joint.motor_max_impulse = v.motor.max_impulse;
+                joint.motor_impulse = v.motor.impulse;
+                joint.motor_model = spring_model_from_id(v.motor.model);
+                JointParams::from(joint)
+            }
+            JointParamsDesc::RevoluteJoint(v) => {
+                // Internal motor state of revolute joint (last motor angle, angular impulse)
+                // is not accessible, it is restored only from solver state of the world.
+                let mut joint = RevoluteJoint::new(
+                    Point3::from(v.local_anchor1),
+                    Unit::<Vector3<f32>>::new_normalize(v.local_axis1),
+                    Point3::from(v.local_anchor2),
+                    Unit::<Vector3<f32>>::new_normalize(v.local_axis2),
+                );
+                joint.impulse = v.impulse.into();
+                joint.motor_target_vel = v.motor.target_vel;
+                joint.motor_target_pos = v.motor.target_pos;
+                joint.motor_stiffness = v.motor.stiffness;
+                joint.motor_damping = v.motor.damping;
+                joint.motor_max_impulse = v.motor.max_impulse;
+                joint.motor_impulse = v.motor.impulse;
+                joint.motor_model = spring_model_from_id(v.motor.model);
+                JointParams::from(joint)
+            }
         }
     }
 }
@@ -836,35 +1281,83 @@ impl JointParamsDesc {
             JointParams::BallJoint(v) => Self::BallJoint(BallJointDesc {
                 local_anchor1: v.local_anchor1.coords,
                 local_anchor2: v.local_anchor2.coords,
+                impulse: v.impulse,
+                motor: BallJointMotorDesc {
+                    target_vel: v.motor_target_vel,
+                    target_pos: v.motor_target_pos,
+                    stiffness: v.motor_stiffness,
+                    damping: v.motor_damping,
+                    max_impulse: v.motor_max_impulse,
+                    impulse: v.motor_impulse,
+                    model: spring_model_to_id(v.motor_model),
+                },
             }),
             JointParams::FixedJoint(v) => Self::FixedJoint(FixedJointDesc {
                 local_anchor1_translation: v.local_frame1.translation.vector,
                 local_anchor1_rotation: v.local_frame1.rotation,
                 local_anchor2_translation: v.local_frame2.translation.vector,
                 local_anchor2_rotation: v.local_frame2.rotation,
+                impulse: v.impulse.into(),
             }),
             JointParams::PrismaticJoint(v) => Self::PrismaticJoint(PrismaticJointDesc {
                 local_anchor1: v.local_anchor1.coords,
                 local_axis1: v.local_axis1().into_inner(),
                 local_anchor2: v.local_anchor2.coords,
                 local_axis2: v.local_axis2().into_inner(),
+                impulse: v.impulse.into(),
+                limits_enabled: v.limits_enabled,
+                limits: v.limits,
+                limits_impulse: v.limits_impulse,
+                motor: JointMotorDesc {
+                    target_vel: v.motor_target_vel,
+                    target_pos: v.motor_target_pos,
+                    stiffness: v.motor_stiffness,
+                    damping: v.motor_damping,
+                    max_impulse: v.motor_max_impulse,
+                    impulse: v.motor_impulse,
+                    model: spring_model_to_id(v.motor_model),
+                },
             }),
             JointParams::RevoluteJoint(v) => Self::RevoluteJoint(RevoluteJointDesc {
                 local_anchor1: v.local_anchor1.coords,
                 local_axis1: v.local_axis1.into_inner(),
                 local_anchor2: v.local_anchor2.coords,
                 local_axis2: v.local_axis2.into_inner(),
+                impulse: v.impulse.into(),
+                motor: JointMotorDesc {
+                    target_vel: v.motor_target_vel,
+                    target_pos: v.motor_target_pos,
+                    stiffness: v.motor_stiffness,
+                    damping: v.motor_damping,
+                    max_impulse: v.motor_max_impulse,
+                    impulse: v.motor_impulse,
+                    model: spring_model_to_id(v.motor_model),
+                },
             }),
         }
     }
 }
 
-#[derive(Clone, Debug, Default, Visit)]
+#[derive(Clone, Debug, Default)]
 #[doc(hidden)]
 pub struct JointDesc<R> {
     pub body1: R,
     pub body2: R,
     pub params: JointParamsDesc,
+    pub limits: JointLimits,
+}
+
+impl<R: Visit> Visit for JointDesc<R> {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.body1.visit("Body1", visitor)?;
+        self.body2.visit("Body2", visitor)?;
+        self.params.visit("Params", visitor)?;
+        let _ = self.limits.visit("Limits", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 impl<R: Hash + Clone + Eq> JointDesc<R> {
@@ -877,6 +1370,7 @@ impl<R: Hash + Clone + Eq> JointDesc<R> {
             body1: handle_map.key_of(&joint.body1).cloned().unwrap(),
             body2: handle_map.key_of(&joint.body2).cloned().unwrap(),
             params: JointParamsDesc::from_params(&joint.params),
+            limits: JointLimits::None,
         }
     }
 }
@@ -892,6 +1386,11 @@ pub struct PhysicsDesc {
     pub body_handle_map: BiDirHashMap<RigidBodyHandle, rapier3d::dynamics::RigidBodyHandle>,
     pub collider_handle_map: BiDirHashMap<ColliderHandle, rapier3d::geometry::ColliderHandle>,
     pub joint_handle_map: BiDirHashMap<JointHandle, rapier3d::dynamics::JointHandle>,
+    /// Exact state of the solver encoded by bincode, it depends on version of Rapier. It is
+    /// filled only when the world is saved, so it is empty in deep copies and in files of older
+    /// versions. If it is empty or cannot be read, the world is restored from descriptors:
+    /// bodies, colliders and joints are the same, but contacts and warm-starting data are lost.
+    pub solver_state: Vec<u8>,
 }
 
 impl Visit for PhysicsDesc {
@@ -996,6 +1495,380 @@ impl Visit for PhysicsDesc {
             }
         }
 
+        // Solver state is optional, older files do not have it.
+        let _ = PodVecView::from_pod_vec(&mut self.solver_state).visit("SolverState", visitor);
+
         visitor.leave_region()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Isometry3, Point3, Translation3, Unit, UnitQuaternion, Vector3},
+            futures::executor::block_on,
+            visitor::prelude::*,
+        },
+        scene::{
+            graph::Graph,
+            physics::{
+                desc::{
+                    BallDesc, ColliderDesc, ColliderShapeDesc, CompoundDesc, CompoundPartDesc,
+                    ConvexHullDesc, InteractionGroupsDesc, PhysicsDesc, RigidBodyDesc,
+                },
+                Physics,
+            },
+        },
+    };
+    use rapier3d::{
+        dynamics::{
+            BallJoint, FixedJoint, PrismaticJoint, RevoluteJoint, RigidBodyBuilder, RigidBodyType,
+            SpringModel,
+        },
+        geometry::ColliderBuilder,
+    };
+
+    #[test]
+    fn compound_of_convex_hulls_round_trip() {
+        let desc = ColliderShapeDesc::Compound(CompoundDesc {
+            parts: vec![CompoundPartDesc {
+                translation: Vector3::new(1.0, 2.0, 3.0),
+                rotation: UnitQuaternion::default(),
+                shape: ColliderShapeDesc::ConvexHull(ConvexHullDesc {
+                    vertices: vec![
+                        Vector3::new(0.0, 0.0, 0.0),
+                        Vector3::new(1.0, 0.0, 0.0),
+                        Vector3::new(0.0, 1.0, 0.0),
+                        Vector3::new(0.0, 0.0, 1.0),
+                    ],
+                }),
+            }],
+        });
+
+        let shape = desc.into_collider_shape();
+
+        if let ColliderShapeDesc::Compound(compound) =
+            ColliderShapeDesc::from_collider_shape(&*shape)
+        {
+            assert_eq!(compound.parts.len(), 1);
+            let part = &compound.parts[0];
+            assert_eq!(part.translation, Vector3::new(1.0, 2.0, 3.0));
+            if let ColliderShapeDesc::ConvexHull(convex_hull) = &part.shape {
+                assert_eq!(convex_hull.vertices.len(), 4);
+            } else {
+                panic!("Part of compound must be a convex hull!");
+            }
+        } else {
+            panic!("Shape must be a compound!");
+        }
+    }
+
+    #[test]
+    fn rigid_body_rotation_locks_round_trip() {
+        let desc = RigidBodyDesc::<u32> {
+            x_rotation_locked: true,
+            y_rotation_locked: false,
+            z_rotation_locked: true,
+            ..Default::default()
+        };
+
+        let body = desc.convert_to_body();
+        assert_eq!(body.is_rotation_locked(), [true, false, true]);
+
+        let desc = RigidBodyDesc::<u32>::from_body(&body, &Default::default());
+        assert!(desc.x_rotation_locked);
+        assert!(!desc.y_rotation_locked);
+        assert!(desc.z_rotation_locked);
+    }
+
+    #[test]
+    fn collider_interaction_groups_round_trip() {
+        let desc = ColliderDesc::<u32> {
+            shape: ColliderShapeDesc::Ball(BallDesc { radius: 1.0 }),
+            collision_groups: InteractionGroupsDesc {
+                memberships: 0b0001,
+                filter: 0b0110,
+            },
+            solver_groups: InteractionGroupsDesc {
+                memberships: 0b1000,
+                filter: 0b0011,
+            },
+            ..Default::default()
+        };
+
+        let (collider, _) = desc.convert_to_collider();
+        assert_eq!(collider.collision_groups().memberships, 0b0001);
+        assert_eq!(collider.collision_groups().filter, 0b0110);
+        assert_eq!(collider.solver_groups().memberships, 0b1000);
+        assert_eq!(collider.solver_groups().filter, 0b0011);
+    }
+
+    fn make_world() -> Physics {
+        let mut physics = Physics::new();
+
+        let add_body = |physics: &mut Physics, builder: RigidBodyBuilder| {
+            let body = physics.add_body(builder.build());
+            physics.add_collider(ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(), &body);
+            body
+        };
+
+        let ground = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .translation(Vector3::new(0.0, -1.0, 0.0))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(20.0, 0.5, 20.0).build(), &ground);
+
+        // Stack of touching boxes standing on the ground.
+        for i in 0..3 {
+            add_body(
+                &mut physics,
+                RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                    .translation(Vector3::new(5.0, i as f32, 0.0)),
+            );
+        }
+
+        let anchor = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Static).translation(Vector3::new(0.0, 10.0, 0.0)),
+        );
+        let pendulum = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(2.0, 10.0, 0.0))
+                .linear_damping(0.3)
+                .angular_damping(0.2),
+        );
+        let slider = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(2.0, 8.0, 0.0))
+                .gravity_scale(0.5),
+        );
+        let welded = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic).translation(Vector3::new(2.0, 7.0, 0.0)),
+        );
+        add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::KinematicPositionBased)
+                .translation(Vector3::new(-5.0, 0.0, 0.0)),
+        );
+        add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(-10.0, 0.0, 0.0))
+                .gravity_scale(0.0)
+                .sleeping(true),
+        );
+        add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(10.0, 0.0, 0.0))
+                .angvel(Vector3::new(1.0, 2.0, 3.0)),
+        );
+
+        let mut ball = BallJoint::new(Point3::origin(), Point3::new(-2.0, 0.0, 0.0));
+        ball.configure_motor_velocity(Vector3::new(0.0, 0.0, 1.0), 0.5);
+        physics.add_joint(&anchor, &pendulum, ball);
+
+        let mut prismatic = PrismaticJoint::new(
+            Point3::origin(),
+            Unit::new_unchecked(Vector3::y()),
+            Vector3::x(),
+            Point3::new(0.0, 2.0, 0.0),
+            Unit::new_unchecked(Vector3::y()),
+            Vector3::x(),
+        );
+        prismatic.limits_enabled = true;
+        prismatic.limits = [-1.0, 1.0];
+        prismatic.configure_motor_model(SpringModel::AccelerationBased);
+        prismatic.configure_motor_position(0.5, 2.0, 0.5);
+        physics.add_joint(&pendulum, &slider, prismatic);
+
+        let fixed = FixedJoint::new(Isometry3::identity(), Isometry3::translation(0.0, 1.0, 0.0));
+        physics.add_joint(&slider, &welded, fixed);
+
+        let door = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(0.0, 10.0, -2.0)),
+        );
+        let mut revolute = RevoluteJoint::new(
+            Point3::new(0.0, 0.0, -1.0),
+            Vector3::y_axis(),
+            Point3::new(0.0, 0.0, 1.0),
+            Vector3::y_axis(),
+        );
+        revolute.configure_motor_position(1.0, 5.0, 0.5);
+        physics.add_joint(&anchor, &door, revolute);
+
+        physics
+    }
+
+    fn assert_same_state(a: &Physics, b: &Physics) {
+        assert_eq!(a.bodies.len(), b.bodies.len());
+        for handle in a.bodies.handle_map().forward_map().keys() {
+            let body_a = a.bodies.get(handle).unwrap();
+            let body_b = b.bodies.get(handle).unwrap();
+            assert_eq!(body_a.position(), body_b.position());
+            assert_eq!(body_a.linvel(), body_b.linvel());
+            assert_eq!(body_a.angvel(), body_b.angvel());
+            assert_eq!(body_a.is_sleeping(), body_b.is_sleeping());
+        }
+    }
+
+    fn save_and_load(physics: &Physics, name: &str) -> Physics {
+        let path = std::env::temp_dir().join(format!("{}_{}.bin", name, std::process::id()));
+
+        // Keep the original world intact, it is used as a reference.
+        let mut desc = physics.generate_desc_for_saving();
+        {
+            let mut visitor = Visitor::new();
+            desc.visit("Desc", &mut visitor).unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+
+        let mut loaded = Physics::new();
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            let mut desc = PhysicsDesc::default();
+            desc.visit("Desc", &mut visitor).unwrap();
+            loaded.desc = Some(desc);
+        }
+
+        let _ = std::fs::remove_file(path);
+
+        loaded
+    }
+
+    fn active_contacts(physics: &Physics) -> usize {
+        physics
+            .narrow_phase
+            .contact_pairs()
+            .filter(|pair| pair.has_any_active_contact)
+            .count()
+    }
+
+    #[test]
+    fn runtime_state_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "rg3d_physics_runtime_state_{}.bin",
+            std::process::id()
+        ));
+
+        let mut physics = make_world();
+        for _ in 0..20 {
+            physics.step(&Default::default());
+        }
+
+        // Leave some runtime state that must survive serialization.
+        for body in physics.bodies.set.iter_mut().map(|(_, b)| b) {
+            if body.is_kinematic() {
+                body.set_next_kinematic_position(Isometry3 {
+                    translation: Translation3::new(-5.0, 1.0, 0.0),
+                    rotation: UnitQuaternion::from_euler_angles(0.0, 0.3, 0.0),
+                });
+            } else if body.is_dynamic() && !body.is_sleeping() {
+                body.apply_force(Vector3::new(0.0, 50.0, 0.0), false);
+                body.apply_torque(Vector3::new(1.0, 0.0, 0.0), false);
+            }
+        }
+
+        {
+            let mut visitor = Visitor::new();
+            physics.visit("Physics", &mut visitor).unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+
+        let mut loaded = Physics::new();
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            loaded.visit("Physics", &mut visitor).unwrap();
+        }
+        loaded.resolve(&Default::default(), &Graph::new());
+
+        assert_eq!(physics.gravity, loaded.gravity);
+        assert_same_state(&physics, &loaded);
+
+        for _ in 0..30 {
+            physics.step(&Default::default());
+            loaded.step(&Default::default());
+            assert_same_state(&physics, &loaded);
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn stack_of_touching_bodies_round_trip() {
+        let mut physics = make_world();
+        for _ in 0..60 {
+            physics.step(&Default::default());
+        }
+        assert!(active_contacts(&physics) >= 3);
+
+        let mut loaded = save_and_load(&physics, "rg3d_physics_stack_round_trip");
+        loaded.resolve(&Default::default(), &Graph::new());
+        assert_eq!(active_contacts(&physics), active_contacts(&loaded));
+        assert_same_state(&physics, &loaded);
+
+        // Contacts and warm-starting impulses are restored exactly, so the stack must
+        // continue to move exactly the same.
+        for _ in 0..60 {
+            physics.step(&Default::default());
+            loaded.step(&Default::default());
+            assert_same_state(&physics, &loaded);
+        }
+    }
+
+    #[test]
+    fn solver_state_is_serialized_only_for_saving() {
+        let mut physics = make_world();
+        for _ in 0..20 {
+            physics.step(&Default::default());
+        }
+
+        // Descriptors for deep copies and other in-memory uses skip the solver state.
+        assert!(physics.generate_desc().solver_state.is_empty());
+
+        let path = std::env::temp_dir().join(format!(
+            "rg3d_physics_saved_solver_state_{}.bin",
+            std::process::id()
+        ));
+        {
+            let mut visitor = Visitor::new();
+            physics.visit("Physics", &mut visitor).unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+        let mut loaded = Physics::new();
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            loaded.visit("Physics", &mut visitor).unwrap();
+        }
+        let _ = std::fs::remove_file(path);
+
+        assert!(!loaded.desc.unwrap().solver_state.is_empty());
+    }
+
+    #[test]
+    fn world_is_restored_from_descriptors_without_solver_state() {
+        let mut physics = make_world();
+        for _ in 0..20 {
+            physics.step(&Default::default());
+        }
+
+        let mut loaded = save_and_load(&physics, "rg3d_physics_descriptors_round_trip");
+        // Simulate a file saved by an older version.
+        loaded.desc.as_mut().unwrap().solver_state.clear();
+        loaded.resolve(&Default::default(), &Graph::new());
+
+        assert_same_state(&physics, &loaded);
+        assert_eq!(physics.colliders.len(), loaded.colliders.len());
+        assert_eq!(physics.joints.set.len(), loaded.joints.set.len());
+        for handle in physics.joints.handle_map.forward_map().keys() {
+            assert!(loaded.joints.handle_map.value_of(handle).is_some());
+        }
+    }
+}
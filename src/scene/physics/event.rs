@@ -0,0 +1,299 @@
+//! Contact and intersection events of physics world.
+//!
+//! # Overview
+//!
+//! Physics collects events during every simulation step and puts them in a queue, use
+//! [`Physics::pop_event`](super::Physics::pop_event) to drain it (usually once per frame).
+//! Events are mapped back to engine handles of colliders and to scene nodes that are bound to
+//! parent rigid bodies of colliders.
+//!
+//! # Important notes
+//!
+//! Rapier does not generate events for colliders by default, a collider must have
+//! `ActiveEvents::CONTACT_EVENTS` and/or `ActiveEvents::INTERSECTION_EVENTS` flags set
+//! (see `ColliderBuilder::active_events`). It is enough to set flags on one collider of a pair.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     physics::{geometry::ColliderBuilder, pipeline::ActiveEvents},
+//!     scene::{physics::event::PhysicsEventKind, Scene},
+//! };
+//!
+//! fn print_contacts(scene: &mut Scene) {
+//!     while let Some(event) = scene.physics.pop_event() {
+//!         if let PhysicsEventKind::ContactStarted = event.kind {
+//!             println!("{:?} touches {:?}", event.node1, event.node2);
+//!         }
+//!     }
+//! }
+//!
+//! let trigger = ColliderBuilder::ball(1.0)
+//!     .sensor(true)
+//!     .active_events(ActiveEvents::INTERSECTION_EVENTS)
+//!     .build();
+//! ```
+
+use crate::{
+    core::{
+        algebra::{Point3, Vector3},
+        pool::Handle,
+    },
+    engine::ColliderHandle,
+    scene::node::Node,
+};
+use rapier3d::{
+    geometry::{ContactEvent, ContactPair, IntersectionEvent},
+    pipeline::EventHandler,
+};
+use std::sync::Mutex;
+
+/// Maximum amount of events in the queue, new events are discarded if the queue is full.
+pub const MAX_QUEUED_EVENTS: usize = 1024;
+
+/// Kind of physics event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PhysicsEventKind {
+    /// Colliders started to touch each other. The event is reported one simulation step after
+    /// the contact was detected, see [`ContactPoint::impulse`].
+    ContactStarted,
+    /// Colliders stopped touching each other.
+    ContactStopped,
+    /// Colliders started to intersect each other, at least one of them is a sensor.
+    IntersectionStarted,
+    /// Colliders stopped intersecting each other, at least one of them is a sensor.
+    IntersectionStopped,
+}
+
+/// A point of contact between two colliders.
+#[derive(Copy, Clone, Debug)]
+pub struct ContactPoint {
+    /// Position of the point in world coordinates, it lies on the surface of first collider.
+    pub position: Point3<f32>,
+    /// Contact normal in world coordinates, points from first collider to second.
+    pub normal: Vector3<f32>,
+    /// Penetration depth, negative values means that colliders are separated.
+    pub depth: f32,
+    /// Impulse along the normal that was applied by solver to separate the colliders.
+    /// Contacts are detected at the end of simulation step, so it is zero for just started
+    /// contacts. This is why [`PhysicsEventKind::ContactStarted`] events are reported at the end
+    /// of the next step, with impulses of that step. It is still zero if the contact has ended
+    /// before the solver processed it.
+    pub impulse: f32,
+}
+
+/// Contact or intersection event.
+#[derive(Clone, Debug)]
+pub struct PhysicsEvent {
+    /// Kind of the event.
+    pub kind: PhysicsEventKind,
+    /// First collider of the pair.
+    pub collider1: ColliderHandle,
+    /// Second collider of the pair.
+    pub collider2: ColliderHandle,
+    /// Node that is bound to parent rigid body of first collider, or [`Handle::NONE`] if there
+    /// is no such node.
+    pub node1: Handle<Node>,
+    /// Node that is bound to parent rigid body of second collider, or [`Handle::NONE`] if there
+    /// is no such node.
+    pub node2: Handle<Node>,
+    /// Contact points, filled only for [`PhysicsEventKind::ContactStarted`].
+    pub contacts: Vec<ContactPoint>,
+}
+
+#[derive(Copy, Clone)]
+pub(super) struct RawEvent {
+    pub kind: PhysicsEventKind,
+    pub collider1: rapier3d::geometry::ColliderHandle,
+    pub collider2: rapier3d::geometry::ColliderHandle,
+}
+
+/// Collects events from rapier and passes them to user-defined handler.
+pub(super) struct EventCollector<'a> {
+    pub events: &'a Mutex<Vec<RawEvent>>,
+    pub handler: &'a dyn EventHandler,
+}
+
+impl<'a> EventHandler for EventCollector<'a> {
+    fn handle_intersection_event(&self, event: IntersectionEvent) {
+        self.events.lock().unwrap().push(RawEvent {
+            kind: if event.intersecting {
+                PhysicsEventKind::IntersectionStarted
+            } else {
+                PhysicsEventKind::IntersectionStopped
+            },
+            collider1: event.collider1,
+            collider2: event.collider2,
+        });
+
+        self.handler.handle_intersection_event(event);
+    }
+
+    fn handle_contact_event(&self, event: ContactEvent, contact_pair: &ContactPair) {
+        let (kind, collider1, collider2) = match event {
+            ContactEvent::Started(a, b) => (PhysicsEventKind::ContactStarted, a, b),
+            ContactEvent::Stopped(a, b) => (PhysicsEventKind::ContactStopped, a, b),
+        };
+        self.events.lock().unwrap().push(RawEvent {
+            kind,
+            collider1,
+            collider2,
+        });
+
+        self.handler.handle_contact_event(event, contact_pair);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector3, pool::Handle},
+        engine::PhysicsBinder,
+        scene::{
+            node::Node,
+            physics::{
+                event::{PhysicsEvent, PhysicsEventKind, MAX_QUEUED_EVENTS},
+                Physics,
+            },
+        },
+    };
+    use rapier3d::{
+        dynamics::{RigidBodyBuilder, RigidBodyType},
+        geometry::ColliderBuilder,
+        pipeline::ActiveEvents,
+    };
+
+    fn drain(physics: &mut Physics) -> Vec<PhysicsEvent> {
+        std::iter::from_fn(|| physics.pop_event()).collect()
+    }
+
+    #[test]
+    fn contact_started_reports_impulses() {
+        let mut physics = Physics::new();
+        let ground = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        let ground_collider =
+            physics.add_collider(ColliderBuilder::cuboid(10.0, 0.5, 10.0).build(), &ground);
+        let ball = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(0.0, 1.5, 0.0))
+                .linvel(Vector3::new(0.0, -5.0, 0.0))
+                .build(),
+        );
+        let ball_collider = physics.add_collider(
+            ColliderBuilder::ball(0.5)
+                .active_events(ActiveEvents::CONTACT_EVENTS)
+                .build(),
+            &ball,
+        );
+
+        let mut binder = PhysicsBinder::default();
+        let ball_node = Handle::<Node>::new(1, 1);
+        binder.bind(ball_node, ball);
+
+        let mut events = Vec::new();
+        for _ in 0..60 {
+            physics.step(&binder);
+            events.extend(drain(&mut physics));
+        }
+
+        let started = events
+            .iter()
+            .filter(|e| e.kind == PhysicsEventKind::ContactStarted)
+            .collect::<Vec<_>>();
+        assert_eq!(started.len(), 1);
+        let event = started[0];
+
+        let (node1, node2) = if event.collider1 == ball_collider {
+            assert_eq!(event.collider2, ground_collider);
+            (event.node1, event.node2)
+        } else {
+            assert_eq!(event.collider1, ground_collider);
+            (event.node2, event.node1)
+        };
+        assert_eq!(node1, ball_node);
+        assert_eq!(node2, Handle::NONE);
+
+        assert!(!event.contacts.is_empty());
+        assert!(event.contacts.iter().any(|contact| contact.impulse > 0.0));
+        for contact in event.contacts.iter() {
+            // Top of the ground.
+            assert!((contact.position.y - 0.5).abs() < 0.1);
+            assert!(contact.normal.y.abs() > 0.99);
+        }
+    }
+
+    #[test]
+    fn intersection_events_are_queued_in_order() {
+        let mut physics = Physics::new();
+        let sensor = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        physics.add_collider(
+            ColliderBuilder::cuboid(1.0, 1.0, 1.0)
+                .sensor(true)
+                .active_events(ActiveEvents::INTERSECTION_EVENTS)
+                .build(),
+            &sensor,
+        );
+        let mover = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(-3.0, 0.0, 0.0))
+                .gravity_scale(0.0)
+                .linvel(Vector3::new(6.0, 0.0, 0.0))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::ball(0.25).build(), &mover);
+
+        let binder = PhysicsBinder::default();
+        let mut kinds = Vec::new();
+        for _ in 0..120 {
+            physics.step(&binder);
+            kinds.extend(drain(&mut physics).into_iter().map(|e| e.kind));
+        }
+
+        assert_eq!(
+            kinds,
+            vec![
+                PhysicsEventKind::IntersectionStarted,
+                PhysicsEventKind::IntersectionStopped
+            ]
+        );
+    }
+
+    #[test]
+    fn event_queue_is_limited() {
+        let mut physics = Physics::new();
+        physics.gravity = Vector3::default();
+
+        let sensor = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        physics.add_collider(
+            ColliderBuilder::cuboid(20.0, 1.0, 20.0)
+                .sensor(true)
+                .active_events(ActiveEvents::INTERSECTION_EVENTS)
+                .build(),
+            &sensor,
+        );
+
+        let count = 34;
+        for i in 0..count {
+            for j in 0..count {
+                let body = physics.add_body(
+                    RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                        .translation(Vector3::new(i as f32 - 17.0, 0.0, j as f32 - 17.0))
+                        .build(),
+                );
+                physics.add_collider(ColliderBuilder::ball(0.1).build(), &body);
+            }
+        }
+        assert!(count * count > MAX_QUEUED_EVENTS);
+
+        physics.step(&PhysicsBinder::default());
+
+        let events = drain(&mut physics);
+        assert_eq!(events.len(), MAX_QUEUED_EVENTS);
+        assert!(events
+            .iter()
+            .all(|e| e.kind == PhysicsEventKind::IntersectionStarted));
+
+        // Colliders still intersect, so there are no new events.
+        physics.step(&PhysicsBinder::default());
+        assert!(physics.pop_event().is_none());
+    }
+}
@@ -0,0 +1,1003 @@
+//! Physics-driven destructible meshes.
+//!
+//! # Overview
+//!
+//! Destructible is a mesh with a rigid body that breaks into pieces (chunks) on strong impacts.
+//! Destruction is split in two stages:
+//!
+//! 1. Pre-fracturing - [`fracture_surfaces`] cuts surfaces of a mesh into convex Voronoi cells
+//!    around a set of sites (by default - random points in bounds of the mesh, see
+//!    [`generate_sites`]). Every chunk keeps original surfaces (with their textures and
+//!    materials) clipped by its cell, and gets an additional interior surface that closes the
+//!    holes left by cutting. Texture coordinates of interior faces are planar projections on
+//!    cutting planes. This is relatively expensive, so it is done once by
+//!    [`DestructibleBuilder::build`] and chunks are stored in [`Destructible`].
+//! 2. Breaking - [`Destructible::handle_event`] checks contact events of physics world, and when
+//!    impact impulse of a contact with the intact body exceeds the threshold, the intact mesh is
+//!    hidden, its body is removed and chunk nodes are created instead, each with its own dynamic
+//!    body and convex collider. Chunks are created at the root of the scene, because physics
+//!    binder sets local transforms of nodes in world coordinates. They inherit velocity of the
+//!    intact body at their positions.
+//!
+//! ```no_run
+//! use rg3d::scene::{physics::fracture::Destructible, Scene};
+//!
+//! fn update(scene: &mut Scene, destructibles: &mut [Destructible]) {
+//!     while let Some(event) = scene.physics.pop_event() {
+//!         for destructible in destructibles.iter_mut() {
+//!             destructible.handle_event(
+//!                 &event,
+//!                 &mut scene.graph,
+//!                 &mut scene.physics,
+//!                 &mut scene.physics_binder,
+//!             );
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! # Important notes
+//!
+//! Source mesh should be closed (watertight), otherwise interior faces can't be built for some
+//! cuts. Interior faces are built for every cut contour separately, so contours with holes (for
+//! example a pipe that is cut across) are filled completely. Skinned meshes are not supported.
+//!
+//! Rapier does not generate contact events by default, so the builder enables them on colliders
+//! of the intact body.
+
+use crate::{
+    core::{
+        algebra::{Isometry3, Point3, Translation3, Vector2, Vector3, Vector4},
+        arrayvec::ArrayVec,
+        math::{triangulator::triangulate, TriangleDefinition},
+        pool::Handle,
+        rand::{rngs::StdRng, Rng, SeedableRng},
+        visitor::prelude::*,
+    },
+    engine::{ColliderHandle, PhysicsBinder, RigidBodyHandle},
+    resource::texture::Texture,
+    scene::{
+        base::BaseBuilder,
+        graph::Graph,
+        mesh::{
+            buffer::{GeometryBuffer, VertexAttributeUsage, VertexBuffer, VertexReadTrait},
+            surface::{Surface, SurfaceData},
+            vertex::StaticVertex,
+            MeshBuilder,
+        },
+        node::Node,
+        physics::{
+            convex_hull_shape,
+            event::{PhysicsEvent, PhysicsEventKind},
+            Physics,
+        },
+        transform::TransformBuilder,
+    },
+    utils::log::{Log, MessageKind},
+};
+use rapier3d::{
+    dynamics::{RigidBodyBuilder, RigidBodyType},
+    geometry::ColliderBuilder,
+    pipeline::ActiveEvents,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+/// Index of interior surface in clipped triangles.
+const INTERIOR: usize = usize::MAX;
+
+/// Relative distance at which ends of cut segments are considered the same point.
+const CONTOUR_EPSILON: f32 = 1.0e-5;
+
+#[derive(Copy, Clone, Debug)]
+struct ClipVertex {
+    position: Vector3<f32>,
+    tex_coord: Vector2<f32>,
+    normal: Vector3<f32>,
+    tangent: Vector4<f32>,
+}
+
+impl ClipVertex {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let tangent = self.tangent.xyz().lerp(&other.tangent.xyz(), t);
+        Self {
+            position: self.position.lerp(&other.position, t),
+            tex_coord: self.tex_coord.lerp(&other.tex_coord, t),
+            normal: self
+                .normal
+                .lerp(&other.normal, t)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or(self.normal),
+            tangent: Vector4::new(tangent.x, tangent.y, tangent.z, self.tangent.w),
+        }
+    }
+
+    fn key(&self) -> [u32; 12] {
+        let mut key = [0; 12];
+        for (k, v) in key.iter_mut().zip(
+            self.position
+                .iter()
+                .chain(self.tex_coord.iter())
+                .chain(self.normal.iter())
+                .chain(self.tangent.iter()),
+        ) {
+            *k = v.to_bits();
+        }
+        key
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct ClipTriangle {
+    vertices: [ClipVertex; 3],
+    // Index of source surface or INTERIOR.
+    surface: usize,
+}
+
+fn is_degenerate(a: &Vector3<f32>, b: &Vector3<f32>, c: &Vector3<f32>) -> bool {
+    (b - a).cross(&(c - a)).norm_squared() <= f32::EPSILON * f32::EPSILON
+}
+
+/// Clips triangles by a plane, keeping parts for which `dot(normal, p) <= d`. Returns the kept
+/// parts and segments of the cut.
+fn clip(
+    triangles: &[ClipTriangle],
+    normal: &Vector3<f32>,
+    d: f32,
+) -> (Vec<ClipTriangle>, Vec<[Vector3<f32>; 2]>) {
+    let mut kept = Vec::with_capacity(triangles.len());
+    let mut segments = Vec::new();
+
+    for triangle in triangles {
+        let distances = [
+            normal.dot(&triangle.vertices[0].position) - d,
+            normal.dot(&triangle.vertices[1].position) - d,
+            normal.dot(&triangle.vertices[2].position) - d,
+        ];
+
+        if distances.iter().all(|d| *d <= 0.0) {
+            kept.push(*triangle);
+            continue;
+        } else if distances.iter().all(|d| *d > 0.0) {
+            continue;
+        }
+
+        let mut polygon = ArrayVec::<ClipVertex, 4>::new();
+        let mut cut = ArrayVec::<Vector3<f32>, 2>::new();
+        for i in 0..3 {
+            let j = (i + 1) % 3;
+            let (a, b) = (&triangle.vertices[i], &triangle.vertices[j]);
+            let (da, db) = (distances[i], distances[j]);
+            if da <= 0.0 {
+                polygon.push(*a);
+            }
+            if (da <= 0.0) != (db <= 0.0) {
+                // Always interpolate from inner to outer vertex, so neighbour triangles will
+                // produce exactly the same point on their shared edge.
+                let point = if da <= 0.0 {
+                    a.lerp(b, da / (da - db))
+                } else {
+                    b.lerp(a, db / (db - da))
+                };
+                polygon.push(point);
+                cut.push(point.position);
+            }
+        }
+
+        for k in 1..polygon.len() - 1 {
+            let vertices = [polygon[0], polygon[k], polygon[k + 1]];
+            if !is_degenerate(
+                &vertices[0].position,
+                &vertices[1].position,
+                &vertices[2].position,
+            ) {
+                kept.push(ClipTriangle {
+                    vertices,
+                    surface: triangle.surface,
+                });
+            }
+        }
+
+        if cut.len() == 2 && cut[0] != cut[1] {
+            segments.push([cut[0], cut[1]]);
+        }
+    }
+
+    (kept, segments)
+}
+
+fn is_same_point(a: &Vector3<f32>, b: &Vector3<f32>) -> bool {
+    // Points of neighbour segments could differ in last bits, because later cuts produce
+    // T-junctions on edges of interior faces.
+    (a - b).norm_squared() <= CONTOUR_EPSILON * CONTOUR_EPSILON * (1.0 + a.norm_squared())
+}
+
+/// Joins cut segments into closed contours, open chains are discarded.
+fn build_contours(segments: &[[Vector3<f32>; 2]]) -> Vec<Vec<Vector3<f32>>> {
+    let mut used = vec![false; segments.len()];
+    let mut contours = Vec::new();
+    for first in 0..segments.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+
+        let start = segments[first][0];
+        let mut current = segments[first][1];
+        let mut contour = vec![start];
+        let closed = loop {
+            if is_same_point(&current, &start) {
+                break true;
+            }
+            contour.push(current);
+            let next = (0..segments.len()).find_map(|s| {
+                if used[s] {
+                    None
+                } else if is_same_point(&segments[s][0], &current) {
+                    Some((s, segments[s][1]))
+                } else if is_same_point(&segments[s][1], &current) {
+                    Some((s, segments[s][0]))
+                } else {
+                    None
+                }
+            });
+            match next {
+                Some((next, point)) => {
+                    used[next] = true;
+                    current = point;
+                }
+                None => break false,
+            }
+        };
+
+        if closed {
+            remove_collinear(&mut contour);
+            if contour.len() >= 3 {
+                contours.push(contour);
+            }
+        }
+    }
+    contours
+}
+
+fn remove_collinear(contour: &mut Vec<Vector3<f32>>) {
+    let mut i = 0;
+    while contour.len() >= 3 && i < contour.len() {
+        let prev = contour[(i + contour.len() - 1) % contour.len()];
+        let next = contour[(i + 1) % contour.len()];
+        if is_degenerate(&prev, &contour[i], &next) {
+            contour.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Builds triangles that close holes left after cut by a plane with given outward normal.
+fn build_caps(segments: &[[Vector3<f32>; 2]], normal: &Vector3<f32>) -> Vec<ClipTriangle> {
+    let tangent = if normal.x.abs() > 0.9 {
+        Vector3::y()
+    } else {
+        Vector3::x()
+    }
+    .cross(normal)
+    .normalize();
+    let bitangent = normal.cross(&tangent);
+
+    let make_vertex = |position: Vector3<f32>| ClipVertex {
+        position,
+        tex_coord: Vector2::new(position.dot(&tangent), position.dot(&bitangent)),
+        normal: *normal,
+        tangent: Vector4::new(tangent.x, tangent.y, tangent.z, 1.0),
+    };
+
+    let mut caps = Vec::new();
+    let mut indices = Vec::new();
+    for contour in build_contours(segments) {
+        triangulate(&contour, &mut indices);
+        for [a, b, c] in indices.iter().cloned() {
+            let (a, b, c) = (contour[a], contour[b], contour[c]);
+            if is_degenerate(&a, &b, &c) {
+                continue;
+            }
+            // Triangulator does not preserve winding, caps must face outside of the chunk.
+            let vertices = if (b - a).cross(&(c - a)).dot(normal) >= 0.0 {
+                [make_vertex(a), make_vertex(b), make_vertex(c)]
+            } else {
+                [make_vertex(a), make_vertex(c), make_vertex(b)]
+            };
+            caps.push(ClipTriangle {
+                vertices,
+                surface: INTERIOR,
+            });
+        }
+    }
+    caps
+}
+
+fn collect_triangles(surfaces: &[Surface]) -> Vec<ClipTriangle> {
+    let mut triangles = Vec::new();
+    for (index, surface) in surfaces.iter().enumerate() {
+        let data = surface.data();
+        let data = data.read().unwrap();
+
+        let vertices = data
+            .vertex_buffer
+            .iter()
+            .map(|view| {
+                Some(ClipVertex {
+                    position: view.read_3_f32(VertexAttributeUsage::Position).ok()?,
+                    tex_coord: view
+                        .read_2_f32(VertexAttributeUsage::TexCoord0)
+                        .unwrap_or_default(),
+                    normal: view
+                        .read_3_f32(VertexAttributeUsage::Normal)
+                        .unwrap_or_else(|_| Vector3::y()),
+                    tangent: view
+                        .read_4_f32(VertexAttributeUsage::Tangent)
+                        .unwrap_or_default(),
+                })
+            })
+            .collect::<Option<Vec<_>>>();
+
+        let vertices = match vertices {
+            Some(vertices) => vertices,
+            None => {
+                Log::writeln(
+                    MessageKind::Warning,
+                    format!("Surface {} has no positions and won't be fractured!", index),
+                );
+                continue;
+            }
+        };
+
+        for triangle in data.geometry_buffer.iter() {
+            triangles.push(ClipTriangle {
+                vertices: [
+                    vertices[triangle[0] as usize],
+                    vertices[triangle[1] as usize],
+                    vertices[triangle[2] as usize],
+                ],
+                surface: index,
+            });
+        }
+    }
+    triangles
+}
+
+fn make_surface_data(triangles: &[ClipTriangle]) -> SurfaceData {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut map = HashMap::new();
+    for triangle in triangles {
+        let mut definition = [0; 3];
+        for (index, vertex) in definition.iter_mut().zip(triangle.vertices.iter()) {
+            *index = *map.entry(vertex.key()).or_insert_with(|| {
+                vertices.push(StaticVertex {
+                    position: vertex.position,
+                    tex_coord: vertex.tex_coord,
+                    normal: vertex.normal,
+                    tangent: vertex.tangent,
+                });
+                vertices.len() as u32 - 1
+            });
+        }
+        indices.push(TriangleDefinition(definition));
+    }
+
+    SurfaceData::new(
+        VertexBuffer::new(vertices.len(), StaticVertex::layout(), vertices).unwrap(),
+        GeometryBuffer::new(indices),
+        true,
+    )
+}
+
+// Creates a surface with given data and the same look as the source surface.
+fn make_surface(source: &Surface, data: SurfaceData) -> Surface {
+    let mut surface = Surface::new(Arc::new(RwLock::new(data)));
+    surface.set_diffuse_texture(source.diffuse_texture());
+    surface.set_normal_texture(source.normal_texture());
+    surface.set_specular_texture(source.specular_texture());
+    surface.set_roughness_texture(source.roughness_texture());
+    surface.set_height_texture(source.height_texture());
+    surface.set_material(source.material());
+    surface.set_color(source.color());
+    surface
+}
+
+/// A piece of pre-fractured mesh.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct FractureChunk {
+    /// Clipped surfaces of source mesh, followed by interior surface. Surfaces that are
+    /// completely outside of the chunk are omitted.
+    pub surfaces: Vec<Surface>,
+}
+
+/// Generates `count` random sites for [`fracture_surfaces`] within bounds of given surfaces. The
+/// same seed always gives the same sites.
+pub fn generate_sites(surfaces: &[Surface], count: usize, seed: u64) -> Vec<Vector3<f32>> {
+    let triangles = collect_triangles(surfaces);
+    let mut min = Vector3::repeat(f32::MAX);
+    let mut max = Vector3::repeat(-f32::MAX);
+    for vertex in triangles.iter().flat_map(|t| t.vertices.iter()) {
+        min = min.inf(&vertex.position);
+        max = max.sup(&vertex.position);
+    }
+    if triangles.is_empty() {
+        return Vec::new();
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| {
+            Vector3::new(
+                rng.gen_range(min.x..=max.x),
+                rng.gen_range(min.y..=max.y),
+                rng.gen_range(min.z..=max.z),
+            )
+        })
+        .collect()
+}
+
+/// Cuts given surfaces into chunks, one per Voronoi cell of given sites (in local coordinates
+/// of surfaces), see module docs. Interior surface of each chunk looks like the first source
+/// surface, except its diffuse texture, which is replaced with `interior_texture` if it is set.
+/// Cells that do not intersect the geometry produce no chunks.
+pub fn fracture_surfaces(
+    surfaces: &[Surface],
+    sites: &[Vector3<f32>],
+    interior_texture: Option<Texture>,
+) -> Vec<FractureChunk> {
+    let source = collect_triangles(surfaces);
+    if source.is_empty() {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::new();
+    for (i, site) in sites.iter().enumerate() {
+        let mut triangles = source.clone();
+        for (j, other) in sites.iter().enumerate() {
+            if i == j || triangles.is_empty() {
+                continue;
+            }
+            let normal = match (other - site).try_normalize(f32::EPSILON) {
+                Some(normal) => normal,
+                // Coincident sites, the cell is shared by the first one.
+                None if j < i => {
+                    triangles.clear();
+                    continue;
+                }
+                None => continue,
+            };
+            let d = normal.dot(&((site + other).scale(0.5)));
+            let (kept, segments) = clip(&triangles, &normal, d);
+            triangles = kept;
+            triangles.extend(build_caps(&segments, &normal));
+        }
+
+        if triangles.is_empty() {
+            continue;
+        }
+
+        let mut chunk = FractureChunk::default();
+        for (index, surface) in surfaces.iter().enumerate() {
+            let part = triangles
+                .iter()
+                .filter(|t| t.surface == index)
+                .cloned()
+                .collect::<Vec<_>>();
+            if !part.is_empty() {
+                chunk
+                    .surfaces
+                    .push(make_surface(surface, make_surface_data(&part)));
+            }
+        }
+        let interior = triangles
+            .iter()
+            .filter(|t| t.surface == INTERIOR)
+            .cloned()
+            .collect::<Vec<_>>();
+        if !interior.is_empty() {
+            let mut surface = make_surface(&surfaces[0], make_surface_data(&interior));
+            if interior_texture.is_some() {
+                surface.set_diffuse_texture(interior_texture.clone());
+            }
+            chunk.surfaces.push(surface);
+        }
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+fn impact_impulse(event: &PhysicsEvent) -> f32 {
+    event.contacts.iter().map(|contact| contact.impulse).sum()
+}
+
+/// A chunk of broken destructible.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct DestructiblePiece {
+    /// Mesh node of the chunk.
+    pub node: Handle<Node>,
+    /// Rigid body of the chunk.
+    pub body: RigidBodyHandle,
+    /// Convex collider of the chunk.
+    pub collider: ColliderHandle,
+}
+
+/// See module docs.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct Destructible {
+    node: Handle<Node>,
+    body: RigidBodyHandle,
+    chunks: Vec<FractureChunk>,
+    pieces: Vec<DestructiblePiece>,
+    fractured: bool,
+    /// Minimum total impulse of a contact that breaks the intact mesh.
+    pub impulse_threshold: f32,
+    /// Density of chunk colliders.
+    pub density: f32,
+}
+
+impl Destructible {
+    /// Returns handle of the intact mesh node.
+    pub fn node(&self) -> Handle<Node> {
+        self.node
+    }
+
+    /// Returns handle of the intact rigid body, it is removed from physics when the mesh breaks.
+    pub fn body(&self) -> RigidBodyHandle {
+        self.body
+    }
+
+    /// Returns pre-fractured chunks.
+    pub fn chunks(&self) -> &[FractureChunk] {
+        &self.chunks
+    }
+
+    /// Returns pieces of the broken mesh, empty until the mesh is broken.
+    pub fn pieces(&self) -> &[DestructiblePiece] {
+        &self.pieces
+    }
+
+    /// Returns true if the mesh is broken.
+    pub fn is_fractured(&self) -> bool {
+        self.fractured
+    }
+
+    /// Checks given physics event and breaks the mesh if the event is a contact of the intact
+    /// body with impact impulse greater or equal to the threshold. Returns true if the mesh was
+    /// broken by this event.
+    ///
+    /// Contact started events are reported one step after the contact was detected, when the
+    /// solver has processed it, so impact impulse is the sum of impulses that the solver
+    /// applied at contact points. Contacts that ended before the solver processed them have
+    /// zero impulse and never break the mesh.
+    pub fn handle_event(
+        &mut self,
+        event: &PhysicsEvent,
+        graph: &mut Graph,
+        physics: &mut Physics,
+        binder: &mut PhysicsBinder<Node>,
+    ) -> bool {
+        if self.fractured || event.kind != PhysicsEventKind::ContactStarted {
+            return false;
+        }
+
+        let involved = [event.collider1, event.collider2]
+            .iter()
+            .any(|collider| physics.collider_parent(collider) == Some(&self.body));
+        if involved && impact_impulse(event) >= self.impulse_threshold {
+            self.fracture(graph, physics, binder);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Breaks the mesh immediately: hides the intact mesh node, removes its body and creates
+    /// chunk nodes with their own bodies at the root of the scene. Does nothing if the mesh is
+    /// already broken.
+    pub fn fracture(
+        &mut self,
+        graph: &mut Graph,
+        physics: &mut Physics,
+        binder: &mut PhysicsBinder<Node>,
+    ) {
+        if self.fractured {
+            return;
+        }
+        self.fractured = true;
+
+        let scale = graph.global_scale(self.node);
+        let (position, linvel, angvel, center_of_mass) = match physics.bodies.get(&self.body) {
+            Some(body) => (
+                *body.position(),
+                *body.linvel(),
+                *body.angvel(),
+                body.position() * body.mass_properties().local_com,
+            ),
+            None => {
+                let (rotation, translation) = graph.isometric_global_rotation_position(self.node);
+                let position = Isometry3 {
+                    translation: Translation3 {
+                        vector: translation,
+                    },
+                    rotation,
+                };
+                (
+                    position,
+                    Vector3::default(),
+                    Vector3::default(),
+                    Point3::from(translation),
+                )
+            }
+        };
+
+        binder.unbind(self.node);
+        physics.remove_body(&self.body);
+        graph[self.node].set_visibility(false);
+
+        let name = graph[self.node].name().to_owned();
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            let points = chunk
+                .surfaces
+                .iter()
+                .flat_map(|surface| {
+                    let data = surface.data();
+                    let data = data.read().unwrap();
+                    data.vertex_buffer
+                        .iter()
+                        .filter_map(|view| view.read_3_f32(VertexAttributeUsage::Position).ok())
+                        .map(|p| Point3::from(p.component_mul(&scale)))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            let shape = match convex_hull_shape(&points) {
+                Some(shape) => shape,
+                None => continue,
+            };
+
+            let center = points
+                .iter()
+                .fold(Vector3::default(), |acc, p| acc + p.coords)
+                / points.len() as f32;
+            let world_center = position * Point3::from(center);
+
+            let body = physics.add_body(
+                RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                    .position(position)
+                    .linvel(linvel + angvel.cross(&(world_center - center_of_mass)))
+                    .angvel(angvel)
+                    .build(),
+            );
+            let collider = physics.add_collider(
+                ColliderBuilder::new(shape).density(self.density).build(),
+                &body,
+            );
+
+            let node = MeshBuilder::new(
+                BaseBuilder::new()
+                    .with_name(format!("{}Chunk{}", name, i))
+                    .with_local_transform(
+                        TransformBuilder::new()
+                            .with_local_position(position.translation.vector)
+                            .with_local_rotation(position.rotation)
+                            .with_local_scale(scale)
+                            .build(),
+                    ),
+            )
+            .with_surfaces(chunk.surfaces.clone())
+            .build(graph);
+            binder.bind(node, body);
+
+            self.pieces.push(DestructiblePiece {
+                node,
+                body,
+                collider,
+            });
+        }
+    }
+}
+
+/// Creates destructible from a mesh node, see module docs for more info.
+pub struct DestructibleBuilder {
+    node: Handle<Node>,
+    chunk_count: usize,
+    seed: u64,
+    sites: Option<Vec<Vector3<f32>>>,
+    interior_texture: Option<Texture>,
+    impulse_threshold: f32,
+    density: f32,
+}
+
+impl DestructibleBuilder {
+    /// Creates new builder for given mesh node.
+    pub fn new(node: Handle<Node>) -> Self {
+        Self {
+            node,
+            chunk_count: 8,
+            seed: 0,
+            sites: None,
+            interior_texture: None,
+            impulse_threshold: 10.0,
+            density: 1.0,
+        }
+    }
+
+    /// Sets amount of random sites, actual amount of chunks could be less, because some cells
+    /// may not intersect the mesh. Default is 8.
+    pub fn with_chunk_count(mut self, count: usize) -> Self {
+        self.chunk_count = count;
+        self
+    }
+
+    /// Sets seed of random sites. Default is 0.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets sites (in local coordinates of the mesh) explicitly, chunk count and seed are
+    /// ignored in this case.
+    pub fn with_sites(mut self, sites: Vec<Vector3<f32>>) -> Self {
+        self.sites = Some(sites);
+        self
+    }
+
+    /// Sets diffuse texture of interior faces.
+    pub fn with_interior_texture(mut self, texture: Texture) -> Self {
+        self.interior_texture = Some(texture);
+        self
+    }
+
+    /// Sets minimum total impulse of a contact that breaks the mesh. Default is 10.0.
+    pub fn with_impulse_threshold(mut self, threshold: f32) -> Self {
+        self.impulse_threshold = threshold;
+        self
+    }
+
+    /// Sets density of chunk colliders. Default is 1.0.
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Pre-fractures the mesh and prepares its body. If the node is already bound to a rigid
+    /// body, the body is used as intact body, otherwise new dynamic body with convex hull
+    /// collider is created and bound to the node. Global transforms of the graph must be up to
+    /// date.
+    pub fn build(
+        self,
+        graph: &Graph,
+        physics: &mut Physics,
+        binder: &mut PhysicsBinder<Node>,
+    ) -> Destructible {
+        let surfaces = match &graph[self.node] {
+            Node::Mesh(mesh) => mesh.surfaces().to_vec(),
+            _ => {
+                Log::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "{} is not a mesh and can't be fractured!",
+                        graph[self.node].name()
+                    ),
+                );
+                Vec::new()
+            }
+        };
+
+        let (chunk_count, seed) = (self.chunk_count, self.seed);
+        let sites = self
+            .sites
+            .unwrap_or_else(|| generate_sites(&surfaces, chunk_count, seed));
+        let chunks = fracture_surfaces(&surfaces, &sites, self.interior_texture);
+
+        let body = match binder.body_of(self.node) {
+            Some(body) => *body,
+            None => {
+                let (rotation, translation) = graph.isometric_global_rotation_position(self.node);
+                let body = physics.add_body(
+                    RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                        .position(Isometry3 {
+                            translation: Translation3 {
+                                vector: translation,
+                            },
+                            rotation,
+                        })
+                        .build(),
+                );
+                if let Some(shape) = Physics::make_convex_hull(self.node, graph) {
+                    physics.add_collider(
+                        ColliderBuilder::new(shape).density(self.density).build(),
+                        &body,
+                    );
+                }
+                binder.bind(self.node, body);
+                body
+            }
+        };
+
+        // Breaking is driven by contact events, make sure they're generated.
+        let colliders = physics
+            .bodies
+            .get(&body)
+            .map(|b| b.colliders().to_vec())
+            .unwrap_or_default();
+        for collider in colliders {
+            if let Some(collider) = physics.colliders.native_mut(collider) {
+                collider.set_active_events(collider.active_events() | ActiveEvents::CONTACT_EVENTS);
+            }
+        }
+
+        Destructible {
+            node: self.node,
+            body,
+            chunks,
+            pieces: Default::default(),
+            fractured: false,
+            impulse_threshold: self.impulse_threshold,
+            density: self.density,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Matrix4, UnitQuaternion, Vector3},
+        engine::PhysicsBinder,
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            mesh::{
+                buffer::{VertexAttributeUsage, VertexReadTrait},
+                surface::{Surface, SurfaceData},
+                MeshBuilder,
+            },
+            physics::{
+                fracture::{fracture_surfaces, generate_sites, DestructibleBuilder},
+                Physics,
+            },
+            transform::TransformBuilder,
+        },
+    };
+    use rapier3d::{
+        dynamics::{RigidBodyBuilder, RigidBodyType},
+        geometry::ColliderBuilder,
+    };
+    use std::sync::{Arc, RwLock};
+
+    fn cube(size: f32) -> Surface {
+        Surface::new(Arc::new(RwLock::new(SurfaceData::make_cube(
+            Matrix4::new_scaling(size),
+        ))))
+    }
+
+    #[test]
+    fn chunks_preserve_volume() {
+        let surfaces = [cube(2.0)];
+        let sites = generate_sites(&surfaces, 6, 42);
+        let chunks = fracture_surfaces(&surfaces, &sites, None);
+        assert!(chunks.len() > 1);
+
+        // Every chunk must be closed, so sum of signed volumes of all chunks is the volume of
+        // source cube.
+        let mut volume = 0.0;
+        for chunk in chunks.iter() {
+            for surface in chunk.surfaces.iter() {
+                let data = surface.data();
+                let data = data.read().unwrap();
+                let position = |i: u32| {
+                    data.vertex_buffer
+                        .get(i as usize)
+                        .unwrap()
+                        .read_3_f32(VertexAttributeUsage::Position)
+                        .unwrap()
+                };
+                for triangle in data.geometry_buffer.iter() {
+                    let (a, b, c) = (
+                        position(triangle[0]),
+                        position(triangle[1]),
+                        position(triangle[2]),
+                    );
+                    volume += a.dot(&b.cross(&c)) / 6.0;
+                }
+            }
+        }
+        assert!((volume - 8.0).abs() < 1.0e-3, "volume is {}", volume);
+    }
+
+    #[test]
+    fn impact_breaks_mesh() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let mut binder = PhysicsBinder::default();
+
+        let ground = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        physics.add_collider(ColliderBuilder::cuboid(10.0, 0.1, 10.0).build(), &ground);
+
+        let mesh = MeshBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(0.0, 3.0, 0.0))
+                    .build(),
+            ),
+        )
+        .with_surfaces(vec![cube(1.0)])
+        .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let mut destructible = DestructibleBuilder::new(mesh)
+            .with_chunk_count(4)
+            .with_impulse_threshold(0.01)
+            .build(&graph, &mut physics, &mut binder);
+        let intact = destructible.body();
+        assert!(!destructible.chunks().is_empty());
+
+        for _ in 0..120 {
+            physics.step(&binder);
+            while let Some(event) = physics.pop_event() {
+                destructible.handle_event(&event, &mut graph, &mut physics, &mut binder);
+            }
+            if destructible.is_fractured() {
+                break;
+            }
+        }
+
+        assert!(destructible.is_fractured());
+        assert!(!destructible.pieces().is_empty());
+        assert!(physics.bodies.get(&intact).is_none());
+        assert!(!graph[mesh].visibility());
+    }
+
+    #[test]
+    fn chunks_are_placed_at_root_in_world_space() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let mut binder = PhysicsBinder::default();
+
+        let mesh = MeshBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(0.0, 3.0, 0.0))
+                    .build(),
+            ),
+        )
+        .with_surfaces(vec![cube(1.0)])
+        .build(&mut graph);
+        BaseBuilder::new()
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(10.0, 0.0, 0.0))
+                    .with_local_rotation(UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.7))
+                    .with_local_scale(Vector3::new(2.0, 2.0, 2.0))
+                    .build(),
+            )
+            .with_children(&[mesh])
+            .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let mut destructible = DestructibleBuilder::new(mesh).with_chunk_count(4).build(
+            &graph,
+            &mut physics,
+            &mut binder,
+        );
+        destructible.fracture(&mut graph, &mut physics, &mut binder);
+        graph.update_hierarchical_data();
+
+        assert!(!destructible.pieces().is_empty());
+        for piece in destructible.pieces() {
+            assert_eq!(graph[piece.node].parent(), graph.get_root());
+
+            // Chunk must be exactly where its body is.
+            let body = physics.bodies.get(&piece.body).unwrap();
+            let (rotation, position) = graph.isometric_global_rotation_position(piece.node);
+            assert!((position - body.position().translation.vector).norm() < 1.0e-4);
+            assert!(rotation.angle_to(&body.position().rotation) < 1.0e-4);
+            assert!((graph.global_scale(piece.node) - Vector3::new(2.0, 2.0, 2.0)).norm() < 1.0e-4);
+        }
+    }
+}
@@ -1,17 +1,81 @@
 //! A container for joints.
 
 use crate::{
-    core::{uuid::Uuid, BiDirHashMap},
+    core::{
+        algebra::{Isometry3, Unit, UnitQuaternion, Vector3},
+        uuid::Uuid,
+        visitor::prelude::*,
+        BiDirHashMap,
+    },
     engine::{JointHandle, RigidBodyHandle},
     physics::dynamics::Joint,
     scene::physics::body::RigidBodyContainer,
 };
-use rapier3d::dynamics::{IslandManager, JointParams, JointSet};
+use rapier3d::dynamics::{IslandManager, JointParams, JointSet, RevoluteJoint, RigidBodySet};
+use std::{collections::HashMap, f32::consts::PI};
+
+/// Angular limits of a joint.
+///
+/// Ball and revolute joints have no built-in limits, so physics world enforces them by itself
+/// right after each simulation step: when a joint leaves its allowed range, second body of the
+/// joint is rotated back around the joint anchor and relative angular velocity that points out
+/// of the range is removed. Such limits are hard - they hold under impacts of any strength,
+/// but the solver knows nothing about them, so bodies do not bounce off the limits.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Visit)]
+pub enum JointLimits {
+    /// The joint is not limited.
+    #[default]
+    None,
+
+    /// Cone limit of a ball joint.
+    Cone {
+        /// Reference direction in local coordinates of the first body.
+        axis1: Vector3<f32>,
+        /// Reference direction in local coordinates of the second body.
+        axis2: Vector3<f32>,
+        /// Maximum angle (in radians) between reference directions of the bodies.
+        angle: f32,
+    },
+
+    /// Angle limits of a revolute joint.
+    Angle {
+        /// Angle of the joint (see [`revolute_angle`]) limits are relative to, usually it is
+        /// the angle at the moment of creation of the joint.
+        rest_angle: f32,
+        /// Minimum rotation angle in radians.
+        min: f32,
+        /// Maximum rotation angle in radians.
+        max: f32,
+    },
+}
+
+/// Returns rotation angle of a revolute joint in `[-pi; pi]` range. Unlike
+/// `RevoluteJoint::estimate_motor_angle` it does not depend on the motor state.
+pub fn revolute_angle(
+    joint: &RevoluteJoint,
+    position1: &Isometry3<f32>,
+    position2: &Isometry3<f32>,
+) -> f32 {
+    let axis = position1 * joint.local_axis1;
+    let ref1 = position1 * joint.basis1[0];
+    let ref2 = position2 * joint.basis2[0];
+    ref1.cross(&ref2).dot(&axis).atan2(ref1.dot(&ref2))
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    let angle = (angle + PI).rem_euclid(2.0 * PI) - PI;
+    if angle == -PI {
+        PI
+    } else {
+        angle
+    }
+}
 
 /// See module docs.
 pub struct JointContainer {
     pub(super) set: JointSet,
     pub(super) handle_map: BiDirHashMap<JointHandle, rapier3d::dynamics::JointHandle>,
+    pub(super) limits: HashMap<JointHandle, JointLimits>,
 }
 
 impl Default for JointContainer {
@@ -19,6 +83,7 @@ impl Default for JointContainer {
         Self {
             set: JointSet::new(),
             handle_map: Default::default(),
+            limits: Default::default(),
         }
     }
 }
@@ -29,6 +94,7 @@ impl JointContainer {
         Self {
             set: JointSet::new(),
             handle_map: Default::default(),
+            limits: Default::default(),
         }
     }
 
@@ -66,9 +132,127 @@ impl JointContainer {
             .value_of(joint_handle)
             .and_then(|&h| joints.remove(h, islands, &mut bodies.set, wake_up));
         self.handle_map.remove_by_key(joint_handle);
+        self.limits.remove(joint_handle);
         result
     }
 
+    /// Sets angular limits of a joint, see [`JointLimits`] docs. Limits must match type of the
+    /// joint - cone limits are used only by ball joints and angle limits only by revolute
+    /// joints, mismatched limits are ignored.
+    pub fn set_limits(&mut self, handle: &JointHandle, limits: JointLimits) {
+        if limits == JointLimits::None {
+            self.limits.remove(handle);
+        } else {
+            self.limits.insert(*handle, limits);
+        }
+    }
+
+    /// Returns angular limits of a joint.
+    pub fn limits(&self, handle: &JointHandle) -> JointLimits {
+        self.limits.get(handle).cloned().unwrap_or_default()
+    }
+
+    /// Moves second bodies of joints that are out of their limits back to the limits.
+    pub(super) fn enforce_limits(&mut self, bodies: &mut RigidBodySet) {
+        let set = &self.set;
+        let handle_map = &self.handle_map;
+
+        // Joints are removed together with their bodies, forget limits of such joints.
+        self.limits
+            .retain(|handle, _| handle_map.value_of(handle).and_then(|&h| set.get(h)).is_some());
+
+        // Joints are visited in arbitrary order (new joints reuse slots of removed ones), so a
+        // correction of a parent joint of a chain (such as a ragdoll) could move its children
+        // out of their limits again. Such violations are small and fixed on following steps.
+        for (handle, joint) in set.iter() {
+            let limits = match handle_map.key_of(&handle).and_then(|h| self.limits.get(h)) {
+                Some(limits) => limits,
+                None => continue,
+            };
+            let (position1, angvel1) = match bodies.get(joint.body1) {
+                Some(body1) => (*body1.position(), *body1.angvel()),
+                None => continue,
+            };
+            let body2 = match bodies.get_mut(joint.body2) {
+                Some(body2) if body2.is_dynamic() => body2,
+                _ => continue,
+            };
+            let position2 = *body2.position();
+
+            // Rotation that brings the joint back to the limit, axis of rotation that moves the
+            // joint out of the limit and the anchor of the joint.
+            let (correction, out_axis, local_anchor2) = match (*limits, &joint.params) {
+                (
+                    JointLimits::Cone {
+                        axis1,
+                        axis2,
+                        angle,
+                    },
+                    JointParams::BallJoint(ball),
+                ) => {
+                    let axis1 = position1 * axis1;
+                    let axis2 = position2 * axis2;
+                    let deviation = axis1.angle(&axis2);
+                    if deviation <= angle {
+                        continue;
+                    }
+                    let out_axis = match Unit::try_new(axis1.cross(&axis2), f32::EPSILON) {
+                        Some(out_axis) => out_axis,
+                        None => continue,
+                    };
+                    (
+                        UnitQuaternion::from_axis_angle(&out_axis, angle - deviation),
+                        out_axis,
+                        ball.local_anchor2,
+                    )
+                }
+                (
+                    JointLimits::Angle {
+                        rest_angle,
+                        min,
+                        max,
+                    },
+                    JointParams::RevoluteJoint(revolute),
+                ) => {
+                    let angle =
+                        wrap_angle(revolute_angle(revolute, &position1, &position2) - rest_angle);
+                    let clamped = angle.clamp(min, max);
+                    if angle == clamped {
+                        continue;
+                    }
+                    let axis = position1 * revolute.local_axis1;
+                    (
+                        UnitQuaternion::from_axis_angle(&axis, clamped - angle),
+                        if angle > max { axis } else { -axis },
+                        revolute.local_anchor2,
+                    )
+                }
+                _ => continue,
+            };
+
+            // Rotate the body around the anchor.
+            let anchor = position2 * local_anchor2;
+            let rotation = correction * position2.rotation;
+            let position = Isometry3::from_parts(
+                (anchor.coords - rotation * local_anchor2.coords).into(),
+                rotation,
+            );
+            body2.set_position(position, true);
+
+            // Remove relative angular velocity that moves the joint out of the limit, linear
+            // velocity of the anchor must stay the same.
+            let out_speed = (body2.angvel() - angvel1).dot(&out_axis);
+            if out_speed > 0.0 {
+                let delta: Vector3<f32> = -out_axis.into_inner() * out_speed;
+                let center_of_mass = position * body2.mass_properties().local_com;
+                let linvel = body2.linvel() - delta.cross(&(anchor - center_of_mass));
+                let angvel = body2.angvel() + delta;
+                body2.set_linvel(linvel, true);
+                body2.set_angvel(angvel, true);
+            }
+        }
+    }
+
     /// Tries to borrow a joint from the container.
     pub fn get_mut(&mut self, handle: &JointHandle) -> Option<&mut Joint> {
         let joints = &mut self.set;
@@ -21,6 +21,10 @@ use crate::{
             body::RigidBodyContainer,
             collider::ColliderContainer,
             desc::{ColliderDesc, ColliderShapeDesc, JointDesc, PhysicsDesc, RigidBodyDesc},
+            event::{
+                ContactPoint, EventCollector, PhysicsEvent, PhysicsEventKind, RawEvent,
+                MAX_QUEUED_EVENTS,
+            },
             joint::JointContainer,
         },
         terrain::Terrain,
@@ -32,27 +36,46 @@ use crate::{
     },
 };
 use rapier3d::{
+    data::ComponentSet,
     dynamics::{
-        CCDSolver, IntegrationParameters, IslandManager, Joint, JointParams, RigidBody,
-        RigidBodyBuilder, RigidBodyType,
+        CCDSolver, IntegrationParameters, IslandManager, Joint, JointParams, JointSet, RigidBody,
+        RigidBodyBuilder, RigidBodyForces, RigidBodySet, RigidBodyType,
+    },
+    geometry::{
+        BroadPhase, Collider, ColliderBuilder, ColliderSet, InteractionGroups, NarrowPhase,
+    },
+    na::{
+        DMatrix, Dynamic, Isometry3, Matrix4, Point3, Translation, UnitQuaternion, VecStorage,
+        Vector3,
+    },
+    parry::{
+        shape::{ConvexPolyhedron, FeatureId, Shape, SharedShape, TriMesh},
+        transformation::{
+            convex_hull,
+            vhacd::{VHACDParameters, VHACD},
+        },
     },
-    geometry::{BroadPhase, Collider, ColliderBuilder, InteractionGroups, NarrowPhase},
-    na::{DMatrix, Dynamic, Isometry3, Point3, Translation, UnitQuaternion, VecStorage, Vector3},
-    parry::shape::{FeatureId, SharedShape, TriMesh},
     pipeline::{EventHandler, PhysicsPipeline, QueryPipeline},
 };
 use std::{
-    cell::{Cell, RefCell},
+    cell::{Cell, RefCell, RefMut},
     cmp::Ordering,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt::{Debug, Display, Formatter},
+    sync::Mutex,
     time::Duration,
 };
 
 pub mod body;
+pub mod character;
 pub mod collider;
 pub mod desc;
+pub mod event;
+pub mod fracture;
 pub mod joint;
+pub mod ragdoll;
+pub mod rope;
+pub mod vehicle;
 
 /// A ray intersection result.
 #[derive(Debug, Clone)]
@@ -96,6 +119,56 @@ pub struct RayCastOptions {
     pub sort_results: bool,
 }
 
+/// A set of options for the shape cast.
+pub struct ShapeCastOptions<'a> {
+    /// A shape to cast, for example `Ball`, `Capsule` or `Cuboid` from `rapier3d::geometry`.
+    pub shape: &'a dyn Shape,
+
+    /// Initial position of the shape in world coordinates.
+    pub position: Isometry3<f32>,
+
+    /// Direction of cast, does not need to be normalized.
+    pub direction: Vector3<f32>,
+
+    /// Maximum distance of cast.
+    pub max_len: f32,
+
+    /// Groups to check.
+    pub groups: InteractionGroups,
+
+    /// A collider that will be ignored, usually it is a collider of the caster itself.
+    pub exclude: Option<ColliderHandle>,
+}
+
+/// A shape cast result.
+#[derive(Debug, Clone)]
+pub struct ShapeCastResult {
+    /// A handle of the collider that was hit first.
+    pub collider: ColliderHandle,
+
+    /// Distance that the shape has traveled before the hit.
+    pub toi: f32,
+
+    /// A point on the surface of the collider where the hit occurred, in world coordinates.
+    pub position: Point3<f32>,
+
+    /// A normal of the collider at the hit position, in world coordinates.
+    pub normal: Vector3<f32>,
+}
+
+/// A point projection result.
+#[derive(Debug, Clone)]
+pub struct PointProjection {
+    /// A handle of the closest collider.
+    pub collider: ColliderHandle,
+
+    /// Projected point on the surface of the collider, in world coordinates.
+    pub position: Point3<f32>,
+
+    /// Whether the point was inside of the collider or not.
+    pub is_inside: bool,
+}
+
 /// A set of data that has all associations with physics from resource.
 /// It is used to embedding physics from resource to a scene during
 /// the instantiation process.
@@ -148,9 +221,17 @@ pub struct Physics {
     /// A container of joints.
     pub joints: JointContainer,
 
-    /// Event handler collects info about contacts and proximity events.
+    /// Custom event handler, it receives contact and intersection events in addition to the
+    /// built-in event queue (see [`Self::pop_event`]).
     pub event_handler: Box<dyn EventHandler>,
 
+    raw_events: Mutex<Vec<RawEvent>>,
+
+    events: VecDeque<PhysicsEvent>,
+
+    // Contact started events are reported one step later, when solver has calculated impulses.
+    started_contacts: Vec<(RawEvent, PhysicsEvent)>,
+
     /// Descriptors have two purposes:
     /// 1) Defer deserialization to resolve stage - the stage where all meshes
     ///    were loaded and there is a possibility to obtain data for trimeshes.
@@ -214,6 +295,38 @@ impl QueryResultsStorage for Vec<Intersection> {
     }
 }
 
+/// A trait for storages of colliders found by overlap tests. Just like [`QueryResultsStorage`]
+/// it has two implementations: Vec and ArrayVec.
+pub trait ColliderQueryStorage {
+    /// Pushes new collider handle in the storage. Returns true if the handle was
+    /// successfully inserted, false otherwise.
+    fn push(&mut self, collider: ColliderHandle) -> bool;
+
+    /// Clears the storage.
+    fn clear(&mut self);
+}
+
+impl ColliderQueryStorage for Vec<ColliderHandle> {
+    fn push(&mut self, collider: ColliderHandle) -> bool {
+        self.push(collider);
+        true
+    }
+
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<const CAP: usize> ColliderQueryStorage for ArrayVec<ColliderHandle, CAP> {
+    fn push(&mut self, collider: ColliderHandle) -> bool {
+        self.try_push(collider).is_ok()
+    }
+
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
 impl<const CAP: usize> QueryResultsStorage for ArrayVec<Intersection, CAP> {
     fn push(&mut self, intersection: Intersection) -> bool {
         self.try_push(intersection).is_ok()
@@ -258,6 +371,43 @@ impl PhysicsPerformanceStatistics {
     }
 }
 
+fn draw_convex_polyhedron(
+    context: &mut SceneDrawingContext,
+    convex_polyhedron: &ConvexPolyhedron,
+    transform: Matrix4<f32>,
+) {
+    let (vertices, indices) = convex_polyhedron.to_trimesh();
+    let vertex = |i: u32| transform.transform_point(&vertices[i as usize]).coords;
+    for triangle in indices {
+        context.draw_triangle(
+            vertex(triangle[0]),
+            vertex(triangle[1]),
+            vertex(triangle[2]),
+            Color::opaque(200, 200, 200),
+        );
+    }
+}
+
+/// Creates convex polyhedron shape from given closed convex mesh. Unlike
+/// `SharedShape::convex_mesh` it does not panic on empty meshes, which are produced from
+/// degenerate input, `None` is returned instead.
+pub(in crate) fn convex_mesh_shape(
+    vertices: Vec<Point3<f32>>,
+    indices: &[[u32; 3]],
+) -> Option<SharedShape> {
+    if indices.is_empty() {
+        None
+    } else {
+        SharedShape::convex_mesh(vertices, indices)
+    }
+}
+
+/// Creates convex hull shape of given points, returns `None` if points are degenerate.
+pub(in crate) fn convex_hull_shape(points: &[Point3<f32>]) -> Option<SharedShape> {
+    let (vertices, indices) = convex_hull(points);
+    convex_mesh_shape(vertices, &indices)
+}
+
 impl Physics {
     pub(in crate) fn new() -> Self {
         Self {
@@ -272,6 +422,9 @@ impl Physics {
             colliders: ColliderContainer::new(),
             joints: JointContainer::new(),
             event_handler: Box::new(()),
+            raw_events: Default::default(),
+            started_contacts: Default::default(),
+            events: Default::default(),
             query: Default::default(),
             desc: Default::default(),
             embedded_resources: Default::default(),
@@ -383,6 +536,18 @@ impl Physics {
                         Color::opaque(200, 200, 200),
                     );
                 }
+            } else if let Some(convex_polyhedron) = collider.shape().as_convex_polyhedron() {
+                draw_convex_polyhedron(context, convex_polyhedron, transform);
+            } else if let Some(compound) = collider.shape().as_compound() {
+                for (part_position, part) in compound.shapes() {
+                    if let Some(convex_polyhedron) = part.as_convex_polyhedron() {
+                        draw_convex_polyhedron(
+                            context,
+                            convex_polyhedron,
+                            transform * part_position.to_homogeneous(),
+                        );
+                    }
+                }
             }
         }
     }
@@ -394,9 +559,14 @@ impl Physics {
             .and_then(|c| self.bodies.handle_map().key_of(&c.parent().unwrap()))
     }
 
-    pub(in crate) fn step(&mut self) {
+    pub(in crate) fn step(&mut self, binder: &PhysicsBinder<Node>) {
         let time = instant::Instant::now();
 
+        let event_collector = EventCollector {
+            events: &self.raw_events,
+            handler: &*self.event_handler,
+        };
+
         self.pipeline.step(
             &self.gravity,
             &self.integration_parameters,
@@ -408,12 +578,121 @@ impl Physics {
             &mut self.joints.set,
             &mut self.ccd_solver,
             &(),
-            &*self.event_handler,
+            &event_collector,
         );
 
+        self.joints.enforce_limits(&mut self.bodies.set);
+
+        // Contacts that were detected at the end of previous step were processed by the solver
+        // during this step, so their points have impulses now.
+        for (raw_event, mut event) in std::mem::take(&mut self.started_contacts) {
+            let contacts = self.contact_points(&raw_event);
+            if !contacts.is_empty() {
+                event.contacts = contacts;
+            }
+            self.push_event(event);
+        }
+
+        let raw_events = std::mem::take(&mut *self.raw_events.lock().unwrap());
+        for raw_event in raw_events {
+            if let Some(event) = self.convert_event(raw_event, binder) {
+                match raw_event.kind {
+                    PhysicsEventKind::ContactStarted => {
+                        self.started_contacts.push((raw_event, event))
+                    }
+                    PhysicsEventKind::ContactStopped => {
+                        // Contact has ended in the same step it started, so there is nothing
+                        // to wait for.
+                        if let Some(index) = self.started_contacts.iter().position(|(e, _)| {
+                            (e.collider1, e.collider2) == (raw_event.collider1, raw_event.collider2)
+                                || (e.collider1, e.collider2)
+                                    == (raw_event.collider2, raw_event.collider1)
+                        }) {
+                            let (_, started) = self.started_contacts.remove(index);
+                            self.push_event(started);
+                        }
+                        self.push_event(event);
+                    }
+                    _ => self.push_event(event),
+                }
+            }
+        }
+
         self.performance_statistics.step_time += instant::Instant::now() - time;
     }
 
+    fn convert_event(
+        &self,
+        raw_event: RawEvent,
+        binder: &PhysicsBinder<Node>,
+    ) -> Option<PhysicsEvent> {
+        let collider_handle_map = self.colliders.handle_map();
+        let collider1 = *collider_handle_map.key_of(&raw_event.collider1)?;
+        let collider2 = *collider_handle_map.key_of(&raw_event.collider2)?;
+
+        let node_of = |collider: &ColliderHandle| {
+            self.collider_parent(collider)
+                .and_then(|body| binder.node_of(*body))
+                .unwrap_or_default()
+        };
+
+        let contacts = if raw_event.kind == PhysicsEventKind::ContactStarted {
+            self.contact_points(&raw_event)
+        } else {
+            Vec::new()
+        };
+
+        Some(PhysicsEvent {
+            kind: raw_event.kind,
+            collider1,
+            collider2,
+            node1: node_of(&collider1),
+            node2: node_of(&collider2),
+            contacts,
+        })
+    }
+
+    fn push_event(&mut self, event: PhysicsEvent) {
+        if self.events.len() < MAX_QUEUED_EVENTS {
+            self.events.push_back(event);
+        }
+    }
+
+    fn contact_points(&self, raw_event: &RawEvent) -> Vec<ContactPoint> {
+        let mut contacts = Vec::new();
+        if let (Some(pair), Some(native_collider1)) = (
+            self.narrow_phase
+                .contact_pair(raw_event.collider1, raw_event.collider2),
+            self.colliders.set.get(raw_event.collider1),
+        ) {
+            for manifold in pair.manifolds.iter() {
+                for point in manifold.points.iter() {
+                    let local_position = manifold
+                        .subshape_pos1
+                        .map_or(point.local_p1, |subshape_position| {
+                            subshape_position * point.local_p1
+                        });
+                    contacts.push(ContactPoint {
+                        position: native_collider1.position() * local_position,
+                        normal: manifold.data.normal,
+                        depth: -point.dist,
+                        impulse: point.data.impulse,
+                    });
+                }
+            }
+        }
+        contacts
+    }
+
+    /// Pops oldest contact or intersection event from the queue. Events are accumulated during
+    /// simulation steps, so the queue should be drained every frame, otherwise new events will
+    /// be discarded when the queue is full. See [`event`] module docs for more info.
+    pub fn pop_event(&mut self) -> Option<PhysicsEvent> {
+        self.events.pop_front()
+    }
+
+    /// Generates descriptors of the world. Solver state is not included, it is expensive to
+    /// serialize and needed only in saved files, see [`Self::generate_desc_for_saving`].
     #[doc(hidden)]
     pub fn generate_desc(&self) -> PhysicsDesc {
         let body_dense_map = self
@@ -475,8 +754,17 @@ impl Physics {
 
             bodies: self
                 .bodies
+                .set
                 .iter()
-                .map(|b| RigidBodyDesc::from_body(b, &self.colliders.handle_map()))
+                .map(|(h, b)| {
+                    // Accumulated forces can be fetched only from the set.
+                    let forces: &RigidBodyForces = self.bodies.set.index(h.0);
+                    RigidBodyDesc {
+                        force: forces.force,
+                        torque: forces.torque,
+                        ..RigidBodyDesc::from_body(b, &self.colliders.handle_map())
+                    }
+                })
                 .collect::<Vec<_>>(),
 
             colliders: self
@@ -489,19 +777,207 @@ impl Physics {
 
             joints: self
                 .joints
-                .iter()
-                .map(|j| JointDesc::from_joint(j, &self.bodies.handle_map()))
+                .pair_iter()
+                .map(|(handle, j)| JointDesc {
+                    limits: self.joints.limits(&handle),
+                    ..JointDesc::from_joint(j, &self.bodies.handle_map())
+                })
                 .collect::<Vec<_>>(),
 
             body_handle_map,
             collider_handle_map,
             joint_handle_map,
+
+            solver_state: Default::default(),
         }
     }
 
-    /// Creates new trimesh collider shape from given mesh node. It also bakes scale into
-    /// vertices of trimesh because rapier does not support collider scaling yet.
-    pub fn make_trimesh(root: Handle<Node>, graph: &Graph) -> SharedShape {
+    // Same as `generate_desc`, but with exact state of the solver. Loaded world falls back to
+    // descriptors if the state is missing or cannot be read.
+    pub(in crate) fn generate_desc_for_saving(&self) -> PhysicsDesc {
+        PhysicsDesc {
+            solver_state: self.serialize_solver_state(),
+            ..self.generate_desc()
+        }
+    }
+
+    // Serializes exact state of the solver, so contacts and warm-starting data will survive
+    // save/load cycle.
+    fn serialize_solver_state(&self) -> Vec<u8> {
+        // Trimeshes and height fields are never stored, they're restored from associated nodes
+        // on resolve stage, so replace them with cheap placeholders.
+        let mut colliders = self.colliders.set.clone();
+        let scene_shapes = colliders
+            .iter()
+            .filter(|(_, c)| {
+                c.shape().as_trimesh().is_some() || c.shape().as_heightfield().is_some()
+            })
+            .map(|(h, _)| h)
+            .collect::<Vec<_>>();
+        for handle in scene_shapes {
+            if let Some(collider) = colliders.get_mut(handle) {
+                collider.set_shape(SharedShape::ball(0.0));
+            }
+        }
+
+        match bincode::serialize(&(
+            &self.bodies.set,
+            &colliders,
+            &self.joints.set,
+            &self.islands,
+            &self.broad_phase,
+            &self.narrow_phase,
+            &self.ccd_solver,
+        )) {
+            Ok(data) => data,
+            Err(e) => {
+                Log::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Unable to serialize solver state, only descriptors will be saved: {:?}",
+                        e
+                    ),
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    // Restores exact state of the solver. Returns false if the state is missing or cannot be
+    // read, in this case the world must be restored from descriptors.
+    fn resolve_solver_state(
+        &mut self,
+        phys_desc: &PhysicsDesc,
+        binder: &PhysicsBinder<Node>,
+        graph: &Graph,
+    ) -> bool {
+        if phys_desc.solver_state.is_empty() {
+            return false;
+        }
+
+        let (mut bodies, mut colliders, joints, mut islands, broad_phase, narrow_phase, ccd_solver) =
+            match bincode::deserialize::<(
+                RigidBodySet,
+                ColliderSet,
+                JointSet,
+                IslandManager,
+                BroadPhase,
+                NarrowPhase,
+                CCDSolver,
+            )>(&phys_desc.solver_state)
+            {
+                Ok(state) => state,
+                Err(e) => {
+                    Log::writeln(
+                        MessageKind::Warning,
+                        format!(
+                            "Unable to read solver state, descriptors will be used instead: {:?}",
+                            e
+                        ),
+                    );
+                    return false;
+                }
+            };
+
+        // Descriptors are stored in the same order as items in the sets, so handles in the maps
+        // of descriptors are indices of actual handles.
+        let body_handles = bodies.iter().map(|(h, _)| h).collect::<Vec<_>>();
+        let collider_handles = colliders.iter().map(|(h, _)| h).collect::<Vec<_>>();
+        let joint_handles = joints.iter().map(|(h, _)| h).collect::<Vec<_>>();
+        if body_handles.len() != phys_desc.bodies.len()
+            || collider_handles.len() != phys_desc.colliders.len()
+            || joint_handles.len() != phys_desc.joints.len()
+        {
+            Log::writeln(
+                MessageKind::Warning,
+                "Solver state does not match descriptors, descriptors will be used instead."
+                    .to_owned(),
+            );
+            return false;
+        }
+
+        self.bodies.handle_map = phys_desc
+            .body_handle_map
+            .forward_map()
+            .iter()
+            .map(|(k, v)| (*k, body_handles[v.into_raw_parts().0 as usize]))
+            .collect::<HashMap<_, _>>()
+            .into();
+        self.colliders.handle_map = phys_desc
+            .collider_handle_map
+            .forward_map()
+            .iter()
+            .map(|(k, v)| (*k, collider_handles[v.into_raw_parts().0 as usize]))
+            .collect::<HashMap<_, _>>()
+            .into();
+        self.joints.handle_map = phys_desc
+            .joint_handle_map
+            .forward_map()
+            .iter()
+            .map(|(k, v)| (*k, joint_handles[v.into_raw_parts().0 as usize]))
+            .collect::<HashMap<_, _>>()
+            .into();
+
+        for (desc, &handle) in phys_desc.colliders.iter().zip(collider_handles.iter()) {
+            if !matches!(
+                desc.shape,
+                ColliderShapeDesc::Trimesh(_) | ColliderShapeDesc::Heightfield(_)
+            ) {
+                continue;
+            }
+
+            let shape = binder
+                .node_of(desc.parent)
+                .filter(|&node| graph.is_valid_handle(node))
+                .and_then(|node| match (&desc.shape, &graph[node]) {
+                    (ColliderShapeDesc::Heightfield(_), Node::Terrain(terrain)) => {
+                        Some(Self::make_heightfield(terrain))
+                    }
+                    (ColliderShapeDesc::Trimesh(_), _) => Some(Self::make_trimesh(node, graph)),
+                    _ => None,
+                });
+
+            if let Some(shape) = shape {
+                if let Some(collider) = colliders.get_mut(handle) {
+                    collider.set_shape(shape);
+                }
+            } else {
+                Log::writeln(
+                    MessageKind::Error,
+                    format!(
+                        "Unable to restore geometry of collider of body {:?}, associated node \
+                         does not exist or has wrong type!",
+                        desc.parent
+                    ),
+                );
+                colliders.remove(handle, &mut islands, &mut bodies, false);
+                self.colliders.handle_map.remove_by_value(&handle);
+            }
+        }
+
+        self.bodies.set = bodies;
+        self.colliders.set = colliders;
+        self.joints.set = joints;
+        self.islands = islands;
+        self.broad_phase = broad_phase;
+        self.narrow_phase = narrow_phase;
+        self.ccd_solver = ccd_solver;
+
+        for (desc, handle) in phys_desc.joints.iter().zip(joint_handles) {
+            if let Some(handle) = self.joints.handle_map.key_of(&handle).cloned() {
+                self.joints.set_limits(&handle, desc.limits);
+            }
+        }
+
+        true
+    }
+
+    // Collects geometry of every mesh in the given subtree into single set of vertices and
+    // triangles, relative to the root. Scale is baked into vertices.
+    fn collect_mesh_geometry(
+        root: Handle<Node>,
+        graph: &Graph,
+    ) -> (Vec<Point3<f32>>, Vec<[u32; 3]>) {
         let mut mesh_builder = RawMeshBuilder::new(0, 0);
 
         // Create inverse transform that will discard rotation and translation, but leave scaling and
@@ -584,6 +1060,14 @@ impl Physics {
             .map(|t| [t.0[0], t.0[1], t.0[2]])
             .collect::<Vec<_>>();
 
+        (vertices, indices)
+    }
+
+    /// Creates new trimesh collider shape from given mesh node. It also bakes scale into
+    /// vertices of trimesh because rapier does not support collider scaling yet.
+    pub fn make_trimesh(root: Handle<Node>, graph: &Graph) -> SharedShape {
+        let (vertices, indices) = Self::collect_mesh_geometry(root, graph);
+
         if indices.is_empty() {
             Log::writeln(
                 MessageKind::Warning,
@@ -599,6 +1083,78 @@ impl Physics {
         }
     }
 
+    /// Creates new convex hull collider shape from given mesh node subtree. Unlike trimesh,
+    /// convex hull can be used on dynamic rigid bodies. Scale is baked into vertices of the
+    /// hull. Returns `None` if the subtree has no geometry or its vertices are degenerate.
+    pub fn make_convex_hull(root: Handle<Node>, graph: &Graph) -> Option<SharedShape> {
+        let (vertices, _) = Self::collect_mesh_geometry(root, graph);
+
+        let shape = convex_hull_shape(&vertices);
+        if shape.is_none() {
+            Log::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Failed to create convex hull collider for {}, its geometry is degenerate!",
+                    graph[root].name()
+                ),
+            );
+        }
+        shape
+    }
+
+    /// Creates new compound collider shape from given mesh node subtree, using approximate
+    /// convex decomposition (V-HACD). Every part of the compound is a convex hull, so the shape
+    /// can be used on dynamic rigid bodies while still following concave geometry of the mesh.
+    /// Scale is baked into vertices. Returns `None` if the subtree has no geometry or no
+    /// convex part could be built.
+    ///
+    /// # Performance
+    ///
+    /// Decomposition is expensive, its cost heavily depends on `params.resolution`. Prefer
+    /// doing it once in the editor and saving the scene - the result is serialized.
+    pub fn make_convex_decomposition(
+        root: Handle<Node>,
+        graph: &Graph,
+        params: &VHACDParameters,
+    ) -> Option<SharedShape> {
+        let (vertices, indices) = Self::collect_mesh_geometry(root, graph);
+
+        if indices.is_empty() {
+            Log::writeln(
+                MessageKind::Warning,
+                format!(
+                    "Failed to create convex decomposition for {}, it has no vertices!",
+                    graph[root].name()
+                ),
+            );
+
+            None
+        } else {
+            // Some parts of decomposition may be empty or degenerate, skip them instead of
+            // building broken convex polyhedra.
+            let parts = VHACD::decompose(params, &vertices, &indices, true)
+                .compute_exact_convex_hulls(&vertices, &indices)
+                .into_iter()
+                .filter_map(|(vertices, indices)| convex_mesh_shape(vertices, &indices))
+                .map(|part| (Isometry3::identity(), part))
+                .collect::<Vec<_>>();
+
+            if parts.is_empty() {
+                Log::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Failed to create convex decomposition for {}, its geometry is degenerate!",
+                        graph[root].name()
+                    ),
+                );
+
+                None
+            } else {
+                Some(SharedShape::compound(parts))
+            }
+        }
+    }
+
     /// Creates height field shape from given terrain.
     pub fn make_heightfield(terrain: &Terrain) -> SharedShape {
         assert!(!terrain.chunks_ref().is_empty());
@@ -669,6 +1225,52 @@ impl Physics {
         handle
     }
 
+    /// Small helper that creates dynamic rigid body with convex hull collider of given mesh
+    /// node subtree. Body is placed at global position of the root, so it can be bound to the
+    /// root using physics binder. Returns `None` if convex hull can't be built.
+    pub fn mesh_to_convex_hull(
+        &mut self,
+        root: Handle<Node>,
+        graph: &Graph,
+    ) -> Option<RigidBodyHandle> {
+        let shape = Self::make_convex_hull(root, graph)?;
+        Some(self.add_dynamic_body_with_shape(root, graph, shape))
+    }
+
+    /// Small helper that creates dynamic rigid body with compound collider made from convex
+    /// decomposition of given mesh node subtree. See [`Self::make_convex_decomposition`] for
+    /// more info.
+    pub fn mesh_to_convex_decomposition(
+        &mut self,
+        root: Handle<Node>,
+        graph: &Graph,
+        params: &VHACDParameters,
+    ) -> Option<RigidBodyHandle> {
+        let shape = Self::make_convex_decomposition(root, graph, params)?;
+        Some(self.add_dynamic_body_with_shape(root, graph, shape))
+    }
+
+    fn add_dynamic_body_with_shape(
+        &mut self,
+        root: Handle<Node>,
+        graph: &Graph,
+        shape: SharedShape,
+    ) -> RigidBodyHandle {
+        let collider = ColliderBuilder::new(shape).build();
+        let (global_rotation, global_position) = graph.isometric_global_rotation_position(root);
+        let body = RigidBodyBuilder::new(RigidBodyType::Dynamic)
+            .position(Isometry3 {
+                rotation: global_rotation,
+                translation: Translation {
+                    vector: global_position,
+                },
+            })
+            .build();
+        let handle = self.add_body(body);
+        self.add_collider(collider, &handle);
+        handle
+    }
+
     /// Creates new height field collider from given terrain scene node.
     pub fn terrain_to_heightfield_collider(
         &mut self,
@@ -714,14 +1316,7 @@ impl Physics {
     pub fn cast_ray<S: QueryResultsStorage>(&self, opts: RayCastOptions, query_buffer: &mut S) {
         let time = instant::Instant::now();
 
-        let mut query = self.query.borrow_mut();
-
-        // TODO: Ideally this must be called once per frame, but it seems to be impossible because
-        // a body can be deleted during the consecutive calls of this method which will most
-        // likely end up in panic because of invalid handle stored in internal acceleration
-        // structure. This could be fixed by delaying deleting of bodies/collider to the end
-        // of the frame.
-        query.update(&self.islands, &self.bodies.set, &self.colliders.set);
+        let query = self.updated_query();
 
         query_buffer.clear();
         let ray = rapier3d::geometry::Ray::new(
@@ -771,20 +1366,147 @@ impl Physics {
         );
     }
 
+    fn updated_query(&self) -> RefMut<QueryPipeline> {
+        let mut query = self.query.borrow_mut();
+
+        // TODO: Ideally this must be called once per frame, but it seems to be impossible because
+        // a body can be deleted during the consecutive calls of this method which will most
+        // likely end up in panic because of invalid handle stored in internal acceleration
+        // structure. This could be fixed by delaying deleting of bodies/collider to the end
+        // of the frame.
+        query.update(&self.islands, &self.bodies.set, &self.colliders.set);
+
+        query
+    }
+
+    fn collider_handle_of(
+        &self,
+        handle: rapier3d::geometry::ColliderHandle,
+    ) -> Option<ColliderHandle> {
+        self.colliders.handle_map().key_of(&handle).cloned()
+    }
+
+    /// Sweeps a shape along given direction and returns first collider that was hit, if any.
+    pub fn cast_shape(&self, opts: ShapeCastOptions) -> Option<ShapeCastResult> {
+        let query = self.updated_query();
+
+        let direction = opts
+            .direction
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_default();
+        let excluded = opts
+            .exclude
+            .and_then(|exclude| self.colliders.handle_map().value_of(&exclude).cloned());
+        let filter = |handle: rapier3d::geometry::ColliderHandle| Some(handle) != excluded;
+        let (handle, toi) = query.cast_shape(
+            &self.colliders.set,
+            &opts.position,
+            &direction,
+            opts.shape,
+            opts.max_len,
+            opts.groups,
+            Some(&filter),
+        )?;
+
+        // Query pipeline transforms witness and normal of the collider that was hit to world
+        // coordinates.
+        Some(ShapeCastResult {
+            collider: self.collider_handle_of(handle)?,
+            toi: toi.toi,
+            position: toi.witness1,
+            normal: toi.normal1.into_inner(),
+        })
+    }
+
+    /// Collects every collider that intersects given shape at given position into the
+    /// storage.
+    pub fn intersections_with_shape<S: ColliderQueryStorage>(
+        &self,
+        shape: &dyn Shape,
+        position: &Isometry3<f32>,
+        groups: InteractionGroups,
+        query_buffer: &mut S,
+    ) {
+        let query = self.updated_query();
+
+        query_buffer.clear();
+        query.intersections_with_shape(
+            &self.colliders.set,
+            position,
+            shape,
+            groups,
+            None,
+            |handle| match self.collider_handle_of(handle) {
+                Some(handle) => query_buffer.push(handle),
+                None => true,
+            },
+        );
+    }
+
+    /// Collects every collider that contains given point (in world coordinates) into the
+    /// storage.
+    pub fn intersections_with_point<S: ColliderQueryStorage>(
+        &self,
+        point: &Point3<f32>,
+        groups: InteractionGroups,
+        query_buffer: &mut S,
+    ) {
+        let query = self.updated_query();
+
+        query_buffer.clear();
+        query.intersections_with_point(&self.colliders.set, point, groups, None, |handle| {
+            match self.collider_handle_of(handle) {
+                Some(handle) => query_buffer.push(handle),
+                None => true,
+            }
+        });
+    }
+
+    /// Projects a point (in world coordinates) on the closest collider. If `solid` is true,
+    /// points inside of colliders are not projected on their surfaces but returned as is.
+    pub fn project_point(
+        &self,
+        point: &Point3<f32>,
+        solid: bool,
+        groups: InteractionGroups,
+    ) -> Option<PointProjection> {
+        let query = self.updated_query();
+
+        let (handle, projection) =
+            query.project_point(&self.colliders.set, point, solid, groups, None)?;
+        Some(PointProjection {
+            collider: self.collider_handle_of(handle)?,
+            position: projection.point,
+            is_inside: projection.is_inside,
+        })
+    }
+
     pub(in crate) fn resolve(&mut self, binder: &PhysicsBinder<Node>, graph: &Graph) {
         assert_eq!(self.bodies.len(), 0);
         assert_eq!(self.colliders.len(), 0);
 
         let mut phys_desc = self.desc.take().unwrap();
 
+        let solver_state_restored = self.resolve_solver_state(&phys_desc, binder, graph);
+
+        self.integration_parameters = phys_desc.integration_parameters.into();
+        self.gravity = phys_desc.gravity;
+
+        if solver_state_restored {
+            return;
+        }
+
         self.bodies.handle_map = phys_desc.body_handle_map;
         self.colliders.handle_map = phys_desc.collider_handle_map;
         self.joints.handle_map = phys_desc.joint_handle_map;
 
-        self.integration_parameters = phys_desc.integration_parameters.into();
-
+        let mut mass_properties = Vec::new();
         for desc in phys_desc.bodies.drain(..) {
-            self.bodies.set.insert(desc.convert_to_body());
+            let props = desc.mass_properties.clone();
+            let handle = self.bodies.set.insert(desc.convert_to_body());
+            if let Some(props) = props {
+                mass_properties.push((handle, props));
+            }
         }
 
         for desc in phys_desc.colliders.drain(..) {
@@ -887,6 +1609,14 @@ impl Physics {
             }
         }
 
+        // Colliders are changing mass properties of bodies when attached, so exact mass
+        // properties must be restored after all colliders were added.
+        for (handle, props) in mass_properties {
+            if let Some(body) = self.bodies.set.get_mut(handle) {
+                body.set_mass_properties(props.into(), false);
+            }
+        }
+
         for desc in phys_desc.joints.drain(..) {
             let b1 = self
                 .bodies
@@ -900,7 +1630,10 @@ impl Physics {
                 .value_of(&desc.body2)
                 .cloned()
                 .unwrap();
-            self.joints.set.insert(b1, b2, desc.params);
+            let handle = self.joints.set.insert(b1, b2, desc.params);
+            if let Some(handle) = self.joints.handle_map.key_of(&handle).cloned() {
+                self.joints.set_limits(&handle, desc.limits);
+            }
         }
     }
 
@@ -918,12 +1651,17 @@ impl Physics {
         let mut link = ResourceLink::default();
 
         // Instantiate rigid bodies.
+        let mut mass_properties = Vec::new();
         for (resource_handle, body) in resource_physics.bodies.set.iter() {
             let desc = RigidBodyDesc::<ColliderHandle>::from_body(
                 body,
                 &resource_physics.colliders.handle_map(),
             );
+            let props = desc.mass_properties.clone();
             let new_handle = self.add_body(desc.convert_to_body());
+            if let Some(props) = props {
+                mass_properties.push((new_handle, props));
+            }
 
             link.bodies.insert(
                 resource_physics
@@ -1040,6 +1778,13 @@ impl Physics {
             }
         }
 
+        // Restore exact mass properties, they were changed when colliders were attached.
+        for (handle, props) in mass_properties {
+            if let Some(body) = self.bodies.get_mut(&handle) {
+                body.set_mass_properties(props.into(), false);
+            }
+        }
+
         // Instantiate joints.
         for (resource_handle, joint) in resource_physics.joints.set.iter() {
             let desc = JointDesc::<RigidBodyHandle>::from_joint(
@@ -1055,6 +1800,15 @@ impl Physics {
                 .get(self.bodies.handle_map().key_of(&joint.body2).unwrap())
                 .unwrap();
             let new_handle = self.add_joint(new_body1_handle, new_body2_handle, desc.params);
+            let resource_joint_handle = resource_physics
+                .joints
+                .handle_map
+                .key_of(&resource_handle)
+                .unwrap();
+            self.joints.set_limits(
+                &new_handle,
+                resource_physics.joints.limits(resource_joint_handle),
+            );
             link.joints.insert(
                 *resource_physics
                     .joints
@@ -1136,7 +1890,7 @@ impl Visit for Physics {
         } else if let Some(desc) = self.desc.as_ref() {
             desc.clone()
         } else {
-            self.generate_desc()
+            self.generate_desc_for_saving()
         };
         desc.visit("Desc", visitor)?;
 
@@ -1151,3 +1905,519 @@ impl Visit for Physics {
         visitor.leave_region()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::pool::Handle,
+        engine::{ColliderHandle, PhysicsBinder, RigidBodyHandle},
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            mesh::{
+                surface::{Surface, SurfaceData},
+                MeshBuilder,
+            },
+            node::Node,
+            physics::{Physics, ShapeCastOptions},
+            transform::TransformBuilder,
+        },
+    };
+    use rapier3d::{
+        dynamics::{RigidBodyBuilder, RigidBodyType},
+        geometry::{Ball, ColliderBuilder, Cuboid, InteractionGroups},
+        na::{Isometry3, Matrix4, Point3, UnitQuaternion, Vector3},
+        parry::transformation::vhacd::VHACDParameters,
+    };
+    use std::sync::{Arc, RwLock};
+
+    struct World {
+        physics: Physics,
+        a: ColliderHandle,
+        b: ColliderHandle,
+        c: ColliderHandle,
+    }
+
+    // Three unit cubes along X axis at 0, 3 and 6, the last one is in a separate group.
+    fn make_world() -> World {
+        let mut physics = Physics::new();
+        let mut add_cube = |x: f32, groups: InteractionGroups| {
+            let body = physics.add_body(
+                RigidBodyBuilder::new(RigidBodyType::Static)
+                    .translation(Vector3::new(x, 0.0, 0.0))
+                    .build(),
+            );
+            physics.add_collider(
+                ColliderBuilder::cuboid(0.5, 0.5, 0.5)
+                    .collision_groups(groups)
+                    .build(),
+                &body,
+            )
+        };
+        let a = add_cube(0.0, InteractionGroups::all());
+        let b = add_cube(3.0, InteractionGroups::all());
+        let c = add_cube(6.0, InteractionGroups::new(0b10, 0b10));
+        World { physics, a, b, c }
+    }
+
+    fn cast_ball(
+        world: &World,
+        x: f32,
+        direction: f32,
+        max_len: f32,
+        groups: InteractionGroups,
+        exclude: Option<ColliderHandle>,
+    ) -> Option<(ColliderHandle, f32, Point3<f32>, Vector3<f32>)> {
+        world
+            .physics
+            .cast_shape(ShapeCastOptions {
+                shape: &Ball::new(0.25),
+                position: Isometry3::translation(x, 0.0, 0.0),
+                direction: Vector3::new(direction, 0.0, 0.0),
+                max_len,
+                groups,
+                exclude,
+            })
+            .map(|result| (result.collider, result.toi, result.position, result.normal))
+    }
+
+    #[test]
+    fn cast_shape_returns_first_hit() {
+        let world = make_world();
+
+        // Direction does not need to be normalized.
+        let (collider, toi, position, normal) =
+            cast_ball(&world, -3.0, 2.0, 10.0, InteractionGroups::all(), None).unwrap();
+        assert_eq!(collider, world.a);
+        assert!((toi - 2.25).abs() < 1.0e-3);
+        assert!((position - Point3::new(-0.5, 0.0, 0.0)).norm() < 1.0e-3);
+        assert!((normal - Vector3::new(-1.0, 0.0, 0.0)).norm() < 1.0e-3);
+
+        assert!(cast_ball(&world, -3.0, 1.0, 2.0, InteractionGroups::all(), None).is_none());
+        assert!(cast_ball(&world, -3.0, -1.0, 10.0, InteractionGroups::all(), None).is_none());
+    }
+
+    #[test]
+    fn cast_shape_skips_excluded_collider() {
+        let world = make_world();
+
+        let (collider, toi, _, _) = cast_ball(
+            &world,
+            -3.0,
+            1.0,
+            10.0,
+            InteractionGroups::all(),
+            Some(world.a),
+        )
+        .unwrap();
+        assert_eq!(collider, world.b);
+        assert!((toi - 5.25).abs() < 1.0e-3);
+
+        // Cast from inside of the excluded collider.
+        let (collider, _, _, _) = cast_ball(
+            &world,
+            0.0,
+            1.0,
+            10.0,
+            InteractionGroups::all(),
+            Some(world.a),
+        )
+        .unwrap();
+        assert_eq!(collider, world.b);
+    }
+
+    #[test]
+    fn cast_shape_reports_contact_in_world_coordinates() {
+        let mut physics = Physics::new();
+        let center = Vector3::new(5.0, 1.0, 0.0);
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 30.0f32.to_radians());
+        let body = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .position(Isometry3::from_parts(center.into(), rotation))
+                .build(),
+        );
+        let collider = physics.add_collider(ColliderBuilder::cuboid(0.5, 0.5, 0.5).build(), &body);
+
+        let result = physics
+            .cast_shape(ShapeCastOptions {
+                shape: &Ball::new(0.25),
+                position: Isometry3::translation(0.0, 1.0, 0.0),
+                direction: Vector3::x(),
+                max_len: 10.0,
+                groups: InteractionGroups::all(),
+                exclude: None,
+            })
+            .unwrap();
+        assert_eq!(result.collider, collider);
+
+        let face_normal = rotation * -Vector3::x();
+        assert!((result.normal - face_normal).norm() < 1.0e-3);
+        assert!(((result.position.coords - center).dot(&face_normal) - 0.5).abs() < 1.0e-3);
+        let ball_center = Point3::new(result.toi, 1.0, 0.0);
+        assert!((result.position - (ball_center - face_normal * 0.25)).norm() < 1.0e-2);
+    }
+
+    #[test]
+    fn cast_shape_respects_groups() {
+        let world = make_world();
+
+        let (collider, _, _, _) =
+            cast_ball(&world, 9.0, -1.0, 10.0, InteractionGroups::all(), None).unwrap();
+        assert_eq!(collider, world.c);
+
+        let (collider, toi, _, _) =
+            cast_ball(&world, 9.0, -1.0, 10.0, InteractionGroups::new(1, 1), None).unwrap();
+        assert_eq!(collider, world.b);
+        assert!((toi - 5.25).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn intersections_with_shape_collects_overlapping_colliders() {
+        let world = make_world();
+
+        // The buffer must be cleared before collecting.
+        let mut colliders = vec![ColliderHandle::default()];
+        world.physics.intersections_with_shape(
+            &Cuboid::new(Vector3::new(4.0, 0.1, 0.1)),
+            &Isometry3::translation(3.0, 0.0, 0.0),
+            InteractionGroups::all(),
+            &mut colliders,
+        );
+        colliders.sort();
+        let mut expected = vec![world.a, world.b, world.c];
+        expected.sort();
+        assert_eq!(colliders, expected);
+
+        world.physics.intersections_with_shape(
+            &Cuboid::new(Vector3::new(4.0, 0.1, 0.1)),
+            &Isometry3::translation(3.0, 0.0, 0.0),
+            InteractionGroups::new(1, 1),
+            &mut colliders,
+        );
+        assert_eq!(colliders.len(), 2);
+        assert!(!colliders.contains(&world.c));
+
+        world.physics.intersections_with_shape(
+            &Ball::new(0.5),
+            &Isometry3::translation(1.5, 0.0, 0.0),
+            InteractionGroups::all(),
+            &mut colliders,
+        );
+        assert!(colliders.is_empty());
+    }
+
+    #[test]
+    fn intersections_with_point_collects_containing_colliders() {
+        let world = make_world();
+
+        let mut colliders = Vec::new();
+        world.physics.intersections_with_point(
+            &Point3::new(3.2, 0.3, -0.3),
+            InteractionGroups::all(),
+            &mut colliders,
+        );
+        assert_eq!(colliders, vec![world.b]);
+
+        world.physics.intersections_with_point(
+            &Point3::new(6.0, 0.0, 0.0),
+            InteractionGroups::new(1, 1),
+            &mut colliders,
+        );
+        assert!(colliders.is_empty());
+
+        world.physics.intersections_with_point(
+            &Point3::new(1.5, 0.0, 0.0),
+            InteractionGroups::all(),
+            &mut colliders,
+        );
+        assert!(colliders.is_empty());
+    }
+
+    #[test]
+    fn project_point_finds_closest_collider() {
+        let world = make_world();
+
+        let projection = world
+            .physics
+            .project_point(&Point3::new(1.2, 0.2, 0.0), true, InteractionGroups::all())
+            .unwrap();
+        assert_eq!(projection.collider, world.a);
+        assert!(!projection.is_inside);
+        assert!((projection.position - Point3::new(0.5, 0.2, 0.0)).norm() < 1.0e-3);
+
+        // Solid projection returns inner points as is.
+        let inner = Point3::new(0.1, 0.0, 0.0);
+        let projection = world
+            .physics
+            .project_point(&inner, true, InteractionGroups::all())
+            .unwrap();
+        assert_eq!(projection.collider, world.a);
+        assert!(projection.is_inside);
+        assert!((projection.position - inner).norm() < 1.0e-3);
+
+        let projection = world
+            .physics
+            .project_point(&inner, false, InteractionGroups::all())
+            .unwrap();
+        assert!(projection.is_inside);
+        assert!((projection.position - Point3::new(0.5, 0.0, 0.0)).norm() < 1.0e-3);
+
+        let projection = world
+            .physics
+            .project_point(
+                &Point3::new(5.0, 0.0, 0.0),
+                true,
+                InteractionGroups::new(1, 1),
+            )
+            .unwrap();
+        assert_eq!(projection.collider, world.b);
+    }
+
+    fn unit_cube_mesh(base: BaseBuilder, graph: &mut Graph) -> Handle<Node> {
+        MeshBuilder::new(base)
+            .with_surfaces(vec![Surface::new(Arc::new(RwLock::new(
+                SurfaceData::make_cube(Matrix4::identity()),
+            )))])
+            .build(graph)
+    }
+
+    fn at(position: Vector3<f32>) -> BaseBuilder {
+        BaseBuilder::new().with_local_transform(
+            TransformBuilder::new()
+                .with_local_position(position)
+                .build(),
+        )
+    }
+
+    // Three unit cubes forming an "L" in XY plane: two along X and one on top of the first one.
+    // The root is placed at (0, 5, 0).
+    fn make_l_shape(graph: &mut Graph) -> Handle<Node> {
+        let right = unit_cube_mesh(at(Vector3::new(1.0, 0.0, 0.0)), graph);
+        let top = unit_cube_mesh(at(Vector3::new(0.0, 1.0, 0.0)), graph);
+        let root = unit_cube_mesh(
+            at(Vector3::new(0.0, 5.0, 0.0)).with_children(&[right, top]),
+            graph,
+        );
+        graph.update_hierarchical_data();
+        root
+    }
+
+    // Coarse voxelization is enough for boxes and keeps tests fast.
+    fn decomposition_params() -> VHACDParameters {
+        VHACDParameters {
+            resolution: 8,
+            ..Default::default()
+        }
+    }
+
+    fn add_ground(physics: &mut Physics) {
+        let ground = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        physics.add_collider(ColliderBuilder::cuboid(10.0, 0.1, 10.0).build(), &ground);
+    }
+
+    fn settle(physics: &mut Physics, body: &RigidBodyHandle) -> Isometry3<f32> {
+        for _ in 0..300 {
+            physics.step(&Default::default());
+        }
+        *physics.bodies.get(body).unwrap().position()
+    }
+
+    #[test]
+    fn convex_hull_bakes_scale_of_mesh() {
+        let mut graph = Graph::new();
+        let mesh = unit_cube_mesh(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(5.0, 1.0, -2.0))
+                    .with_local_rotation(UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.7))
+                    .with_local_scale(Vector3::new(2.0, 1.0, 3.0))
+                    .build(),
+            ),
+            &mut graph,
+        );
+        graph.update_hierarchical_data();
+
+        let shape = Physics::make_convex_hull(mesh, &graph).unwrap();
+        let hull = shape.as_convex_polyhedron().unwrap();
+        assert_eq!(hull.points().len(), 8);
+
+        // Rotation and translation belong to the body, only scale is baked.
+        let aabb = shape.compute_local_aabb();
+        assert!((aabb.mins - Point3::new(-1.0, -0.5, -1.5)).norm() < 1.0e-4);
+        assert!((aabb.maxs - Point3::new(1.0, 0.5, 1.5)).norm() < 1.0e-4);
+    }
+
+    #[test]
+    fn convex_hull_covers_whole_subtree() {
+        let mut graph = Graph::new();
+        let root = make_l_shape(&mut graph);
+
+        let shape = Physics::make_convex_hull(root, &graph).unwrap();
+        let aabb = shape.compute_local_aabb();
+        assert!((aabb.mins - Point3::new(-0.5, -0.5, -0.5)).norm() < 1.0e-4);
+        assert!((aabb.maxs - Point3::new(1.5, 1.5, 0.5)).norm() < 1.0e-4);
+
+        // Hull fills the notch of the "L".
+        assert!(shape.contains_local_point(&Point3::new(0.9, 0.9, 0.0)));
+        assert!(!shape.contains_local_point(&Point3::new(1.3, 1.3, 0.0)));
+    }
+
+    #[test]
+    fn convex_decomposition_follows_concave_mesh() {
+        let mut graph = Graph::new();
+        let root = make_l_shape(&mut graph);
+
+        let shape =
+            Physics::make_convex_decomposition(root, &graph, &decomposition_params()).unwrap();
+        let compound = shape.as_compound().unwrap();
+        assert!(compound.shapes().len() > 1);
+        assert!(compound
+            .shapes()
+            .iter()
+            .all(|(_, part)| part.as_convex_polyhedron().is_some()));
+
+        // Every cube of the "L" is covered, but the notch is not.
+        for &(x, y) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)].iter() {
+            assert!(shape.contains_local_point(&Point3::new(x, y, 0.0)));
+        }
+        assert!(!shape.contains_local_point(&Point3::new(0.9, 0.9, 0.0)));
+        let aabb = shape.compute_local_aabb();
+        assert!((aabb.mins - Point3::new(-0.5, -0.5, -0.5)).norm() < 0.1);
+        assert!((aabb.maxs - Point3::new(1.5, 1.5, 0.5)).norm() < 0.1);
+    }
+
+    #[test]
+    fn empty_subtree_has_no_convex_shapes() {
+        let mut graph = Graph::new();
+        let pivot = BaseBuilder::new().build(&mut graph);
+        graph.update_hierarchical_data();
+
+        assert!(Physics::make_convex_hull(pivot, &graph).is_none());
+        assert!(
+            Physics::make_convex_decomposition(pivot, &graph, &decomposition_params()).is_none()
+        );
+
+        let mut physics = Physics::new();
+        assert!(physics.mesh_to_convex_hull(pivot, &graph).is_none());
+        assert!(physics
+            .mesh_to_convex_decomposition(pivot, &graph, &decomposition_params())
+            .is_none());
+        assert_eq!(physics.bodies.len(), 0);
+    }
+
+    #[test]
+    fn mesh_to_convex_hull_creates_dynamic_body_at_mesh() {
+        let mut graph = Graph::new();
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.5);
+        let mesh = unit_cube_mesh(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(1.0, 3.0, 2.0))
+                    .with_local_rotation(rotation)
+                    .build(),
+            ),
+            &mut graph,
+        );
+        graph.update_hierarchical_data();
+
+        let mut physics = Physics::new();
+        add_ground(&mut physics);
+        let handle = physics.mesh_to_convex_hull(mesh, &graph).unwrap();
+
+        let body = physics.bodies.get(&handle).unwrap();
+        assert!(body.is_dynamic());
+        assert_eq!(
+            body.position().translation.vector,
+            Vector3::new(1.0, 3.0, 2.0)
+        );
+        assert!(body.position().rotation.angle_to(&rotation) < 1.0e-4);
+        assert!(body.mass() > 0.0);
+        assert_eq!(body.colliders().len(), 1);
+        let collider = physics.colliders.native_ref(body.colliders()[0]).unwrap();
+        assert!(collider.shape().as_convex_polyhedron().is_some());
+
+        // Cube falls and rests on the ground.
+        let position = settle(&mut physics, &handle);
+        assert!((position.translation.y - 0.6).abs() < 0.05);
+        assert!((position.translation.x - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn mesh_to_convex_decomposition_creates_dynamic_compound_body() {
+        let mut graph = Graph::new();
+        let root = make_l_shape(&mut graph);
+
+        let mut physics = Physics::new();
+        add_ground(&mut physics);
+        let handle = physics
+            .mesh_to_convex_decomposition(root, &graph, &decomposition_params())
+            .unwrap();
+
+        let body = physics.bodies.get(&handle).unwrap();
+        assert!(body.is_dynamic());
+        assert_eq!(
+            body.position().translation.vector,
+            Vector3::new(0.0, 5.0, 0.0)
+        );
+        assert!(body.mass() > 0.0);
+        let collider = physics.colliders.native_ref(body.colliders()[0]).unwrap();
+        assert!(collider.shape().as_compound().is_some());
+
+        // The "L" stands on its long side.
+        let position = settle(&mut physics, &handle);
+        assert!((position.translation.y - 0.6).abs() < 0.05);
+        assert!(position.rotation.angle() < 0.05);
+    }
+
+    #[test]
+    fn trimesh_is_rebuilt_from_scene_when_solver_state_is_restored() {
+        let mut graph = Graph::new();
+        let floor = unit_cube_mesh(BaseBuilder::new(), &mut graph);
+        graph.update_hierarchical_data();
+
+        let mut physics = Physics::new();
+        let mut binder = PhysicsBinder::default();
+        let floor_body = physics.mesh_to_trimesh(floor, &graph);
+        binder.bind(floor, floor_body);
+        let ball = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(0.0, 1.0, 0.0))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::ball(0.5).build(), &ball);
+        for _ in 0..30 {
+            physics.step(&Default::default());
+        }
+
+        let desc = physics.generate_desc_for_saving();
+        assert!(!desc.solver_state.is_empty());
+        let mut loaded = Physics::new();
+        loaded.desc = Some(desc);
+        loaded.resolve(&binder, &graph);
+
+        // Geometry is taken from the mesh, the rest of properties are restored exactly.
+        let collider_of = |physics: &Physics| {
+            let body = physics.bodies.get(&floor_body).unwrap();
+            physics
+                .colliders
+                .native_ref(body.colliders()[0])
+                .unwrap()
+                .clone()
+        };
+        let original = collider_of(&physics);
+        let restored = collider_of(&loaded);
+        assert_eq!(
+            restored.shape().as_trimesh().unwrap().indices(),
+            original.shape().as_trimesh().unwrap().indices()
+        );
+        assert_eq!(restored.friction(), 0.0);
+
+        for _ in 0..30 {
+            physics.step(&Default::default());
+            loaded.step(&Default::default());
+        }
+        let position =
+            |physics: &Physics| physics.bodies.get(&ball).unwrap().position().translation;
+        assert!((position(&physics).vector - position(&loaded).vector).norm() < 1.0e-3);
+    }
+}
@@ -0,0 +1,653 @@
+//! Ragdoll generation from bone hierarchies.
+//!
+//! # Overview
+//!
+//! Ragdoll is a set of rigid bodies connected with joints that follows bones of a skinned
+//! character. [`RagdollBuilder`] generates it from a bone hierarchy using a simple mapping -
+//! a list of bone names with radii of colliders and joint settings. Each mapped bone gets a
+//! dynamic rigid body with a capsule collider that spans from the bone to its first mapped
+//! child (or to its first child if there is no mapped children, or a ball if the bone is a
+//! leaf). Each mapped bone, except the first one, is connected with its closest mapped
+//! ancestor using ball (with cone limit) or revolute (with angle limits) joint.
+//!
+//! Bodies, colliders and joints are usual physics entities, so they are saved together with
+//! the rest of physics world. [`Ragdoll`] itself only holds handles and could be saved using
+//! [`Visit`] trait.
+//!
+//! # Joint limits
+//!
+//! Ball joints get cone limits and revolute joints get angle limits, see
+//! [`JointLimits`](super::joint::JointLimits). Limits are stored in physics world and are
+//! enforced by it after each simulation step, so they hold even on strong impacts. Use
+//! `physics.joints.limits(joint)` to get limits of a bone joint.
+//!
+//! # Blending
+//!
+//! Ragdoll has two modes: when it is not simulated, bodies are kinematic and follow animated
+//! pose of bones, when it is simulated bodies are dynamic and bones follow them. Transition
+//! between modes could be smoothed using [`Ragdoll::weight`], that blends animated pose of
+//! bones with simulated one. [`Ragdoll::update`] must be called every frame after scene
+//! update.
+//!
+//! # Collisions
+//!
+//! Capsules of neighbour bones overlap at joints, so ragdoll colliders by default are put in
+//! the last collision group, which is excluded from their filter. Change it using
+//! [`RagdollBuilder::with_collision_groups`] if needed.
+
+use crate::{
+    core::{
+        algebra::{Isometry3, Point3, Translation3, Unit, UnitQuaternion, Vector3},
+        pool::Handle,
+        visitor::prelude::*,
+    },
+    engine::{ColliderHandle, JointHandle, RigidBodyHandle},
+    scene::{
+        graph::Graph,
+        node::Node,
+        physics::{
+            joint::{revolute_angle, JointLimits},
+            Physics,
+        },
+    },
+};
+use rapier3d::{
+    dynamics::{BallJoint, JointParams, RevoluteJoint, RigidBodyBuilder, RigidBodyType},
+    geometry::{ColliderBuilder, InteractionGroups, SharedShape},
+};
+use std::collections::HashMap;
+
+/// A joint that connects a bone with its parent bone.
+#[derive(Copy, Clone, Debug)]
+pub enum RagdollJoint {
+    /// Ball joint, the bone can rotate freely around the joint, but direction of the bone can
+    /// deviate from its initial direction by `cone_angle` radians at most.
+    Ball {
+        /// Maximum angle (in radians) between initial and current direction of the bone.
+        cone_angle: f32,
+    },
+
+    /// Hinge joint, the bone can rotate only around given axis. Elbows and knees usually use
+    /// this joint.
+    Revolute {
+        /// Rotation axis in local coordinates of the bone.
+        axis: Vector3<f32>,
+        /// Minimum rotation angle in radians.
+        min_angle: f32,
+        /// Maximum rotation angle in radians.
+        max_angle: f32,
+    },
+}
+
+/// Mapping of a bone to a ragdoll part.
+#[derive(Clone, Debug)]
+pub struct RagdollBoneDefinition {
+    /// Name of the bone node.
+    pub name: String,
+    /// Radius of the collider of the bone.
+    pub radius: f32,
+    /// Joint that connects the bone with its parent bone, ignored for the root bone.
+    pub joint: RagdollJoint,
+}
+
+impl RagdollBoneDefinition {
+    /// Creates new bone definition.
+    pub fn new<S: AsRef<str>>(name: S, radius: f32, joint: RagdollJoint) -> Self {
+        Self {
+            name: name.as_ref().to_owned(),
+            radius,
+            joint,
+        }
+    }
+}
+
+/// A bone of a ragdoll.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct RagdollBone {
+    /// Handle of the bone node.
+    pub node: Handle<Node>,
+    /// Rigid body of the bone.
+    pub body: RigidBodyHandle,
+    /// Collider of the bone.
+    pub collider: ColliderHandle,
+    /// Joint that connects the bone with its parent bone, `None` for the root bone.
+    pub joint: Option<JointHandle>,
+}
+
+/// See module docs.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct Ragdoll {
+    bones: Vec<RagdollBone>,
+    simulated: bool,
+    /// Blend factor between animated (0.0) and simulated (1.0) pose of bones. It is used only
+    /// when the ragdoll is simulated.
+    pub weight: f32,
+}
+
+fn isometry(rotation: UnitQuaternion<f32>, position: Vector3<f32>) -> Isometry3<f32> {
+    Isometry3 {
+        translation: Translation3 { vector: position },
+        rotation,
+    }
+}
+
+fn bone_isometry(graph: &Graph, bone: Handle<Node>) -> Isometry3<f32> {
+    let (rotation, position) = graph.isometric_global_rotation_position(bone);
+    isometry(rotation, position)
+}
+
+impl Ragdoll {
+    /// Returns bones of the ragdoll, parents are always stored before their children.
+    pub fn bones(&self) -> &[RagdollBone] {
+        &self.bones
+    }
+
+    /// Returns true if bones follow simulated bodies.
+    pub fn is_simulated(&self) -> bool {
+        self.simulated
+    }
+
+    /// Switches the ragdoll between animated and simulated modes. Bodies keep velocities they
+    /// had while followed animation, so the transition is seamless.
+    pub fn set_simulated(&mut self, physics: &mut Physics, simulated: bool) {
+        self.simulated = simulated;
+        let body_type = if simulated {
+            RigidBodyType::Dynamic
+        } else {
+            RigidBodyType::KinematicPositionBased
+        };
+        for bone in self.bones.iter() {
+            if let Some(body) = physics.bodies.get_mut(&bone.body) {
+                body.set_body_type(body_type);
+                body.wake_up(true);
+            }
+        }
+    }
+
+    /// Synchronizes bones and bodies, must be called every frame after scene update. When the
+    /// ragdoll is not simulated, bodies are moved to animated pose of bones. Otherwise bones
+    /// are moved to blended pose and global transforms of the graph are recalculated.
+    pub fn update(&self, graph: &mut Graph, physics: &mut Physics) {
+        if !self.simulated {
+            for bone in self.bones.iter() {
+                let animated = bone_isometry(graph, bone.node);
+                if let Some(body) = physics.bodies.get_mut(&bone.body) {
+                    body.set_next_kinematic_position(animated);
+                }
+            }
+            return;
+        }
+
+        let weight = self.weight.clamp(0.0, 1.0);
+        let mut blended = HashMap::<Handle<Node>, Isometry3<f32>>::new();
+        for bone in self.bones.iter() {
+            let body_position = match physics.bodies.get(&bone.body) {
+                Some(body) => *body.position(),
+                None => continue,
+            };
+
+            let animated = bone_isometry(graph, bone.node);
+            let rotation = animated.rotation.slerp(&body_position.rotation, weight);
+            let position = animated
+                .translation
+                .vector
+                .lerp(&body_position.translation.vector, weight);
+
+            // Convert world pose to local transform of the bone, parent can be already moved.
+            let parent = graph[bone.node].parent();
+            let (parent_rotation, parent_transform) = if parent.is_none() {
+                (UnitQuaternion::identity(), None)
+            } else if let Some(parent_isometry) = blended.get(&parent) {
+                (
+                    parent_isometry.rotation,
+                    Some(parent_isometry.to_homogeneous() * graph.global_scale_matrix(parent)),
+                )
+            } else {
+                (
+                    graph.isometric_global_rotation(parent),
+                    Some(graph[parent].global_transform()),
+                )
+            };
+
+            let local_position = parent_transform
+                .and_then(|m| m.try_inverse())
+                .map(|inv| inv.transform_point(&Point3::from(position)).coords)
+                .unwrap_or(position);
+
+            let transform = graph[bone.node].local_transform_mut();
+            let local_rotation = (**transform.pre_rotation()).inverse()
+                * parent_rotation.inverse()
+                * rotation
+                * (**transform.post_rotation()).inverse();
+            transform
+                .set_position(local_position)
+                .set_rotation(local_rotation);
+
+            blended.insert(bone.node, isometry(rotation, position));
+        }
+
+        graph.update_hierarchical_data();
+    }
+
+    /// Removes bodies, colliders and joints of the ragdoll from physics world.
+    pub fn remove(self, physics: &mut Physics) {
+        for bone in self.bones {
+            physics.remove_body(&bone.body);
+        }
+    }
+}
+
+/// Creates ragdoll from a bone hierarchy, see module docs for more info.
+pub struct RagdollBuilder {
+    root: Handle<Node>,
+    bones: Vec<RagdollBoneDefinition>,
+    collision_groups: InteractionGroups,
+    simulated: bool,
+}
+
+impl RagdollBuilder {
+    /// Creates new builder, bones will be searched in the hierarchy starting from given root.
+    pub fn new(root: Handle<Node>) -> Self {
+        Self {
+            root,
+            bones: Default::default(),
+            collision_groups: InteractionGroups::new(1 << 15, !(1 << 15)),
+            simulated: false,
+        }
+    }
+
+    /// Adds a bone to the mapping.
+    pub fn with_bone(mut self, bone: RagdollBoneDefinition) -> Self {
+        self.bones.push(bone);
+        self
+    }
+
+    /// Sets the whole bone mapping.
+    pub fn with_bones(mut self, bones: Vec<RagdollBoneDefinition>) -> Self {
+        self.bones = bones;
+        self
+    }
+
+    /// Sets collision groups of ragdoll colliders.
+    pub fn with_collision_groups(mut self, groups: InteractionGroups) -> Self {
+        self.collision_groups = groups;
+        self
+    }
+
+    /// Sets whether the ragdoll will be simulated right after creation or not.
+    pub fn with_simulated(mut self, simulated: bool) -> Self {
+        self.simulated = simulated;
+        self
+    }
+
+    /// Creates bodies, colliders and joints of the ragdoll. Global transforms of the graph must
+    /// be up to date. Bones that were not found in the hierarchy are ignored.
+    pub fn build(self, graph: &Graph, physics: &mut Physics) -> Ragdoll {
+        // Collect mapped bones in hierarchy order, so parents always go before children.
+        let mut mapped = Vec::new();
+        let mut stack = vec![self.root];
+        while let Some(handle) = stack.pop() {
+            let node = &graph[handle];
+            if let Some(definition) = self.bones.iter().find(|b| b.name == node.name()) {
+                mapped.push((handle, definition));
+            }
+            stack.extend(node.children().iter().rev());
+        }
+
+        let is_mapped = |handle: Handle<Node>| mapped.iter().any(|(h, _)| *h == handle);
+
+        let mapped_parent = |mut handle: Handle<Node>| loop {
+            handle = graph[handle].parent();
+            if handle.is_none() || handle == graph[self.root].parent() {
+                return None;
+            }
+            if is_mapped(handle) {
+                return Some(handle);
+            }
+        };
+
+        let mut ragdoll = Ragdoll::default();
+        let mut bodies = HashMap::new();
+        for (bone, definition) in mapped.iter() {
+            let pose = bone_isometry(graph, *bone);
+            let bone_position = pose.translation.vector;
+
+            // Capsule spans to first mapped child, or to any child if there is no mapped ones.
+            let end = mapped
+                .iter()
+                .find(|(child, _)| mapped_parent(*child) == Some(*bone))
+                .map(|(child, _)| *child)
+                .or_else(|| graph[*bone].children().first().cloned())
+                .map(|child| graph[child].global_position());
+
+            let mut body = RigidBodyBuilder::new(if self.simulated {
+                RigidBodyType::Dynamic
+            } else {
+                RigidBodyType::KinematicPositionBased
+            })
+            .position(pose)
+            .build();
+            body.wake_up(true);
+            let body = physics.add_body(body);
+
+            let local_end = end.map(|end| pose.inverse_transform_vector(&(end - bone_position)));
+            let shape = match local_end {
+                Some(local_end) if local_end.norm() > 2.0 * definition.radius => {
+                    let direction = local_end.normalize();
+                    SharedShape::capsule(
+                        Point3::from(direction * definition.radius),
+                        Point3::from(local_end - direction * definition.radius),
+                        definition.radius,
+                    )
+                }
+                _ => SharedShape::ball(definition.radius),
+            };
+            let collider = physics.add_collider(
+                ColliderBuilder::new(shape)
+                    .collision_groups(self.collision_groups)
+                    .build(),
+                &body,
+            );
+
+            let joint = mapped_parent(*bone).map(|parent| {
+                let parent_body = bodies[&parent];
+                let parent_isometry = bone_isometry(graph, parent);
+
+                // Initial direction of the bone, it is the reference axis of cone limit.
+                let direction = local_end
+                    .map(|local_end| pose.transform_vector(&local_end))
+                    .unwrap_or_else(|| bone_position - parent_isometry.translation.vector)
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(Vector3::y);
+
+                let anchor1 = parent_isometry.inverse_transform_point(&Point3::from(bone_position));
+                let (params, limits) = match definition.joint {
+                    RagdollJoint::Ball { cone_angle } => (
+                        JointParams::from(BallJoint::new(anchor1, Point3::origin())),
+                        JointLimits::Cone {
+                            axis1: parent_isometry.inverse_transform_vector(&direction),
+                            axis2: pose.inverse_transform_vector(&direction),
+                            angle: cone_angle,
+                        },
+                    ),
+                    RagdollJoint::Revolute {
+                        axis,
+                        min_angle,
+                        max_angle,
+                    } => {
+                        let world_axis = pose.transform_vector(&axis);
+                        let joint = RevoluteJoint::new(
+                            anchor1,
+                            Unit::new_normalize(
+                                parent_isometry.inverse_transform_vector(&world_axis),
+                            ),
+                            Point3::origin(),
+                            Unit::new_normalize(axis),
+                        );
+                        let limits = JointLimits::Angle {
+                            rest_angle: revolute_angle(&joint, &parent_isometry, &pose),
+                            min: min_angle,
+                            max: max_angle,
+                        };
+                        (JointParams::from(joint), limits)
+                    }
+                };
+
+                let joint = physics.add_joint(&parent_body, &body, params);
+                physics.joints.set_limits(&joint, limits);
+                joint
+            });
+
+            bodies.insert(*bone, body);
+            ragdoll.bones.push(RagdollBone {
+                node: *bone,
+                body,
+                collider,
+                joint,
+            });
+        }
+
+        ragdoll.simulated = self.simulated;
+        ragdoll.weight = 1.0;
+        ragdoll
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Isometry3, Vector3},
+            futures::executor::block_on,
+            pool::Handle,
+            visitor::prelude::*,
+        },
+        engine::{JointHandle, PhysicsBinder},
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            node::Node,
+            physics::{
+                joint::{revolute_angle, JointLimits},
+                ragdoll::{Ragdoll, RagdollBoneDefinition, RagdollBuilder, RagdollJoint},
+                Physics,
+            },
+            transform::TransformBuilder,
+        },
+    };
+    use rapier3d::dynamics::{JointParams, RigidBodyType};
+
+    // Arm that hangs down from (0, 2, 0): shoulder ball joint, elbow revolute joint and wrist
+    // ball joint.
+    fn make_arm(graph: &mut Graph, physics: &mut Physics, map_hand: bool) -> Ragdoll {
+        let bone = |graph: &mut Graph, name: &str, offset: f32, children: &[Handle<Node>]| {
+            BaseBuilder::new()
+                .with_name(name)
+                .with_children(children)
+                .with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_position(Vector3::new(0.0, offset, 0.0))
+                        .build(),
+                )
+                .build(graph)
+        };
+
+        let finger = bone(graph, "Finger", -0.2, &[]);
+        let hand = bone(graph, "Hand", -0.5, &[finger]);
+        let forearm = bone(graph, "ForeArm", -0.5, &[hand]);
+        let arm = bone(graph, "Arm", 2.0, &[forearm]);
+        graph.update_hierarchical_data();
+
+        let mut builder = RagdollBuilder::new(arm)
+            .with_bone(RagdollBoneDefinition::new(
+                "Arm",
+                0.1,
+                RagdollJoint::Ball { cone_angle: 1.0 },
+            ))
+            .with_bone(RagdollBoneDefinition::new(
+                "ForeArm",
+                0.1,
+                RagdollJoint::Revolute {
+                    axis: Vector3::x(),
+                    min_angle: 0.0,
+                    max_angle: 1.0,
+                },
+            ));
+        if map_hand {
+            builder = builder.with_bone(RagdollBoneDefinition::new(
+                "Hand",
+                0.05,
+                RagdollJoint::Ball { cone_angle: 0.3 },
+            ));
+        }
+        builder.build(graph, physics)
+    }
+
+    // Returns how much a joint exceeds its limits, zero if it is within the limits.
+    fn limit_violation(physics: &Physics, joint: &JointHandle, limits: JointLimits) -> f32 {
+        let joint = physics.joints.get(joint).unwrap();
+        let position =
+            |body| -> Isometry3<f32> { *physics.bodies.native_ref(body).unwrap().position() };
+        let (position1, position2) = (position(joint.body1), position(joint.body2));
+        match (limits, &joint.params) {
+            (
+                JointLimits::Cone {
+                    axis1,
+                    axis2,
+                    angle,
+                },
+                JointParams::BallJoint(_),
+            ) => ((position1 * axis1).angle(&(position2 * axis2)) - angle).max(0.0),
+            (
+                JointLimits::Angle {
+                    rest_angle,
+                    min,
+                    max,
+                },
+                JointParams::RevoluteJoint(revolute),
+            ) => {
+                let angle = revolute_angle(revolute, &position1, &position2) - rest_angle;
+                let angle = angle.sin().atan2(angle.cos());
+                (min - angle).max(angle - max).max(0.0)
+            }
+            _ => panic!("limits do not match the joint"),
+        }
+    }
+
+    fn joint_limits(physics: &Physics, ragdoll: &Ragdoll) -> Vec<(JointHandle, JointLimits)> {
+        ragdoll.bones()[1..]
+            .iter()
+            .map(|bone| {
+                let joint = bone.joint.unwrap();
+                (joint, physics.joints.limits(&joint))
+            })
+            .collect()
+    }
+
+    // Hangs the arm on static shoulder and hits the forearm and the hand in different
+    // directions, returns maximum violation of given limits.
+    fn hit_arm(
+        physics: &mut Physics,
+        ragdoll: &mut Ragdoll,
+        limits: &[(JointHandle, JointLimits)],
+    ) -> f32 {
+        ragdoll.set_simulated(physics, true);
+        physics
+            .bodies
+            .get_mut(&ragdoll.bones()[0].body)
+            .unwrap()
+            .set_body_type(RigidBodyType::Static);
+
+        let binder = PhysicsBinder::default();
+        let mut max_violation = 0.0f32;
+        for i in 0..240 {
+            if i % 30 == 0 {
+                let direction = match (i / 30) % 4 {
+                    0 => Vector3::z(),
+                    1 => -Vector3::z(),
+                    2 => Vector3::x(),
+                    _ => Vector3::new(1.0, 0.0, -1.0),
+                };
+                for bone in ragdoll.bones()[1..].iter() {
+                    let body = physics.bodies.get_mut(&bone.body).unwrap();
+                    let impulse = direction * body.mass() * 30.0;
+                    body.apply_impulse(impulse, true);
+                }
+            }
+
+            physics.step(&binder);
+
+            for (joint, limits) in limits.iter() {
+                max_violation = max_violation.max(limit_violation(physics, joint, *limits));
+            }
+        }
+        max_violation
+    }
+
+    #[test]
+    fn ragdoll_from_bone_chain() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let mut ragdoll = make_arm(&mut graph, &mut physics, false);
+        let arm = graph.find_by_name_from_root("Arm");
+        let forearm = graph.find_by_name_from_root("ForeArm");
+
+        // Hand is not mapped, so it only defines length of the forearm capsule.
+        assert_eq!(ragdoll.bones().len(), 2);
+        assert_eq!(ragdoll.bones()[0].node, arm);
+        assert!(ragdoll.bones()[0].joint.is_none());
+        assert_eq!(ragdoll.bones()[1].node, forearm);
+        assert!(ragdoll.bones()[1].joint.is_some());
+        assert_eq!(physics.bodies.len(), 2);
+        assert_eq!(physics.colliders.len(), 2);
+
+        assert!(matches!(
+            physics.joints.limits(&ragdoll.bones()[1].joint.unwrap()),
+            JointLimits::Angle { min, max, .. } if min == 0.0 && max == 1.0
+        ));
+
+        // Joints must survive serialization through descriptors.
+        let desc = physics.generate_desc();
+        assert_eq!(desc.joints.len(), 1);
+
+        ragdoll.set_simulated(&mut physics, true);
+        assert!(ragdoll.is_simulated());
+        ragdoll.update(&mut graph, &mut physics);
+        assert!((graph[forearm].global_position() - Vector3::new(0.0, 1.5, 0.0)).norm() < 1.0e-4);
+    }
+
+    #[test]
+    fn limits_hold_under_impacts() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let mut ragdoll = make_arm(&mut graph, &mut physics, true);
+        assert_eq!(ragdoll.bones().len(), 3);
+        let limits = joint_limits(&physics, &ragdoll);
+        assert!(hit_arm(&mut physics, &mut ragdoll, &limits) < 1.0e-3);
+
+        // The same impacts move free joints way out of the limits.
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let mut ragdoll = make_arm(&mut graph, &mut physics, true);
+        let limits = joint_limits(&physics, &ragdoll);
+        for (joint, _) in limits.iter() {
+            physics.joints.set_limits(joint, JointLimits::None);
+        }
+        assert!(hit_arm(&mut physics, &mut ragdoll, &limits) > 0.1);
+    }
+
+    #[test]
+    fn limits_are_saved_with_physics() {
+        let path = std::env::temp_dir().join(format!(
+            "rg3d_ragdoll_limits_test_{}.bin",
+            std::process::id()
+        ));
+
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let mut ragdoll = make_arm(&mut graph, &mut physics, true);
+
+        {
+            let mut visitor = Visitor::new();
+            physics.visit("Physics", &mut visitor).unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+
+        let mut loaded = Physics::new();
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            loaded.visit("Physics", &mut visitor).unwrap();
+        }
+        loaded.resolve(&Default::default(), &graph);
+        std::fs::remove_file(&path).unwrap();
+
+        let limits = joint_limits(&physics, &ragdoll);
+        for (joint, limits) in limits.iter() {
+            assert_ne!(*limits, JointLimits::None);
+            assert_eq!(loaded.joints.limits(joint), *limits);
+        }
+
+        // Loaded limits are enforced.
+        assert!(hit_arm(&mut loaded, &mut ragdoll, &limits) < 1.0e-3);
+    }
+}
@@ -0,0 +1,1037 @@
+//! Ropes and chains built from rigid bodies and joints.
+//!
+//! # Overview
+//!
+//! Rope is a chain of dynamic rigid bodies (segments) with capsule colliders, neighbour segments
+//! are connected with ball joints. [`RopeBuilder`] creates segments between two anchors, each
+//! anchor could be a rigid body, a scene node or a free point in space (a loose end). The rope
+//! is visualized by a tube mesh that is rebuilt from positions of segments in
+//! [`Rope::update`], so it must be called every frame.
+//!
+//! Bodies, colliders and joints are usual physics entities, so they are saved together with the
+//! rest of physics world, the tube mesh is saved together with the graph. [`Rope`] holds handles
+//! of these entities together with parameters of the tube and stiffness of joints, it should be
+//! saved using [`Visit`] trait next to the scene to continue updating the rope after loading.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::{algebra::Vector3, pool::Handle},
+//!     scene::{
+//!         node::Node,
+//!         physics::rope::{Rope, RopeAnchor, RopeBuilder},
+//!         Scene,
+//!     },
+//! };
+//!
+//! fn create_bridge(scene: &mut Scene, left: Handle<Node>, right: Handle<Node>) -> Rope {
+//!     RopeBuilder::new(RopeAnchor::Node(left), RopeAnchor::Node(right))
+//!         .with_segment_count(20)
+//!         .with_length(12.0)
+//!         .with_mass(5.0)
+//!         .build(&mut scene.graph, &mut scene.physics, &scene.physics_binder)
+//! }
+//! ```
+//!
+//! # Stiffness
+//!
+//! Ball joints do not resist bending, so a rope with zero stiffness behaves like a chain. When
+//! stiffness is non-zero, position motors of joints pull segments back to straight line, which
+//! makes the rope behave like a cable or a hose. Ends of such rope are clamped to anchor bodies,
+//! motors keep orientation of end segments relative to anchors. Stiffness is the maximum torque
+//! that each motor can apply, so the rope keeps its shape under small loads and bends under
+//! large ones. Motors are limited by impulse, so the limit is recalculated from time step of
+//! physics world in [`Rope::update`].
+//!
+//! Motors of a stiff rope have to transfer torque along the whole chain, which takes many solver
+//! iterations. With default integration parameters long stiff ropes are wobbly, increase
+//! `max_velocity_iterations` of [`Physics::integration_parameters`] if needed.
+//!
+//! # Important notes
+//!
+//! Nodes that are bound to rigid bodies are attached using their bodies, other nodes are
+//! followed by kinematic bodies that are moved in [`Rope::update`].
+//!
+//! Capsules of neighbour segments overlap at joints, so rope colliders by default are put in
+//! the 15th collision group, which is excluded from their filter. Change it using
+//! [`RopeBuilder::with_collision_groups`] if needed.
+
+use crate::{
+    core::{
+        algebra::{Isometry3, Point3, Translation3, UnitQuaternion, Vector2, Vector3, Vector4},
+        math::TriangleDefinition,
+        pool::Handle,
+        visitor::prelude::*,
+    },
+    engine::{ColliderHandle, JointHandle, PhysicsBinder, RigidBodyHandle},
+    scene::{
+        base::BaseBuilder,
+        graph::Graph,
+        mesh::{
+            buffer::{GeometryBuffer, VertexBuffer},
+            surface::{Surface, SurfaceData},
+            vertex::StaticVertex,
+            MeshBuilder,
+        },
+        node::Node,
+        physics::Physics,
+    },
+};
+use rapier3d::{
+    dynamics::{BallJoint, JointParams, RigidBodyBuilder, RigidBodyType},
+    geometry::{ColliderBuilder, InteractionGroups, SharedShape},
+};
+use std::sync::{Arc, RwLock};
+
+/// An end of a rope.
+#[derive(Copy, Clone, Debug)]
+pub enum RopeAnchor {
+    /// The end is not attached to anything and initially located at given point in world
+    /// coordinates.
+    Free(Vector3<f32>),
+
+    /// The end is attached to a rigid body.
+    Body {
+        /// Handle of the body.
+        body: RigidBodyHandle,
+        /// Attachment point in local coordinates of the body.
+        local_point: Vector3<f32>,
+    },
+
+    /// The end is attached to origin of a scene node.
+    Node(Handle<Node>),
+}
+
+/// A segment of a rope.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct RopeSegment {
+    /// Rigid body of the segment, its local Y axis is directed along the rope.
+    pub body: RigidBodyHandle,
+    /// Capsule collider of the segment.
+    pub collider: ColliderHandle,
+    /// Joint that connects the segment with previous segment or with the start anchor, `None`
+    /// if the start is free.
+    pub joint: Option<JointHandle>,
+    /// Half of the length of the segment.
+    pub half_length: f32,
+}
+
+/// A kinematic body that follows a scene node.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct RopeNodeAnchor {
+    /// Handle of the followed node.
+    pub node: Handle<Node>,
+    /// Kinematic body that follows the node.
+    pub body: RigidBodyHandle,
+}
+
+/// See module docs.
+#[derive(Clone, Debug, Default, Visit)]
+pub struct Rope {
+    segments: Vec<RopeSegment>,
+    end_joint: Option<JointHandle>,
+    node_anchors: Vec<RopeNodeAnchor>,
+    mesh: Handle<Node>,
+    radius: f32,
+    radial_segments: u32,
+    stiffness: f32,
+}
+
+/// Ratio of bending that is corrected at each step.
+const MOTOR_STIFFNESS: f32 = 0.2;
+/// Ratio of relative angular velocity of segments that is damped at each step.
+const MOTOR_DAMPING: f32 = 0.5;
+
+fn isometry(rotation: UnitQuaternion<f32>, position: Vector3<f32>) -> Isometry3<f32> {
+    Isometry3 {
+        translation: Translation3 { vector: position },
+        rotation,
+    }
+}
+
+fn node_isometry(graph: &Graph, node: Handle<Node>) -> Isometry3<f32> {
+    let (rotation, position) = graph.isometric_global_rotation_position(node);
+    isometry(rotation, position)
+}
+
+fn any_perpendicular(v: &Vector3<f32>) -> Vector3<f32> {
+    if v.x.abs() > 0.9 {
+        Vector3::y()
+    } else {
+        Vector3::x()
+    }
+    .cross(v)
+    .normalize()
+}
+
+/// Writes vertices of a tube that goes along given path.
+fn write_tube(
+    path: &[Vector3<f32>],
+    radius: f32,
+    radial_segments: usize,
+    out: &mut [StaticVertex],
+) {
+    let mut normal = Vector3::default();
+    let mut previous_tangent = Vector3::default();
+    let mut distance = 0.0;
+    for (i, point) in path.iter().enumerate() {
+        let next = path[(i + 1).min(path.len() - 1)];
+        let prev = path[i.saturating_sub(1)];
+        let tangent = (next - prev)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(previous_tangent);
+
+        // Parallel transport of the frame prevents tube from twisting.
+        normal = if i == 0 {
+            any_perpendicular(&tangent)
+        } else {
+            distance += (point - prev).norm();
+            UnitQuaternion::rotation_between(&previous_tangent, &tangent)
+                .map(|rotation| rotation * normal)
+                .unwrap_or(normal)
+        };
+        normal = (normal - tangent.scale(normal.dot(&tangent)))
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| any_perpendicular(&tangent));
+        let binormal = tangent.cross(&normal);
+        previous_tangent = tangent;
+
+        for k in 0..=radial_segments {
+            let (sin, cos) = (k as f32 / radial_segments as f32 * std::f32::consts::TAU).sin_cos();
+            let direction = normal.scale(cos) + binormal.scale(sin);
+            let around = binormal.scale(cos) - normal.scale(sin);
+            out[i * (radial_segments + 1) + k] = StaticVertex {
+                position: point + direction.scale(radius),
+                tex_coord: Vector2::new(
+                    k as f32 / radial_segments as f32,
+                    distance / (std::f32::consts::TAU * radius),
+                ),
+                normal: direction,
+                tangent: Vector4::new(around.x, around.y, around.z, 1.0),
+            };
+        }
+    }
+}
+
+fn make_tube(path: &[Vector3<f32>], radius: f32, radial_segments: usize) -> SurfaceData {
+    let ring = radial_segments + 1;
+    let mut vertices = vec![StaticVertex::default(); path.len() * ring];
+    write_tube(path, radius, radial_segments, &mut vertices);
+
+    let mut triangles = Vec::new();
+    for i in 0..path.len().saturating_sub(1) {
+        for k in 0..radial_segments {
+            let a = (i * ring + k) as u32;
+            let b = a + 1;
+            let c = a + ring as u32;
+            let d = c + 1;
+            triangles.push(TriangleDefinition([a, b, c]));
+            triangles.push(TriangleDefinition([b, d, c]));
+        }
+    }
+
+    SurfaceData::new(
+        VertexBuffer::new(vertices.len(), StaticVertex::layout(), vertices).unwrap(),
+        GeometryBuffer::new(triangles),
+        true,
+    )
+}
+
+impl Rope {
+    /// Returns segments of the rope, from the start to the end.
+    pub fn segments(&self) -> &[RopeSegment] {
+        &self.segments
+    }
+
+    /// Returns joint that connects the last segment with the end anchor, `None` if the end is
+    /// free.
+    pub fn end_joint(&self) -> Option<JointHandle> {
+        self.end_joint
+    }
+
+    /// Returns kinematic bodies that follow anchor nodes.
+    pub fn node_anchors(&self) -> &[RopeNodeAnchor] {
+        &self.node_anchors
+    }
+
+    /// Returns handle of the tube mesh.
+    pub fn mesh(&self) -> Handle<Node> {
+        self.mesh
+    }
+
+    /// Returns radius of colliders and of the tube mesh.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Returns amount of vertices around the tube mesh.
+    pub fn radial_segments(&self) -> usize {
+        self.radial_segments as usize
+    }
+
+    /// Returns bending stiffness of the rope, see module docs.
+    pub fn stiffness(&self) -> f32 {
+        self.stiffness
+    }
+
+    /// Returns points of the center line of the rope in world coordinates - ends of segments.
+    /// Returns `None` if a body of any segment does not exist.
+    pub fn path(&self, physics: &Physics) -> Option<Vec<Vector3<f32>>> {
+        let mut path = Vec::with_capacity(self.segments.len() + 1);
+        for segment in self.segments.iter() {
+            let position = physics.bodies.get(&segment.body)?.position();
+            path.push(
+                position
+                    .transform_point(&Point3::new(0.0, -segment.half_length, 0.0))
+                    .coords,
+            );
+            if path.len() == self.segments.len() {
+                path.push(
+                    position
+                        .transform_point(&Point3::new(0.0, segment.half_length, 0.0))
+                        .coords,
+                );
+            }
+        }
+        Some(path)
+    }
+
+    fn joints(&self) -> impl Iterator<Item = JointHandle> + '_ {
+        self.segments
+            .iter()
+            .filter_map(|segment| segment.joint)
+            .chain(self.end_joint)
+    }
+
+    /// Moves kinematic anchor bodies to their nodes, updates limits of joint motors and
+    /// rebuilds the tube mesh from positions of segments. Must be called every frame after
+    /// scene update.
+    pub fn update(&self, graph: &mut Graph, physics: &mut Physics) {
+        for anchor in self.node_anchors.iter() {
+            if graph.is_valid_handle(anchor.node) {
+                let position = node_isometry(graph, anchor.node);
+                if let Some(body) = physics.bodies.get_mut(&anchor.body) {
+                    body.set_next_kinematic_position(position);
+                }
+            }
+        }
+
+        if self.stiffness > 0.0 {
+            let max_impulse = self.stiffness * physics.integration_parameters.dt;
+            for handle in self.joints() {
+                if let Some(joint) = physics.joints.get_mut(&handle) {
+                    if let JointParams::BallJoint(ball) = &mut joint.params {
+                        ball.motor_max_impulse = max_impulse;
+                    }
+                }
+            }
+        }
+
+        let path = match self.path(physics) {
+            Some(path) => path,
+            None => return,
+        };
+        let radial_segments = self.radial_segments as usize;
+        if let Some(Node::Mesh(mesh)) = graph.try_get_mut(self.mesh) {
+            if let Some(surface) = mesh.surfaces().first() {
+                let data = surface.data();
+                let mut data = data.write().unwrap();
+                let written = data
+                    .vertex_buffer
+                    .modify()
+                    .cast_data_mut::<StaticVertex>()
+                    .ok()
+                    .filter(|vertices| vertices.len() == path.len() * (radial_segments + 1))
+                    .map(|vertices| write_tube(&path, self.radius, radial_segments, vertices))
+                    .is_some();
+                // Layout of the tube does not match the rope (for example the mesh was
+                // modified), so it is created from scratch.
+                if !written {
+                    *data = make_tube(&path, self.radius, radial_segments);
+                }
+            }
+            mesh.invalidate_bounding_box();
+        }
+    }
+
+    /// Removes bodies, colliders and joints of the rope from physics world and the tube mesh
+    /// from the graph. Bodies of anchors are kept, except kinematic bodies that follow nodes.
+    pub fn remove(self, graph: &mut Graph, physics: &mut Physics) {
+        for segment in self.segments {
+            physics.remove_body(&segment.body);
+        }
+        for anchor in self.node_anchors {
+            physics.remove_body(&anchor.body);
+        }
+        if graph.is_valid_handle(self.mesh) {
+            graph.remove_node(self.mesh);
+        }
+    }
+}
+
+/// Creates rope between two anchors, see module docs for more info.
+pub struct RopeBuilder {
+    start: RopeAnchor,
+    end: RopeAnchor,
+    segment_count: usize,
+    length: Option<f32>,
+    radius: f32,
+    mass: f32,
+    stiffness: f32,
+    radial_segments: usize,
+    collision_groups: InteractionGroups,
+    surface: Option<Surface>,
+}
+
+impl RopeBuilder {
+    /// Creates new builder for a rope between given anchors.
+    pub fn new(start: RopeAnchor, end: RopeAnchor) -> Self {
+        Self {
+            start,
+            end,
+            segment_count: 10,
+            length: None,
+            radius: 0.05,
+            mass: 1.0,
+            stiffness: 0.0,
+            radial_segments: 8,
+            collision_groups: InteractionGroups::new(1 << 14, !(1 << 14)),
+            surface: None,
+        }
+    }
+
+    /// Sets amount of segments. Default is 10.
+    pub fn with_segment_count(mut self, count: usize) -> Self {
+        self.segment_count = count.max(1);
+        self
+    }
+
+    /// Sets total length of the rope. If it is longer than distance between anchors, the rope
+    /// is created sagging down. Default is the distance between anchors.
+    pub fn with_length(mut self, length: f32) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// Sets radius of colliders and of the tube mesh. Default is 0.05.
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets total mass of the rope, it is evenly distributed between segments. Default is 1.0.
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    /// Sets bending stiffness of the rope - maximum torque (in N·m) that each joint applies to
+    /// keep the rope straight, see module docs. Default is 0.0 (a chain).
+    pub fn with_stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self
+    }
+
+    /// Sets amount of vertices around the tube mesh. Default is 8.
+    pub fn with_radial_segments(mut self, count: usize) -> Self {
+        self.radial_segments = count.max(3);
+        self
+    }
+
+    /// Sets collision groups of rope colliders.
+    pub fn with_collision_groups(mut self, groups: InteractionGroups) -> Self {
+        self.collision_groups = groups;
+        self
+    }
+
+    /// Sets a surface, whose textures, material and color will be used by the tube mesh.
+    pub fn with_surface(mut self, surface: Surface) -> Self {
+        self.surface = Some(surface);
+        self
+    }
+
+    /// Creates bodies, colliders and joints of the rope together with the tube mesh. Global
+    /// transforms of the graph must be up to date.
+    pub fn build(
+        self,
+        graph: &mut Graph,
+        physics: &mut Physics,
+        binder: &PhysicsBinder<Node>,
+    ) -> Rope {
+        let mut rope = Rope {
+            radius: self.radius,
+            radial_segments: self.radial_segments as u32,
+            stiffness: self.stiffness,
+            ..Default::default()
+        };
+
+        // Resolves an anchor to a body with attachment point in its local coordinates and
+        // position of the point in world coordinates.
+        let mut resolve = |anchor: RopeAnchor, physics: &mut Physics| match anchor {
+            RopeAnchor::Free(position) => (None, position),
+            RopeAnchor::Body { body, local_point } => {
+                let position = physics
+                    .bodies
+                    .get(&body)
+                    .map(|b| {
+                        b.position()
+                            .transform_point(&Point3::from(local_point))
+                            .coords
+                    })
+                    .unwrap_or(local_point);
+                (Some((body, local_point)), position)
+            }
+            RopeAnchor::Node(node) => {
+                let pose = node_isometry(graph, node);
+                let position = pose.translation.vector;
+                match binder.body_of(node).cloned() {
+                    Some(body) => {
+                        let local_point = physics
+                            .bodies
+                            .get(&body)
+                            .map(|b| {
+                                b.position()
+                                    .inverse_transform_point(&Point3::from(position))
+                                    .coords
+                            })
+                            .unwrap_or_default();
+                        (Some((body, local_point)), position)
+                    }
+                    None => {
+                        let body = physics.add_body(
+                            RigidBodyBuilder::new(RigidBodyType::KinematicPositionBased)
+                                .position(pose)
+                                .build(),
+                        );
+                        rope.node_anchors.push(RopeNodeAnchor { node, body });
+                        (Some((body, Vector3::default())), position)
+                    }
+                }
+            }
+        };
+        let (start_body, start) = resolve(self.start, physics);
+        let (end_body, end) = resolve(self.end, physics);
+
+        // Slack rope is laid out as two straight lines that meet below the middle point.
+        let distance = (end - start).norm();
+        let length = self.length.unwrap_or(distance).max(distance);
+        let direction = (end - start)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| -Vector3::y());
+        let down = (-Vector3::y() + direction.scale(direction.y))
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| any_perpendicular(&direction));
+        let middle = (start + end).scale(0.5)
+            + down.scale(((length * 0.5).powi(2) - (distance * 0.5).powi(2)).sqrt());
+        let point_at = |s: f32| {
+            let half = length * 0.5;
+            if s <= half {
+                start.lerp(&middle, if half > 0.0 { s / half } else { 0.0 })
+            } else {
+                middle.lerp(&end, (s - half) / half)
+            }
+        };
+
+        let segment_length = length / self.segment_count as f32;
+        let mut path = Vec::with_capacity(self.segment_count + 1);
+        for i in 0..=self.segment_count {
+            path.push(point_at(i as f32 * segment_length));
+        }
+
+        let stiffness = self.stiffness;
+        let add_joint = |physics: &mut Physics,
+                         (body1, point1): (RigidBodyHandle, Vector3<f32>),
+                         (body2, point2): (RigidBodyHandle, Vector3<f32>),
+                         target: Option<UnitQuaternion<f32>>| {
+            let mut joint = BallJoint::new(Point3::from(point1), Point3::from(point2));
+            if stiffness > 0.0 {
+                let target = target.unwrap_or_else(|| {
+                    let rotation = |body| {
+                        physics
+                            .bodies
+                            .get(&body)
+                            .map(|b| b.position().rotation)
+                            .unwrap_or_else(UnitQuaternion::identity)
+                    };
+                    rotation(body1).inverse() * rotation(body2)
+                });
+                joint.configure_motor_position(target, MOTOR_STIFFNESS, MOTOR_DAMPING);
+                // Kept in sync with time step by `Rope::update`.
+                joint.motor_max_impulse = stiffness * physics.integration_parameters.dt;
+            }
+            physics.add_joint(&body1, &body2, JointParams::from(joint))
+        };
+
+        let mut previous: Option<(RigidBodyHandle, Vector3<f32>)> = start_body;
+        for pair in path.windows(2) {
+            let axis = pair[1] - pair[0];
+            let half_length = (axis.norm() * 0.5).max(f32::EPSILON);
+            let rotation =
+                UnitQuaternion::rotation_between(&Vector3::y(), &axis).unwrap_or_else(|| {
+                    UnitQuaternion::from_euler_angles(std::f32::consts::PI, 0.0, 0.0)
+                });
+
+            let body = physics.add_body(
+                RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                    .position(isometry(rotation, (pair[0] + pair[1]).scale(0.5)))
+                    .build(),
+            );
+
+            let volume = std::f32::consts::PI * self.radius.powi(2) * 2.0 * half_length
+                + 4.0 / 3.0 * std::f32::consts::PI * self.radius.powi(3);
+            let collider = physics.add_collider(
+                ColliderBuilder::new(SharedShape::capsule(
+                    Point3::new(0.0, -half_length, 0.0),
+                    Point3::new(0.0, half_length, 0.0),
+                    self.radius,
+                ))
+                .density(self.mass / self.segment_count as f32 / volume)
+                .collision_groups(self.collision_groups)
+                .build(),
+                &body,
+            );
+
+            // Neighbour segments are straight at rest, so rest target of their motors is
+            // identity, ends of the rope are clamped in orientation they were created with.
+            let target = if rope.segments.is_empty() {
+                None
+            } else {
+                Some(UnitQuaternion::identity())
+            };
+            let joint = previous.map(|(previous, local_point)| {
+                add_joint(
+                    physics,
+                    (previous, local_point),
+                    (body, Vector3::new(0.0, -half_length, 0.0)),
+                    target,
+                )
+            });
+
+            rope.segments.push(RopeSegment {
+                body,
+                collider,
+                joint,
+                half_length,
+            });
+            previous = Some((body, Vector3::new(0.0, half_length, 0.0)));
+        }
+
+        if let (Some(last), Some(end_body)) = (previous, end_body) {
+            rope.end_joint = Some(add_joint(physics, last, end_body, None));
+        }
+
+        let data = Arc::new(RwLock::new(make_tube(
+            &path,
+            self.radius,
+            self.radial_segments,
+        )));
+        let mut surface = Surface::new(data);
+        if let Some(source) = self.surface.as_ref() {
+            surface.set_diffuse_texture(source.diffuse_texture());
+            surface.set_normal_texture(source.normal_texture());
+            surface.set_specular_texture(source.specular_texture());
+            surface.set_roughness_texture(source.roughness_texture());
+            surface.set_height_texture(source.height_texture());
+            surface.set_material(source.material());
+            surface.set_color(source.color());
+        }
+        rope.mesh = MeshBuilder::new(BaseBuilder::new().with_name("Rope"))
+            .with_surfaces(vec![surface])
+            .build(graph);
+
+        rope
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{algebra::Vector3, futures::executor::block_on, pool::Handle, visitor::prelude::*},
+        engine::PhysicsBinder,
+        scene::{
+            base::BaseBuilder,
+            graph::Graph,
+            mesh::buffer::{VertexAttributeUsage, VertexReadTrait},
+            node::Node,
+            physics::{
+                rope::{Rope, RopeAnchor, RopeBuilder},
+                Physics,
+            },
+            transform::TransformBuilder,
+        },
+    };
+    use rapier3d::{
+        dynamics::{JointParams, RigidBodyBuilder, RigidBodyType},
+        geometry::{ColliderBuilder, InteractionGroups},
+    };
+
+    fn pivot(graph: &mut Graph, position: Vector3<f32>) -> Handle<Node> {
+        BaseBuilder::new()
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(position)
+                    .build(),
+            )
+            .build(graph)
+    }
+
+    fn tube_vertices(graph: &Graph, rope: &Rope) -> Vec<Vector3<f32>> {
+        if let Node::Mesh(mesh) = &graph[rope.mesh()] {
+            let data = mesh.surfaces()[0].data();
+            let data = data.read().unwrap();
+            data.vertex_buffer
+                .iter()
+                .map(|v| v.read_3_f32(VertexAttributeUsage::Position).unwrap())
+                .collect()
+        } else {
+            panic!("rope mesh must be a mesh");
+        }
+    }
+
+    #[test]
+    fn rope_between_nodes() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let binder = PhysicsBinder::default();
+
+        let left = pivot(&mut graph, Vector3::new(-2.0, 5.0, 0.0));
+        let right = pivot(&mut graph, Vector3::new(2.0, 5.0, 0.0));
+        graph.update_hierarchical_data();
+
+        let rope = RopeBuilder::new(RopeAnchor::Node(left), RopeAnchor::Node(right))
+            .with_segment_count(8)
+            .with_length(5.0)
+            .with_radial_segments(6)
+            .build(&mut graph, &mut physics, &binder);
+        assert_eq!(rope.segments().len(), 8);
+        assert_eq!(rope.node_anchors().len(), 2);
+        assert!(rope.segments().iter().all(|s| s.joint.is_some()));
+        assert!(rope.end_joint().is_some());
+
+        for _ in 0..120 {
+            rope.update(&mut graph, &mut physics);
+            physics.step(&binder);
+        }
+        rope.update(&mut graph, &mut physics);
+
+        // Ends of the rope stay at anchors, segments stay connected and the rope hangs down.
+        let path = rope.path(&physics).unwrap();
+        assert_eq!(path.len(), 9);
+        assert!((path[0] - Vector3::new(-2.0, 5.0, 0.0)).norm() < 0.05);
+        assert!((path[8] - Vector3::new(2.0, 5.0, 0.0)).norm() < 0.05);
+        assert!(path[4].y < 4.5);
+        let length = path.windows(2).map(|p| (p[1] - p[0]).norm()).sum::<f32>();
+        assert!((length - 5.0).abs() < 0.05);
+
+        // Tube follows the segments.
+        if let Node::Mesh(mesh) = &graph[rope.mesh()] {
+            let data = mesh.surfaces()[0].data();
+            let data = data.read().unwrap();
+            assert_eq!(data.vertex_buffer.vertex_count(), 9 * 7);
+            let vertex = data.vertex_buffer.get(4 * 7).unwrap();
+            let position = vertex.read_3_f32(VertexAttributeUsage::Position).unwrap();
+            assert!(((position - path[4]).norm() - 0.05).abs() < 1.0e-4);
+        } else {
+            panic!("rope mesh must be a mesh");
+        }
+
+        rope.remove(&mut graph, &mut physics);
+        assert_eq!(physics.bodies.len(), 0);
+    }
+
+    #[test]
+    fn stiff_rope_bends_less() {
+        let free_end_height = |stiffness: f32| {
+            let mut graph = Graph::new();
+            let mut physics = Physics::new();
+            physics.integration_parameters.max_velocity_iterations = 20;
+            let binder = PhysicsBinder::default();
+
+            let wall = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+            let rope = RopeBuilder::new(
+                RopeAnchor::Body {
+                    body: wall,
+                    local_point: Vector3::new(0.0, 5.0, 0.0),
+                },
+                RopeAnchor::Free(Vector3::new(2.0, 5.0, 0.0)),
+            )
+            .with_segment_count(4)
+            .with_stiffness(stiffness)
+            .build(&mut graph, &mut physics, &binder);
+            assert!(rope.end_joint().is_none());
+
+            for _ in 0..60 {
+                physics.step(&binder);
+            }
+            rope.path(&physics).unwrap().last().unwrap().y
+        };
+
+        // Rope weighs 1 kg and is 2 m long, so gravity torque at the wall is about 10 N·m.
+        assert!(free_end_height(0.0) < 4.5);
+        assert!(free_end_height(20.0) > 4.9);
+    }
+
+    #[test]
+    fn rope_between_bodies() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let mut binder = PhysicsBinder::default();
+
+        let wall = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .translation(Vector3::new(-2.0, 5.0, 0.0))
+                .build(),
+        );
+
+        // Node that is bound to a body is attached using the body.
+        let weight = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector3::new(1.5, 5.0, 0.0))
+                .build(),
+        );
+        physics.add_collider(
+            ColliderBuilder::ball(0.2)
+                .collision_groups(InteractionGroups::none())
+                .build(),
+            &weight,
+        );
+        let node = pivot(&mut graph, Vector3::new(1.5, 5.0, 0.0));
+        graph.update_hierarchical_data();
+        binder.bind(node, weight);
+
+        let rope = RopeBuilder::new(
+            RopeAnchor::Body {
+                body: wall,
+                local_point: Vector3::new(0.5, 0.0, 0.0),
+            },
+            RopeAnchor::Node(node),
+        )
+        .with_segment_count(6)
+        .build(&mut graph, &mut physics, &binder);
+        assert!(rope.node_anchors().is_empty());
+        assert!(rope.end_joint().is_some());
+        assert_eq!(physics.bodies.len(), 2 + 6);
+
+        // The weight swings down on the rope like a pendulum, ends of the rope stay at
+        // attachment points.
+        let mut lowest = f32::MAX;
+        for _ in 0..120 {
+            rope.update(&mut graph, &mut physics);
+            physics.step(&binder);
+
+            let path = rope.path(&physics).unwrap();
+            let weight_position = physics.bodies.get(&weight).unwrap().position().translation;
+            assert!((path[0] - Vector3::new(-1.5, 5.0, 0.0)).norm() < 0.05);
+            assert!((path[6] - weight_position.vector).norm() < 0.05);
+            lowest = lowest.min(weight_position.y);
+        }
+        assert!(lowest < 2.5, "lowest position is {}", lowest);
+    }
+
+    #[test]
+    fn remove_keeps_anchor_bodies() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let binder = PhysicsBinder::default();
+
+        let wall = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        let node = pivot(&mut graph, Vector3::new(3.0, 0.0, 0.0));
+        graph.update_hierarchical_data();
+
+        let rope = RopeBuilder::new(
+            RopeAnchor::Body {
+                body: wall,
+                local_point: Vector3::default(),
+            },
+            RopeAnchor::Node(node),
+        )
+        .with_segment_count(4)
+        .build(&mut graph, &mut physics, &binder);
+        let mesh = rope.mesh();
+        assert_eq!(physics.bodies.len(), 1 + 1 + 4);
+        assert_eq!(physics.joints.len(), 5);
+
+        rope.remove(&mut graph, &mut physics);
+
+        assert_eq!(physics.bodies.len(), 1);
+        assert!(physics.bodies.contains(&wall));
+        assert_eq!(physics.colliders.len(), 0);
+        assert_eq!(physics.joints.len(), 0);
+        assert!(!graph.is_valid_handle(mesh));
+        assert!(graph.is_valid_handle(node));
+    }
+
+    #[test]
+    fn path_requires_all_segments() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let binder = PhysicsBinder::default();
+
+        let rope = RopeBuilder::new(
+            RopeAnchor::Free(Vector3::new(0.0, 0.0, 0.0)),
+            RopeAnchor::Free(Vector3::new(2.0, 0.0, 0.0)),
+        )
+        .with_segment_count(4)
+        .build(&mut graph, &mut physics, &binder);
+        let vertices = tube_vertices(&graph, &rope);
+
+        physics.remove_body(&rope.segments()[2].body);
+        assert!(rope.path(&physics).is_none());
+
+        // Tube is left as is.
+        rope.update(&mut graph, &mut physics);
+        assert_eq!(tube_vertices(&graph, &rope), vertices);
+    }
+
+    #[test]
+    fn tube_is_rebuilt_when_its_layout_does_not_match() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let binder = PhysicsBinder::default();
+
+        let rope = RopeBuilder::new(
+            RopeAnchor::Free(Vector3::new(0.0, 0.0, 0.0)),
+            RopeAnchor::Free(Vector3::new(2.0, 0.0, 0.0)),
+        )
+        .with_segment_count(4)
+        .with_radial_segments(6)
+        .build(&mut graph, &mut physics, &binder);
+        let vertices = tube_vertices(&graph, &rope);
+        assert_eq!(vertices.len(), 5 * 7);
+
+        if let Node::Mesh(mesh) = &graph[rope.mesh()] {
+            mesh.surfaces()[0].data().write().unwrap().clear();
+        }
+        rope.update(&mut graph, &mut physics);
+
+        let rebuilt = tube_vertices(&graph, &rope);
+        assert_eq!(rebuilt.len(), vertices.len());
+        for (a, b) in rebuilt.iter().zip(vertices.iter()) {
+            assert!((a - b).norm() < 1.0e-5);
+        }
+    }
+
+    #[test]
+    fn motor_limits_follow_time_step() {
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let binder = PhysicsBinder::default();
+
+        let wall = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        let rope = RopeBuilder::new(
+            RopeAnchor::Body {
+                body: wall,
+                local_point: Vector3::default(),
+            },
+            RopeAnchor::Free(Vector3::new(2.0, 0.0, 0.0)),
+        )
+        .with_segment_count(4)
+        .with_stiffness(10.0)
+        .build(&mut graph, &mut physics, &binder);
+
+        physics.integration_parameters.dt = 1.0 / 120.0;
+        rope.update(&mut graph, &mut physics);
+
+        for segment in rope.segments() {
+            let joint = physics.joints.get(&segment.joint.unwrap()).unwrap();
+            if let JointParams::BallJoint(ball) = &joint.params {
+                assert_eq!(ball.motor_max_impulse, 10.0 / 120.0);
+            } else {
+                panic!("segments must be connected with ball joints");
+            }
+        }
+    }
+
+    #[test]
+    fn rope_round_trip() {
+        let path = std::env::temp_dir().join(format!("rg3d_rope_{}.bin", std::process::id()));
+
+        let mut graph = Graph::new();
+        let mut physics = Physics::new();
+        let binder = PhysicsBinder::default();
+
+        let wall = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .translation(Vector3::new(0.0, 5.0, 0.0))
+                .build(),
+        );
+        let node = pivot(&mut graph, Vector3::new(3.0, 5.0, 0.0));
+        graph.update_hierarchical_data();
+
+        let mut rope = RopeBuilder::new(
+            RopeAnchor::Body {
+                body: wall,
+                local_point: Vector3::default(),
+            },
+            RopeAnchor::Node(node),
+        )
+        .with_segment_count(5)
+        .with_length(4.0)
+        .with_radius(0.1)
+        .with_radial_segments(5)
+        .with_stiffness(2.0)
+        .build(&mut graph, &mut physics, &binder);
+
+        for _ in 0..30 {
+            rope.update(&mut graph, &mut physics);
+            physics.step(&binder);
+        }
+        rope.update(&mut graph, &mut physics);
+
+        {
+            let mut visitor = Visitor::new();
+            graph.visit("Graph", &mut visitor).unwrap();
+            physics.visit("Physics", &mut visitor).unwrap();
+            rope.visit("Rope", &mut visitor).unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+
+        let mut loaded_graph = Graph::default();
+        let mut loaded_physics = Physics::new();
+        let mut loaded = Rope::default();
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            loaded_graph.visit("Graph", &mut visitor).unwrap();
+            loaded_physics.visit("Physics", &mut visitor).unwrap();
+            loaded.visit("Rope", &mut visitor).unwrap();
+        }
+        loaded_graph.resolve();
+        loaded_physics.resolve(&binder, &loaded_graph);
+
+        assert_eq!(loaded.radius(), 0.1);
+        assert_eq!(loaded.radial_segments(), 5);
+        assert_eq!(loaded.stiffness(), 2.0);
+        assert_eq!(loaded.mesh(), rope.mesh());
+        assert_eq!(
+            tube_vertices(&loaded_graph, &loaded),
+            tube_vertices(&graph, &rope)
+        );
+
+        // Moving the anchor node must move the loaded rope exactly as the original one.
+        let move_node = |graph: &mut Graph| {
+            graph[node]
+                .local_transform_mut()
+                .set_position(Vector3::new(3.0, 6.0, 0.0));
+            graph.update_hierarchical_data();
+        };
+        move_node(&mut graph);
+        move_node(&mut loaded_graph);
+        for _ in 0..30 {
+            rope.update(&mut graph, &mut physics);
+            physics.step(&binder);
+            loaded.update(&mut loaded_graph, &mut loaded_physics);
+            loaded_physics.step(&binder);
+        }
+        rope.update(&mut graph, &mut physics);
+        loaded.update(&mut loaded_graph, &mut loaded_physics);
+
+        assert_eq!(loaded.path(&loaded_physics), rope.path(&physics));
+        assert_eq!(
+            tube_vertices(&loaded_graph, &loaded),
+            tube_vertices(&graph, &rope)
+        );
+        let end = loaded.path(&loaded_physics).unwrap()[5];
+        assert!((end - Vector3::new(3.0, 6.0, 0.0)).norm() < 0.05);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
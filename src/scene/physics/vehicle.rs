@@ -0,0 +1,1069 @@
+//! Raycast vehicle controller.
+//!
+//! # Overview
+//!
+//! Vehicle is a dynamic rigid body (chassis) that is held above the ground by a set of
+//! suspension rays, one per wheel. Wheels are not rigid bodies with colliders, instead every
+//! physics step each wheel casts a ray down from its suspension mount point and if the ray hits
+//! something, the vehicle applies following impulses at the contact point:
+//!
+//! - Suspension - spring and damper along up axis of the chassis.
+//! - Traction - engine torque transferred through the gearbox to driven wheels.
+//! - Braking - force that opposes rolling of a wheel, limited by brake torque.
+//! - Side friction - force that opposes sliding of a wheel sideways.
+//!
+//! Traction, braking and side friction are limited by friction circle of a tire - their sum
+//! can't exceed normal load of the wheel multiplied by [`Wheel::friction`].
+//!
+//! All impulses are calculated using time step of physics world, so vehicle behaves exactly
+//! the same with the same input and the same world.
+//!
+//! # Usage
+//!
+//! Call [`Vehicle::update`] every frame **before** scene update. Each wheel has kinematic rigid
+//! body without colliders that follows suspension, steering and spin of the wheel, bind wheel
+//! nodes to them using physics binder. Keep in mind that binder sets local transform of a node,
+//! so both chassis and wheel nodes must not have parents with transform other than identity.
+//! Vehicle owns its bodies, use [`Vehicle::remove`] to remove them from physics world.
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::{algebra::Vector3, pool::Handle},
+//!     scene::{
+//!         node::Node,
+//!         physics::vehicle::{Vehicle, VehicleBuilder, WheelBuilder},
+//!         Scene,
+//!     },
+//! };
+//!
+//! fn create_car(scene: &mut Scene, chassis: Handle<Node>, wheels: [Handle<Node>; 4]) -> Vehicle {
+//!     let vehicle = VehicleBuilder::new()
+//!         .with_position(Vector3::new(0.0, 1.0, 0.0))
+//!         .with_wheel(WheelBuilder::new(Vector3::new(0.8, -0.2, 1.4)).with_steerable(true))
+//!         .with_wheel(WheelBuilder::new(Vector3::new(-0.8, -0.2, 1.4)).with_steerable(true))
+//!         .with_wheel(WheelBuilder::new(Vector3::new(0.8, -0.2, -1.4)).with_driven(true))
+//!         .with_wheel(WheelBuilder::new(Vector3::new(-0.8, -0.2, -1.4)).with_driven(true))
+//!         .build(&mut scene.physics);
+//!
+//!     scene.physics_binder.bind(chassis, vehicle.chassis());
+//!     for (node, wheel) in wheels.iter().zip(vehicle.wheels()) {
+//!         scene.physics_binder.bind(*node, wheel.body());
+//!     }
+//!
+//!     // Then every frame:
+//!     // vehicle.controls.throttle = 1.0;
+//!     // vehicle.update(&mut scene.physics);
+//!
+//!     vehicle
+//! }
+//! ```
+
+use crate::{
+    core::{
+        algebra::{Isometry3, Point3, Translation3, UnitQuaternion, Vector2, Vector3},
+        arrayvec::ArrayVec,
+        math::ray::Ray,
+        visitor::prelude::*,
+    },
+    engine::{ColliderHandle, RigidBodyHandle},
+    scene::physics::{Intersection, Physics, RayCastOptions},
+};
+use rapier3d::{
+    dynamics::{RigidBodyBuilder, RigidBodyType},
+    geometry::{ColliderBuilder, InteractionGroups},
+};
+
+/// Single point of engine torque curve.
+#[derive(Copy, Clone, Debug, Default, Visit)]
+pub struct TorqueCurvePoint {
+    /// Revolutions per minute.
+    pub rpm: f32,
+    /// Torque (in N·m) at given revolutions per minute.
+    pub torque: f32,
+}
+
+/// Engine produces torque that depends on its revolutions per minute.
+#[derive(Clone, Debug, Visit)]
+pub struct Engine {
+    /// Torque curve of the engine, points must be sorted by rpm. Torque between points is
+    /// linearly interpolated.
+    pub torque_curve: Vec<TorqueCurvePoint>,
+    /// Minimum revolutions per minute of the engine.
+    pub idle_rpm: f32,
+    /// Maximum revolutions per minute of the engine, the engine produces no torque above it.
+    pub max_rpm: f32,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self {
+            torque_curve: vec![
+                TorqueCurvePoint {
+                    rpm: 1000.0,
+                    torque: 200.0,
+                },
+                TorqueCurvePoint {
+                    rpm: 3000.0,
+                    torque: 300.0,
+                },
+                TorqueCurvePoint {
+                    rpm: 5000.0,
+                    torque: 280.0,
+                },
+                TorqueCurvePoint {
+                    rpm: 6500.0,
+                    torque: 220.0,
+                },
+            ],
+            idle_rpm: 800.0,
+            max_rpm: 7000.0,
+        }
+    }
+}
+
+impl Engine {
+    /// Returns torque of the engine at given revolutions per minute with full throttle.
+    pub fn torque(&self, rpm: f32) -> f32 {
+        if rpm >= self.max_rpm {
+            return 0.0;
+        }
+
+        let (first, last) = match (self.torque_curve.first(), self.torque_curve.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        if rpm <= first.rpm {
+            return first.torque;
+        }
+
+        for pair in self.torque_curve.windows(2) {
+            let (left, right) = (&pair[0], &pair[1]);
+            if rpm <= right.rpm {
+                let span = right.rpm - left.rpm;
+                let t = if span > f32::EPSILON {
+                    (rpm - left.rpm) / span
+                } else {
+                    1.0
+                };
+                return left.torque + (right.torque - left.torque) * t;
+            }
+        }
+
+        last.torque
+    }
+}
+
+/// Gearbox transfers engine torque to driven wheels.
+#[derive(Clone, Debug, Visit)]
+pub struct Gearbox {
+    /// Ratios of forward gears, starting from the first gear.
+    pub forward_ratios: Vec<f32>,
+    /// Ratio of reverse gear.
+    pub reverse_ratio: f32,
+    /// Ratio of final drive, it is applied on top of the ratio of current gear.
+    pub final_drive: f32,
+    /// Whether forward gears should be switched automatically or not.
+    pub automatic: bool,
+    /// Revolutions per minute of the engine at which automatic gearbox switches to upper gear.
+    pub upshift_rpm: f32,
+    /// Revolutions per minute of the engine at which automatic gearbox switches to lower gear.
+    pub downshift_rpm: f32,
+    gear: i32,
+}
+
+impl Default for Gearbox {
+    fn default() -> Self {
+        Self {
+            forward_ratios: vec![3.5, 2.2, 1.5, 1.1, 0.9],
+            reverse_ratio: 3.2,
+            final_drive: 3.7,
+            automatic: true,
+            upshift_rpm: 6000.0,
+            downshift_rpm: 2500.0,
+            gear: 1,
+        }
+    }
+}
+
+impl Gearbox {
+    /// Returns current gear: negative values mean reverse, zero - neutral, positive values -
+    /// forward gears, starting from one.
+    pub fn gear(&self) -> i32 {
+        self.gear
+    }
+
+    /// Sets current gear, see [`Self::gear`] for meaning of values. Gear will be clamped to
+    /// available range.
+    pub fn set_gear(&mut self, gear: i32) {
+        self.gear = gear.max(-1).min(self.forward_ratios.len() as i32);
+    }
+
+    /// Switches to upper gear.
+    pub fn shift_up(&mut self) {
+        self.set_gear(self.gear + 1);
+    }
+
+    /// Switches to lower gear.
+    pub fn shift_down(&mut self) {
+        self.set_gear(self.gear - 1);
+    }
+
+    /// Returns total ratio between engine and driven wheels, including final drive. Ratio is
+    /// negative for reverse gear and zero for neutral.
+    pub fn ratio(&self) -> f32 {
+        let ratio = if self.gear < 0 {
+            -self.reverse_ratio
+        } else if self.gear == 0 {
+            0.0
+        } else {
+            self.forward_ratios
+                .get(self.gear as usize - 1)
+                .copied()
+                .unwrap_or_default()
+        };
+        ratio * self.final_drive
+    }
+}
+
+/// Input of a vehicle.
+#[derive(Copy, Clone, Debug, Default, Visit)]
+pub struct VehicleControls {
+    /// Throttle in `[0; 1]` range.
+    pub throttle: f32,
+    /// Brake in `[0; 1]` range.
+    pub brake: f32,
+    /// Steering in `[-1; 1]` range, positive values rotate steerable wheels counterclockwise
+    /// around up axis of the chassis (when looking from above).
+    pub steering: f32,
+}
+
+/// Wheel of a vehicle, see module docs for more info.
+#[derive(Clone, Debug, Visit)]
+pub struct Wheel {
+    body: RigidBodyHandle,
+    /// Point at which suspension is attached to the chassis, in local coordinates of the chassis.
+    pub position: Vector3<f32>,
+    /// Radius of the wheel.
+    pub radius: f32,
+    /// Length of the suspension when it is not loaded.
+    pub suspension_rest_length: f32,
+    /// Stiffness of the suspension spring (in N/m).
+    pub suspension_stiffness: f32,
+    /// Damping of the suspension (in N·s/m).
+    pub suspension_damping: f32,
+    /// Friction coefficient of the tire, defines maximum force the tire can transfer to the
+    /// ground relative to its normal load.
+    pub friction: f32,
+    /// Fraction of sideways velocity of the wheel that side friction tries to remove each
+    /// physics step. Values close to one make vehicle very grippy, but could cause jitter.
+    pub lateral_grip: f32,
+    /// Whether the wheel is rotated by steering or not.
+    pub steerable: bool,
+    /// Whether the wheel receives torque from the engine or not.
+    pub driven: bool,
+    steering_angle: f32,
+    suspension_length: f32,
+    rotation_angle: f32,
+    angular_velocity: f32,
+    in_contact: bool,
+    contact_point: Vector3<f32>,
+    contact_normal: Vector3<f32>,
+    #[visit(skip)]
+    contact_collider: Option<ColliderHandle>,
+    #[visit(skip)]
+    suspension_force: f32,
+}
+
+impl Default for Wheel {
+    fn default() -> Self {
+        Self {
+            body: Default::default(),
+            position: Default::default(),
+            radius: 0.35,
+            suspension_rest_length: 0.3,
+            suspension_stiffness: 25000.0,
+            suspension_damping: 4000.0,
+            friction: 1.0,
+            lateral_grip: 0.5,
+            steerable: false,
+            driven: false,
+            steering_angle: 0.0,
+            suspension_length: 0.3,
+            rotation_angle: 0.0,
+            angular_velocity: 0.0,
+            in_contact: false,
+            contact_point: Default::default(),
+            contact_normal: Vector3::y(),
+            contact_collider: None,
+            suspension_force: 0.0,
+        }
+    }
+}
+
+impl Wheel {
+    /// Returns handle of kinematic rigid body that follows the wheel, bind wheel node to it.
+    pub fn body(&self) -> RigidBodyHandle {
+        self.body
+    }
+
+    /// Returns true if the wheel touches the ground.
+    pub fn is_in_contact(&self) -> bool {
+        self.in_contact
+    }
+
+    /// Returns contact point of the wheel with the ground in world coordinates, it is valid only
+    /// if the wheel is in contact.
+    pub fn contact_point(&self) -> Vector3<f32> {
+        self.contact_point
+    }
+
+    /// Returns normal of the ground at contact point, or up vector if the wheel is in the air.
+    pub fn contact_normal(&self) -> Vector3<f32> {
+        self.contact_normal
+    }
+
+    /// Returns collider the wheel stands on.
+    pub fn contact_collider(&self) -> Option<ColliderHandle> {
+        self.contact_collider
+    }
+
+    /// Returns current length of the suspension.
+    pub fn suspension_length(&self) -> f32 {
+        self.suspension_length
+    }
+
+    /// Returns force (in N) with which suspension pushes the chassis up.
+    pub fn suspension_force(&self) -> f32 {
+        self.suspension_force
+    }
+
+    /// Returns current steering angle of the wheel in radians.
+    pub fn steering_angle(&self) -> f32 {
+        self.steering_angle
+    }
+
+    /// Returns angular velocity of the wheel around its axle in rad/s.
+    pub fn angular_velocity(&self) -> f32 {
+        self.angular_velocity
+    }
+
+    /// Returns rotation angle of the wheel around its axle in radians.
+    pub fn rotation_angle(&self) -> f32 {
+        self.rotation_angle
+    }
+}
+
+/// Maximum amount of ray intersections checked per wheel, intersections with the chassis are
+/// skipped so there must be some room for them.
+const MAX_WHEEL_INTERSECTIONS: usize = 8;
+
+/// Fraction of angular velocity of a wheel that is kept each step when the wheel is in the air.
+const AIR_SPIN_DAMPING: f32 = 0.99;
+
+struct WheelHit {
+    toi: f32,
+    position: Point3<f32>,
+    normal: Vector3<f32>,
+    collider: ColliderHandle,
+}
+
+/// See module docs.
+#[derive(Clone, Debug, Visit)]
+pub struct Vehicle {
+    chassis: RigidBodyHandle,
+    chassis_collider: ColliderHandle,
+    wheels: Vec<Wheel>,
+    /// Engine of the vehicle.
+    pub engine: Engine,
+    /// Gearbox of the vehicle.
+    pub gearbox: Gearbox,
+    /// Current input of the vehicle.
+    pub controls: VehicleControls,
+    /// Maximum steering angle of steerable wheels in radians.
+    pub max_steering_angle: f32,
+    /// Maximum brake torque (in N·m) applied to each wheel.
+    pub max_brake_torque: f32,
+    /// Groups of colliders that will be hit by suspension rays.
+    #[visit(skip)]
+    pub groups: InteractionGroups,
+    engine_rpm: f32,
+}
+
+impl Default for Vehicle {
+    fn default() -> Self {
+        Self {
+            chassis: Default::default(),
+            chassis_collider: Default::default(),
+            wheels: Default::default(),
+            engine: Default::default(),
+            gearbox: Default::default(),
+            controls: Default::default(),
+            max_steering_angle: 35.0f32.to_radians(),
+            max_brake_torque: 1500.0,
+            groups: InteractionGroups::all(),
+            engine_rpm: 0.0,
+        }
+    }
+}
+
+impl Vehicle {
+    /// Returns handle of dynamic rigid body of the chassis, bind chassis node to it.
+    pub fn chassis(&self) -> RigidBodyHandle {
+        self.chassis
+    }
+
+    /// Returns handle of collider of the chassis.
+    pub fn chassis_collider(&self) -> ColliderHandle {
+        self.chassis_collider
+    }
+
+    /// Returns wheels of the vehicle.
+    pub fn wheels(&self) -> &[Wheel] {
+        &self.wheels
+    }
+
+    /// Returns wheels of the vehicle, could be used to tweak their parameters.
+    pub fn wheels_mut(&mut self) -> &mut [Wheel] {
+        &mut self.wheels
+    }
+
+    /// Returns current revolutions per minute of the engine.
+    pub fn engine_rpm(&self) -> f32 {
+        self.engine_rpm
+    }
+
+    /// Returns velocity of the chassis along its forward (Z) axis in m/s.
+    pub fn speed(&self, physics: &Physics) -> f32 {
+        physics.bodies.get(&self.chassis).map_or(0.0, |body| {
+            body.linvel()
+                .dot(&(body.position().rotation * Vector3::z()))
+        })
+    }
+
+    /// Removes chassis, its collider and kinematic bodies of wheels from physics world.
+    pub fn remove(self, physics: &mut Physics) {
+        physics.remove_body(&self.chassis);
+        for wheel in self.wheels {
+            physics.remove_body(&wheel.body);
+        }
+    }
+
+    fn cast_wheel_ray(
+        &self,
+        physics: &Physics,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+        max_len: f32,
+    ) -> Option<WheelHit> {
+        let mut intersections = ArrayVec::<Intersection, MAX_WHEEL_INTERSECTIONS>::new();
+        physics.cast_ray(
+            RayCastOptions {
+                ray: Ray::new(origin.coords, direction.scale(max_len)),
+                max_len,
+                groups: self.groups,
+                sort_results: true,
+            },
+            &mut intersections,
+        );
+        intersections
+            .into_iter()
+            .find(|i| physics.collider_parent(&i.collider) != Some(&self.chassis))
+            .map(|i| WheelHit {
+                toi: i.toi,
+                position: i.position,
+                normal: i.normal,
+                collider: i.collider,
+            })
+    }
+
+    /// Performs single step of the vehicle simulation using time step of given physics world.
+    /// It must be called every frame before scene update (or before [`Physics`] step).
+    pub fn update(&mut self, physics: &mut Physics) {
+        let dt = physics.integration_parameters.dt;
+
+        let (chassis_position, mass) = match physics.bodies.get(&self.chassis) {
+            Some(body) => (*body.position(), body.mass()),
+            None => return,
+        };
+        let rotation = chassis_position.rotation;
+        let up = rotation * Vector3::y();
+        let mass_share = if self.wheels.is_empty() {
+            0.0
+        } else {
+            mass / self.wheels.len() as f32
+        };
+
+        // Suspension rays.
+        let mut hits = Vec::with_capacity(self.wheels.len());
+        for wheel in self.wheels.iter() {
+            let mount = chassis_position * Point3::from(wheel.position);
+            hits.push(self.cast_wheel_ray(
+                physics,
+                mount,
+                -up,
+                wheel.suspension_rest_length + wheel.radius,
+            ));
+        }
+
+        // Engine and gearbox.
+        let ratio = self.gearbox.ratio();
+        let throttle = self.controls.throttle.clamp(0.0, 1.0);
+        let (driven_count, driven_angular_velocity) = self
+            .wheels
+            .iter()
+            .filter(|w| w.driven)
+            .fold((0usize, 0.0f32), |(count, sum), w| {
+                (count + 1, sum + w.angular_velocity)
+            });
+        let wheel_rpm = if driven_count > 0 {
+            driven_angular_velocity / driven_count as f32 * 60.0 / (2.0 * std::f32::consts::PI)
+        } else {
+            0.0
+        };
+        let rpm = if ratio == 0.0 {
+            self.engine.idle_rpm + (self.engine.max_rpm - self.engine.idle_rpm) * throttle
+        } else {
+            (wheel_rpm * ratio).abs().max(self.engine.idle_rpm)
+        };
+        let engine_torque = self.engine.torque(rpm) * throttle;
+        let wheel_torque = if driven_count > 0 {
+            engine_torque * ratio / driven_count as f32
+        } else {
+            0.0
+        };
+        self.engine_rpm = rpm.min(self.engine.max_rpm);
+
+        if self.gearbox.automatic && self.gearbox.gear() >= 1 {
+            if rpm > self.gearbox.upshift_rpm {
+                self.gearbox.shift_up();
+            } else if rpm < self.gearbox.downshift_rpm && self.gearbox.gear() > 1 {
+                self.gearbox.shift_down();
+            }
+        }
+
+        // Suspension and tire impulses.
+        let steering = self.controls.steering.clamp(-1.0, 1.0) * self.max_steering_angle;
+        let brake = self.controls.brake.clamp(0.0, 1.0);
+        let mut impulses = Vec::with_capacity(self.wheels.len());
+        if let Some(chassis) = physics.bodies.get(&self.chassis) {
+            for (wheel, hit) in self.wheels.iter_mut().zip(hits.iter()) {
+                wheel.steering_angle = if wheel.steerable { steering } else { 0.0 };
+                let wheel_rotation = rotation
+                    * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), wheel.steering_angle);
+
+                let hit = match hit {
+                    Some(hit) => hit,
+                    None => {
+                        wheel.in_contact = false;
+                        wheel.contact_normal = up;
+                        wheel.contact_collider = None;
+                        wheel.suspension_length = wheel.suspension_rest_length;
+                        wheel.suspension_force = 0.0;
+                        wheel.angular_velocity *= AIR_SPIN_DAMPING;
+                        continue;
+                    }
+                };
+
+                wheel.in_contact = true;
+                wheel.contact_point = hit.position.coords;
+                wheel.contact_normal = hit.normal;
+                wheel.contact_collider = Some(hit.collider);
+                wheel.suspension_length = (hit.toi - wheel.radius)
+                    .max(0.0)
+                    .min(wheel.suspension_rest_length);
+
+                let velocity = chassis.velocity_at_point(&hit.position);
+
+                // Suspension.
+                let compression = wheel.suspension_rest_length - wheel.suspension_length;
+                let compression_velocity = -velocity.dot(&up);
+                let suspension_force = (wheel.suspension_stiffness * compression
+                    + wheel.suspension_damping * compression_velocity)
+                    .max(0.0);
+                wheel.suspension_force = suspension_force;
+
+                // Tire, forward and side axes lie in the plane of the ground.
+                let normal = hit.normal;
+                let axis = wheel_rotation * Vector3::z();
+                let forward =
+                    match (axis - normal.scale(axis.dot(&normal))).try_normalize(f32::EPSILON) {
+                        Some(forward) => forward,
+                        None => {
+                            impulses.push((up.scale(suspension_force * dt), hit.position));
+                            continue;
+                        }
+                    };
+                let side = normal.cross(&forward);
+
+                let longitudinal_velocity = velocity.dot(&forward);
+                let lateral_velocity = velocity.dot(&side);
+
+                let mut longitudinal = 0.0;
+                if wheel.driven {
+                    longitudinal += wheel_torque / wheel.radius * dt;
+                }
+                let max_brake = brake * self.max_brake_torque / wheel.radius * dt;
+                longitudinal += (-longitudinal_velocity * mass_share)
+                    .max(-max_brake)
+                    .min(max_brake);
+                let lateral = -lateral_velocity * mass_share * wheel.lateral_grip;
+
+                // Friction circle.
+                let mut traction = Vector2::new(longitudinal, lateral);
+                let max_traction = wheel.friction * suspension_force * dt;
+                let traction_magnitude = traction.norm();
+                if traction_magnitude > max_traction {
+                    traction = traction.scale(max_traction / traction_magnitude);
+                }
+
+                impulses.push((
+                    up.scale(suspension_force * dt)
+                        + forward.scale(traction.x)
+                        + side.scale(traction.y),
+                    hit.position,
+                ));
+
+                wheel.angular_velocity = longitudinal_velocity / wheel.radius;
+            }
+        }
+
+        if let Some(chassis) = physics.bodies.get_mut(&self.chassis) {
+            for (impulse, point) in impulses {
+                chassis.apply_impulse_at_point(impulse, point, true);
+            }
+        }
+
+        // Wheel visuals.
+        for wheel in self.wheels.iter_mut() {
+            wheel.rotation_angle += wheel.angular_velocity * dt;
+            wheel.rotation_angle %= 2.0 * std::f32::consts::PI;
+
+            if let Some(body) = physics.bodies.get_mut(&wheel.body) {
+                let mount = chassis_position * Point3::from(wheel.position);
+                body.set_next_kinematic_position(Isometry3 {
+                    translation: Translation3 {
+                        vector: mount.coords - up.scale(wheel.suspension_length),
+                    },
+                    rotation: rotation
+                        * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), wheel.steering_angle)
+                        * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), wheel.rotation_angle),
+                });
+            }
+        }
+    }
+}
+
+/// Creates wheel of a vehicle.
+pub struct WheelBuilder {
+    position: Vector3<f32>,
+    radius: f32,
+    suspension_rest_length: f32,
+    suspension_stiffness: f32,
+    suspension_damping: f32,
+    friction: f32,
+    lateral_grip: f32,
+    steerable: bool,
+    driven: bool,
+}
+
+impl WheelBuilder {
+    /// Creates new builder of a wheel with suspension attached to the chassis at given point in
+    /// local coordinates of the chassis.
+    pub fn new(position: Vector3<f32>) -> Self {
+        let defaults = Wheel::default();
+        Self {
+            position,
+            radius: defaults.radius,
+            suspension_rest_length: defaults.suspension_rest_length,
+            suspension_stiffness: defaults.suspension_stiffness,
+            suspension_damping: defaults.suspension_damping,
+            friction: defaults.friction,
+            lateral_grip: defaults.lateral_grip,
+            steerable: defaults.steerable,
+            driven: defaults.driven,
+        }
+    }
+
+    /// Sets radius of the wheel.
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets length of unloaded suspension.
+    pub fn with_suspension_rest_length(mut self, length: f32) -> Self {
+        self.suspension_rest_length = length;
+        self
+    }
+
+    /// Sets stiffness of suspension spring (in N/m).
+    pub fn with_suspension_stiffness(mut self, stiffness: f32) -> Self {
+        self.suspension_stiffness = stiffness;
+        self
+    }
+
+    /// Sets damping of suspension (in N·s/m).
+    pub fn with_suspension_damping(mut self, damping: f32) -> Self {
+        self.suspension_damping = damping;
+        self
+    }
+
+    /// Sets friction coefficient of the tire.
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    /// Sets fraction of sideways velocity that side friction removes each step.
+    pub fn with_lateral_grip(mut self, lateral_grip: f32) -> Self {
+        self.lateral_grip = lateral_grip;
+        self
+    }
+
+    /// Sets whether the wheel is rotated by steering or not.
+    pub fn with_steerable(mut self, steerable: bool) -> Self {
+        self.steerable = steerable;
+        self
+    }
+
+    /// Sets whether the wheel receives torque from the engine or not.
+    pub fn with_driven(mut self, driven: bool) -> Self {
+        self.driven = driven;
+        self
+    }
+
+    fn build(self, physics: &mut Physics, chassis_position: &Isometry3<f32>) -> Wheel {
+        let mount = chassis_position * Point3::from(self.position);
+        let body = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::KinematicPositionBased)
+                .position(Isometry3 {
+                    translation: Translation3 {
+                        vector: mount.coords
+                            - (chassis_position.rotation * Vector3::y())
+                                .scale(self.suspension_rest_length),
+                    },
+                    rotation: chassis_position.rotation,
+                })
+                .build(),
+        );
+
+        Wheel {
+            body,
+            position: self.position,
+            radius: self.radius,
+            suspension_rest_length: self.suspension_rest_length,
+            suspension_stiffness: self.suspension_stiffness,
+            suspension_damping: self.suspension_damping,
+            friction: self.friction,
+            lateral_grip: self.lateral_grip,
+            steerable: self.steerable,
+            driven: self.driven,
+            suspension_length: self.suspension_rest_length,
+            ..Default::default()
+        }
+    }
+}
+
+/// Creates vehicle with dynamic chassis and a set of wheels.
+pub struct VehicleBuilder {
+    position: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+    mass: f32,
+    chassis_half_extents: Vector3<f32>,
+    wheels: Vec<WheelBuilder>,
+    engine: Engine,
+    gearbox: Gearbox,
+    max_steering_angle: f32,
+    max_brake_torque: f32,
+    groups: InteractionGroups,
+}
+
+impl Default for VehicleBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VehicleBuilder {
+    /// Creates new builder with default settings and without wheels.
+    pub fn new() -> Self {
+        let defaults = Vehicle::default();
+        Self {
+            position: Default::default(),
+            rotation: UnitQuaternion::identity(),
+            mass: 1000.0,
+            chassis_half_extents: Vector3::new(0.9, 0.3, 2.0),
+            wheels: Default::default(),
+            engine: defaults.engine,
+            gearbox: defaults.gearbox,
+            max_steering_angle: defaults.max_steering_angle,
+            max_brake_torque: defaults.max_brake_torque,
+            groups: defaults.groups,
+        }
+    }
+
+    /// Sets initial position of the chassis.
+    pub fn with_position(mut self, position: Vector3<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Sets initial rotation of the chassis.
+    pub fn with_rotation(mut self, rotation: UnitQuaternion<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets mass of the chassis in kilograms.
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    /// Sets half extents of box collider of the chassis.
+    pub fn with_chassis_half_extents(mut self, half_extents: Vector3<f32>) -> Self {
+        self.chassis_half_extents = half_extents;
+        self
+    }
+
+    /// Adds new wheel.
+    pub fn with_wheel(mut self, wheel: WheelBuilder) -> Self {
+        self.wheels.push(wheel);
+        self
+    }
+
+    /// Sets engine of the vehicle.
+    pub fn with_engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Sets gearbox of the vehicle.
+    pub fn with_gearbox(mut self, gearbox: Gearbox) -> Self {
+        self.gearbox = gearbox;
+        self
+    }
+
+    /// Sets maximum steering angle of steerable wheels in radians.
+    pub fn with_max_steering_angle(mut self, max_steering_angle: f32) -> Self {
+        self.max_steering_angle = max_steering_angle;
+        self
+    }
+
+    /// Sets maximum brake torque applied to each wheel.
+    pub fn with_max_brake_torque(mut self, max_brake_torque: f32) -> Self {
+        self.max_brake_torque = max_brake_torque;
+        self
+    }
+
+    /// Sets groups of colliders that will be hit by suspension rays.
+    pub fn with_groups(mut self, groups: InteractionGroups) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// Creates chassis, its collider and wheels in given physics world.
+    pub fn build(self, physics: &mut Physics) -> Vehicle {
+        let chassis_position = Isometry3 {
+            translation: Translation3 {
+                vector: self.position,
+            },
+            rotation: self.rotation,
+        };
+
+        let chassis = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .position(chassis_position)
+                .build(),
+        );
+        let e = self.chassis_half_extents;
+        let volume = 8.0 * e.x * e.y * e.z;
+        let chassis_collider = physics.add_collider(
+            ColliderBuilder::cuboid(e.x, e.y, e.z)
+                .density(if volume > 0.0 {
+                    self.mass / volume
+                } else {
+                    1.0
+                })
+                .build(),
+            &chassis,
+        );
+
+        let wheels = self
+            .wheels
+            .into_iter()
+            .map(|w| w.build(physics, &chassis_position))
+            .collect();
+
+        Vehicle {
+            chassis,
+            chassis_collider,
+            wheels,
+            engine: self.engine,
+            gearbox: self.gearbox,
+            controls: Default::default(),
+            max_steering_angle: self.max_steering_angle,
+            max_brake_torque: self.max_brake_torque,
+            groups: self.groups,
+            engine_rpm: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Isometry3, Translation3, UnitQuaternion, Vector3},
+        scene::physics::{
+            vehicle::{Engine, Vehicle, VehicleBuilder, WheelBuilder},
+            Physics,
+        },
+    };
+    use rapier3d::{
+        dynamics::{RigidBodyBuilder, RigidBodyType},
+        geometry::ColliderBuilder,
+    };
+
+    fn flat_ground(physics: &mut Physics) {
+        let body = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .position(Isometry3 {
+                    translation: Translation3 {
+                        vector: Vector3::new(0.0, -0.5, 0.0),
+                    },
+                    rotation: UnitQuaternion::identity(),
+                })
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(200.0, 0.5, 200.0).build(), &body);
+    }
+
+    fn car(physics: &mut Physics) -> Vehicle {
+        VehicleBuilder::new()
+            .with_position(Vector3::new(0.0, 1.0, 0.0))
+            .with_wheel(WheelBuilder::new(Vector3::new(0.8, -0.2, 1.4)).with_steerable(true))
+            .with_wheel(WheelBuilder::new(Vector3::new(-0.8, -0.2, 1.4)).with_steerable(true))
+            .with_wheel(WheelBuilder::new(Vector3::new(0.8, -0.2, -1.4)).with_driven(true))
+            .with_wheel(WheelBuilder::new(Vector3::new(-0.8, -0.2, -1.4)).with_driven(true))
+            .build(physics)
+    }
+
+    fn simulate(vehicle: &mut Vehicle, physics: &mut Physics, steps: usize) {
+        for _ in 0..steps {
+            vehicle.update(physics);
+            physics.step(&Default::default());
+        }
+    }
+
+    fn chassis_position(vehicle: &Vehicle, physics: &Physics) -> Vector3<f32> {
+        physics
+            .bodies
+            .get(&vehicle.chassis())
+            .unwrap()
+            .position()
+            .translation
+            .vector
+    }
+
+    #[test]
+    fn engine_torque_curve() {
+        let engine = Engine::default();
+        assert_eq!(engine.torque(0.0), 200.0);
+        assert_eq!(engine.torque(2000.0), 250.0);
+        assert_eq!(engine.torque(3000.0), 300.0);
+        assert_eq!(engine.torque(6800.0), 220.0);
+        assert_eq!(engine.torque(7000.0), 0.0);
+    }
+
+    #[test]
+    fn rests_on_flat_ground() {
+        let mut physics = Physics::new();
+        flat_ground(&mut physics);
+        let mut vehicle = car(&mut physics);
+
+        simulate(&mut vehicle, &mut physics, 300);
+
+        for wheel in vehicle.wheels() {
+            assert!(wheel.is_in_contact());
+            assert!(wheel.suspension_length() < wheel.suspension_rest_length);
+            assert!(wheel.suspension_length() > 0.0);
+        }
+        let position = chassis_position(&vehicle, &physics);
+        assert!(position.x.abs() < 0.05);
+        assert!(position.z.abs() < 0.05);
+        assert!(vehicle.speed(&physics).abs() < 0.05);
+    }
+
+    #[test]
+    fn accelerates_steers_and_brakes() {
+        let mut physics = Physics::new();
+        flat_ground(&mut physics);
+        let mut vehicle = car(&mut physics);
+        simulate(&mut vehicle, &mut physics, 120);
+
+        vehicle.controls.throttle = 1.0;
+        simulate(&mut vehicle, &mut physics, 180);
+        let position = chassis_position(&vehicle, &physics);
+        assert!(vehicle.speed(&physics) > 5.0);
+        assert!(position.z > 5.0);
+        assert!(position.x.abs() < 0.5);
+
+        vehicle.controls.throttle = 0.3;
+        vehicle.controls.steering = 1.0;
+        simulate(&mut vehicle, &mut physics, 60);
+        assert!(chassis_position(&vehicle, &physics).x > position.x + 1.0);
+
+        vehicle.controls.throttle = 0.0;
+        vehicle.controls.steering = 0.0;
+        vehicle.controls.brake = 1.0;
+        simulate(&mut vehicle, &mut physics, 300);
+        assert!(vehicle.speed(&physics).abs() < 0.1);
+    }
+
+    #[test]
+    fn simulation_is_deterministic() {
+        let run = || {
+            let mut physics = Physics::new();
+            flat_ground(&mut physics);
+            let mut vehicle = car(&mut physics);
+            vehicle.controls.throttle = 1.0;
+            vehicle.controls.steering = 0.5;
+            simulate(&mut vehicle, &mut physics, 200);
+            (
+                chassis_position(&vehicle, &physics),
+                vehicle.engine_rpm(),
+                vehicle.gearbox.gear(),
+            )
+        };
+
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn removal_frees_bodies_and_colliders() {
+        let mut physics = Physics::new();
+        flat_ground(&mut physics);
+        let mut vehicle = car(&mut physics);
+        assert_eq!(physics.bodies.len(), 6);
+        assert_eq!(physics.colliders.len(), 2);
+
+        vehicle.controls.throttle = 1.0;
+        simulate(&mut vehicle, &mut physics, 30);
+        let chassis = vehicle.chassis();
+        let wheels = vehicle
+            .wheels()
+            .iter()
+            .map(|w| w.body())
+            .collect::<Vec<_>>();
+
+        vehicle.remove(&mut physics);
+        assert_eq!(physics.bodies.len(), 1);
+        assert_eq!(physics.colliders.len(), 1);
+        assert!(!physics.bodies.contains(&chassis));
+        assert!(wheels.iter().all(|w| !physics.bodies.contains(w)));
+
+        // The world keeps working without the vehicle.
+        physics.step(&Default::default());
+    }
+}
@@ -0,0 +1,614 @@
+//! Post-processing settings of a camera.
+//!
+//! # Overview
+//!
+//! Scene is rendered into a frame with high dynamic range (HDR), then post-processing chain of
+//! a camera is applied to the frame in following order:
+//!
+//! 1. Auto exposure - average luminance of the frame is calculated from luminance histogram,
+//!    exposure smoothly adapts to it over time, just like eyes do.
+//! 2. Bloom - bright parts of the frame are extracted, blurred and added back to the frame.
+//! 3. Tone mapping - HDR colors are mapped into displayable range using selected operator.
+//! 4. Color grading - colors are remapped using 3D look-up table (LUT).
+//! 5. Chromatic aberration and vignette.
+//! 6. Custom post effects, see [`crate::renderer::post_processing::PostEffect`].
+//!
+//! FXAA (if enabled in quality settings) is applied after the chain.
+//!
+//! # Performance
+//!
+//! Default settings do not change the image, in this case the chain is skipped completely
+//! (unless there are custom post effects). Bloom is the most expensive effect, each blur pass
+//! is two full-screen passes in half resolution.
+//!
+//! # Color grading
+//!
+//! Look-up table must be a volume texture, red channel of input color selects a texel along
+//! width, green - along height and blue - along depth. Use [`make_identity_lut`] to get neutral
+//! table, that could be used as a starting point.
+
+use crate::{
+    core::{
+        algebra::Vector3,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    resource::texture::{Texture, TextureKind, TexturePixelKind},
+};
+
+/// Operator that maps HDR colors into displayable range.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[repr(u32)]
+pub enum ToneMapping {
+    /// Colors are just clamped to \[0; 1\] range.
+    #[default]
+    None = 0,
+    /// Simple `c / (1 + c)` operator, desaturates bright colors.
+    Reinhard = 1,
+    /// Filmic curve approximation of Academy Color Encoding System.
+    Aces = 2,
+    /// Filmic curve that was used in Uncharted 2.
+    Uncharted2 = 3,
+}
+
+impl ToneMapping {
+    /// Creates tone mapping operator from its id.
+    pub fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Reinhard),
+            2 => Ok(Self::Aces),
+            3 => Ok(Self::Uncharted2),
+            _ => Err(format!("Invalid tone mapping id {}!", id)),
+        }
+    }
+
+    /// Maps HDR color into displayable range using the same curve as the renderer does. Could
+    /// be used to preview operators or to bake tone mapping into color grading tables.
+    pub fn map(self, color: Vector3<f32>) -> Vector3<f32> {
+        match self {
+            Self::None => color.map(|c| c.clamp(0.0, 1.0)),
+            Self::Reinhard => color.map(|c| c / (1.0 + c)),
+            // Fit by Krzysztof Narkowicz.
+            Self::Aces => color.map(|c| {
+                ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+            }),
+            Self::Uncharted2 => {
+                const WHITE_POINT: f32 = 11.2;
+                color.map(|c| uncharted2_curve(2.0 * c) / uncharted2_curve(WHITE_POINT))
+            }
+        }
+    }
+}
+
+fn uncharted2_curve(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+/// Defines how exposure of a frame is calculated. Frame color is multiplied by exposure before
+/// tone mapping.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Exposure {
+    /// Fixed exposure.
+    Manual(f32),
+
+    /// Exposure is calculated from average luminance of a frame.
+    Auto {
+        /// Desired average luminance of a frame, 0.18 is middle gray.
+        key_value: f32,
+        /// Average luminance will be clamped to this value from below, prevents too bright
+        /// dark scenes.
+        min_luminance: f32,
+        /// Average luminance will be clamped to this value from above, prevents too dark
+        /// bright scenes.
+        max_luminance: f32,
+        /// How fast exposure adapts to changes of luminance. Bigger values means faster
+        /// adaptation.
+        adaptation_speed: f32,
+    },
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self::Manual(1.0)
+    }
+}
+
+impl Exposure {
+    /// Creates auto exposure with reasonable defaults.
+    pub fn auto() -> Self {
+        Self::Auto {
+            key_value: 0.18,
+            min_luminance: 0.03,
+            max_luminance: 8.0,
+            adaptation_speed: 1.5,
+        }
+    }
+
+    /// Returns exposure of a frame with given average luminance. Auto exposure maps clamped
+    /// average luminance to the key value, renderer adapts to it over time (see
+    /// [`Self::adaptation`]).
+    pub fn target(&self, average_luminance: f32) -> f32 {
+        match *self {
+            Self::Manual(exposure) => exposure,
+            Self::Auto {
+                key_value,
+                min_luminance,
+                max_luminance,
+                ..
+            } => key_value / average_luminance.max(min_luminance).min(max_luminance),
+        }
+    }
+
+    /// Returns portion of difference between current and target exposure that is applied
+    /// after `dt` seconds. Adaptation does not depend on frame rate: two steps of `dt / 2`
+    /// give the same result as one step of `dt`. Manual exposure is applied immediately.
+    pub fn adaptation(&self, dt: f32) -> f32 {
+        match *self {
+            Self::Manual(_) => 1.0,
+            Self::Auto {
+                adaptation_speed, ..
+            } => 1.0 - (-dt * adaptation_speed).exp(),
+        }
+    }
+
+    fn id(&self) -> u32 {
+        match self {
+            Self::Manual(_) => 0,
+            Self::Auto { .. } => 1,
+        }
+    }
+
+    fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::default()),
+            1 => Ok(Self::auto()),
+            _ => Err(format!("Invalid exposure id {}!", id)),
+        }
+    }
+}
+
+impl Visit for Exposure {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        let mut id = self.id();
+        id.visit("Id", visitor)?;
+        if visitor.is_reading() {
+            *self = Self::from_id(id)?;
+        }
+
+        match self {
+            Self::Manual(exposure) => exposure.visit("Exposure", visitor)?,
+            Self::Auto {
+                key_value,
+                min_luminance,
+                max_luminance,
+                adaptation_speed,
+            } => {
+                key_value.visit("KeyValue", visitor)?;
+                min_luminance.visit("MinLuminance", visitor)?;
+                max_luminance.visit("MaxLuminance", visitor)?;
+                adaptation_speed.visit("AdaptationSpeed", visitor)?;
+            }
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Glow around bright parts of a frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bloom {
+    /// Whether bloom is enabled or not.
+    pub enabled: bool,
+    /// Parts of a frame with luminance above this value will glow.
+    pub threshold: f32,
+    /// Multiplier of glow.
+    pub intensity: f32,
+    /// Amount of blur passes, more passes gives wider glow.
+    pub blur_passes: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 1.0,
+            intensity: 0.5,
+            blur_passes: 4,
+        }
+    }
+}
+
+impl Visit for Bloom {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.enabled.visit("Enabled", visitor)?;
+        self.threshold.visit("Threshold", visitor)?;
+        self.intensity.visit("Intensity", visitor)?;
+        self.blur_passes.visit("BlurPasses", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Darkening of a frame towards its edges.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vignette {
+    /// Whether vignette is enabled or not.
+    pub enabled: bool,
+    /// How dark edges of a frame will be, in \[0; 1\] range.
+    pub intensity: f32,
+    /// Distance from center of a frame (0.5 is an edge) where darkening starts.
+    pub radius: f32,
+    /// Width of transition between normal and dark parts of a frame.
+    pub smoothness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            intensity: 0.4,
+            radius: 0.35,
+            smoothness: 0.45,
+        }
+    }
+}
+
+impl Visit for Vignette {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.enabled.visit("Enabled", visitor)?;
+        self.intensity.visit("Intensity", visitor)?;
+        self.radius.visit("Radius", visitor)?;
+        self.smoothness.visit("Smoothness", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// See module docs.
+#[derive(Clone, Debug)]
+pub struct PostProcessing {
+    /// Bloom settings.
+    pub bloom: Bloom,
+    /// Tone mapping operator.
+    pub tone_mapping: ToneMapping,
+    /// Exposure settings.
+    pub exposure: Exposure,
+    /// Optional color grading look-up table, must be a volume texture.
+    pub color_grading_lut: Option<Texture>,
+    /// Vignette settings.
+    pub vignette: Vignette,
+    /// Strength of chromatic aberration - maximum offset of color channels in fractions of frame
+    /// size at edges of a frame. Zero disables the effect.
+    pub chromatic_aberration: f32,
+}
+
+impl Default for PostProcessing {
+    fn default() -> Self {
+        Self {
+            bloom: Default::default(),
+            tone_mapping: Default::default(),
+            exposure: Default::default(),
+            color_grading_lut: None,
+            vignette: Default::default(),
+            chromatic_aberration: 0.0,
+        }
+    }
+}
+
+impl PostProcessing {
+    /// Returns true if the settings do not change the image.
+    pub fn is_identity(&self) -> bool {
+        !self.bloom.enabled
+            && self.tone_mapping == ToneMapping::None
+            && self.exposure == Exposure::Manual(1.0)
+            && self.color_grading_lut.is_none()
+            && !self.vignette.enabled
+            && self.chromatic_aberration == 0.0
+    }
+}
+
+impl Visit for PostProcessing {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.bloom.visit("Bloom", visitor)?;
+
+        let mut tone_mapping = self.tone_mapping as u32;
+        tone_mapping.visit("ToneMapping", visitor)?;
+        if visitor.is_reading() {
+            self.tone_mapping = ToneMapping::from_id(tone_mapping)?;
+        }
+
+        self.exposure.visit("Exposure", visitor)?;
+        self.color_grading_lut.visit("ColorGradingLut", visitor)?;
+        self.vignette.visit("Vignette", visitor)?;
+        self.chromatic_aberration
+            .visit("ChromaticAberration", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+/// Creates color grading look-up table of given size that does not change colors. Size of 16 or
+/// 32 is enough in most cases, colors between texels are interpolated.
+pub fn make_identity_lut(size: u32) -> Texture {
+    let size = size.max(2);
+    let max = (size - 1) as f32;
+    let mut bytes = Vec::with_capacity((size * size * size * 3) as usize);
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                bytes.push((r as f32 / max * 255.0).round() as u8);
+                bytes.push((g as f32 / max * 255.0).round() as u8);
+                bytes.push((b as f32 / max * 255.0).round() as u8);
+            }
+        }
+    }
+
+    Texture::from_bytes(
+        TextureKind::Volume {
+            width: size,
+            height: size,
+            depth: size,
+        },
+        TexturePixelKind::RGB8,
+        bytes,
+        // There is no source file, so content must be serialized.
+        true,
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::Vector3,
+            futures::executor::block_on,
+            visitor::{Visit, Visitor},
+        },
+        resource::texture::TextureKind,
+        scene::post_processing::{
+            make_identity_lut, Bloom, Exposure, PostProcessing, ToneMapping, Vignette,
+        },
+    };
+
+    #[test]
+    fn default_post_processing_is_identity() {
+        let mut post_processing = PostProcessing::default();
+        assert!(post_processing.is_identity());
+
+        post_processing.exposure = Exposure::auto();
+        assert!(!post_processing.is_identity());
+    }
+
+    #[test]
+    fn identity_lut_is_volume_texture() {
+        let lut = make_identity_lut(16);
+        let data = lut.data_ref();
+        assert!(matches!(
+            data.kind(),
+            TextureKind::Volume {
+                width: 16,
+                height: 16,
+                depth: 16
+            }
+        ));
+    }
+
+    #[test]
+    fn every_effect_breaks_identity() {
+        let modifications: [fn(&mut PostProcessing); 6] = [
+            |p| p.bloom.enabled = true,
+            |p| p.tone_mapping = ToneMapping::Aces,
+            |p| p.exposure = Exposure::Manual(2.0),
+            |p| p.color_grading_lut = Some(make_identity_lut(2)),
+            |p| p.vignette.enabled = true,
+            |p| p.chromatic_aberration = 0.01,
+        ];
+        for modify in modifications.iter() {
+            let mut post_processing = PostProcessing::default();
+            modify(&mut post_processing);
+            assert!(!post_processing.is_identity());
+        }
+
+        // Parameters of disabled effects do not matter.
+        let mut post_processing = PostProcessing::default();
+        post_processing.bloom.threshold = 0.1;
+        post_processing.vignette.intensity = 1.0;
+        assert!(post_processing.is_identity());
+    }
+
+    #[test]
+    fn tone_mapping_ids() {
+        for tone_mapping in [
+            ToneMapping::None,
+            ToneMapping::Reinhard,
+            ToneMapping::Aces,
+            ToneMapping::Uncharted2,
+        ]
+        .iter()
+        {
+            assert_eq!(
+                ToneMapping::from_id(*tone_mapping as u32),
+                Ok(*tone_mapping)
+            );
+        }
+        assert!(ToneMapping::from_id(4).is_err());
+    }
+
+    #[test]
+    fn post_processing_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("rg3d_post_processing_{}.bin", std::process::id()));
+
+        let mut post_processing = PostProcessing {
+            bloom: Bloom {
+                enabled: true,
+                threshold: 0.8,
+                intensity: 1.5,
+                blur_passes: 2,
+            },
+            tone_mapping: ToneMapping::Uncharted2,
+            exposure: Exposure::Auto {
+                key_value: 0.25,
+                min_luminance: 0.1,
+                max_luminance: 4.0,
+                adaptation_speed: 3.0,
+            },
+            color_grading_lut: None,
+            vignette: Vignette {
+                enabled: true,
+                intensity: 0.7,
+                radius: 0.2,
+                smoothness: 0.1,
+            },
+            chromatic_aberration: 0.005,
+        };
+
+        {
+            let mut visitor = Visitor::new();
+            post_processing
+                .visit("PostProcessing", &mut visitor)
+                .unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+
+        let mut loaded = PostProcessing::default();
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            loaded.visit("PostProcessing", &mut visitor).unwrap();
+        }
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.bloom, post_processing.bloom);
+        assert_eq!(loaded.tone_mapping, post_processing.tone_mapping);
+        assert_eq!(loaded.exposure, post_processing.exposure);
+        assert!(loaded.color_grading_lut.is_none());
+        assert_eq!(loaded.vignette, post_processing.vignette);
+        assert_eq!(
+            loaded.chromatic_aberration,
+            post_processing.chromatic_aberration
+        );
+
+        // Manual exposure must keep its value too.
+        post_processing.exposure = Exposure::Manual(0.5);
+        {
+            let mut visitor = Visitor::new();
+            post_processing
+                .visit("PostProcessing", &mut visitor)
+                .unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            loaded.visit("PostProcessing", &mut visitor).unwrap();
+        }
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded.exposure, Exposure::Manual(0.5));
+    }
+
+    #[test]
+    fn identity_lut_maps_colors_to_themselves() {
+        let lut = make_identity_lut(4);
+        let data = lut.data_ref();
+        let bytes = data.data();
+        assert_eq!(bytes.len(), 4 * 4 * 4 * 3);
+
+        let texel = |r: usize, g: usize, b: usize| {
+            let index = ((b * 4 + g) * 4 + r) * 3;
+            [bytes[index], bytes[index + 1], bytes[index + 2]]
+        };
+        assert_eq!(texel(0, 0, 0), [0, 0, 0]);
+        assert_eq!(texel(3, 3, 3), [255, 255, 255]);
+        assert_eq!(texel(1, 2, 3), [85, 170, 255]);
+        assert_eq!(texel(3, 0, 1), [255, 0, 85]);
+
+        // Too small tables are enlarged, single texel cannot represent a gradient.
+        assert!(matches!(
+            make_identity_lut(1).data_ref().kind(),
+            TextureKind::Volume {
+                width: 2,
+                height: 2,
+                depth: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn tone_mapping_curves() {
+        let operators = [
+            ToneMapping::None,
+            ToneMapping::Reinhard,
+            ToneMapping::Aces,
+            ToneMapping::Uncharted2,
+        ];
+        for operator in operators.iter() {
+            let map = |c: f32| operator.map(Vector3::new(c, c, c)).x;
+
+            // Black stays black, brighter colors never become darker.
+            assert!(map(0.0).abs() < 1.0e-6);
+            // Colors up to white point of Uncharted2 operator stay in displayable range.
+            let mut previous = 0.0;
+            for i in 1..=28 {
+                let mapped = map(i as f32 * 0.2);
+                assert!(mapped >= previous);
+                assert!(mapped <= 1.0 + 1.0e-6);
+                previous = mapped;
+            }
+        }
+
+        let map = |operator: ToneMapping, c: f32| operator.map(Vector3::new(c, c, c)).x;
+        assert_eq!(map(ToneMapping::None, 4.0), 1.0);
+        assert_eq!(map(ToneMapping::None, 0.25), 0.25);
+        assert_eq!(map(ToneMapping::Reinhard, 1.0), 0.5);
+        assert!((map(ToneMapping::Aces, 1.0) - 2.54 / 3.16).abs() < 1.0e-6);
+        assert_eq!(map(ToneMapping::Aces, 100.0), 1.0);
+        // White point is 11.2 after exposure bias of 2.
+        assert!((map(ToneMapping::Uncharted2, 5.6) - 1.0).abs() < 1.0e-6);
+
+        // Channels are mapped independently.
+        assert_eq!(
+            ToneMapping::Reinhard.map(Vector3::new(0.0, 1.0, 3.0)),
+            Vector3::new(0.0, 0.5, 0.75)
+        );
+    }
+
+    #[test]
+    fn exposure_adaptation() {
+        let manual = Exposure::Manual(2.0);
+        assert_eq!(manual.target(10.0), 2.0);
+        assert_eq!(manual.adaptation(0.0), 1.0);
+
+        let auto = Exposure::Auto {
+            key_value: 0.18,
+            min_luminance: 0.03,
+            max_luminance: 8.0,
+            adaptation_speed: 1.5,
+        };
+        assert!((auto.target(1.0) - 0.18).abs() < 1.0e-6);
+        assert!((auto.target(0.5) - 0.36).abs() < 1.0e-6);
+        // Average luminance is clamped.
+        assert!((auto.target(0.001) - 6.0).abs() < 1.0e-5);
+        assert!((auto.target(100.0) - 0.18 / 8.0).abs() < 1.0e-6);
+
+        assert_eq!(auto.adaptation(0.0), 0.0);
+        assert!(auto.adaptation(100.0) > 0.999);
+        let half = auto.adaptation(0.1);
+        let full = auto.adaptation(0.2);
+        assert!(half > 0.0 && half < full);
+        assert!((1.0 - (1.0 - half) * (1.0 - half) - full).abs() < 1.0e-6);
+    }
+}
@@ -0,0 +1,474 @@
+//! Reflection probe is a node that captures its surroundings into a cube map, which is then
+//! used for reflections of every surface inside the volume of the probe.
+//!
+//! # Overview
+//!
+//! Camera environment map is a single cube map for the whole scene, so everything reflects
+//! the same (usually outdoor) environment, which looks wrong in interiors. Reflection probes
+//! solve this - each probe renders the scene from its position into a cube map, and surfaces
+//! inside the volume of the probe use that cube map instead of the camera environment.
+//!
+//! Volume of a probe is an axis-aligned box centered at the position of the probe, rotation
+//! and scale of the node are ignored.
+//!
+//! # Box projection
+//!
+//! Cube map captured at a single point is correct only for surfaces near that point. Box
+//! projection (also known as parallax correction) intersects the reflection vector with the
+//! volume of the probe and uses direction from the capture point to the intersection point
+//! to sample the cube map. This gives much more plausible reflections in rooms that have
+//! box-like shape, so box projection is enabled by default.
+//!
+//! # Blending
+//!
+//! Influence of a probe fades out linearly to zero in `blend_distance` range near the faces
+//! of its volume. Influences of overlapping probes are normalized, and if they sum to less
+//! than one, the rest is taken from the environment map of the camera.
+//!
+//! # Performance
+//!
+//! Capturing costs six full renders of the scene, so by default probes are captured once
+//! (see [ReflectionProbeUpdateMode]). Call [ReflectionProbe::request_capture] when the scene
+//! around a probe changes significantly.
+
+use crate::{
+    core::{
+        algebra::Vector3,
+        math::aabb::AxisAlignedBoundingBox,
+        pool::Handle,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{
+        base::{Base, BaseBuilder},
+        graph::Graph,
+        node::Node,
+    },
+};
+use std::ops::{Deref, DerefMut};
+
+/// Defines when the renderer captures a reflection probe.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[repr(u32)]
+pub enum ReflectionProbeUpdateMode {
+    /// Probe is captured once when it is rendered for the first time, and then every time
+    /// when [ReflectionProbe::request_capture] is called. Suitable for static environments.
+    Once = 0,
+
+    /// Probe is captured every frame. This is very expensive and should be used only for
+    /// few probes with low resolution.
+    EachFrame = 1,
+}
+
+impl Default for ReflectionProbeUpdateMode {
+    fn default() -> Self {
+        Self::Once
+    }
+}
+
+impl ReflectionProbeUpdateMode {
+    /// Creates update mode from its id.
+    pub fn from_id(id: u32) -> Result<Self, String> {
+        match id {
+            0 => Ok(Self::Once),
+            1 => Ok(Self::EachFrame),
+            _ => Err(format!("Invalid reflection probe update mode id {}!", id)),
+        }
+    }
+}
+
+/// See module docs.
+#[derive(Debug)]
+pub struct ReflectionProbe {
+    base: Base,
+    size: Vector3<f32>,
+    blend_distance: f32,
+    intensity: f32,
+    resolution: u32,
+    z_near: f32,
+    z_far: f32,
+    box_projection: bool,
+    update_mode: ReflectionProbeUpdateMode,
+    capture_generation: u64,
+}
+
+impl Deref for ReflectionProbe {
+    type Target = Base;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for ReflectionProbe {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+impl Default for ReflectionProbe {
+    fn default() -> Self {
+        ReflectionProbeBuilder::new(BaseBuilder::new()).build_reflection_probe()
+    }
+}
+
+impl ReflectionProbe {
+    /// Creates a raw copy of a reflection probe node.
+    pub fn raw_copy(&self) -> Self {
+        Self {
+            base: self.base.raw_copy(),
+            size: self.size,
+            blend_distance: self.blend_distance,
+            intensity: self.intensity,
+            resolution: self.resolution,
+            z_near: self.z_near,
+            z_far: self.z_far,
+            box_projection: self.box_projection,
+            update_mode: self.update_mode,
+            // Copy must be captured on its own.
+            capture_generation: 0,
+        }
+    }
+
+    /// Sets new size of the volume of the probe. Negative components are turned into positive.
+    pub fn set_size(&mut self, size: Vector3<f32>) {
+        self.size = size.abs();
+    }
+
+    /// Returns size of the volume of the probe.
+    pub fn size(&self) -> Vector3<f32> {
+        self.size
+    }
+
+    /// Sets distance from faces of the volume at which influence of the probe fades out.
+    pub fn set_blend_distance(&mut self, blend_distance: f32) {
+        self.blend_distance = blend_distance.max(0.0);
+    }
+
+    /// Returns distance from faces of the volume at which influence of the probe fades out.
+    pub fn blend_distance(&self) -> f32 {
+        self.blend_distance
+    }
+
+    /// Sets multiplier of captured colors.
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity.max(0.0);
+    }
+
+    /// Returns multiplier of captured colors.
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Sets size of a face of the cube map in pixels. Changing resolution forces the probe to
+    /// be captured again.
+    pub fn set_resolution(&mut self, resolution: u32) {
+        let resolution = resolution.max(1);
+        if self.resolution != resolution {
+            self.resolution = resolution;
+            self.request_capture();
+        }
+    }
+
+    /// Returns size of a face of the cube map in pixels.
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    /// Sets near clipping plane distance that is used when capturing.
+    pub fn set_z_near(&mut self, z_near: f32) {
+        self.z_near = z_near;
+    }
+
+    /// Returns near clipping plane distance that is used when capturing.
+    pub fn z_near(&self) -> f32 {
+        self.z_near
+    }
+
+    /// Sets far clipping plane distance that is used when capturing.
+    pub fn set_z_far(&mut self, z_far: f32) {
+        self.z_far = z_far;
+    }
+
+    /// Returns far clipping plane distance that is used when capturing.
+    pub fn z_far(&self) -> f32 {
+        self.z_far
+    }
+
+    /// Enables or disables box projection, see module docs for more info.
+    pub fn set_box_projection(&mut self, box_projection: bool) {
+        self.box_projection = box_projection;
+    }
+
+    /// Returns true if box projection is enabled.
+    pub fn is_box_projection(&self) -> bool {
+        self.box_projection
+    }
+
+    /// Sets new update mode.
+    pub fn set_update_mode(&mut self, update_mode: ReflectionProbeUpdateMode) {
+        self.update_mode = update_mode;
+    }
+
+    /// Returns current update mode.
+    pub fn update_mode(&self) -> ReflectionProbeUpdateMode {
+        self.update_mode
+    }
+
+    /// Forces renderer to capture the probe again on next frame.
+    pub fn request_capture(&mut self) {
+        self.capture_generation = self.capture_generation.wrapping_add(1);
+    }
+
+    /// Returns a number that is changed every time when capture is requested. Renderer
+    /// compares it with the number of the last capture.
+    pub fn capture_generation(&self) -> u64 {
+        self.capture_generation
+    }
+
+    /// Returns world-space bounds of the volume of the probe.
+    pub fn world_bounds(&self) -> AxisAlignedBoundingBox {
+        let center = self.global_position();
+        let half_size = self.size.scale(0.5);
+        AxisAlignedBoundingBox {
+            min: center - half_size,
+            max: center + half_size,
+        }
+    }
+
+    /// Returns influence of the probe at given world-space point in `[0; 1]` range. Influence
+    /// is zero outside of the volume and fades in over `blend_distance` near its faces.
+    pub fn influence(&self, point: Vector3<f32>) -> f32 {
+        let bounds = self.world_bounds();
+        let distance_to_face = (point - bounds.min).inf(&(bounds.max - point)).min();
+        if distance_to_face < 0.0 {
+            0.0
+        } else if self.blend_distance > 0.0 {
+            (distance_to_face / self.blend_distance).min(1.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Visit for ReflectionProbe {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.base.visit("Base", visitor)?;
+        self.size.visit("Size", visitor)?;
+        self.blend_distance.visit("BlendDistance", visitor)?;
+        self.intensity.visit("Intensity", visitor)?;
+        self.resolution.visit("Resolution", visitor)?;
+        self.z_near.visit("ZNear", visitor)?;
+        self.z_far.visit("ZFar", visitor)?;
+        self.box_projection.visit("BoxProjection", visitor)?;
+
+        let mut update_mode = self.update_mode as u32;
+        update_mode.visit("UpdateMode", visitor)?;
+        if visitor.is_reading() {
+            self.update_mode = ReflectionProbeUpdateMode::from_id(update_mode)?;
+        }
+
+        visitor.leave_region()
+    }
+}
+
+/// Allows you to build reflection probe in declarative manner.
+pub struct ReflectionProbeBuilder {
+    base_builder: BaseBuilder,
+    size: Vector3<f32>,
+    blend_distance: f32,
+    intensity: f32,
+    resolution: u32,
+    z_near: f32,
+    z_far: f32,
+    box_projection: bool,
+    update_mode: ReflectionProbeUpdateMode,
+}
+
+impl ReflectionProbeBuilder {
+    /// Creates new builder with default state (10x10x10 volume, 1.0 blend distance, 128
+    /// pixels resolution, box projection enabled, captured once).
+    pub fn new(base_builder: BaseBuilder) -> Self {
+        Self {
+            base_builder,
+            size: Vector3::new(10.0, 10.0, 10.0),
+            blend_distance: 1.0,
+            intensity: 1.0,
+            resolution: 128,
+            z_near: 0.05,
+            z_far: 100.0,
+            box_projection: true,
+            update_mode: Default::default(),
+        }
+    }
+
+    /// Sets desired size of the volume.
+    pub fn with_size(mut self, size: Vector3<f32>) -> Self {
+        self.size = size.abs();
+        self
+    }
+
+    /// Sets desired blend distance.
+    pub fn with_blend_distance(mut self, blend_distance: f32) -> Self {
+        self.blend_distance = blend_distance.max(0.0);
+        self
+    }
+
+    /// Sets desired intensity.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity.max(0.0);
+        self
+    }
+
+    /// Sets desired resolution of a face of the cube map.
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    /// Sets desired near clipping plane distance.
+    pub fn with_z_near(mut self, z_near: f32) -> Self {
+        self.z_near = z_near;
+        self
+    }
+
+    /// Sets desired far clipping plane distance.
+    pub fn with_z_far(mut self, z_far: f32) -> Self {
+        self.z_far = z_far;
+        self
+    }
+
+    /// Enables or disables box projection.
+    pub fn with_box_projection(mut self, box_projection: bool) -> Self {
+        self.box_projection = box_projection;
+        self
+    }
+
+    /// Sets desired update mode.
+    pub fn with_update_mode(mut self, update_mode: ReflectionProbeUpdateMode) -> Self {
+        self.update_mode = update_mode;
+        self
+    }
+
+    fn build_reflection_probe(self) -> ReflectionProbe {
+        ReflectionProbe {
+            base: self.base_builder.build_base(),
+            size: self.size,
+            blend_distance: self.blend_distance,
+            intensity: self.intensity,
+            resolution: self.resolution,
+            z_near: self.z_near,
+            z_far: self.z_far,
+            box_projection: self.box_projection,
+            update_mode: self.update_mode,
+            capture_generation: 0,
+        }
+    }
+
+    /// Creates new reflection probe instance.
+    pub fn build_node(self) -> Node {
+        Node::ReflectionProbe(self.build_reflection_probe())
+    }
+
+    /// Creates new reflection probe instance and adds it to the graph.
+    pub fn build(self, graph: &mut Graph) -> Handle<Node> {
+        graph.add_node(self.build_node())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        scene::{
+            base::BaseBuilder, graph::Graph, reflection_probe::ReflectionProbeBuilder,
+            transform::TransformBuilder,
+        },
+    };
+
+    #[test]
+    fn reflection_probe_influence_fades_near_faces() {
+        let mut graph = Graph::new();
+        let handle = ReflectionProbeBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(10.0, 0.0, 0.0))
+                    .build(),
+            ),
+        )
+        .with_size(Vector3::new(4.0, 4.0, 4.0))
+        .with_blend_distance(1.0)
+        .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let probe = graph[handle].as_reflection_probe();
+        let bounds = probe.world_bounds();
+        assert_eq!(bounds.min, Vector3::new(8.0, -2.0, -2.0));
+        assert_eq!(bounds.max, Vector3::new(12.0, 2.0, 2.0));
+
+        assert_eq!(probe.influence(Vector3::new(10.0, 0.0, 0.0)), 1.0);
+        assert_eq!(probe.influence(Vector3::new(11.5, 0.0, 0.0)), 0.5);
+        assert_eq!(probe.influence(Vector3::new(10.0, 0.0, -1.75)), 0.25);
+        assert_eq!(probe.influence(Vector3::new(13.0, 0.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn reflection_probe_influence_without_blend_distance() {
+        let mut graph = Graph::new();
+        let handle = ReflectionProbeBuilder::new(BaseBuilder::new())
+            .with_size(Vector3::new(-2.0, 4.0, 6.0))
+            .with_blend_distance(-1.0)
+            .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let probe = graph[handle].as_reflection_probe();
+        assert_eq!(probe.size(), Vector3::new(2.0, 4.0, 6.0));
+        assert_eq!(probe.blend_distance(), 0.0);
+
+        // Hard edge: full influence up to the faces, including the faces itself.
+        assert_eq!(probe.influence(Vector3::new(0.0, 0.0, 0.0)), 1.0);
+        assert_eq!(probe.influence(Vector3::new(0.99, 1.99, 2.99)), 1.0);
+        assert_eq!(probe.influence(Vector3::new(1.0, 0.0, 0.0)), 1.0);
+        assert_eq!(probe.influence(Vector3::new(1.01, 0.0, 0.0)), 0.0);
+        assert_eq!(probe.influence(Vector3::new(0.0, -2.01, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn reflection_probe_influence_uses_nearest_face_and_global_position() {
+        let mut graph = Graph::new();
+        let probe = ReflectionProbeBuilder::new(BaseBuilder::new())
+            .with_size(Vector3::new(4.0, 4.0, 4.0))
+            .with_blend_distance(2.0)
+            .build(&mut graph);
+        let parent = BaseBuilder::new()
+            .with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(0.0, 10.0, 0.0))
+                    .build(),
+            )
+            .with_children(&[probe])
+            .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let probe_ref = graph[probe].as_reflection_probe();
+        assert_eq!(probe_ref.world_bounds().min, Vector3::new(-2.0, 8.0, -2.0));
+
+        // Local-space origin is outside of the volume.
+        assert_eq!(probe_ref.influence(Vector3::new(0.0, 0.0, 0.0)), 0.0);
+        assert_eq!(probe_ref.influence(Vector3::new(0.0, 10.0, 0.0)), 1.0);
+        // Nearest face defines influence near corners.
+        assert_eq!(probe_ref.influence(Vector3::new(1.0, 10.0, 1.5)), 0.25);
+        assert_eq!(probe_ref.influence(Vector3::new(-1.5, 11.0, 0.0)), 0.25);
+
+        // Influence follows the probe.
+        graph[parent]
+            .local_transform_mut()
+            .set_position(Vector3::new(5.0, 0.0, 0.0));
+        graph.update_hierarchical_data();
+        let probe_ref = graph[probe].as_reflection_probe();
+        assert_eq!(probe_ref.influence(Vector3::new(0.0, 10.0, 0.0)), 0.0);
+        assert_eq!(probe_ref.influence(Vector3::new(5.0, 0.0, 0.0)), 1.0);
+        assert_eq!(probe_ref.influence(Vector3::new(6.0, 0.0, 0.0)), 0.5);
+    }
+}
@@ -0,0 +1,798 @@
+//! Contains all structures and methods to stream large scenes in and out piece by piece.
+//!
+//! Large open levels are expensive to load in full, instead a level can be split into a set of
+//! cells, where each cell is a separate model resource (FBX or RGS) with known world-space bounds.
+//! [`SceneStreamer`] tracks a focus point (usually a player or a camera) and requests cells
+//! that are close to the focus point from resource manager. Resource manager loads them in the
+//! background and once a cell is loaded it is instantiated into the live scene - its nodes,
+//! physics entities and navmeshes are merged into the scene. Cells that are far from the focus
+//! point are removed from the scene.
+//!
+//! Amount of work per update is bounded: you can limit amount of nodes that can be copied to or
+//! removed from the scene during single update call. Large cells are instantiated and unloaded
+//! over several updates and stay hidden until they are complete, so there will be no hitches when
+//! a cell is large or when many cells change their state at once.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use rg3d::{
+//!     core::{algebra::Vector3, math::aabb::AxisAlignedBoundingBox},
+//!     engine::resource_manager::ResourceManager,
+//!     scene::{streaming::SceneStreamer, Scene},
+//! };
+//!
+//! fn create_streamer() -> SceneStreamer {
+//!     let mut streamer = SceneStreamer::new(100.0, 150.0);
+//!     for x in 0..4 {
+//!         for z in 0..4 {
+//!             let min = Vector3::new(x as f32 * 50.0, -50.0, z as f32 * 50.0);
+//!             let max = min + Vector3::new(50.0, 100.0, 50.0);
+//!             streamer.add_cell(
+//!                 format!("data/level/cell_{}_{}.rgs", x, z),
+//!                 AxisAlignedBoundingBox::from_min_max(min, max),
+//!             );
+//!         }
+//!     }
+//!     streamer
+//! }
+//!
+//! fn update(
+//!     streamer: &mut SceneStreamer,
+//!     scene: &mut Scene,
+//!     resource_manager: &ResourceManager,
+//!     player_position: Vector3<f32>,
+//! ) {
+//!     streamer.update(scene, resource_manager, player_position);
+//! }
+//! ```
+
+use crate::{
+    animation::Animation,
+    core::{
+        algebra::Vector3,
+        math::aabb::AxisAlignedBoundingBox,
+        pool::{Handle, Pool, PoolPairIterator},
+    },
+    engine::resource_manager::{MaterialSearchOptions, ResourceManager},
+    resource::{model::Model, ResourceState},
+    scene::{graph::remap_handles, node::Node, Scene},
+    utils::{
+        log::{Log, MessageKind},
+        navmesh::Navmesh,
+    },
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Current state of a streaming cell.
+#[derive(Debug)]
+pub enum CellState {
+    /// Cell is not loaded and not requested.
+    Unloaded,
+
+    /// Cell was requested from resource manager and is loading in the background.
+    Loading(Model),
+
+    /// Cell is loaded and its nodes are being copied to the scene. The copy is hidden until
+    /// every node is copied.
+    Instantiating {
+        /// Model resource of the cell.
+        model: Model,
+        /// Root node of the instance of the cell.
+        root: Handle<Node>,
+    },
+
+    /// Cell is loaded and instantiated in the scene.
+    Loaded {
+        /// Model resource of the cell.
+        model: Model,
+        /// Root node of the instance of the cell.
+        root: Handle<Node>,
+        /// Animations that were instantiated together with the cell.
+        animations: Vec<Handle<Animation>>,
+        /// Navmeshes that were instantiated together with the cell.
+        navmeshes: Vec<Handle<Navmesh>>,
+    },
+
+    /// Nodes of the cell are being removed from the scene. Remaining nodes are hidden.
+    Unloading,
+
+    /// Cell failed to load, it won't be requested again until [`SceneStreamer::reset_failed`]
+    /// is called.
+    Failed,
+}
+
+/// A piece of a level which can be loaded and unloaded independently.
+#[derive(Debug)]
+pub struct StreamingCell {
+    path: PathBuf,
+    bounds: AxisAlignedBoundingBox,
+    state: CellState,
+    // Nodes of the model that are waiting to be copied, paired with parents of their copies.
+    to_copy: Vec<(Handle<Node>, Handle<Node>)>,
+    old_to_new: HashMap<Handle<Node>, Handle<Node>>,
+    // Nodes of the instance that are waiting to be removed, descendants go last.
+    to_remove: Vec<Handle<Node>>,
+}
+
+impl StreamingCell {
+    /// Returns path to the model resource of the cell.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns world-space bounds of the cell.
+    pub fn bounds(&self) -> &AxisAlignedBoundingBox {
+        &self.bounds
+    }
+
+    /// Returns current state of the cell.
+    pub fn state(&self) -> &CellState {
+        &self.state
+    }
+
+    /// Returns handle to root node of the cell instance, if the cell is loaded.
+    pub fn root(&self) -> Handle<Node> {
+        match self.state {
+            CellState::Loaded { root, .. } => root,
+            _ => Handle::NONE,
+        }
+    }
+
+    /// Returns true if the cell is loaded and instantiated in the scene.
+    pub fn is_loaded(&self) -> bool {
+        matches!(self.state, CellState::Loaded { .. })
+    }
+}
+
+/// Statistics of last [`SceneStreamer::update`] call.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct StreamingStatistics {
+    /// Amount of cells that were requested from resource manager.
+    pub requested: usize,
+    /// Amount of cells that were completely instantiated in the scene.
+    pub instantiated: usize,
+    /// Amount of cells that were completely removed from the scene.
+    pub unloaded: usize,
+    /// Amount of nodes that were copied to or removed from the scene.
+    pub processed_nodes: usize,
+    /// Amount of cells that are loading in the background.
+    pub loading: usize,
+    /// Amount of cells that are being instantiated.
+    pub instantiating: usize,
+    /// Amount of cells that are loaded.
+    pub loaded: usize,
+    /// Amount of cells that are being removed from the scene.
+    pub unloading: usize,
+}
+
+/// See module docs.
+#[derive(Debug)]
+pub struct SceneStreamer {
+    cells: Pool<StreamingCell>,
+    load_radius: f32,
+    unload_radius: f32,
+    max_nodes_per_update: usize,
+    material_search_options: MaterialSearchOptions,
+    statistics: StreamingStatistics,
+}
+
+impl SceneStreamer {
+    /// Creates new scene streamer. Cells that intersect sphere with `load_radius` around focus
+    /// point will be loaded, cells that do not intersect sphere with `unload_radius` will be
+    /// unloaded. Unload radius is clamped to be at least load radius - a gap between the radii
+    /// prevents cells from being loaded and unloaded repeatedly at the border.
+    pub fn new(load_radius: f32, unload_radius: f32) -> Self {
+        let load_radius = load_radius.max(0.0);
+        Self {
+            cells: Default::default(),
+            load_radius,
+            unload_radius: unload_radius.max(load_radius),
+            max_nodes_per_update: 256,
+            material_search_options: MaterialSearchOptions::RecursiveUp,
+            statistics: Default::default(),
+        }
+    }
+
+    /// Sets maximum amount of nodes that can be copied to or removed from the scene during
+    /// single update. Cells with more nodes are processed over several updates.
+    pub fn set_max_nodes_per_update(&mut self, amount: usize) {
+        self.max_nodes_per_update = amount.max(1);
+    }
+
+    /// Returns maximum amount of nodes that can be copied to or removed from the scene during
+    /// single update.
+    pub fn max_nodes_per_update(&self) -> usize {
+        self.max_nodes_per_update
+    }
+
+    /// Sets material search options that will be used to load cells.
+    pub fn set_material_search_options(&mut self, options: MaterialSearchOptions) {
+        self.material_search_options = options;
+    }
+
+    /// Sets new load and unload radii. See [`Self::new`] for more info.
+    pub fn set_radii(&mut self, load_radius: f32, unload_radius: f32) {
+        self.load_radius = load_radius.max(0.0);
+        self.unload_radius = unload_radius.max(self.load_radius);
+    }
+
+    /// Returns current load radius.
+    pub fn load_radius(&self) -> f32 {
+        self.load_radius
+    }
+
+    /// Returns current unload radius.
+    pub fn unload_radius(&self) -> f32 {
+        self.unload_radius
+    }
+
+    /// Adds new cell. `path` must point to a model resource, `bounds` must be world-space
+    /// bounds of the content of the cell.
+    pub fn add_cell<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        bounds: AxisAlignedBoundingBox,
+    ) -> Handle<StreamingCell> {
+        self.cells.spawn(StreamingCell {
+            path: path.as_ref().to_owned(),
+            bounds,
+            state: CellState::Unloaded,
+            to_copy: Default::default(),
+            old_to_new: Default::default(),
+            to_remove: Default::default(),
+        })
+    }
+
+    /// Removes a cell, if it is loaded, its content will be removed from the scene.
+    pub fn remove_cell(&mut self, scene: &mut Scene, cell: Handle<StreamingCell>) {
+        let mut cell = self.cells.free(cell);
+        unload(&mut cell, scene);
+    }
+
+    /// Returns a reference to a cell.
+    pub fn cell(&self, cell: Handle<StreamingCell>) -> &StreamingCell {
+        &self.cells[cell]
+    }
+
+    /// Returns an iterator over (handle, cell) pairs.
+    pub fn pair_iter(&self) -> PoolPairIterator<StreamingCell> {
+        self.cells.pair_iter()
+    }
+
+    /// Returns statistics of last update.
+    pub fn statistics(&self) -> StreamingStatistics {
+        self.statistics
+    }
+
+    /// Marks failed cells as unloaded, so they will be requested again.
+    pub fn reset_failed(&mut self) {
+        for cell in self.cells.iter_mut() {
+            if let CellState::Failed = cell.state {
+                cell.state = CellState::Unloaded;
+            }
+        }
+    }
+
+    /// Removes content of every loaded cell from the scene. Unlike [`Self::update`] it does
+    /// not limit amount of work.
+    pub fn unload_all(&mut self, scene: &mut Scene) {
+        for cell in self.cells.iter_mut() {
+            unload(cell, scene);
+        }
+    }
+
+    /// Updates state of cells according to given focus point. Should be called once per frame.
+    pub fn update(
+        &mut self,
+        scene: &mut Scene,
+        resource_manager: &ResourceManager,
+        focus: Vector3<f32>,
+    ) {
+        let mut statistics = StreamingStatistics::default();
+        let mut budget = self.max_nodes_per_update;
+
+        for cell in self.cells.iter_mut() {
+            let in_load_range = cell.bounds.is_intersects_sphere(focus, self.load_radius);
+            let in_unload_range = !cell.bounds.is_intersects_sphere(focus, self.unload_radius);
+
+            match cell.state {
+                CellState::Unloaded => {
+                    if in_load_range {
+                        // Requests are cheap - loading itself is done in the background.
+                        cell.state = CellState::Loading(
+                            resource_manager
+                                .request_model(&cell.path, self.material_search_options.clone()),
+                        );
+                        statistics.requested += 1;
+                    }
+                }
+                CellState::Loading(ref model) => {
+                    if in_unload_range {
+                        // Focus point moved away before the cell was loaded, discard it.
+                        cell.state = CellState::Unloaded;
+                    } else {
+                        let ready = match *model.state() {
+                            ResourceState::Ok(_) => Some(true),
+                            ResourceState::LoadError { .. } => Some(false),
+                            ResourceState::Pending { .. } => None,
+                        };
+
+                        match ready {
+                            Some(true) if budget > 0 => {
+                                let model = model.clone();
+                                begin_instantiation(cell, model, scene);
+                                budget -= 1;
+                                statistics.processed_nodes += 1;
+                            }
+                            Some(false) => {
+                                Log::writeln(
+                                    MessageKind::Error,
+                                    format!("Unable to load streaming cell {:?}!", cell.path),
+                                );
+                                cell.state = CellState::Failed;
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                CellState::Instantiating { .. } | CellState::Loaded { .. } => {
+                    if in_unload_range {
+                        begin_unload(cell, scene);
+                    }
+                }
+                CellState::Unloading | CellState::Failed => (),
+            }
+
+            match cell.state {
+                CellState::Instantiating { .. } => {
+                    let copied = copy_nodes(cell, scene, budget);
+                    budget -= copied;
+                    statistics.processed_nodes += copied;
+                    if cell.to_copy.is_empty() {
+                        finish_instantiation(cell, scene);
+                        statistics.instantiated += 1;
+                    }
+                }
+                CellState::Unloading => {
+                    let removed = remove_nodes(cell, scene, budget);
+                    budget -= removed;
+                    statistics.processed_nodes += removed;
+                    if cell.to_remove.is_empty() {
+                        cell.state = CellState::Unloaded;
+                        statistics.unloaded += 1;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        for cell in self.cells.iter() {
+            match cell.state {
+                CellState::Loading(_) => statistics.loading += 1,
+                CellState::Instantiating { .. } => statistics.instantiating += 1,
+                CellState::Loaded { .. } => statistics.loaded += 1,
+                CellState::Unloading => statistics.unloading += 1,
+                _ => (),
+            }
+        }
+
+        self.statistics = statistics;
+    }
+}
+
+// Copies root node of the model, the rest is copied by `copy_nodes`.
+fn begin_instantiation(cell: &mut StreamingCell, model: Model, scene: &mut Scene) {
+    let data = model.data_ref();
+    let graph = &data.get_scene().graph;
+
+    // Root is hidden until every node is copied, so a half-built cell won't be rendered.
+    let mut root_copy = graph[graph.get_root()].raw_copy();
+    root_copy.set_visibility(false);
+    let root = scene.graph.add_node(root_copy);
+
+    cell.old_to_new.clear();
+    cell.old_to_new.insert(graph.get_root(), root);
+    cell.to_copy.clear();
+    cell.to_copy.extend(
+        graph[graph.get_root()]
+            .children()
+            .iter()
+            .rev()
+            .map(|&child| (child, root)),
+    );
+
+    std::mem::drop(data);
+
+    cell.state = CellState::Instantiating { model, root };
+}
+
+// Copies at most `budget` nodes of the model of the cell, returns amount of copied nodes.
+fn copy_nodes(cell: &mut StreamingCell, scene: &mut Scene, budget: usize) -> usize {
+    let model = match cell.state {
+        CellState::Instantiating { ref model, .. } => model.clone(),
+        _ => return 0,
+    };
+    let data = model.data_ref();
+    let graph = &data.get_scene().graph;
+
+    let mut copied = 0;
+    while copied < budget {
+        if let Some((node, parent)) = cell.to_copy.pop() {
+            let copy = scene.graph.add_node(graph[node].raw_copy());
+            scene.graph.link_nodes(copy, parent);
+            cell.old_to_new.insert(node, copy);
+            cell.to_copy.extend(
+                graph[node]
+                    .children()
+                    .iter()
+                    .rev()
+                    .map(|&child| (child, copy)),
+            );
+            copied += 1;
+        } else {
+            break;
+        }
+    }
+
+    copied
+}
+
+fn finish_instantiation(cell: &mut StreamingCell, scene: &mut Scene) {
+    if let CellState::Instantiating { model, root } =
+        std::mem::replace(&mut cell.state, CellState::Unloaded)
+    {
+        let old_to_new = std::mem::take(&mut cell.old_to_new);
+        remap_handles(&old_to_new, &mut scene.graph);
+
+        let visibility = {
+            let data = model.data_ref();
+            let graph = &data.get_scene().graph;
+            graph[graph.get_root()].visibility()
+        };
+        scene.graph[root].set_visibility(visibility);
+
+        let navmeshes = model.finish_instantiation(scene, root, old_to_new);
+        let animations = model.retarget_animations(root, scene);
+
+        cell.state = CellState::Loaded {
+            model,
+            root,
+            animations,
+            navmeshes,
+        };
+    }
+}
+
+fn begin_unload(cell: &mut StreamingCell, scene: &mut Scene) {
+    let root = match std::mem::replace(&mut cell.state, CellState::Unloading) {
+        CellState::Loaded {
+            root,
+            animations,
+            navmeshes,
+            ..
+        } => {
+            for animation in animations {
+                if scene.animations.is_valid_handle(animation) {
+                    scene.animations.remove(animation);
+                }
+            }
+
+            for navmesh in navmeshes {
+                if scene.navmeshes.is_valid_handle(navmesh) {
+                    scene.navmeshes.remove(navmesh);
+                }
+            }
+
+            root
+        }
+        CellState::Instantiating { root, .. } => {
+            cell.to_copy.clear();
+            cell.old_to_new.clear();
+            root
+        }
+        state => {
+            cell.state = state;
+            return;
+        }
+    };
+
+    cell.to_remove.clear();
+    if scene.graph.is_valid_handle(root) {
+        scene.graph[root].set_visibility(false);
+        // Parents go before their descendants, so nodes are removed from the end.
+        cell.to_remove
+            .extend(scene.graph.traverse_handle_iter(root));
+    }
+}
+
+// Removes at most `budget` nodes of the cell, returns amount of removed nodes.
+fn remove_nodes(cell: &mut StreamingCell, scene: &mut Scene, budget: usize) -> usize {
+    let mut removed = 0;
+    while removed < budget {
+        if let Some(node) = cell.to_remove.pop() {
+            if scene.graph.is_valid_handle(node) {
+                // This also removes physics entities bound to the node.
+                scene.remove_node(node);
+            }
+            removed += 1;
+        } else {
+            break;
+        }
+    }
+    removed
+}
+
+fn unload(cell: &mut StreamingCell, scene: &mut Scene) {
+    begin_unload(cell, scene);
+    if let CellState::Unloading = cell.state {
+        remove_nodes(cell, scene, usize::MAX);
+        cell.state = CellState::Unloaded;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::Vector3,
+            futures::executor::block_on,
+            math::aabb::AxisAlignedBoundingBox,
+            pool::Handle,
+            visitor::{Visit, Visitor},
+        },
+        engine::resource_manager::{MaterialSearchOptions, ResourceManager},
+        scene::{
+            base::BaseBuilder,
+            node::Node,
+            streaming::{CellState, SceneStreamer, StreamingCell},
+            Scene,
+        },
+    };
+    use std::path::PathBuf;
+
+    // Saves a cell with `count` nodes, every node except the first one is a child of the
+    // previous one.
+    fn save_cell(name: &str, count: usize) -> PathBuf {
+        let mut scene = Scene::new();
+        let mut parent = Handle::NONE;
+        for i in 0..count {
+            let node = BaseBuilder::new()
+                .with_name(format!("{}_{}", name, i))
+                .build(&mut scene.graph);
+            if parent.is_some() {
+                scene.graph.link_nodes(node, parent);
+            }
+            parent = node;
+        }
+
+        let path = std::env::temp_dir().join(format!("{}_{}.rgs", std::process::id(), name));
+        let mut visitor = Visitor::new();
+        scene.visit("Scene", &mut visitor).unwrap();
+        visitor.save_binary(&path).unwrap();
+        path
+    }
+
+    fn cell_bounds(x: f32) -> AxisAlignedBoundingBox {
+        AxisAlignedBoundingBox::from_min_max(
+            Vector3::new(x, -1.0, -1.0),
+            Vector3::new(x + 2.0, 1.0, 1.0),
+        )
+    }
+
+    // Makes sure that a model is loaded, so streamer will get it immediately.
+    fn preload(resource_manager: &ResourceManager, path: &PathBuf) {
+        block_on(resource_manager.request_model(path, MaterialSearchOptions::UsePathDirectly))
+            .unwrap();
+    }
+
+    fn make_streamer(cells: &[(&PathBuf, f32)]) -> (SceneStreamer, Vec<Handle<StreamingCell>>) {
+        let mut streamer = SceneStreamer::new(5.0, 10.0);
+        streamer.set_material_search_options(MaterialSearchOptions::UsePathDirectly);
+        let handles = cells
+            .iter()
+            .map(|(path, x)| streamer.add_cell(path, cell_bounds(*x)))
+            .collect();
+        (streamer, handles)
+    }
+
+    fn instantiating_root(streamer: &SceneStreamer, cell: Handle<StreamingCell>) -> Handle<Node> {
+        match streamer.cell(cell).state() {
+            CellState::Instantiating { root, .. } => *root,
+            state => panic!("unexpected state {:?}", state),
+        }
+    }
+
+    #[test]
+    fn cells_follow_focus_point() {
+        let near = save_cell("rg3d_streaming_near", 3);
+        let far = save_cell("rg3d_streaming_far", 2);
+        let resource_manager = ResourceManager::new_headless();
+        preload(&resource_manager, &near);
+        preload(&resource_manager, &far);
+
+        let mut scene = Scene::new();
+        let (mut streamer, cells) = make_streamer(&[(&near, 0.0), (&far, 100.0)]);
+
+        // Request and instantiation.
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert_eq!(streamer.statistics().requested, 1);
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert_eq!(streamer.statistics().instantiated, 1);
+
+        assert!(streamer.cell(cells[0]).is_loaded());
+        assert!(matches!(
+            streamer.cell(cells[1]).state(),
+            CellState::Unloaded
+        ));
+        let root = streamer.cell(cells[0]).root();
+        assert!(scene.graph[root].visibility());
+        let node = scene.graph.find_by_name(root, "rg3d_streaming_near_2");
+        assert!(node.is_some());
+        // Root of the model and three nodes of the cell.
+        assert_eq!(scene.graph.node_count(), 5);
+
+        // Focus point moved between the radii, nothing must change.
+        streamer.update(&mut scene, &resource_manager, Vector3::new(9.0, 0.0, 0.0));
+        assert!(streamer.cell(cells[0]).is_loaded());
+
+        let focus = Vector3::new(100.0, 0.0, 0.0);
+        streamer.update(&mut scene, &resource_manager, focus);
+        assert_eq!(streamer.statistics().unloaded, 1);
+        assert_eq!(streamer.statistics().requested, 1);
+        streamer.update(&mut scene, &resource_manager, focus);
+        assert_eq!(streamer.statistics().instantiated, 1);
+
+        assert!(!scene.graph.is_valid_handle(node));
+        assert!(matches!(
+            streamer.cell(cells[0]).state(),
+            CellState::Unloaded
+        ));
+        assert!(streamer.cell(cells[1]).is_loaded());
+        assert_eq!(scene.graph.node_count(), 4);
+
+        streamer.unload_all(&mut scene);
+        assert_eq!(scene.graph.node_count(), 1);
+
+        let _ = std::fs::remove_file(near);
+        let _ = std::fs::remove_file(far);
+    }
+
+    #[test]
+    fn large_cell_is_processed_over_several_updates() {
+        let path = save_cell("rg3d_streaming_large", 9);
+        let resource_manager = ResourceManager::new_headless();
+        preload(&resource_manager, &path);
+
+        let mut scene = Scene::new();
+        let (mut streamer, cells) = make_streamer(&[(&path, 0.0)]);
+        streamer.set_max_nodes_per_update(4);
+
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+
+        // Ten nodes (root of the model and nine nodes) must be copied in three updates.
+        for copied in [4, 4] {
+            streamer.update(&mut scene, &resource_manager, Vector3::default());
+            assert_eq!(streamer.statistics().processed_nodes, copied);
+            assert_eq!(streamer.statistics().instantiating, 1);
+            let root = instantiating_root(&streamer, cells[0]);
+            assert!(!scene.graph[root].visibility());
+        }
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert_eq!(streamer.statistics().processed_nodes, 2);
+        assert_eq!(streamer.statistics().instantiated, 1);
+
+        let root = streamer.cell(cells[0]).root();
+        assert!(scene.graph[root].visibility());
+        assert_eq!(scene.graph.node_count(), 11);
+        // Hierarchy must be preserved.
+        let first = scene.graph.find_by_name(root, "rg3d_streaming_large_0");
+        let last = scene.graph.find_by_name(first, "rg3d_streaming_large_8");
+        assert!(last.is_some());
+        assert_eq!(
+            scene.graph[last].parent(),
+            scene.graph.find_by_name(root, "rg3d_streaming_large_7")
+        );
+
+        // Unload is spread too and remains are hidden.
+        let focus = Vector3::new(100.0, 0.0, 0.0);
+        streamer.update(&mut scene, &resource_manager, focus);
+        assert_eq!(streamer.statistics().processed_nodes, 4);
+        assert_eq!(streamer.statistics().unloading, 1);
+        assert!(!scene.graph[root].visibility());
+        streamer.update(&mut scene, &resource_manager, focus);
+        streamer.update(&mut scene, &resource_manager, focus);
+        assert_eq!(streamer.statistics().unloaded, 1);
+        assert!(matches!(
+            streamer.cell(cells[0]).state(),
+            CellState::Unloaded
+        ));
+        assert_eq!(scene.graph.node_count(), 1);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn budget_is_shared_between_cells() {
+        let a = save_cell("rg3d_streaming_shared_a", 3);
+        let b = save_cell("rg3d_streaming_shared_b", 3);
+        let resource_manager = ResourceManager::new_headless();
+        preload(&resource_manager, &a);
+        preload(&resource_manager, &b);
+
+        let mut scene = Scene::new();
+        let (mut streamer, _) = make_streamer(&[(&a, 0.0), (&b, 1.0)]);
+        streamer.set_max_nodes_per_update(6);
+
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert_eq!(streamer.statistics().processed_nodes, 6);
+        assert_eq!(streamer.statistics().instantiated, 1);
+        assert_eq!(streamer.statistics().instantiating, 1);
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert_eq!(streamer.statistics().processed_nodes, 2);
+        assert_eq!(streamer.statistics().loaded, 2);
+
+        let _ = std::fs::remove_file(a);
+        let _ = std::fs::remove_file(b);
+    }
+
+    #[test]
+    fn leaving_cell_during_instantiation_removes_copied_nodes() {
+        let path = save_cell("rg3d_streaming_interrupted", 9);
+        let resource_manager = ResourceManager::new_headless();
+        preload(&resource_manager, &path);
+
+        let mut scene = Scene::new();
+        let (mut streamer, cells) = make_streamer(&[(&path, 0.0)]);
+        streamer.set_max_nodes_per_update(4);
+
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert_eq!(scene.graph.node_count(), 5);
+
+        let focus = Vector3::new(100.0, 0.0, 0.0);
+        streamer.update(&mut scene, &resource_manager, focus);
+        assert_eq!(streamer.statistics().unloaded, 1);
+        assert!(matches!(
+            streamer.cell(cells[0]).state(),
+            CellState::Unloaded
+        ));
+        assert_eq!(scene.graph.node_count(), 1);
+
+        // Cell must be instantiated completely when focus point returns.
+        streamer.set_max_nodes_per_update(100);
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert!(streamer.cell(cells[0]).is_loaded());
+        assert_eq!(scene.graph.node_count(), 11);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn failed_cell_is_not_requested_again() {
+        let path =
+            std::env::temp_dir().join(format!("{}_rg3d_streaming_missing.rgs", std::process::id()));
+        let resource_manager = ResourceManager::new_headless();
+        let mut scene = Scene::new();
+        let (mut streamer, cells) = make_streamer(&[(&path, 0.0)]);
+
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        if let CellState::Loading(model) = streamer.cell(cells[0]).state() {
+            assert!(block_on(model.clone()).is_err());
+        }
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert!(matches!(streamer.cell(cells[0]).state(), CellState::Failed));
+
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert_eq!(streamer.statistics().requested, 0);
+
+        streamer.reset_failed();
+        streamer.update(&mut scene, &resource_manager, Vector3::default());
+        assert_eq!(streamer.statistics().requested, 1);
+    }
+}
@@ -0,0 +1,185 @@
+//! Contains all structures and methods to perform fixed-timestep scene updates.
+//!
+//! By default [Scene::update](crate::scene::Scene::update) steps everything with a delta time
+//! it receives, so simulation depends on frame rate. Fixed timestep accumulates frame time
+//! and advances the scene by whole ticks of constant duration, which makes simulation
+//! reproducible. Since ticks do not match frames, graph keeps global transforms of nodes from
+//! previous tick and renderer interpolates between previous and current transforms using
+//! [Graph::interpolation_factor](crate::scene::graph::Graph::interpolation_factor).
+
+use crate::core::{
+    algebra::{Matrix4, Rotation3, UnitQuaternion, Vector3},
+    math::Matrix4Ext,
+    visitor::{Visit, VisitResult, Visitor},
+};
+
+/// Fixed timestep accumulator. See module docs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FixedTimestep {
+    tick_rate: f32,
+    max_steps_per_update: u32,
+    accumulator: f32,
+    tick_count: u64,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(60.0, 5)
+    }
+}
+
+impl FixedTimestep {
+    /// Creates new fixed timestep with given tick rate (in Hz) and maximum amount of ticks that
+    /// can be performed during single update. The latter is used to prevent "spiral of death"
+    /// when simulation takes more time than it simulates - excessive time will be discarded.
+    pub fn new(tick_rate: f32, max_steps_per_update: u32) -> Self {
+        Self {
+            tick_rate: tick_rate.max(1.0),
+            max_steps_per_update: max_steps_per_update.max(1),
+            accumulator: 0.0,
+            tick_count: 0,
+        }
+    }
+
+    /// Sets new tick rate (in Hz).
+    pub fn set_tick_rate(&mut self, tick_rate: f32) {
+        self.tick_rate = tick_rate.max(1.0);
+    }
+
+    /// Returns current tick rate (in Hz).
+    pub fn tick_rate(&self) -> f32 {
+        self.tick_rate
+    }
+
+    /// Sets maximum amount of ticks that can be performed during single update.
+    pub fn set_max_steps_per_update(&mut self, max_steps: u32) {
+        self.max_steps_per_update = max_steps.max(1);
+    }
+
+    /// Returns maximum amount of ticks that can be performed during single update.
+    pub fn max_steps_per_update(&self) -> u32 {
+        self.max_steps_per_update
+    }
+
+    /// Returns duration of a single tick in seconds.
+    pub fn step_duration(&self) -> f32 {
+        1.0 / self.tick_rate
+    }
+
+    /// Returns total amount of ticks performed so far.
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    /// Returns a fraction of time between previous and next tick in \[0; 1\] range. It should be
+    /// used to interpolate between previous and current state of simulation.
+    pub fn interpolation_factor(&self) -> f32 {
+        (self.accumulator / self.step_duration()).min(1.0).max(0.0)
+    }
+
+    /// Adds frame time to the accumulator and returns amount of ticks that must be performed.
+    pub fn accumulate(&mut self, dt: f32) -> u32 {
+        let step = self.step_duration();
+
+        self.accumulator += dt.max(0.0);
+
+        let mut steps = 0;
+        while self.accumulator >= step && steps < self.max_steps_per_update {
+            self.accumulator -= step;
+            steps += 1;
+        }
+
+        // Discard time that cannot be simulated in this update.
+        if steps == self.max_steps_per_update {
+            self.accumulator = self.accumulator.min(step);
+        }
+
+        self.tick_count += steps as u64;
+
+        steps
+    }
+
+    /// Discards accumulated time.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+impl Visit for FixedTimestep {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.tick_rate.visit("TickRate", visitor)?;
+        self.max_steps_per_update.visit("MaxSteps", visitor)?;
+        self.accumulator.visit("Accumulator", visitor)?;
+        self.tick_count.visit("TickCount", visitor)?;
+
+        visitor.leave_region()
+    }
+}
+
+fn decompose(m: &Matrix4<f32>) -> (Vector3<f32>, UnitQuaternion<f32>, Vector3<f32>) {
+    let basis = m.basis();
+    let scale = Vector3::new(
+        basis.column(0).norm(),
+        basis.column(1).norm(),
+        basis.column(2).norm(),
+    );
+    let mut rotation_basis = basis;
+    for (i, s) in scale.iter().enumerate() {
+        if *s > f32::EPSILON {
+            rotation_basis.column_mut(i).unscale_mut(*s);
+        }
+    }
+    (
+        m.position(),
+        UnitQuaternion::from(Rotation3::from_matrix(&rotation_basis)),
+        scale,
+    )
+}
+
+/// Interpolates two transformation matrices. Matrices are decomposed into translation, rotation
+/// and scale, then each part is interpolated separately. Shear is not preserved.
+pub fn interpolate_transforms(from: &Matrix4<f32>, to: &Matrix4<f32>, t: f32) -> Matrix4<f32> {
+    let (from_position, from_rotation, from_scale) = decompose(from);
+    let (to_position, to_rotation, to_scale) = decompose(to);
+
+    Matrix4::new_translation(&from_position.lerp(&to_position, t))
+        * from_rotation.nlerp(&to_rotation, t).to_homogeneous()
+        * Matrix4::new_nonuniform_scaling(&from_scale.lerp(&to_scale, t))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Matrix4, Vector3},
+            math::Matrix4Ext,
+        },
+        scene::timestep::{interpolate_transforms, FixedTimestep},
+    };
+
+    #[test]
+    fn fixed_timestep_accumulate() {
+        let mut timestep = FixedTimestep::new(10.0, 3);
+
+        assert_eq!(timestep.accumulate(0.05), 0);
+        assert!((timestep.interpolation_factor() - 0.5).abs() < 1e-5);
+
+        assert_eq!(timestep.accumulate(0.06), 1);
+        assert!((timestep.interpolation_factor() - 0.1).abs() < 1e-4);
+
+        // Catch-up is limited, excessive time is discarded.
+        assert_eq!(timestep.accumulate(10.0), 3);
+        assert!(timestep.interpolation_factor() <= 1.0);
+        assert_eq!(timestep.tick_count(), 4);
+    }
+
+    #[test]
+    fn transform_interpolation() {
+        let a = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 0.0));
+        let b = Matrix4::new_translation(&Vector3::new(2.0, 4.0, 0.0));
+        let c = interpolate_transforms(&a, &b, 0.5);
+        assert!((c.position() - Vector3::new(1.0, 2.0, 0.0)).norm() < 1e-5);
+    }
+}
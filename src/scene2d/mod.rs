@@ -1,9 +1,10 @@
 use crate::core::algebra::ComplexField;
 use crate::{
     core::{
-        algebra::{Isometry2, Translation2, Vector2},
+        algebra::{Isometry2, Matrix4, Point2, Point3, Translation2, Vector2, Vector3},
         color::Color,
         instant,
+        math::{Matrix4Ext, Rect},
         pool::{Handle, Pool},
         visitor::prelude::*,
     },
@@ -15,6 +16,7 @@ use crate::{
 };
 use std::collections::HashMap;
 use std::{
+    fmt::{Display, Formatter},
     ops::{Index, IndexMut},
     sync::{Arc, Mutex},
 };
@@ -41,6 +43,183 @@ pub struct PerformanceStatistics {
     pub sound_update_time: f32,
 }
 
+impl Display for PerformanceStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\nGraph: {} ms\nSounds: {} ms",
+            self.physics,
+            self.graph_update_time * 1000.0,
+            self.sound_update_time * 1000.0
+        )
+    }
+}
+
+/// Colored line between two points.
+#[derive(Clone, Debug)]
+pub struct Line {
+    /// Beginning of the line.
+    pub begin: Vector2<f32>,
+    /// End of the line.
+    pub end: Vector2<f32>,
+    /// Color of the line.
+    pub color: Color,
+}
+
+/// Drawing context for simple 2D graphics, it allows you to draw simple figures using
+/// set of lines. Most common use is to draw some debug geometry in your game, draw
+/// physics info (contacts, shapes, joints, etc.) and so on.
+#[derive(Default, Clone, Debug)]
+pub struct SceneDrawingContext {
+    /// List of lines to draw.
+    pub lines: Vec<Line>,
+}
+
+impl SceneDrawingContext {
+    /// Draws a line between two points.
+    pub fn draw_line(&mut self, begin: Vector2<f32>, end: Vector2<f32>, color: Color) {
+        self.add_line(Line { begin, end, color });
+    }
+
+    /// Draws axis-aligned rectangle.
+    pub fn draw_rect(&mut self, rect: &Rect<f32>, color: Color) {
+        self.draw_polygon(
+            &[
+                rect.left_top_corner(),
+                rect.right_top_corner(),
+                rect.right_bottom_corner(),
+                rect.left_bottom_corner(),
+            ],
+            color,
+        );
+    }
+
+    /// Draws closed polygon by given points.
+    pub fn draw_polygon(&mut self, points: &[Vector2<f32>], color: Color) {
+        for (i, &begin) in points.iter().enumerate() {
+            let end = points[(i + 1) % points.len()];
+            self.draw_line(begin, end, color);
+        }
+    }
+
+    /// Draws circle using given amount of segments.
+    pub fn draw_circle(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        segments: usize,
+        color: Color,
+    ) {
+        self.draw_arc(
+            center,
+            radius,
+            0.0,
+            2.0 * std::f32::consts::PI,
+            segments,
+            color,
+        );
+    }
+
+    /// Draws an arc of a circle from `start_angle` to `end_angle` (in radians) using given amount
+    /// of segments.
+    pub fn draw_arc(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        segments: usize,
+        color: Color,
+    ) {
+        let segments = segments.max(1);
+        let step = (end_angle - start_angle) / segments as f32;
+        let point = |i: usize| {
+            let angle = start_angle + step * i as f32;
+            center + Vector2::new(angle.cos(), angle.sin()).scale(radius)
+        };
+        for i in 0..segments {
+            self.draw_line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Draws capsule between two points in given transform.
+    pub fn draw_capsule(
+        &mut self,
+        begin: Vector2<f32>,
+        end: Vector2<f32>,
+        radius: f32,
+        segments: usize,
+        transform: Matrix4<f32>,
+        color: Color,
+    ) {
+        let transform_point = |p: Vector2<f32>| {
+            transform
+                .transform_point(&Point3::new(p.x, p.y, 0.0))
+                .coords
+                .xy()
+        };
+
+        let axis = end - begin;
+        let angle = axis.y.atan2(axis.x);
+        let half_turn = std::f32::consts::PI;
+        let segments = segments.max(1);
+
+        // Caps are half-circles around the ends, their last points are connected by sides.
+        let mut points = Vec::with_capacity(2 * segments + 2);
+        for &(center, start_angle) in [
+            (end, angle - half_turn * 0.5),
+            (begin, angle + half_turn * 0.5),
+        ]
+        .iter()
+        {
+            for i in 0..=segments {
+                let a = start_angle + half_turn * i as f32 / segments as f32;
+                points.push(transform_point(
+                    center + Vector2::new(a.cos(), a.sin()).scale(radius),
+                ));
+            }
+        }
+
+        self.draw_polygon(&points, color);
+    }
+
+    /// Draws transform as basis vectors.
+    pub fn draw_transform(&mut self, matrix: Matrix4<f32>) {
+        let x = matrix.transform_vector(&Vector3::x()).xy();
+        let y = matrix.transform_vector(&Vector3::y()).xy();
+        let origin = matrix.position().xy();
+        self.draw_line(origin, origin + x, Color::RED);
+        self.draw_line(origin, origin + y, Color::GREEN);
+    }
+
+    /// Draws a cross at given point, it is useful to mark some positions, for example contact
+    /// points.
+    pub fn draw_cross(&mut self, point: Point2<f32>, size: f32, color: Color) {
+        let half_size = size * 0.5;
+        self.draw_line(
+            point.coords - Vector2::new(half_size, half_size),
+            point.coords + Vector2::new(half_size, half_size),
+            color,
+        );
+        self.draw_line(
+            point.coords - Vector2::new(half_size, -half_size),
+            point.coords + Vector2::new(half_size, -half_size),
+            color,
+        );
+    }
+
+    /// Adds single line into internal buffer.
+    pub fn add_line(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+
+    /// Removes all lines from internal buffer. For dynamic drawing you should call it
+    /// every update tick of your application.
+    pub fn clear_lines(&mut self) {
+        self.lines.clear()
+    }
+}
+
 #[derive(Visit, Default)]
 pub struct Scene2d {
     pub graph: Graph,
@@ -58,6 +237,10 @@ pub struct Scene2d {
 
     pub physics_binder: PhysicsBinder<Node>,
 
+    /// Drawing context for simple graphics.
+    #[visit(skip)]
+    pub drawing_context: SceneDrawingContext,
+
     #[visit(skip)]
     pub performance_statistics: PerformanceStatistics,
 }
@@ -72,12 +255,13 @@ impl Scene2d {
             sound_context: SoundContext::new(),
             physics: Default::default(),
             physics_binder: Default::default(),
+            drawing_context: Default::default(),
             performance_statistics: Default::default(),
         }
     }
 
     fn update_physics(&mut self) {
-        self.physics.step();
+        self.physics.step(&self.physics_binder);
 
         self.performance_statistics.physics = self.physics.performance_statistics.clone();
         self.physics.performance_statistics.reset();
@@ -161,6 +345,7 @@ impl Scene2d {
                 // will redraw frame completely.
                 render_target: Default::default(),
                 sound_context: self.sound_context.deep_clone(),
+                drawing_context: self.drawing_context.clone(),
                 performance_statistics: Default::default(),
                 ambient_light_color: self.ambient_light_color,
                 enabled: self.enabled,
@@ -236,3 +421,152 @@ impl IndexMut<Handle<Scene2d>> for Scene2dContainer {
         &mut self.pool[index]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Matrix4, Point2, Vector2, Vector3},
+            color::Color,
+            math::Rect,
+        },
+        scene2d::{Line, SceneDrawingContext},
+    };
+
+    fn approx_eq(a: Vector2<f32>, b: Vector2<f32>) -> bool {
+        (a - b).norm() < 1.0e-4
+    }
+
+    fn distance_to_segment(p: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+        let ab = b - a;
+        let t = ((p - a).dot(&ab) / ab.norm_squared()).clamp(0.0, 1.0);
+        (p - (a + ab.scale(t))).norm()
+    }
+
+    fn assert_closed_loop(lines: &[Line]) {
+        for (i, line) in lines.iter().enumerate() {
+            let next = &lines[(i + 1) % lines.len()];
+            assert!(approx_eq(line.end, next.begin), "gap after line {}", i);
+        }
+    }
+
+    #[test]
+    fn rect_is_closed_loop_of_corners() {
+        let mut context = SceneDrawingContext::default();
+        context.draw_rect(&Rect::new(1.0, 2.0, 3.0, 4.0), Color::WHITE);
+
+        assert_eq!(context.lines.len(), 4);
+        assert_closed_loop(&context.lines);
+        let corners = context.lines.iter().map(|l| l.begin).collect::<Vec<_>>();
+        assert_eq!(
+            corners,
+            vec![
+                Vector2::new(1.0, 2.0),
+                Vector2::new(4.0, 2.0),
+                Vector2::new(4.0, 6.0),
+                Vector2::new(1.0, 6.0),
+            ]
+        );
+        assert!(context.lines.iter().all(|l| l.color == Color::WHITE));
+    }
+
+    #[test]
+    fn circle_points_lie_on_circle() {
+        let mut context = SceneDrawingContext::default();
+        let center = Vector2::new(-1.0, 3.0);
+        context.draw_circle(center, 2.0, 12, Color::WHITE);
+
+        assert_eq!(context.lines.len(), 12);
+        assert_closed_loop(&context.lines);
+        for line in context.lines.iter() {
+            assert!(((line.begin - center).norm() - 2.0).abs() < 1.0e-4);
+            assert!(((line.end - center).norm() - 2.0).abs() < 1.0e-4);
+        }
+    }
+
+    #[test]
+    fn arc_spans_given_angles() {
+        let mut context = SceneDrawingContext::default();
+        let center = Vector2::new(1.0, 1.0);
+        context.draw_arc(
+            center,
+            2.0,
+            0.0,
+            std::f32::consts::FRAC_PI_2,
+            4,
+            Color::WHITE,
+        );
+
+        assert_eq!(context.lines.len(), 4);
+        assert!(approx_eq(context.lines[0].begin, Vector2::new(3.0, 1.0)));
+        assert!(approx_eq(context.lines[3].end, Vector2::new(1.0, 3.0)));
+        for pair in context.lines.windows(2) {
+            assert!(approx_eq(pair[0].end, pair[1].begin));
+        }
+    }
+
+    #[test]
+    fn capsule_outline_surrounds_segment() {
+        let mut context = SceneDrawingContext::default();
+        let begin = Vector2::new(0.0, -1.0);
+        let end = Vector2::new(0.0, 1.0);
+        let offset = Vector2::new(5.0, 2.0);
+        context.draw_capsule(
+            begin,
+            end,
+            0.5,
+            6,
+            Matrix4::new_translation(&Vector3::new(offset.x, offset.y, 0.0)),
+            Color::WHITE,
+        );
+
+        assert_eq!(context.lines.len(), 14);
+        assert_closed_loop(&context.lines);
+        for line in context.lines.iter() {
+            let distance = distance_to_segment(line.begin, begin + offset, end + offset);
+            assert!((distance - 0.5).abs() < 1.0e-4);
+        }
+        // Both caps must be present.
+        assert!(context
+            .lines
+            .iter()
+            .any(|l| approx_eq(l.begin, Vector2::new(5.0, 3.5))));
+        assert!(context
+            .lines
+            .iter()
+            .any(|l| approx_eq(l.begin, Vector2::new(5.0, 0.5))));
+    }
+
+    #[test]
+    fn cross_is_centered_at_point() {
+        let mut context = SceneDrawingContext::default();
+        context.draw_cross(Point2::new(2.0, -1.0), 0.5, Color::RED);
+
+        assert_eq!(context.lines.len(), 2);
+        for line in context.lines.iter() {
+            assert!(approx_eq(
+                (line.begin + line.end).scale(0.5),
+                Vector2::new(2.0, -1.0)
+            ));
+            assert!(((line.end - line.begin).norm() - 0.5 * 2.0f32.sqrt()).abs() < 1.0e-4);
+        }
+    }
+
+    #[test]
+    fn transform_is_drawn_as_basis_vectors() {
+        let mut context = SceneDrawingContext::default();
+        let matrix = Matrix4::new_translation(&Vector3::new(1.0, 2.0, 0.0))
+            * Matrix4::new_rotation(Vector3::new(0.0, 0.0, std::f32::consts::FRAC_PI_2));
+        context.draw_transform(matrix);
+
+        assert_eq!(context.lines.len(), 2);
+        let (x, y) = (&context.lines[0], &context.lines[1]);
+        assert_eq!((x.color, y.color), (Color::RED, Color::GREEN));
+        assert!(approx_eq(x.begin, Vector2::new(1.0, 2.0)));
+        assert!(approx_eq(x.end, Vector2::new(1.0, 3.0)));
+        assert!(approx_eq(y.end, Vector2::new(0.0, 2.0)));
+
+        context.clear_lines();
+        assert!(context.lines.is_empty());
+    }
+}
@@ -4,38 +4,44 @@ use crate::core::algebra::{Const, DVector};
 use crate::{
     core::{
         algebra::{
-            Dynamic, Isometry2, Point2, Translation, Translation2, Unit, UnitComplex, VecStorage,
-            Vector2,
+            Dynamic, Isometry2, Matrix3, Matrix4, Point2, Translation, Translation2, Unit,
+            UnitComplex, VecStorage, Vector2, Vector3,
         },
         arrayvec::ArrayVec,
+        color::Color,
         instant,
         math::ray::Ray,
-        pool::ErasedHandle,
+        pool::{ErasedHandle, Handle},
         uuid::Uuid,
-        visitor::prelude::*,
+        visitor::{prelude::*, PodVecView},
         BiDirHashMap,
     },
-    engine::{ColliderHandle, JointHandle, RigidBodyHandle},
+    engine::{ColliderHandle, JointHandle, PhysicsBinder, RigidBodyHandle},
+    scene2d::{node::Node, SceneDrawingContext},
+    utils::log::{Log, MessageKind},
 };
 use rapier2d::dynamics::{IslandManager, RigidBodyType};
 use rapier2d::{
+    data::ComponentSet,
     dynamics::{
         BallJoint, CCDSolver, FixedJoint, IntegrationParameters, Joint, JointParams, JointSet,
-        PrismaticJoint, RigidBody, RigidBodyBuilder, RigidBodySet,
+        MassProperties, PrismaticJoint, RigidBody, RigidBodyActivation, RigidBodyBuilder,
+        RigidBodyForces, RigidBodySet, SpringModel,
     },
     geometry::{
-        BroadPhase, Collider, ColliderBuilder, ColliderSet, InteractionGroups, NarrowPhase,
-        Segment, Shape,
+        BroadPhase, Collider, ColliderBuilder, ColliderSet, ContactEvent,
+        ContactPair, InteractionGroups, IntersectionEvent, NarrowPhase, Segment, Shape,
     },
     parry::shape::{FeatureId, SharedShape},
-    pipeline::{EventHandler, PhysicsPipeline, QueryPipeline},
+    pipeline::{ActiveEvents, EventHandler, PhysicsPipeline, QueryPipeline},
 };
 use std::{
-    cell::{Cell, RefCell},
+    cell::{Cell, RefCell, RefMut},
     cmp::Ordering,
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt::{Debug, Display, Formatter},
     hash::Hash,
+    sync::Mutex,
     time::Duration,
 };
 
@@ -74,6 +80,132 @@ pub struct RayCastOptions {
     pub sort_results: bool,
 }
 
+/// A shape cast result.
+#[derive(Debug, Clone)]
+pub struct ShapeCastResult {
+    /// A handle of the collider that was hit first.
+    pub collider: ColliderHandle,
+
+    /// Distance that the shape has traveled before the hit.
+    pub toi: f32,
+
+    /// A point on the surface of the collider where the hit occurred, in world coordinates.
+    pub position: Point2<f32>,
+
+    /// A normal of the collider at the hit position, in world coordinates.
+    pub normal: Vector2<f32>,
+}
+
+/// A point projection result.
+#[derive(Debug, Clone)]
+pub struct PointProjection {
+    /// A handle of the closest collider.
+    pub collider: ColliderHandle,
+
+    /// Projected point on the surface of the collider, in world coordinates.
+    pub position: Point2<f32>,
+
+    /// Whether the point was inside of the collider or not.
+    pub is_inside: bool,
+}
+
+/// Maximum amount of events in the queue, new events are discarded if the queue is full.
+pub const MAX_QUEUED_EVENTS: usize = 1024;
+
+/// Kind of physics event.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PhysicsEventKind {
+    /// Colliders started to touch each other. The event is reported one simulation step after
+    /// the contact was detected, see [`ContactPoint::impulse`].
+    ContactStarted,
+    /// Colliders stopped touching each other.
+    ContactStopped,
+    /// Colliders started to intersect each other, at least one of them is a sensor.
+    IntersectionStarted,
+    /// Colliders stopped intersecting each other, at least one of them is a sensor.
+    IntersectionStopped,
+}
+
+/// A point of contact between two colliders.
+#[derive(Copy, Clone, Debug)]
+pub struct ContactPoint {
+    /// Position of the point in world coordinates, it lies on the surface of first collider.
+    pub position: Point2<f32>,
+    /// Contact normal in world coordinates, points from first collider to second.
+    pub normal: Vector2<f32>,
+    /// Penetration depth, negative values means that colliders are separated.
+    pub depth: f32,
+    /// Impulse along the normal that was applied by solver to separate the colliders.
+    /// Contacts are detected at the end of simulation step, so it is zero for just started
+    /// contacts. This is why [`PhysicsEventKind::ContactStarted`] events are reported at the end
+    /// of the next step, with impulses of that step. It is still zero if the contact has ended
+    /// before the solver processed it.
+    pub impulse: f32,
+}
+
+/// Contact or intersection event. Colliders generate events only if they have
+/// `ActiveEvents::CONTACT_EVENTS` and/or `ActiveEvents::INTERSECTION_EVENTS` flags set.
+#[derive(Clone, Debug)]
+pub struct PhysicsEvent {
+    /// Kind of the event.
+    pub kind: PhysicsEventKind,
+    /// First collider of the pair.
+    pub collider1: ColliderHandle,
+    /// Second collider of the pair.
+    pub collider2: ColliderHandle,
+    /// Node that is bound to parent rigid body of first collider, or [`Handle::NONE`] if there
+    /// is no such node.
+    pub node1: Handle<Node>,
+    /// Node that is bound to parent rigid body of second collider, or [`Handle::NONE`] if there
+    /// is no such node.
+    pub node2: Handle<Node>,
+    /// Contact points, filled only for [`PhysicsEventKind::ContactStarted`].
+    pub contacts: Vec<ContactPoint>,
+}
+
+#[derive(Copy, Clone)]
+struct RawEvent {
+    kind: PhysicsEventKind,
+    collider1: rapier2d::geometry::ColliderHandle,
+    collider2: rapier2d::geometry::ColliderHandle,
+}
+
+/// Collects events from rapier and passes them to user-defined handler.
+struct EventCollector<'a> {
+    events: &'a Mutex<Vec<RawEvent>>,
+    handler: &'a dyn EventHandler,
+}
+
+impl<'a> EventHandler for EventCollector<'a> {
+    fn handle_intersection_event(&self, event: IntersectionEvent) {
+        self.events.lock().unwrap().push(RawEvent {
+            kind: if event.intersecting {
+                PhysicsEventKind::IntersectionStarted
+            } else {
+                PhysicsEventKind::IntersectionStopped
+            },
+            collider1: event.collider1,
+            collider2: event.collider2,
+        });
+
+        self.handler.handle_intersection_event(event);
+    }
+
+    fn handle_contact_event(&self, event: ContactEvent, contact_pair: &ContactPair) {
+        let (kind, collider1, collider2) = match event {
+            ContactEvent::Started(a, b) => (PhysicsEventKind::ContactStarted, a, b),
+            ContactEvent::Stopped(a, b) => (PhysicsEventKind::ContactStopped, a, b),
+        };
+        self.events.lock().unwrap().push(RawEvent {
+            kind,
+            collider1,
+            collider2,
+        });
+
+        self.handler.handle_contact_event(event, contact_pair);
+    }
+}
+
 /// Physics world.
 pub struct Physics {
     /// Current physics pipeline.
@@ -101,9 +233,17 @@ pub struct Physics {
     /// A set of joints.
     joints: JointSet,
 
-    /// Event handler collects info about contacts and proximity events.
+    /// Custom event handler, it receives contact and intersection events in addition to the
+    /// built-in event queue (see [`Self::pop_event`]).
     pub event_handler: Box<dyn EventHandler>,
 
+    raw_events: Mutex<Vec<RawEvent>>,
+
+    events: VecDeque<PhysicsEvent>,
+
+    // Contact started events are reported one step later, when solver has calculated impulses.
+    started_contacts: Vec<(RawEvent, PhysicsEvent)>,
+
     /// Descriptors have two purposes:
     /// 1) Defer deserialization to resolve stage - the stage where all meshes
     ///    were loaded and there is a possibility to obtain data for trimeshes.
@@ -169,6 +309,38 @@ impl QueryResultsStorage for Vec<Intersection> {
     }
 }
 
+/// A trait for storages of colliders found by overlap tests. Just like [`QueryResultsStorage`]
+/// it has two implementations: Vec and ArrayVec.
+pub trait ColliderQueryStorage {
+    /// Pushes new collider handle in the storage. Returns true if the handle was
+    /// successfully inserted, false otherwise.
+    fn push(&mut self, collider: ColliderHandle) -> bool;
+
+    /// Clears the storage.
+    fn clear(&mut self);
+}
+
+impl ColliderQueryStorage for Vec<ColliderHandle> {
+    fn push(&mut self, collider: ColliderHandle) -> bool {
+        self.push(collider);
+        true
+    }
+
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
+impl<const CAP: usize> ColliderQueryStorage for ArrayVec<ColliderHandle, CAP> {
+    fn push(&mut self, collider: ColliderHandle) -> bool {
+        self.try_push(collider).is_ok()
+    }
+
+    fn clear(&mut self) {
+        self.clear()
+    }
+}
+
 impl<const CAP: usize> QueryResultsStorage for ArrayVec<Intersection, CAP> {
     fn push(&mut self, intersection: Intersection) -> bool {
         self.try_push(intersection).is_ok()
@@ -213,6 +385,12 @@ impl PhysicsPerformanceStatistics {
     }
 }
 
+fn isometry_to_matrix(isometry: &Isometry2<f32>) -> Matrix4<f32> {
+    let translation = isometry.translation.vector;
+    Matrix4::new_translation(&Vector3::new(translation.x, translation.y, 0.0))
+        * Matrix3::new_rotation(isometry.rotation.angle()).to_homogeneous()
+}
+
 macro_rules! impl_convert_map {
     ($name:ident, $key:ty, $value:ty) => {
         pub fn $name(
@@ -261,6 +439,9 @@ impl Physics {
             colliders: ColliderSet::new(),
             joints: JointSet::new(),
             event_handler: Box::new(()),
+            raw_events: Default::default(),
+            started_contacts: Default::default(),
+            events: Default::default(),
             query: Default::default(),
             desc: Default::default(),
             performance_statistics: Default::default(),
@@ -378,9 +559,151 @@ impl Physics {
             .and_then(|c| self.body_handle_map.key_of(&c.parent().unwrap()))
     }
 
-    pub(in crate) fn step(&mut self) {
+    /// Draws physics world. Very useful for debugging, it allows you to see where are
+    /// rigid bodies, which colliders they have, how they are connected with joints and
+    /// where are contact points.
+    pub fn draw(&self, context: &mut SceneDrawingContext) {
+        let shape_color = Color::opaque(200, 200, 200);
+        let joint_color = Color::opaque(255, 200, 0);
+
+        for (_, body) in self.bodies.iter() {
+            context.draw_transform(isometry_to_matrix(body.position()));
+        }
+
+        for (_, collider) in self.colliders.iter() {
+            let position = collider.position();
+            let transform_point = |p: &Point2<f32>| (position * p).coords;
+            let shape = collider.shape();
+            if let Some(ball) = shape.as_ball() {
+                context.draw_circle(position.translation.vector, ball.radius, 16, shape_color);
+            } else if let Some(cuboid) = shape.as_cuboid() {
+                let e = cuboid.half_extents;
+                context.draw_polygon(
+                    &[
+                        transform_point(&Point2::new(-e.x, -e.y)),
+                        transform_point(&Point2::new(e.x, -e.y)),
+                        transform_point(&Point2::new(e.x, e.y)),
+                        transform_point(&Point2::new(-e.x, e.y)),
+                    ],
+                    shape_color,
+                );
+            } else if let Some(capsule) = shape.as_capsule() {
+                context.draw_capsule(
+                    capsule.segment.a.coords,
+                    capsule.segment.b.coords,
+                    capsule.radius,
+                    8,
+                    isometry_to_matrix(position),
+                    shape_color,
+                );
+            } else if let Some(segment) = shape.as_segment() {
+                context.draw_line(
+                    transform_point(&segment.a),
+                    transform_point(&segment.b),
+                    shape_color,
+                );
+            } else if let Some(triangle) = shape.as_triangle() {
+                context.draw_polygon(
+                    &[
+                        transform_point(&triangle.a),
+                        transform_point(&triangle.b),
+                        transform_point(&triangle.c),
+                    ],
+                    shape_color,
+                );
+            } else if let Some(polygon) = shape.as_convex_polygon() {
+                let points = polygon
+                    .points()
+                    .iter()
+                    .map(transform_point)
+                    .collect::<Vec<_>>();
+                context.draw_polygon(&points, shape_color);
+            } else if let Some(trimesh) = shape.as_trimesh() {
+                for triangle in trimesh.triangles() {
+                    context.draw_polygon(
+                        &[
+                            transform_point(&triangle.a),
+                            transform_point(&triangle.b),
+                            transform_point(&triangle.c),
+                        ],
+                        shape_color,
+                    );
+                }
+            } else if let Some(polyline) = shape.as_polyline() {
+                for segment in polyline.segments() {
+                    context.draw_line(
+                        transform_point(&segment.a),
+                        transform_point(&segment.b),
+                        shape_color,
+                    );
+                }
+            } else if let Some(heightfield) = shape.as_heightfield() {
+                for segment in heightfield.segments() {
+                    context.draw_line(
+                        transform_point(&segment.a),
+                        transform_point(&segment.b),
+                        shape_color,
+                    );
+                }
+            }
+        }
+
+        for (_, joint) in self.joints.iter() {
+            let (body1, body2) = match (self.bodies.get(joint.body1), self.bodies.get(joint.body2))
+            {
+                (Some(body1), Some(body2)) => (body1.position(), body2.position()),
+                _ => continue,
+            };
+            let (anchor1, anchor2) = match &joint.params {
+                JointParams::BallJoint(v) => (body1 * v.local_anchor1, body2 * v.local_anchor2),
+                JointParams::FixedJoint(v) => (
+                    body1 * Point2::from(v.local_frame1.translation.vector),
+                    body2 * Point2::from(v.local_frame2.translation.vector),
+                ),
+                JointParams::PrismaticJoint(v) => {
+                    let anchor1 = body1 * v.local_anchor1;
+                    let axis = body1 * v.local_axis1().into_inner();
+                    context.draw_line(anchor1.coords, anchor1.coords + axis, Color::BLUE);
+                    (anchor1, body2 * v.local_anchor2)
+                }
+            };
+            context.draw_line(body1.translation.vector, anchor1.coords, joint_color);
+            context.draw_line(anchor1.coords, anchor2.coords, joint_color);
+            context.draw_line(anchor2.coords, body2.translation.vector, joint_color);
+        }
+
+        for pair in self.narrow_phase.contact_pairs() {
+            let collider1 = match self.colliders.get(pair.collider1) {
+                Some(collider1) => collider1,
+                None => continue,
+            };
+            for manifold in pair.manifolds.iter() {
+                for point in manifold.points.iter().filter(|p| p.dist <= 0.0) {
+                    let local_position = manifold
+                        .subshape_pos1
+                        .map_or(point.local_p1, |subshape_position| {
+                            subshape_position * point.local_p1
+                        });
+                    let position = collider1.position() * local_position;
+                    context.draw_cross(position, 0.1, Color::RED);
+                    context.draw_line(
+                        position.coords,
+                        position.coords + manifold.data.normal.scale(0.25),
+                        Color::RED,
+                    );
+                }
+            }
+        }
+    }
+
+    pub(in crate) fn step(&mut self, binder: &PhysicsBinder<Node>) {
         let time = instant::Instant::now();
 
+        let event_collector = EventCollector {
+            events: &self.raw_events,
+            handler: &*self.event_handler,
+        };
+
         self.pipeline.step(
             &self.gravity,
             &self.integration_parameters,
@@ -392,12 +715,118 @@ impl Physics {
             &mut self.joints,
             &mut self.ccd_solver,
             &(),
-            &*self.event_handler,
+            &event_collector,
         );
 
+        // Contacts that were detected at the end of previous step were processed by the solver
+        // during this step, so their points have impulses now.
+        for (raw_event, mut event) in std::mem::take(&mut self.started_contacts) {
+            let contacts = self.contact_points(&raw_event);
+            if !contacts.is_empty() {
+                event.contacts = contacts;
+            }
+            self.push_event(event);
+        }
+
+        let raw_events = std::mem::take(&mut *self.raw_events.lock().unwrap());
+        for raw_event in raw_events {
+            if let Some(event) = self.convert_event(raw_event, binder) {
+                match raw_event.kind {
+                    PhysicsEventKind::ContactStarted => {
+                        self.started_contacts.push((raw_event, event))
+                    }
+                    PhysicsEventKind::ContactStopped => {
+                        // Contact has ended in the same step it started, so there is nothing
+                        // to wait for.
+                        if let Some(index) = self.started_contacts.iter().position(|(e, _)| {
+                            (e.collider1, e.collider2) == (raw_event.collider1, raw_event.collider2)
+                                || (e.collider1, e.collider2)
+                                    == (raw_event.collider2, raw_event.collider1)
+                        }) {
+                            let (_, started) = self.started_contacts.remove(index);
+                            self.push_event(started);
+                        }
+                        self.push_event(event);
+                    }
+                    _ => self.push_event(event),
+                }
+            }
+        }
+
         self.performance_statistics.step_time += instant::Instant::now() - time;
     }
 
+    fn convert_event(
+        &self,
+        raw_event: RawEvent,
+        binder: &PhysicsBinder<Node>,
+    ) -> Option<PhysicsEvent> {
+        let collider1 = *self.collider_handle_map.key_of(&raw_event.collider1)?;
+        let collider2 = *self.collider_handle_map.key_of(&raw_event.collider2)?;
+
+        let node_of = |collider: &ColliderHandle| {
+            self.collider_parent(collider)
+                .and_then(|body| binder.node_of(*body))
+                .unwrap_or_default()
+        };
+
+        let contacts = if raw_event.kind == PhysicsEventKind::ContactStarted {
+            self.contact_points(&raw_event)
+        } else {
+            Vec::new()
+        };
+
+        Some(PhysicsEvent {
+            kind: raw_event.kind,
+            collider1,
+            collider2,
+            node1: node_of(&collider1),
+            node2: node_of(&collider2),
+            contacts,
+        })
+    }
+
+    fn push_event(&mut self, event: PhysicsEvent) {
+        if self.events.len() < MAX_QUEUED_EVENTS {
+            self.events.push_back(event);
+        }
+    }
+
+    fn contact_points(&self, raw_event: &RawEvent) -> Vec<ContactPoint> {
+        let mut contacts = Vec::new();
+        if let (Some(pair), Some(native_collider1)) = (
+            self.narrow_phase
+                .contact_pair(raw_event.collider1, raw_event.collider2),
+            self.colliders.get(raw_event.collider1),
+        ) {
+            for manifold in pair.manifolds.iter() {
+                for point in manifold.points.iter() {
+                    let local_position = manifold
+                        .subshape_pos1
+                        .map_or(point.local_p1, |subshape_position| {
+                            subshape_position * point.local_p1
+                        });
+                    contacts.push(ContactPoint {
+                        position: native_collider1.position() * local_position,
+                        normal: manifold.data.normal,
+                        depth: -point.dist,
+                        impulse: point.data.impulse,
+                    });
+                }
+            }
+        }
+        contacts
+    }
+
+    /// Pops oldest contact or intersection event from the queue. Events are accumulated during
+    /// simulation steps, so the queue should be drained every frame, otherwise new events will
+    /// be discarded when the queue is full.
+    pub fn pop_event(&mut self) -> Option<PhysicsEvent> {
+        self.events.pop_front()
+    }
+
+    /// Generates descriptors of the world. Solver state is not included, it is expensive to
+    /// serialize and needed only in saved files, see [`Self::generate_desc_for_saving`].
     #[doc(hidden)]
     pub fn generate_desc(&self) -> PhysicsDesc {
         let body_dense_map = self
@@ -466,7 +895,15 @@ impl Physics {
             bodies: self
                 .bodies
                 .iter()
-                .map(|(_, b)| RigidBodyDesc::from_body(b, &self.collider_handle_map))
+                .map(|(h, b)| {
+                    // Accumulated forces can be fetched only from the set.
+                    let forces: &RigidBodyForces = self.bodies.index(h.0);
+                    RigidBodyDesc {
+                        force: forces.force,
+                        torque: forces.torque,
+                        ..RigidBodyDesc::from_body(b, &self.collider_handle_map)
+                    }
+                })
                 .collect::<Vec<_>>(),
 
             colliders: self
@@ -486,9 +923,124 @@ impl Physics {
             body_handle_map,
             collider_handle_map,
             joint_handle_map,
+
+            solver_state: Default::default(),
+        }
+    }
+
+    // Same as `generate_desc`, but with exact state of the solver. Loaded world falls back to
+    // descriptors if the state is missing or cannot be read.
+    pub(in crate) fn generate_desc_for_saving(&self) -> PhysicsDesc {
+        PhysicsDesc {
+            solver_state: self.serialize_solver_state(),
+            ..self.generate_desc()
+        }
+    }
+
+    // Serializes exact state of the solver, so contacts and warm-starting data will survive
+    // save/load cycle.
+    fn serialize_solver_state(&self) -> Vec<u8> {
+        match bincode::serialize(&(
+            &self.bodies,
+            &self.colliders,
+            &self.joints,
+            &self.islands,
+            &self.broad_phase,
+            &self.narrow_phase,
+            &self.ccd_solver,
+        )) {
+            Ok(data) => data,
+            Err(e) => {
+                Log::writeln(
+                    MessageKind::Warning,
+                    format!(
+                        "Unable to serialize solver state, only descriptors will be saved: {:?}",
+                        e
+                    ),
+                );
+                Vec::new()
+            }
         }
     }
 
+    // Restores exact state of the solver. Returns false if the state is missing or cannot be
+    // read, in this case the world must be restored from descriptors.
+    fn resolve_solver_state(&mut self, phys_desc: &PhysicsDesc) -> bool {
+        if phys_desc.solver_state.is_empty() {
+            return false;
+        }
+
+        let (bodies, colliders, joints, islands, broad_phase, narrow_phase, ccd_solver) =
+            match bincode::deserialize::<(
+                RigidBodySet,
+                ColliderSet,
+                JointSet,
+                IslandManager,
+                BroadPhase,
+                NarrowPhase,
+                CCDSolver,
+            )>(&phys_desc.solver_state)
+            {
+                Ok(state) => state,
+                Err(e) => {
+                    Log::writeln(
+                        MessageKind::Warning,
+                        format!(
+                            "Unable to read solver state, descriptors will be used instead: {:?}",
+                            e
+                        ),
+                    );
+                    return false;
+                }
+            };
+
+        // Descriptors are stored in the same order as items in the sets, so handles in the maps
+        // of descriptors are indices of actual handles.
+        let body_handles = bodies.iter().map(|(h, _)| h).collect::<Vec<_>>();
+        let collider_handles = colliders.iter().map(|(h, _)| h).collect::<Vec<_>>();
+        let joint_handles = joints.iter().map(|(h, _)| h).collect::<Vec<_>>();
+        if body_handles.len() != phys_desc.bodies.len()
+            || collider_handles.len() != phys_desc.colliders.len()
+            || joint_handles.len() != phys_desc.joints.len()
+        {
+            Log::writeln(
+                MessageKind::Warning,
+                "Solver state does not match descriptors, descriptors will be used instead."
+                    .to_owned(),
+            );
+            return false;
+        }
+
+        self.body_handle_map = phys_desc
+            .body_handle_map
+            .forward_map()
+            .iter()
+            .map(|(k, v)| (*k, body_handles[v.index() as usize]))
+            .collect();
+        self.collider_handle_map = phys_desc
+            .collider_handle_map
+            .forward_map()
+            .iter()
+            .map(|(k, v)| (*k, collider_handles[v.index() as usize]))
+            .collect();
+        self.joint_handle_map = phys_desc
+            .joint_handle_map
+            .forward_map()
+            .iter()
+            .map(|(k, v)| (*k, joint_handles[v.index() as usize]))
+            .collect();
+
+        self.bodies = bodies;
+        self.colliders = colliders;
+        self.joints = joints;
+        self.islands = islands;
+        self.broad_phase = broad_phase;
+        self.narrow_phase = narrow_phase;
+        self.ccd_solver = ccd_solver;
+
+        true
+    }
+
     /// Casts a ray with given options.
     pub fn cast_ray<S: QueryResultsStorage>(
         &self,
@@ -501,14 +1053,7 @@ impl Physics {
     ) {
         let time = instant::Instant::now();
 
-        let mut query = self.query.borrow_mut();
-
-        // TODO: Ideally this must be called once per frame, but it seems to be impossible because
-        // a body can be deleted during the consecutive calls of this method which will most
-        // likely end up in panic because of invalid handle stored in internal acceleration
-        // structure. This could be fixed by delaying deleting of bodies/collider to the end
-        // of the frame.
-        query.update(&self.islands, &self.bodies, &self.colliders);
+        let query = self.updated_query();
 
         query_buffer.clear();
         let ray = rapier2d::geometry::Ray::new(
@@ -552,6 +1097,122 @@ impl Physics {
         );
     }
 
+    fn updated_query(&self) -> RefMut<QueryPipeline> {
+        let mut query = self.query.borrow_mut();
+
+        // TODO: Ideally this must be called once per frame, but it seems to be impossible because
+        // a body can be deleted during the consecutive calls of this method which will most
+        // likely end up in panic because of invalid handle stored in internal acceleration
+        // structure. This could be fixed by delaying deleting of bodies/collider to the end
+        // of the frame.
+        query.update(&self.islands, &self.bodies, &self.colliders);
+
+        query
+    }
+
+    fn collider_handle_of(
+        &self,
+        handle: rapier2d::geometry::ColliderHandle,
+    ) -> Option<ColliderHandle> {
+        self.collider_handle_map.key_of(&handle).cloned()
+    }
+
+    /// Sweeps a shape (for example `Ball`, `Capsule` or `Cuboid` from `rapier2d::geometry`)
+    /// from given position along given direction and returns first collider that was hit,
+    /// if any. `exclude` is a collider that will be ignored, usually it is a collider of the
+    /// caster itself.
+    pub fn cast_shape(
+        &self,
+        shape: &dyn Shape,
+        position: &Isometry2<f32>,
+        direction: Vector2<f32>,
+        max_len: f32,
+        groups: InteractionGroups,
+        exclude: Option<ColliderHandle>,
+    ) -> Option<ShapeCastResult> {
+        let query = self.updated_query();
+
+        let direction = direction.try_normalize(f32::EPSILON).unwrap_or_default();
+        let excluded = exclude.and_then(|exclude| self.collider_handle_map.value_of(&exclude));
+        let filter = |handle: rapier2d::geometry::ColliderHandle| Some(&handle) != excluded;
+        let (handle, toi) = query.cast_shape(
+            &self.colliders,
+            position,
+            &direction,
+            shape,
+            max_len,
+            groups,
+            Some(&filter),
+        )?;
+
+        // Query pipeline transforms witness and normal of the collider that was hit to world
+        // coordinates.
+        Some(ShapeCastResult {
+            collider: self.collider_handle_of(handle)?,
+            toi: toi.toi,
+            position: toi.witness1,
+            normal: toi.normal1.into_inner(),
+        })
+    }
+
+    /// Collects every collider that intersects given shape at given position into the
+    /// storage.
+    pub fn intersections_with_shape<S: ColliderQueryStorage>(
+        &self,
+        shape: &dyn Shape,
+        position: &Isometry2<f32>,
+        groups: InteractionGroups,
+        query_buffer: &mut S,
+    ) {
+        let query = self.updated_query();
+
+        query_buffer.clear();
+        query.intersections_with_shape(&self.colliders, position, shape, groups, None, |handle| {
+            match self.collider_handle_of(handle) {
+                Some(handle) => query_buffer.push(handle),
+                None => true,
+            }
+        });
+    }
+
+    /// Collects every collider that contains given point (in world coordinates) into the
+    /// storage.
+    pub fn intersections_with_point<S: ColliderQueryStorage>(
+        &self,
+        point: &Point2<f32>,
+        groups: InteractionGroups,
+        query_buffer: &mut S,
+    ) {
+        let query = self.updated_query();
+
+        query_buffer.clear();
+        query.intersections_with_point(&self.colliders, point, groups, None, |handle| {
+            match self.collider_handle_of(handle) {
+                Some(handle) => query_buffer.push(handle),
+                None => true,
+            }
+        });
+    }
+
+    /// Projects a point (in world coordinates) on the closest collider. If `solid` is true,
+    /// points inside of colliders are not projected on their surfaces but returned as is.
+    pub fn project_point(
+        &self,
+        point: &Point2<f32>,
+        solid: bool,
+        groups: InteractionGroups,
+    ) -> Option<PointProjection> {
+        let query = self.updated_query();
+
+        let (handle, projection) =
+            query.project_point(&self.colliders, point, solid, groups, None)?;
+        Some(PointProjection {
+            collider: self.collider_handle_of(handle)?,
+            position: projection.point,
+            is_inside: projection.is_inside,
+        })
+    }
+
     pub(in crate) fn resolve(&mut self) {
         assert_eq!(self.bodies.len(), 0);
         assert_eq!(self.colliders.len(), 0);
@@ -559,14 +1220,26 @@ impl Physics {
 
         let mut phys_desc = self.desc.take().unwrap();
 
+        let solver_state_restored = self.resolve_solver_state(&phys_desc);
+
+        self.integration_parameters = phys_desc.integration_parameters.into();
+        self.gravity = phys_desc.gravity;
+
+        if solver_state_restored {
+            return;
+        }
+
         self.body_handle_map = convert_rigid_body_map(&phys_desc.body_handle_map);
         self.collider_handle_map = convert_collider_map(&phys_desc.collider_handle_map);
         self.joint_handle_map = convert_joint_map(&phys_desc.joint_handle_map);
 
-        self.integration_parameters = phys_desc.integration_parameters.into();
-
+        let mut mass_properties = Vec::new();
         for desc in phys_desc.bodies.drain(..) {
-            self.bodies.insert(desc.convert_to_body());
+            let props = desc.mass_properties.clone();
+            let handle = self.bodies.insert(desc.convert_to_body());
+            if let Some(props) = props {
+                mass_properties.push((handle, props));
+            }
         }
 
         for desc in phys_desc.colliders.drain(..) {
@@ -578,6 +1251,14 @@ impl Physics {
             );
         }
 
+        // Colliders are changing mass properties of bodies when attached, so exact mass
+        // properties must be restored after all colliders were added.
+        for (handle, props) in mass_properties {
+            if let Some(body) = self.bodies.get_mut(handle) {
+                body.set_mass_properties(props.into(), false);
+            }
+        }
+
         for desc in phys_desc.joints.drain(..) {
             let b1 = self
                 .body_handle_map()
@@ -721,7 +1402,35 @@ impl Into<RigidBodyType> for RigidBodyTypeDesc {
     }
 }
 
-#[derive(Clone, Debug, Visit)]
+#[derive(Default, Copy, Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct MassPropertiesDesc {
+    pub local_com: Vector2<f32>,
+    pub inv_mass: f32,
+    pub inv_principal_inertia_sqrt: f32,
+}
+
+impl From<MassProperties> for MassPropertiesDesc {
+    fn from(props: MassProperties) -> Self {
+        Self {
+            local_com: props.local_com.coords,
+            inv_mass: props.inv_mass,
+            inv_principal_inertia_sqrt: props.inv_principal_inertia_sqrt,
+        }
+    }
+}
+
+impl Into<MassProperties> for MassPropertiesDesc {
+    fn into(self) -> MassProperties {
+        MassProperties {
+            local_com: Point2::from(self.local_com),
+            inv_mass: self.inv_mass,
+            inv_principal_inertia_sqrt: self.inv_principal_inertia_sqrt,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 #[doc(hidden)]
 pub struct RigidBodyDesc<C> {
     pub position: Vector2<f32>,
@@ -734,10 +1443,24 @@ pub struct RigidBodyDesc<C> {
     pub mass: f32,
     pub rotation_locked: bool,
     pub translation_locked: bool,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub gravity_scale: f32,
+    pub dominance_group: i8,
+    pub ccd_enabled: bool,
+    pub force: Vector2<f32>,
+    pub torque: f32,
+    pub next_position: Vector2<f32>,
+    pub next_rotation: UnitComplex<f32>,
+    pub activation_energy: f32,
+    pub activation_threshold: f32,
+    // Exact mass properties, if absent `mass` is used as additional mass.
+    pub mass_properties: Option<MassPropertiesDesc>,
 }
 
 impl<C> Default for RigidBodyDesc<C> {
     fn default() -> Self {
+        let activation = RigidBodyActivation::active();
         Self {
             position: Default::default(),
             rotation: UnitComplex::identity(),
@@ -749,7 +1472,59 @@ impl<C> Default for RigidBodyDesc<C> {
             mass: 1.0,
             rotation_locked: false,
             translation_locked: false,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            gravity_scale: 1.0,
+            dominance_group: 0,
+            ccd_enabled: false,
+            force: Default::default(),
+            torque: 0.0,
+            next_position: Default::default(),
+            next_rotation: UnitComplex::identity(),
+            activation_energy: activation.energy,
+            activation_threshold: activation.threshold,
+            mass_properties: None,
+        }
+    }
+}
+
+impl<C: Visit + Default + 'static> Visit for RigidBodyDesc<C> {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.position.visit("Position", visitor)?;
+        self.rotation.visit("Rotation", visitor)?;
+        self.linvel.visit("Linvel", visitor)?;
+        self.angvel.visit("Angvel", visitor)?;
+        self.sleeping.visit("Sleeping", visitor)?;
+        self.status.visit("Status", visitor)?;
+        self.colliders.visit("Colliders", visitor)?;
+        self.mass.visit("Mass", visitor)?;
+        self.rotation_locked.visit("RotationLocked", visitor)?;
+        self.translation_locked
+            .visit("TranslationLocked", visitor)?;
+
+        // Runtime state, missing in older versions.
+        if visitor.is_reading() {
+            self.next_position = self.position;
+            self.next_rotation = self.rotation;
         }
+        let _ = self.linear_damping.visit("LinearDamping", visitor);
+        let _ = self.angular_damping.visit("AngularDamping", visitor);
+        let _ = self.gravity_scale.visit("GravityScale", visitor);
+        let _ = self.dominance_group.visit("DominanceGroup", visitor);
+        let _ = self.ccd_enabled.visit("CcdEnabled", visitor);
+        let _ = self.force.visit("Force", visitor);
+        let _ = self.torque.visit("Torque", visitor);
+        let _ = self.next_position.visit("NextPosition", visitor);
+        let _ = self.next_rotation.visit("NextRotation", visitor);
+        let _ = self.activation_energy.visit("ActivationEnergy", visitor);
+        let _ = self
+            .activation_threshold
+            .visit("ActivationThreshold", visitor);
+        let _ = self.mass_properties.visit("MassProperties", visitor);
+
+        visitor.leave_region()
     }
 }
 
@@ -774,6 +1549,19 @@ impl<C: Hash + Clone + Eq> RigidBodyDesc<C> {
             mass: body.mass(),
             rotation_locked: body.is_rotation_locked(),
             translation_locked: body.is_translation_locked(),
+            linear_damping: body.linear_damping(),
+            angular_damping: body.angular_damping(),
+            gravity_scale: body.gravity_scale(),
+            dominance_group: body.dominance_group(),
+            ccd_enabled: body.is_ccd_enabled(),
+            // Accumulated forces are not accessible through a body, they're filled by physics.
+            force: Default::default(),
+            torque: Default::default(),
+            next_position: body.next_position().translation.vector,
+            next_rotation: body.next_position().rotation,
+            activation_energy: body.activation().energy,
+            activation_threshold: body.activation().threshold,
+            mass_properties: Some((*body.mass_properties()).into()),
         }
     }
 
@@ -785,9 +1573,13 @@ impl<C: Hash + Clone + Eq> RigidBodyDesc<C> {
                 },
                 rotation: self.rotation,
             })
-            .additional_mass(self.mass)
             .linvel(self.linvel)
-            .angvel(self.angvel);
+            .angvel(self.angvel)
+            .linear_damping(self.linear_damping)
+            .angular_damping(self.angular_damping)
+            .gravity_scale(self.gravity_scale)
+            .dominance_group(self.dominance_group)
+            .ccd_enabled(self.ccd_enabled);
 
         if self.translation_locked {
             builder = builder.lock_translations();
@@ -796,10 +1588,28 @@ impl<C: Hash + Clone + Eq> RigidBodyDesc<C> {
             builder = builder.lock_rotations();
         }
 
+        // Exact mass properties will be set after colliders are attached.
+        if self.mass_properties.is_none() {
+            builder = builder.additional_mass(self.mass);
+        }
+
         let mut body = builder.build();
         if self.sleeping {
             body.sleep();
         }
+        let activation = body.activation_mut();
+        activation.energy = self.activation_energy;
+        activation.threshold = self.activation_threshold;
+        body.apply_force(self.force, false);
+        body.apply_torque(self.torque, false);
+        if body.is_kinematic() {
+            body.set_next_kinematic_position(Isometry2 {
+                translation: Translation {
+                    vector: self.next_position,
+                },
+                rotation: self.next_rotation,
+            });
+        }
         body
     }
 }
@@ -955,7 +1765,7 @@ impl ColliderShapeDesc {
     }
 }
 
-#[derive(Clone, Debug, Visit)]
+#[derive(Clone, Debug)]
 #[doc(hidden)]
 pub struct ColliderDesc<R> {
     pub shape: ColliderShapeDesc,
@@ -968,6 +1778,27 @@ pub struct ColliderDesc<R> {
     pub rotation: UnitComplex<f32>,
     pub collision_groups: InteractionGroupsDesc,
     pub solver_groups: InteractionGroupsDesc,
+    pub active_events: u32,
+}
+
+impl<R: Visit> Visit for ColliderDesc<R> {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.shape.visit("Shape", visitor)?;
+        self.parent.visit("Parent", visitor)?;
+        self.friction.visit("Friction", visitor)?;
+        self.density.visit("Density", visitor)?;
+        self.restitution.visit("Restitution", visitor)?;
+        self.is_sensor.visit("IsSensor", visitor)?;
+        self.translation.visit("Translation", visitor)?;
+        self.rotation.visit("Rotation", visitor)?;
+        self.collision_groups.visit("CollisionGroups", visitor)?;
+        self.solver_groups.visit("SolverGroups", visitor)?;
+        let _ = self.active_events.visit("ActiveEvents", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 #[derive(Visit, Debug, Clone)]
@@ -1007,6 +1838,7 @@ impl<R: Default> Default for ColliderDesc<R> {
             rotation: UnitComplex::identity(),
             collision_groups: Default::default(),
             solver_groups: Default::default(),
+            active_events: 0,
         }
     }
 }
@@ -1030,6 +1862,7 @@ impl<R: Hash + Clone + Eq> ColliderDesc<R> {
             rotation: collider.position_wrt_parent().unwrap().rotation,
             collision_groups: collider.collision_groups().into(),
             solver_groups: collider.solver_groups().into(),
+            active_events: collider.active_events().bits(),
         }
     }
 
@@ -1051,7 +1884,8 @@ impl<R: Hash + Clone + Eq> ColliderDesc<R> {
                 self.collision_groups.memberships,
                 self.collision_groups.filter,
             ))
-            .sensor(self.is_sensor);
+            .sensor(self.is_sensor)
+            .active_events(ActiveEvents::from_bits_truncate(self.active_events));
         if let Some(density) = self.density {
             builder = builder.density(density);
         }
@@ -1068,7 +1902,7 @@ impl Visit for Physics {
         } else if let Some(desc) = self.desc.as_ref() {
             desc.clone()
         } else {
-            self.generate_desc()
+            self.generate_desc_for_saving()
         };
         desc.visit("Desc", visitor)?;
 
@@ -1158,20 +1992,110 @@ impl Into<IntegrationParameters> for IntegrationParametersDesc {
     }
 }
 
-#[derive(Default, Clone, Debug, Visit)]
+#[doc(hidden)]
+pub fn spring_model_to_id(model: SpringModel) -> u32 {
+    match model {
+        SpringModel::Disabled => 0,
+        SpringModel::VelocityBased => 1,
+        SpringModel::AccelerationBased => 2,
+        SpringModel::ForceBased => 3,
+    }
+}
+
+#[doc(hidden)]
+pub fn spring_model_from_id(id: u32) -> SpringModel {
+    match id {
+        0 => SpringModel::Disabled,
+        2 => SpringModel::AccelerationBased,
+        3 => SpringModel::ForceBased,
+        _ => SpringModel::VelocityBased,
+    }
+}
+
+// Motor state of prismatic joint.
+#[derive(Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct JointMotorDesc {
+    pub target_vel: f32,
+    pub target_pos: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub max_impulse: f32,
+    pub impulse: f32,
+    pub model: u32,
+}
+
+impl Default for JointMotorDesc {
+    fn default() -> Self {
+        Self {
+            target_vel: 0.0,
+            target_pos: 0.0,
+            stiffness: 0.0,
+            damping: 0.0,
+            max_impulse: f32::MAX,
+            impulse: 0.0,
+            model: spring_model_to_id(SpringModel::default()),
+        }
+    }
+}
+
+// Motor state of ball joint.
+#[derive(Clone, Debug, Visit)]
+#[doc(hidden)]
+pub struct BallJointMotorDesc {
+    pub target_vel: f32,
+    pub target_pos: UnitComplex<f32>,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub max_impulse: f32,
+    pub impulse: f32,
+    pub model: u32,
+}
+
+impl Default for BallJointMotorDesc {
+    fn default() -> Self {
+        Self {
+            target_vel: 0.0,
+            target_pos: UnitComplex::identity(),
+            stiffness: 0.0,
+            damping: 0.0,
+            max_impulse: f32::MAX,
+            impulse: 0.0,
+            model: spring_model_to_id(SpringModel::default()),
+        }
+    }
+}
+
+#[derive(Default, Clone, Debug)]
 #[doc(hidden)]
 pub struct BallJointDesc {
     pub local_anchor1: Vector2<f32>,
     pub local_anchor2: Vector2<f32>,
+    pub impulse: Vector2<f32>,
+    pub motor: BallJointMotorDesc,
 }
 
-#[derive(Clone, Debug, Visit)]
+impl Visit for BallJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1.visit("LocalAnchor1", visitor)?;
+        self.local_anchor2.visit("LocalAnchor2", visitor)?;
+        let _ = self.impulse.visit("Impulse", visitor);
+        let _ = self.motor.visit("Motor", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Clone, Debug)]
 #[doc(hidden)]
 pub struct FixedJointDesc {
     pub local_anchor1_translation: Vector2<f32>,
     pub local_anchor1_rotation: UnitComplex<f32>,
     pub local_anchor2_translation: Vector2<f32>,
     pub local_anchor2_rotation: UnitComplex<f32>,
+    pub impulse: Vector3<f32>,
 }
 
 impl Default for FixedJointDesc {
@@ -1181,17 +2105,75 @@ impl Default for FixedJointDesc {
             local_anchor1_rotation: UnitComplex::identity(),
             local_anchor2_translation: Default::default(),
             local_anchor2_rotation: UnitComplex::identity(),
+            impulse: Default::default(),
         }
     }
 }
 
-#[derive(Default, Clone, Debug, Visit)]
+impl Visit for FixedJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1_translation
+            .visit("LocalAnchor1Translation", visitor)?;
+        self.local_anchor1_rotation
+            .visit("LocalAnchor1Rotation", visitor)?;
+        self.local_anchor2_translation
+            .visit("LocalAnchor2Translation", visitor)?;
+        self.local_anchor2_rotation
+            .visit("LocalAnchor2Rotation", visitor)?;
+        let _ = self.impulse.visit("Impulse", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+#[derive(Clone, Debug)]
 #[doc(hidden)]
 pub struct PrismaticJointDesc {
     pub local_anchor1: Vector2<f32>,
     pub local_axis1: Vector2<f32>,
     pub local_anchor2: Vector2<f32>,
     pub local_axis2: Vector2<f32>,
+    pub impulse: Vector2<f32>,
+    pub limits_enabled: bool,
+    pub limits: [f32; 2],
+    pub limits_impulse: f32,
+    pub motor: JointMotorDesc,
+}
+
+impl Default for PrismaticJointDesc {
+    fn default() -> Self {
+        Self {
+            local_anchor1: Default::default(),
+            local_axis1: Default::default(),
+            local_anchor2: Default::default(),
+            local_axis2: Default::default(),
+            impulse: Default::default(),
+            limits_enabled: false,
+            limits: [-f32::MAX, f32::MAX],
+            limits_impulse: 0.0,
+            motor: Default::default(),
+        }
+    }
+}
+
+impl Visit for PrismaticJointDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.local_anchor1.visit("LocalAnchor1", visitor)?;
+        self.local_axis1.visit("LocalAxis1", visitor)?;
+        self.local_anchor2.visit("LocalAnchor2", visitor)?;
+        self.local_axis2.visit("LocalAxis2", visitor)?;
+        let _ = self.impulse.visit("Impulse", visitor);
+        let _ = self.limits_enabled.visit("LimitsEnabled", visitor);
+        let _ = self.limits.visit("Limits", visitor);
+        let _ = self.limits_impulse.visit("LimitsImpulse", visitor);
+        let _ = self.motor.visit("Motor", visitor);
+
+        visitor.leave_region()
+    }
 }
 
 #[derive(Default, Clone, Debug, Visit)]
@@ -1220,30 +2202,57 @@ impl Default for JointParamsDesc {
 impl Into<JointParams> for JointParamsDesc {
     fn into(self) -> JointParams {
         match self {
-            JointParamsDesc::BallJoint(v) => JointParams::from(BallJoint::new(
-                Point2::from(v.local_anchor1),
-                Point2::from(v.local_anchor2),
-            )),
-            JointParamsDesc::FixedJoint(v) => JointParams::from(FixedJoint::new(
-                Isometry2 {
-                    translation: Translation2 {
-                        vector: v.local_anchor1_translation,
+            JointParamsDesc::BallJoint(v) => {
+                let mut joint =
+                    BallJoint::new(Point2::from(v.local_anchor1), Point2::from(v.local_anchor2));
+                joint.impulse = v.impulse;
+                joint.motor_target_vel = v.motor.target_vel;
+                joint.motor_target_pos = v.motor.target_pos;
+                joint.motor_stiffness = v.motor.stiffness;
+                joint.motor_damping = v.motor.damping;
+                joint.motor_max_impulse = v.motor.max_impulse;
+                joint.motor_impulse = v.motor.impulse;
+                joint.motor_model = spring_model_from_id(v.motor.model);
+                JointParams::from(joint)
+            }
+            JointParamsDesc::FixedJoint(v) => {
+                let mut joint = FixedJoint::new(
+                    Isometry2 {
+                        translation: Translation2 {
+                            vector: v.local_anchor1_translation,
+                        },
+                        rotation: v.local_anchor1_rotation,
                     },
-                    rotation: v.local_anchor1_rotation,
-                },
-                Isometry2 {
-                    translation: Translation2 {
-                        vector: v.local_anchor2_translation,
+                    Isometry2 {
+                        translation: Translation2 {
+                            vector: v.local_anchor2_translation,
+                        },
+                        rotation: v.local_anchor2_rotation,
                     },
-                    rotation: v.local_anchor2_rotation,
-                },
-            )),
-            JointParamsDesc::PrismaticJoint(v) => JointParams::from(PrismaticJoint::new(
-                Point2::from(v.local_anchor1),
-                Unit::<Vector2<f32>>::new_normalize(v.local_axis1),
-                Point2::from(v.local_anchor2),
-                Unit::<Vector2<f32>>::new_normalize(v.local_axis2),
-            )),
+                );
+                joint.impulse = v.impulse;
+                JointParams::from(joint)
+            }
+            JointParamsDesc::PrismaticJoint(v) => {
+                let mut joint = PrismaticJoint::new(
+                    Point2::from(v.local_anchor1),
+                    Unit::<Vector2<f32>>::new_normalize(v.local_axis1),
+                    Point2::from(v.local_anchor2),
+                    Unit::<Vector2<f32>>::new_normalize(v.local_axis2),
+                );
+                joint.impulse = v.impulse;
+                joint.limits_enabled = v.limits_enabled;
+                joint.limits = v.limits;
+                joint.limits_impulse = v.limits_impulse;
+                joint.motor_target_vel = v.motor.target_vel;
+                joint.motor_target_pos = v.motor.target_pos;
+                joint.motor_stiffness = v.motor.stiffness;
+                joint.motor_damping = v.motor.damping;
+                joint.motor_max_impulse = v.motor.max_impulse;
+                joint.motor_impulse = v.motor.impulse;
+                joint.motor_model = spring_model_from_id(v.motor.model);
+                JointParams::from(joint)
+            }
         }
     }
 }
@@ -1255,18 +2264,42 @@ impl JointParamsDesc {
             JointParams::BallJoint(v) => Self::BallJoint(BallJointDesc {
                 local_anchor1: v.local_anchor1.coords,
                 local_anchor2: v.local_anchor2.coords,
+                impulse: v.impulse,
+                motor: BallJointMotorDesc {
+                    target_vel: v.motor_target_vel,
+                    target_pos: v.motor_target_pos,
+                    stiffness: v.motor_stiffness,
+                    damping: v.motor_damping,
+                    max_impulse: v.motor_max_impulse,
+                    impulse: v.motor_impulse,
+                    model: spring_model_to_id(v.motor_model),
+                },
             }),
             JointParams::FixedJoint(v) => Self::FixedJoint(FixedJointDesc {
                 local_anchor1_translation: v.local_frame1.translation.vector,
                 local_anchor1_rotation: v.local_frame1.rotation,
                 local_anchor2_translation: v.local_frame2.translation.vector,
                 local_anchor2_rotation: v.local_frame2.rotation,
+                impulse: v.impulse,
             }),
             JointParams::PrismaticJoint(v) => Self::PrismaticJoint(PrismaticJointDesc {
                 local_anchor1: v.local_anchor1.coords,
                 local_axis1: v.local_axis1().into_inner(),
                 local_anchor2: v.local_anchor2.coords,
                 local_axis2: v.local_axis2().into_inner(),
+                impulse: v.impulse,
+                limits_enabled: v.limits_enabled,
+                limits: v.limits,
+                limits_impulse: v.limits_impulse,
+                motor: JointMotorDesc {
+                    target_vel: v.motor_target_vel,
+                    target_pos: v.motor_target_pos,
+                    stiffness: v.motor_stiffness,
+                    damping: v.motor_damping,
+                    max_impulse: v.motor_max_impulse,
+                    impulse: v.motor_impulse,
+                    model: spring_model_to_id(v.motor_model),
+                },
             }),
         }
     }
@@ -1294,7 +2327,7 @@ impl<R: Hash + Clone + Eq> JointDesc<R> {
     }
 }
 
-#[derive(Default, Clone, Debug, Visit)]
+#[derive(Default, Clone, Debug)]
 #[doc(hidden)]
 pub struct PhysicsDesc {
     pub integration_parameters: IntegrationParametersDesc,
@@ -1305,4 +2338,680 @@ pub struct PhysicsDesc {
     pub body_handle_map: BiDirHashMap<RigidBodyHandle, ErasedHandle>,
     pub collider_handle_map: BiDirHashMap<ColliderHandle, ErasedHandle>,
     pub joint_handle_map: BiDirHashMap<JointHandle, ErasedHandle>,
+    /// Exact state of the solver encoded by bincode, it depends on version of Rapier. It is
+    /// filled only when the world is saved, so it is empty in deep copies and in files of older
+    /// versions. If it is empty or cannot be read, the world is restored from descriptors:
+    /// bodies, colliders and joints are the same, but contacts and warm-starting data are lost.
+    pub solver_state: Vec<u8>,
+}
+
+impl Visit for PhysicsDesc {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        visitor.enter_region(name)?;
+
+        self.integration_parameters
+            .visit("IntegrationParameters", visitor)?;
+        self.colliders.visit("Colliders", visitor)?;
+        self.bodies.visit("Bodies", visitor)?;
+        self.gravity.visit("Gravity", visitor)?;
+        self.joints.visit("Joints", visitor)?;
+        self.body_handle_map.visit("BodyHandleMap", visitor)?;
+        self.collider_handle_map
+            .visit("ColliderHandleMap", visitor)?;
+        self.joint_handle_map.visit("JointHandleMap", visitor)?;
+
+        // Solver state is optional, older files do not have it.
+        let _ = PodVecView::from_pod_vec(&mut self.solver_state).visit("SolverState", visitor);
+
+        visitor.leave_region()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::{
+            algebra::{Isometry2, Point2, Translation2, Unit, UnitComplex, Vector2},
+            color::Color,
+            futures::executor::block_on,
+            pool::Handle,
+            visitor::prelude::*,
+        },
+        engine::{ColliderHandle, PhysicsBinder, RigidBodyHandle},
+        scene2d::{
+            node::Node,
+            physics::{Physics, PhysicsDesc, PhysicsEvent, PhysicsEventKind, MAX_QUEUED_EVENTS},
+            SceneDrawingContext,
+        },
+    };
+    use rapier2d::{
+        dynamics::{
+            BallJoint, FixedJoint, PrismaticJoint, RigidBodyBuilder, RigidBodyType, SpringModel,
+        },
+        geometry::{Ball, ColliderBuilder, Cuboid, InteractionGroups},
+        pipeline::ActiveEvents,
+    };
+
+    fn make_world() -> Physics {
+        let mut physics = Physics::new();
+
+        let add_body = |physics: &mut Physics, builder: RigidBodyBuilder| {
+            let body = physics.add_body(builder.build());
+            physics.add_collider(ColliderBuilder::cuboid(0.5, 0.5).build(), &body);
+            body
+        };
+
+        let ground = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .translation(Vector2::new(0.0, -1.0))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(20.0, 0.5).build(), &ground);
+
+        // Stack of touching boxes standing on the ground.
+        for i in 0..3 {
+            add_body(
+                &mut physics,
+                RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                    .translation(Vector2::new(5.0, i as f32)),
+            );
+        }
+
+        let anchor = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Static).translation(Vector2::new(0.0, 10.0)),
+        );
+        let pendulum = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector2::new(2.0, 10.0))
+                .linear_damping(0.3)
+                .angular_damping(0.2),
+        );
+        let slider = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector2::new(2.0, 8.0))
+                .gravity_scale(0.5),
+        );
+        let welded = add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic).translation(Vector2::new(2.0, 7.0)),
+        );
+        add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::KinematicPositionBased)
+                .translation(Vector2::new(-5.0, 0.0)),
+        );
+        add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector2::new(-10.0, 0.0))
+                .gravity_scale(0.0)
+                .sleeping(true),
+        );
+        add_body(
+            &mut physics,
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector2::new(10.0, 0.0))
+                .angvel(2.0),
+        );
+
+        let mut ball = BallJoint::new(Point2::origin(), Point2::new(-2.0, 0.0));
+        ball.configure_motor_velocity(1.0, 0.5);
+        physics.add_joint(&anchor, &pendulum, ball);
+
+        let mut prismatic = PrismaticJoint::new(
+            Point2::origin(),
+            Unit::new_unchecked(Vector2::y()),
+            Point2::new(0.0, 2.0),
+            Unit::new_unchecked(Vector2::y()),
+        );
+        prismatic.limits_enabled = true;
+        prismatic.limits = [-1.0, 1.0];
+        prismatic.configure_motor_model(SpringModel::AccelerationBased);
+        prismatic.configure_motor_position(0.5, 2.0, 0.5);
+        physics.add_joint(&pendulum, &slider, prismatic);
+
+        let fixed = FixedJoint::new(Isometry2::identity(), Isometry2::translation(0.0, 1.0));
+        physics.add_joint(&slider, &welded, fixed);
+
+        physics
+    }
+
+    fn assert_same_state(a: &Physics, b: &Physics) {
+        assert_eq!(a.bodies.len(), b.bodies.len());
+        for handle in a.body_handle_map().forward_map().keys() {
+            let body_a = a.body(handle).unwrap();
+            let body_b = b.body(handle).unwrap();
+            assert_eq!(body_a.position(), body_b.position());
+            assert_eq!(body_a.linvel(), body_b.linvel());
+            assert_eq!(body_a.angvel(), body_b.angvel());
+            assert_eq!(body_a.is_sleeping(), body_b.is_sleeping());
+        }
+    }
+
+    fn save_and_load(physics: &Physics, name: &str) -> Physics {
+        let path = std::env::temp_dir().join(format!("{}_{}.bin", name, std::process::id()));
+
+        // Keep the original world intact, it is used as a reference.
+        let mut desc = physics.generate_desc_for_saving();
+        {
+            let mut visitor = Visitor::new();
+            desc.visit("Desc", &mut visitor).unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+
+        let mut loaded = Physics::new();
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            let mut desc = PhysicsDesc::default();
+            desc.visit("Desc", &mut visitor).unwrap();
+            loaded.desc = Some(desc);
+        }
+
+        let _ = std::fs::remove_file(path);
+
+        loaded
+    }
+
+    fn active_contacts(physics: &Physics) -> usize {
+        physics
+            .narrow_phase
+            .contact_pairs()
+            .filter(|pair| pair.has_any_active_contact)
+            .count()
+    }
+
+    #[test]
+    fn runtime_state_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "rg3d_physics2d_runtime_state_{}.bin",
+            std::process::id()
+        ));
+
+        let mut physics = make_world();
+        for _ in 0..20 {
+            physics.step(&Default::default());
+        }
+
+        // Leave some runtime state that must survive serialization.
+        for (_, body) in physics.bodies.iter_mut() {
+            if body.is_kinematic() {
+                body.set_next_kinematic_position(Isometry2 {
+                    translation: Translation2::new(-5.0, 1.0),
+                    rotation: UnitComplex::new(0.3),
+                });
+            } else if body.is_dynamic() && !body.is_sleeping() {
+                body.apply_force(Vector2::new(0.0, 50.0), false);
+                body.apply_torque(1.0, false);
+            }
+        }
+
+        {
+            let mut visitor = Visitor::new();
+            physics.visit("Physics", &mut visitor).unwrap();
+            visitor.save_binary(&path).unwrap();
+        }
+
+        let mut loaded = Physics::new();
+        {
+            let mut visitor = block_on(Visitor::load_binary(&path)).unwrap();
+            loaded.visit("Physics", &mut visitor).unwrap();
+        }
+        loaded.resolve();
+
+        assert_eq!(physics.gravity, loaded.gravity);
+        assert_same_state(&physics, &loaded);
+
+        for _ in 0..30 {
+            physics.step(&Default::default());
+            loaded.step(&Default::default());
+            assert_same_state(&physics, &loaded);
+        }
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn stack_of_touching_bodies_round_trip() {
+        let mut physics = make_world();
+        for _ in 0..60 {
+            physics.step(&Default::default());
+        }
+        assert!(active_contacts(&physics) >= 3);
+
+        let mut loaded = save_and_load(&physics, "rg3d_physics2d_stack_round_trip");
+        loaded.resolve();
+        assert_eq!(active_contacts(&physics), active_contacts(&loaded));
+        assert_same_state(&physics, &loaded);
+
+        // Contacts and warm-starting impulses are restored exactly, so the stack must
+        // continue to move exactly the same.
+        for _ in 0..60 {
+            physics.step(&Default::default());
+            loaded.step(&Default::default());
+            assert_same_state(&physics, &loaded);
+        }
+    }
+
+    #[test]
+    fn world_is_restored_from_descriptors_without_solver_state() {
+        let mut physics = make_world();
+        for _ in 0..20 {
+            physics.step(&Default::default());
+        }
+
+        let mut loaded = save_and_load(&physics, "rg3d_physics2d_descriptors_round_trip");
+        // Simulate a file saved by an older version.
+        loaded.desc.as_mut().unwrap().solver_state.clear();
+        loaded.resolve();
+
+        assert_same_state(&physics, &loaded);
+        assert_eq!(physics.colliders.len(), loaded.colliders.len());
+        assert_eq!(physics.joints.len(), loaded.joints.len());
+        for handle in physics.joint_handle_map.forward_map().keys() {
+            assert!(loaded.joint_handle_map.value_of(handle).is_some());
+        }
+    }
+
+    fn drain(physics: &mut Physics) -> Vec<PhysicsEvent> {
+        std::iter::from_fn(|| physics.pop_event()).collect()
+    }
+
+    #[test]
+    fn contact_started_reports_impulses() {
+        let mut physics = Physics::new();
+        let ground = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        let ground_collider =
+            physics.add_collider(ColliderBuilder::cuboid(10.0, 0.5).build(), &ground);
+        let ball = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector2::new(0.0, 1.5))
+                .linvel(Vector2::new(0.0, -5.0))
+                .build(),
+        );
+        let ball_collider = physics.add_collider(
+            ColliderBuilder::ball(0.5)
+                .active_events(ActiveEvents::CONTACT_EVENTS)
+                .build(),
+            &ball,
+        );
+
+        let mut binder = PhysicsBinder::default();
+        let ball_node = Handle::<Node>::new(1, 1);
+        binder.bind(ball_node, ball);
+
+        let mut events = Vec::new();
+        for _ in 0..60 {
+            physics.step(&binder);
+            events.extend(drain(&mut physics));
+        }
+
+        let started = events
+            .iter()
+            .filter(|e| e.kind == PhysicsEventKind::ContactStarted)
+            .collect::<Vec<_>>();
+        assert_eq!(started.len(), 1);
+        let event = started[0];
+
+        let (node1, node2) = if event.collider1 == ball_collider {
+            assert_eq!(event.collider2, ground_collider);
+            (event.node1, event.node2)
+        } else {
+            assert_eq!(event.collider1, ground_collider);
+            (event.node2, event.node1)
+        };
+        assert_eq!(node1, ball_node);
+        assert_eq!(node2, Handle::NONE);
+
+        assert!(!event.contacts.is_empty());
+        assert!(event.contacts.iter().any(|contact| contact.impulse > 0.0));
+        for contact in event.contacts.iter() {
+            // Top of the ground.
+            assert!((contact.position.y - 0.5).abs() < 0.1);
+            assert!(contact.normal.y.abs() > 0.99);
+        }
+    }
+
+    #[test]
+    fn intersection_events_are_queued_in_order() {
+        let mut physics = Physics::new();
+        let sensor = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        physics.add_collider(
+            ColliderBuilder::cuboid(1.0, 1.0)
+                .sensor(true)
+                .active_events(ActiveEvents::INTERSECTION_EVENTS)
+                .build(),
+            &sensor,
+        );
+        let mover = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector2::new(-3.0, 0.0))
+                .gravity_scale(0.0)
+                .linvel(Vector2::new(6.0, 0.0))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::ball(0.25).build(), &mover);
+
+        let binder = PhysicsBinder::default();
+        let mut kinds = Vec::new();
+        for _ in 0..120 {
+            physics.step(&binder);
+            kinds.extend(drain(&mut physics).into_iter().map(|e| e.kind));
+        }
+
+        assert_eq!(
+            kinds,
+            vec![
+                PhysicsEventKind::IntersectionStarted,
+                PhysicsEventKind::IntersectionStopped
+            ]
+        );
+    }
+
+    #[test]
+    fn event_queue_is_limited() {
+        let mut physics = Physics::new();
+        physics.gravity = Vector2::default();
+
+        let sensor = physics.add_body(RigidBodyBuilder::new(RigidBodyType::Static).build());
+        physics.add_collider(
+            ColliderBuilder::cuboid(20.0, 20.0)
+                .sensor(true)
+                .active_events(ActiveEvents::INTERSECTION_EVENTS)
+                .build(),
+            &sensor,
+        );
+
+        let count = 34;
+        for i in 0..count {
+            for j in 0..count {
+                let body = physics.add_body(
+                    RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                        .translation(Vector2::new(i as f32 - 17.0, j as f32 - 17.0))
+                        .build(),
+                );
+                physics.add_collider(ColliderBuilder::ball(0.1).build(), &body);
+            }
+        }
+        assert!(count * count > MAX_QUEUED_EVENTS);
+
+        physics.step(&PhysicsBinder::default());
+
+        let events = drain(&mut physics);
+        assert_eq!(events.len(), MAX_QUEUED_EVENTS);
+        assert!(events
+            .iter()
+            .all(|e| e.kind == PhysicsEventKind::IntersectionStarted));
+
+        // Colliders still intersect, so there are no new events.
+        physics.step(&PhysicsBinder::default());
+        assert!(physics.pop_event().is_none());
+    }
+
+    // Three unit squares along X axis at 0, 3 and 6, the last one is in a separate group.
+    fn make_query_world() -> (Physics, [ColliderHandle; 3]) {
+        let mut physics = Physics::new();
+        let mut add_square = |x: f32, groups: InteractionGroups| {
+            let body = physics.add_body(
+                RigidBodyBuilder::new(RigidBodyType::Static)
+                    .translation(Vector2::new(x, 0.0))
+                    .build(),
+            );
+            physics.add_collider(
+                ColliderBuilder::cuboid(0.5, 0.5)
+                    .collision_groups(groups)
+                    .build(),
+                &body,
+            )
+        };
+        let a = add_square(0.0, InteractionGroups::all());
+        let b = add_square(3.0, InteractionGroups::all());
+        let c = add_square(6.0, InteractionGroups::new(0b10, 0b10));
+        (physics, [a, b, c])
+    }
+
+    #[test]
+    fn cast_shape_returns_first_hit() {
+        let (physics, [a, b, c]) = make_query_world();
+        let ball = Ball::new(0.25);
+        let cast = |x: f32, direction: f32, groups, exclude| {
+            physics.cast_shape(
+                &ball,
+                &Isometry2::translation(x, 0.0),
+                Vector2::new(direction, 0.0),
+                10.0,
+                groups,
+                exclude,
+            )
+        };
+
+        let result = cast(-3.0, 2.0, InteractionGroups::all(), None).unwrap();
+        assert_eq!(result.collider, a);
+        assert!((result.toi - 2.25).abs() < 1.0e-3);
+        assert!((result.position - Point2::new(-0.5, 0.0)).norm() < 1.0e-3);
+        assert!((result.normal - Vector2::new(-1.0, 0.0)).norm() < 1.0e-3);
+        assert!(cast(-3.0, -1.0, InteractionGroups::all(), None).is_none());
+
+        let result = cast(-3.0, 1.0, InteractionGroups::all(), Some(a)).unwrap();
+        assert_eq!(result.collider, b);
+        assert!((result.toi - 5.25).abs() < 1.0e-3);
+
+        assert_eq!(
+            cast(9.0, -1.0, InteractionGroups::all(), None)
+                .unwrap()
+                .collider,
+            c
+        );
+        assert_eq!(
+            cast(9.0, -1.0, InteractionGroups::new(1, 1), None)
+                .unwrap()
+                .collider,
+            b
+        );
+    }
+
+    #[test]
+    fn cast_shape_reports_contact_in_world_coordinates() {
+        let mut physics = Physics::new();
+        let center = Vector2::new(5.0, 1.0);
+        let rotation = UnitComplex::new(30.0f32.to_radians());
+        let body = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .position(Isometry2::from_parts(center.into(), rotation))
+                .build(),
+        );
+        let collider = physics.add_collider(ColliderBuilder::cuboid(0.5, 0.5).build(), &body);
+
+        let result = physics
+            .cast_shape(
+                &Ball::new(0.25),
+                &Isometry2::translation(0.0, 1.0),
+                Vector2::x(),
+                10.0,
+                InteractionGroups::all(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(result.collider, collider);
+
+        let face_normal = rotation * -Vector2::x();
+        assert!((result.normal - face_normal).norm() < 1.0e-3);
+        let ball_center = Point2::new(result.toi, 1.0);
+        assert!((result.position - (ball_center - face_normal * 0.25)).norm() < 1.0e-3);
+    }
+
+    #[test]
+    fn intersection_queries_collect_colliders() {
+        let (physics, [a, b, c]) = make_query_world();
+
+        // The buffer must be cleared before collecting.
+        let mut colliders = vec![ColliderHandle::default()];
+        physics.intersections_with_shape(
+            &Cuboid::new(Vector2::new(4.0, 0.1)),
+            &Isometry2::translation(3.0, 0.0),
+            InteractionGroups::new(1, 1),
+            &mut colliders,
+        );
+        colliders.sort();
+        let mut expected = vec![a, b];
+        expected.sort();
+        assert_eq!(colliders, expected);
+
+        physics.intersections_with_shape(
+            &Ball::new(0.5),
+            &Isometry2::translation(1.5, 0.0),
+            InteractionGroups::all(),
+            &mut colliders,
+        );
+        assert!(colliders.is_empty());
+
+        physics.intersections_with_point(
+            &Point2::new(6.2, 0.3),
+            InteractionGroups::all(),
+            &mut colliders,
+        );
+        assert_eq!(colliders, vec![c]);
+
+        physics.intersections_with_point(
+            &Point2::new(6.2, 0.3),
+            InteractionGroups::new(1, 1),
+            &mut colliders,
+        );
+        assert!(colliders.is_empty());
+    }
+
+    #[test]
+    fn project_point_finds_closest_collider() {
+        let (physics, [a, b, _]) = make_query_world();
+
+        let projection = physics
+            .project_point(&Point2::new(1.2, 0.2), true, InteractionGroups::all())
+            .unwrap();
+        assert_eq!(projection.collider, a);
+        assert!(!projection.is_inside);
+        assert!((projection.position - Point2::new(0.5, 0.2)).norm() < 1.0e-3);
+
+        // Solid projection returns inner points as is.
+        let inner = Point2::new(0.1, 0.0);
+        let projection = physics
+            .project_point(&inner, true, InteractionGroups::all())
+            .unwrap();
+        assert!(projection.is_inside);
+        assert!((projection.position - inner).norm() < 1.0e-3);
+
+        let projection = physics
+            .project_point(&inner, false, InteractionGroups::all())
+            .unwrap();
+        assert!(projection.is_inside);
+        assert!((projection.position - Point2::new(0.5, 0.0)).norm() < 1.0e-3);
+
+        let projection = physics
+            .project_point(&Point2::new(5.0, 0.0), true, InteractionGroups::new(1, 1))
+            .unwrap();
+        assert_eq!(projection.collider, b);
+    }
+
+    #[test]
+    fn draw_outlines_colliders_joints_and_contacts() {
+        let mut physics = Physics::new();
+        physics.gravity = Vector2::default();
+
+        let ball = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .translation(Vector2::new(2.0, 1.0))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::ball(0.5).build(), &ball);
+        // Slightly sinks into the ball to produce contacts.
+        let plank = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Dynamic)
+                .translation(Vector2::new(2.0, 1.7))
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(1.0, 0.25).build(), &plank);
+        let rotated = physics.add_body(
+            RigidBodyBuilder::new(RigidBodyType::Static)
+                .translation(Vector2::new(-3.0, 0.0))
+                .rotation(0.3)
+                .build(),
+        );
+        physics.add_collider(ColliderBuilder::cuboid(1.0, 0.5).build(), &rotated);
+        physics.add_joint(
+            &ball,
+            &rotated,
+            BallJoint::new(Point2::new(0.0, 0.5), Point2::new(1.0, 0.0)),
+        );
+
+        physics.step(&Default::default());
+
+        let mut context = SceneDrawingContext::default();
+        physics.draw(&mut context);
+
+        let position = |body: &RigidBodyHandle| *physics.body(body).unwrap().position();
+        let approx_eq = |a: Vector2<f32>, b: Vector2<f32>| (a - b).norm() < 1.0e-4;
+        let lines_of = |color| {
+            context
+                .lines
+                .iter()
+                .filter(|l| l.color == color)
+                .collect::<Vec<_>>()
+        };
+
+        // Every body has its basis drawn.
+        let axes = lines_of(Color::GREEN);
+        assert_eq!(axes.len(), 3);
+        for body in [ball, plank, rotated].iter() {
+            let origin = position(body).translation.vector;
+            assert!(axes.iter().any(|l| approx_eq(l.begin, origin)));
+        }
+
+        let shapes = lines_of(Color::opaque(200, 200, 200));
+        assert_eq!(shapes.len(), 16 + 4 + 4);
+        let ball_center = position(&ball).translation.vector;
+        let ball_lines = shapes
+            .iter()
+            .filter(|l| {
+                ((l.begin - ball_center).norm() - 0.5).abs() < 1.0e-4
+                    && ((l.end - ball_center).norm() - 0.5).abs() < 1.0e-4
+            })
+            .count();
+        assert_eq!(ball_lines, 16);
+        let rotated_position = position(&rotated);
+        let corners = [(-1.0, -0.5), (1.0, -0.5), (1.0, 0.5), (-1.0, 0.5)]
+            .iter()
+            .map(|&(x, y)| (rotated_position * Point2::new(x, y)).coords)
+            .collect::<Vec<_>>();
+        for (i, &corner) in corners.iter().enumerate() {
+            let next = corners[(i + 1) % corners.len()];
+            assert!(shapes
+                .iter()
+                .any(|l| approx_eq(l.begin, corner) && approx_eq(l.end, next)));
+        }
+
+        // Joint is drawn as a chain body1 -> anchor1 -> anchor2 -> body2.
+        let joint = lines_of(Color::opaque(255, 200, 0));
+        assert_eq!(joint.len(), 3);
+        assert!(approx_eq(joint[0].begin, ball_center));
+        assert!(approx_eq(joint[0].end, Vector2::new(2.0, 1.5)));
+        assert!(approx_eq(
+            joint[1].end,
+            (rotated_position * Point2::new(1.0, 0.0)).coords
+        ));
+        assert!(approx_eq(joint[2].end, rotated_position.translation.vector));
+
+        // Contacts between the ball and the plank are marked with crosses near the top of the
+        // ball, each cross is followed by a contact normal.
+        let crosses = lines_of(Color::RED)
+            .into_iter()
+            .filter(|l| ((l.end - l.begin).norm() - 0.1 * 2.0f32.sqrt()).abs() < 1.0e-4)
+            .collect::<Vec<_>>();
+        assert!(!crosses.is_empty());
+        for cross in crosses {
+            let center = (cross.begin + cross.end).scale(0.5);
+            assert!((center.x - 2.0).abs() < 0.1);
+            assert!(center.y > 1.3 && center.y < 1.55, "{:?}", center);
+        }
+    }
 }